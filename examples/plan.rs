@@ -0,0 +1,25 @@
+/// Example demonstrating how to preview the split plan before converting
+///
+/// Run with: `cargo run --example plan`
+use text2audio::Text2Audio;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let api_key =
+        std::env::var("ZHIPU_API_KEY").expect("Please set ZHIPU_API_KEY environment variable");
+
+    let text = std::fs::read_to_string("assets/text.md")?;
+
+    let converter = Text2Audio::new(&api_key).with_max_segment_length(300);
+
+    // Split only; no audio is synthesized
+    let plan = converter.plan(&text).await?;
+
+    for (idx, segment) in plan.segments.iter().enumerate() {
+        println!("[{}] ({} chars) {}", idx, segment.chars().count(), segment);
+    }
+    println!();
+    print!("{}", plan);
+
+    Ok(())
+}
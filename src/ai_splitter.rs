@@ -1,15 +1,96 @@
+use crate::backend::ChatBackend;
 use crate::client::{Client, Model};
-use crate::error::Result;
+use crate::client::{SPLIT_SYSTEM_PROMPT, TTS_MAX_CHARS};
+use crate::error::{Error, Result};
+use crate::local_splitter::{LengthUnit, LocalSplitter, DEFAULT_SENTENCE_TERMINATORS};
+use crate::plan::split_score;
+use crate::progress;
+use crate::retry::RetryPolicy;
+use crate::stats::{Endpoint, StatsRecorder};
+use crate::temp::write_atomic;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Default delimiter for AI-split text segments
 const SEGMENT_DELIMITER: &str = "|||";
 
+/// Bytes reserved for the instructions of a prompt, which take about 400
+const PROMPT_INSTRUCTIONS_CAPACITY: usize = 512;
+
+/// Default upper bound on the characters of a rendered chat prompt
+pub const DEFAULT_MAX_PROMPT_CHARS: usize = 100_000;
+
+/// Which choice of a chat response the AI splitter uses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChoiceSelection {
+    /// Use the first choice and ignore any alternatives
+    #[default]
+    First,
+    /// Use the choice whose segments best fit the length limit, as scored
+    /// by [`split_score`]; ties go to the earlier choice
+    BestFit,
+}
+
+/// Directory of cached AI split results, one JSON file per key
+#[derive(Debug, Clone)]
+pub(crate) struct SplitCache {
+    dir: PathBuf,
+}
+
+impl SplitCache {
+    pub(crate) fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Segments stored under `key`; unreadable entries count as misses
+    pub(crate) fn get(&self, key: &str) -> Option<Vec<String>> {
+        let json = std::fs::read(self.entry(key)).ok()?;
+        serde_json::from_slice(&json).ok()
+    }
+
+    /// Store `segments` under `key`
+    ///
+    /// Failures are ignored, as the cache only saves requests. Entries are
+    /// written to a temporary file and renamed, so concurrent conversions
+    /// never read a partial entry.
+    pub(crate) fn put(&self, key: &str, segments: &[String]) {
+        let _ = self.try_put(key, segments);
+    }
+
+    fn try_put(&self, key: &str, segments: &[String]) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let entry = self.entry(key);
+        let temp = entry.with_extension(format!("{}.tmp", std::process::id()));
+        write_atomic(&entry, temp, serde_json::to_vec(segments)?)
+    }
+
+    fn entry(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
 /// AI-powered text splitter using GLM models
 ///
 /// Uses AI to semantically split long text while maintaining coherence.
 pub struct AiSplitter {
     client: Client,
+    model: Model,
+    thinking: bool,
+    greedy: bool,
     max_length: usize,
+    max_prompt_chars: usize,
+    max_prompt_bytes: Option<usize>,
+    length_unit: LengthUnit,
+    choice_selection: ChoiceSelection,
+    inter_request_delay: Duration,
+    cache: Option<SplitCache>,
+    chat_backend: Option<Arc<dyn ChatBackend>>,
+    retry_policy: RetryPolicy,
+    stats: Option<Arc<StatsRecorder>>,
 }
 
 impl AiSplitter {
@@ -25,7 +106,22 @@ impl AiSplitter {
     /// ```
     pub fn new(api_key: impl Into<String>, model: Model, max_length: usize) -> Self {
         let client = Client::new(api_key).with_model(model);
-        Self { client, max_length }
+        Self {
+            client,
+            model,
+            thinking: false,
+            greedy: false,
+            max_length,
+            max_prompt_chars: DEFAULT_MAX_PROMPT_CHARS,
+            max_prompt_bytes: None,
+            length_unit: LengthUnit::Chars,
+            choice_selection: ChoiceSelection::First,
+            inter_request_delay: Duration::ZERO,
+            cache: None,
+            chat_backend: None,
+            retry_policy: RetryPolicy::new(0, Duration::ZERO),
+            stats: None,
+        }
     }
 
     /// Enable or disable thinking mode for better semantic understanding
@@ -34,6 +130,7 @@ impl AiSplitter {
     /// which can improve semantic coherence but increases processing time.
     pub fn with_thinking(mut self, enable: bool) -> Self {
         self.client = self.client.with_thinking(enable);
+        self.thinking = enable;
         self
     }
 
@@ -45,37 +142,358 @@ impl AiSplitter {
         self
     }
 
+    /// Enable or disable greedy decoding of split requests
+    ///
+    /// Turns sampling off, so the same text is split the same way as far
+    /// as the API allows. See [`Client::with_greedy_decoding`].
+    pub fn with_greedy_decoding(mut self, enable: bool) -> Self {
+        self.client = self.client.with_greedy_decoding(enable);
+        self.greedy = enable;
+        self
+    }
+
+    /// Set the maximum characters of a prompt sent to the AI
+    ///
+    /// The limit is checked against the fully rendered prompt, instructions
+    /// included. A larger prompt fails with [`Error::AiApi`] before any
+    /// request is made, so a misconfiguration cannot run up token costs.
+    /// Defaults to [`DEFAULT_MAX_PROMPT_CHARS`].
+    pub fn with_max_prompt_chars(mut self, max_chars: usize) -> Self {
+        self.max_prompt_chars = max_chars;
+        self
+    }
+
+    /// Set the maximum UTF-8 bytes of a prompt sent to the AI
+    ///
+    /// Input that would render a larger prompt is first cut into chunks,
+    /// preferably at sentence ends, and each chunk is split with its own
+    /// request. This keeps request bodies under gateway size limits, which
+    /// matters for CJK text where every character takes three bytes. The
+    /// limit covers the rendered prompt only, so leave some headroom for the
+    /// JSON envelope of the request. No limit by default.
+    pub fn with_max_prompt_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_prompt_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Set the unit in which the maximum segment length is measured
+    ///
+    /// With [`LengthUnit::Words`] the AI is asked for segments of at most
+    /// `max_length` words, and no more than [`TTS_MAX_CHARS`] characters.
+    pub fn with_length_unit(mut self, unit: LengthUnit) -> Self {
+        self.length_unit = unit;
+        self
+    }
+
+    /// Set which choice to use when the API returns several
+    ///
+    /// Only matters if the API is configured to return alternative
+    /// completions; with a single choice both selections behave the same.
+    pub fn with_choice_selection(mut self, selection: ChoiceSelection) -> Self {
+        self.choice_selection = selection;
+        self
+    }
+
+    /// Wait `delay` between consecutive chunk requests
+    ///
+    /// Not applied before the first request, nor after a chunk short
+    /// enough to need no request. Off by default.
+    pub fn with_inter_request_delay(mut self, delay: Duration) -> Self {
+        self.inter_request_delay = delay;
+        self
+    }
+
+    /// Send prompts to `backend` instead of the Zhipu AI chat API
+    ///
+    /// Chunking, prompts and response parsing stay the same; thinking and
+    /// the coding plan only apply to the default client.
+    pub fn with_chat_backend(mut self, backend: Arc<dyn ChatBackend>) -> Self {
+        self.chat_backend = Some(backend);
+        self
+    }
+
+    /// Retry each chunk request under `policy`
+    ///
+    /// A chunk whose request or response fails is retried on its own, so
+    /// chunks already split are not requested again. No retries by default.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Record every chat request in `stats`
+    pub(crate) fn with_stats(mut self, stats: Arc<StatsRecorder>) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Cache split results on disk in `dir`
+    ///
+    /// A split of the same text with the same model, length limit, system
+    /// prompt and settings is read back from the cache instead of calling
+    /// the API again. Requests use the model's default temperature, so it
+    /// is not part of the key. The directory is created on the first write;
+    /// cache failures never fail a split.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::ai_splitter::AiSplitter;
+    /// use text2audio::client::Model;
+    ///
+    /// let splitter =
+    ///     AiSplitter::new("api_key", Model::GLM4_5Flash, 1000).with_split_cache("split-cache");
+    /// ```
+    pub fn with_split_cache(mut self, dir: impl AsRef<Path>) -> Self {
+        self.cache = Some(SplitCache::new(dir));
+        self
+    }
+
+    /// Key of the cached split of `text`
+    ///
+    /// A 128-bit FNV-1a hash of the text and every setting that shapes the
+    /// response, as 32 hex digits.
+    pub(crate) fn cache_key(&self, text: &str) -> String {
+        let settings = format!(
+            "{:?}:{:?}:{}:{}:{:?}",
+            self.length_unit,
+            self.choice_selection,
+            self.thinking,
+            self.greedy,
+            self.max_prompt_bytes
+        );
+        let parts = [
+            text,
+            self.model.as_str(),
+            &self.max_length.to_string(),
+            SPLIT_SYSTEM_PROMPT,
+            &settings,
+        ];
+        let mut hash = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d_u128;
+        for part in parts {
+            // Length prefixes keep the boundaries between parts unambiguous
+            let bytes = (part.len() as u64).to_le_bytes();
+            for &byte in bytes.iter().chain(part.as_bytes()) {
+                hash =
+                    (hash ^ byte as u128).wrapping_mul(0x0000_0000_0100_0000_0000_0000_0000_013b);
+            }
+        }
+        format!("{:032x}", hash)
+    }
+
     /// Split text using AI to ensure semantic coherence
     ///
+    /// With a [split cache](Self::with_split_cache), a cached split is
+    /// returned without calling the API and new splits are stored.
+    ///
     /// # Process
     ///
     /// 1. If text is short enough, return as-is
-    /// 2. Send to AI model with splitting instructions
-    /// 3. Parse AI response using delimiter
+    /// 2. Cut the text into chunks that fit the prompt byte limit, if set
+    /// 3. Build each chunk's prompt and check it against the size limits
+    /// 4. Send to AI model with splitting instructions
+    /// 5. Parse AI response using delimiter, falling back to
+    ///    [`LocalSplitter`] if the response has no content
+    /// 6. Pick a choice according to the [`ChoiceSelection`]
     pub async fn split(&self, text: &str) -> Result<Vec<String>> {
-        let char_count = text.chars().count();
+        let Some(cache) = &self.cache else {
+            return self.split_uncached(text).await;
+        };
+        let key = self.cache_key(text);
+        if let Some(segments) = cache.get(&key) {
+            return Ok(segments);
+        }
+        let segments = self.split_uncached(text).await?;
+        cache.put(&key, &segments);
+        Ok(segments)
+    }
 
-        if char_count == 0 {
+    async fn split_uncached(&self, text: &str) -> Result<Vec<String>> {
+        if text.is_empty() {
             return Ok(vec![]);
         }
 
-        if char_count <= self.max_length {
+        if self.length_unit.fits(text, self.max_length) {
             return Ok(vec![text.to_string()]);
         }
 
-        let prompt = self.build_prompt(text);
-        let raw_response = self.client.chat_completion(&prompt).await?;
-        self.parse_segments(&raw_response)
+        let mut segments = Vec::new();
+        let chunks = self.prompt_chunks(text)?;
+        let mut after_request = false;
+        for (index, &chunk) in chunks.iter().enumerate() {
+            if self.length_unit.fits(chunk, self.max_length) {
+                segments.push(chunk.to_string());
+                after_request = false;
+                continue;
+            }
+            let prompt = self.build_prompt(chunk);
+            self.check_prompt_size(&prompt)?;
+            if after_request && !self.inter_request_delay.is_zero() {
+                tokio::time::sleep(self.inter_request_delay).await;
+            }
+            after_request = true;
+            progress::split_chunk_sent(index, chunks.len());
+            let (split, _) = self
+                .retry_policy
+                .run(
+                    |attempt| self.split_chunk(chunk, &prompt, attempt),
+                    Error::retry_class,
+                )
+                .await;
+            let split = split?;
+            progress::split_chunk_parsed(index, chunks.len(), split.len());
+            segments.extend(split);
+        }
+        Ok(segments)
+    }
+
+    /// Request and parse the split of one chunk, `attempt` counting from 0
+    async fn split_chunk(&self, chunk: &str, prompt: &str, attempt: u32) -> Result<Vec<String>> {
+        let started = tokio::time::Instant::now();
+        let raw_responses = self.chat(prompt).await;
+        if let Some(stats) = &self.stats {
+            stats.record(
+                Endpoint::Chat,
+                started.elapsed(),
+                raw_responses.is_ok(),
+                attempt > 0,
+            );
+        }
+        let raw_responses = raw_responses?;
+        match self.choice_selection {
+            ChoiceSelection::First => {
+                let raw_response = raw_responses.into_iter().next().unwrap_or_default();
+                self.segments_from_response(chunk, &raw_response)
+            }
+            ChoiceSelection::BestFit => self.best_choice(chunk, &raw_responses),
+        }
+    }
+
+    /// Complete `prompt` with the chat backend, or the client if none is set
+    async fn chat(&self, prompt: &str) -> Result<Vec<String>> {
+        match &self.chat_backend {
+            Some(backend) => backend.chat(prompt).await,
+            None => self.client.chat_completion_all(prompt).await,
+        }
+    }
+
+    /// Segments of the response that best fits the length limit
+    fn best_choice(&self, text: &str, raw_responses: &[String]) -> Result<Vec<String>> {
+        let fits = |segment: &str| self.length_unit.fits(segment, self.max_length);
+        let mut best: Option<(f64, Vec<String>)> = None;
+        for raw_response in raw_responses {
+            let segments = self.segments_from_response(text, raw_response)?;
+            let score = split_score(&segments, fits);
+            let best_score = best.as_ref().map_or(f64::NEG_INFINITY, |(score, _)| *score);
+            if score > best_score {
+                best = Some((score, segments));
+            }
+        }
+        best.map(|(_, segments)| segments)
+            .ok_or_else(|| Error::AiApi("Invalid AI response format".to_string()))
+    }
+
+    /// Cut text into chunks whose prompts fit the prompt byte limit
+    ///
+    /// Chunks end after the last sentence terminator or newline that fits,
+    /// or at the last fitting char boundary if there is none.
+    fn prompt_chunks<'a>(&self, text: &'a str) -> Result<Vec<&'a str>> {
+        let Some(max_bytes) = self.max_prompt_bytes else {
+            return Ok(vec![text]);
+        };
+        let overhead = self.build_prompt("").len();
+        let budget = max_bytes.saturating_sub(overhead);
+        let first_char = text.chars().next().map_or(0, char::len_utf8);
+        if budget < first_char {
+            return Err(Error::AiApi(format!(
+                "prompt byte limit {} leaves no room for text; instructions take {} bytes",
+                max_bytes, overhead
+            )));
+        }
+
+        let mut chunks = Vec::new();
+        let mut rest = text;
+        while rest.len() > budget {
+            let mut end = budget;
+            while !rest.is_char_boundary(end) {
+                end -= 1;
+            }
+            if end == 0 {
+                return Err(Error::AiApi(format!(
+                    "prompt byte limit {} leaves no room for a single character",
+                    max_bytes
+                )));
+            }
+            let cut = rest[..end]
+                .char_indices()
+                .rev()
+                .find(|(_, c)| *c == '\n' || DEFAULT_SENTENCE_TERMINATORS.contains(c))
+                .map_or(end, |(idx, c)| idx + c.len_utf8());
+            chunks.push(&rest[..cut]);
+            rest = &rest[cut..];
+        }
+        if !rest.is_empty() {
+            chunks.push(rest);
+        }
+        Ok(chunks)
+    }
+
+    fn segments_from_response(&self, text: &str, raw_response: &str) -> Result<Vec<String>> {
+        // Some models occasionally answer with no content at all; splitting
+        // locally beats synthesizing an empty segment
+        if raw_response
+            .split(SEGMENT_DELIMITER)
+            .all(|s| s.trim().is_empty())
+        {
+            return Ok(LocalSplitter::new(self.max_length)
+                .with_length_unit(self.length_unit)
+                .split(text));
+        }
+
+        self.parse_segments(raw_response)
     }
 
+    /// Render the prompt for `text` in a single allocation
     fn build_prompt(&self, text: &str) -> String {
-        format!(
-            "请将以下文本分割成多个段落，每个段落的字符数不超过 {} 字符。\
-            分割时要保持语义完整性，优先按照句子的自然边界（如句号、问号、感叹号）进行分割。\
+        let mut prompt = String::with_capacity(PROMPT_INSTRUCTIONS_CAPACITY + text.len());
+        prompt.push_str("请将以下文本分割成多个段落，每个段落");
+        let limit = if self.length_unit == LengthUnit::Words {
+            write!(
+                prompt,
+                "不超过 {} 个单词，且不超过 {} 个字符",
+                self.max_length, TTS_MAX_CHARS
+            )
+        } else {
+            write!(prompt, "的字符数不超过 {} 字符", self.max_length)
+        };
+        limit.expect("writing to a String cannot fail");
+        prompt.push_str(
+            "。分割时要保持语义完整性，优先按照句子的自然边界（如句号、问号、感叹号）进行分割。\
             分割后，请按顺序输出每个段落，每个段落用特殊标记 ||| 分隔。\
-            不要添加任何解释性文字，只输出分割后的段落。\n\n待分割的文本：\n{}",
-            self.max_length, text
-        )
+            不要添加任何解释性文字，只输出分割后的段落。\n\n待分割的文本：\n",
+        );
+        prompt.push_str(text);
+        prompt
+    }
+
+    fn check_prompt_size(&self, prompt: &str) -> Result<()> {
+        let chars = prompt.chars().count();
+        if chars > self.max_prompt_chars {
+            return Err(Error::AiApi(format!(
+                "prompt too large: {} chars, limit {}",
+                chars, self.max_prompt_chars
+            )));
+        }
+        if let Some(max_bytes) = self.max_prompt_bytes {
+            if prompt.len() > max_bytes {
+                return Err(Error::AiApi(format!(
+                    "prompt too large: {} bytes, limit {}",
+                    prompt.len(),
+                    max_bytes
+                )));
+            }
+        }
+        Ok(())
     }
 
     fn parse_segments(&self, raw_response: &str) -> Result<Vec<String>> {
@@ -121,6 +539,94 @@ mod tests {
         assert!(prompt.contains("100"));
         assert!(prompt.contains(text));
         assert!(prompt.contains("|||"));
+        assert!(prompt.ends_with("待分割的文本：\nHello world!"));
+        // Built in a single allocation
+        assert_eq!(prompt.capacity(), PROMPT_INSTRUCTIONS_CAPACITY + text.len());
+    }
+
+    #[test]
+    fn test_build_prompt_words() {
+        let splitter =
+            AiSplitter::new("api_key", Model::GLM4_7, 100).with_length_unit(LengthUnit::Words);
+        let prompt = splitter.build_prompt("Hello world!");
+        assert!(prompt.contains("100 个单词"));
+        assert!(prompt.contains("Hello world!"));
+    }
+
+    #[tokio::test]
+    async fn test_words_short_text_not_sent() {
+        let splitter =
+            AiSplitter::new("api_key", Model::GLM4_7, 100).with_length_unit(LengthUnit::Words);
+        let text = "This sentence has far more than one hundred characters in it but only about twenty five words in total, so it fits.";
+        assert!(text.chars().count() > 100);
+        assert_eq!(splitter.split(text).await.unwrap(), vec![text]);
+    }
+
+    #[tokio::test]
+    async fn test_prompt_too_large_fails_before_request() {
+        let splitter = AiSplitter::new("api_key", Model::GLM4_7, 100).with_max_prompt_chars(1000);
+        let text = "很长的文本。".repeat(200);
+        let prompt_chars = splitter.build_prompt(&text).chars().count();
+
+        match splitter.split(&text).await {
+            Err(Error::AiApi(message)) => assert_eq!(
+                message,
+                format!("prompt too large: {} chars, limit 1000", prompt_chars)
+            ),
+            other => panic!("expected a prompt size error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_prompt_limit_counts_instructions() {
+        let text = "短";
+        let splitter = AiSplitter::new("api_key", Model::GLM4_7, 100).with_max_prompt_chars(10);
+        assert!(splitter
+            .check_prompt_size(&splitter.build_prompt(text))
+            .is_err());
+
+        let splitter = AiSplitter::new("api_key", Model::GLM4_7, 100);
+        assert!(splitter
+            .check_prompt_size(&splitter.build_prompt(text))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_prompt_chunks_fit_byte_limit() {
+        let limit = 2000;
+        let splitter = AiSplitter::new("api_key", Model::GLM4_7, 100).with_max_prompt_bytes(limit);
+        let text = "这是一个很长的中文句子，用来测试按字节分块。".repeat(500);
+
+        let chunks = splitter.prompt_chunks(&text).unwrap();
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks.concat(), text);
+        for chunk in &chunks {
+            let prompt = splitter.build_prompt(chunk);
+            assert!(prompt.len() <= limit, "{} bytes", prompt.len());
+            assert!(splitter.check_prompt_size(&prompt).is_ok());
+            assert!(chunk.ends_with('。'));
+        }
+    }
+
+    #[test]
+    fn test_prompt_chunks_without_terminators() {
+        let splitter = AiSplitter::new("api_key", Model::GLM4_7, 100).with_max_prompt_bytes(1000);
+        let text = "字".repeat(1000);
+
+        let chunks = splitter.prompt_chunks(&text).unwrap();
+        assert_eq!(chunks.concat(), text);
+        assert!(chunks
+            .iter()
+            .all(|chunk| splitter.build_prompt(chunk).len() <= 1000));
+    }
+
+    #[test]
+    fn test_prompt_byte_limit_below_instructions() {
+        let splitter = AiSplitter::new("api_key", Model::GLM4_7, 100).with_max_prompt_bytes(10);
+        assert!(matches!(
+            splitter.prompt_chunks("你好。"),
+            Err(Error::AiApi(_))
+        ));
     }
 
     #[test]
@@ -134,6 +640,50 @@ mod tests {
         assert_eq!(segments[2], "Third segment");
     }
 
+    #[test]
+    fn test_empty_response_falls_back_to_local_split() {
+        let splitter = AiSplitter::new("api_key", Model::GLM4_7, 100);
+        let text = "这是一个句子。".repeat(30);
+
+        for raw in ["", "  \n\t", "||| |||"] {
+            let segments = splitter.segments_from_response(&text, raw).unwrap();
+            assert!(segments.len() > 1);
+            assert!(segments.iter().all(|s| !s.trim().is_empty()));
+            assert!(segments.iter().all(|s| s.chars().count() <= 100));
+            assert_eq!(segments.concat(), text);
+        }
+    }
+
+    #[test]
+    fn test_best_choice() {
+        let splitter = AiSplitter::new("api_key", Model::GLM4_7, 100);
+        let text = format!("{}{}", "长".repeat(150), "短。");
+        let responses = [
+            format!("{}|||短。", "长".repeat(150)),
+            format!("{}。|||{}|||短。", "长".repeat(75), "长".repeat(75)),
+            format!("{}|||{}|||短。", "长".repeat(75), "长".repeat(75)),
+        ];
+
+        // The first choice has an over-long segment; the last two fit, and
+        // the second ends a segment at a sentence end more often
+        assert_eq!(
+            splitter.best_choice(&text, &responses).unwrap(),
+            vec![
+                format!("{}。", "长".repeat(75)),
+                "长".repeat(75),
+                "短。".to_string()
+            ]
+        );
+        assert_eq!(
+            splitter.best_choice(&text, &responses[..1]).unwrap().len(),
+            2
+        );
+        assert!(matches!(
+            splitter.best_choice(&text, &[]),
+            Err(Error::AiApi(_))
+        ));
+    }
+
     #[test]
     fn test_parse_segments_empty() {
         let splitter = AiSplitter::new("api_key", Model::GLM4_7, 100);
@@ -142,4 +692,83 @@ mod tests {
         assert_eq!(segments.len(), 1);
         assert_eq!(segments[0], "No delimiters here");
     }
+
+    #[tokio::test]
+    async fn test_split_cache() {
+        let dir = crate::test_util::temp_path("ai_split_cache");
+        let text = "长".repeat(150);
+        let splitter = AiSplitter::new("api_key", Model::GLM4_7, 100).with_split_cache(&dir);
+        let key = splitter.cache_key(&text);
+        let cached = vec!["长".repeat(100), "长".repeat(50)];
+        SplitCache::new(&dir).put(&key, &cached);
+
+        // A hit never reaches the API, which would reject this key
+        assert_eq!(splitter.split(&text).await.unwrap(), cached);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let other = |splitter: AiSplitter| splitter.cache_key(&text) != key;
+        assert!(!other(AiSplitter::new("other_key", Model::GLM4_7, 100)));
+        assert!(other(AiSplitter::new("api_key", Model::GLM4_6, 100)));
+        assert!(other(AiSplitter::new("api_key", Model::GLM4_7, 99)));
+        assert!(other(
+            AiSplitter::new("api_key", Model::GLM4_7, 100).with_thinking(true)
+        ));
+        assert!(other(
+            AiSplitter::new("api_key", Model::GLM4_7, 100).with_greedy_decoding(true)
+        ));
+        assert_ne!(splitter.cache_key(&"长".repeat(151)), key);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_inter_request_delay_between_chunks() {
+        let splitter = AiSplitter::new("test_key", Model::default(), 300)
+            .with_max_prompt_bytes(1500)
+            .with_chat_backend(Arc::new(crate::test_util::EchoChatBackend::new(
+                Duration::ZERO,
+            )))
+            .with_inter_request_delay(Duration::from_secs(2));
+        let text = "人工智能改变世界。".repeat(120);
+        let requests = splitter
+            .prompt_chunks(&text)
+            .unwrap()
+            .iter()
+            .filter(|chunk| !splitter.length_unit.fits(chunk, splitter.max_length))
+            .count() as u32;
+        assert!(requests > 2);
+
+        let started = tokio::time::Instant::now();
+        let segments = splitter.split(&text).await.unwrap();
+        assert_eq!(segments.concat(), text);
+        assert_eq!(started.elapsed(), Duration::from_secs(2) * (requests - 1));
+    }
+
+    #[tokio::test]
+    async fn test_failed_chunk_retried_alone() {
+        let backend = Arc::new(crate::test_util::FlakyChatBackend::new(&[1]));
+        let splitter = AiSplitter::new("test_key", Model::default(), 300)
+            .with_max_prompt_bytes(1500)
+            .with_chat_backend(backend.clone())
+            .with_retry_policy(RetryPolicy::new(1, Duration::ZERO));
+        let text: String = (0..120)
+            .map(|n| format!("人工智能第{}次改变世界。", n))
+            .collect();
+
+        let segments = splitter.split(&text).await.unwrap();
+        assert_eq!(segments.concat(), text);
+        let prompts = backend.prompts();
+        assert!(prompts.len() > 3);
+        // Only the second chunk was sent twice
+        assert_eq!(prompts[1], prompts[2]);
+        let mut unique = prompts.clone();
+        unique.dedup();
+        assert_eq!(unique.len(), prompts.len() - 1);
+
+        // Without retries the failure is returned
+        let backend = Arc::new(crate::test_util::FlakyChatBackend::new(&[1]));
+        let splitter = AiSplitter::new("test_key", Model::default(), 300)
+            .with_max_prompt_bytes(1500)
+            .with_chat_backend(backend.clone());
+        assert!(splitter.split(&text).await.is_err());
+        assert_eq!(backend.prompts().len(), 2);
+    }
 }
@@ -0,0 +1,182 @@
+//! Mapping of synthesized segments back to the original input text
+
+use std::ops::Range;
+
+/// Number of leading non-whitespace characters used to locate a segment
+const ANCHOR_CHARS: usize = 8;
+
+/// Map each segment to a character range of the original text
+///
+/// Preprocessing and AI splitting may rewrite the text, so the mapping is
+/// approximate. Each segment is located by searching for its first few
+/// characters after the previous match; a segment that cannot be found is
+/// assumed to start right after the previous segment's length. The returned
+/// ranges are guaranteed to be:
+///
+/// - monotonic: each range starts where the previous one ends
+/// - non-overlapping
+/// - covering: the first starts at 0 and the last ends at the char count
+///
+/// Ranges are in `char` indices, not bytes.
+pub(crate) fn align_segments<S: AsRef<str>>(original: &str, segments: &[S]) -> Vec<Range<usize>> {
+    let anchors: Vec<SegmentAnchor> = segments
+        .iter()
+        .map(|segment| SegmentAnchor::new(segment.as_ref()))
+        .collect();
+    align_anchors(original, &anchors)
+}
+
+/// What aligning a segment needs of its text: the first few non-whitespace
+/// characters and the length
+///
+/// Lets reports that drop segment text still be aligned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SegmentAnchor {
+    anchor: Vec<char>,
+    chars: usize,
+}
+
+impl SegmentAnchor {
+    pub(crate) fn new(segment: &str) -> Self {
+        Self {
+            anchor: segment
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .take(ANCHOR_CHARS)
+                .collect(),
+            chars: segment.chars().count(),
+        }
+    }
+}
+
+/// Map each segment, given by its anchor, to a character range of the
+/// original text, like [`align_segments`]
+pub(crate) fn align_anchors(original: &str, segments: &[SegmentAnchor]) -> Vec<Range<usize>> {
+    let chars: Vec<char> = original.chars().collect();
+    let total = chars.len();
+    if segments.is_empty() {
+        return Vec::new();
+    }
+
+    let mut starts = Vec::with_capacity(segments.len());
+    let mut cursor = 0;
+    let mut previous_len = 0;
+
+    for (idx, segment) in segments.iter().enumerate() {
+        let start = if idx == 0 {
+            0
+        } else {
+            // Skip most of the previous segment so repeated sentences do not
+            // match inside it, leaving slack for rewrites that changed its length
+            let from = (cursor + previous_len * 3 / 4).min(total);
+            // Assume an unmatched segment directly follows the previous one
+            find_anchor(&chars, from, &segment.anchor)
+                .unwrap_or_else(|| (cursor + previous_len).min(total))
+        };
+        starts.push(start);
+        cursor = start;
+        previous_len = segment.chars;
+    }
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(idx, &start)| {
+            let end = starts.get(idx + 1).copied().unwrap_or(total);
+            start..end
+        })
+        .collect()
+}
+
+/// Find the first position at or after `from` where the segment's anchor occurs
+fn find_anchor(chars: &[char], from: usize, anchor: &[char]) -> Option<usize> {
+    if anchor.is_empty() {
+        return None;
+    }
+
+    (from..chars.len()).find(|&start| {
+        if chars[start] != anchor[0] {
+            return false;
+        }
+        let mut needle = anchor.iter().peekable();
+        for c in &chars[start..] {
+            match needle.peek() {
+                None => break,
+                Some(&&expected) if *c == expected => {
+                    needle.next();
+                }
+                // Whitespace in the original is ignored inside the anchor
+                Some(_) if c.is_whitespace() => {}
+                Some(_) => return false,
+            }
+        }
+        needle.peek().is_none()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slice(text: &str, range: &Range<usize>) -> String {
+        text.chars()
+            .skip(range.start)
+            .take(range.end - range.start)
+            .collect()
+    }
+
+    #[test]
+    fn test_align_exact_segments() {
+        let original = "  第一句话。第二句话。第三句话。";
+        let segments = ["第一句话。", "第二句话。", "第三句话。"];
+        let ranges = align_segments(original, &segments);
+
+        assert_eq!(ranges, vec![0..7, 7..12, 12..17]);
+        for (range, segment) in ranges.iter().zip(segments) {
+            assert!(slice(original, range).contains(segment));
+        }
+    }
+
+    #[test]
+    fn test_align_is_monotonic_and_covering() {
+        let original = "Hello world. This is a test. Another sentence here.";
+        let segments = ["Hello world.", "This is a test.", "Another sentence here."];
+        let ranges = align_segments(original, &segments);
+
+        assert_eq!(ranges[0].start, 0);
+        assert_eq!(ranges.last().unwrap().end, original.chars().count());
+        for pair in ranges.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+            assert!(pair[0].start <= pair[0].end);
+        }
+        for (range, segment) in ranges.iter().zip(segments) {
+            assert!(slice(original, range).contains(segment));
+        }
+    }
+
+    #[test]
+    fn test_align_rewritten_segment_falls_back_to_estimate() {
+        let original = "原文一。原文二。原文三。";
+        let segments = ["原文一。", "改写后的内容", "原文三。"];
+        let ranges = align_segments(original, &segments);
+
+        assert_eq!(ranges, vec![0..4, 4..8, 8..12]);
+        for pair in ranges.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+    }
+
+    #[test]
+    fn test_align_repeated_sentences() {
+        let original = "重复。".repeat(6);
+        let segments = ["重复。重复。", "重复。重复。", "重复。重复。"];
+        let ranges = align_segments(&original, &segments);
+
+        assert_eq!(ranges, vec![0..6, 6..12, 12..18]);
+    }
+
+    #[test]
+    fn test_align_empty() {
+        assert!(align_segments::<&str>("text", &[]).is_empty());
+    }
+}
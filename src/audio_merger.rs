@@ -1,16 +1,28 @@
+use crate::encode::{encode_mp3, OutputFormat};
 use crate::error::Result;
-use hound::{WavReader, WavSpec, WavWriter};
-use std::io::Cursor;
+use crate::merged::MergedAudio;
+use crate::report::Warning;
+use crate::temp::{write_atomic, TempFile};
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Cursor, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::Duration;
+use tokio::runtime::Handle;
 
 /// Audio merger for combining multiple audio segments into a single WAV file
 ///
 /// Uses the hound library to read and write WAV files with proper format handling.
+/// A data chunk with an odd byte count, as with 8-bit mono audio and an odd
+/// sample count, is followed by the pad byte RIFF requires.
 pub struct AudioMerger;
 
 impl AudioMerger {
     /// Merge multiple audio byte segments into a single WAV file
     ///
-    /// All audio segments must have the same sample rate and format.
+    /// Segments whose format differs from the majority are converted; see
+    /// [`merge_with_warnings`](Self::merge_with_warnings).
     ///
     /// # Arguments
     ///
@@ -21,29 +33,256 @@ impl AudioMerger {
     ///
     /// Returns error if:
     /// - No audio segments provided
-    /// - Audio segments have incompatible formats
+    /// - An audio segment is not valid WAV
     /// - File I/O fails
     pub async fn merge(audio_segments: Vec<Vec<u8>>, output_path: &str) -> Result<()> {
+        Self::merge_with_warnings(audio_segments, output_path)
+            .await
+            .map(|_| ())
+    }
+
+    /// Merge audio segments and return warnings about converted segments
+    ///
+    /// The output spec is the one shared by most segments (ties go to the
+    /// highest sample rate), so a single anomalous segment cannot degrade the
+    /// whole file. Segments with a different spec are resampled and remixed
+    /// to the output spec, and each one produces a
+    /// [`Warning::SegmentSpecConverted`] naming its index. Only samples are
+    /// merged; extra chunks embedded in the segments, such as `LIST` or
+    /// `bext`, are discarded.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no segments are provided, a segment is not valid WAV,
+    /// or file I/O fails.
+    pub async fn merge_with_warnings(
+        audio_segments: Vec<Vec<u8>>,
+        output_path: &str,
+    ) -> Result<Vec<Warning>> {
+        Self::merge_with_pauses(audio_segments, &[], output_path).await
+    }
+
+    /// Merge audio segments, inserting silence after each one
+    ///
+    /// `pauses[i]` is the silence written after segment `i`; missing entries
+    /// mean no silence. Otherwise behaves like
+    /// [`merge_with_warnings`](Self::merge_with_warnings).
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no segments are provided, a segment is not valid WAV,
+    /// or file I/O fails.
+    pub async fn merge_with_pauses(
+        audio_segments: Vec<Vec<u8>>,
+        pauses: &[Duration],
+        output_path: &str,
+    ) -> Result<Vec<Warning>> {
+        let pauses = pauses.to_vec();
+        write_blocking(&Handle::current(), output_path, move |path| {
+            Self::merge_file_with_threads(audio_segments, &pauses, None, path, 0)
+        })
+        .await
+    }
+
+    /// Merge to a file, decoding segments on `threads` threads
+    ///
+    /// `0` uses the available parallelism and `1` decodes sequentially;
+    /// without the `rayon` feature decoding is always sequential. With a
+    /// `uniform_duration`, each shorter segment is padded with silence to
+    /// that duration before its pause.
+    pub(crate) fn merge_file_with_threads(
+        audio_segments: Vec<Vec<u8>>,
+        pauses: &[Duration],
+        uniform_duration: Option<Duration>,
+        output_path: &str,
+        threads: usize,
+    ) -> Result<Vec<Warning>> {
+        if audio_segments.is_empty() {
+            return Err(crate::error::Error::audio(
+                crate::error::AudioErrorKind::Empty,
+                "No audio segments to merge".to_string(),
+            ));
+        }
+
+        // Checked before the file is created, so an oversized merge leaves
+        // no file behind; only headers are read
+        Self::plan_merge(&audio_segments, pauses, uniform_duration)?;
+        let file = BufWriter::new(File::create(output_path)?);
+        Self::merge_into(
+            &audio_segments,
+            pauses,
+            uniform_duration,
+            file,
+            threads,
+            None,
+        )
+    }
+
+    /// Merge audio segments into in-memory WAV bytes
+    ///
+    /// Behaves like [`merge_with_pauses`](Self::merge_with_pauses) but
+    /// returns the merged file instead of writing it to disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no segments are provided or a segment is not valid
+    /// WAV.
+    pub async fn merge_to_vec(
+        audio_segments: Vec<Vec<u8>>,
+        pauses: &[Duration],
+    ) -> Result<(Vec<u8>, Vec<Warning>)> {
+        let pauses = pauses.to_vec();
+        run_blocking(&Handle::current(), move || {
+            Self::merge_vec_with_threads(audio_segments, &pauses, None, 0)
+        })
+        .await
+    }
+
+    /// Merge to in-memory WAV bytes, decoding segments on `threads` threads
+    ///
+    /// See [`merge_file_with_threads`](Self::merge_file_with_threads).
+    pub(crate) fn merge_vec_with_threads(
+        audio_segments: Vec<Vec<u8>>,
+        pauses: &[Duration],
+        uniform_duration: Option<Duration>,
+        threads: usize,
+    ) -> Result<(Vec<u8>, Vec<Warning>)> {
         if audio_segments.is_empty() {
-            return Err(crate::error::Error::Audio(
+            return Err(crate::error::Error::audio(
+                crate::error::AudioErrorKind::Empty,
                 "No audio segments to merge".to_string(),
             ));
         }
 
-        // Get spec from first segment
-        let first_spec = Self::extract_wav_spec(&audio_segments[0])?;
+        let mut cursor = Cursor::new(Vec::new());
+        let warnings = Self::merge_into(
+            &audio_segments,
+            pauses,
+            uniform_duration,
+            &mut cursor,
+            threads,
+            None,
+        )?;
+        Ok((cursor.into_inner(), warnings))
+    }
+
+    /// Merge to in-memory WAV bytes, also keeping the merged samples
+    ///
+    /// See [`merge_file_with_threads`](Self::merge_file_with_threads).
+    pub(crate) fn merge_pcm_with_threads(
+        audio_segments: Vec<Vec<u8>>,
+        pauses: &[Duration],
+        uniform_duration: Option<Duration>,
+        threads: usize,
+    ) -> Result<(Vec<u8>, PcmAudio, Vec<Warning>)> {
+        if audio_segments.is_empty() {
+            return Err(crate::error::Error::audio(
+                crate::error::AudioErrorKind::Empty,
+                "No audio segments to merge".to_string(),
+            ));
+        }
+
+        let mut cursor = Cursor::new(Vec::new());
+        let mut samples = Vec::new();
+        let warnings = Self::merge_into(
+            &audio_segments,
+            pauses,
+            uniform_duration,
+            &mut cursor,
+            threads,
+            Some(&mut samples),
+        )?;
+        let wav = cursor.into_inner();
+        let spec = Self::extract_wav_spec(&wav, 0)?;
+        Ok((
+            wav,
+            PcmAudio {
+                sample_rate: spec.sample_rate,
+                channels: spec.channels,
+                samples,
+            },
+            warnings,
+        ))
+    }
+
+    /// Read the spec of each segment and pick the output spec, failing if
+    /// the merged audio would not fit in a WAV file
+    fn plan_merge(
+        audio_segments: &[Vec<u8>],
+        pauses: &[Duration],
+        uniform_duration: Option<Duration>,
+    ) -> Result<(Vec<WavSpec>, WavSpec)> {
+        let infos = audio_segments
+            .iter()
+            .enumerate()
+            .map(|(idx, segment)| Self::probe_segment(segment, idx))
+            .collect::<Result<Vec<_>>>()?;
+        let specs: Vec<WavSpec> = infos.iter().map(|info| info.spec).collect();
+        let spec = Self::majority_spec(&specs);
+        check_wav_size(projected_data_bytes(
+            infos
+                .iter()
+                .map(|info| (info.frames as u64, info.spec.sample_rate)),
+            spec,
+            pauses,
+            uniform_duration,
+        ))?;
+        Ok((specs, spec))
+    }
+
+    /// Write the merged WAV to `output`, copying the merged samples in
+    /// 16-bit scale to `pcm` if given
+    fn merge_into<W: Write + Seek>(
+        audio_segments: &[Vec<u8>],
+        pauses: &[Duration],
+        uniform_duration: Option<Duration>,
+        mut output: W,
+        threads: usize,
+        mut pcm: Option<&mut Vec<i16>>,
+    ) -> Result<Vec<Warning>> {
+        let (specs, spec) = Self::plan_merge(audio_segments, pauses, uniform_duration)?;
 
-        // Create output writer with first segment's spec
-        let spec = first_spec;
-        let mut writer = WavWriter::create(output_path, spec)?;
+        let mut writer = WavWriter::new(&mut output, spec)?;
+        let mut warnings = Vec::new();
 
-        // Write each audio segment
-        for (idx, segment) in audio_segments.iter().enumerate() {
-            Self::write_segment(&mut writer, segment, idx)?;
+        // Decoding may run in parallel, but writes stay sequential and ordered
+        let prepared = Self::prepare_segments(audio_segments, &specs, spec, threads)?;
+        for (idx, (segment, segment_spec)) in prepared.zip(&specs).enumerate() {
+            let segment = segment?;
+            let frames = segment.len() / spec.channels.max(1) as usize;
+            if let Some(pcm) = pcm.as_deref_mut() {
+                match &segment {
+                    PreparedSegment::Native(samples) => {
+                        pcm.extend(samples.iter().map(|&s| s << (16 - spec.bits_per_sample)))
+                    }
+                    PreparedSegment::Converted(samples) => pcm.extend_from_slice(samples),
+                }
+            }
+            Self::write_prepared(
+                &mut writer,
+                segment,
+                idx,
+                *segment_spec,
+                spec,
+                &mut warnings,
+            )?;
+            let mut silence = 0;
+            if let Some(duration) = uniform_duration {
+                silence +=
+                    pad_to_duration(&mut writer, frames, duration, idx, spec, &mut warnings)?;
+            }
+            if let Some(pause) = pauses.get(idx) {
+                silence += write_silence(&mut writer, *pause, spec)?;
+            }
+            if let Some(pcm) = pcm.as_deref_mut() {
+                pcm.resize(pcm.len() + silence * spec.channels as usize, 0);
+            }
         }
 
+        let data_bytes = data_bytes(&writer);
         writer.finalize()?;
-        Ok(())
+        pad_data_chunk(&mut output, data_bytes)?;
+        Ok(warnings)
     }
 
     /// Convert a single audio segment to WAV file
@@ -54,55 +293,1352 @@ impl AudioMerger {
     /// * `output_path` - Path to save the WAV file
     pub async fn save_single(audio_bytes: &[u8], output_path: &str) -> Result<()> {
         if audio_bytes.is_empty() {
-            return Err(crate::error::Error::Audio("Empty audio data".to_string()));
+            return Err(crate::error::Error::audio(
+                crate::error::AudioErrorKind::Empty,
+                "Empty audio data".to_string(),
+            ));
+        }
+
+        let audio_bytes = audio_bytes.to_vec();
+        write_blocking(&Handle::current(), output_path, move |path| {
+            Self::write_single(&audio_bytes, BufWriter::new(File::create(path)?))
+        })
+        .await
+    }
+
+    /// Convert a single audio segment to a WAV file, keeping extra chunks
+    ///
+    /// Like [`save_single`](Self::save_single), but chunks other than `fmt `
+    /// and `data` embedded by the provider, such as `LIST`, `bext` or
+    /// `fact`, are copied verbatim after the data chunk.
+    ///
+    /// # Arguments
+    ///
+    /// * `audio_bytes` - Raw audio data in WAV format
+    /// * `output_path` - Path to save the WAV file
+    pub async fn save_single_preserving_chunks(
+        audio_bytes: &[u8],
+        output_path: &str,
+    ) -> Result<()> {
+        let audio_bytes = audio_bytes.to_vec();
+        write_blocking(&Handle::current(), output_path, move |path| {
+            let wav = Self::single_to_vec(&audio_bytes, true)?;
+            std::fs::write(path, wav)?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Re-encode a single audio segment into in-memory WAV bytes
+    pub(crate) fn single_to_vec(
+        audio_bytes: &[u8],
+        preserve_extra_chunks: bool,
+    ) -> Result<Vec<u8>> {
+        if audio_bytes.is_empty() {
+            return Err(crate::error::Error::audio(
+                crate::error::AudioErrorKind::Empty,
+                "Empty audio data".to_string(),
+            ));
         }
 
+        let mut cursor = Cursor::new(Vec::new());
+        Self::write_single(audio_bytes, &mut cursor)?;
+        let mut wav = cursor.into_inner();
+        if preserve_extra_chunks {
+            append_chunks(&mut wav, &extra_chunks(audio_bytes))?;
+        }
+        Ok(wav)
+    }
+
+    fn write_single<W: Write + Seek>(audio_bytes: &[u8], mut output: W) -> Result<()> {
         let cursor = Cursor::new(audio_bytes);
-        let mut reader = WavReader::new(cursor)
-            .map_err(|e| crate::error::Error::Audio(format!("Invalid WAV format: {}", e)))?;
+        let mut reader = WavReader::new(cursor).map_err(|e| {
+            crate::error::Error::audio(
+                crate::error::AudioErrorKind::InvalidWav,
+                format!("Invalid WAV format: {}", e),
+            )
+        })?;
 
         let spec = reader.spec();
-        let mut writer = WavWriter::create(output_path, spec)?;
+        let mut writer = WavWriter::new(&mut output, spec)?;
 
         for sample in reader.samples::<i16>() {
             writer.write_sample(sample?)?;
         }
 
+        let data_bytes = data_bytes(&writer);
         writer.finalize()?;
+        pad_data_chunk(&mut output, data_bytes)
+    }
+
+    /// Compare the samples of two WAVs, such as a conversion and a golden file
+    ///
+    /// Samples are compared at 16-bit precision. Headers and extra chunks are
+    /// ignored, so two files with the same spec and samples are identical
+    /// even if their bytes differ.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if either input is not valid WAV or their specs differ.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use text2audio::AudioMerger;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let golden = std::fs::read("golden.wav")?;
+    /// let output = std::fs::read("output.wav")?;
+    /// let result = AudioMerger::compare(&golden, &output)?;
+    /// assert!(result.identical);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compare(a: &[u8], b: &[u8]) -> Result<CompareResult> {
+        let spec_a = Self::extract_wav_spec(a, 0)?;
+        let spec_b = Self::extract_wav_spec(b, 1)?;
+        if spec_a != spec_b {
+            return Err(crate::error::Error::audio(crate::error::AudioErrorKind::SpecMismatch, format!(
+                "Cannot compare WAVs with different specs: {} Hz/{} ch/{} bit and {} Hz/{} ch/{} bit",
+                spec_a.sample_rate,
+                spec_a.channels,
+                spec_a.bits_per_sample,
+                spec_b.sample_rate,
+                spec_b.channels,
+                spec_b.bits_per_sample
+            )));
+        }
+
+        let samples_a = Self::decode_segment(a, 0)?;
+        let samples_b = Self::decode_segment(b, 1)?;
+        let max_amplitude_diff = samples_a
+            .iter()
+            .zip(&samples_b)
+            .map(|(x, y)| x.abs_diff(*y))
+            .max()
+            .unwrap_or(0);
+        let sample_count_diff = samples_a.len().abs_diff(samples_b.len());
+
+        Ok(CompareResult {
+            sample_count_diff,
+            max_amplitude_diff,
+            identical: sample_count_diff == 0 && max_amplitude_diff == 0,
+        })
+    }
+
+    /// Repair a truncated or corrupt WAV file in place
+    ///
+    /// Recovers as much audio as the file holds: a data chunk cut short or
+    /// with a wrong size keeps the bytes actually present, a trailing
+    /// partial frame is dropped, and the RIFF and data chunk sizes are
+    /// rewritten to match. Chunks before the data chunk are kept, as are
+    /// complete chunks after it if its size was intact. A file that needs
+    /// no repair is left untouched.
+    ///
+    /// If the output has a metadata or timing sidecar, the duration it
+    /// records is reported as [`RepairReport::expected`].
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - WAV file to repair
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Audio`](crate::Error::Audio) if the file has no
+    /// `RIFF`/`WAVE` signature, no complete `fmt ` chunk, no data chunk or
+    /// an unsupported encoding, and an I/O error if it cannot be read or
+    /// rewritten.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use text2audio::AudioMerger;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let report = AudioMerger::repair("output.wav")?;
+    /// if let Some(missing) = report.missing() {
+    ///     println!("lost {:.1}s of audio", missing.as_secs_f64());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn repair(path: impl AsRef<Path>) -> Result<RepairReport> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        let (wav, mut report) = repair_bytes(&bytes)?;
+        if report.modified {
+            // Written beside the file and renamed, so a second failure
+            // cannot lose what this one left
+            let mut temp = path.as_os_str().to_owned();
+            temp.push(".repair.tmp");
+            write_atomic(path, temp, &wav)?;
+        }
+        report.expected = sidecar_duration(path);
+        Ok(report)
+    }
+
+    /// Convert a WAV file to `format`, streaming its samples through the
+    /// encoder
+    ///
+    /// Only a block of samples is held in memory at a time, so files of any
+    /// length can be converted. The output is written beside `output_path`
+    /// and renamed into place once complete. WAV output is a copy of the
+    /// input; samples in other formats are encoded as 16-bit.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_path` - WAV file to convert
+    /// * `output_path` - Path to write the converted audio to
+    /// * `format` - Format of the converted audio
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Hound`](crate::Error::Hound) if the input is not a
+    /// valid WAV file, [`Error::Config`](crate::Error::Config) for MP3
+    /// output without the `mp3` feature, and
+    /// [`Error::Audio`](crate::Error::Audio) if the encoder rejects the
+    /// audio.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use text2audio::{AudioMerger, OutputFormat};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// AudioMerger::transcode(
+    ///     "output.wav",
+    ///     "output.mp3",
+    ///     OutputFormat::Mp3 { bitrate_kbps: 128 },
+    /// )?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn transcode(
+        input_path: impl AsRef<Path>,
+        output_path: impl AsRef<Path>,
+        format: OutputFormat,
+    ) -> Result<()> {
+        let (input, output) = (input_path.as_ref(), output_path.as_ref());
+        let reader = WavReader::open(input)?;
+        let mut temp = output.as_os_str().to_owned();
+        temp.push(".transcode.tmp");
+        let temp = TempFile::new(temp);
+        match format {
+            OutputFormat::Wav => {
+                drop(reader);
+                std::fs::copy(input, temp.path())?;
+            }
+            OutputFormat::Mp3 { bitrate_kbps } => {
+                let mut file = BufWriter::new(File::create(temp.path())?);
+                encode_mp3(reader, bitrate_kbps, &mut file)?;
+                file.flush()?;
+            }
+        }
+        temp.persist(output)?;
         Ok(())
     }
 
-    /// Extract WAV specification from audio bytes
-    fn extract_wav_spec(audio_bytes: &[u8]) -> Result<WavSpec> {
-        let cursor = Cursor::new(audio_bytes);
-        let reader = WavReader::new(cursor)
-            .map_err(|e| crate::error::Error::Audio(format!("Invalid WAV format: {}", e)))?;
+    /// Extract WAV specification from audio bytes, naming the segment on failure
+    fn extract_wav_spec(audio_bytes: &[u8], idx: usize) -> Result<WavSpec> {
+        Self::probe_segment(audio_bytes, idx).map(|info| info.spec)
+    }
+
+    /// Read a segment's WAV header, naming the segment on failure
+    fn probe_segment(audio_bytes: &[u8], idx: usize) -> Result<WavInfo> {
+        probe(audio_bytes).map_err(|reason| {
+            crate::error::Error::audio(
+                crate::error::AudioErrorKind::InvalidWav,
+                format!("Segment {} invalid WAV: {}", idx, reason),
+            )
+        })
+    }
+
+    /// Pick the spec shared by most segments, preferring higher sample rates on ties
+    fn majority_spec(specs: &[WavSpec]) -> WavSpec {
+        let mut counts: HashMap<(u16, u32, u16, bool), usize> = HashMap::new();
+        for spec in specs {
+            *counts.entry(spec_key(spec)).or_default() += 1;
+        }
+
+        specs
+            .iter()
+            .enumerate()
+            .max_by_key(|(idx, spec)| {
+                (
+                    counts[&spec_key(spec)],
+                    spec.sample_rate,
+                    std::cmp::Reverse(*idx),
+                )
+            })
+            .map(|(_, spec)| *spec)
+            .expect("specs is not empty")
+    }
+
+    /// Decode a segment into 16-bit samples in `spec`'s sample rate and
+    /// channel count
+    #[cfg(feature = "live")]
+    pub(crate) fn decode_as(segment: &[u8], idx: usize, spec: WavSpec) -> Result<Vec<i16>> {
+        let segment_spec = Self::extract_wav_spec(segment, idx)?;
+        let samples = Self::decode_segment(segment, idx)?;
+        if segment_spec.sample_rate == spec.sample_rate && segment_spec.channels == spec.channels {
+            return Ok(samples);
+        }
+        Ok(convert_samples(&samples, segment_spec, spec))
+    }
+
+    /// Decode a segment into 16-bit samples regardless of its sample format
+    fn decode_segment(segment: &[u8], idx: usize) -> Result<Vec<i16>> {
+        let cursor = Cursor::new(segment);
+        let mut reader = WavReader::new(cursor).map_err(|e| {
+            crate::error::Error::audio(
+                crate::error::AudioErrorKind::InvalidWav,
+                format!("Segment {} invalid WAV: {}", idx, e),
+            )
+        })?;
+        let samples = samples_i16(&mut reader).collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(samples)
+    }
+
+    /// Write a segment, converting it first if its spec differs from the
+    /// output, returning the number of frames written
+    fn append_segment<W: Write + Seek>(
+        writer: &mut WavWriter<W>,
+        segment: &[u8],
+        idx: usize,
+        segment_spec: WavSpec,
+        spec: WavSpec,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<usize> {
+        let prepared = Self::prepare_segment(segment, idx, segment_spec, spec)?;
+        let frames = prepared.len() / spec.channels.max(1) as usize;
+        Self::write_prepared(writer, prepared, idx, segment_spec, spec, warnings)?;
+        Ok(frames)
+    }
+
+    /// Decode segments in order, on a thread pool unless `threads` is 1
+    #[cfg_attr(not(feature = "rayon"), allow(unused_variables))]
+    fn prepare_segments<'a>(
+        audio_segments: &'a [Vec<u8>],
+        specs: &'a [WavSpec],
+        spec: WavSpec,
+        threads: usize,
+    ) -> Result<Box<dyn Iterator<Item = Result<PreparedSegment>> + 'a>> {
+        #[cfg(feature = "rayon")]
+        if threads != 1 && audio_segments.len() > 1 {
+            let prepared = Self::prepare_parallel(audio_segments, specs, spec, threads)?;
+            return Ok(Box::new(prepared.into_iter().map(Ok)));
+        }
 
-        Ok(reader.spec())
+        Ok(Box::new(audio_segments.iter().zip(specs).enumerate().map(
+            move |(idx, (segment, segment_spec))| {
+                Self::prepare_segment(segment, idx, *segment_spec, spec)
+            },
+        )))
     }
 
-    /// Write a single audio segment to the WAV writer
-    fn write_segment(
-        writer: &mut WavWriter<std::io::BufWriter<std::fs::File>>,
+    /// Decode all segments on a rayon pool of `threads` threads, or on the
+    /// global pool when `threads` is 0
+    #[cfg(feature = "rayon")]
+    fn prepare_parallel(
+        audio_segments: &[Vec<u8>],
+        specs: &[WavSpec],
+        spec: WavSpec,
+        threads: usize,
+    ) -> Result<Vec<PreparedSegment>> {
+        use rayon::prelude::*;
+
+        let prepare = || {
+            audio_segments
+                .par_iter()
+                .zip(specs)
+                .enumerate()
+                .map(|(idx, (segment, segment_spec))| {
+                    Self::prepare_segment(segment, idx, *segment_spec, spec)
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+        if threads == 0 {
+            return prepare();
+        }
+
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(|e| {
+                crate::error::Error::audio(
+                    crate::error::AudioErrorKind::Other,
+                    format!("Failed to start merge threads: {}", e),
+                )
+            })?
+            .install(prepare)
+    }
+
+    /// Decode a segment's samples, converting them if its spec differs from
+    /// the output
+    fn prepare_segment(
         segment: &[u8],
         idx: usize,
-    ) -> Result<()> {
+        segment_spec: WavSpec,
+        spec: WavSpec,
+    ) -> Result<PreparedSegment> {
+        if segment_spec != spec {
+            let samples = Self::decode_segment(segment, idx)?;
+            return Ok(PreparedSegment::Converted(convert_samples(
+                &samples,
+                segment_spec,
+                spec,
+            )));
+        }
+
         let cursor = Cursor::new(segment);
         let mut reader = WavReader::new(cursor).map_err(|e| {
-            crate::error::Error::Audio(format!("Segment {} invalid WAV: {}", idx, e))
+            crate::error::Error::audio(
+                crate::error::AudioErrorKind::InvalidWav,
+                format!("Segment {} invalid WAV: {}", idx, e),
+            )
         })?;
+        let samples = reader
+            .samples::<i16>()
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(PreparedSegment::Native(samples))
+    }
 
-        for sample in reader.samples::<i16>() {
-            writer.write_sample(sample?)?;
+    /// Write a prepared segment, warning if it was converted
+    fn write_prepared<W: Write + Seek>(
+        writer: &mut WavWriter<W>,
+        prepared: PreparedSegment,
+        idx: usize,
+        segment_spec: WavSpec,
+        spec: WavSpec,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<()> {
+        match prepared {
+            PreparedSegment::Native(samples) => {
+                for sample in samples {
+                    writer.write_sample(sample)?;
+                }
+            }
+            PreparedSegment::Converted(samples) => {
+                warnings.push(Warning::SegmentSpecConverted {
+                    index: idx,
+                    sample_rate: segment_spec.sample_rate,
+                    channels: segment_spec.channels,
+                    target_sample_rate: spec.sample_rate,
+                    target_channels: spec.channels,
+                });
+                for sample in samples {
+                    write_i16_as(writer, sample, spec)?;
+                }
+            }
         }
 
         Ok(())
     }
 }
 
+/// Segment samples decoded for writing in the output spec
+enum PreparedSegment {
+    /// Samples of a segment already in the output spec, written as read
+    Native(Vec<i16>),
+    /// Samples converted from a different spec
+    Converted(Vec<i16>),
+}
+
+impl PreparedSegment {
+    /// Number of interleaved samples
+    fn len(&self) -> usize {
+        match self {
+            PreparedSegment::Native(samples) | PreparedSegment::Converted(samples) => samples.len(),
+        }
+    }
+}
+
+/// Decoded samples of a conversion's audio
+///
+/// Passed to the inspector set with
+/// [`Text2Audio::with_audio_inspector`](crate::Text2Audio::with_audio_inspector).
+/// Samples are interleaved and scaled to 16 bits whatever the bit depth of
+/// the output file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcmAudio {
+    sample_rate: u32,
+    channels: u16,
+    samples: Vec<i16>,
+}
+
+impl PcmAudio {
+    /// Sample rate in Hz
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Number of interleaved channels
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// All samples, interleaved
+    pub fn samples(&self) -> &[i16] {
+        &self.samples
+    }
+
+    /// Largest absolute sample value
+    pub fn peak(&self) -> u16 {
+        self.samples
+            .iter()
+            .map(|sample| sample.unsigned_abs())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Integrated loudness in LUFS, measured per ITU-R BS.1770
+    ///
+    /// Returns `None` for audio shorter than 400 ms or too quiet to
+    /// measure; see [`loudness::integrated_loudness`](crate::loudness::integrated_loudness).
+    pub fn integrated_loudness(&self) -> Option<f64> {
+        crate::loudness::integrated_loudness(&self.samples, self.channels, self.sample_rate)
+    }
+
+    /// True peak in dBTP, estimated by four-times oversampling
+    pub fn true_peak(&self) -> f64 {
+        crate::loudness::true_peak(&self.samples, self.channels)
+    }
+
+    /// Bring the audio to `target` LUFS, returning the gain applied in dB
+    pub(crate) fn normalize_loudness(&mut self, target: f64) -> Option<f64> {
+        crate::loudness::normalize(&mut self.samples, self.channels, self.sample_rate, target)
+    }
+
+    /// Encode the samples as WAV in `spec`, whose channels and sample rate
+    /// must match the audio's
+    pub(crate) fn to_wav(&self, spec: WavSpec) -> Result<Vec<u8>> {
+        let mut cursor = Cursor::new(Vec::new());
+        let mut writer = WavWriter::new(&mut cursor, spec)?;
+        for &sample in &self.samples {
+            write_i16_as(&mut writer, sample, spec)?;
+        }
+        let data_bytes = data_bytes(&writer);
+        writer.finalize()?;
+        pad_data_chunk(&mut cursor, data_bytes)?;
+        Ok(cursor.into_inner())
+    }
+
+    /// Iterate over frames, each holding one sample per channel
+    pub fn frames(&self) -> impl Iterator<Item = &[i16]> {
+        self.samples.chunks_exact(self.channels.max(1) as usize)
+    }
+
+    /// Playback duration
+    pub fn duration(&self) -> Duration {
+        let frames = self.samples.len() / self.channels.max(1) as usize;
+        Duration::from_secs_f64(frames as f64 / self.sample_rate.max(1) as f64)
+    }
+
+    /// Decode the WAV bytes of segment `idx`
+    pub(crate) fn from_wav(wav: &[u8], idx: usize) -> Result<Self> {
+        let spec = AudioMerger::extract_wav_spec(wav, idx)?;
+        Ok(Self {
+            sample_rate: spec.sample_rate,
+            channels: spec.channels,
+            samples: AudioMerger::decode_segment(wav, idx)?,
+        })
+    }
+
+    /// Consecutive pieces of at most `max` each, cut at frame boundaries
+    ///
+    /// Every piece but the last holds exactly the frames that fit in
+    /// `max`, and at least one frame.
+    pub(crate) fn split(&self, max: Duration) -> impl Iterator<Item = PcmAudio> + '_ {
+        let frames = max.as_nanos() * u128::from(self.sample_rate) / 1_000_000_000;
+        let frames = usize::try_from(frames).unwrap_or(usize::MAX).max(1);
+        self.samples
+            .chunks(frames.saturating_mul(self.channels.max(1) as usize))
+            .map(move |samples| PcmAudio {
+                sample_rate: self.sample_rate,
+                channels: self.channels,
+                samples: samples.to_vec(),
+            })
+    }
+}
+
+/// Piece of a segment's audio yielded by
+/// [`Text2Audio::convert_stream_chunks`](crate::Text2Audio::convert_stream_chunks)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioChunk {
+    /// Index of the segment the chunk belongs to
+    pub segment_index: usize,
+    /// Index of the chunk within its segment
+    pub chunk_index: usize,
+    /// Playback position of the chunk's start, from the start of the
+    /// conversion
+    pub offset: Duration,
+    /// Decoded audio of the chunk
+    pub pcm: PcmAudio,
+}
+
+/// Run CPU- and IO-heavy audio work on the blocking thread pool of
+/// `runtime`, so that it does not hold up other tasks
+///
+/// A panic in `work` is resumed in the caller.
+pub(crate) async fn run_blocking<T: Send + 'static>(
+    runtime: &Handle,
+    work: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    match runtime.spawn_blocking(work).await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(crate::error::Error::audio(
+            crate::error::AudioErrorKind::Other,
+            format!("audio task did not complete: {}", e),
+        )),
+    }
+}
+
+/// Run `write`, which writes the file at `output_path`, on the blocking
+/// thread pool of `runtime`
+///
+/// Blocking work cannot be interrupted, so if the caller stops waiting
+/// before it finishes, the file it wrote is removed once it does.
+pub(crate) async fn write_blocking<T: Send + 'static>(
+    runtime: &Handle,
+    output_path: &str,
+    write: impl FnOnce(&str) -> Result<T> + Send + 'static,
+) -> Result<T> {
+    let path = output_path.to_string();
+    let (value, written) = run_blocking(runtime, move || {
+        let value = write(&path)?;
+        // Dropped with the task's output unless the caller receives it
+        Ok((value, TempFile::new(path)))
+    })
+    .await?;
+    written.keep();
+    Ok(value)
+}
+
+/// Read the samples of `reader` as 16-bit samples regardless of their format
+pub(crate) fn samples_i16<R: Read>(
+    reader: &mut WavReader<R>,
+) -> Box<dyn Iterator<Item = hound::Result<i16>> + '_> {
+    let spec = reader.spec();
+    match (spec.sample_format, spec.bits_per_sample) {
+        (SampleFormat::Float, _) => Box::new(
+            reader
+                .samples::<f32>()
+                .map(|s| s.map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)),
+        ),
+        (SampleFormat::Int, bits) if bits <= 16 => Box::new(
+            reader
+                .samples::<i16>()
+                .map(move |s| s.map(|s| s << (16 - bits))),
+        ),
+        (SampleFormat::Int, bits) => Box::new(
+            reader
+                .samples::<i32>()
+                .map(move |s| s.map(|s| (s >> (bits - 16)) as i16)),
+        ),
+    }
+}
+
+/// Format and length of WAV audio, read from its header
+///
+/// Returned by [`probe_bytes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WavInfo {
+    /// Sample rate, channels, bit depth and sample format
+    pub spec: WavSpec,
+    /// Number of frames, each holding one sample per channel
+    pub frames: u32,
+    /// Playback duration
+    pub duration: Duration,
+}
+
+/// Read the format and length of WAV bytes without decoding the samples
+///
+/// Only the header up to the start of the data chunk is parsed, so probing
+/// is cheap regardless of the audio's length. Chunks before the data chunk,
+/// such as `LIST`, are skipped.
+///
+/// # Errors
+///
+/// Returns [`Error::Audio`](crate::Error::Audio) saying what is wrong with
+/// the header: empty input, a truncated header, a missing `RIFF`/`WAVE`
+/// signature or `fmt ` chunk, or an unsupported encoding.
+///
+/// # Examples
+///
+/// ```no_run
+/// use text2audio::probe_bytes;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let info = probe_bytes(&std::fs::read("output.wav")?)?;
+/// println!(
+///     "{} Hz, {} ch, {:.1}s",
+///     info.spec.sample_rate,
+///     info.spec.channels,
+///     info.duration.as_secs_f64()
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn probe_bytes(bytes: &[u8]) -> Result<WavInfo> {
+    probe(bytes).map_err(invalid_header)
+}
+
+/// Like [`probe_bytes`], reading the header from `reader`
+pub(crate) fn probe_reader<R: Read>(reader: R) -> Result<WavInfo> {
+    probe_header(reader).map_err(invalid_header)
+}
+
+fn invalid_header(reason: String) -> crate::error::Error {
+    crate::error::Error::audio(
+        crate::error::AudioErrorKind::InvalidWav,
+        format!("Invalid WAV header: {}", reason),
+    )
+}
+
+/// Header of WAV bytes, or the reason it cannot be read
+fn probe(bytes: &[u8]) -> std::result::Result<WavInfo, String> {
+    if bytes.is_empty() {
+        return Err("input is empty".to_string());
+    }
+    probe_header(bytes)
+}
+
+fn probe_header<R: Read>(reader: R) -> std::result::Result<WavInfo, String> {
+    // hound reports some short reads as `Other` I/O errors
+    let reader = WavReader::new(reader).map_err(|e| match e {
+        hound::Error::IoError(e)
+            if e.kind() == ErrorKind::UnexpectedEof
+                || e.to_string() == "Failed to read enough bytes." =>
+        {
+            "truncated before the data chunk".to_string()
+        }
+        hound::Error::FormatError(reason) => reason.to_string(),
+        hound::Error::Unsupported => "unsupported encoding".to_string(),
+        e => e.to_string(),
+    })?;
+    let spec = reader.spec();
+    let frames = reader.duration();
+    Ok(WavInfo {
+        spec,
+        frames,
+        duration: Duration::from_secs_f64(frames as f64 / spec.sample_rate.max(1) as f64),
+    })
+}
+
+/// Sample-by-sample difference between two WAVs
+///
+/// Returned by [`AudioMerger::compare`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompareResult {
+    /// Absolute difference between the two sample counts
+    pub sample_count_diff: usize,
+    /// Largest absolute difference between samples at the same position,
+    /// compared over the shorter of the two
+    pub max_amplitude_diff: u16,
+    /// Whether both have the same samples
+    pub identical: bool,
+}
+
+/// Audio recovered by [`AudioMerger::repair`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RepairReport {
+    /// Number of frames recovered
+    pub frames: u32,
+    /// Playback duration of the recovered audio
+    pub recovered: Duration,
+    /// Duration recorded by the output's sidecar, if one was found
+    pub expected: Option<Duration>,
+    /// Bytes of a trailing partial frame that were dropped
+    pub partial_frame_bytes: usize,
+    /// Whether the file was rewritten
+    pub modified: bool,
+}
+
+impl RepairReport {
+    /// Audio lost compared with the sidecar, if one was found
+    pub fn missing(&self) -> Option<Duration> {
+        self.expected
+            .map(|expected| expected.saturating_sub(self.recovered))
+    }
+}
+
+/// Repaired WAV bytes and what was recovered
+///
+/// A data chunk whose size is zero, as left by a writer that never
+/// finalized, or larger than the bytes present takes every byte to the end
+/// of the input.
+fn repair_bytes(bytes: &[u8]) -> Result<(Vec<u8>, RepairReport)> {
+    let unrecoverable = |reason: &str| {
+        crate::error::Error::audio(
+            crate::error::AudioErrorKind::InvalidWav,
+            format!("Unrecoverable WAV: {}", reason),
+        )
+    };
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(unrecoverable("missing RIFF WAVE header"));
+    }
+
+    let mut fmt = None;
+    let mut offset = 12;
+    let (data_offset, declared) = loop {
+        let Some((id, size)) = chunk_header(bytes, offset) else {
+            return Err(unrecoverable("no data chunk"));
+        };
+        if &id == b"data" {
+            break (offset, size);
+        }
+        let body = offset + 8;
+        let Some(body_end) = body.checked_add(size).filter(|&end| end <= bytes.len()) else {
+            return Err(unrecoverable("truncated before the data chunk"));
+        };
+        if &id == b"fmt " {
+            fmt = Some(&bytes[body..body_end]);
+        }
+        offset = body_end + size % 2;
+    };
+    let block_align = match fmt {
+        Some(fmt) if fmt.len() >= 16 => u16::from_le_bytes([fmt[12], fmt[13]]) as usize,
+        _ => return Err(unrecoverable("no complete fmt chunk")),
+    };
+    if block_align == 0 {
+        return Err(unrecoverable("fmt chunk has a block alignment of zero"));
+    }
+
+    let data = data_offset + 8;
+    let available = bytes.len() - data;
+    let intact = declared != 0 && declared <= available;
+    let data_len = if intact { declared } else { available };
+    let kept = data_len - data_len % block_align;
+    let trailing = if intact {
+        extra_chunks_from(bytes, data + declared + declared % 2)
+    } else {
+        Vec::new()
+    };
+
+    let mut wav = bytes[..data_offset].to_vec();
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(kept as u32).to_le_bytes());
+    wav.extend_from_slice(&bytes[data..data + kept]);
+    if kept % 2 == 1 {
+        wav.push(0);
+    }
+    append_chunks(&mut wav, &trailing)?;
+    let info = probe(&wav).map_err(invalid_header)?;
+
+    let report = RepairReport {
+        frames: info.frames,
+        recovered: info.duration,
+        expected: None,
+        partial_frame_bytes: data_len - kept,
+        modified: wav != bytes,
+    };
+    Ok((wav, report))
+}
+
+/// Duration recorded by the metadata sidecar of `path`, or else the end of
+/// the last segment in its timing sidecar
+fn sidecar_duration(path: &Path) -> Option<Duration> {
+    let sidecar = |suffix: &str| -> Option<serde_json::Value> {
+        let mut name = path.as_os_str().to_owned();
+        name.push(suffix);
+        serde_json::from_slice(&std::fs::read(name).ok()?).ok()
+    };
+    let seconds = sidecar(".json")
+        .and_then(|metadata| metadata["duration_seconds"].as_f64())
+        .or_else(|| {
+            sidecar(".segments.json")?
+                .as_array()?
+                .iter()
+                .filter_map(|entry| {
+                    Some(entry["start_seconds"].as_f64()? + entry["duration_seconds"].as_f64()?)
+                })
+                .reduce(f64::max)
+        })?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Merger that appends segments to the output file as they arrive
+///
+/// Unlike [`AudioMerger::merge`], segments are written immediately instead of
+/// being held in memory, so memory use stays bounded regardless of document
+/// length. The tradeoff is that segments must be appended in their final
+/// order and the output spec is fixed by the first segment rather than by
+/// majority vote; later segments with a different spec are converted to it.
+///
+/// A merger dropped without [`finalize`](Self::finalize), for example
+/// because the conversion writing it failed or its future was dropped,
+/// deletes the partial output file.
+pub struct IncrementalMerger {
+    output_path: String,
+    writer: Option<(WavWriter<BufWriter<File>>, WavSpec)>,
+    next_index: usize,
+    uniform_duration: Option<Duration>,
+    warnings: Vec<Warning>,
+}
+
+impl IncrementalMerger {
+    /// Create a merger writing to `output_path`
+    ///
+    /// The file is created when the first segment is appended.
+    pub fn new(output_path: impl Into<String>) -> Self {
+        Self {
+            output_path: output_path.into(),
+            writer: None,
+            next_index: 0,
+            uniform_duration: None,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Pad each appended segment with silence to `duration`
+    ///
+    /// Segments longer than `duration` are written in full and produce a
+    /// [`Warning::SegmentExceedsUniformDuration`].
+    pub fn with_uniform_segment_duration(mut self, duration: Duration) -> Self {
+        self.uniform_duration = Some(duration);
+        self
+    }
+
+    /// Append the next segment's WAV bytes to the output file
+    pub fn append(&mut self, segment: &[u8]) -> Result<()> {
+        let idx = self.next_index;
+        let info = AudioMerger::probe_segment(segment, idx)?;
+        let segment_spec = info.spec;
+        let written = self
+            .writer
+            .as_ref()
+            .map_or(0, |(writer, _)| data_bytes(writer));
+        let spec = self.writer.as_ref().map_or(segment_spec, |(_, spec)| *spec);
+        check_wav_size(
+            written
+                + projected_data_bytes(
+                    [(info.frames as u64, segment_spec.sample_rate)].into_iter(),
+                    spec,
+                    &[],
+                    self.uniform_duration,
+                ),
+        )?;
+
+        let (writer, spec) = match &mut self.writer {
+            Some((writer, spec)) => (writer, *spec),
+            None => {
+                let writer = WavWriter::create(&self.output_path, segment_spec)?;
+                let (writer, spec) = self.writer.insert((writer, segment_spec));
+                (writer, *spec)
+            }
+        };
+
+        let frames = AudioMerger::append_segment(
+            writer,
+            segment,
+            idx,
+            segment_spec,
+            spec,
+            &mut self.warnings,
+        )?;
+        if let Some(duration) = self.uniform_duration {
+            pad_to_duration(writer, frames, duration, idx, spec, &mut self.warnings)?;
+        }
+        self.next_index += 1;
+        Ok(())
+    }
+
+    /// Append silence in the output spec
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no segment was appended yet, since the output spec
+    /// is not known until then, or if file I/O fails.
+    pub fn append_silence(&mut self, duration: Duration) -> Result<()> {
+        let (writer, spec) = self.writer.as_mut().ok_or_else(|| {
+            crate::error::Error::audio(
+                crate::error::AudioErrorKind::Other,
+                "Cannot append silence before the first segment".to_string(),
+            )
+        })?;
+        let silence = duration_frames(duration, *spec) as u64 * frame_bytes(*spec);
+        check_wav_size(data_bytes(writer) + silence)?;
+        write_silence(writer, duration, *spec)?;
+        Ok(())
+    }
+
+    /// Number of segments appended so far
+    pub fn segment_count(&self) -> usize {
+        self.next_index
+    }
+
+    /// Finish the output file and return warnings about converted segments
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no segment was appended or file I/O fails; the
+    /// unfinished output is then deleted.
+    pub fn finalize(mut self) -> Result<Vec<Warning>> {
+        let (writer, _) = self.writer.take().ok_or_else(|| {
+            crate::error::Error::audio(
+                crate::error::AudioErrorKind::Empty,
+                "No audio segments to merge".to_string(),
+            )
+        })?;
+        let data_bytes = data_bytes(&writer);
+        let finished = (|| -> Result<()> {
+            writer.finalize()?;
+            if !data_bytes.is_multiple_of(2) {
+                let mut file = OpenOptions::new().write(true).open(&self.output_path)?;
+                pad_data_chunk(&mut file, data_bytes)?;
+            }
+            Ok(())
+        })();
+        if finished.is_err() {
+            let _ = std::fs::remove_file(&self.output_path);
+        }
+        finished.map(|()| std::mem::take(&mut self.warnings))
+    }
+
+    /// Finish the output file and load it as [`MergedAudio`], with the
+    /// warnings about converted segments
+    ///
+    /// The finished file is read back once, so later encoding or decoding
+    /// works from memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if [`finalize`](Self::finalize) fails or the file
+    /// cannot be read back.
+    pub fn finalize_audio(self) -> Result<(MergedAudio, Vec<Warning>)> {
+        let path = self.output_path.clone();
+        let warnings = self.finalize()?;
+        Ok((MergedAudio::from_file(path)?, warnings))
+    }
+}
+
+impl Drop for IncrementalMerger {
+    fn drop(&mut self) {
+        if let Some((writer, _)) = self.writer.take() {
+            // Closed first, as open files cannot be removed on Windows
+            drop(writer);
+            let _ = std::fs::remove_file(&self.output_path);
+        }
+    }
+}
+
+/// Size of the sample data written so far, in bytes
+fn data_bytes<W: Write + Seek>(writer: &WavWriter<W>) -> u64 {
+    let bytes_per_sample = (writer.spec().bits_per_sample as u64).div_ceil(8);
+    writer.len() as u64 * bytes_per_sample
+}
+
+/// Append the pad byte RIFF requires after an odd-sized data chunk
+///
+/// hound writes the data chunk last and does not pad it, so the pad goes at
+/// the end of the file and the RIFF size is updated to include it.
+fn pad_data_chunk<W: Write + Seek>(output: &mut W, data_bytes: u64) -> Result<()> {
+    if data_bytes.is_multiple_of(2) {
+        return Ok(());
+    }
+
+    let end = output.seek(SeekFrom::End(0))?;
+    output.write_all(&[0])?;
+    let riff_size = u32::try_from(end + 1 - 8).map_err(|_| {
+        crate::error::Error::audio(
+            crate::error::AudioErrorKind::TooLarge,
+            "WAV output exceeds 4 GiB".to_string(),
+        )
+    })?;
+    output.seek(SeekFrom::Start(4))?;
+    output.write_all(&riff_size.to_le_bytes())?;
+    output.seek(SeekFrom::End(0))?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Split RIFF WAVE bytes into their chunks' IDs and bodies
+///
+/// The RIFF size must match the length of `bytes`, and every chunk must be
+/// followed by a pad byte when its size is odd.
+pub(crate) fn riff_chunks(bytes: &[u8]) -> Result<Vec<([u8; 4], &[u8])>> {
+    let malformed = |reason: String| {
+        crate::error::Error::audio(
+            crate::error::AudioErrorKind::InvalidWav,
+            format!("Malformed RIFF: {}", reason),
+        )
+    };
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(malformed("missing RIFF WAVE header".to_string()));
+    }
+    let riff_size = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+    if riff_size + 8 != bytes.len() {
+        return Err(malformed(format!(
+            "RIFF size {} does not match file length {}",
+            riff_size,
+            bytes.len()
+        )));
+    }
+
+    let mut chunks = Vec::new();
+    let mut offset = 12;
+    while offset < bytes.len() {
+        let Some((id, size)) = chunk_header(bytes, offset) else {
+            return Err(malformed(format!(
+                "truncated chunk header at offset {}",
+                offset
+            )));
+        };
+        let body = offset + 8;
+        let end = body + size + size % 2;
+        if end > bytes.len() {
+            return Err(malformed(format!(
+                "chunk \"{}\" at offset {} needs {} bytes but only {} remain",
+                String::from_utf8_lossy(&id),
+                offset,
+                end - body,
+                bytes.len() - body
+            )));
+        }
+        chunks.push((id, &bytes[body..body + size]));
+        offset = end;
+    }
+
+    Ok(chunks)
+}
+
+/// Chunks of a WAV file other than `fmt ` and `data`, such as `LIST` or `bext`
+///
+/// Unlike [`riff_chunks`], this tolerates a wrong RIFF size and a missing pad
+/// byte after the last chunk, and stops at the first truncated chunk, since
+/// provider WAVs are not always well-formed.
+fn extra_chunks(bytes: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Vec::new();
+    }
+    extra_chunks_from(bytes, 12)
+}
+
+/// Complete chunks other than `fmt ` and `data` from `offset` on
+fn extra_chunks_from(bytes: &[u8], mut offset: usize) -> Vec<([u8; 4], &[u8])> {
+    let mut chunks = Vec::new();
+    while let Some((id, size)) = chunk_header(bytes, offset) {
+        let body = offset + 8;
+        let Some(body_end) = body.checked_add(size).filter(|&end| end <= bytes.len()) else {
+            break;
+        };
+        if &id != b"fmt " && &id != b"data" {
+            chunks.push((id, &bytes[body..body_end]));
+        }
+        offset = body_end + size % 2;
+    }
+
+    chunks
+}
+
+/// Append chunks, padded to even length, to finished WAV bytes
+fn append_chunks(wav: &mut Vec<u8>, chunks: &[([u8; 4], &[u8])]) -> Result<()> {
+    for (id, body) in chunks {
+        wav.extend_from_slice(id);
+        wav.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        wav.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            wav.push(0);
+        }
+    }
+
+    let riff_size = u32::try_from(wav.len() - 8).map_err(|_| {
+        crate::error::Error::audio(
+            crate::error::AudioErrorKind::TooLarge,
+            "WAV output exceeds 4 GiB".to_string(),
+        )
+    })?;
+    wav[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Ok(())
+}
+
+/// ID and size of the chunk starting at `offset`, if its header is complete
+fn chunk_header(bytes: &[u8], offset: usize) -> Option<([u8; 4], usize)> {
+    let header = bytes.get(offset..offset.checked_add(8)?)?;
+    let id = [header[0], header[1], header[2], header[3]];
+    let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    Some((id, size))
+}
+
+/// Write `duration` of silence in the writer's spec, returning the number
+/// of frames written
+fn write_silence<W: Write + Seek>(
+    writer: &mut WavWriter<W>,
+    duration: Duration,
+    spec: WavSpec,
+) -> Result<usize> {
+    let frames = duration_frames(duration, spec);
+    write_silent_frames(writer, frames, spec)?;
+    Ok(frames)
+}
+
+/// Pad a segment of `frames` frames with silence up to `duration`,
+/// returning the number of frames of padding written
+///
+/// A longer segment is left as is, with a warning.
+fn pad_to_duration<W: Write + Seek>(
+    writer: &mut WavWriter<W>,
+    frames: usize,
+    duration: Duration,
+    idx: usize,
+    spec: WavSpec,
+    warnings: &mut Vec<Warning>,
+) -> Result<usize> {
+    let target = duration_frames(duration, spec);
+    if frames > target {
+        warnings.push(Warning::SegmentExceedsUniformDuration {
+            index: idx,
+            duration: Duration::from_secs_f64(frames as f64 / spec.sample_rate.max(1) as f64),
+            target: duration,
+        });
+        return Ok(0);
+    }
+    write_silent_frames(writer, target - frames, spec)?;
+    Ok(target - frames)
+}
+
+/// Most bytes of sample data a WAV file can hold
+///
+/// RIFF chunk sizes are 32-bit, and the size of the outermost chunk also
+/// covers the format header, up to 60 bytes, and a pad byte.
+pub const MAX_WAV_DATA_BYTES: u64 = u32::MAX as u64 - 64;
+
+/// Bytes of sample data a merge writes in `spec` for segments of the given
+/// frame counts and sample rates, with their pauses and uniform padding
+pub(crate) fn projected_data_bytes(
+    segments: impl Iterator<Item = (u64, u32)>,
+    spec: WavSpec,
+    pauses: &[Duration],
+    uniform_duration: Option<Duration>,
+) -> u64 {
+    let mut frames = 0;
+    for (idx, (segment_frames, sample_rate)) in segments.enumerate() {
+        let converted = if sample_rate == spec.sample_rate {
+            segment_frames
+        } else {
+            (segment_frames as f64 * spec.sample_rate as f64 / sample_rate.max(1) as f64).round()
+                as u64
+        };
+        frames += match uniform_duration {
+            Some(duration) => converted.max(duration_frames(duration, spec) as u64),
+            None => converted,
+        };
+        frames += pauses
+            .get(idx)
+            .map_or(0, |pause| duration_frames(*pause, spec) as u64);
+    }
+    frames * frame_bytes(spec)
+}
+
+/// Fail unless `data_bytes` of samples fit in a WAV file
+pub(crate) fn check_wav_size(data_bytes: u64) -> Result<()> {
+    if data_bytes <= MAX_WAV_DATA_BYTES {
+        return Ok(());
+    }
+    Err(crate::error::Error::audio(
+        crate::error::AudioErrorKind::TooLarge,
+        format!(
+            "merged audio would need {} bytes of samples, over the {} bytes a WAV file \
+         can hold; convert the text in parts, for example with convert_to_files",
+            data_bytes, MAX_WAV_DATA_BYTES
+        ),
+    ))
+}
+
+/// Size of one frame in `spec`, in bytes
+fn frame_bytes(spec: WavSpec) -> u64 {
+    spec.channels.max(1) as u64 * (spec.bits_per_sample as u64).div_ceil(8)
+}
+
+/// Number of whole frames closest to `duration`
+fn duration_frames(duration: Duration, spec: WavSpec) -> usize {
+    (duration.as_secs_f64() * spec.sample_rate as f64).round() as usize
+}
+
+fn write_silent_frames<W: Write + Seek>(
+    writer: &mut WavWriter<W>,
+    frames: usize,
+    spec: WavSpec,
+) -> Result<()> {
+    for _ in 0..frames * spec.channels as usize {
+        write_i16_as(writer, 0, spec)?;
+    }
+    Ok(())
+}
+
+fn spec_key(spec: &WavSpec) -> (u16, u32, u16, bool) {
+    (
+        spec.channels,
+        spec.sample_rate,
+        spec.bits_per_sample,
+        spec.sample_format == SampleFormat::Float,
+    )
+}
+
+/// Write a 16-bit sample scaled to the writer's sample format
+fn write_i16_as<W: Write + Seek>(
+    writer: &mut WavWriter<W>,
+    sample: i16,
+    spec: WavSpec,
+) -> Result<()> {
+    match (spec.sample_format, spec.bits_per_sample) {
+        (SampleFormat::Float, _) => writer.write_sample(sample as f32 / i16::MAX as f32)?,
+        (SampleFormat::Int, bits) if bits <= 16 => writer.write_sample(sample >> (16 - bits))?,
+        (SampleFormat::Int, bits) => writer.write_sample((sample as i32) << (bits - 16))?,
+    }
+    Ok(())
+}
+
+/// Convert interleaved samples between channel counts and sample rates
+///
+/// Channels are remixed by averaging down to mono and duplicating up, and
+/// the sample rate is changed with linear interpolation.
+pub(crate) fn convert_samples(samples: &[i16], from: WavSpec, to: WavSpec) -> Vec<i16> {
+    let from_channels = from.channels.max(1) as usize;
+    let to_channels = to.channels.max(1) as usize;
+
+    // Remix each frame to the target channel count
+    let frames: Vec<Vec<i16>> = samples
+        .chunks_exact(from_channels)
+        .map(|frame| {
+            if from_channels == to_channels {
+                frame.to_vec()
+            } else {
+                let mono = frame.iter().map(|&s| s as i32).sum::<i32>() / from_channels as i32;
+                vec![mono as i16; to_channels]
+            }
+        })
+        .collect();
+
+    if from.sample_rate == to.sample_rate || frames.is_empty() {
+        return frames.concat();
+    }
+
+    let ratio = from.sample_rate as f64 / to.sample_rate as f64;
+    let out_frames = (frames.len() as f64 / ratio).round() as usize;
+    let mut output = Vec::with_capacity(out_frames * to_channels);
+
+    for i in 0..out_frames {
+        let position = i as f64 * ratio;
+        let index = position.floor() as usize;
+        let fraction = position - index as f64;
+        let current = &frames[index.min(frames.len() - 1)];
+        let next = &frames[(index + 1).min(frames.len() - 1)];
+        for channel in 0..to_channels {
+            let a = current[channel] as f64;
+            let b = next[channel] as f64;
+            output.push((a + (b - a) * fraction).round() as i16);
+        }
+    }
+
+    output
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{read_samples, temp_path, wav_bytes, wav_with_extra_chunks};
 
     // Note: Actual audio tests require real WAV data
     // These are placeholder tests for structure
@@ -120,6 +1656,610 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_merge_majority_spec() {
+        let good = wav_bytes(&[100; 240], 24000, 1);
+        let bad = wav_bytes(&[100; 80], 8000, 1);
+        let segments = vec![bad, good.clone(), good.clone(), good.clone(), good];
+        let output = temp_path("majority_spec.wav");
+
+        let warnings = AudioMerger::merge_with_warnings(segments, &output)
+            .await
+            .unwrap();
+        let audio = std::fs::read(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        let reader = WavReader::new(Cursor::new(&audio)).unwrap();
+        assert_eq!(reader.spec().sample_rate, 24000);
+        assert_eq!(reader.len(), 240 * 5);
+        assert_eq!(
+            warnings,
+            vec![Warning::SegmentSpecConverted {
+                index: 0,
+                sample_rate: 8000,
+                channels: 1,
+                target_sample_rate: 24000,
+                target_channels: 1,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_identical_specs_unchanged() {
+        let segments = vec![
+            wav_bytes(&[1, 2, 3], 24000, 1),
+            wav_bytes(&[4, 5], 24000, 1),
+        ];
+        let output = temp_path("identical_specs.wav");
+
+        let warnings = AudioMerger::merge_with_warnings(segments, &output)
+            .await
+            .unwrap();
+        let audio = std::fs::read(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        assert!(warnings.is_empty());
+        assert_eq!(read_samples(&audio), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_majority_spec_tie_prefers_higher_rate() {
+        let spec = |sample_rate| WavSpec {
+            channels: 1,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let chosen = AudioMerger::majority_spec(&[spec(16000), spec(24000)]);
+        assert_eq!(chosen.sample_rate, 24000);
+    }
+
+    #[test]
+    fn test_convert_samples_channels() {
+        let mono = WavSpec {
+            channels: 1,
+            sample_rate: 24000,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let stereo = WavSpec {
+            channels: 2,
+            ..mono
+        };
+        assert_eq!(
+            convert_samples(&[10, 20], mono, stereo),
+            vec![10, 10, 20, 20]
+        );
+        assert_eq!(
+            convert_samples(&[10, 20, 30, 50], stereo, mono),
+            vec![15, 40]
+        );
+    }
+
+    #[test]
+    fn test_incremental_merger() {
+        let output = temp_path("incremental.wav");
+        let mut merger = IncrementalMerger::new(&output);
+        merger.append(&wav_bytes(&[1, 2], 24000, 1)).unwrap();
+        merger.append(&wav_bytes(&[3, 4], 24000, 2)).unwrap();
+        assert_eq!(merger.segment_count(), 2);
+        let warnings = merger.finalize().unwrap();
+
+        let audio = std::fs::read(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(read_samples(&audio), vec![1, 2, 3]);
+        assert!(matches!(
+            warnings[..],
+            [Warning::SegmentSpecConverted { index: 1, .. }]
+        ));
+    }
+
+    /// 8-bit mono WAV bytes, whose data chunk is odd-sized for odd lengths
+    fn wav_bytes_8bit(samples: &[i8]) -> Vec<u8> {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 8,
+            sample_format: SampleFormat::Int,
+        };
+        let mut cursor = Cursor::new(Vec::new());
+        let mut writer = WavWriter::new(&mut cursor, spec).unwrap();
+        for &sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        cursor.into_inner()
+    }
+
+    #[tokio::test]
+    async fn test_odd_data_chunk_padded() {
+        let segments = vec![wav_bytes_8bit(&[1, 2]), wav_bytes_8bit(&[3])];
+        // hound alone leaves the odd-sized data chunk unpadded
+        assert!(riff_chunks(&segments[1]).is_err());
+
+        let (audio, _) = AudioMerger::merge_to_vec(segments, &[]).await.unwrap();
+
+        let chunks = riff_chunks(&audio).unwrap();
+        let (id, data) = chunks.last().unwrap();
+        assert_eq!(id, b"data");
+        assert_eq!(data.len(), 3);
+        assert_eq!(audio.len() % 2, 0);
+        assert_eq!(audio.last(), Some(&0));
+        let mut reader = WavReader::new(Cursor::new(&audio)).unwrap();
+        let samples: Vec<i8> = reader.samples().map(|s| s.unwrap()).collect();
+        assert_eq!(samples, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_incremental_merger_pads_odd_data_chunk() {
+        let output = temp_path("incremental_odd.wav");
+        let mut merger = IncrementalMerger::new(&output);
+        merger.append(&wav_bytes_8bit(&[1, 2, 3])).unwrap();
+        merger.finalize().unwrap();
+
+        let audio = std::fs::read(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(riff_chunks(&audio).unwrap().last().unwrap().1.len(), 3);
+        assert_eq!(audio.last(), Some(&0));
+    }
+
+    #[test]
+    fn test_incremental_merger_dropped_deletes_output() {
+        let output = temp_path("incremental_dropped.wav");
+        let mut merger = IncrementalMerger::new(&output);
+        merger.append(&wav_bytes(&[1, 2, 3], 24000, 1)).unwrap();
+        assert!(Path::new(&output).exists());
+        drop(merger);
+        assert!(!Path::new(&output).exists());
+
+        // Nothing to delete before the first segment
+        drop(IncrementalMerger::new(&output));
+    }
+
+    #[cfg(feature = "mp3")]
+    #[test]
+    fn test_transcode_wav_to_mp3() {
+        // Three seconds of stereo, spanning several encoder blocks
+        let samples: Vec<i16> = (0..3 * 44100)
+            .flat_map(|i| {
+                let s =
+                    ((i as f32 * 440.0 * std::f32::consts::TAU / 44100.0).sin() * 8000.0) as i16;
+                [s, s / 2]
+            })
+            .collect();
+        let wav = wav_bytes(&samples, 44100, 2);
+        let input = temp_path("transcode.wav");
+        let output = temp_path("transcode.mp3");
+        let copy = temp_path("transcode_copy.wav");
+        std::fs::write(&input, &wav).unwrap();
+
+        AudioMerger::transcode(&input, &output, OutputFormat::Mp3 { bitrate_kbps: 128 }).unwrap();
+        AudioMerger::transcode(&input, &copy, OutputFormat::Wav).unwrap();
+        let mp3 = std::fs::read(&output).unwrap();
+        assert_eq!(std::fs::read(&copy).unwrap(), wav);
+        for path in [&input, &output, &copy] {
+            std::fs::remove_file(path).unwrap();
+        }
+
+        let mut decoder = minimp3::Decoder::new(Cursor::new(mp3));
+        let mut frames = 0;
+        loop {
+            match decoder.next_frame() {
+                Ok(frame) => {
+                    assert_eq!((frame.sample_rate, frame.channels), (44100, 2));
+                    frames += frame.data.len() / frame.channels;
+                }
+                Err(minimp3::Error::Eof) => break,
+                Err(e) => panic!("invalid MP3: {}", e),
+            }
+        }
+        // Encoder delay and frame padding add up to a few MP3 frames
+        assert!((frames as f64 / 44100.0 - 3.0).abs() < 0.15);
+
+        let missing = temp_path("transcode_missing.wav");
+        assert!(AudioMerger::transcode(&missing, &output, OutputFormat::Wav).is_err());
+        assert!(!Path::new(&output).exists());
+    }
+
+    #[test]
+    fn test_repair_truncated_files() {
+        let samples: Vec<i16> = (0..4800).map(|i| (i % 100) as i16).collect();
+        let fixture = wav_bytes(&samples, 24000, 2);
+        let data = fixture.windows(4).position(|w| w == b"data").unwrap() + 8;
+        let path = temp_path("repair.wav");
+        let metadata = format!("{}.json", path);
+        std::fs::write(&metadata, r#"{"duration_seconds": 0.1}"#).unwrap();
+
+        for (len, frames, partial) in [
+            (data, 0, 0),
+            (data + 4 * 1000, 1000, 0),
+            (data + 4 * 1000 + 3, 1000, 3),
+            (fixture.len() - 1, 2399, 3),
+        ] {
+            std::fs::write(&path, &fixture[..len]).unwrap();
+            let report = AudioMerger::repair(&path).unwrap();
+            assert_eq!(report.frames, frames);
+            assert_eq!(report.partial_frame_bytes, partial);
+            assert!(report.modified);
+            assert_eq!(
+                report.recovered,
+                Duration::from_secs_f64(frames as f64 / 24000.0)
+            );
+            assert_eq!(report.expected, Some(Duration::from_millis(100)));
+            assert_eq!(
+                report.missing(),
+                Some(Duration::from_millis(100) - report.recovered)
+            );
+
+            let repaired = std::fs::read(&path).unwrap();
+            riff_chunks(&repaired).unwrap();
+            assert_eq!(read_samples(&repaired), samples[..frames as usize * 2]);
+        }
+        std::fs::remove_file(&metadata).unwrap();
+
+        // Sizes left at zero by a writer that never finalized
+        let mut unfinalized = fixture.clone();
+        unfinalized[4..8].fill(0);
+        unfinalized[data - 4..data].fill(0);
+        std::fs::write(&path, &unfinalized).unwrap();
+        let report = AudioMerger::repair(&path).unwrap();
+        assert_eq!((report.frames, report.expected), (2400, None));
+        assert_eq!(std::fs::read(&path).unwrap(), fixture);
+
+        // An intact file is left alone
+        let report = AudioMerger::repair(&path).unwrap();
+        assert!(!report.modified);
+
+        // Chunks after an intact data chunk survive a wrong RIFF size, and
+        // the last one gets its missing pad byte
+        let mut source = wav_with_extra_chunks();
+        source[4..8].fill(0xff);
+        std::fs::write(&path, &source).unwrap();
+        AudioMerger::repair(&path).unwrap();
+        let repaired = std::fs::read(&path).unwrap();
+        let ids: Vec<[u8; 4]> = riff_chunks(&repaired)
+            .unwrap()
+            .iter()
+            .map(|c| c.0)
+            .collect();
+        assert_eq!(ids, [*b"fmt ", *b"cust", *b"data", *b"bext", *b"LIST"]);
+        assert_eq!(read_samples(&repaired), vec![1, 2, 3]);
+
+        for len in [0, 11, 20, data - 1] {
+            std::fs::write(&path, &fixture[..len]).unwrap();
+            assert!(matches!(
+                AudioMerger::repair(&path),
+                Err(crate::error::Error::Audio { .. })
+            ));
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_single_preserves_extra_chunks() {
+        let source = wav_with_extra_chunks();
+
+        let wav = AudioMerger::single_to_vec(&source, true).unwrap();
+
+        let ids: Vec<[u8; 4]> = riff_chunks(&wav).unwrap().iter().map(|c| c.0).collect();
+        assert_eq!(ids, [*b"fmt ", *b"data", *b"cust", *b"bext", *b"LIST"]);
+        let mut expected_tail = b"cust\x04\0\0\0\x01\x02\x03\x04".to_vec();
+        expected_tail.extend_from_slice(b"bext\x03\0\0\0\x05\x06\x07\0");
+        expected_tail.extend_from_slice(b"LIST\x05\0\0\0INFOx\0");
+        assert!(wav.ends_with(&expected_tail));
+        assert_eq!(read_samples(&wav), vec![1, 2, 3]);
+
+        let plain = AudioMerger::single_to_vec(&source, false).unwrap();
+        assert_eq!(riff_chunks(&plain).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_extra_chunks_stops_at_truncated_chunk() {
+        // Pad the LIST chunk, then add a chunk claiming more bytes than remain,
+        // leaving the RIFF size stale
+        let mut source = wav_with_extra_chunks();
+        source.push(0);
+        source.extend_from_slice(b"junk\x10\0\0\0abc");
+
+        let chunks = extra_chunks(&source);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[2], (*b"LIST", &b"INFOx"[..]));
+    }
+
+    #[test]
+    fn test_merge_pcm_matches_wav() {
+        let segments = vec![
+            wav_bytes(&[100, 200], 24000, 1),
+            wav_bytes(&[300, 400, 500, 600], 48000, 1),
+        ];
+        let pauses = [Duration::from_millis(1)];
+
+        let (wav, pcm, warnings) =
+            AudioMerger::merge_pcm_with_threads(segments, &pauses, None, 1).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(pcm.samples(), read_samples(&wav));
+        assert_eq!(pcm.sample_rate(), 48000);
+        assert_eq!(pcm.channels(), 1);
+        assert_eq!(pcm.frames().count(), 4 + 48 + 4);
+    }
+
+    #[test]
+    fn test_compare() {
+        let audio = wav_bytes(&[0, 100, -100, 500], 24000, 1);
+        let same = AudioMerger::compare(&audio, &audio).unwrap();
+        assert_eq!(
+            same,
+            CompareResult {
+                sample_count_diff: 0,
+                max_amplitude_diff: 0,
+                identical: true,
+            }
+        );
+
+        let modified = wav_bytes(&[0, 103, -100, 500, 7], 24000, 1);
+        let result = AudioMerger::compare(&audio, &modified).unwrap();
+        assert_eq!(result.sample_count_diff, 1);
+        assert_eq!(result.max_amplitude_diff, 3);
+        assert!(!result.identical);
+    }
+
+    #[test]
+    fn test_compare_different_specs() {
+        let a = wav_bytes(&[1, 2], 24000, 1);
+        let b = wav_bytes(&[1, 2], 16000, 1);
+        assert!(matches!(
+            AudioMerger::compare(&a, &b),
+            Err(crate::error::Error::Audio { .. })
+        ));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_merge_matches_sequential() {
+        // Mix of native, resampled, remixed and 8-bit segments
+        let segments: Vec<Vec<u8>> = (0..12i16)
+            .map(|i| {
+                let samples: Vec<i16> = (0..240).map(|n| (n * 37 + i * 101) % 2000).collect();
+                match i % 4 {
+                    0 | 1 => wav_bytes(&samples, 24000, 1),
+                    2 => wav_bytes(&samples, 16000, 1),
+                    _ => wav_bytes(&samples, 24000, 2),
+                }
+            })
+            .chain([wav_bytes_8bit(&[1, -2, 3])])
+            .collect();
+        let pauses: Vec<Duration> = (0..segments.len() as u64)
+            .map(Duration::from_millis)
+            .collect();
+
+        let sequential =
+            AudioMerger::merge_vec_with_threads(segments.clone(), &pauses, None, 1).unwrap();
+        for threads in [0, 4] {
+            let parallel =
+                AudioMerger::merge_vec_with_threads(segments.clone(), &pauses, None, threads)
+                    .unwrap();
+            assert_eq!(parallel, sequential);
+        }
+        assert_eq!(sequential.1.len(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_merge_to_vec() {
+        let segments = vec![wav_bytes(&[1, 2], 24000, 1), wav_bytes(&[3, 4], 24000, 1)];
+
+        let (audio, warnings) = AudioMerger::merge_to_vec(segments, &[]).await.unwrap();
+
+        assert!(warnings.is_empty());
+        assert_eq!(read_samples(&audio), vec![1, 2, 3, 4]);
+        assert!(AudioMerger::merge_to_vec(vec![], &[]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_variants_fail_like_file_variants() {
+        let output = temp_path("in_memory_errors.wav");
+        for segments in [vec![], vec![vec![]], vec![b"not a wav".to_vec()]] {
+            let to_file = AudioMerger::merge(segments.clone(), &output).await;
+            let to_vec = AudioMerger::merge_to_vec(segments, &[]).await;
+            assert_eq!(
+                to_vec.unwrap_err().to_string(),
+                to_file.unwrap_err().to_string()
+            );
+        }
+        let to_file = AudioMerger::save_single(&[], &output).await;
+        let to_vec = AudioMerger::single_to_vec(&[], false);
+        assert_eq!(
+            to_vec.unwrap_err().to_string(),
+            to_file.unwrap_err().to_string()
+        );
+        assert!(!Path::new(&output).exists());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_merge_leaves_runtime_responsive() {
+        // A minute of audio, with every other segment resampled
+        let segments: Vec<Vec<u8>> = (0..12)
+            .map(|i| wav_bytes(&vec![i as i16; 120_000], [24000, 16000][i % 2], 1))
+            .collect();
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move { sender.send(()).unwrap() });
+
+        let merge = AudioMerger::merge_to_vec(segments, &[]);
+        tokio::pin!(merge);
+        // The merge is handed off instead of running on the only worker
+        // thread, which stays free for the other task
+        assert!(futures::poll!(merge.as_mut()).is_pending());
+        receiver.await.unwrap();
+        let (audio, _) = merge.await.unwrap();
+        assert!(probe_bytes(&audio).unwrap().duration > Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_abandoned_merge_removes_output() {
+        let output = temp_path("abandoned_merge.wav");
+        let segments: Vec<Vec<u8>> = (0..12)
+            .map(|i| wav_bytes(&vec![i as i16; 120_000], 16000, 1))
+            .collect();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        // Given up on after the first poll, which only starts the merge
+        let result = runtime.block_on(async {
+            tokio::time::timeout(Duration::ZERO, AudioMerger::merge(segments, &output)).await
+        });
+        assert!(result.is_err());
+        // Dropping the runtime waits for the merge still running
+        drop(runtime);
+        assert!(!Path::new(&output).exists());
+    }
+
+    #[tokio::test]
+    async fn test_merge_with_pauses() {
+        let segments = vec![
+            wav_bytes(&[1, 1], 1000, 1),
+            wav_bytes(&[2, 2], 1000, 1),
+            wav_bytes(&[3], 1000, 1),
+        ];
+        let pauses = [Duration::from_millis(3), Duration::ZERO];
+        let output = temp_path("pauses.wav");
+
+        AudioMerger::merge_with_pauses(segments, &pauses, &output)
+            .await
+            .unwrap();
+        let audio = std::fs::read(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(read_samples(&audio), vec![1, 1, 0, 0, 0, 2, 2, 3]);
+    }
+
+    #[test]
+    fn test_uniform_duration_pads_each_segment() {
+        // Stereo at 1000 Hz, so 4 ms is 4 frames of 2 samples
+        let segments = vec![
+            wav_bytes(&[1, 2, 3, 4], 1000, 2),
+            wav_bytes(&[5, 6], 1000, 2),
+            wav_bytes(&[7; 12], 1000, 2),
+        ];
+        let pauses = [Duration::from_millis(1)];
+        let uniform = Some(Duration::from_millis(4));
+
+        let (wav, pcm, warnings) =
+            AudioMerger::merge_pcm_with_threads(segments.clone(), &pauses, uniform, 1).unwrap();
+        let samples = read_samples(&wav);
+
+        let mut expected = vec![1, 2, 3, 4, 0, 0, 0, 0];
+        expected.extend([0, 0]);
+        expected.extend([5, 6, 0, 0, 0, 0, 0, 0]);
+        expected.extend([7; 12]);
+        assert_eq!(samples, expected);
+        assert_eq!(pcm.samples(), expected.as_slice());
+        assert_eq!(
+            warnings,
+            vec![Warning::SegmentExceedsUniformDuration {
+                index: 2,
+                duration: Duration::from_millis(6),
+                target: Duration::from_millis(4),
+            }]
+        );
+
+        let output = temp_path("incremental_uniform.wav");
+        let mut merger =
+            IncrementalMerger::new(&output).with_uniform_segment_duration(Duration::from_millis(4));
+        for (idx, segment) in segments.iter().enumerate() {
+            merger.append(segment).unwrap();
+            merger
+                .append_silence(pauses.get(idx).copied().unwrap_or_default())
+                .unwrap();
+        }
+        merger.finalize().unwrap();
+        let incremental = read_samples(&std::fs::read(&output).unwrap());
+        std::fs::remove_file(&output).unwrap();
+        assert_eq!(incremental, expected);
+    }
+
+    #[test]
+    fn test_projected_size_over_wav_limit() {
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        // 800 segments of 30 s fit in 4.23 GB; half a second after each
+        // pushes it to 4.30 GB
+        let segments = || (0..800).map(|_| (30 * 44100, 44100));
+        let fits = projected_data_bytes(segments(), spec, &[], None);
+        assert_eq!(fits, 800 * 30 * 44100 * 4);
+        assert!(check_wav_size(fits).is_ok());
+
+        let pauses = vec![Duration::from_millis(500); 800];
+        let over = projected_data_bytes(segments(), spec, &pauses, None);
+        assert_eq!(over, 800 * 30_500 * 441 / 10 * 4);
+        let error = check_wav_size(over).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Audio processing error: merged audio would need 4304160000 bytes of samples, \
+                 over the {} bytes a WAV file can hold; convert the text in parts, for \
+                 example with convert_to_files",
+                MAX_WAV_DATA_BYTES
+            )
+        );
+
+        // Segments at other rates are counted after conversion, and padded
+        // to the uniform duration
+        let bytes = projected_data_bytes(
+            [(22050, 22050), (100, 44100)].into_iter(),
+            spec,
+            &[],
+            Some(Duration::from_millis(100)),
+        );
+        assert_eq!(bytes, (44100 + 4410) * 4);
+    }
+
+    #[test]
+    fn test_merge_rejects_oversized_output_before_writing() {
+        // Headers claiming 2^31 frames each, with no samples behind them
+        let mut segment = wav_bytes(&[], 24000, 1);
+        segment[40..44].copy_from_slice(&(1u32 << 31).to_le_bytes());
+        let output = temp_path("oversized.wav");
+
+        let result = AudioMerger::merge_file_with_threads(
+            vec![segment.clone(), segment],
+            &[],
+            None,
+            &output,
+            1,
+        );
+        assert!(
+            matches!(result, Err(crate::error::Error::Audio { message: m, .. }) if m.contains("WAV file can hold"))
+        );
+        assert!(!Path::new(&output).exists());
+    }
+
+    #[test]
+    fn test_incremental_merger_silence() {
+        let output = temp_path("incremental_silence.wav");
+        let mut merger = IncrementalMerger::new(&output);
+        assert!(merger.append_silence(Duration::from_millis(1)).is_err());
+        merger.append(&wav_bytes(&[5, 6], 1000, 2)).unwrap();
+        merger.append_silence(Duration::from_millis(2)).unwrap();
+        merger.finalize().unwrap();
+
+        let audio = std::fs::read(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(read_samples(&audio), vec![5, 6, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_incremental_merger_empty() {
+        let merger = IncrementalMerger::new(temp_path("incremental_empty.wav"));
+        assert!(merger.finalize().is_err());
+    }
+
     #[test]
     fn test_empty_single() {
         let result = std::thread::spawn(|| {
@@ -132,4 +2272,79 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_probe_bytes_across_specs() {
+        let formats = [
+            (8, SampleFormat::Int),
+            (16, SampleFormat::Int),
+            (24, SampleFormat::Int),
+            (32, SampleFormat::Int),
+            (32, SampleFormat::Float),
+        ];
+        for sample_rate in [8000, 22050, 24000, 44100, 48000] {
+            for channels in [1, 2, 6] {
+                for (bits_per_sample, sample_format) in formats {
+                    for frames in [0, 1, 7, 1000] {
+                        let spec = WavSpec {
+                            channels,
+                            sample_rate,
+                            bits_per_sample,
+                            sample_format,
+                        };
+                        let mut cursor = Cursor::new(Vec::new());
+                        let mut writer = WavWriter::new(&mut cursor, spec).unwrap();
+                        for _ in 0..frames * channels as u32 {
+                            match (sample_format, bits_per_sample) {
+                                (SampleFormat::Float, _) => writer.write_sample(0.5_f32),
+                                (SampleFormat::Int, 8) => writer.write_sample(1_i8),
+                                (SampleFormat::Int, 16) => writer.write_sample(1_i16),
+                                (SampleFormat::Int, _) => writer.write_sample(1_i32),
+                            }
+                            .unwrap();
+                        }
+                        writer.finalize().unwrap();
+
+                        let info = probe_bytes(&cursor.into_inner()).unwrap();
+                        assert_eq!(info.spec, spec);
+                        assert_eq!(info.frames, frames);
+                        assert_eq!(
+                            info.duration,
+                            Duration::from_secs_f64(frames as f64 / sample_rate as f64)
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_probe_bytes_skips_extra_chunks() {
+        let info = probe_bytes(&wav_with_extra_chunks()).unwrap();
+        assert_eq!(info.spec.sample_rate, 24000);
+        assert_eq!(info.frames, 3);
+    }
+
+    #[test]
+    fn test_probe_bytes_errors() {
+        let wav = wav_bytes(&[1, 2, 3], 24000, 1);
+        let reason = |bytes: &[u8]| match probe_bytes(bytes) {
+            Err(crate::error::Error::Audio { message, .. }) => message,
+            other => panic!("expected an audio error, got {:?}", other),
+        };
+
+        assert_eq!(reason(&[]), "Invalid WAV header: input is empty");
+        assert_eq!(
+            reason(&wav[..20]),
+            "Invalid WAV header: truncated before the data chunk"
+        );
+        assert!(reason(b"not a wav file at all").contains("RIFF"));
+
+        let mut no_wave = wav.clone();
+        no_wave[8..12].copy_from_slice(b"AVI ");
+        assert!(reason(&no_wave).contains("WAVE"));
+
+        // Header-only probing ignores a data chunk cut short
+        assert_eq!(probe_bytes(&wav[..wav.len() - 2]).unwrap().frames, 3);
+    }
 }
@@ -0,0 +1,87 @@
+use crate::ai_splitter::AiSplitter;
+use crate::client::{Client, TtsConfig};
+use crate::error::{Error, Result};
+use crate::retry::RetryClass;
+use futures::future::BoxFuture;
+
+/// Text-to-speech backend used by [`Text2Audio`](crate::Text2Audio)
+///
+/// The default backend is the Zhipu AI [`Client`]. Implement this trait to
+/// route synthesis through a different service or a local TTS server.
+/// Implementations must return WAV audio bytes.
+pub trait TtsBackend: Send + Sync {
+    /// Synthesize `text` into WAV audio bytes
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        config: &'a TtsConfig,
+    ) -> BoxFuture<'a, Result<Vec<u8>>>;
+
+    /// How the retry policy should treat `error`, returned by this backend
+    ///
+    /// Defaults to [`Error::retry_class`]. Override it when the backend
+    /// knows more about its failures, e.g. that a server error is
+    /// permanent or how long a rate limit lasts.
+    fn classify(&self, error: &Error) -> RetryClass {
+        error.retry_class()
+    }
+}
+
+/// AI splitting backend used by [`Text2Audio`](crate::Text2Audio)
+///
+/// The default backend is an [`AiSplitter`] built from the converter's
+/// model and segment length. Implement this trait to split with a different
+/// model or service. It is only called for text longer than the maximum
+/// segment length, and should return segments within that length.
+pub trait SplitBackend: Send + Sync {
+    /// Split `text` into segments in reading order
+    fn split<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<Vec<String>>>;
+
+    /// How the retry policy should treat `error`, returned by this backend
+    ///
+    /// Defaults to [`Error::retry_class`].
+    fn classify(&self, error: &Error) -> RetryClass {
+        error.retry_class()
+    }
+}
+
+/// Chat completion backend used by [`AiSplitter`]
+///
+/// The default backend is the Zhipu AI [`Client`]. Implement this trait to
+/// send the splitter's prompts to a different model or service while
+/// keeping its chunking and response parsing.
+pub trait ChatBackend: Send + Sync {
+    /// Complete `prompt`, returning the content of every choice
+    fn chat<'a>(&'a self, prompt: &'a str) -> BoxFuture<'a, Result<Vec<String>>>;
+}
+
+impl ChatBackend for Client {
+    fn chat<'a>(&'a self, prompt: &'a str) -> BoxFuture<'a, Result<Vec<String>>> {
+        Box::pin(self.chat_completion_all(prompt))
+    }
+}
+
+impl SplitBackend for AiSplitter {
+    fn split<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<Vec<String>>> {
+        Box::pin(AiSplitter::split(self, text))
+    }
+}
+
+impl TtsBackend for Client {
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        config: &'a TtsConfig,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        Box::pin(self.text_to_audio(text, config))
+    }
+
+    /// Retries an MP3 response to a WAV request only once, as it is often
+    /// a transient fault upstream
+    fn classify(&self, error: &Error) -> RetryClass {
+        match error {
+            Error::UnexpectedMp3 { .. } => RetryClass::RetryableOnce,
+            error => error.retry_class(),
+        }
+    }
+}
@@ -0,0 +1,304 @@
+//! Diagnostic bundles written when a conversion fails
+
+use crate::error::{Error, Result};
+use crate::plan::ConversionMode;
+use crate::project::ProjectSettings;
+use crate::report::{ConversionReport, SegmentSource};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Most recent warnings kept in a bundle
+pub const MAX_BUNDLE_WARNINGS: usize = 20;
+
+/// Largest audio attachment written next to a bundle, in bytes
+///
+/// Longer audio is truncated; the header is what usually matters.
+pub const MAX_ATTACHMENT_BYTES: usize = 64 * 1024;
+
+/// Bundles written by this process, numbered so that names never collide
+static BUNDLES_WRITTEN: AtomicU64 = AtomicU64::new(0);
+
+/// Contents of a failure bundle's JSON file
+///
+/// Written by converters configured with
+/// [`with_failure_bundle`](crate::Text2Audio::with_failure_bundle) when a
+/// conversion fails. The API key is never included, and segment text only
+/// with [`with_failure_bundle_text`](crate::Text2Audio::with_failure_bundle_text);
+/// without it, warnings that quote the input keep only their counts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailureBundle {
+    /// Version of this crate
+    pub crate_version: String,
+    /// Time the bundle was written, in milliseconds since the Unix epoch
+    pub created_at_ms: u64,
+    /// Effective converter settings
+    pub config: BundleConfig,
+    /// The error that ended the conversion
+    pub error: BundleError,
+    /// Processing mode in use when the conversion failed
+    pub mode: ConversionMode,
+    /// Status of every segment known when the conversion failed
+    pub segments: Vec<BundleSegment>,
+    /// Segment whose synthesis or audio failed, if one did
+    pub failed_segment: Option<FailedSegment>,
+    /// The last [`MAX_BUNDLE_WARNINGS`] warnings, oldest first, with text
+    /// from the input left out unless text is included
+    pub warnings: Vec<String>,
+    /// File names of attachments next to the JSON file
+    pub attachments: Vec<String>,
+}
+
+/// Converter settings recorded in a failure bundle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleConfig {
+    /// Always `"<redacted>"`, or empty if no key was set
+    pub api_key: String,
+    /// Settings that determine the audio
+    pub settings: ProjectSettings,
+    /// Maximum concurrent requests, `None` if sequential
+    pub parallel: Option<usize>,
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    /// Initial delay between retries
+    pub retry_delay: Duration,
+    /// Output format, as its debug name, or `inferred` if it follows the
+    /// output path
+    pub output_format: String,
+    /// Whether a custom TTS backend was in use
+    pub custom_backend: bool,
+}
+
+/// Error recorded in a failure bundle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleError {
+    /// Stable code from [`Error::code`]
+    pub code: String,
+    /// Error message
+    pub message: String,
+    /// Messages of the underlying causes, outermost first
+    pub chain: Vec<String>,
+}
+
+/// Status of one segment in a failure bundle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentStatus {
+    /// Synthesized and merged, or yielded by a stream
+    Done,
+    /// Synthesis or its audio failed
+    Failed,
+    /// Not reached before the failure
+    Pending,
+}
+
+/// One segment in a failure bundle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleSegment {
+    /// Position of the segment
+    pub index: usize,
+    /// What happened to the segment
+    pub status: SegmentStatus,
+    /// Characters in the segment's text
+    pub char_count: usize,
+    /// Requests the segment took, if it was synthesized
+    pub attempts: Option<u32>,
+    /// Offset of the segment in the merged audio, if it was merged
+    pub start: Option<Duration>,
+    /// Duration of the segment's audio, if it was merged
+    pub duration: Option<Duration>,
+}
+
+/// The segment that failed, in a failure bundle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailedSegment {
+    /// Position of the segment
+    pub index: usize,
+    /// Characters in the segment's effective text
+    pub char_count: usize,
+    /// Effective text sent to the TTS API, if text is included
+    pub text: Option<String>,
+    /// Attachment holding the audio that could not be read, if any
+    pub audio_attachment: Option<String>,
+}
+
+tokio::task_local! {
+    static RECORDER: Arc<Mutex<Recorder>>;
+}
+
+/// What a conversion got to before failing, beyond its report
+#[derive(Default)]
+pub(crate) struct Recorder {
+    segments: Vec<String>,
+    failed: Option<usize>,
+    invalid_audio: Option<Vec<u8>>,
+    streamed: usize,
+    written: bool,
+}
+
+impl Recorder {
+    /// Whether a conversion inside the captured work wrote a bundle already
+    pub(crate) fn bundle_written(&self) -> bool {
+        self.written
+    }
+}
+
+fn record(f: impl FnOnce(&mut Recorder)) {
+    let _ = RECORDER.try_with(|recorder| f(&mut recorder.lock().unwrap()));
+}
+
+/// Record the segments about to be synthesized
+pub(crate) fn planned<S: AsRef<str>>(segments: &[S]) {
+    record(|recorder| {
+        recorder.segments = segments.iter().map(|s| s.as_ref().to_string()).collect();
+    });
+}
+
+/// Record that segment `index` ran out of attempts
+pub(crate) fn segment_failed(index: usize) {
+    record(|recorder| {
+        recorder.failed.get_or_insert(index);
+    });
+}
+
+/// Record audio of segment `index` that could not be read
+pub(crate) fn invalid_audio(index: usize, audio: &[u8]) {
+    record(|recorder| {
+        recorder.failed.get_or_insert(index);
+        if recorder.invalid_audio.is_none() {
+            let len = audio.len().min(MAX_ATTACHMENT_BYTES);
+            recorder.invalid_audio = Some(audio[..len].to_vec());
+        }
+    });
+}
+
+/// Record that the segments before `index` were streamed to the caller
+pub(crate) fn streamed(index: usize) {
+    record(|recorder| recorder.streamed = index);
+}
+
+/// Record that a bundle was written for the conversion's error
+pub(crate) fn mark_written() {
+    record(|recorder| recorder.written = true);
+}
+
+/// Run `work`, returning what it recorded alongside its result
+pub(crate) async fn capture<T>(work: impl Future<Output = Result<T>>) -> (Result<T>, Recorder) {
+    let recorder = Arc::new(Mutex::new(Recorder::default()));
+    let result = RECORDER.scope(recorder.clone(), work).await;
+    let recorded = std::mem::take(&mut *recorder.lock().unwrap());
+    (result, recorded)
+}
+
+impl FailureBundle {
+    /// Assemble a bundle from a failed conversion
+    pub(crate) fn new(
+        config: BundleConfig,
+        error: &Error,
+        report: &ConversionReport,
+        recorder: &Recorder,
+        include_text: bool,
+    ) -> Self {
+        let mut chain = Vec::new();
+        let mut source = std::error::Error::source(error);
+        while let Some(cause) = source {
+            chain.push(cause.to_string());
+            source = cause.source();
+        }
+
+        let known = recorder.segments.len().max(report.segments.len());
+        let segments = (0..known)
+            .map(|index| {
+                let merged = report.segments.get(index);
+                let text = recorder
+                    .segments
+                    .get(index)
+                    .or(merged.and_then(|segment| segment.text.as_ref()));
+                let status = if merged.is_some() || index < recorder.streamed {
+                    SegmentStatus::Done
+                } else if recorder.failed == Some(index) {
+                    SegmentStatus::Failed
+                } else {
+                    SegmentStatus::Pending
+                };
+                BundleSegment {
+                    index,
+                    status,
+                    char_count: text.map_or(0, |text| text.chars().count()),
+                    attempts: merged.and_then(|segment| match segment.source {
+                        SegmentSource::Synthesized { attempts } => Some(attempts),
+                        SegmentSource::Checkpoint => None,
+                    }),
+                    start: merged.map(|segment| segment.start),
+                    duration: merged.map(|segment| segment.duration),
+                }
+            })
+            .collect();
+
+        let failed_segment = recorder.failed.map(|index| {
+            let text = recorder.segments.get(index).cloned().unwrap_or_default();
+            FailedSegment {
+                index,
+                char_count: text.chars().count(),
+                text: include_text.then_some(text),
+                audio_attachment: None,
+            }
+        });
+
+        let skip = report.warnings.len().saturating_sub(MAX_BUNDLE_WARNINGS);
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            config,
+            error: BundleError {
+                code: error.code().to_string(),
+                message: error.to_string(),
+                chain,
+            },
+            mode: report.mode,
+            segments,
+            failed_segment,
+            warnings: report.warnings[skip..]
+                .iter()
+                .map(|warning| {
+                    if include_text {
+                        warning.to_string()
+                    } else {
+                        warning.without_text()
+                    }
+                })
+                .collect(),
+            attachments: Vec::new(),
+        }
+    }
+
+    /// Write the bundle and its attachments to `dir`, returning the JSON path
+    pub(crate) fn write(mut self, dir: &Path, recorder: &Recorder) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let stem = format!(
+            "text2audio-failure-{}-{}-{}",
+            self.created_at_ms,
+            std::process::id(),
+            BUNDLES_WRITTEN.fetch_add(1, Ordering::Relaxed)
+        );
+
+        if let (Some(audio), Some(failed)) = (&recorder.invalid_audio, &mut self.failed_segment) {
+            let name = format!("{}.segment-{}.wav", stem, failed.index);
+            std::fs::write(dir.join(&name), audio)?;
+            failed.audio_attachment = Some(name.clone());
+            self.attachments.push(name);
+        }
+
+        let path = dir.join(format!("{}.json", stem));
+        let json = serde_json::to_string_pretty(&self)
+            .map_err(|e| Error::Config(format!("Failed to serialize failure bundle: {}", e)))?;
+        std::fs::write(&path, json)?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,123 @@
+//! Speech rate calibration learned from completed conversions
+
+use crate::config::Voice;
+use crate::error::{Error, Result};
+use crate::temp::write_atomic;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::Duration;
+
+/// Seconds per character assumed for an uncalibrated voice at speed 1.0
+///
+/// Fits Chinese speech; Latin-script text is read faster per character, so
+/// its estimates run long until calibrated.
+pub const DEFAULT_SECONDS_PER_CHAR: f64 = 0.25;
+
+/// Weight of a new observation in the smoothed rate
+pub const CALIBRATION_SMOOTHING: f64 = 0.3;
+
+/// Observed speech rate of one voice and speed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationEntry {
+    /// Exponentially smoothed seconds of audio per spoken character
+    pub seconds_per_char: f64,
+    /// Number of conversions observed
+    pub observations: u32,
+}
+
+/// Speech rates per voice and speed, as stored in a calibration file
+///
+/// Written by converters configured with
+/// [`with_calibration_file`](crate::Text2Audio::with_calibration_file) and
+/// used by [`estimate`](crate::Text2Audio::estimate).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Calibration {
+    entries: BTreeMap<String, CalibrationEntry>,
+}
+
+impl Calibration {
+    /// Read a calibration file, or start empty if it does not exist
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] if the file is not a calibration file, and
+    /// error if file I/O fails.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| Error::Config(format!("Invalid calibration file: {}", e))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write the calibration file, replacing it atomically
+    pub(crate) fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Config(format!("Failed to serialize calibration: {}", e)))?;
+        write_atomic(path, path.with_extension("t2a.tmp"), json)?;
+        Ok(())
+    }
+
+    /// Calibrated rate of `voice` at `speed`, if any was observed
+    pub fn get(&self, voice: Voice, speed: f32) -> Option<CalibrationEntry> {
+        self.entries.get(&key(voice, speed)).copied()
+    }
+
+    /// All calibrated rates, keyed by voice and speed such as `Tongtong@1.00`
+    pub fn entries(&self) -> &BTreeMap<String, CalibrationEntry> {
+        &self.entries
+    }
+
+    /// Fold in a conversion of `chars` spoken characters lasting `duration`
+    pub(crate) fn observe(&mut self, voice: Voice, speed: f32, chars: usize, duration: Duration) {
+        if chars == 0 || duration.is_zero() {
+            return;
+        }
+        let observed = duration.as_secs_f64() / chars as f64;
+        self.entries
+            .entry(key(voice, speed))
+            .and_modify(|entry| {
+                entry.seconds_per_char +=
+                    CALIBRATION_SMOOTHING * (observed - entry.seconds_per_char);
+                entry.observations += 1;
+            })
+            .or_insert(CalibrationEntry {
+                seconds_per_char: observed,
+                observations: 1,
+            });
+    }
+}
+
+fn key(voice: Voice, speed: f32) -> String {
+    format!("{:?}@{:.2}", voice, speed)
+}
+
+/// Characters that take time to speak, which excludes whitespace
+pub(crate) fn spoken_chars(text: &str) -> usize {
+    text.chars().filter(|c| !c.is_whitespace()).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observe_smooths_toward_new_rate() {
+        let mut calibration = Calibration::default();
+        assert_eq!(calibration.get(Voice::Tongtong, 1.0), None);
+
+        calibration.observe(Voice::Tongtong, 1.0, 10, Duration::from_secs(2));
+        calibration.observe(Voice::Tongtong, 1.0, 10, Duration::from_secs(4));
+        calibration.observe(Voice::Tongtong, 1.0, 0, Duration::from_secs(4));
+
+        let entry = calibration.get(Voice::Tongtong, 1.0).unwrap();
+        assert!((entry.seconds_per_char - 0.26).abs() < 1e-9);
+        assert_eq!(entry.observations, 2);
+        assert_eq!(calibration.get(Voice::Tongtong, 1.2), None);
+        assert_eq!(calibration.get(Voice::Jam, 1.0), None);
+        assert!(calibration.entries().contains_key("Tongtong@1.00"));
+    }
+}
@@ -1,6 +1,11 @@
+use crate::digest::text_sha256;
 use crate::error::{Error, Result};
-use serde::Serialize;
-use zai_rs::client::HttpClient;
+use crate::stats::{ClientStats, Endpoint, StatsRecorder};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use std::time::Instant;
+use zai_rs::client::{HttpClient, ZaiError};
 use zai_rs::model::chat_base_response::ChatCompletionResponse;
 use zai_rs::model::text_to_audio::{
     data::TextToAudioRequest, model::GlmTts, request::TtsAudioFormat, Voice,
@@ -10,8 +15,16 @@ use zai_rs::model::{
     ChatCompletion, GLM4_5_air, GLM4_5_flash, TextMessage, ThinkingType, GLM4_5, GLM4_6, GLM4_7,
 };
 
+/// System prompt of chat requests for AI splitting
+pub(crate) const SPLIT_SYSTEM_PROMPT: &str =
+    "作为全球顶级的语言学家，你取得了全球所有语种博士学位，
+            并且每种语言都拥有100年的使用经验。根据提供的文本，按照语义学进行分段。";
+
+/// Maximum number of characters accepted by a single TTS request
+pub const TTS_MAX_CHARS: usize = 1024;
+
 /// AI model for text splitting
-#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
 pub enum Model {
     /// GLM-4.7 - Latest flagship model
     GLM4_7,
@@ -36,25 +49,213 @@ impl Model {
             Model::GLM4_5Air => "glm-4.5-air",
         }
     }
+
+    /// Optional API features the model supports, from [`MODEL_CAPABILITIES`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Model;
+    ///
+    /// assert!(Model::GLM4_7.capabilities().coding_plan);
+    /// assert!(!Model::GLM4_5Flash.capabilities().coding_plan);
+    /// ```
+    pub fn capabilities(&self) -> ModelCapabilities {
+        MODEL_CAPABILITIES
+            .iter()
+            .find(|(model, _)| model == self)
+            .map(|(_, capabilities)| *capabilities)
+            .unwrap_or_default()
+    }
 }
 
+/// Optional API features of a [`Model`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ModelCapabilities {
+    /// Thinking mode, set with [`Client::with_thinking`]
+    pub thinking: bool,
+    /// The coding plan endpoint, set with [`Client::with_coding_plan`]
+    pub coding_plan: bool,
+}
+
+/// Features each model supports; a model missing here supports none
+pub const MODEL_CAPABILITIES: &[(Model, ModelCapabilities)] = &[
+    (
+        Model::GLM4_7,
+        ModelCapabilities {
+            thinking: true,
+            coding_plan: true,
+        },
+    ),
+    (
+        Model::GLM4_6,
+        ModelCapabilities {
+            thinking: true,
+            coding_plan: true,
+        },
+    ),
+    (
+        Model::GLM4_5,
+        ModelCapabilities {
+            thinking: true,
+            coding_plan: true,
+        },
+    ),
+    (
+        Model::GLM4_5Flash,
+        ModelCapabilities {
+            thinking: true,
+            coding_plan: false,
+        },
+    ),
+    (
+        Model::GLM4_5Air,
+        ModelCapabilities {
+            thinking: true,
+            coding_plan: true,
+        },
+    ),
+];
+
+/// Default speech speed
+pub(crate) const DEFAULT_SPEED: f32 = 1.0;
+
+/// Default speech volume
+pub(crate) const DEFAULT_VOLUME: f32 = 1.0;
+
+/// Accepted speech speed range
+pub(crate) const SPEED_RANGE: RangeInclusive<f32> = 0.5..=2.0;
+
+/// Accepted speech volume range
+pub(crate) const VOLUME_RANGE: RangeInclusive<f32> = 0.0..=10.0;
+
 /// TTS configuration
+///
+/// Construct with [`TtsConfig::builder`] or [`TtsConfig::default`].
+///
+/// # Examples
+///
+/// ```
+/// use text2audio::{TtsConfig, Voice};
+///
+/// # fn main() -> text2audio::Result<()> {
+/// let config = TtsConfig::builder()
+///     .voice(Voice::Jam)
+///     .speed(1.2)
+///     .volume(2.0)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct TtsConfig {
     pub voice: Voice,
     pub speed: f32,
     pub volume: f32,
+    /// Key identifying one logical request, identical across its retries
+    ///
+    /// Lets a deduplicating server avoid charging twice for a retried request
+    /// that already succeeded. Only custom [`TtsBackend`](crate::TtsBackend)s can use it: the
+    /// built-in [`Client`] drops the key, because the Zhipu TTS request has
+    /// no field to carry it.
+    pub idempotency_key: Option<String>,
 }
 
 impl Default for TtsConfig {
     fn default() -> Self {
         Self {
             voice: Voice::Tongtong,
-            speed: 1.0,
-            volume: 1.0,
+            speed: DEFAULT_SPEED,
+            volume: DEFAULT_VOLUME,
+            idempotency_key: None,
         }
     }
 }
 
+impl TtsConfig {
+    /// Create a builder starting from the default configuration
+    pub fn builder() -> TtsConfigBuilder {
+        TtsConfigBuilder {
+            config: TtsConfig::default(),
+        }
+    }
+}
+
+/// Builder for [`TtsConfig`] that validates values on [`build`](Self::build)
+#[derive(Debug, Clone)]
+pub struct TtsConfigBuilder {
+    config: TtsConfig,
+}
+
+impl TtsConfigBuilder {
+    /// Set the voice
+    pub fn voice(mut self, voice: crate::Voice) -> Self {
+        self.config.voice = voice.as_tts_voice();
+        self
+    }
+
+    /// Set the speech speed, between 0.5 and 2.0
+    pub fn speed(mut self, speed: f32) -> Self {
+        self.config.speed = speed;
+        self
+    }
+
+    /// Set the speech volume, between 0.0 and 10.0
+    pub fn volume(mut self, volume: f32) -> Self {
+        self.config.volume = volume;
+        self
+    }
+
+    /// Set the idempotency key sent with the request
+    pub fn idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.config.idempotency_key = Some(key.into());
+        self
+    }
+
+    /// Validate the configuration
+    ///
+    /// Returns [`Error::Config`] if the speed or volume is NaN, infinite or
+    /// outside its accepted range.
+    pub fn build(self) -> Result<TtsConfig> {
+        check_range("speed", self.config.speed, SPEED_RANGE)?;
+        check_range("volume", self.config.volume, VOLUME_RANGE)?;
+        Ok(self.config)
+    }
+}
+
+fn check_range(name: &str, value: f32, range: RangeInclusive<f32>) -> Result<()> {
+    // NaN fails `contains`, so it is rejected along with out-of-range values
+    if range.contains(&value) {
+        Ok(())
+    } else {
+        Err(Error::Config(format!(
+            "{} must be between {} and {}, got {}",
+            name,
+            range.start(),
+            range.end(),
+            value
+        )))
+    }
+}
+
+/// Idempotency key of segment `index` of a plan, holding `text` and sent
+/// with the settings of `config`
+///
+/// Derived from the segment rather than drawn per request, so every request
+/// for it carries the same key, whether it is a retry, a sequential retry
+/// after a parallel failure or a resumed project run in another process.
+pub(crate) fn segment_idempotency_key(index: usize, text: &str, config: &TtsConfig) -> String {
+    let segment = format!(
+        "{}\n{:?}\n{}\n{}\n{}",
+        index, config.voice, config.speed, config.volume, text
+    );
+    format!("text2audio-{}", &text_sha256(&segment)[..32])
+}
+
+/// Transform applied to raw TTS response bodies
+pub(crate) type ResponseHandler = Arc<dyn Fn(&[u8]) -> Result<Vec<u8>> + Send + Sync>;
+
 /// Zhipu AI API client wrapper
 ///
 /// Provides a unified interface for chat completion and text-to-speech APIs.
@@ -64,6 +265,9 @@ pub struct Client {
     model: Model,
     thinking: bool,
     coding_plan: bool,
+    greedy: bool,
+    response_handler: Option<ResponseHandler>,
+    stats: StatsRecorder,
 }
 
 impl Client {
@@ -74,6 +278,9 @@ impl Client {
             model: Model::default(),
             thinking: false,
             coding_plan: false,
+            greedy: false,
+            response_handler: None,
+            stats: StatsRecorder::default(),
         }
     }
 
@@ -84,60 +291,103 @@ impl Client {
     }
 
     /// Enable or disable thinking mode for chat completion
+    ///
+    /// Ignored for models whose [capabilities](Model::capabilities) lack it.
     pub fn with_thinking(mut self, enable: bool) -> Self {
         self.thinking = enable;
         self
     }
 
     /// Enable or disable coding plan endpoint
+    ///
+    /// Ignored for models whose [capabilities](Model::capabilities) lack it.
     pub fn with_coding_plan(mut self, enable: bool) -> Self {
         self.coding_plan = enable;
         self
     }
 
-    /// Perform chat completion
+    /// Enable or disable greedy decoding of chat completions
+    ///
+    /// When enabled, sampling is turned off so the model picks its most
+    /// likely answer, and the same prompt gets the same completion as far as
+    /// the API allows.
+    pub fn with_greedy_decoding(mut self, enable: bool) -> Self {
+        self.greedy = enable;
+        self
+    }
+
+    /// Transform each raw TTS response body before it is checked
+    ///
+    /// The handler receives the bytes exactly as returned by the API, for
+    /// example to unwrap a gateway's envelope or to transcode through a
+    /// proxy. Its output must be valid audio in the requested format (WAV);
+    /// an error it returns fails the request. By default the body is used
+    /// as is.
+    pub fn with_response_handler(
+        mut self,
+        handler: impl Fn(&[u8]) -> Result<Vec<u8>> + Send + Sync + 'static,
+    ) -> Self {
+        self.response_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Request statistics accumulated by this client
+    ///
+    /// The client itself never retries, so retry counts stay at zero.
+    pub fn stats(&self) -> ClientStats {
+        self.stats.snapshot()
+    }
+
+    /// Clear the accumulated request statistics
+    pub fn reset_stats(&self) {
+        self.stats.reset();
+    }
+
+    /// Perform chat completion, returning the content of the first choice
     pub async fn chat_completion(&self, prompt: &str) -> Result<String> {
+        let choices = self.chat_completion_all(prompt).await?;
+        Ok(choices.into_iter().next().unwrap_or_default())
+    }
+
+    /// Perform chat completion, returning the content of every choice
+    ///
+    /// The API returns one choice unless configured to offer alternatives;
+    /// choices without text content are skipped. Counts as a single request.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::AiApi`] if the request fails or no choice has text
+    /// content.
+    pub async fn chat_completion_all(&self, prompt: &str) -> Result<Vec<String>> {
+        let started = Instant::now();
+        let result = self.request_chat_completion(prompt).await;
+        self.stats
+            .record(Endpoint::Chat, started.elapsed(), result.is_ok(), false);
+        result
+    }
+
+    async fn request_chat_completion(&self, prompt: &str) -> Result<Vec<String>> {
         let response: ChatCompletionResponse = match self.model {
-            Model::GLM4_7 => {
-                if self.thinking {
-                    self.call_chat_with_thinking(GLM4_7 {}, prompt).await?
-                } else {
-                    self.call_chat(GLM4_7 {}, prompt).await?
-                }
-            }
-            Model::GLM4_6 => {
-                if self.thinking {
-                    self.call_chat_with_thinking(GLM4_6 {}, prompt).await?
-                } else {
-                    self.call_chat(GLM4_6 {}, prompt).await?
-                }
-            }
-            Model::GLM4_5 => {
-                if self.thinking {
-                    self.call_chat_with_thinking(GLM4_5 {}, prompt).await?
-                } else {
-                    self.call_chat(GLM4_5 {}, prompt).await?
-                }
-            }
+            Model::GLM4_7 => self.call_chat(GLM4_7 {}, prompt).await?,
+            Model::GLM4_6 => self.call_chat(GLM4_6 {}, prompt).await?,
+            Model::GLM4_5 => self.call_chat(GLM4_5 {}, prompt).await?,
             Model::GLM4_5Flash => self.call_chat(GLM4_5_flash {}, prompt).await?,
             Model::GLM4_5Air => self.call_chat(GLM4_5_air {}, prompt).await?,
         };
 
-        let content = response
-            .choices
-            .and_then(|choices: Vec<_>| choices.into_iter().next())
-            .and_then(|choice| choice.message.content)
-            .and_then(|content| match content {
-                serde_json::Value::String(s) => Some(s),
-                _ => None,
-            })
-            .ok_or_else(|| Error::AiApi("Invalid AI response format".to_string()))?;
-
-        Ok(content)
+        choice_contents(response)
     }
 
     /// Perform text-to-audio conversion
     pub async fn text_to_audio(&self, text: &str, config: &TtsConfig) -> Result<Vec<u8>> {
+        let started = Instant::now();
+        let result = self.request_text_to_audio(text, config).await;
+        self.stats
+            .record(Endpoint::Tts, started.elapsed(), result.is_ok(), false);
+        result
+    }
+
+    async fn request_text_to_audio(&self, text: &str, config: &TtsConfig) -> Result<Vec<u8>> {
         let request = TextToAudioRequest::new(GlmTts {}, self.api_key.clone())
             .with_input(text)
             .with_voice(config.voice.clone())
@@ -145,77 +395,161 @@ impl Client {
             .with_volume(config.volume)
             .with_response_format(TtsAudioFormat::Wav);
 
-        let response = request
-            .post()
-            .await
-            .map_err(|e| Error::TtsApi(format!("TTS request failed: {}", e)))?;
-
+        let response = request.post().await.map_err(|e| match e {
+            ZaiError::NetworkError(e) => Error::Http(format!("TTS request failed: {}", e)),
+            e if is_rate_limit(&e) => Error::RateLimited(format!("TTS request failed: {}", e)),
+            e => Error::TtsApi(format!("TTS request failed: {}", e)),
+        })?;
+
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
         let audio_bytes = response
             .bytes()
             .await
-            .map_err(|e| Error::TtsApi(format!("Failed to read audio data: {}", e)))?;
+            .map_err(|e| Error::Http(format!("Failed to read audio data: {}", e)))?;
+
+        self.handle_response(&audio_bytes, content_type.as_deref())
+    }
+
+    /// Run the response handler on a TTS response body and check the result
+    ///
+    /// MP3 in place of the requested WAV is decoded with the `mp3-decode`
+    /// feature, and otherwise fails with [`Error::UnexpectedMp3`] naming
+    /// `content_type`.
+    fn handle_response(&self, body: &[u8], content_type: Option<&str>) -> Result<Vec<u8>> {
+        let audio_bytes = match &self.response_handler {
+            Some(handler) => handler(body)?,
+            None => body.to_vec(),
+        };
 
         if audio_bytes.is_empty() {
             return Err(Error::TtsApi("Received empty audio data".to_string()));
         }
+        if is_mp3(&audio_bytes) {
+            return mp3_to_wav(&audio_bytes, content_type);
+        }
 
-        Ok(audio_bytes.to_vec())
+        Ok(audio_bytes)
     }
 
+    /// Send a chat request, with the features enabled on this client that
+    /// the model supports
     async fn call_chat<M>(&self, model: M, prompt: &str) -> Result<ChatCompletionResponse>
     where
-        M: ModelName + Chat + Serialize + Send + Sync + 'static,
+        M: ModelName + Chat + ThinkEnable + Serialize + Send + Sync + 'static,
         (M, TextMessage): Bounded,
     {
-        let system_message = TextMessage::system(
-            "作为全球顶级的语言学家，你取得了全球所有语种博士学位，
-            并且每种语言都拥有100年的使用经验。根据提供的文本，按照语义学进行分段。",
-        );
+        let capabilities = self.model.capabilities();
+        let system_message = TextMessage::system(SPLIT_SYSTEM_PROMPT);
         let mut request = ChatCompletion::new(model, system_message, self.api_key.clone())
             .add_messages(TextMessage::user(prompt));
 
-        if self.coding_plan {
+        if self.coding_plan && capabilities.coding_plan {
             request = request.with_coding_plan();
         }
 
-        request
-            .send()
-            .await
-            .map_err(|e| Error::AiApi(format!("Chat completion failed: {}", e)))
+        if self.thinking && capabilities.thinking {
+            request = request.with_thinking(ThinkingType::Enabled);
+        }
+
+        if self.greedy {
+            request = request.with_do_sample(false);
+        }
+
+        request.send().await.map_err(chat_error)
     }
+}
 
-    async fn call_chat_with_thinking<M>(
-        &self,
-        model: M,
-        prompt: &str,
-    ) -> Result<ChatCompletionResponse>
-    where
-        M: ModelName + Chat + ThinkEnable + Serialize + Send + Sync + 'static,
-        (M, TextMessage): Bounded,
+/// Whether `audio` starts with an ID3 tag or MP3 frame sync rather than RIFF
+fn is_mp3(audio: &[u8]) -> bool {
+    audio.starts_with(b"ID3") || matches!(audio, [0xFF, second, ..] if second & 0xE0 == 0xE0)
+}
+
+/// WAV audio of an MP3 response, decoded if the `mp3-decode` feature is
+/// enabled
+fn mp3_to_wav(audio: &[u8], content_type: Option<&str>) -> Result<Vec<u8>> {
+    let error = |decode_error: Option<String>| Error::UnexpectedMp3 {
+        content_type: content_type.map(str::to_string),
+        decode_error,
+    };
+    #[cfg(feature = "mp3-decode")]
+    return crate::encode::decode_mp3(audio).map_err(|e| error(Some(e.to_string())));
+    #[cfg(not(feature = "mp3-decode"))]
     {
-        let system_message = TextMessage::system(
-            "作为全球顶级的语言学家，你取得了全球所有语种博士学位，
-            并且每种语言都拥有100年的使用经验。根据提供的文本，按照语义学进行分段。",
-        );
-        let mut request = ChatCompletion::new(model, system_message, self.api_key.clone())
-            .add_messages(TextMessage::user(prompt));
+        let _ = audio;
+        Err(error(None))
+    }
+}
 
-        if self.coding_plan {
-            request = request.with_coding_plan();
-        }
+/// Whether the API refused a request for its rate limit or quota
+fn is_rate_limit(error: &ZaiError) -> bool {
+    error.is_rate_limit() || matches!(error, ZaiError::HttpError { status: 429, .. })
+}
 
-        request = request.with_thinking(ThinkingType::Enabled);
+fn chat_error(error: ZaiError) -> Error {
+    let message = format!("Chat completion failed: {}", error);
+    if is_rate_limit(&error) {
+        Error::RateLimited(message)
+    } else {
+        Error::AiApi(message)
+    }
+}
 
-        request
-            .send()
-            .await
-            .map_err(|e| Error::AiApi(format!("Chat completion failed: {}", e)))
+/// Text content of every choice in a chat response, in order
+fn choice_contents(response: ChatCompletionResponse) -> Result<Vec<String>> {
+    let contents: Vec<String> = response
+        .choices
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|choice| match choice.message.content {
+            Some(serde_json::Value::String(s)) => Some(s),
+            _ => None,
+        })
+        .collect();
+
+    if contents.is_empty() {
+        return Err(Error::AiApi("Invalid AI response format".to_string()));
     }
+    Ok(contents)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::mp3_bytes;
+    #[cfg(not(feature = "mp3-decode"))]
+    use crate::{RetryClass, TtsBackend};
+
+    fn chat_response(choices: serde_json::Value) -> ChatCompletionResponse {
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "request_id": "1",
+            "choices": choices,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_choice_contents() {
+        let response = chat_response(serde_json::json!([
+            {"index": 0, "message": {"content": "first"}},
+            {"index": 1, "message": {"content": null}},
+            {"index": 2, "message": {"content": "second"}},
+        ]));
+        assert_eq!(choice_contents(response).unwrap(), vec!["first", "second"]);
+
+        let response = chat_response(serde_json::json!([
+            {"index": 0, "message": {"content": [1, 2]}},
+        ]));
+        assert!(matches!(choice_contents(response), Err(Error::AiApi(_))));
+        assert!(matches!(
+            choice_contents(chat_response(serde_json::json!([]))),
+            Err(Error::AiApi(_))
+        ));
+    }
 
     #[test]
     fn test_model_as_str() {
@@ -226,6 +560,88 @@ mod tests {
         assert_eq!(Model::GLM4_5Air.as_str(), "glm-4.5-air");
     }
 
+    #[test]
+    fn test_response_handler() {
+        let client = Client::new("test_key");
+        assert_eq!(client.handle_response(b"RIFF", None).unwrap(), b"RIFF");
+        assert!(matches!(
+            client.handle_response(b"", None),
+            Err(Error::TtsApi(_))
+        ));
+
+        // Unwrap a gateway envelope of a 4-byte tag around the audio
+        let client = client.with_response_handler(|body| match body.strip_prefix(b"ENV:") {
+            Some(audio) => Ok(audio.to_vec()),
+            None => Err(Error::TtsApi("missing envelope".to_string())),
+        });
+        assert_eq!(client.handle_response(b"ENV:RIFF", None).unwrap(), b"RIFF");
+        assert!(
+            matches!(client.handle_response(b"RIFF", None), Err(Error::TtsApi(m)) if m == "missing envelope")
+        );
+        assert!(matches!(
+            client.handle_response(b"ENV:", None),
+            Err(Error::TtsApi(_))
+        ));
+    }
+
+    #[test]
+    fn test_mp3_response() {
+        let client = Client::new("test_key");
+        for mp3 in [mp3_bytes(8, true), mp3_bytes(8, false)] {
+            let result = client.handle_response(&mp3, Some("audio/mpeg"));
+            #[cfg(feature = "mp3-decode")]
+            {
+                let wav = result.unwrap();
+                let reader = hound::WavReader::new(wav.as_slice()).unwrap();
+                assert_eq!(
+                    (reader.spec().sample_rate, reader.spec().channels),
+                    (44100, 1)
+                );
+                assert_eq!(reader.duration(), 8 * 1152);
+            }
+            #[cfg(not(feature = "mp3-decode"))]
+            {
+                let error = result.unwrap_err();
+                assert!(
+                    matches!(&error, Error::UnexpectedMp3 { content_type: Some(t), .. }
+                    if t == "audio/mpeg")
+                );
+                assert_eq!(
+                    error.to_string(),
+                    "TTS API error: provider returned mp3 but wav was requested \
+                     (content-type: audio/mpeg)"
+                );
+                assert_eq!(client.classify(&error), RetryClass::RetryableOnce);
+            }
+        }
+        // A frame sync is two bytes; a lone 0xFF is not MP3
+        assert!(!is_mp3(b"\xFFRIFF"));
+        assert!(!is_mp3(b"RIFF\xFF\xFB"));
+    }
+
+    #[test]
+    fn test_model_capabilities() {
+        let models = [
+            (Model::GLM4_7, true, true),
+            (Model::GLM4_6, true, true),
+            (Model::GLM4_5, true, true),
+            (Model::GLM4_5Flash, true, false),
+            (Model::GLM4_5Air, true, true),
+        ];
+        assert_eq!(MODEL_CAPABILITIES.len(), models.len());
+        for (model, thinking, coding_plan) in models {
+            assert_eq!(
+                model.capabilities(),
+                ModelCapabilities {
+                    thinking,
+                    coding_plan
+                },
+                "{}",
+                model.as_str()
+            );
+        }
+    }
+
     #[test]
     fn test_model_default() {
         assert_eq!(Model::default(), Model::GLM4_5Flash);
@@ -237,6 +653,47 @@ mod tests {
         assert!(matches!(config.voice, Voice::Tongtong));
         assert_eq!(config.speed, 1.0);
         assert_eq!(config.volume, 1.0);
+        assert!(config.idempotency_key.is_none());
+    }
+
+    #[test]
+    fn test_tts_config_builder() {
+        let config = TtsConfig::builder()
+            .voice(crate::Voice::Jam)
+            .speed(1.2)
+            .volume(2.0)
+            .idempotency_key("key")
+            .build()
+            .unwrap();
+        assert!(matches!(config.voice, Voice::Jam));
+        assert_eq!(config.speed, 1.2);
+        assert_eq!(config.volume, 2.0);
+        assert_eq!(config.idempotency_key.as_deref(), Some("key"));
+    }
+
+    #[test]
+    fn test_tts_config_builder_rejects_nonsense() {
+        for speed in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, -1.0, 0.0, 2.5] {
+            let result = TtsConfig::builder().speed(speed).build();
+            assert!(matches!(result, Err(Error::Config(_))), "speed {}", speed);
+        }
+        for volume in [f32::NAN, f32::INFINITY, -0.5, 10.5] {
+            let result = TtsConfig::builder().volume(volume).build();
+            assert!(matches!(result, Err(Error::Config(_))), "volume {}", volume);
+        }
+    }
+
+    #[test]
+    fn test_idempotency_keys_follow_the_segment() {
+        let config = TtsConfig::default();
+        let key = segment_idempotency_key(0, "你好。", &config);
+        assert!(key.starts_with("text2audio-"));
+        assert_eq!(key, segment_idempotency_key(0, "你好。", &config));
+
+        let faster = TtsConfig::builder().speed(1.5).build().unwrap();
+        assert_ne!(key, segment_idempotency_key(1, "你好。", &config));
+        assert_ne!(key, segment_idempotency_key(0, "再见。", &config));
+        assert_ne!(key, segment_idempotency_key(0, "你好。", &faster));
     }
 
     #[test]
@@ -1,7 +1,10 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
 /// Voice selection for TTS
 ///
 /// Maps directly to zai-rs Voice enum.
-#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Voice {
     #[default]
     Tongtong,
@@ -46,6 +49,129 @@ impl std::fmt::Display for Voice {
     }
 }
 
+/// Named preset of voice, pacing and segmentation settings
+///
+/// Applied with [`Text2Audio::with_profile`](crate::Text2Audio::with_profile).
+/// Settings configured after the profile override its values.
+///
+/// | Profile | Voice | Speed | Volume | Max segment | Newline pauses |
+/// |---|---|---|---|---|---|
+/// | `Audiobook` | Tongtong | 1.0 | 1.0 | 800 | 400 ms / 900 ms |
+/// | `Podcast` | Jam | 1.1 | 1.0 | 500 | 250 ms / 600 ms |
+/// | `Announcement` | Xiaochen | 0.9 | 1.5 | 300 | 500 ms / 1200 ms |
+/// | `FastReview` | Tongtong | 1.5 | 1.0 | 1000 | none |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadingProfile {
+    /// Steady narration in long segments, with paragraph pauses
+    Audiobook,
+    /// Slightly brisk conversational delivery
+    Podcast,
+    /// Slow, loud and short segments for clear public announcements
+    Announcement,
+    /// Fast reading without pauses for skimming drafts
+    FastReview,
+}
+
+impl ReadingProfile {
+    /// Voice used by the profile
+    pub fn voice(&self) -> Voice {
+        match self {
+            ReadingProfile::Audiobook | ReadingProfile::FastReview => Voice::Tongtong,
+            ReadingProfile::Podcast => Voice::Jam,
+            ReadingProfile::Announcement => Voice::Xiaochen,
+        }
+    }
+
+    /// Speech speed used by the profile
+    pub fn speed(&self) -> f32 {
+        match self {
+            ReadingProfile::Audiobook => 1.0,
+            ReadingProfile::Podcast => 1.1,
+            ReadingProfile::Announcement => 0.9,
+            ReadingProfile::FastReview => 1.5,
+        }
+    }
+
+    /// Speech volume used by the profile
+    pub fn volume(&self) -> f32 {
+        match self {
+            ReadingProfile::Announcement => 1.5,
+            _ => 1.0,
+        }
+    }
+
+    /// Maximum segment length used by the profile
+    pub fn max_segment_length(&self) -> usize {
+        match self {
+            ReadingProfile::Audiobook => 800,
+            ReadingProfile::Podcast => 500,
+            ReadingProfile::Announcement => 300,
+            ReadingProfile::FastReview => 1000,
+        }
+    }
+
+    /// Silence inserted for a single newline and for a blank line, if any
+    pub fn newline_pauses(&self) -> Option<(Duration, Duration)> {
+        let pauses =
+            |short, long| Some((Duration::from_millis(short), Duration::from_millis(long)));
+        match self {
+            ReadingProfile::Audiobook => pauses(400, 900),
+            ReadingProfile::Podcast => pauses(250, 600),
+            ReadingProfile::Announcement => pauses(500, 1200),
+            ReadingProfile::FastReview => None,
+        }
+    }
+}
+
+/// Settings of a named section of a document
+///
+/// Registered with
+/// [`Text2Audio::with_section_profiles`](crate::Text2Audio::with_section_profiles)
+/// and switched on by `<!-- section: name -->` markers in the text. Unset
+/// settings keep the converter's own.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SectionProfile {
+    /// Voice of the section's segments
+    pub voice: Option<Voice>,
+    /// Speech speed of the section's segments
+    pub speed: Option<f32>,
+    /// Speech volume of the section's segments
+    pub volume: Option<f32>,
+    /// Silence after each of the section's segments, on top of other pauses
+    pub gap: Option<Duration>,
+}
+
+impl SectionProfile {
+    /// Create a profile that keeps every converter setting
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the voice of the section
+    pub fn with_voice(mut self, voice: Voice) -> Self {
+        self.voice = Some(voice);
+        self
+    }
+
+    /// Set the speech speed of the section
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = Some(speed);
+        self
+    }
+
+    /// Set the speech volume of the section
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = Some(volume);
+        self
+    }
+
+    /// Set the silence after each segment of the section
+    pub fn with_gap(mut self, gap: Duration) -> Self {
+        self.gap = Some(gap);
+        self
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -93,4 +219,44 @@ mod tests {
     fn test_voice_display() {
         assert_eq!(format!("{}", Voice::Jam), "Jam");
     }
+
+    #[test]
+    fn test_reading_profile_values() {
+        let audiobook = ReadingProfile::Audiobook;
+        assert_eq!(audiobook.voice(), Voice::Tongtong);
+        assert_eq!(audiobook.speed(), 1.0);
+        assert_eq!(audiobook.volume(), 1.0);
+        assert_eq!(audiobook.max_segment_length(), 800);
+        assert_eq!(
+            audiobook.newline_pauses(),
+            Some((Duration::from_millis(400), Duration::from_millis(900)))
+        );
+
+        let podcast = ReadingProfile::Podcast;
+        assert_eq!(podcast.voice(), Voice::Jam);
+        assert_eq!(podcast.speed(), 1.1);
+        assert_eq!(podcast.volume(), 1.0);
+        assert_eq!(podcast.max_segment_length(), 500);
+        assert_eq!(
+            podcast.newline_pauses(),
+            Some((Duration::from_millis(250), Duration::from_millis(600)))
+        );
+
+        let announcement = ReadingProfile::Announcement;
+        assert_eq!(announcement.voice(), Voice::Xiaochen);
+        assert_eq!(announcement.speed(), 0.9);
+        assert_eq!(announcement.volume(), 1.5);
+        assert_eq!(announcement.max_segment_length(), 300);
+        assert_eq!(
+            announcement.newline_pauses(),
+            Some((Duration::from_millis(500), Duration::from_millis(1200)))
+        );
+
+        let fast_review = ReadingProfile::FastReview;
+        assert_eq!(fast_review.voice(), Voice::Tongtong);
+        assert_eq!(fast_review.speed(), 1.5);
+        assert_eq!(fast_review.volume(), 1.0);
+        assert_eq!(fast_review.max_segment_length(), 1000);
+        assert_eq!(fast_review.newline_pauses(), None);
+    }
 }
@@ -0,0 +1,147 @@
+//! Checksums of written output files
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+
+/// Checksums of a file written by a conversion
+///
+/// Listed in [`ConversionReport::outputs`](crate::ConversionReport::outputs)
+/// so downstream systems can verify the artifact without hashing it again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OutputDigest {
+    /// Path the file was written to
+    pub path: String,
+    /// Size of the file in bytes
+    pub bytes: u64,
+    /// SHA-256 of the file as lowercase hex
+    pub sha256: String,
+    /// BLAKE3 of the file as lowercase hex, with the `blake3` feature
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blake3: Option<String>,
+}
+
+/// SHA-256 of a `sha256sum`-style listing of `outputs`, as lowercase hex
+///
+/// Each output contributes a line of its SHA-256, two spaces and its path,
+/// so the hash covers the contents and names of every file in order.
+pub(crate) fn manifest_sha256(outputs: &[OutputDigest]) -> String {
+    let mut hasher = Sha256::new();
+    for output in outputs {
+        hasher.update(format!("{}  {}\n", output.sha256, output.path));
+    }
+    hex(&hasher.finalize())
+}
+
+/// Writer hashing everything written through it
+pub(crate) struct HashingWriter<W> {
+    inner: W,
+    bytes: u64,
+    sha256: Sha256,
+    #[cfg(feature = "blake3")]
+    blake3: blake3::Hasher,
+}
+
+impl<W: Write> HashingWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self {
+            inner,
+            bytes: 0,
+            sha256: Sha256::new(),
+            #[cfg(feature = "blake3")]
+            blake3: blake3::Hasher::new(),
+        }
+    }
+
+    /// Flush the inner writer and return the digest of what was written
+    pub(crate) fn finish(mut self, path: &str) -> io::Result<OutputDigest> {
+        self.inner.flush()?;
+        #[cfg(feature = "blake3")]
+        let blake3 = Some(self.blake3.finalize().to_hex().to_string());
+        #[cfg(not(feature = "blake3"))]
+        let blake3 = None;
+        Ok(OutputDigest {
+            path: path.to_string(),
+            bytes: self.bytes,
+            sha256: hex(&self.sha256.finalize()),
+            blake3,
+        })
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written as u64;
+        self.sha256.update(&buf[..written]);
+        #[cfg(feature = "blake3")]
+        self.blake3.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Write `data` to `path`, hashing it on the way
+pub(crate) fn write_hashed(path: &str, data: &[u8]) -> io::Result<OutputDigest> {
+    let mut writer = HashingWriter::new(File::create(path)?);
+    writer.write_all(data)?;
+    writer.finish(path)
+}
+
+/// Hash the file at `path` as it is on disk
+///
+/// Used for files written in place, whose headers are patched after the
+/// data, so the bytes as first written are not the final ones.
+pub(crate) fn digest_file(path: &str) -> io::Result<OutputDigest> {
+    let mut writer = HashingWriter::new(io::sink());
+    io::copy(&mut BufReader::new(File::open(path)?), &mut writer)?;
+    writer.finish(path)
+}
+
+/// SHA-256 of `text` as lowercase hex
+pub(crate) fn text_sha256(text: &str) -> String {
+    hex(&Sha256::digest(text.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        write!(hex, "{:02x}", byte).expect("writing to a String cannot fail");
+    }
+    hex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_path;
+
+    #[test]
+    fn test_written_and_read_digests_match() {
+        let path = &temp_path("digest.bin");
+        let written = write_hashed(path, b"abc").unwrap();
+        assert_eq!(
+            written.sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(written.bytes, 3);
+        assert_eq!(digest_file(path).unwrap(), written);
+        #[cfg(feature = "blake3")]
+        assert_eq!(
+            written.blake3.as_deref(),
+            Some("6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85")
+        );
+        std::fs::remove_file(path).unwrap();
+
+        let listing = format!("{}  {}\n", written.sha256, path);
+        assert_eq!(
+            manifest_sha256(&[written]),
+            hex(&Sha256::digest(listing.as_bytes()))
+        );
+    }
+}
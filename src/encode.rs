@@ -0,0 +1,416 @@
+//! Encoding of merged audio into output formats
+
+use crate::digest::{write_hashed, OutputDigest};
+use crate::error::{AudioErrorKind, Error, Result};
+use crate::report::Warning;
+use base64::Engine;
+use hound::WavReader;
+use std::borrow::Cow;
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+
+/// Decode MP3 into 16-bit WAV with the stream's sample rate and channels
+///
+/// # Errors
+///
+/// Returns [`Error::Audio`] if the MP3 is invalid, holds no audio, or
+/// changes format between frames.
+#[cfg(feature = "mp3-decode")]
+pub(crate) fn decode_mp3(mp3: &[u8]) -> Result<Vec<u8>> {
+    let mut decoder = minimp3::Decoder::new(Cursor::new(mp3));
+    let mut spec = None;
+    let mut samples = Vec::new();
+    loop {
+        let frame = match decoder.next_frame() {
+            Ok(frame) => frame,
+            Err(minimp3::Error::Eof) => break,
+            Err(minimp3::Error::SkippedData) => continue,
+            Err(e) => {
+                return Err(Error::audio(
+                    AudioErrorKind::Codec,
+                    format!("Failed to decode MP3: {}", e),
+                ))
+            }
+        };
+        let frame_spec = hound::WavSpec {
+            channels: frame.channels as u16,
+            sample_rate: frame.sample_rate as u32,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        if *spec.get_or_insert(frame_spec) != frame_spec {
+            return Err(Error::audio(
+                AudioErrorKind::Codec,
+                "MP3 changes format between frames",
+            ));
+        }
+        samples.extend(frame.data);
+    }
+    let spec =
+        spec.ok_or_else(|| Error::audio(AudioErrorKind::Empty, "MP3 holds no audio frames"))?;
+
+    let mut wav = Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut wav, spec)?;
+    for sample in samples {
+        writer.write_sample(sample)?;
+    }
+    writer.finalize()?;
+    Ok(wav.into_inner())
+}
+
+/// Bitrate of MP3 output chosen from a `.mp3` extension, in kbps
+pub const DEFAULT_MP3_BITRATE_KBPS: u32 = 128;
+
+/// Format of converted audio
+///
+/// Chosen per output with
+/// [`Text2Audio::convert_formats`](crate::Text2Audio::convert_formats), for
+/// all conversions with
+/// [`Text2Audio::with_output_format`](crate::Text2Audio::with_output_format),
+/// or otherwise from the extension of the output path.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OutputFormat {
+    /// WAV exactly as merged from the synthesized segments
+    #[default]
+    Wav,
+    /// Constant-bitrate MP3
+    ///
+    /// Requires the `mp3` feature. The bitrate is rounded down to the
+    /// nearest one supported by MP3, between 8 and 320 kbps.
+    Mp3 {
+        /// Target bitrate in kbps
+        bitrate_kbps: u32,
+    },
+}
+
+impl OutputFormat {
+    /// MIME type of audio in this format
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Wav => "audio/wav",
+            OutputFormat::Mp3 { .. } => "audio/mpeg",
+        }
+    }
+
+    /// File extension of audio in this format, without the dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Wav => "wav",
+            OutputFormat::Mp3 { .. } => "mp3",
+        }
+    }
+
+    /// Format written to files with `extension`, ignoring case
+    ///
+    /// MP3 uses [`DEFAULT_MP3_BITRATE_KBPS`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::OutputFormat;
+    ///
+    /// assert_eq!(OutputFormat::from_extension("WAV"), Some(OutputFormat::Wav));
+    /// assert_eq!(OutputFormat::from_extension("flac"), None);
+    /// ```
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "wav" => Some(OutputFormat::Wav),
+            "mp3" => Some(OutputFormat::Mp3 {
+                bitrate_kbps: DEFAULT_MP3_BITRATE_KBPS,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Format to write `path` in
+///
+/// A `configured` format always wins, with a warning if the extension names
+/// a different supported format. Otherwise the format follows the
+/// extension, falling back to WAV with a warning for an unsupported one.
+/// Paths without an extension are written as configured, or as WAV.
+pub(crate) fn resolve_format(
+    path: &str,
+    configured: Option<OutputFormat>,
+    warnings: &mut Vec<Warning>,
+) -> OutputFormat {
+    let Some(extension) = Path::new(path).extension().and_then(|e| e.to_str()) else {
+        return configured.unwrap_or_default();
+    };
+    match (configured, OutputFormat::from_extension(extension)) {
+        (Some(format), Some(inferred)) => {
+            if format.extension() != inferred.extension() {
+                warnings.push(Warning::OutputFormatMismatch {
+                    extension: extension.to_string(),
+                    format: format.extension(),
+                });
+            }
+            format
+        }
+        (Some(format), None) => format,
+        (None, Some(inferred)) => inferred,
+        (None, None) => {
+            warnings.push(Warning::UnknownOutputExtension {
+                extension: extension.to_string(),
+            });
+            OutputFormat::Wav
+        }
+    }
+}
+
+/// Fail if audio cannot be encoded in `format` with the enabled features,
+/// so the check can run before any synthesis
+pub(crate) fn check_encodable(format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Mp3 { .. } if !cfg!(feature = "mp3") => Err(Error::Config(
+            "MP3 output requires the `mp3` feature".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Base64-encoded audio with its MIME type
+///
+/// Returned by
+/// [`Text2Audio::convert_to_base64`](crate::Text2Audio::convert_to_base64).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodedAudio {
+    /// MIME type of the audio, such as `audio/wav`
+    pub mime_type: &'static str,
+    /// Audio bytes in standard, padded base64
+    pub base64: String,
+}
+
+impl EncodedAudio {
+    pub(crate) fn new(audio: &[u8], format: OutputFormat) -> Self {
+        Self {
+            mime_type: format.mime_type(),
+            base64: base64::engine::general_purpose::STANDARD.encode(audio),
+        }
+    }
+
+    /// Format the audio as a `data:` URI
+    pub fn data_uri(&self) -> String {
+        format!("data:{};base64,{}", self.mime_type, self.base64)
+    }
+}
+
+/// Encode merged WAV bytes in `format`
+pub(crate) fn encode(wav: &[u8], format: OutputFormat) -> Result<Cow<'_, [u8]>> {
+    match format {
+        OutputFormat::Wav => Ok(Cow::Borrowed(wav)),
+        OutputFormat::Mp3 { bitrate_kbps } => {
+            let reader = WavReader::new(Cursor::new(wav)).map_err(|e| {
+                Error::audio(
+                    AudioErrorKind::InvalidWav,
+                    format!("Invalid WAV format: {}", e),
+                )
+            })?;
+            let mut mp3 = Vec::new();
+            encode_mp3(reader, bitrate_kbps, &mut mp3)?;
+            Ok(Cow::Owned(mp3))
+        }
+    }
+}
+
+/// Encode merged WAV bytes in `format` and write them to `path`, returning
+/// the digest of the file
+pub(crate) fn encode_to_file(wav: &[u8], format: OutputFormat, path: &str) -> Result<OutputDigest> {
+    Ok(write_hashed(path, &encode(wav, format)?)?)
+}
+
+/// Frames passed to the MP3 encoder at a time, a whole number of MP3 frames
+#[cfg(feature = "mp3")]
+const MP3_BLOCK_FRAMES: usize = 1152 * 32;
+
+/// Encode the WAV read by `reader` as MP3 into `output`, holding only a
+/// block of samples at a time
+#[cfg(feature = "mp3")]
+pub(crate) fn encode_mp3<R: Read>(
+    mut reader: WavReader<R>,
+    bitrate_kbps: u32,
+    output: &mut impl Write,
+) -> Result<()> {
+    use crate::audio_merger::samples_i16;
+    use mp3lame_encoder::{Bitrate, Builder, FlushNoGap, InterleavedPcm, MonoPcm};
+
+    const BITRATES: [(u32, Bitrate); 16] = [
+        (8, Bitrate::Kbps8),
+        (16, Bitrate::Kbps16),
+        (24, Bitrate::Kbps24),
+        (32, Bitrate::Kbps32),
+        (40, Bitrate::Kbps40),
+        (48, Bitrate::Kbps48),
+        (64, Bitrate::Kbps64),
+        (80, Bitrate::Kbps80),
+        (96, Bitrate::Kbps96),
+        (112, Bitrate::Kbps112),
+        (128, Bitrate::Kbps128),
+        (160, Bitrate::Kbps160),
+        (192, Bitrate::Kbps192),
+        (224, Bitrate::Kbps224),
+        (256, Bitrate::Kbps256),
+        (320, Bitrate::Kbps320),
+    ];
+
+    let spec = reader.spec();
+    if spec.channels > 2 {
+        return Err(Error::audio(
+            AudioErrorKind::Codec,
+            format!("MP3 supports at most 2 channels, got {}", spec.channels),
+        ));
+    }
+    let bitrate = BITRATES
+        .iter()
+        .rev()
+        .find(|(kbps, _)| *kbps <= bitrate_kbps)
+        .unwrap_or(&BITRATES[0])
+        .1;
+    let mp3_error = |e: &dyn std::fmt::Display| {
+        Error::audio(AudioErrorKind::Codec, format!("MP3 encoding failed: {}", e))
+    };
+    let mut builder = Builder::new()
+        .ok_or_else(|| Error::audio(AudioErrorKind::Codec, "Failed to initialize MP3 encoder"))?;
+    builder
+        .set_num_channels(spec.channels as u8)
+        .map_err(|e| mp3_error(&e))?;
+    builder
+        .set_sample_rate(spec.sample_rate)
+        .map_err(|e| mp3_error(&e))?;
+    builder.set_brate(bitrate).map_err(|e| mp3_error(&e))?;
+    let mut encoder = builder.build().map_err(|e| mp3_error(&e))?;
+
+    let block_len = MP3_BLOCK_FRAMES * spec.channels as usize;
+    let mut samples = samples_i16(&mut reader);
+    let mut block = Vec::with_capacity(block_len);
+    let mut mp3 = Vec::with_capacity(mp3lame_encoder::max_required_buffer_size(block_len));
+    loop {
+        block.clear();
+        for sample in samples.by_ref().take(block_len) {
+            block.push(sample?);
+        }
+        if block.is_empty() {
+            break;
+        }
+        mp3.clear();
+        if spec.channels == 1 {
+            encoder.encode_to_vec(MonoPcm(&block), &mut mp3)
+        } else {
+            encoder.encode_to_vec(InterleavedPcm(&block), &mut mp3)
+        }
+        .map_err(|e| mp3_error(&e))?;
+        output.write_all(&mp3)?;
+    }
+    mp3.clear();
+    encoder
+        .flush_to_vec::<FlushNoGap>(&mut mp3)
+        .map_err(|e| mp3_error(&e))?;
+    output.write_all(&mp3)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "mp3"))]
+pub(crate) fn encode_mp3<R: Read>(
+    _reader: WavReader<R>,
+    _bitrate_kbps: u32,
+    _output: &mut impl Write,
+) -> Result<()> {
+    Err(Error::Config(
+        "MP3 output requires the `mp3` feature".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{temp_path, wav_bytes};
+
+    #[test]
+    fn test_encode_wav_unchanged() {
+        let wav = wav_bytes(&[1, 2, 3], 24000, 1);
+        let path = temp_path("encode.wav");
+        encode_to_file(&wav, OutputFormat::Wav, &path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), wav);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_encoded_audio_data_uri() {
+        let encoded = EncodedAudio::new(b"RIFF", OutputFormat::Wav);
+        assert_eq!(encoded.base64, "UklGRg==");
+        assert_eq!(encoded.data_uri(), "data:audio/wav;base64,UklGRg==");
+        assert_eq!(
+            OutputFormat::Mp3 { bitrate_kbps: 64 }.mime_type(),
+            "audio/mpeg"
+        );
+    }
+
+    #[test]
+    fn test_resolve_format() {
+        let mp3 = OutputFormat::Mp3 { bitrate_kbps: 64 };
+        let inferred_mp3 = OutputFormat::Mp3 {
+            bitrate_kbps: DEFAULT_MP3_BITRATE_KBPS,
+        };
+        let cases: &[(&str, Option<OutputFormat>, OutputFormat, Option<Warning>)] = &[
+            ("out.wav", None, OutputFormat::Wav, None),
+            ("out.mp3", None, inferred_mp3, None),
+            ("dir.v2/OUT.MP3", None, inferred_mp3, None),
+            ("out.mp3", Some(mp3), mp3, None),
+            ("out", None, OutputFormat::Wav, None),
+            ("out", Some(mp3), mp3, None),
+            ("dir.v2/out", Some(mp3), mp3, None),
+            ("out.audio", Some(mp3), mp3, None),
+            (
+                "out.wav",
+                Some(mp3),
+                mp3,
+                Some(Warning::OutputFormatMismatch {
+                    extension: "wav".to_string(),
+                    format: "mp3",
+                }),
+            ),
+            (
+                "out.mp3",
+                Some(OutputFormat::Wav),
+                OutputFormat::Wav,
+                Some(Warning::OutputFormatMismatch {
+                    extension: "mp3".to_string(),
+                    format: "wav",
+                }),
+            ),
+            (
+                "out.flac",
+                None,
+                OutputFormat::Wav,
+                Some(Warning::UnknownOutputExtension {
+                    extension: "flac".to_string(),
+                }),
+            ),
+        ];
+
+        for (path, configured, format, warning) in cases {
+            let mut warnings = Vec::new();
+            assert_eq!(
+                resolve_format(path, *configured, &mut warnings),
+                *format,
+                "{}",
+                path
+            );
+            assert_eq!(warnings.first(), warning.as_ref(), "{}", path);
+            assert!(warnings.len() <= 1);
+        }
+    }
+
+    #[cfg(not(feature = "mp3"))]
+    #[test]
+    fn test_mp3_requires_feature() {
+        let wav = wav_bytes(&[1, 2, 3], 24000, 1);
+        let result = encode_to_file(
+            &wav,
+            OutputFormat::Mp3 { bitrate_kbps: 128 },
+            &temp_path("encode.mp3"),
+        );
+        assert!(matches!(result, Err(Error::Config(_))));
+    }
+}
@@ -1,3 +1,6 @@
+use crate::report::{describe_in_flight, StalledSegment};
+use crate::retry::RetryClass;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Error types for text2audio library
@@ -12,8 +15,13 @@ pub enum Error {
     TtsApi(String),
 
     /// Audio processing failed
-    #[error("Audio processing error: {0}")]
-    Audio(String),
+    #[error("Audio processing error: {message}")]
+    Audio {
+        /// Condition that failed, for telling failures apart
+        kind: AudioErrorKind,
+        /// What went wrong
+        message: String,
+    },
 
     /// IO error
     #[error("IO error: {0}")]
@@ -34,6 +42,367 @@ pub enum Error {
     /// Empty input text
     #[error("Input text is empty")]
     EmptyInput,
+
+    /// Several operations of a batch failed
+    #[error("{}", summarize(.0))]
+    Multiple(Vec<Error>),
+
+    /// Synthesis made no progress for two stall timeouts in a row
+    #[error(
+        "Conversion stalled: no progress for {:.1}s; segments in flight: {}",
+        .idle.as_secs_f64(),
+        describe_in_flight(.in_flight)
+    )]
+    Stalled {
+        /// Time since the last progress
+        idle: Duration,
+        /// Segments being synthesized when the conversion was cancelled
+        in_flight: Vec<StalledSegment>,
+    },
+
+    /// The synthesis queue of an incremental input is full
+    #[error("Incremental input queue is full: {depth} segments awaiting synthesis")]
+    QueueFull {
+        /// Segments the queue holds
+        depth: usize,
+    },
+
+    /// The API refused the request because of its rate limit or quota
+    #[error("Rate limited: {0}")]
+    RateLimited(String),
+
+    /// The TTS API returned MP3 where WAV was requested, and it could not
+    /// be decoded
+    #[error(
+        "TTS API error: provider returned mp3 but wav was requested (content-type: {}){}",
+        .content_type.as_deref().unwrap_or("none"),
+        .decode_error.as_ref().map(|e| format!(": {}", e)).unwrap_or_default()
+    )]
+    UnexpectedMp3 {
+        /// Content type of the response, if it had one
+        content_type: Option<String>,
+        /// Why decoding failed, `None` without the `mp3-decode` feature
+        decode_error: Option<String>,
+    },
+}
+
+/// Condition behind an [`Error::Audio`]
+///
+/// Each kind has its own [code](Error::code).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AudioErrorKind {
+    /// Audio bytes are not well-formed WAV
+    InvalidWav,
+    /// There was no audio to merge or the output holds none
+    Empty,
+    /// The output is shorter than the minimum duration
+    TooShort,
+    /// The output is silent
+    Silent,
+    /// Audio of different formats could not be combined
+    SpecMismatch,
+    /// The output is too large for a WAV file
+    TooLarge,
+    /// Loudness could not be normalized under strict validation
+    LoudnessNotNormalized,
+    /// Segments came back out of order or with gaps
+    SegmentOrder,
+    /// Encoding or decoding MP3 failed
+    Codec,
+    /// The audio output device could not play the audio
+    Playback,
+    /// Any other failure, such as a background task that did not complete
+    Other,
+}
+
+/// How batch operations such as
+/// [`Text2Audio::convert_many`](crate::Text2Audio::convert_many) surface
+/// errors
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorMode {
+    /// Run every item and return each item's result (default)
+    #[default]
+    Collect,
+    /// Stop at the first failed item and return its error
+    FailFast,
+    /// Run every item and return all errors as one [`Error::Multiple`]
+    Aggregate,
+}
+
+impl Error {
+    /// Audio error of `kind`
+    pub(crate) fn audio(kind: AudioErrorKind, message: impl Into<String>) -> Self {
+        Error::Audio {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    /// Whether the error came from the transport rather than the API itself
+    ///
+    /// Transport errors (connection resets, timeouts, broken streams) are
+    /// likely to clear up when fewer requests are in flight.
+    pub fn is_transport(&self) -> bool {
+        matches!(self, Error::Http(_) | Error::Io(_))
+    }
+
+    /// How a [`RetryPolicy`](crate::RetryPolicy) treats the error by default
+    ///
+    /// Rate limits are retried after their backoff, and invalid input or
+    /// configuration is never retried. Every other error is retryable.
+    /// Backends can classify their own errors with
+    /// [`TtsBackend::classify`](crate::TtsBackend::classify).
+    pub fn retry_class(&self) -> RetryClass {
+        match self {
+            Error::RateLimited(_) => RetryClass::RateLimited { retry_after: None },
+            Error::Config(_) | Error::EmptyInput => RetryClass::Fatal,
+            _ => RetryClass::Retryable,
+        }
+    }
+
+    /// Stable code identifying the error's condition
+    ///
+    /// Codes never change meaning between releases, so they are safe to map
+    /// to user-facing messages, unlike the `Display` text. New conditions
+    /// get new codes rather than reusing old ones.
+    ///
+    /// | Code | Condition |
+    /// |------|-----------|
+    /// | `T2A_AI_API` | AI splitting request failed |
+    /// | `T2A_TTS_API` | TTS request failed or returned no audio |
+    /// | `T2A_AUDIO` | Audio processing failed for a reason without its own code |
+    /// | `T2A_AUDIO_INVALID_WAV` | Audio bytes are not well-formed WAV |
+    /// | `T2A_AUDIO_EMPTY` | No audio to merge, or the output holds none |
+    /// | `T2A_AUDIO_TOO_SHORT` | The output is below the minimum duration |
+    /// | `T2A_AUDIO_SILENT` | The output is silent |
+    /// | `T2A_AUDIO_SPEC_MISMATCH` | Audio of different formats could not be combined |
+    /// | `T2A_AUDIO_TOO_LARGE` | The output exceeds what a WAV file can hold |
+    /// | `T2A_AUDIO_LOUDNESS_NOT_NORMALIZED` | Loudness could not be normalized in strict mode |
+    /// | `T2A_AUDIO_SEGMENT_ORDER` | Segments came back out of order or with gaps |
+    /// | `T2A_AUDIO_CODEC` | MP3 encoding or decoding failed |
+    /// | `T2A_AUDIO_PLAYBACK` | The audio output device failed |
+    /// | `T2A_IO_NOT_FOUND` | A file or directory does not exist |
+    /// | `T2A_IO_PERMISSION_DENIED` | A file or directory is not accessible |
+    /// | `T2A_IO_TIMED_OUT` | An I/O operation timed out |
+    /// | `T2A_IO` | Any other I/O failure |
+    /// | `T2A_AUDIO_IO` | I/O failure while reading or writing WAV |
+    /// | `T2A_AUDIO_FORMAT` | Ill-formed WAV data |
+    /// | `T2A_AUDIO_UNSUPPORTED` | Unsupported WAV encoding |
+    /// | `T2A_AUDIO_SAMPLE_TOO_WIDE` | Sample wider than its destination type |
+    /// | `T2A_AUDIO_UNFINISHED_SAMPLE` | Sample count not a multiple of the channel count |
+    /// | `T2A_AUDIO_SAMPLE_FORMAT_MISMATCH` | Integer and float sample formats mixed |
+    /// | `T2A_HTTP` | Network transport failed |
+    /// | `T2A_CONFIG` | Invalid configuration or project file |
+    /// | `T2A_EMPTY_INPUT` | Input text is empty |
+    /// | `T2A_MULTIPLE` | Several operations of a batch failed |
+    /// | `T2A_STALLED` | Synthesis made no progress within the stall timeout |
+    /// | `T2A_QUEUE_FULL` | An incremental input's synthesis queue is full |
+    /// | `T2A_RATE_LIMITED` | The API's rate limit or quota was exceeded |
+    /// | `T2A_TTS_UNEXPECTED_MP3` | The TTS API returned MP3 instead of WAV |
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Error;
+    ///
+    /// assert_eq!(Error::EmptyInput.code(), "T2A_EMPTY_INPUT");
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::AiApi(_) => "T2A_AI_API",
+            Error::TtsApi(_) => "T2A_TTS_API",
+            Error::Audio { kind, .. } => match kind {
+                AudioErrorKind::InvalidWav => "T2A_AUDIO_INVALID_WAV",
+                AudioErrorKind::Empty => "T2A_AUDIO_EMPTY",
+                AudioErrorKind::TooShort => "T2A_AUDIO_TOO_SHORT",
+                AudioErrorKind::Silent => "T2A_AUDIO_SILENT",
+                AudioErrorKind::SpecMismatch => "T2A_AUDIO_SPEC_MISMATCH",
+                AudioErrorKind::TooLarge => "T2A_AUDIO_TOO_LARGE",
+                AudioErrorKind::LoudnessNotNormalized => "T2A_AUDIO_LOUDNESS_NOT_NORMALIZED",
+                AudioErrorKind::SegmentOrder => "T2A_AUDIO_SEGMENT_ORDER",
+                AudioErrorKind::Codec => "T2A_AUDIO_CODEC",
+                AudioErrorKind::Playback => "T2A_AUDIO_PLAYBACK",
+                AudioErrorKind::Other => "T2A_AUDIO",
+            },
+            Error::Io(e) => match e.kind() {
+                std::io::ErrorKind::NotFound => "T2A_IO_NOT_FOUND",
+                std::io::ErrorKind::PermissionDenied => "T2A_IO_PERMISSION_DENIED",
+                std::io::ErrorKind::TimedOut => "T2A_IO_TIMED_OUT",
+                _ => "T2A_IO",
+            },
+            Error::Hound(e) => match e {
+                hound::Error::IoError(_) => "T2A_AUDIO_IO",
+                hound::Error::FormatError(_) => "T2A_AUDIO_FORMAT",
+                hound::Error::Unsupported => "T2A_AUDIO_UNSUPPORTED",
+                hound::Error::TooWide => "T2A_AUDIO_SAMPLE_TOO_WIDE",
+                hound::Error::UnfinishedSample => "T2A_AUDIO_UNFINISHED_SAMPLE",
+                hound::Error::InvalidSampleFormat => "T2A_AUDIO_SAMPLE_FORMAT_MISMATCH",
+            },
+            Error::Http(_) => "T2A_HTTP",
+            Error::Config(_) => "T2A_CONFIG",
+            Error::EmptyInput => "T2A_EMPTY_INPUT",
+            Error::Multiple(_) => "T2A_MULTIPLE",
+            Error::Stalled { .. } => "T2A_STALLED",
+            Error::QueueFull { .. } => "T2A_QUEUE_FULL",
+            Error::RateLimited(_) => "T2A_RATE_LIMITED",
+            Error::UnexpectedMp3 { .. } => "T2A_TTS_UNEXPECTED_MP3",
+        }
+    }
+
+    /// Short name of the error's kind
+    fn kind(&self) -> &'static str {
+        match self {
+            Error::AiApi(_) => "AI API",
+            Error::TtsApi(_) => "TTS API",
+            Error::Audio { .. } => "audio processing",
+            Error::Io(_) => "IO",
+            Error::Hound(_) => "audio library",
+            Error::Http(_) => "HTTP",
+            Error::Config(_) => "configuration",
+            Error::EmptyInput => "empty input",
+            Error::Multiple(_) => "multiple",
+            Error::Stalled { .. } => "stalled",
+            Error::QueueFull { .. } => "queue full",
+            Error::RateLimited(_) => "rate limit",
+            Error::UnexpectedMp3 { .. } => "TTS API",
+        }
+    }
+}
+
+/// Summary of aggregated errors, e.g. "3 errors (2 HTTP, 1 TTS API); first: ..."
+fn summarize(errors: &[Error]) -> String {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for error in errors {
+        match counts.iter_mut().find(|(kind, _)| *kind == error.kind()) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((error.kind(), 1)),
+        }
+    }
+    let counts = counts
+        .iter()
+        .map(|(kind, count)| format!("{} {}", count, kind))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    match errors.first() {
+        Some(first) => format!("{} errors ({}); first: {}", errors.len(), counts, first),
+        None => "0 errors".to_string(),
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_transport() {
+        assert!(Error::Http("connection reset".to_string()).is_transport());
+        assert!(Error::Io(std::io::Error::other("broken pipe")).is_transport());
+        assert!(!Error::TtsApi("invalid voice".to_string()).is_transport());
+        assert!(!Error::EmptyInput.is_transport());
+    }
+
+    #[test]
+    fn test_multiple_display() {
+        let error = Error::Multiple(vec![
+            Error::Http("timed out".to_string()),
+            Error::TtsApi("invalid voice".to_string()),
+            Error::Http("connection reset".to_string()),
+        ]);
+        assert_eq!(
+            error.to_string(),
+            "3 errors (2 HTTP, 1 TTS API); first: HTTP error: timed out"
+        );
+        assert_eq!(Error::Multiple(Vec::new()).to_string(), "0 errors");
+    }
+
+    #[test]
+    fn test_code_table() {
+        use std::io::ErrorKind;
+
+        let io = |kind: ErrorKind| Error::Io(std::io::Error::new(kind, "io"));
+        let errors = [
+            Error::AiApi("a".to_string()),
+            Error::TtsApi("t".to_string()),
+            Error::audio(AudioErrorKind::Other, "o"),
+            io(ErrorKind::NotFound),
+            io(ErrorKind::PermissionDenied),
+            io(ErrorKind::TimedOut),
+            io(ErrorKind::BrokenPipe),
+            Error::Hound(hound::Error::IoError(std::io::Error::other("io"))),
+            Error::Hound(hound::Error::FormatError("no RIFF tag found")),
+            Error::Hound(hound::Error::Unsupported),
+            Error::Hound(hound::Error::TooWide),
+            Error::Hound(hound::Error::UnfinishedSample),
+            Error::Hound(hound::Error::InvalidSampleFormat),
+            Error::Http("h".to_string()),
+            Error::Config("c".to_string()),
+            Error::EmptyInput,
+            Error::Multiple(Vec::new()),
+            Error::Stalled {
+                idle: Duration::from_secs(60),
+                in_flight: Vec::new(),
+            },
+            Error::QueueFull { depth: 32 },
+            Error::RateLimited("r".to_string()),
+            Error::UnexpectedMp3 {
+                content_type: None,
+                decode_error: None,
+            },
+            Error::audio(AudioErrorKind::InvalidWav, "w"),
+            Error::audio(AudioErrorKind::Empty, "e"),
+            Error::audio(AudioErrorKind::TooShort, "t"),
+            Error::audio(AudioErrorKind::Silent, "output is silent"),
+            Error::audio(AudioErrorKind::SpecMismatch, "s"),
+            Error::audio(AudioErrorKind::TooLarge, "l"),
+            Error::audio(AudioErrorKind::LoudnessNotNormalized, "n"),
+            Error::audio(AudioErrorKind::SegmentOrder, "o"),
+            Error::audio(AudioErrorKind::Codec, "c"),
+            Error::audio(AudioErrorKind::Playback, "p"),
+        ];
+        let codes: Vec<&str> = errors.iter().map(Error::code).collect();
+
+        // Changing this table breaks callers; add codes, never edit them
+        assert_eq!(
+            codes,
+            [
+                "T2A_AI_API",
+                "T2A_TTS_API",
+                "T2A_AUDIO",
+                "T2A_IO_NOT_FOUND",
+                "T2A_IO_PERMISSION_DENIED",
+                "T2A_IO_TIMED_OUT",
+                "T2A_IO",
+                "T2A_AUDIO_IO",
+                "T2A_AUDIO_FORMAT",
+                "T2A_AUDIO_UNSUPPORTED",
+                "T2A_AUDIO_SAMPLE_TOO_WIDE",
+                "T2A_AUDIO_UNFINISHED_SAMPLE",
+                "T2A_AUDIO_SAMPLE_FORMAT_MISMATCH",
+                "T2A_HTTP",
+                "T2A_CONFIG",
+                "T2A_EMPTY_INPUT",
+                "T2A_MULTIPLE",
+                "T2A_STALLED",
+                "T2A_QUEUE_FULL",
+                "T2A_RATE_LIMITED",
+                "T2A_TTS_UNEXPECTED_MP3",
+                "T2A_AUDIO_INVALID_WAV",
+                "T2A_AUDIO_EMPTY",
+                "T2A_AUDIO_TOO_SHORT",
+                "T2A_AUDIO_SILENT",
+                "T2A_AUDIO_SPEC_MISMATCH",
+                "T2A_AUDIO_TOO_LARGE",
+                "T2A_AUDIO_LOUDNESS_NOT_NORMALIZED",
+                "T2A_AUDIO_SEGMENT_ORDER",
+                "T2A_AUDIO_CODEC",
+                "T2A_AUDIO_PLAYBACK",
+            ]
+        );
+        let unique: std::collections::HashSet<_> = codes.iter().collect();
+        assert_eq!(unique.len(), codes.len());
+    }
+}
@@ -0,0 +1,255 @@
+//! Conversion of text that arrives in chunks, such as streaming LLM output
+
+use crate::error::{Error, Result};
+use crate::local_splitter::{LengthUnit, LocalSplitter};
+use crate::plan::CLOSING_MARKS;
+use std::collections::VecDeque;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::Sender;
+
+/// Default number of segments queued for synthesis by an incremental input
+pub const DEFAULT_QUEUE_DEPTH: usize = 32;
+
+/// What [`IncrementalInput::push`] does when the queue is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueuePolicy {
+    /// Wait until synthesis makes room (default)
+    #[default]
+    Wait,
+    /// Reject the chunk with [`Error::QueueFull`]
+    Error,
+}
+
+/// Input side of an incremental conversion
+///
+/// Created by [`Text2Audio::convert_incremental`](crate::Text2Audio::convert_incremental).
+/// Text pushed here is buffered until a sentence is complete, then queued
+/// for synthesis by the audio stream. The queue holds at most
+/// [`with_queue_depth`](crate::Text2Audio::with_queue_depth) segments, so
+/// a producer faster than synthesis is held back instead of growing memory
+/// without bound; the stream must therefore be consumed concurrently with
+/// pushing. Call [`finish`](Self::finish) after the last chunk to
+/// synthesize the trailing text; dropping the handle without finishing
+/// discards any unfinished sentence.
+pub struct IncrementalInput {
+    buffer: SentenceBuffer,
+    /// Completed sentences still waiting for room in the queue
+    unsent: VecDeque<String>,
+    sender: Sender<String>,
+    policy: QueuePolicy,
+}
+
+impl IncrementalInput {
+    pub(crate) fn new(buffer: SentenceBuffer, sender: Sender<String>, policy: QueuePolicy) -> Self {
+        Self {
+            buffer,
+            unsent: VecDeque::new(),
+            sender,
+            policy,
+        }
+    }
+
+    /// Add a chunk of text
+    ///
+    /// Chunks may end anywhere, even mid-word; every sentence the chunk
+    /// completes is queued for synthesis. With a full queue, the
+    /// [`QueuePolicy`] decides whether to wait for room or fail.
+    ///
+    /// # Cancel safety
+    ///
+    /// Dropping the future while it waits for room loses no text: the
+    /// chunk is taken as soon as the future is first polled, and sentences
+    /// not yet queued are queued by the next `push` or by
+    /// [`finish`](Self::finish).
+    ///
+    /// # Errors
+    ///
+    /// With [`QueuePolicy::Error`], returns [`Error::QueueFull`] if the
+    /// sentences the chunk completes do not all fit in the queue. The chunk
+    /// is then not taken, so it can be pushed again once synthesis catches
+    /// up; a chunk completing more sentences than the queue depth never fits.
+    pub async fn push(&mut self, chunk: &str) -> Result<()> {
+        // A dropped audio stream no longer wants the text
+        if self.sender.is_closed() {
+            return Ok(());
+        }
+        if self.policy == QueuePolicy::Wait {
+            self.unsent.extend(self.buffer.push(chunk));
+            self.send_unsent().await;
+            return Ok(());
+        }
+
+        let pending = self.buffer.pending.clone();
+        let segments = self.buffer.push(chunk);
+        // Room for the whole chunk is reserved at once, or not at all
+        let permits = match self.sender.try_reserve_many(segments.len()) {
+            Ok(permits) => permits,
+            Err(TrySendError::Full(())) => {
+                self.buffer.pending = pending;
+                return Err(Error::QueueFull {
+                    depth: self.sender.max_capacity(),
+                });
+            }
+            Err(TrySendError::Closed(())) => return Ok(()),
+        };
+        for (permit, segment) in permits.zip(segments) {
+            permit.send(segment);
+        }
+        Ok(())
+    }
+
+    /// End the input, queueing the remaining text for synthesis
+    ///
+    /// Waits for room in the queue whatever the [`QueuePolicy`], as no
+    /// more text follows. The audio stream ends after the last queued
+    /// segment.
+    pub async fn finish(mut self) {
+        self.unsent.extend(self.buffer.finish());
+        self.send_unsent().await;
+    }
+
+    /// Queue the unsent sentences, taking each only once there is room
+    async fn send_unsent(&mut self) {
+        while !self.unsent.is_empty() {
+            let Ok(permit) = self.sender.reserve().await else {
+                self.unsent.clear();
+                return;
+            };
+            if let Some(segment) = self.unsent.pop_front() {
+                permit.send(segment);
+            }
+        }
+    }
+}
+
+/// Text held back until it forms complete sentences
+///
+/// A terminator only ends a sentence once the character after it is known,
+/// so closing quotes stay with their sentence, and `.`, `!` or `?` must be
+/// followed by whitespace, which keeps a chunk ending in "3." from cutting
+/// "3.5" apart. Text without a boundary is released once it no longer fits
+/// in one segment.
+pub(crate) struct SentenceBuffer {
+    pending: String,
+    splitter: LocalSplitter,
+    max_length: usize,
+    unit: LengthUnit,
+    terminators: Vec<char>,
+}
+
+impl SentenceBuffer {
+    pub(crate) fn new(max_length: usize, unit: LengthUnit, terminators: &[char]) -> Self {
+        let max_length = max_length.max(1);
+        Self {
+            pending: String::new(),
+            splitter: LocalSplitter::new(max_length)
+                .with_length_unit(unit)
+                .with_sentence_terminators(terminators),
+            max_length,
+            unit,
+            terminators: terminators.to_vec(),
+        }
+    }
+
+    /// Add `chunk`, returning the segments it completes
+    pub(crate) fn push(&mut self, chunk: &str) -> Vec<String> {
+        self.pending.push_str(chunk);
+        let end = complete_end(&self.pending, &self.terminators);
+        let complete: String = self.pending.drain(..end).collect();
+        let mut segments = self.splitter.split(&complete);
+
+        if !self.unit.fits(self.pending.trim(), self.max_length) {
+            let mut pieces = self.splitter.split(&self.pending);
+            // Keep the last piece untrimmed, as the next chunk continues it
+            if let Some(last) = pieces.pop() {
+                let start = self.pending.rfind(last.as_str()).unwrap_or(0);
+                self.pending.drain(..start);
+            }
+            segments.extend(pieces);
+        }
+        segments
+    }
+
+    /// Release the remaining text
+    pub(crate) fn finish(&mut self) -> Vec<String> {
+        let segments = self.splitter.split(&self.pending);
+        self.pending.clear();
+        segments
+    }
+}
+
+/// Byte offset just past the last sentence boundary known to be complete
+fn complete_end(text: &str, terminators: &[char]) -> usize {
+    let mut end = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        if !terminators.contains(&c) {
+            continue;
+        }
+        // Repeated terminators and closing marks belong to the sentence
+        while let Some(&(_, next)) = chars.peek() {
+            if terminators.contains(&next) || CLOSING_MARKS.contains(&next) {
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        match chars.peek() {
+            Some(&(idx, next)) if !c.is_ascii() || next.is_whitespace() => end = idx,
+            _ => {}
+        }
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_splitter::DEFAULT_SENTENCE_TERMINATORS;
+
+    fn push_all(buffer: &mut SentenceBuffer, chunks: &[&str]) -> Vec<Vec<String>> {
+        chunks.iter().map(|chunk| buffer.push(chunk)).collect()
+    }
+
+    #[test]
+    fn test_sentences_released_once_complete() {
+        let mut buffer = SentenceBuffer::new(100, LengthUnit::Chars, DEFAULT_SENTENCE_TERMINATORS);
+        let released = push_all(&mut buffer, &["你好，世", "界！今天", "“很好。", "”明天"]);
+
+        assert_eq!(
+            released,
+            vec![
+                vec![],
+                vec!["你好，世界！".to_string()],
+                vec![],
+                vec!["今天“很好。”".to_string()],
+            ]
+        );
+        assert_eq!(buffer.finish(), vec!["明天"]);
+        assert!(buffer.finish().is_empty());
+    }
+
+    #[test]
+    fn test_latin_terminator_needs_whitespace() {
+        let mut buffer = SentenceBuffer::new(100, LengthUnit::Words, DEFAULT_SENTENCE_TERMINATORS);
+        let released = push_all(&mut buffer, &["Pi is 3.", "14. Next", " one"]);
+
+        assert_eq!(
+            released,
+            vec![vec![], vec!["Pi is 3.14.".to_string()], vec![]]
+        );
+        assert_eq!(buffer.finish(), vec!["Next one"]);
+    }
+
+    #[test]
+    fn test_unterminated_text_cut_at_max_length() {
+        let mut buffer = SentenceBuffer::new(3, LengthUnit::Words, DEFAULT_SENTENCE_TERMINATORS);
+        let released = push_all(&mut buffer, &["one two ", "three four fi", "ve six"]);
+
+        assert_eq!(
+            released,
+            vec![vec![], vec!["one two three".to_string()], vec![]]
+        );
+        assert_eq!(buffer.finish(), vec!["four five six"]);
+    }
+}
@@ -1,23 +1,188 @@
 pub mod ai_splitter;
+mod alignment;
 pub mod audio_merger;
+pub mod backend;
+pub mod bundle;
+pub mod calibration;
 pub mod client;
 pub mod config;
+pub mod digest;
+pub mod encode;
 pub mod error;
+pub mod incremental;
+#[cfg(feature = "live")]
+pub mod live;
+pub mod local_splitter;
+mod lock;
+pub mod loudness;
+pub mod merged;
+pub mod naming;
+pub mod numeric;
+pub mod paths;
+pub mod plan;
+#[cfg(feature = "playback")]
+mod playback;
+pub mod preprocess;
+pub mod progress;
+pub mod project;
+pub mod report;
+pub mod retry;
+pub mod stats;
+mod temp;
+mod watchdog;
 
-pub use ai_splitter::AiSplitter;
-pub use audio_merger::AudioMerger;
-pub use client::{Client, Model, TtsConfig};
-pub use config::Voice;
-pub use error::{Error, Result};
+#[cfg(test)]
+mod test_util;
+#[cfg(any(test, feature = "test-util"))]
+pub mod verify;
+
+pub use ai_splitter::{AiSplitter, ChoiceSelection};
+pub use audio_merger::{
+    probe_bytes, AudioChunk, AudioMerger, CompareResult, IncrementalMerger, PcmAudio, RepairReport,
+    WavInfo,
+};
+pub use backend::{ChatBackend, SplitBackend, TtsBackend};
+pub use bundle::FailureBundle;
+pub use calibration::{Calibration, CalibrationEntry};
+pub use client::{
+    Client, Model, ModelCapabilities, TtsConfig, TtsConfigBuilder, MODEL_CAPABILITIES,
+    TTS_MAX_CHARS,
+};
+pub use config::{ReadingProfile, SectionProfile, Voice};
+pub use digest::OutputDigest;
+pub use encode::{EncodedAudio, OutputFormat};
+pub use error::{AudioErrorKind, Error, ErrorMode, Result};
+pub use hound::{SampleFormat, WavSpec};
+pub use incremental::{IncrementalInput, QueuePolicy};
+#[cfg(feature = "live")]
+pub use live::{live_buffer, LiveConsumer, LiveProducer};
+pub use local_splitter::{LengthUnit, LocalSplitter};
+pub use merged::MergedAudio;
+pub use naming::{FileNaming, OverwritePolicy};
+pub use numeric::{DateOrder, DecimalSeparator, NumericContext, SlashReading};
+pub use paths::OutputTemplate;
+pub use plan::{
+    ConversionMode, ConversionPlan, ModeSelection, OversizePolicy, SegmentStats, SplitChoice,
+    SplitRace, SplitStrategy,
+};
+pub use preprocess::{
+    FootnoteBodies, FootnotePolicy, LongTokenPolicy, SpacingMode, SymbolLanguage, DEFAULT_SECTION,
+};
+pub use progress::{ProgressEvent, ProgressKind, Throughput};
+pub use project::{Project, ProjectSettings};
+pub use report::{
+    ConversionReport, OutputMetadata, PhaseTimings, ReportDetail, SegmentReport, SegmentSource,
+    SourceCounts, StalledSegment, Track, Warning,
+};
+pub use retry::{RetryClass, RetryPolicy};
+pub use stats::{ClientStats, Endpoint, EndpointStats, LatencyHistogram};
+#[cfg(any(test, feature = "test-util"))]
+pub use verify::{verify_output, Discrepancy, VerificationError};
 
-use futures::stream::{self, StreamExt};
+use ai_splitter::{SplitCache, DEFAULT_MAX_PROMPT_CHARS};
+use alignment::align_segments;
+use audio_merger::{riff_chunks, run_blocking, write_blocking};
+use calibration::{spoken_chars, DEFAULT_SECONDS_PER_CHAR};
+use client::{
+    segment_idempotency_key, ResponseHandler, DEFAULT_SPEED, DEFAULT_VOLUME, SPEED_RANGE,
+    VOLUME_RANGE,
+};
+use digest::digest_file;
+use encode::{check_encodable, encode_to_file, resolve_format};
+use futures::stream::{self, Stream, StreamExt};
+use incremental::{SentenceBuffer, DEFAULT_QUEUE_DEPTH};
+use local_splitter::{list_blocks, DEFAULT_SENTENCE_TERMINATORS};
+use lock::FileLock;
+use loudness::{DEFAULT_TARGET_LOUDNESS, TARGET_LOUDNESS_RANGE};
+use paths::TemplateValues;
+use preprocess::{rewrite, split_at_newlines, Pause, SplitPattern};
+use progress::ProgressHandler;
+use project::ProjectSegment;
+use stats::StatsRecorder;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::time::Instant;
 
-/// Main entry point for text-to-audio conversion
+/// Text synthesized to check that a voice is available
+const VOICE_PROBE_TEXT: &str = "你好";
+
+/// Convert text to an audio file with default settings
+///
+/// Shorthand for [`Text2Audio::new`] followed by
+/// [`Text2Audio::convert`], for scripts that need no configuration.
+///
+/// # Arguments
+///
+/// * `api_key` - Zhipu AI API key
+/// * `text` - Input text to convert
+/// * `output_path` - Output audio file path, whose extension selects the format
+///
+/// # Errors
+///
+/// Returns error if text processing, API calls, or audio processing fail.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let api_key = std::env::var("ZHIPU_API_KEY")?;
+/// text2audio::convert(&api_key, "你好，世界！", "output.wav").await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn convert(api_key: &str, text: &str, output_path: &str) -> Result<()> {
+    Text2Audio::new(api_key).convert(text, output_path).await
+}
+
+/// Convert text to an audio file in `voice`, with otherwise default settings
+///
+/// Shorthand for [`Text2Audio::new`] and [`Text2Audio::with_voice`]
+/// followed by [`Text2Audio::convert`].
+///
+/// # Arguments
+///
+/// * `api_key` - Zhipu AI API key
+/// * `text` - Input text to convert
+/// * `output_path` - Output audio file path, whose extension selects the format
+/// * `voice` - Voice to read the text in
+///
+/// # Errors
+///
+/// Returns error if text processing, API calls, or audio processing fail.
 ///
 /// # Examples
 ///
+/// ```no_run
+/// use text2audio::Voice;
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let api_key = std::env::var("ZHIPU_API_KEY")?;
+/// text2audio::convert_with_voice(&api_key, "你好，世界！", "output.wav", Voice::Xiaochen).await?;
+/// # Ok(())
+/// # }
 /// ```
+pub async fn convert_with_voice(
+    api_key: &str,
+    text: &str,
+    output_path: &str,
+    voice: Voice,
+) -> Result<()> {
+    Text2Audio::new(api_key)
+        .with_voice(voice)
+        .convert(text, output_path)
+        .await
+}
+
+/// Main entry point for text-to-audio conversion
+///
+/// # Examples
+///
+/// ```no_run
 /// use text2audio::Text2Audio;
 ///
 /// # #[tokio::main]
@@ -34,15 +199,117 @@ pub struct Text2Audio {
     voice: Voice,
     speed: f32,
     volume: f32,
+    section_profiles: HashMap<String, SectionProfile>,
     max_segment_length: usize,
+    target_segment_duration: Option<Duration>,
+    length_unit: LengthUnit,
     enable_parallel: bool,
     max_parallel: usize,
-    max_retries: u32,
-    retry_delay: Duration,
+    inter_request_delay: Duration,
+    retry_policy: RetryPolicy,
     enable_thinking: bool,
     coding_plan: bool,
+    max_prompt_chars: usize,
+    max_prompt_bytes: Option<usize>,
+    backend: Option<Arc<dyn TtsBackend>>,
+    split_backend: Option<Arc<dyn SplitBackend>>,
+    split_cache: Option<SplitCache>,
+    split_strategy: SplitStrategy,
+    choice_selection: ChoiceSelection,
+    long_token_policy: Option<(usize, LongTokenPolicy)>,
+    strip_link_urls: bool,
+    footnote_policy: Option<FootnotePolicy>,
+    footnote_bodies: FootnoteBodies,
+    cjk_latin_spacing: Option<SpacingMode>,
+    symbol_verbalization: bool,
+    symbol_language: SymbolLanguage,
+    symbol_readings: HashMap<char, String>,
+    unknown_symbol_threshold: usize,
+    numeric_context: Option<NumericContext>,
+    segment_overrides: HashMap<usize, String>,
+    timing_sidecar: bool,
+    report_detail: Option<ReportDetail>,
+    sidecar_metadata: bool,
+    speech_marks: Option<PathBuf>,
+    speech_mark_sections: bool,
+    max_memory: Option<usize>,
+    parallel_fallback: bool,
+    idempotency_keys: bool,
+    deterministic: bool,
+    failure_bundle: Option<PathBuf>,
+    failure_bundle_text: bool,
+    calibration_file: Option<PathBuf>,
+    file_template: OutputTemplate,
+    overwrite_policy: OverwritePolicy,
+    output_preflight: bool,
+    voice_probes: Mutex<HashMap<Voice, bool>>,
+    strict_validation: bool,
+    parameter_warnings: Vec<Warning>,
+    newline_pauses: Option<(Duration, Duration)>,
+    split_pattern: Option<std::result::Result<SplitPattern, String>>,
+    sentence_terminators: Vec<char>,
+    list_awareness: bool,
+    bilingual_pauses: (Duration, Duration),
+    min_duration: Duration,
+    require_nonsilent: bool,
+    target_loudness: Option<f32>,
+    strict_riff: bool,
+    preserve_extra_chunks: bool,
+    merge_threads: usize,
+    audio_inspector: Option<AudioInspector>,
+    progress_handler: Option<ProgressHandler>,
+    progress_heartbeat: Duration,
+    queue_depth: usize,
+    chunk_duration: Option<Duration>,
+    queue_policy: QueuePolicy,
+    response_handler: Option<ResponseHandler>,
+    output_format: Option<OutputFormat>,
+    mode: ModeSelection,
+    oversize_policy: OversizePolicy,
+    error_mode: ErrorMode,
+    uniform_segment_duration: Option<Duration>,
+    lock_timeout: Duration,
+    stall_timeout: Option<Duration>,
+    abort_on_stall: bool,
+    runtime: Option<tokio::runtime::Handle>,
+    stats: Arc<StatsRecorder>,
 }
 
+/// Peak amplitude, about -60 dBFS, that output must reach to count as
+/// non-silent
+///
+/// See [`Text2Audio::with_require_nonsilent`].
+pub const NONSILENT_MIN_PEAK: u16 = 33;
+
+/// Fraction by which a segment may exceed the target segment duration
+/// before it is reported
+///
+/// See [`Text2Audio::with_target_segment_duration`].
+pub const SEGMENT_DURATION_TOLERANCE: f64 = 0.1;
+
+/// Bytes of segment text above which reports keep only hashes by default
+///
+/// See [`Text2Audio::with_report_detail`].
+pub const FULL_REPORT_MAX_BYTES: usize = 256 * 1024;
+
+/// Default silence between the texts of a bilingual pair
+const DEFAULT_PAIR_PAUSE: Duration = Duration::from_millis(400);
+
+/// Default silence between bilingual pairs
+const DEFAULT_BETWEEN_PAIRS_PAUSE: Duration = Duration::from_millis(1000);
+
+/// Distinct ambiguous numbers listed in a [`Warning::AmbiguousNumbers`]
+const AMBIGUOUS_NUMBER_SAMPLES: usize = 10;
+
+/// Default wait for a project lock held by another process
+const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default interval of progress heartbeats
+const DEFAULT_PROGRESS_HEARTBEAT: Duration = Duration::from_secs(1);
+
+/// Callback run on the decoded audio of each conversion
+type AudioInspector = Arc<dyn Fn(&PcmAudio) + Send + Sync>;
+
 impl Text2Audio {
     /// Create a new Text2Audio converter with default settings
     ///
@@ -54,15 +321,82 @@ impl Text2Audio {
             api_key: api_key.into(),
             model: Model::default(),
             voice: Voice::default(),
-            speed: 1.0,
-            volume: 1.0,
+            speed: DEFAULT_SPEED,
+            volume: DEFAULT_VOLUME,
+            section_profiles: HashMap::new(),
             max_segment_length: 500,
+            target_segment_duration: None,
+            length_unit: LengthUnit::Chars,
             enable_parallel: false,
             max_parallel: 3,
-            max_retries: 3,
-            retry_delay: Duration::from_millis(100),
+            inter_request_delay: Duration::ZERO,
+            retry_policy: RetryPolicy::default(),
             enable_thinking: false,
             coding_plan: false,
+            max_prompt_chars: DEFAULT_MAX_PROMPT_CHARS,
+            max_prompt_bytes: None,
+            backend: None,
+            split_backend: None,
+            split_cache: None,
+            split_strategy: SplitStrategy::Ai,
+            choice_selection: ChoiceSelection::First,
+            long_token_policy: None,
+            strip_link_urls: false,
+            footnote_policy: None,
+            footnote_bodies: FootnoteBodies::Keep,
+            cjk_latin_spacing: None,
+            symbol_verbalization: false,
+            symbol_language: SymbolLanguage::Chinese,
+            symbol_readings: HashMap::new(),
+            unknown_symbol_threshold: 0,
+            numeric_context: None,
+            segment_overrides: HashMap::new(),
+            timing_sidecar: false,
+            report_detail: None,
+            sidecar_metadata: false,
+            speech_marks: None,
+            speech_mark_sections: false,
+            max_memory: None,
+            parallel_fallback: false,
+            idempotency_keys: true,
+            deterministic: false,
+            failure_bundle: None,
+            failure_bundle_text: false,
+            calibration_file: None,
+            file_template: FileNaming::Index.into(),
+            overwrite_policy: OverwritePolicy::Overwrite,
+            output_preflight: true,
+            voice_probes: Mutex::new(HashMap::new()),
+            strict_validation: false,
+            parameter_warnings: Vec::new(),
+            newline_pauses: None,
+            split_pattern: None,
+            sentence_terminators: DEFAULT_SENTENCE_TERMINATORS.to_vec(),
+            list_awareness: false,
+            bilingual_pauses: (DEFAULT_PAIR_PAUSE, DEFAULT_BETWEEN_PAIRS_PAUSE),
+            min_duration: Duration::ZERO,
+            require_nonsilent: false,
+            target_loudness: None,
+            strict_riff: false,
+            preserve_extra_chunks: false,
+            merge_threads: 0,
+            audio_inspector: None,
+            progress_handler: None,
+            progress_heartbeat: DEFAULT_PROGRESS_HEARTBEAT,
+            queue_depth: DEFAULT_QUEUE_DEPTH,
+            chunk_duration: None,
+            queue_policy: QueuePolicy::Wait,
+            response_handler: None,
+            output_format: None,
+            mode: ModeSelection::Auto,
+            oversize_policy: OversizePolicy::Error,
+            error_mode: ErrorMode::Collect,
+            uniform_segment_duration: None,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            stall_timeout: None,
+            abort_on_stall: false,
+            runtime: None,
+            stats: Arc::new(StatsRecorder::default()),
         }
     }
 
@@ -113,8 +447,41 @@ impl Text2Audio {
         self
     }
 
+    /// Apply a reading profile preset
+    ///
+    /// Sets the voice, speed, volume, maximum segment length and newline
+    /// pauses documented on [`ReadingProfile`]. Settings configured
+    /// afterwards override the profile's values.
+    ///
+    /// # Arguments
+    ///
+    /// * `profile` - Preset to apply
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{ReadingProfile, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_profile(ReadingProfile::Audiobook)
+    ///     .with_speed(1.1);
+    /// ```
+    pub fn with_profile(mut self, profile: ReadingProfile) -> Self {
+        self = self
+            .with_voice(profile.voice())
+            .with_speed(profile.speed())
+            .with_volume(profile.volume())
+            .with_max_segment_length(profile.max_segment_length());
+        self.newline_pauses = profile.newline_pauses();
+        self
+    }
+
     /// Set the speech speed
     ///
+    /// Values outside the range are clamped. NaN and infinite values are
+    /// replaced with the default speed and reported as a warning, or
+    /// rejected under [`with_strict_validation`](Self::with_strict_validation).
+    ///
     /// # Arguments
     ///
     /// * `speed` - Speech speed between 0.5 (slow) and 2.0 (fast)
@@ -128,12 +495,17 @@ impl Text2Audio {
     ///     .with_speed(1.5);
     /// ```
     pub fn with_speed(mut self, speed: f32) -> Self {
-        self.speed = speed.clamp(0.5, 2.0);
+        let speed = self.finite_or_default("speed", speed, DEFAULT_SPEED);
+        self.speed = speed.clamp(*SPEED_RANGE.start(), *SPEED_RANGE.end());
         self
     }
 
     /// Set the speech volume
     ///
+    /// Values outside the range are clamped. NaN and infinite values are
+    /// replaced with the default volume and reported as a warning, or
+    /// rejected under [`with_strict_validation`](Self::with_strict_validation).
+    ///
     /// # Arguments
     ///
     /// * `volume` - Speech volume between 0.0 (silent) and 10.0 (loud)
@@ -147,54 +519,103 @@ impl Text2Audio {
     ///     .with_volume(3.0);
     /// ```
     pub fn with_volume(mut self, volume: f32) -> Self {
-        self.volume = volume.clamp(0.0, 10.0);
+        let volume = self.finite_or_default("volume", volume, DEFAULT_VOLUME);
+        self.volume = volume.clamp(*VOLUME_RANGE.start(), *VOLUME_RANGE.end());
         self
     }
 
-    /// Set the maximum segment length
+    /// Register profiles for named sections of a document
+    ///
+    /// A `<!-- section: name -->` marker in the text switches to the profile
+    /// registered as `name` for every segment up to the next marker, and
+    /// [`DEFAULT_SECTION`] switches back to the converter's own settings
+    /// unless a profile of that name is registered. Markers are never read
+    /// aloud and always end a segment. A marker naming no profile keeps the
+    /// settings in effect and adds [`Warning::UnknownSection`]. Each
+    /// segment's profile is recorded in [`SegmentReport::section`]. A
+    /// non-finite profile speed or volume is replaced by the converter's
+    /// own, with [`Warning::NonFiniteParameter`].
     ///
     /// # Arguments
     ///
-    /// * `max_length` - Maximum length per segment (100-1024 characters)
+    /// * `profiles` - Profiles by section name
     ///
     /// # Examples
     ///
     /// ```
-    /// use text2audio::Text2Audio;
+    /// use std::collections::HashMap;
+    /// use std::time::Duration;
+    /// use text2audio::{SectionProfile, Text2Audio, Voice};
     ///
+    /// let ad = SectionProfile::new()
+    ///     .with_voice(Voice::Xiaochen)
+    ///     .with_speed(1.2)
+    ///     .with_gap(Duration::from_millis(300));
     /// let converter = Text2Audio::new("api_key")
-    ///     .with_max_segment_length(800);
+    ///     .with_section_profiles(HashMap::from([("ad".to_string(), ad)]));
     /// ```
-    pub fn with_max_segment_length(mut self, max_length: usize) -> Self {
-        self.max_segment_length = max_length.clamp(100, 1024);
+    pub fn with_section_profiles(mut self, mut profiles: HashMap<String, SectionProfile>) -> Self {
+        self.forget_non_finite("section speed");
+        self.forget_non_finite("section volume");
+        for profile in profiles.values_mut() {
+            if let Some(speed) = profile.speed.filter(|speed| !speed.is_finite()) {
+                self.remember_non_finite("section speed", speed, self.speed);
+                profile.speed = None;
+            }
+            if let Some(volume) = profile.volume.filter(|volume| !volume.is_finite()) {
+                self.remember_non_finite("section volume", volume, self.volume);
+                profile.volume = None;
+            }
+        }
+        self.section_profiles = profiles;
         self
     }
 
-    /// Enable parallel processing of audio segments
+    /// Insert silence where the text has line breaks
+    ///
+    /// A single newline inserts `short` of silence and a blank line inserts
+    /// `long`, matching the common convention of line breaks for short
+    /// pauses and paragraph breaks for long ones. Every line break becomes a
+    /// segment boundary, so text with many short lines produces more TTS
+    /// requests. Pauses are not inserted by
+    /// [`convert_stream`](Self::convert_stream).
     ///
     /// # Arguments
     ///
-    /// * `max_parallel` - Maximum number of parallel requests (1-10)
+    /// * `short` - Silence for a single newline
+    /// * `long` - Silence for a blank line
     ///
     /// # Examples
     ///
     /// ```
+    /// use std::time::Duration;
     /// use text2audio::Text2Audio;
     ///
     /// let converter = Text2Audio::new("api_key")
-    ///     .with_parallel(5);
+    ///     .with_newline_pauses(Duration::from_millis(300), Duration::from_millis(800));
     /// ```
-    pub fn with_parallel(mut self, max_parallel: usize) -> Self {
-        self.enable_parallel = true;
-        self.max_parallel = max_parallel.clamp(1, 10);
+    pub fn with_newline_pauses(mut self, short: Duration, long: Duration) -> Self {
+        self.newline_pauses = Some((short, long));
         self
     }
 
-    /// Enable thinking mode for AI splitting
+    /// Split the text at every match of a regular expression
+    ///
+    /// Matches are segment boundaries, applied before length-based
+    /// splitting, so each piece is then split on its own to fit
+    /// `max_segment_length`. The pattern is compiled in multi-line mode, so
+    /// `^` and `$` match at line boundaries, as with chapter headings. A
+    /// kept delimiter starts the piece after it; a dropped one is not
+    /// spoken. Text containing a match is always converted in segmented
+    /// mode.
+    ///
+    /// An invalid pattern is reported as [`Error::Config`] by the next
+    /// conversion.
     ///
     /// # Arguments
     ///
-    /// * `enable` - Whether to enable thinking
+    /// * `pattern` - Regular expression whose matches split the text
+    /// * `keep_delimiter` - Whether the matched text is kept and spoken
     ///
     /// # Examples
     ///
@@ -202,18 +623,26 @@ impl Text2Audio {
     /// use text2audio::Text2Audio;
     ///
     /// let converter = Text2Audio::new("api_key")
-    ///     .with_thinking(true);
+    ///     .with_split_regex("^第[一二三四五六七八九十]+章", true);
     /// ```
-    pub fn with_thinking(mut self, enable: bool) -> Self {
-        self.enable_thinking = enable;
+    pub fn with_split_regex(mut self, pattern: &str, keep_delimiter: bool) -> Self {
+        self.split_pattern = Some(SplitPattern::new(pattern, keep_delimiter));
         self
     }
 
-    /// Enable coding plan endpoint
+    /// Set the punctuation marks at which local splitting ends a sentence
+    ///
+    /// Replaces [`DEFAULT_SENTENCE_TERMINATORS`] wherever text is split
+    /// without AI: the rule-based split of a race, the fallback for
+    /// over-long direct input and incremental conversion. Add `；` or `：`
+    /// for finer segments, or leave out `.` for text full of abbreviations.
+    ///
+    /// An empty set is reported as [`Error::Config`] by the next
+    /// conversion.
     ///
     /// # Arguments
     ///
-    /// * `enable` - Whether to enable coding plan
+    /// * `terminators` - Characters that end a sentence
     ///
     /// # Examples
     ///
@@ -221,389 +650,10380 @@ impl Text2Audio {
     /// use text2audio::Text2Audio;
     ///
     /// let converter = Text2Audio::new("api_key")
-    ///     .with_coding_plan(true);
+    ///     .with_sentence_terminators(&['。', '！', '？', '；']);
     /// ```
-    pub fn with_coding_plan(mut self, enable: bool) -> Self {
-        self.coding_plan = enable;
+    pub fn with_sentence_terminators(mut self, terminators: &[char]) -> Self {
+        self.sentence_terminators = terminators.to_vec();
         self
     }
 
-    /// Set retry configuration for API calls
+    /// Keep markdown list items and table rows whole when splitting
+    ///
+    /// Runs of list items and table rows are split locally rather than by
+    /// the AI splitter, one item or row per unit, with units packed into
+    /// segments up to the maximum segment length, so each item is read as
+    /// a whole. Only an item too long for one segment is split at its
+    /// sentences. Prose around the lists is split as before. See
+    /// [`LocalSplitter::with_list_awareness`].
     ///
     /// # Arguments
     ///
-    /// * `max_retries` - Maximum number of retry attempts on failure
-    /// * `retry_delay` - Initial delay between retries (exponential backoff is applied)
+    /// * `enable` - Whether to split structured text by list item
     ///
     /// # Examples
     ///
     /// ```
     /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_coding_plan(true)
+    ///     .with_list_awareness(true);
+    /// ```
+    pub fn with_list_awareness(mut self, enable: bool) -> Self {
+        self.list_awareness = enable;
+        self
+    }
+
+    /// Set the pauses of [`convert_bilingual`](Self::convert_bilingual)
+    ///
+    /// # Arguments
+    ///
+    /// * `within_pair` - Silence between the two texts of a pair (default 400ms)
+    /// * `between_pairs` - Silence after each pair but the last (default 1s)
+    ///
+    /// # Examples
+    ///
+    /// ```
     /// use std::time::Duration;
+    /// use text2audio::Text2Audio;
     ///
     /// let converter = Text2Audio::new("api_key")
-    ///     .with_retry_config(5, Duration::from_millis(200));
+    ///     .with_bilingual_pauses(Duration::from_millis(300), Duration::from_millis(1500));
     /// ```
-    pub fn with_retry_config(mut self, max_retries: u32, retry_delay: Duration) -> Self {
-        self.max_retries = max_retries;
-        self.retry_delay = retry_delay;
+    pub fn with_bilingual_pauses(mut self, within_pair: Duration, between_pairs: Duration) -> Self {
+        self.bilingual_pauses = (within_pair, between_pairs);
         self
     }
 
-    /// Convert text to audio file
+    /// Set the minimum duration of a successful conversion's audio
     ///
-    /// Automatically determines whether to use segmented or direct mode
-    /// based on text length. AI splitting is used when needed.
+    /// A conversion whose merged audio has no frames, or is shorter than
+    /// `duration`, fails with [`Error::Audio`] naming what removed the
+    /// content, of kind [`AudioErrorKind::Empty`] or
+    /// [`AudioErrorKind::TooShort`], and the output file is deleted.
+    /// Inserted pauses count toward
+    /// the duration. Defaults to zero, which only rejects empty audio.
     ///
     /// # Arguments
     ///
-    /// * `text` - Input text to convert
-    /// * `output_path` - Output WAV file path
+    /// * `duration` - Shortest acceptable output
     ///
-    /// # Errors
+    /// # Examples
     ///
-    /// Returns error if text processing, API calls, or audio processing fail.
-    pub async fn convert(&self, text: &str, output_path: &str) -> Result<()> {
-        let text = text.trim();
-        if text.is_empty() {
-            return Err(Error::EmptyInput);
-        }
-
-        let char_count = text.chars().count();
-
-        if char_count <= self.max_segment_length {
-            self.convert_direct(text, output_path).await
-        } else {
-            self.convert_segmented(text, output_path).await
-        }
+    /// ```
+    /// use std::time::Duration;
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_min_duration(Duration::from_millis(200));
+    /// ```
+    pub fn with_min_duration(mut self, duration: Duration) -> Self {
+        self.min_duration = duration;
+        self
     }
 
-    async fn convert_direct(&self, text: &str, output_path: &str) -> Result<()> {
-        let audio_bytes = self.text_to_audio_with_retry(text).await?;
-        AudioMerger::save_single(&audio_bytes, output_path).await
+    /// Reject output whose audio is silent
+    ///
+    /// All-silent output almost always means an upstream failure, such as
+    /// segments that decoded to silence, rather than a successful
+    /// conversion. When enabled, a conversion whose merged audio never
+    /// exceeds [`NONSILENT_MIN_PEAK`] fails with
+    /// an [`Error::Audio`] of kind [`AudioErrorKind::Silent`], with the
+    /// message "output is silent", and no output is written. The peak is
+    /// taken from the samples decoded during the in-memory merge, so the
+    /// check reads nothing back from disk. It is skipped when a memory limit
+    /// forces an incremental merge on disk.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to reject silent output
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_require_nonsilent(true);
+    /// ```
+    pub fn with_require_nonsilent(mut self, enable: bool) -> Self {
+        self.require_nonsilent = enable;
+        self
     }
 
-    async fn convert_segmented(&self, text: &str, output_path: &str) -> Result<()> {
-        let splitter = AiSplitter::new(self.api_key.clone(), self.model, self.max_segment_length)
-            .with_thinking(self.enable_thinking)
-            .with_coding_plan(self.coding_plan);
-
-        let segments = splitter.split(text).await?;
-
-        if segments.is_empty() {
-            return Err(Error::EmptyInput);
-        }
-
-        let audio_segments = if self.enable_parallel {
-            self.collect_audio_parallel(&segments).await?
-        } else {
-            self.collect_audio_sequential(&segments).await?
-        };
-
-        AudioMerger::merge(audio_segments, output_path).await
+    /// Normalize the loudness of each conversion's audio to a target
+    ///
+    /// The integrated loudness of the merged audio is measured per ITU-R
+    /// BS.1770 and a single gain brings it to `lufs`, so documents and
+    /// voices come out equally loud. A limiter keeps true peaks at or below
+    /// [`loudness::TRUE_PEAK_CEILING`], so a target too loud for the
+    /// material ends up slightly quieter than asked rather than clipping.
+    /// Audio too short or quiet to measure is written unchanged with
+    /// [`Warning::LoudnessNotNormalized`], or rejected under
+    /// [`with_strict_validation`](Self::with_strict_validation). The pass
+    /// runs on the samples decoded during the in-memory merge, so it cannot
+    /// be combined with [`with_max_memory`](Self::with_max_memory).
+    ///
+    /// Values outside the range are clamped. NaN and infinite values are
+    /// replaced with [`loudness::DEFAULT_TARGET_LOUDNESS`] and reported as a
+    /// warning, or rejected under
+    /// [`with_strict_validation`](Self::with_strict_validation).
+    ///
+    /// # Arguments
+    ///
+    /// * `lufs` - Target integrated loudness between -70.0 and 0.0 LUFS
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// // A common target for podcasts
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_target_loudness(-16.0);
+    /// ```
+    pub fn with_target_loudness(mut self, lufs: f32) -> Self {
+        let lufs = self.finite_or_default("target loudness", lufs, DEFAULT_TARGET_LOUDNESS);
+        self.target_loudness =
+            Some(lufs.clamp(*TARGET_LOUDNESS_RANGE.start(), *TARGET_LOUDNESS_RANGE.end()));
+        self
     }
 
-    async fn text_to_audio_with_retry(&self, text: &str) -> Result<Vec<u8>> {
-        let mut last_error = None;
-
-        for attempt in 0..self.max_retries {
-            match self.try_convert(text).await {
-                Ok(audio) => return Ok(audio),
-                Err(e) => {
-                    last_error = Some(e);
-                    if attempt < self.max_retries - 1 {
-                        let delay = self.retry_delay * 2_u32.pow(attempt);
-                        tokio::time::sleep(delay).await;
-                    }
-                }
-            }
-        }
-
-        Err(last_error.unwrap_or_else(|| Error::TtsApi("Unknown error".to_string())))
+    /// Validate the RIFF structure of the output after writing it
+    ///
+    /// Output is always written with the pad byte RIFF requires after an
+    /// odd-sized data chunk. With strict checking enabled, the finished WAV
+    /// is also parsed back, and a conversion whose RIFF size or chunk
+    /// alignment is wrong fails with [`Error::Audio`] and its output file is
+    /// deleted.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to validate the output
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_strict_riff(true);
+    /// ```
+    pub fn with_strict_riff(mut self, enable: bool) -> Self {
+        self.strict_riff = enable;
+        self
     }
 
-    async fn try_convert(&self, text: &str) -> Result<Vec<u8>> {
-        let tts_config = TtsConfig {
-            voice: self.voice.as_tts_voice(),
-            speed: self.speed,
-            volume: self.volume,
-        };
-
-        let client = Client::new(self.api_key.clone());
-        client
-            .text_to_audio(text, &tts_config)
-            .await
-            .map_err(|e| Error::TtsApi(format!("TTS request failed: {}", e)))
+    /// Keep extra chunks from the provider's WAV in direct-mode output
+    ///
+    /// Output is normally re-encoded with only `fmt ` and `data` chunks.
+    /// When enabled, a conversion written from a single TTS response copies
+    /// the response's other chunks, such as `LIST`, `bext` or `fact`,
+    /// verbatim after the data chunk. Segmented output is merged from
+    /// several responses and always discards extra chunks, as does direct
+    /// output when an option applied while merging, such as
+    /// [`with_uniform_segment_duration`](Self::with_uniform_segment_duration),
+    /// is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to preserve extra chunks
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_preserve_extra_chunks(true);
+    /// ```
+    pub fn with_preserve_extra_chunks(mut self, enable: bool) -> Self {
+        self.preserve_extra_chunks = enable;
+        self
     }
 
-    async fn collect_audio_sequential(&self, segments: &[String]) -> Result<Vec<Vec<u8>>> {
-        let mut audio_segments = Vec::new();
-
-        for segment in segments {
-            let audio_bytes = self.text_to_audio_with_retry(segment).await?;
-            audio_segments.push(audio_bytes);
-        }
-
-        Ok(audio_segments)
+    /// Set the format of converted audio
+    ///
+    /// Applies to [`convert`](Self::convert),
+    /// [`convert_with_report`](Self::convert_with_report),
+    /// [`convert_to_bytes`](Self::convert_to_bytes) and
+    /// [`convert_to_base64`](Self::convert_to_base64). Formats other than WAV
+    /// are encoded from an in-memory WAV, so the memory limit of
+    /// [`with_max_memory`](Self::with_max_memory) does not apply to them.
+    ///
+    /// By default the format follows the extension of the output path, and
+    /// is WAV for in-memory output, for paths without an extension, and,
+    /// with [`Warning::UnknownOutputExtension`], for unsupported extensions.
+    /// A configured format always wins; if the extension names another
+    /// format, the report carries [`Warning::OutputFormatMismatch`].
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Format of the output
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{OutputFormat, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_output_format(OutputFormat::Mp3 { bitrate_kbps: 64 });
+    /// ```
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = Some(format);
+        self
     }
 
-    async fn collect_audio_parallel(&self, segments: &[String]) -> Result<Vec<Vec<u8>>> {
-        let api_key = self.api_key.clone();
-        let speed = self.speed;
-        let volume = self.volume;
-        let voice = self.voice.as_tts_voice();
-        let max_retries = self.max_retries;
-        let retry_delay = self.retry_delay;
-        let max_parallel = self.max_parallel;
-
-        let results = stream::iter(segments)
-            .map(move |segment| {
-                let api_key = api_key.clone();
-                let segment = segment.clone();
-                let voice = voice.clone();
-
-                async move {
-                    let tts_config = TtsConfig {
-                        voice: voice.clone(),
-                        speed,
-                        volume,
-                    };
-
-                    let mut last_error: Option<Error> = None;
-                    for attempt in 0..max_retries {
-                        let client = Client::new(api_key.clone());
-                        match client.text_to_audio(&segment, &tts_config).await {
-                            Ok(bytes) => return Ok::<Vec<u8>, Error>(bytes),
-                            Err(e) => {
-                                last_error =
-                                    Some(Error::TtsApi(format!("Retry {}: {}", attempt, e)));
-                                if attempt < max_retries - 1 {
-                                    tokio::time::sleep(retry_delay * 2_u32.pow(attempt)).await;
-                                }
-                            }
-                        }
-                    }
-                    if let Some(e) = last_error {
-                        Err(e)
-                    } else {
-                        Err(Error::TtsApi("All retry attempts failed".to_string()))
-                    }
-                }
-            })
-            .buffer_unordered(max_parallel)
-            .collect::<Vec<_>>()
-            .await;
+    /// Force direct or segmented mode regardless of text length
+    ///
+    /// [`ModeSelection::Auto`], the default, converts text within the
+    /// max segment length directly and splits longer text.
+    /// [`ModeSelection::ForceDirect`] sends any text in a single request,
+    /// failing if it exceeds [`TTS_MAX_CHARS`]; newline pauses are not
+    /// inserted. [`ModeSelection::ForceSegmented`] always runs the splitter
+    /// and merge, even for text that yields one segment.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - Mode selection
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{ModeSelection, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_mode(ModeSelection::ForceSegmented);
+    /// ```
+    pub fn with_mode(mut self, mode: ModeSelection) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set how segments longer than a TTS request accepts are handled
+    ///
+    /// Checked right before a segment is sent, as a last line of defense
+    /// against segments that bypassed splitting, such as overrides or
+    /// edited plans. By default such a segment fails with
+    /// [`Error::Config`] instead of an opaque API error.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Handling of segments over [`TTS_MAX_CHARS`] characters
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{OversizePolicy, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_oversize_policy(OversizePolicy::AutoSplit);
+    /// ```
+    pub fn with_oversize_policy(mut self, policy: OversizePolicy) -> Self {
+        self.oversize_policy = policy;
+        self
+    }
+
+    /// Set how batch operations surface errors
+    ///
+    /// Applies to [`convert_many`](Self::convert_many). By default every
+    /// item runs and its result is returned ([`ErrorMode::Collect`]);
+    /// [`ErrorMode::FailFast`] stops at the first failure and
+    /// [`ErrorMode::Aggregate`] returns all failures as one
+    /// [`Error::Multiple`].
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - Error handling for batch operations
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{ErrorMode, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_error_mode(ErrorMode::FailFast);
+    /// ```
+    pub fn with_error_mode(mut self, mode: ErrorMode) -> Self {
+        self.error_mode = mode;
+        self
+    }
+
+    /// Pad every segment with trailing silence to the same duration
+    ///
+    /// Each segment shorter than `duration` is followed by silence up to
+    /// it, before any pause, so segments start on a fixed grid, for
+    /// example one phrase per time slot on language-learning cards.
+    /// Padding is exact to the frame and applies to all channels. A longer
+    /// segment is written in full and reported with a
+    /// [`Warning::SegmentExceedsUniformDuration`].
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - Length of each segment's slot
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_uniform_segment_duration(Duration::from_secs(3));
+    /// ```
+    pub fn with_uniform_segment_duration(mut self, duration: Duration) -> Self {
+        self.uniform_segment_duration = Some(duration);
+        self
+    }
+
+    /// Set how long to wait for another process's lock on a project
+    ///
+    /// [`run_project`](Self::run_project) locks the project so concurrent
+    /// workers do not corrupt each other's checkpoints. If the lock is still
+    /// held after `timeout`, the run proceeds read-only: it uses completed
+    /// segments but neither saves the project nor stores new audio. Locks
+    /// left behind by crashed processes are detected and taken over.
+    /// Defaults to 30 seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Longest wait for a held lock
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_lock_timeout(Duration::from_secs(5));
+    /// ```
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// Watch synthesis for stretches without progress
+    ///
+    /// A hung connection can leave a conversion waiting forever without an
+    /// error. When no segment completes, retries or writes output for
+    /// `timeout`, a [`Warning::Stalled`] lists the segments in flight with
+    /// their elapsed time and attempts. See
+    /// [`with_abort_on_stall`](Self::with_abort_on_stall) to fail instead.
+    /// Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Longest stretch without progress before warning
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_stall_timeout(Duration::from_secs(120))
+    ///     .with_abort_on_stall(true);
+    /// ```
+    pub fn with_stall_timeout(mut self, timeout: Duration) -> Self {
+        self.stall_timeout = Some(timeout);
+        self
+    }
+
+    /// Fail a stalled conversion instead of only warning
+    ///
+    /// With a [stall timeout](Self::with_stall_timeout) set, a conversion
+    /// that makes no progress for a second timeout after the warning is
+    /// cancelled with [`Error::Stalled`].
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to abort stalled conversions (default `false`)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_stall_timeout(Duration::from_secs(120))
+    ///     .with_abort_on_stall(true);
+    /// ```
+    pub fn with_abort_on_stall(mut self, enable: bool) -> Self {
+        self.abort_on_stall = enable;
+        self
+    }
+
+    /// Report the progress of synthesis to a callback
+    ///
+    /// The handler receives a [`ProgressEvent`] each time a segment is
+    /// synthesized, and a heartbeat whenever the
+    /// [heartbeat interval](Self::with_progress_heartbeat) passes without
+    /// one, so a progress bar keeps moving during a long segment. Events
+    /// carry the throughput over the last
+    /// [`THROUGHPUT_WINDOW`](progress::THROUGHPUT_WINDOW) and an estimated
+    /// time remaining, which assumes a segment already retrying fails once
+    /// more. Both are `None` until
+    /// [`MIN_ETA_SAMPLES`](progress::MIN_ETA_SAMPLES) segments are done.
+    ///
+    /// Splitting with the AI splitter reports too: a
+    /// [`ProgressKind::SplitStarted`] event, then an event as each chunk of
+    /// the text is sent and parsed. Their `total` is zero, as the segments
+    /// are not known yet.
+    ///
+    /// Progress is reported by the methods that synthesize a whole text or
+    /// plan at once; streaming, incremental and project conversions report
+    /// none. The handler runs on the converting task, so it should return
+    /// quickly.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - Callback receiving each progress event
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key").with_progress_handler(|event| {
+    ///     match event.eta {
+    ///         Some(eta) => println!("{}/{}, {:.0}s left", event.completed, event.total, eta.as_secs_f64()),
+    ///         None => println!("{}/{}", event.completed, event.total),
+    ///     }
+    /// });
+    /// ```
+    pub fn with_progress_handler(
+        mut self,
+        handler: impl Fn(&ProgressEvent) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Set the interval of progress heartbeats
+    ///
+    /// A [`ProgressKind::Heartbeat`] event is sent to the
+    /// [progress handler](Self::with_progress_handler) whenever this long
+    /// passes without another event.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - Longest time without an event (default 1 second);
+    ///   zero disables heartbeats
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_progress_handler(|event| println!("{:?}", event))
+    ///     .with_progress_heartbeat(Duration::from_millis(250));
+    /// ```
+    pub fn with_progress_heartbeat(mut self, interval: Duration) -> Self {
+        self.progress_heartbeat = interval;
+        self
+    }
+
+    /// Set how many segments an incremental input queues for synthesis
+    ///
+    /// Once the queue is full, [`IncrementalInput::push`] waits for room
+    /// or fails, depending on the [queue policy](Self::with_queue_policy).
+    /// This keeps a fast producer, such as a streaming LLM, from growing
+    /// memory without bound while synthesis lags behind.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - Segments queued at most (default 32, at least 1)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key").with_queue_depth(8);
+    /// ```
+    pub fn with_queue_depth(mut self, depth: usize) -> Self {
+        self.queue_depth = depth.max(1);
+        self
+    }
+
+    /// Set the longest chunk yielded by
+    /// [`convert_stream_chunks`](Self::convert_stream_chunks)
+    ///
+    /// Long segments are sliced into chunks of this duration, so a player
+    /// buffering ahead receives audio in even steps rather than whole
+    /// segments. Chunks hold at least one frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - Longest chunk (default: one chunk per segment)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key").with_chunk_duration(Duration::from_secs(5));
+    /// ```
+    pub fn with_chunk_duration(mut self, duration: Duration) -> Self {
+        self.chunk_duration = Some(duration);
+        self
+    }
+
+    /// Set what an incremental input does when its queue is full
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - [`QueuePolicy::Wait`] (default) to wait for room, or
+    ///   [`QueuePolicy::Error`] to reject the chunk with
+    ///   [`Error::QueueFull`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{QueuePolicy, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_queue_depth(8)
+    ///     .with_queue_policy(QueuePolicy::Error);
+    /// ```
+    pub fn with_queue_policy(mut self, policy: QueuePolicy) -> Self {
+        self.queue_policy = policy;
+        self
+    }
+
+    /// Run a callback on the audio of each conversion before it is written
+    ///
+    /// The inspector receives the decoded samples held in memory, so
+    /// analyses such as silence maps or speech rate estimates need not read
+    /// the output back from disk. It only gets shared access to the samples
+    /// and cannot change what is written. A panic in the inspector is caught
+    /// and reported as [`Warning::InspectorPanic`]. The inspector is not run
+    /// when a memory limit forces an incremental merge on disk.
+    ///
+    /// # Arguments
+    ///
+    /// * `inspector` - Callback receiving the conversion's audio
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key").with_audio_inspector(|audio| {
+    ///     let silent = audio.frames().filter(|frame| frame[0] == 0).count();
+    ///     println!("{} silent frames in {:?}", silent, audio.duration());
+    /// });
+    /// ```
+    pub fn with_audio_inspector(
+        mut self,
+        inspector: impl Fn(&PcmAudio) + Send + Sync + 'static,
+    ) -> Self {
+        self.audio_inspector = Some(Arc::new(inspector));
+        self
+    }
+
+    /// Transform each raw TTS response body before it is checked
+    ///
+    /// An escape hatch for non-standard endpoints, such as gateways that
+    /// wrap the audio in an envelope. The handler receives the response
+    /// bytes exactly as returned and its output replaces them, so it must
+    /// be valid WAV audio; an error it returns fails the request like any
+    /// TTS error. By default the body is used as is. Applies to the built-in
+    /// client only, not to a backend set with
+    /// [`with_backend`](Self::with_backend).
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - Transform from response body to WAV bytes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{Error, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key").with_response_handler(|body| {
+    ///     body.strip_prefix(b"ENVELOPE")
+    ///         .map(<[u8]>::to_vec)
+    ///         .ok_or_else(|| Error::TtsApi("missing envelope".to_string()))
+    /// });
+    /// ```
+    pub fn with_response_handler(
+        mut self,
+        handler: impl Fn(&[u8]) -> Result<Vec<u8>> + Send + Sync + 'static,
+    ) -> Self {
+        self.response_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Set the number of threads decoding and converting segments during merge
+    ///
+    /// Segments are decoded and resampled on a rayon pool, while the output
+    /// is still written sequentially in segment order, so the result is
+    /// identical to a sequential merge. `0`, the default, uses the available
+    /// parallelism and `1` merges sequentially. Incremental merges under
+    /// [`with_max_memory`](Self::with_max_memory) are always sequential.
+    ///
+    /// # Arguments
+    ///
+    /// * `threads` - Number of decoding threads
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_merge_threads(4);
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn with_merge_threads(mut self, threads: usize) -> Self {
+        self.merge_threads = threads;
+        self
+    }
+
+    /// Spawn background tasks on `handle` instead of the ambient runtime
+    ///
+    /// Lets applications that run network I/O on a dedicated runtime keep
+    /// the converter's tasks there. This covers the tasks APIs spawn, such
+    /// as the player of `convert_and_play_streaming`, and the blocking
+    /// merge and write work of every conversion, which runs on the blocking
+    /// pool of `handle`. Requests themselves are still driven by whichever
+    /// runtime polls the conversion.
+    ///
+    /// # Arguments
+    ///
+    /// * `handle` - Runtime to spawn tasks on
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use text2audio::Text2Audio;
+    ///
+    /// let io_runtime = tokio::runtime::Runtime::new().unwrap();
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_runtime_handle(io_runtime.handle().clone());
+    /// ```
+    pub fn with_runtime_handle(mut self, handle: tokio::runtime::Handle) -> Self {
+        self.runtime = Some(handle);
+        self
+    }
+
+    /// Runtime that background tasks are spawned on
+    ///
+    /// The handle set with [`with_runtime_handle`](Self::with_runtime_handle),
+    /// or else the runtime of the calling task.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no handle was set and this is called outside a tokio
+    /// runtime, so APIs that spawn must be used from within one.
+    pub fn runtime_handle(&self) -> tokio::runtime::Handle {
+        self.runtime
+            .clone()
+            .unwrap_or_else(tokio::runtime::Handle::current)
+    }
+
+    /// Reject invalid settings instead of substituting defaults
+    ///
+    /// By default a NaN or infinite numeric setting is replaced with its
+    /// default, and a feature the AI model does not support is left off,
+    /// each reported as a warning in the conversion report. In strict mode
+    /// conversions fail with [`Error::Config`] instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to reject invalid settings
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_strict_validation(true);
+    /// ```
+    pub fn with_strict_validation(mut self, enable: bool) -> Self {
+        self.strict_validation = enable;
+        self
+    }
+
+    /// Set the maximum segment length
+    ///
+    /// # Arguments
+    ///
+    /// * `max_length` - Maximum length per segment (100-1024 characters)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_max_segment_length(800);
+    /// ```
+    pub fn with_max_segment_length(mut self, max_length: usize) -> Self {
+        self.max_segment_length = max_length.clamp(100, TTS_MAX_CHARS);
+        self
+    }
+
+    /// Split into segments of about `duration` of audio each
+    ///
+    /// The duration is turned into a character budget at the speech rate
+    /// [`estimate`](Self::estimate) uses, calibrated for the current voice
+    /// and speed if a [calibration file](Self::with_calibration_file) is
+    /// set, and the budget replaces the maximum segment length, up to
+    /// [`TTS_MAX_CHARS`]. The rate is per character, so the budget assumes
+    /// [`LengthUnit::Chars`].
+    ///
+    /// After synthesis, each segment's target is recorded in
+    /// [`SegmentReport::target_duration`], and a segment longer than the
+    /// target by more than [`SEGMENT_DURATION_TOLERANCE`] adds
+    /// [`Warning::SegmentOverDuration`]. Under
+    /// [`with_strict_validation`](Self::with_strict_validation), such
+    /// segments are instead split again at sentence boundaries, sized at the
+    /// rate they were spoken at, and resynthesized; a segment holding a
+    /// single sentence is kept and only warned about. Re-splitting
+    /// needs the segments in memory, so conversions under a memory limit
+    /// only warn.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - Target duration of each segment's audio
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use text2audio::Text2Audio;
+    ///
+    /// // Chunks of at most about 45 seconds for streaming
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_target_segment_duration(Duration::from_secs(45));
+    /// ```
+    pub fn with_target_segment_duration(mut self, duration: Duration) -> Self {
+        self.target_segment_duration = Some(duration);
+        self
+    }
+
+    /// Set the unit in which the maximum segment length is measured
+    ///
+    /// 100 characters hold a lot of Chinese but barely one English sentence,
+    /// so character limits make Latin-script text choppy.
+    /// [`LengthUnit::Words`] measures segments in whitespace-delimited words
+    /// instead, while still keeping each segment within the TTS request
+    /// limit of [`TTS_MAX_CHARS`] characters. Defaults to
+    /// [`LengthUnit::Chars`].
+    ///
+    /// # Arguments
+    ///
+    /// * `unit` - Unit of `max_segment_length`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{LengthUnit, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_length_unit(LengthUnit::Words)
+    ///     .with_max_segment_length(120);
+    /// ```
+    pub fn with_length_unit(mut self, unit: LengthUnit) -> Self {
+        self.length_unit = unit;
+        self
+    }
+
+    /// Enable parallel processing of audio segments
+    ///
+    /// # Arguments
+    ///
+    /// * `max_parallel` - Maximum number of parallel requests (1-10)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_parallel(5);
+    /// ```
+    pub fn with_parallel(mut self, max_parallel: usize) -> Self {
+        self.enable_parallel = true;
+        self.max_parallel = max_parallel.clamp(1, 10);
+        self
+    }
+
+    /// Wait between consecutive requests of sequential synthesis and AI
+    /// splitting
+    ///
+    /// A lighter alternative to rate limiting for batch jobs that should
+    /// go easy on the API. The delay is not applied before the first
+    /// request or after the last, nor after a segment restored from a
+    /// [project](Self::run_project) checkpoint or a split chunk that needed
+    /// no request. Parallel synthesis is not delayed, but streaming and
+    /// incremental conversions, which synthesize one segment at a time, are.
+    /// Dropping the conversion future cancels a pending delay.
+    ///
+    /// # Arguments
+    ///
+    /// * `delay` - Time between the end of one request and the start of
+    ///   the next (default zero)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_inter_request_delay(Duration::from_millis(500));
+    /// ```
+    pub fn with_inter_request_delay(mut self, delay: Duration) -> Self {
+        self.inter_request_delay = delay;
+        self
+    }
+
+    /// Enable thinking mode for AI splitting
+    ///
+    /// Left off with a [`Warning::UnsupportedModelFeature`], or rejected
+    /// under [`with_strict_validation`](Self::with_strict_validation), for
+    /// models that do not support it; see [`MODEL_CAPABILITIES`].
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to enable thinking
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_thinking(true);
+    /// ```
+    pub fn with_thinking(mut self, enable: bool) -> Self {
+        self.enable_thinking = enable;
+        self
+    }
+
+    /// Enable coding plan endpoint
+    ///
+    /// Left off with a [`Warning::UnsupportedModelFeature`], or rejected
+    /// under [`with_strict_validation`](Self::with_strict_validation), for
+    /// models that do not support it; see [`MODEL_CAPABILITIES`].
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to enable coding plan
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_coding_plan(true);
+    /// ```
+    pub fn with_coding_plan(mut self, enable: bool) -> Self {
+        self.coding_plan = enable;
+        self
+    }
+
+    /// Set the maximum characters of a prompt sent for AI splitting
+    ///
+    /// A rendered prompt above the limit fails with [`Error::AiApi`] before
+    /// the request is sent. Defaults to
+    /// [`DEFAULT_MAX_PROMPT_CHARS`](ai_splitter::DEFAULT_MAX_PROMPT_CHARS).
+    ///
+    /// # Arguments
+    ///
+    /// * `max_chars` - Largest prompt to send, in characters
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_max_prompt_chars(20_000);
+    /// ```
+    pub fn with_max_prompt_chars(mut self, max_chars: usize) -> Self {
+        self.max_prompt_chars = max_chars;
+        self
+    }
+
+    /// Set the maximum UTF-8 bytes of a prompt sent for AI splitting
+    ///
+    /// Text that would exceed the limit is cut into chunks, preferably at
+    /// sentence ends, and each chunk is split with its own request. Use this
+    /// when a gateway rejects large request bodies; CJK text takes about
+    /// three bytes per character. No limit by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_bytes` - Largest prompt to send, in bytes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_max_prompt_bytes(64 * 1024);
+    /// ```
+    pub fn with_max_prompt_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_prompt_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Set retry configuration for API calls
+    ///
+    /// `max_retries` counts the attempts after the first, so each request is
+    /// tried up to `max_retries + 1` times. 0 disables retries: each request
+    /// is attempted once and its error is returned as is, for callers that
+    /// retry at a higher layer. The default is 2 retries.
+    ///
+    /// Shorthand for [`with_retry_policy`](Self::with_retry_policy) with
+    /// [`RetryPolicy::new`], replacing any policy set before.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_retries` - Maximum number of retries after the first attempt
+    /// * `retry_delay` - Initial delay between retries (exponential backoff is applied)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    /// use std::time::Duration;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_retry_config(5, Duration::from_millis(200));
+    /// ```
+    pub fn with_retry_config(self, max_retries: u32, retry_delay: Duration) -> Self {
+        self.with_retry_policy(RetryPolicy::new(max_retries, retry_delay))
+    }
+
+    /// Set how failed TTS and split requests are retried
+    ///
+    /// The policy covers every remote call of a conversion. Progress ETAs
+    /// allow for its delays.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Retries, backoff, jitter, budget and which errors to retry
+    ///   (default [`RetryPolicy::default`])
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{Error, RetryPolicy, Text2Audio};
+    /// use std::time::Duration;
+    ///
+    /// let converter = Text2Audio::new("api_key").with_retry_policy(
+    ///     RetryPolicy::new(5, Duration::from_millis(200))
+    ///         .with_max_delay(Duration::from_secs(5))
+    ///         .with_jitter(0.3)
+    ///         .with_classifier(Error::is_transport),
+    /// );
+    /// ```
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Set a custom TTS backend
+    ///
+    /// By default audio is synthesized with the Zhipu AI [`Client`].
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - Backend used for every TTS request
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use text2audio::{Client, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_backend(Arc::new(Client::new("other_key")));
+    /// ```
+    pub fn with_backend(mut self, backend: Arc<dyn TtsBackend>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Set a custom AI splitting backend
+    ///
+    /// By default text is split with an [`AiSplitter`] using the
+    /// converter's model, segment length and retry policy, which retries
+    /// each chunk of a long text on its own. A custom backend is retried
+    /// as a whole under the [retry policy](Self::with_retry_policy).
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - Backend used for every AI split request
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use text2audio::{AiSplitter, Model, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key").with_split_backend(Arc::new(
+    ///     AiSplitter::new("other_key", Model::GLM4_7, 300),
+    /// ));
+    /// ```
+    pub fn with_split_backend(mut self, backend: Arc<dyn SplitBackend>) -> Self {
+        self.split_backend = Some(backend);
+        self
+    }
+
+    /// Cache AI split results on disk
+    ///
+    /// Splits are keyed on a hash of the text, model, segment length, system
+    /// prompt and splitter settings, so converting the same text again
+    /// makes no chat requests. A custom split backend is cached under the
+    /// same key. Cache failures never fail a conversion. See
+    /// [`AiSplitter::with_split_cache`].
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Directory of the cache, created on the first write
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key").with_split_cache("split-cache");
+    /// ```
+    pub fn with_split_cache(mut self, dir: impl AsRef<Path>) -> Self {
+        self.split_cache = Some(SplitCache::new(dir));
+        self
+    }
+
+    /// Set how text longer than the segment length is split
+    ///
+    /// [`SplitStrategy::Race`] avoids waiting for the AI splitter when the
+    /// rule-based split is good enough. The outcome of each race is
+    /// recorded in [`ConversionReport::split_races`].
+    ///
+    /// # Arguments
+    ///
+    /// * `strategy` - Splitting strategy (default [`SplitStrategy::Ai`])
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use text2audio::{SplitStrategy, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key").with_split_strategy(SplitStrategy::Race {
+    ///     deadline: Duration::from_secs(3),
+    /// });
+    /// ```
+    pub fn with_split_strategy(mut self, strategy: SplitStrategy) -> Self {
+        self.split_strategy = strategy;
+        self
+    }
+
+    /// Set which choice of an AI split response is used
+    ///
+    /// When the API is configured to return alternative completions,
+    /// [`ChoiceSelection::BestFit`] picks the one whose segments best fit
+    /// the segment length instead of the first.
+    ///
+    /// # Arguments
+    ///
+    /// * `selection` - Choice selection (default [`ChoiceSelection::First`])
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{ChoiceSelection, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key").with_choice_selection(ChoiceSelection::BestFit);
+    /// ```
+    pub fn with_choice_selection(mut self, selection: ChoiceSelection) -> Self {
+        self.choice_selection = selection;
+        self
+    }
+
+    /// Rewrite over-long unbreakable tokens before splitting
+    ///
+    /// Tokens are maximal runs without whitespace or CJK characters, such as
+    /// URLs or base64 blobs. Tokens longer than `max_chars` are rewritten with
+    /// `policy` (URLs are read as "一个链接") so that splitting never cuts
+    /// them mid-token. Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_chars` - Longest token kept as-is
+    /// * `policy` - How to rewrite longer tokens
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{LongTokenPolicy, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_long_token_policy(80, LongTokenPolicy::SpellFirst(10));
+    /// ```
+    pub fn with_long_token_policy(mut self, max_chars: usize, policy: LongTokenPolicy) -> Self {
+        self.long_token_policy = Some((max_chars.max(1), policy));
+        self
+    }
+
+    /// Read markdown links as their text and images as their alt text
+    ///
+    /// Without this, `[文档](https://example.com/...)` is read with its raw
+    /// URL, and the URL counts toward `max_segment_length`. With it, links
+    /// keep only their text and images only their alt text, or are dropped
+    /// if they have none, before splitting. Backtick code spans are left
+    /// untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to strip link and image URLs (default `false`)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key").with_strip_link_urls(true);
+    /// ```
+    pub fn with_strip_link_urls(mut self, enable: bool) -> Self {
+        self.strip_link_urls = enable;
+        self
+    }
+
+    /// Handle footnote and reference markers such as `[12]` or `¹`
+    ///
+    /// Academic and technical text is full of markers that are read aloud as
+    /// noise. `policy` strips them, reads them ("参考文献一", "注二") in the
+    /// [symbol language](Self::with_symbol_language), or keeps them.
+    /// Superscripts after Latin letters or digits are left to
+    /// [symbol verbalization](Self::with_symbol_verbalization) as powers.
+    /// Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - What to do with inline markers
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{FootnotePolicy, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_footnote_policy(FootnotePolicy::Verbalize);
+    /// ```
+    pub fn with_footnote_policy(mut self, policy: FootnotePolicy) -> Self {
+        self.footnote_policy = Some(policy);
+        self
+    }
+
+    /// Skip footnote bodies or move them to the end of the text
+    ///
+    /// Footnote bodies are lines starting with a marker, such as
+    /// "[1] 张三，2020。". Moved bodies are read after all other text under
+    /// a "注释" heading ("Notes" in English). Markers starting the bodies
+    /// follow the [footnote policy](Self::with_footnote_policy).
+    ///
+    /// # Arguments
+    ///
+    /// * `bodies` - What to do with footnote bodies (default [`FootnoteBodies::Keep`])
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{FootnoteBodies, FootnotePolicy, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_footnote_policy(FootnotePolicy::Strip)
+    ///     .with_footnote_bodies(FootnoteBodies::MoveToEnd);
+    /// ```
+    pub fn with_footnote_bodies(mut self, bodies: FootnoteBodies) -> Self {
+        self.footnote_bodies = bodies;
+        self
+    }
+
+    /// Normalize spacing between CJK and Latin text
+    ///
+    /// "使用GPU加速" and "使用 GPU 加速" are paced differently by the TTS
+    /// engine, so mixed input reads inconsistently. This inserts or removes
+    /// a single space at every CJK/Latin boundary, after all other
+    /// preprocessing, so the whole document follows one convention.
+    /// Backtick code spans and URLs are left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - Whether to insert or remove boundary spaces
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{SpacingMode, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_cjk_latin_spacing(SpacingMode::Insert);
+    /// ```
+    pub fn with_cjk_latin_spacing(mut self, mode: SpacingMode) -> Self {
+        self.cjk_latin_spacing = Some(mode);
+        self
+    }
+
+    /// Replace math and technical symbols with spoken readings
+    ///
+    /// TTS engines skip or garble text such as "x²+y²=r²" or "25℃". With
+    /// this enabled, preprocessing reads symbols from a built-in table
+    /// ("≈" becomes "约等于", "℃" becomes "摄氏度"), superscripts as powers
+    /// ("x²" becomes "x的平方") and subscript digits as plain digits.
+    /// Backtick code spans are left untouched. Symbols without a reading
+    /// are kept; see
+    /// [`with_unknown_symbol_threshold`](Self::with_unknown_symbol_threshold).
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to verbalize symbols (default `false`)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{SymbolLanguage, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_symbol_verbalization(true)
+    ///     .with_symbol_language(SymbolLanguage::English);
+    /// ```
+    pub fn with_symbol_verbalization(mut self, enable: bool) -> Self {
+        self.symbol_verbalization = enable;
+        self
+    }
+
+    /// Set the language of the readings used for symbols
+    ///
+    /// # Arguments
+    ///
+    /// * `language` - Reading language (default [`SymbolLanguage::Chinese`])
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{SymbolLanguage, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_symbol_verbalization(true)
+    ///     .with_symbol_language(SymbolLanguage::English);
+    /// ```
+    pub fn with_symbol_language(mut self, language: SymbolLanguage) -> Self {
+        self.symbol_language = language;
+        self
+    }
+
+    /// Add or override symbol readings
+    ///
+    /// Entries take precedence over the built-in table, in any language.
+    /// Only used when [symbol verbalization](Self::with_symbol_verbalization)
+    /// is enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `readings` - Spoken reading keyed by symbol
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_symbol_verbalization(true)
+    ///     .with_symbol_readings(HashMap::from([('∮', "环路积分".to_string())]));
+    /// ```
+    pub fn with_symbol_readings(mut self, readings: HashMap<char, String>) -> Self {
+        self.symbol_readings.extend(readings);
+        self
+    }
+
+    /// Set how many symbols without a reading may reach the TTS input
+    ///
+    /// When verbalization leaves more unknown symbols than this in the
+    /// text, a [`Warning::UnknownSymbols`] lists them so the table can be
+    /// extended with [`with_symbol_readings`](Self::with_symbol_readings).
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - Allowed occurrences of unknown symbols (default 0)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_symbol_verbalization(true)
+    ///     .with_unknown_symbol_threshold(5);
+    /// ```
+    pub fn with_unknown_symbol_threshold(mut self, threshold: usize) -> Self {
+        self.unknown_symbol_threshold = threshold;
+        self
+    }
+
+    /// Read dates, times, phone numbers and other numbers aloud per locale
+    ///
+    /// Numbers are spelled out before symbol verbalization, so "3/4" is
+    /// read as a date or a fraction and "13800138000" digit by digit as
+    /// the profile says. Numbers that could be read more than one way are
+    /// read as the profile prefers and listed in a
+    /// [`Warning::AmbiguousNumbers`]. Off by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - A built-in profile such as [`NumericContext::zh_cn`],
+    ///   optionally with fields overridden
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{DecimalSeparator, NumericContext, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key").with_numeric_profile(
+    ///     NumericContext::en_us().with_decimal_separator(DecimalSeparator::Comma),
+    /// );
+    /// ```
+    pub fn with_numeric_profile(mut self, context: NumericContext) -> Self {
+        self.numeric_context = Some(context);
+        self
+    }
+
+    /// Replace the text of specific segments before synthesis
+    ///
+    /// Indices refer to the post-split segment order as returned by
+    /// [`plan`](Self::plan) or [`split_only`](Self::split_only); direct-mode
+    /// conversions have a single segment with index 0. Changing the split
+    /// settings (or the AI model's output changing between runs) invalidates
+    /// the indices. Overrides for indices past the last segment are reported
+    /// as warnings.
+    ///
+    /// # Arguments
+    ///
+    /// * `overrides` - Replacement text keyed by segment index
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use text2audio::Text2Audio;
+    ///
+    /// let overrides = HashMap::from([(5, "A. P. I. 接口".to_string())]);
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_segment_overrides(overrides);
+    /// ```
+    pub fn with_segment_overrides(mut self, overrides: HashMap<usize, String>) -> Self {
+        self.segment_overrides = overrides;
+        self
+    }
+
+    /// Write a per-segment timing sidecar next to the output file
+    ///
+    /// When enabled, `<output>.segments.json` is written after a successful
+    /// conversion. It maps each segment's audio start and duration to the
+    /// char range of the original input it covers, as produced by
+    /// [`ConversionReport::to_timing_json`].
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to write the sidecar
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_timing_sidecar(true);
+    /// ```
+    pub fn with_timing_sidecar(mut self, enable: bool) -> Self {
+        self.timing_sidecar = enable;
+        self
+    }
+
+    /// Set how much segment text conversion reports keep
+    ///
+    /// Reports keep every segment's text with [`ReportDetail::Full`], which
+    /// for a large document holds a second copy of it. By default reports
+    /// keep full text up to [`FULL_REPORT_MAX_BYTES`] of segment text and
+    /// only hashes above that. Text a report will not keep is dropped as
+    /// each segment is recorded, so it is never held in full. The timing
+    /// sidecar, speech marks and calibration need the text, so with them
+    /// it is kept until they are written, and they are the same at every
+    /// level. Source ranges are filled in at every level. Features needing more
+    /// detail than a report keeps, such as
+    /// [`ConversionReport::to_plan`], return an error saying which level to
+    /// convert with.
+    ///
+    /// # Arguments
+    ///
+    /// * `detail` - Detail kept for every conversion, whatever its size
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{ReportDetail, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_report_detail(ReportDetail::Full);
+    /// ```
+    pub fn with_report_detail(mut self, detail: ReportDetail) -> Self {
+        self.report_detail = Some(detail);
+        self
+    }
+
+    /// Write a metadata sidecar next to the output file
+    ///
+    /// When enabled, `<output>.json` is written after a successful
+    /// conversion with the audio's total duration, sample rate, channel
+    /// count, segment count and file size, as an [`OutputMetadata`]. The
+    /// values come from the merged audio's header, so the audio is not read
+    /// again.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to write the sidecar
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_sidecar_metadata(true);
+    /// ```
+    pub fn with_sidecar_metadata(mut self, enable: bool) -> Self {
+        self.sidecar_metadata = enable;
+        self
+    }
+
+    /// Write speech marks for web players to `path` after a successful
+    /// conversion
+    ///
+    /// The file holds one JSON object per line in the format of Amazon
+    /// Polly's speech marks: a `sentence` entry per segment with its start
+    /// in milliseconds and its text, as described for
+    /// [`ConversionReport::to_speech_marks`]. Each conversion overwrites
+    /// the file, so [`convert_many`](Self::convert_many) rejects more than
+    /// one job as conflicting outputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the speech marks file
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_speech_marks("output.marks")
+    ///     .with_speech_mark_sections(true);
+    /// ```
+    pub fn with_speech_marks(mut self, path: impl Into<PathBuf>) -> Self {
+        self.speech_marks = Some(path.into());
+        self
+    }
+
+    /// Mark section boundaries in speech marks
+    ///
+    /// When enabled, an `ssml` entry `<mark name="section"/>` precedes the
+    /// first segment of each [section](Self::with_section_profiles).
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to write section entries
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_speech_marks("output.marks")
+    ///     .with_speech_mark_sections(true);
+    /// ```
+    pub fn with_speech_mark_sections(mut self, enable: bool) -> Self {
+        self.speech_mark_sections = enable;
+        self
+    }
+
+    /// Bound the memory used to hold synthesized segment audio
+    ///
+    /// By default all segment WAVs are kept in memory until the final merge,
+    /// and the merged output is hashed as it is written. With a limit set,
+    /// segments are collected in order and, once the held audio exceeds
+    /// `bytes`, written to the output file as they complete. Memory then
+    /// stays bounded by the parallelism level rather than the document
+    /// length. The incremental merge fixes the output spec from the first
+    /// segment instead of the majority vote, segments can no longer be
+    /// reordered or replaced after they are written, and a WAV output is
+    /// read back once to compute its checksum. The limit does not apply to
+    /// [`convert_formats`](Self::convert_formats), which always merges in
+    /// memory.
+    ///
+    /// [`with_target_loudness`](Self::with_target_loudness),
+    /// [`with_require_nonsilent`](Self::with_require_nonsilent) and
+    /// [`with_audio_inspector`](Self::with_audio_inspector) need the whole
+    /// output in memory. With one of them set, a segmented conversion to a
+    /// file fails with [`Error::Config`] before any TTS request.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Maximum bytes of segment audio held in memory
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_max_memory(64 * 1024 * 1024);
+    /// ```
+    pub fn with_max_memory(mut self, bytes: usize) -> Self {
+        self.max_memory = Some(bytes);
+        self
+    }
+
+    /// Retry failed segments sequentially when parallel synthesis fails
+    ///
+    /// Some networks and proxies drop concurrent connections. With fallback
+    /// enabled, if parallel synthesis still fails after retries and most of
+    /// the failures are transport errors, the segments that failed are
+    /// synthesized again one at a time. Segments that already succeeded are
+    /// reused. The fallback is recorded as
+    /// [`Warning::ParallelFallback`] in the conversion report.
+    ///
+    /// Has no effect unless parallel processing is enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to fall back to sequential synthesis
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_parallel(4)
+    ///     .with_parallel_fallback(true);
+    /// ```
+    pub fn with_parallel_fallback(mut self, enable: bool) -> Self {
+        self.parallel_fallback = enable;
+        self
+    }
+
+    /// Attach an idempotency key to each segment's TTS requests
+    ///
+    /// Enabled by default. Each segment's key is derived from its position,
+    /// text and TTS settings, so every request for the segment sends the
+    /// same key: its retries, the sequential retry of
+    /// [parallel fallback](Self::with_parallel_fallback) and a resumed
+    /// [project](Self::run_project). A server that deduplicates by key then
+    /// does not charge twice for a request that timed out locally but
+    /// succeeded remotely. Converting the same text with the same settings
+    /// again sends the same keys as well.
+    ///
+    /// The key is passed to the backend in [`TtsConfig::idempotency_key`].
+    /// The built-in Zhipu backend drops it, so this only has an effect with
+    /// a [custom backend](Self::with_backend).
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to generate idempotency keys
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_idempotency_keys(false);
+    /// ```
+    pub fn with_idempotency_keys(mut self, enable: bool) -> Self {
+        self.idempotency_keys = enable;
+        self
+    }
+
+    /// Make repeated conversions of the same input reproducible
+    ///
+    /// Meant for golden-file tests that compare outputs byte for byte. When
+    /// enabled:
+    ///
+    /// * AI split requests use greedy decoding instead of sampling, and a
+    ///   [race](SplitStrategy::Race) waits for the AI split rather than
+    ///   giving up at its deadline
+    /// * parallel synthesis collects segments in input order rather than
+    ///   as they complete
+    /// * reports carry zero [`PhaseTimings`] and empty latency histograms
+    ///
+    /// Outputs and sidecars hold no timestamps in any mode, and segments
+    /// are always merged in input order. Out of the library's control are
+    /// the audio the TTS service returns and, if the API varies even
+    /// without sampling, the AI split; a
+    /// [split cache](Self::with_split_cache) pins the latter. Stall
+    /// warnings and failure bundles still depend on the clock.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to make conversions reproducible (default
+    ///   `false`)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key").with_deterministic(true);
+    /// ```
+    pub fn with_deterministic(mut self, enable: bool) -> Self {
+        self.deterministic = enable;
+        self
+    }
+
+    /// Write a diagnostic bundle to `dir` when a conversion fails
+    ///
+    /// The bundle is a JSON file following the [`FailureBundle`] schema: the
+    /// effective settings with the API key redacted, the error with its code
+    /// and causes, the status and timing of each segment, the last warnings
+    /// and the crate version. Audio that could not be read is attached,
+    /// capped at [`MAX_ATTACHMENT_BYTES`](bundle::MAX_ATTACHMENT_BYTES).
+    /// Failing to write the bundle does not change the error returned.
+    ///
+    /// Every conversion method writes one bundle per failed call; streams
+    /// write one for each segment they yield an error for. Bundle names
+    /// hold the time, the process id and a counter, so they never collide.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Directory for bundles, created if missing
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_failure_bundle("diagnostics")
+    ///     .with_failure_bundle_text(true);
+    /// ```
+    pub fn with_failure_bundle(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.failure_bundle = Some(dir.into());
+        self
+    }
+
+    /// Include the failing segment's text in failure bundles
+    ///
+    /// Off by default, since the text may be confidential; the bundle then
+    /// only records its length, and warnings that quote the input, such as
+    /// [`Warning::LongToken`], only their counts.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether bundles include segment text
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_failure_bundle("diagnostics")
+    ///     .with_failure_bundle_text(true);
+    /// ```
+    pub fn with_failure_bundle_text(mut self, enable: bool) -> Self {
+        self.failure_bundle_text = enable;
+        self
+    }
+
+    /// Learn the speech rate from completed conversions
+    ///
+    /// After each successful conversion, the seconds of audio per spoken
+    /// character are folded into the [`Calibration`] stored at `path` for
+    /// the current voice and speed, smoothed exponentially.
+    /// [`estimate`](Self::estimate) then uses the calibrated rate instead
+    /// of [`DEFAULT_SECONDS_PER_CHAR`](calibration::DEFAULT_SECONDS_PER_CHAR).
+    /// Converters sharing the file take turns through a lock file; a
+    /// calibration that cannot be saved is reported as a warning.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Calibration file, created on the first conversion
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_calibration_file("calibration.json");
+    /// ```
+    pub fn with_calibration_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.calibration_file = Some(path.into());
+        self
+    }
+
+    /// Set how [`convert_to_files`](Self::convert_to_files) names files
+    ///
+    /// Slugs keep the letters and digits of the segment's opening words,
+    /// join them with `_`, and are cut to
+    /// [`MAX_SLUG_CHARS`](naming::MAX_SLUG_CHARS) characters.
+    ///
+    /// # Arguments
+    ///
+    /// * `naming` - Number, slug, or both (default number)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{FileNaming, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_file_naming(FileNaming::IndexAndSlug);
+    /// ```
+    pub fn with_file_naming(mut self, naming: FileNaming) -> Self {
+        self.file_template = naming.into();
+        self
+    }
+
+    /// Set a template for the names of the files
+    /// [`convert_to_files`](Self::convert_to_files) writes
+    ///
+    /// Replaces the [file naming](Self::with_file_naming). `{stem}` is the
+    /// name of the directory the files are written to, `{voice}` and
+    /// `{speed}` are the configured voice and speed, and `{chapter}` is
+    /// empty. Names that come out empty or taken get the segment number,
+    /// as with [`FileNaming::Slug`].
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - Template of the file names, without extension
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{OutputTemplate, Text2Audio};
+    ///
+    /// let converter = Text2Audio::new("api_key")
+    ///     .with_file_template(OutputTemplate::new("{stem}_{index:04}_{voice}")?);
+    /// # Ok::<(), text2audio::Error>(())
+    /// ```
+    pub fn with_file_template(mut self, template: OutputTemplate) -> Self {
+        self.file_template = template;
+        self
+    }
+
+    /// Set what happens when the output file already exists
+    ///
+    /// Applies to the output path of [`convert`](Self::convert) and the
+    /// other methods writing output files, including each output of
+    /// [`convert_formats`](Self::convert_formats) and each file of
+    /// [`convert_to_files`](Self::convert_to_files), checked before any
+    /// work is done. A skipped conversion returns a report without segments that
+    /// holds [`Warning::OutputSkipped`]; a renamed output is reported with
+    /// [`Warning::OutputRenamed`], and its sidecars follow the new name.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Overwrite, fail, skip or rename (default [`OverwritePolicy::Overwrite`])
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{OverwritePolicy, Text2Audio};
+    ///
+    /// // Resumable batch job: outputs from an earlier run are kept
+    /// let converter = Text2Audio::new("api_key").with_overwrite_policy(OverwritePolicy::Skip);
+    /// ```
+    pub fn with_overwrite_policy(mut self, policy: OverwritePolicy) -> Self {
+        self.overwrite_policy = policy;
+        self
+    }
+
+    /// Check that the output location can be written before any work is
+    /// done
+    ///
+    /// Enabled by default. The output must not be a directory, its parent
+    /// directory must exist, and a probe file is created and removed next
+    /// to it. A bad location then fails before any request
+    /// with [`Error::Config`] or [`Error::Io`] naming the path and the
+    /// problem. Disable it for filesystems where probe files misbehave.
+    ///
+    /// # Arguments
+    ///
+    /// * `enable` - Whether to check the output location first
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key").with_output_preflight(false);
+    /// ```
+    pub fn with_output_preflight(mut self, enable: bool) -> Self {
+        self.output_preflight = enable;
+        self
+    }
+
+    /// Convert text to audio file
+    ///
+    /// Automatically determines whether to use segmented or direct mode
+    /// based on text length. AI splitting is used when needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - Input text to convert
+    /// * `output_path` - Output audio file path, whose extension selects the
+    ///   format unless one is [configured](Self::with_output_format)
+    ///
+    /// # Errors
+    ///
+    /// Returns error if text processing, API calls, or audio processing fail.
+    pub async fn convert(&self, text: &str, output_path: &str) -> Result<()> {
+        self.convert_with_report(text, output_path)
+            .await
+            .map(|_| ())
+    }
+
+    /// Convert text to audio file and return a conversion report
+    ///
+    /// Behaves like [`convert`](Self::convert), additionally reporting the
+    /// mode that was used and any warnings encountered.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if text processing, API calls, or audio processing fail.
+    pub async fn convert_with_report(
+        &self,
+        text: &str,
+        output_path: &str,
+    ) -> Result<ConversionReport> {
+        self.bundled(
+            ConversionMode::Direct,
+            self.convert_to_output(text, output_path),
+        )
+        .await
+        .map(|(report, _)| report)
+    }
+
+    /// Convert text to audio file and return the report with the merged
+    /// audio
+    ///
+    /// Behaves like [`convert_with_report`](Self::convert_with_report), and
+    /// also returns the merged WAV audio the output was written from, so it
+    /// can be encoded again or played without reading the output back. The
+    /// audio is WAV whatever the output format.
+    ///
+    /// Two cases have no merged audio in memory, and load the output file
+    /// instead: a WAV output written under a
+    /// [memory limit](Self::with_max_memory), and an output skipped by the
+    /// [overwrite policy](Self::with_overwrite_policy), whose existing file
+    /// must then be a WAV file.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if text processing, API calls, or audio processing fail.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use text2audio::{OutputFormat, Text2Audio};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let converter = Text2Audio::new("api_key");
+    /// let (report, audio) = converter.convert_with_audio("你好，世界！", "hello.wav").await?;
+    /// let mp3 = audio.encode(OutputFormat::Mp3 { bitrate_kbps: 64 })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn convert_with_audio(
+        &self,
+        text: &str,
+        output_path: &str,
+    ) -> Result<(ConversionReport, MergedAudio)> {
+        let (report, wav) = self
+            .bundled(
+                ConversionMode::Direct,
+                self.convert_to_output(text, output_path),
+            )
+            .await?;
+        let audio = match wav {
+            Some(wav) => MergedAudio::from_wav_bytes(wav)?.with_segments(&report),
+            None => match report.outputs.last() {
+                Some(output) => MergedAudio::from_file(&output.path)?.with_segments(&report),
+                None => MergedAudio::from_file(output_path)?,
+            },
+        };
+        Ok((report, audio))
+    }
+
+    /// Convert text to `output_path`, returning the report and the merged
+    /// WAV if it was held in memory
+    async fn convert_to_output(
+        &self,
+        text: &str,
+        output_path: &str,
+    ) -> Result<(ConversionReport, Option<Vec<u8>>)> {
+        let mut warnings = Vec::new();
+        let Some(output_path) = self.output_target(output_path, &mut warnings)? else {
+            return Ok((ConversionReport::skipped(warnings), None));
+        };
+        let output_path = output_path.as_str();
+        let format = resolve_format(output_path, self.output_format, &mut warnings);
+        check_encodable(format)?;
+        if format == OutputFormat::Wav && self.max_memory.is_some() {
+            let mut report = self
+                .convert_into(text, &mut Output::Path(output_path))
+                .await?;
+            report.warnings.extend(warnings);
+            let output = digest_file(output_path)?;
+            self.finish_output(&mut report, None, output)?;
+            self.limit_report_detail(&mut report);
+            Ok((report, None))
+        } else {
+            let mut wav = Vec::new();
+            let mut report = self
+                .convert_into(text, &mut Output::Memory(&mut wav))
+                .await?;
+            report.warnings.extend(warnings);
+            let (wav, output) = self
+                .write_output(wav, format, output_path, &mut report)
+                .await?;
+            self.finish_output(&mut report, Some(&wav), output)?;
+            self.limit_report_detail(&mut report);
+            Ok((report, Some(wav)))
+        }
+    }
+
+    /// Synthesize and merge a plan exported with [`ConversionPlan::to_json`]
+    ///
+    /// Lets splitting run in one process and synthesis in another: the
+    /// segments and pauses are taken from the plan as is, without any AI
+    /// split request. Segment overrides still apply.
+    ///
+    /// # Arguments
+    ///
+    /// * `plan_path` - Path of the plan JSON
+    /// * `output_path` - Output audio file path
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] if the file is not a valid plan or the
+    /// output or one of its sidecars would overwrite it,
+    /// [`Error::EmptyInput`] if it has no segments, and error if API calls or
+    /// audio processing fail.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use text2audio::Text2Audio;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let converter = Text2Audio::new("api_key");
+    /// let plan = converter.plan("很长的文本……").await?;
+    /// std::fs::write("book.plan.json", plan.to_json())?;
+    ///
+    /// // Possibly in another process
+    /// converter
+    ///     .convert_segments_from_json("book.plan.json", "book.wav")
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn convert_segments_from_json(
+        &self,
+        plan_path: &str,
+        output_path: &str,
+    ) -> Result<ConversionReport> {
+        self.bundled(
+            ConversionMode::Segmented,
+            self.convert_segments_from_json_unbundled(plan_path, output_path),
+        )
+        .await
+    }
+
+    async fn convert_segments_from_json_unbundled(
+        &self,
+        plan_path: &str,
+        output_path: &str,
+    ) -> Result<ConversionReport> {
+        let plan = ConversionPlan::from_json(&std::fs::read_to_string(plan_path)?)?;
+        let mut warnings = Vec::new();
+        let Some(output_path) = self.output_target(output_path, &mut warnings)? else {
+            return Ok(ConversionReport::skipped(warnings));
+        };
+        let output_path = output_path.as_str();
+        naming::check_path_conflicts(&[plan_path], &self.output_files(output_path))?;
+        let format = resolve_format(output_path, self.output_format, &mut warnings);
+        check_encodable(format)?;
+        if format == OutputFormat::Wav && self.max_memory.is_some() {
+            let mut report = self
+                .convert_plan_into(plan, &mut Output::Path(output_path))
+                .await?;
+            report.warnings.extend(warnings);
+            let output = digest_file(output_path)?;
+            self.finish_output(&mut report, None, output)?;
+            self.limit_report_detail(&mut report);
+            Ok(report)
+        } else {
+            let mut wav = Vec::new();
+            let mut report = self
+                .convert_plan_into(plan, &mut Output::Memory(&mut wav))
+                .await?;
+            report.warnings.extend(warnings);
+            let (wav, output) = self
+                .write_output(wav, format, output_path, &mut report)
+                .await?;
+            self.finish_output(&mut report, Some(&wav), output)?;
+            self.limit_report_detail(&mut report);
+            Ok(report)
+        }
+    }
+
+    /// Convert text to audio in memory
+    ///
+    /// Behaves like [`convert`](Self::convert), but merges in memory and
+    /// returns the audio in the configured
+    /// [output format](Self::with_output_format) instead of writing a file.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if text processing, API calls, or audio processing fail.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use text2audio::Text2Audio;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let converter = Text2Audio::new("api_key");
+    /// let wav = converter.convert_to_bytes("你好，世界！").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn convert_to_bytes(&self, text: &str) -> Result<Vec<u8>> {
+        self.bundled(
+            ConversionMode::Direct,
+            self.convert_to_bytes_unbundled(text),
+        )
+        .await
+    }
+
+    async fn convert_to_bytes_unbundled(&self, text: &str) -> Result<Vec<u8>> {
+        let format = self.output_format.unwrap_or_default();
+        check_encodable(format)?;
+        self.convert_to_audio(text).await?.encode(format)
+    }
+
+    /// Convert text to merged WAV audio in memory
+    ///
+    /// Behaves like [`convert_to_bytes`](Self::convert_to_bytes), but
+    /// always returns WAV, together with its format and the span of each
+    /// segment, so it can be encoded, written or decoded later without
+    /// another conversion.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if text processing, API calls, or audio processing fail.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use text2audio::{OutputFormat, Text2Audio};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let converter = Text2Audio::new("api_key");
+    /// let audio = converter.convert_to_audio("你好，世界！").await?;
+    /// audio.write_to("hello.wav")?;
+    /// let mp3 = audio.encode(OutputFormat::Mp3 { bitrate_kbps: 64 })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn convert_to_audio(&self, text: &str) -> Result<MergedAudio> {
+        self.bundled(
+            ConversionMode::Direct,
+            self.convert_to_audio_unbundled(text),
+        )
+        .await
+    }
+
+    async fn convert_to_audio_unbundled(&self, text: &str) -> Result<MergedAudio> {
+        let mut wav = Vec::new();
+        let report = self
+            .convert_into(text, &mut Output::Memory(&mut wav))
+            .await?;
+        Ok(MergedAudio::from_wav_bytes(wav)?.with_segments(&report))
+    }
+
+    /// Convert text to base64-encoded audio for embedding
+    ///
+    /// Encodes the result of [`convert_to_bytes`](Self::convert_to_bytes)
+    /// and returns it with the MIME type of the configured output format,
+    /// ready for a `data:` URI in a web page or JSON response.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if text processing, API calls, or audio processing fail.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use text2audio::Text2Audio;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let converter = Text2Audio::new("api_key");
+    /// let audio = converter.convert_to_base64("你好，世界！").await?;
+    /// let html = format!("<audio src=\"{}\"></audio>", audio.data_uri());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn convert_to_base64(&self, text: &str) -> Result<EncodedAudio> {
+        let audio = self.convert_to_bytes(text).await?;
+        Ok(EncodedAudio::new(
+            &audio,
+            self.output_format.unwrap_or_default(),
+        ))
+    }
+
+    /// Convert text once and write it to several files and formats
+    ///
+    /// Synthesis and merging happen a single time into an in-memory WAV,
+    /// which is then encoded to each requested path and format, so the TTS
+    /// work is shared by all outputs. With sidecars enabled, each output
+    /// gets its own. The [overwrite policy](Self::with_overwrite_policy)
+    /// applies to each output; skipped outputs are left out of the report,
+    /// and if all are skipped nothing is converted.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - Input text to convert
+    /// * `outputs` - Output paths and the format to write at each
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no outputs are given, conversion fails, or a format
+    /// cannot be encoded, such as MP3 without the `mp3` feature. Returns
+    /// [`Error::Config`] before any request if two outputs or their sidecars
+    /// share a path.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use text2audio::{OutputFormat, Text2Audio};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let converter = Text2Audio::new("api_key");
+    /// converter
+    ///     .convert_formats(
+    ///         "你好，世界！",
+    ///         vec![
+    ///             ("output.wav".to_string(), OutputFormat::Wav),
+    ///             ("output.mp3".to_string(), OutputFormat::Mp3 { bitrate_kbps: 64 }),
+    ///         ],
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn convert_formats(
+        &self,
+        text: &str,
+        outputs: Vec<(String, OutputFormat)>,
+    ) -> Result<ConversionReport> {
+        self.bundled(
+            ConversionMode::Direct,
+            self.convert_formats_unbundled(text, outputs),
+        )
+        .await
+    }
+
+    async fn convert_formats_unbundled(
+        &self,
+        text: &str,
+        outputs: Vec<(String, OutputFormat)>,
+    ) -> Result<ConversionReport> {
+        if outputs.is_empty() {
+            return Err(Error::Config("no output formats requested".to_string()));
+        }
+        // Every output gets the same speech marks, so they share one file
+        let mut files: Vec<PathBuf> = outputs
+            .iter()
+            .flat_map(|(path, _)| self.output_files(path))
+            .filter(|file| Some(file) != self.speech_marks.as_ref())
+            .collect();
+        files.extend(self.speech_marks.clone());
+        naming::check_path_conflicts::<&str, _>(&[], &files)?;
+        let mut warnings = Vec::new();
+        let mut targets = Vec::with_capacity(outputs.len());
+        for (path, format) in outputs {
+            check_encodable(format)?;
+            if let Some(path) = self.output_target(&path, &mut warnings)? {
+                targets.push((path, format));
+            }
+        }
+        if targets.is_empty() {
+            return Ok(ConversionReport::skipped(warnings));
+        }
+
+        let mut wav = Vec::new();
+        let mut report = self
+            .convert_into(text, &mut Output::Memory(&mut wav))
+            .await?;
+        report.warnings.extend(warnings);
+        for (path, format) in &targets {
+            let output;
+            (wav, output) = self.write_output(wav, *format, path, &mut report).await?;
+            self.finish_output(&mut report, Some(&wav), output)?;
+        }
+        self.limit_report_detail(&mut report);
+
+        Ok(report)
+    }
+
+    /// Convert text to one audio file per segment
+    ///
+    /// Splits the text like [`convert`](Self::convert) and writes each
+    /// segment's audio to `dir` in the configured
+    /// [output format](Self::with_output_format), without merging. Files are
+    /// named by the [file naming](Self::with_file_naming) or
+    /// [template](Self::with_file_template). Returns the paths
+    /// in segment order.
+    ///
+    /// The [overwrite policy](Self::with_overwrite_policy) applies to each
+    /// file before any TTS request. Segments whose file is skipped are not
+    /// synthesized and their paths are not returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - Input text to convert
+    /// * `dir` - Directory for the files, created if missing
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the text is empty, or splitting, synthesis or file
+    /// I/O fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use text2audio::{FileNaming, Text2Audio};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let converter = Text2Audio::new("api_key").with_file_naming(FileNaming::IndexAndSlug);
+    /// let files = converter.convert_to_files("很长的文本……", "segments").await?;
+    /// println!("wrote {} files", files.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn convert_to_files(
+        &self,
+        text: &str,
+        dir: impl AsRef<Path>,
+    ) -> Result<Vec<PathBuf>> {
+        let report = self.convert_to_files_with_report(text, dir).await?;
+        Ok(report
+            .outputs
+            .into_iter()
+            .map(|output| PathBuf::from(output.path))
+            .collect())
+    }
+
+    /// Convert text to one audio file per segment and return a report
+    ///
+    /// Like [`convert_to_files`](Self::convert_to_files), but returns a
+    /// [`ConversionReport`] instead of the paths. The report covers the
+    /// segments that were synthesized, and [`outputs`](ConversionReport::outputs)
+    /// lists the path and checksums of each file written, in segment order.
+    /// Files skipped under the [overwrite policy](Self::with_overwrite_policy)
+    /// add warnings.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - Input text to convert
+    /// * `dir` - Directory for the files, created if missing
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the text is empty, or splitting, synthesis or file
+    /// I/O fails.
+    pub async fn convert_to_files_with_report(
+        &self,
+        text: &str,
+        dir: impl AsRef<Path>,
+    ) -> Result<ConversionReport> {
+        self.bundled(
+            ConversionMode::Segmented,
+            self.convert_to_files_unbundled(text, dir),
+        )
+        .await
+    }
+
+    async fn convert_to_files_unbundled(
+        &self,
+        text: &str,
+        dir: impl AsRef<Path>,
+    ) -> Result<ConversionReport> {
+        let started = Instant::now();
+        let stats_before = self.stats.snapshot();
+        let plan = self.plan(text).await?;
+        let mut report = self.new_report(ConversionMode::Segmented);
+        let segments = self.apply_segment_overrides(plan.segments, &mut report.warnings);
+
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        let format = self.output_format.unwrap_or_default();
+        check_encodable(format)?;
+        let stem = dir
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let values = TemplateValues {
+            stem: &stem,
+            voice: Some(self.voice),
+            speed: Some(self.speed),
+            ..TemplateValues::default()
+        };
+        let names = self
+            .file_template
+            .file_names(&segments, &values, format.extension());
+        let mut paths = Vec::with_capacity(names.len());
+        let mut pending = Vec::with_capacity(names.len());
+        for (name, segment) in names.into_iter().zip(segments) {
+            let path = dir.join(name).to_string_lossy().into_owned();
+            if let Some(path) = self.output_target(&path, &mut report.warnings)? {
+                paths.push(path);
+                pending.push(segment);
+            }
+        }
+        if pending.is_empty() {
+            return Ok(ConversionReport::skipped(report.warnings));
+        }
+
+        let segments: Arc<[String]> = pending.into();
+        bundle::planned(&segments);
+        let synth_started = Instant::now();
+        let collect = async {
+            if self.enable_parallel {
+                let results = self.collect_audio_parallel(segments.clone()).await?;
+                results.into_iter().collect::<Result<Vec<_>>>()
+            } else {
+                self.collect_audio_sequential(&segments, &[]).await
+            }
+        };
+        let audio = self.tracked(segments.len(), collect).await?;
+        report.timings.synth_duration = synth_started.elapsed();
+
+        report.timings.total = started.elapsed();
+        for ((path, segment), (audio, attempts)) in paths.iter().zip(segments.iter()).zip(audio) {
+            report.push_segment(segment, &audio, SegmentSource::Synthesized { attempts })?;
+            let (_, output) = self.write_output(audio, format, path, &mut report).await?;
+            report.outputs.push(output);
+        }
+        report.stats = self.stats.snapshot().since(&stats_before);
+        if self.deterministic {
+            report.clear_wall_clock();
+        }
+        self.limit_report_detail(&mut report);
+        Ok(report)
+    }
+
+    /// Convert several texts, each to its own file
+    ///
+    /// Jobs run one after another, each like
+    /// [`convert_with_report`](Self::convert_with_report). How failures
+    /// are surfaced depends on the [error mode](Self::with_error_mode): in
+    /// [`ErrorMode::Collect`] the result of every job is returned, while in
+    /// the other modes the returned results are all `Ok` and failures are
+    /// returned as the error.
+    ///
+    /// # Arguments
+    ///
+    /// * `jobs` - Input text and output path of each conversion
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] before any job runs if two jobs would write
+    /// the same file, the first job's error in [`ErrorMode::FailFast`], and
+    /// [`Error::Multiple`] with every job's error in
+    /// [`ErrorMode::Aggregate`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use text2audio::Text2Audio;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let converter = Text2Audio::new("api_key");
+    /// let results = converter
+    ///     .convert_many(&[("第一章", "chapter1.wav"), ("第二章", "chapter2.wav")])
+    ///     .await?;
+    /// for result in results {
+    ///     if let Err(e) = result {
+    ///         eprintln!("conversion failed: {}", e);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn convert_many(
+        &self,
+        jobs: &[(&str, &str)],
+    ) -> Result<Vec<Result<ConversionReport>>> {
+        self.bundled(ConversionMode::Direct, self.convert_many_unbundled(jobs))
+            .await
+    }
+
+    async fn convert_many_unbundled(
+        &self,
+        jobs: &[(&str, &str)],
+    ) -> Result<Vec<Result<ConversionReport>>> {
+        let outputs: Vec<PathBuf> = jobs
+            .iter()
+            .flat_map(|(_, output_path)| self.output_files(output_path))
+            .collect();
+        naming::check_path_conflicts::<&str, _>(&[], &outputs)?;
+        let mut results = Vec::with_capacity(jobs.len());
+        for (text, output_path) in jobs {
+            let result = self.convert_with_report(text, output_path).await;
+            if self.error_mode == ErrorMode::FailFast {
+                results.push(Ok(result?));
+            } else {
+                results.push(result);
+            }
+        }
+
+        if self.error_mode == ErrorMode::Aggregate && results.iter().any(Result::is_err) {
+            return Err(Error::Multiple(
+                results.into_iter().filter_map(Result::err).collect(),
+            ));
+        }
+        Ok(results)
+    }
+
+    /// Convert pairs of texts into one file alternating two voices
+    ///
+    /// Each pair's first text is read in the converter's voice and its
+    /// second in `secondary_voice`, for language-learning audio that plays
+    /// a sentence followed by its translation. Both texts are split like
+    /// [`convert`](Self::convert) would split them. A short pause follows
+    /// the first text of each pair and a longer one follows each pair; see
+    /// [`with_bilingual_pauses`](Self::with_bilingual_pauses). The report's
+    /// segments record their [`Track`], and each segment's source range
+    /// refers to its own text of the pair.
+    ///
+    /// # Arguments
+    ///
+    /// * `pairs` - Primary and secondary texts in playback order
+    /// * `output_path` - Output file path
+    /// * `secondary_voice` - Voice for the second text of each pair
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::EmptyInput`] if there are no pairs or a text is
+    /// empty, and error if splitting, synthesis or file I/O fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use text2audio::{Text2Audio, Voice};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let converter = Text2Audio::new("api_key").with_voice(Voice::Tongtong);
+    /// let pairs = vec![
+    ///     ("你好。".to_string(), "Hello.".to_string()),
+    ///     ("谢谢。".to_string(), "Thank you.".to_string()),
+    /// ];
+    /// converter
+    ///     .convert_bilingual(pairs, "lesson.wav", Voice::Jam)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn convert_bilingual(
+        &self,
+        pairs: Vec<(String, String)>,
+        output_path: &str,
+        secondary_voice: Voice,
+    ) -> Result<ConversionReport> {
+        let mut report = self.new_report(ConversionMode::Segmented);
+        let Some(dir) = &self.failure_bundle else {
+            self.convert_bilingual_into(pairs, output_path, secondary_voice, &mut report)
+                .await?;
+            return Ok(report);
+        };
+        let (result, recorder) = bundle::capture(self.convert_bilingual_into(
+            pairs,
+            output_path,
+            secondary_voice,
+            &mut report,
+        ))
+        .await;
+        self.bundle_failure(dir, &result, &report, &recorder);
+        result.map(|()| report)
+    }
+
+    async fn convert_bilingual_into(
+        &self,
+        pairs: Vec<(String, String)>,
+        output_path: &str,
+        secondary_voice: Voice,
+        report: &mut ConversionReport,
+    ) -> Result<()> {
+        if pairs.is_empty() {
+            return Err(Error::EmptyInput);
+        }
+        let started = Instant::now();
+        let stats_before = self.stats.snapshot();
+        let Some(output_path) = self.output_target(output_path, &mut report.warnings)? else {
+            *report = ConversionReport::skipped(std::mem::take(&mut report.warnings));
+            return Ok(());
+        };
+        let output_path = output_path.as_str();
+        let format = resolve_format(output_path, self.output_format, &mut report.warnings);
+        check_encodable(format)?;
+        self.check_parameters(&mut report.warnings)?;
+        let (within_pair, between_pairs) = self.bilingual_pauses;
+
+        // Split every text up front so a bad pair fails before any synthesis
+        let mut items = Vec::new();
+        let mut ranges = Vec::new();
+        for (index, (primary, secondary)) in pairs.iter().enumerate() {
+            let pair_pause = if index + 1 < pairs.len() {
+                between_pairs
+            } else {
+                Duration::ZERO
+            };
+            let sides = [
+                (primary, self.voice, Track::Primary, within_pair),
+                (secondary, secondary_voice, Track::Secondary, pair_pause),
+            ];
+            for (text, voice, track, pause) in sides {
+                let segments = self.plan(text).await?.segments;
+                ranges.extend(align_segments(text, &segments));
+                let last = segments.len() - 1;
+                for (i, segment) in segments.into_iter().enumerate() {
+                    let pause = if i == last { pause } else { Duration::ZERO };
+                    items.push((segment, voice, track, pause));
+                }
+            }
+        }
+
+        report.timings.split_duration = started.elapsed();
+        bundle::planned(&items.iter().map(|(text, ..)| text).collect::<Vec<_>>());
+
+        let synth_started = Instant::now();
+        let concurrency = if self.enable_parallel {
+            self.max_parallel
+        } else {
+            1
+        };
+        let synthesize = async {
+            let mut results = stream::iter(&items)
+                .enumerate()
+                .map(|(index, (text, voice, _, _))| {
+                    self.after_delay(index, self.text_to_audio_in_voice(index, text, *voice))
+                })
+                .buffered(concurrency);
+            let mut audio_segments = Vec::with_capacity(items.len());
+            let mut pauses = Vec::with_capacity(items.len());
+            for (text, _, track, pause) in &items {
+                let Some(result) = results.next().await else {
+                    break;
+                };
+                let (audio, attempts) = result?;
+                report.push_segment(text, &audio, SegmentSource::Synthesized { attempts })?;
+                report.pad_last_segment(self.uniform_segment_duration);
+                report.push_pause(*pause);
+                if let Some(segment) = report.segments.last_mut() {
+                    segment.track = Some(*track);
+                }
+                audio_segments.push(audio);
+                pauses.push(*pause);
+            }
+            Ok::<_, Error>((audio_segments, pauses))
+        };
+        let (audio_segments, pauses) = self.tracked(items.len(), synthesize).await?;
+        for (segment, range) in report.segments.iter_mut().zip(ranges) {
+            segment.source_range = range;
+        }
+        report.timings.synth_duration = synth_started.elapsed();
+
+        let merge_started = Instant::now();
+        let mut wav = Vec::new();
+        let mut output = Output::Memory(&mut wav);
+        self.merge_to_output(audio_segments, &pauses, &mut output, &mut report.warnings)
+            .await?;
+        self.check_output(report, &output)?;
+        let output = encode_to_file(&wav, format, output_path)?;
+        report.timings.merge_duration = merge_started.elapsed();
+        report.stats = self.stats.snapshot().since(&stats_before);
+        report.timings.total = started.elapsed();
+        if self.deterministic {
+            report.clear_wall_clock();
+        }
+        self.finish_output(report, Some(&wav), output)?;
+        self.limit_report_detail(report);
+        Ok(())
+    }
+
+    /// Settings a new project captures from this converter
+    ///
+    /// [`run_project`](Self::run_project) only runs projects whose settings
+    /// match these.
+    pub fn project_settings(&self) -> ProjectSettings {
+        ProjectSettings {
+            model: self.model,
+            voice: self.voice,
+            speed: self.speed,
+            volume: self.volume,
+            max_segment_length: self.max_segment_length,
+            length_unit: self.length_unit,
+            newline_pauses: self.newline_pauses,
+            strip_link_urls: self.strip_link_urls,
+            footnote_policy: self.footnote_policy,
+            footnote_bodies: self.footnote_bodies,
+            cjk_latin_spacing: self.cjk_latin_spacing,
+            symbol_verbalization: self.symbol_verbalization,
+            symbol_language: self.symbol_language,
+            symbol_readings: self.symbol_readings.clone(),
+            unknown_symbol_threshold: self.unknown_symbol_threshold,
+            numeric_context: self.numeric_context.clone(),
+            long_token_policy: self.long_token_policy,
+        }
+    }
+
+    /// Create a resumable project for `text` with this converter's settings
+    ///
+    /// Save it with [`Project::save`] and run it with
+    /// [`run_project`](Self::run_project).
+    pub fn new_project(&self, text: impl Into<String>) -> Project {
+        Project::new(text, self.project_settings())
+    }
+
+    /// Run or resume the project saved at `project_path`
+    ///
+    /// Splits the text on the first run and stores the segments in the
+    /// project. Each synthesized segment is written to the project's audio
+    /// directory and the project file is saved after every segment, so a
+    /// failed or interrupted run can be resumed by calling this again, even
+    /// from another process. Segments whose audio is missing or does not
+    /// match its recorded hash are synthesized again. Once every segment is
+    /// complete, the audio is merged into `output_path`, with loudness
+    /// normalization and padding applied to stored and new segments alike.
+    ///
+    /// The project is locked while it runs, so several processes can safely
+    /// run the same project; see [`with_lock_timeout`](Self::with_lock_timeout).
+    /// Segment overrides and the memory limit do not apply to projects.
+    ///
+    /// # Arguments
+    ///
+    /// * `project_path` - Path of a project file created with [`Project::save`]
+    /// * `output_path` - Output audio file path, whose extension selects the
+    ///   format unless one is [configured](Self::with_output_format)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] if the output or one of its sidecars would
+    /// overwrite the project, its lock or its audio directory, if the project
+    /// cannot be loaded, or if its settings differ from this converter's (see
+    /// [`ProjectSettings::apply`]), and error if splitting, synthesis or file I/O fails. Progress made
+    /// before the error is kept in the project.
+    pub async fn run_project(
+        &self,
+        project_path: &str,
+        output_path: &str,
+    ) -> Result<ConversionReport> {
+        let mut report = self.new_report(ConversionMode::Direct);
+        let Some(dir) = &self.failure_bundle else {
+            self.run_project_into(project_path, output_path, &mut report)
+                .await?;
+            return Ok(report);
+        };
+        let (result, recorder) =
+            bundle::capture(self.run_project_into(project_path, output_path, &mut report)).await;
+        self.bundle_failure(dir, &result, &report, &recorder);
+        result.map(|()| report)
+    }
+
+    async fn run_project_into(
+        &self,
+        project_path: &str,
+        output_path: &str,
+        report: &mut ConversionReport,
+    ) -> Result<()> {
+        let check_conflicts = |output_path: &str| {
+            naming::check_path_conflicts(
+                &[
+                    PathBuf::from(project_path),
+                    Project::audio_dir(project_path),
+                    Project::lock_path(project_path),
+                ],
+                &self.output_files(output_path),
+            )
+        };
+        // Checked first, as the output may lie in a directory that does not
+        // exist yet
+        check_conflicts(output_path)?;
+        let mut output_warnings = Vec::new();
+        let Some(target) = self.output_target(output_path, &mut output_warnings)? else {
+            *report = ConversionReport::skipped(output_warnings);
+            return Ok(());
+        };
+        if target != output_path {
+            check_conflicts(&target)?;
+        }
+        let output_path = target.as_str();
+        // Without the lock, run read-only rather than race the holder
+        let mut lock =
+            FileLock::acquire(Project::lock_path(project_path), self.lock_timeout).await?;
+        let started = Instant::now();
+        let mut project = Project::load(project_path)?;
+        if project.settings != self.project_settings() {
+            return Err(Error::Config(
+                "project settings differ from the converter's".to_string(),
+            ));
+        }
+
+        let stats_before = self.stats.snapshot();
+        let segments = match project.segments.clone() {
+            Some(segments) => segments,
+            None => {
+                let split_started = Instant::now();
+                let segments = self.split_project(&project.text, report).await?;
+                report.timings.split_duration = split_started.elapsed();
+                project.segments = Some(segments.clone());
+                project
+                    .warnings
+                    .extend(report.warnings.iter().map(ToString::to_string));
+                if let Some(lock) = &mut lock {
+                    project.save(project_path)?;
+                    lock.refresh()?;
+                }
+                segments
+            }
+        };
+        if segments.len() > 1 || self.mode == ModeSelection::ForceSegmented {
+            report.mode = ConversionMode::Segmented;
+        }
+
+        bundle::planned(&segments.iter().map(|s| &s.text).collect::<Vec<_>>());
+        let audio_dir = Project::audio_dir(project_path);
+        if lock.is_some() {
+            std::fs::create_dir_all(&audio_dir)?;
+        }
+        let synth_started = Instant::now();
+        let synthesize = async {
+            let mut audio_segments = Vec::with_capacity(segments.len());
+            let mut after_request = false;
+            for (index, segment) in segments.iter().enumerate() {
+                let (audio, source) = match project.completed_audio(index, &audio_dir) {
+                    Some(audio) => {
+                        after_request = false;
+                        progress::segment_finished(index, &audio, SegmentSource::Checkpoint);
+                        (audio, SegmentSource::Checkpoint)
+                    }
+                    None => {
+                        if after_request {
+                            self.delay_next_request().await;
+                        }
+                        after_request = true;
+                        let (audio, attempts) =
+                            self.synthesize_segment(index, &segment.text).await?;
+                        if let Some(lock) = &mut lock {
+                            project.complete_segment(index, &audio, &audio_dir)?;
+                            project.save(project_path)?;
+                            lock.refresh()?;
+                        }
+                        (audio, SegmentSource::Synthesized { attempts })
+                    }
+                };
+                report.push_segment(&segment.text, &audio, source)?;
+                report.pad_last_segment(self.uniform_segment_duration);
+                report.push_pause(segment.pause_after);
+                audio_segments.push(audio);
+            }
+            Ok::<_, Error>(audio_segments)
+        };
+        let audio_segments = self.tracked(segments.len(), synthesize).await?;
+        report.timings.synth_duration = synth_started.elapsed();
+
+        let merge_started = Instant::now();
+        let pauses: Vec<Duration> = segments.iter().map(|s| s.pause_after).collect();
+        let mut warnings = Vec::new();
+        let mut wav = Vec::new();
+        self.merge_to_output(
+            audio_segments,
+            &pauses,
+            &mut Output::Memory(&mut wav),
+            &mut warnings,
+        )
+        .await?;
+        report.timings.merge_duration = merge_started.elapsed();
+        let (wav, output) = self
+            .write_output(wav, OutputFormat::Wav, output_path, report)
+            .await?;
+        if lock.is_some() {
+            project
+                .warnings
+                .extend(warnings.iter().map(ToString::to_string));
+            project.save(project_path)?;
+        }
+        report.warnings.extend(warnings);
+        report.warnings.extend(output_warnings);
+
+        report.align_to_source(&project.text);
+        report.stats = self.stats.snapshot().since(&stats_before);
+        report.timings.total = started.elapsed();
+        if self.deterministic {
+            report.clear_wall_clock();
+        }
+        self.finish_output(report, Some(&wav), output)?;
+        self.limit_report_detail(report);
+        Ok(())
+    }
+
+    /// Encode merged WAV bytes in `format` and write them to `output_path`
+    /// on the blocking thread pool, hashing them as they are written
+    ///
+    /// The time taken counts as merging in `report`. The WAV bytes are
+    /// handed back for the sidecars.
+    async fn write_output(
+        &self,
+        wav: Vec<u8>,
+        format: OutputFormat,
+        output_path: &str,
+        report: &mut ConversionReport,
+    ) -> Result<(Vec<u8>, OutputDigest)> {
+        let started = Instant::now();
+        let written = write_blocking(&self.runtime_handle(), output_path, move |path| {
+            let output = encode_to_file(&wav, format, path)?;
+            Ok((wav, output))
+        })
+        .await?;
+        if !self.deterministic {
+            let elapsed = started.elapsed();
+            report.timings.merge_duration += elapsed;
+            report.timings.total += elapsed;
+        }
+        Ok(written)
+    }
+
+    /// Path to write the output of a conversion to under the overwrite
+    /// policy, or `None` if the conversion is skipped
+    fn output_target(
+        &self,
+        output_path: &str,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<Option<String>> {
+        if self.output_preflight {
+            naming::preflight_output(Path::new(output_path))?;
+        }
+        if !Path::new(output_path).exists() {
+            return Ok(Some(output_path.to_string()));
+        }
+        match self.overwrite_policy {
+            OverwritePolicy::Overwrite => Ok(Some(output_path.to_string())),
+            OverwritePolicy::Error => Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("output file {} already exists", output_path),
+            ))),
+            OverwritePolicy::Skip => {
+                warnings.push(Warning::OutputSkipped {
+                    path: output_path.to_string(),
+                });
+                Ok(None)
+            }
+            OverwritePolicy::Rename => {
+                let renamed = naming::numbered_path(Path::new(output_path))
+                    .to_string_lossy()
+                    .into_owned();
+                warnings.push(Warning::OutputRenamed {
+                    path: output_path.to_string(),
+                    renamed: renamed.clone(),
+                });
+                Ok(Some(renamed))
+            }
+        }
+    }
+
+    /// Files a conversion to `output_path` writes: the output and its
+    /// enabled sidecars
+    fn output_files(&self, output_path: &str) -> Vec<PathBuf> {
+        let mut files = vec![PathBuf::from(output_path)];
+        if self.timing_sidecar {
+            files.push(format!("{}.segments.json", output_path).into());
+        }
+        if self.sidecar_metadata {
+            files.push(format!("{}.json", output_path).into());
+        }
+        if let Some(path) = &self.speech_marks {
+            files.push(path.clone());
+        }
+        files
+    }
+
+    /// Record the checksums of a written output in `report` and write the
+    /// enabled sidecars next to it
+    ///
+    /// `wav` is the merged audio when the output was encoded from memory;
+    /// otherwise the output itself is a WAV and its header is read.
+    fn finish_output(
+        &self,
+        report: &mut ConversionReport,
+        wav: Option<&[u8]>,
+        output: OutputDigest,
+    ) -> Result<()> {
+        let output_path = output.path.as_str();
+        if self.timing_sidecar {
+            std::fs::write(
+                format!("{}.segments.json", output_path),
+                report.to_timing_json()?,
+            )?;
+        }
+        if self.sidecar_metadata {
+            let metadata = match wav {
+                Some(wav) => OutputMetadata::from_wav_header(wav, report.segment_count, &output)?,
+                None => OutputMetadata::from_wav_header(
+                    std::io::BufReader::new(std::fs::File::open(output_path)?),
+                    report.segment_count,
+                    &output,
+                )?,
+            };
+            std::fs::write(format!("{}.json", output_path), metadata.to_json())?;
+        }
+        if let Some(path) = &self.speech_marks {
+            std::fs::write(path, report.to_speech_marks(self.speech_mark_sections)?)?;
+        }
+        report.outputs.push(output);
+        Ok(())
+    }
+
+    /// Start a report that keeps no more segment text while segments are
+    /// pushed than the configured detail and the enabled sidecars need
+    fn new_report(&self, mode: ConversionMode) -> ConversionReport {
+        let mut report = ConversionReport::new(mode);
+        // Sidecars and calibration read the text once the conversion is done
+        if self.timing_sidecar || self.speech_marks.is_some() || self.calibration_file.is_some() {
+            return report;
+        }
+        match self.report_detail {
+            Some(detail) => report.limit_pushed_detail(detail, None),
+            None => report.limit_pushed_detail(ReportDetail::Full, Some(FULL_REPORT_MAX_BYTES)),
+        }
+        report
+    }
+
+    /// Drop the segment text the configured report detail leaves out
+    fn limit_report_detail(&self, report: &mut ConversionReport) {
+        let detail = self
+            .report_detail
+            .unwrap_or(if report.text_bytes() > FULL_REPORT_MAX_BYTES {
+                ReportDetail::Hashes
+            } else {
+                ReportDetail::Full
+            });
+        report.reduce_detail(detail);
+    }
+
+    /// Split a project's text the way [`convert`](Self::convert) would
+    async fn split_project(
+        &self,
+        text: &str,
+        report: &mut ConversionReport,
+    ) -> Result<Vec<ProjectSegment>> {
+        self.check_parameters(&mut report.warnings)?;
+        let text = self.preprocess(text, &mut report.warnings);
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(Error::EmptyInput);
+        }
+
+        let max_length = self.max_length(&mut report.warnings);
+        let (segments, pauses) = match self.select_mode(text, max_length)? {
+            ConversionMode::Direct => (vec![text.to_string()], Vec::new()),
+            ConversionMode::Segmented => {
+                self.split_segments(text, max_length, &mut report.split_races)
+                    .await?
+            }
+        };
+        if segments.is_empty() {
+            return Err(Error::EmptyInput);
+        }
+
+        Ok(segments
+            .into_iter()
+            .enumerate()
+            .map(|(idx, text)| ProjectSegment {
+                text,
+                pause_after: pause_at(&pauses, idx),
+            })
+            .collect())
+    }
+
+    async fn convert_into(&self, text: &str, output: &mut Output<'_>) -> Result<ConversionReport> {
+        let mut report = self.new_report(ConversionMode::Direct);
+        let Some(dir) = &self.failure_bundle else {
+            self.convert_into_report(text, output, &mut report).await?;
+            return Ok(report);
+        };
+        let (result, recorder) =
+            bundle::capture(self.convert_into_report(text, output, &mut report)).await;
+        self.bundle_failure(dir, &result, &report, &recorder);
+        result.map(|()| report)
+    }
+
+    async fn convert_into_report(
+        &self,
+        text: &str,
+        output: &mut Output<'_>,
+        report: &mut ConversionReport,
+    ) -> Result<()> {
+        let started = Instant::now();
+        let original = text;
+        let stats_before = self.stats.snapshot();
+        self.check_parameters(&mut report.warnings)?;
+        let max_length = self.max_length(&mut report.warnings);
+        let sections = preprocess::split_sections(text);
+        if sections.len() > 1 {
+            report.mode = ConversionMode::Segmented;
+            self.convert_sections(sections, max_length, output, report)
+                .await?;
+        } else {
+            let text = self.preprocess(text, &mut report.warnings);
+            let text = text.trim();
+            if text.is_empty() {
+                return Err(Error::EmptyInput);
+            }
+
+            match self.select_mode(text, max_length)? {
+                ConversionMode::Direct => {
+                    self.convert_direct(text, max_length, output, report)
+                        .await?
+                }
+                ConversionMode::Segmented => {
+                    report.mode = ConversionMode::Segmented;
+                    self.convert_segmented(text, max_length, output, report)
+                        .await?;
+                }
+            }
+        }
+
+        if let Err(e) = self.check_output(report, output) {
+            if let Output::Path(path) = output {
+                let _ = std::fs::remove_file(path);
+            }
+            return Err(e);
+        }
+
+        self.check_segment_durations(report);
+        report.align_to_source(original);
+        report.stats = self.stats.snapshot().since(&stats_before);
+        report.timings.total = started.elapsed();
+        self.record_calibration(report).await;
+        if self.deterministic {
+            report.clear_wall_clock();
+        }
+        Ok(())
+    }
+
+    async fn convert_plan_into(
+        &self,
+        plan: ConversionPlan,
+        output: &mut Output<'_>,
+    ) -> Result<ConversionReport> {
+        let mut report = self.new_report(plan.mode);
+        let Some(dir) = &self.failure_bundle else {
+            self.convert_plan_into_report(plan, output, &mut report)
+                .await?;
+            return Ok(report);
+        };
+        let (result, recorder) =
+            bundle::capture(self.convert_plan_into_report(plan, output, &mut report)).await;
+        self.bundle_failure(dir, &result, &report, &recorder);
+        result.map(|()| report)
+    }
+
+    async fn convert_plan_into_report(
+        &self,
+        plan: ConversionPlan,
+        output: &mut Output<'_>,
+        report: &mut ConversionReport,
+    ) -> Result<()> {
+        let started = Instant::now();
+        let stats_before = self.stats.snapshot();
+        self.check_parameters(&mut report.warnings)?;
+        if plan.segments.is_empty() {
+            return Err(Error::EmptyInput);
+        }
+
+        let original = plan.segments.concat();
+        let segments = self.apply_segment_overrides(plan.segments, &mut report.warnings);
+        self.synthesize_and_merge(segments.into(), &[], &plan.pauses, output, report)
+            .await?;
+
+        if let Err(e) = self.check_output(report, output) {
+            if let Output::Path(path) = output {
+                let _ = std::fs::remove_file(path);
+            }
+            return Err(e);
+        }
+
+        self.check_segment_durations(report);
+        report.align_to_source(&original);
+        report.stats = self.stats.snapshot().since(&stats_before);
+        report.timings.total = started.elapsed();
+        self.record_calibration(report).await;
+        if self.deterministic {
+            report.clear_wall_clock();
+        }
+        Ok(())
+    }
+
+    /// Run a conversion entry point, writing a failure bundle if it fails
+    ///
+    /// Conversions inside `work` that keep a report write a more detailed
+    /// bundle themselves; a report for `mode` stands in otherwise. `work` is
+    /// boxed, as entry points that call each other would otherwise nest
+    /// their futures inline.
+    fn bundled<'a, T: 'a>(
+        &'a self,
+        mode: ConversionMode,
+        work: impl std::future::Future<Output = Result<T>> + 'a,
+    ) -> impl std::future::Future<Output = Result<T>> + 'a {
+        let work = Box::pin(work);
+        async move {
+            let Some(dir) = &self.failure_bundle else {
+                return work.await;
+            };
+            let (result, recorder) = bundle::capture(work).await;
+            self.bundle_failure(dir, &result, &self.new_report(mode), &recorder);
+            result
+        }
+    }
+
+    /// Write a failure bundle if `result` is an error that has none yet
+    fn bundle_failure<T>(
+        &self,
+        dir: &Path,
+        result: &Result<T>,
+        report: &ConversionReport,
+        recorder: &bundle::Recorder,
+    ) {
+        if let Err(e) = result {
+            if !recorder.bundle_written() {
+                self.write_failure_bundle(dir, e, report, recorder);
+            }
+            bundle::mark_written();
+        }
+    }
+
+    /// Write a failure bundle for `error`, ignoring errors while writing
+    fn write_failure_bundle(
+        &self,
+        dir: &Path,
+        error: &Error,
+        report: &ConversionReport,
+        recorder: &bundle::Recorder,
+    ) {
+        let config = bundle::BundleConfig {
+            api_key: if self.api_key.is_empty() {
+                String::new()
+            } else {
+                "<redacted>".to_string()
+            },
+            settings: self.project_settings(),
+            parallel: self.enable_parallel.then_some(self.max_parallel),
+            max_retries: self.retry_policy.max_retries(),
+            retry_delay: self.retry_policy.base_delay(),
+            output_format: self
+                .output_format
+                .map_or("inferred".to_string(), |format| format!("{:?}", format)),
+            custom_backend: self.backend.is_some(),
+        };
+        let bundle = FailureBundle::new(config, error, report, recorder, self.failure_bundle_text);
+        let _ = bundle.write(dir, recorder);
+    }
+
+    /// Convert text to a stream of per-segment audio
+    ///
+    /// Splits the text like [`convert`](Self::convert), then synthesizes the
+    /// segments one at a time in order, yielding each segment's WAV bytes as
+    /// soon as it is ready. Nothing is merged or written to disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the text is empty or splitting fails; synthesis
+    /// errors are yielded by the stream.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use futures::StreamExt;
+    /// use text2audio::Text2Audio;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let converter = Text2Audio::new("api_key");
+    /// let stream = converter.convert_stream("很长的文本……").await?;
+    /// futures::pin_mut!(stream);
+    /// while let Some(audio) = stream.next().await {
+    ///     let wav_bytes = audio?;
+    ///     println!("segment ready: {} bytes", wav_bytes.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn convert_stream(
+        &self,
+        text: &str,
+    ) -> Result<impl Stream<Item = Result<Vec<u8>>> + '_> {
+        let plan = self
+            .bundled(ConversionMode::Segmented, self.plan(text))
+            .await?;
+        let segments = self.apply_segment_overrides(plan.segments, &mut Vec::new());
+
+        Ok(self.synthesize_in_order(plan.mode, segments))
+    }
+
+    /// Synthesize segments one at a time in order, yielding each segment's
+    /// audio as soon as it is ready
+    fn synthesize_in_order(
+        &self,
+        mode: ConversionMode,
+        segments: Vec<String>,
+    ) -> impl Stream<Item = Result<Vec<u8>>> + '_ {
+        let segments: Arc<[String]> = segments.into();
+        stream::iter(0..segments.len()).then(move |index| {
+            let segments = segments.clone();
+            async move {
+                // Segments are synthesized one at a time here, even in
+                // parallel mode
+                if index > 0 {
+                    self.delay_next_request().await;
+                }
+                self.synthesize_streamed(mode, &segments, index).await
+            }
+        })
+    }
+
+    /// Synthesize segment `index` of a stream, writing a failure bundle if
+    /// it fails
+    ///
+    /// `segments` are those known so far; the ones before `index` were
+    /// already yielded.
+    async fn synthesize_streamed<S: AsRef<str>>(
+        &self,
+        mode: ConversionMode,
+        segments: &[S],
+        index: usize,
+    ) -> Result<Vec<u8>> {
+        let synthesize = async {
+            bundle::planned(segments);
+            bundle::streamed(index);
+            self.synthesize_segment(index, segments[index].as_ref())
+                .await
+        };
+        self.bundled(mode, synthesize).await.map(|(audio, _)| audio)
+    }
+
+    /// Convert text to a stream of decoded audio chunks
+    ///
+    /// Synthesizes segments like [`convert_stream`](Self::convert_stream)
+    /// and slices each segment's decoded audio into chunks of at most the
+    /// [chunk duration](Self::with_chunk_duration), the last chunk of a
+    /// segment being shorter. Without a chunk duration, each segment is one
+    /// chunk. Chunks are cut at frame boundaries and, concatenated, hold
+    /// exactly the segment's samples.
+    ///
+    /// Offsets are counted in samples and include the pause after each
+    /// segment, so they match the positions in the merged output of
+    /// [`convert`](Self::convert). A segment that fails to synthesize or
+    /// decode yields its error and no audio; the pause after it still
+    /// counts.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the text is empty or splitting fails; synthesis and
+    /// decoding errors are yielded by the stream.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use futures::StreamExt;
+    /// use std::time::Duration;
+    /// use text2audio::Text2Audio;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let converter = Text2Audio::new("api_key").with_chunk_duration(Duration::from_secs(5));
+    /// let stream = converter.convert_stream_chunks("很长的文本……").await?;
+    /// futures::pin_mut!(stream);
+    /// while let Some(chunk) = stream.next().await {
+    ///     let chunk = chunk?;
+    ///     println!(
+    ///         "segment {} chunk {} at {:?}: {} samples",
+    ///         chunk.segment_index,
+    ///         chunk.chunk_index,
+    ///         chunk.offset,
+    ///         chunk.pcm.samples().len()
+    ///     );
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn convert_stream_chunks(
+        &self,
+        text: &str,
+    ) -> Result<impl Stream<Item = Result<AudioChunk>> + '_> {
+        let chunk_duration = self.chunk_duration.unwrap_or(Duration::MAX);
+        let plan = self
+            .bundled(ConversionMode::Segmented, self.plan(text))
+            .await?;
+        let pauses = plan.pauses;
+        let segments = self.apply_segment_overrides(plan.segments, &mut Vec::new());
+        let runtime = self.runtime_handle();
+
+        Ok(self
+            .synthesize_in_order(plan.mode, segments)
+            .enumerate()
+            .then(move |(segment_index, audio)| {
+                let runtime = runtime.clone();
+                async move {
+                    let wav = audio?;
+                    run_blocking(&runtime, move || PcmAudio::from_wav(&wav, segment_index)).await
+                }
+            })
+            .enumerate()
+            .scan(Duration::ZERO, move |offset, (segment_index, pcm)| {
+                let start = *offset;
+                let chunks = pcm.map(|pcm| {
+                    *offset += frames_duration(pcm.frames().count(), pcm.sample_rate());
+                    let mut frames = 0;
+                    pcm.split(chunk_duration)
+                        .enumerate()
+                        .map(|(chunk_index, pcm)| {
+                            let chunk = AudioChunk {
+                                segment_index,
+                                chunk_index,
+                                offset: start + frames_duration(frames, pcm.sample_rate()),
+                                pcm,
+                            };
+                            frames += chunk.pcm.frames().count();
+                            chunk
+                        })
+                        .collect::<Vec<_>>()
+                });
+                *offset += pause_at(&pauses, segment_index);
+                futures::future::ready(Some(chunks))
+            })
+            .flat_map(|chunks| {
+                let chunks = match chunks {
+                    Ok(chunks) => chunks.into_iter().map(Ok).collect(),
+                    Err(e) => vec![Err(e)],
+                };
+                stream::iter(chunks)
+            }))
+    }
+
+    /// Convert text that arrives in chunks, such as streaming LLM output
+    ///
+    /// Returns an input handle and a stream of per-segment audio. Text pushed
+    /// to the handle is synthesized as soon as a sentence is complete, without
+    /// waiting for the rest of the input; sentences are split locally, never
+    /// by AI, and segments are synthesized one at a time in order. The stream
+    /// ends after [`IncrementalInput::finish`] once the remaining text is
+    /// synthesized. At most [`with_queue_depth`](Self::with_queue_depth)
+    /// segments wait for synthesis, so push from one task while another
+    /// consumes the stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the converter's settings are invalid; synthesis
+    /// errors are yielded by the stream. With
+    /// [`with_failure_bundle`](Self::with_failure_bundle), each yielded
+    /// error writes a bundle listing the segments pushed so far.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use futures::StreamExt;
+    /// use text2audio::Text2Audio;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let converter = Text2Audio::new("api_key");
+    /// let (mut input, audio) = converter.convert_incremental()?;
+    ///
+    /// let feed = async move {
+    ///     input.push("你好，").await?;
+    ///     input.push("世界！今天").await?;
+    ///     // "你好，世界！" is already being synthesized
+    ///     input.push("天气很好。").await?;
+    ///     input.finish().await;
+    ///     Ok::<_, text2audio::Error>(())
+    /// };
+    /// let play = async {
+    ///     futures::pin_mut!(audio);
+    ///     while let Some(segment) = audio.next().await {
+    ///         println!("segment ready: {} bytes", segment?.len());
+    ///     }
+    ///     Ok::<_, text2audio::Error>(())
+    /// };
+    /// futures::try_join!(feed, play)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn convert_incremental(
+        &self,
+    ) -> Result<(IncrementalInput, impl Stream<Item = Result<Vec<u8>>> + '_)> {
+        let checked = self.check_parameters(&mut Vec::new());
+        if let Some(dir) = &self.failure_bundle {
+            let report = self.new_report(ConversionMode::Segmented);
+            self.bundle_failure(dir, &checked, &report, &bundle::Recorder::default());
+        }
+        checked?;
+        let (sender, receiver) = tokio::sync::mpsc::channel(self.queue_depth);
+        let input = IncrementalInput::new(
+            SentenceBuffer::new(
+                self.max_length(&mut Vec::new()),
+                self.length_unit,
+                &self.sentence_terminators,
+            ),
+            sender,
+            self.queue_policy,
+        );
+
+        let segments = stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|segment| (segment, receiver))
+        });
+        let mut synthesized: Vec<Arc<str>> = Vec::new();
+        let audio = segments
+            .map(move |segment| self.preprocess(&segment, &mut Vec::new()).into_owned())
+            .filter(|segment| futures::future::ready(!segment.is_empty()))
+            .enumerate()
+            .then(move |(index, segment)| {
+                let segment = self.segment_overrides.get(&index).unwrap_or(&segment);
+                // Kept for failure bundles, which list the segments so far
+                synthesized.push(Arc::from(segment.as_str()));
+                let segments = synthesized.clone();
+                async move {
+                    if index > 0 {
+                        self.delay_next_request().await;
+                    }
+                    self.synthesize_streamed(ConversionMode::Segmented, &segments, index)
+                        .await
+                }
+            });
+        Ok((input, audio))
+    }
+
+    /// Convert text that arrives in chunks into a live playback buffer
+    ///
+    /// Works like [`convert_incremental`](Self::convert_incremental), but
+    /// instead of yielding WAV segments, the returned future decodes each
+    /// one into `producer`'s ring buffer as soon as it is synthesized,
+    /// waiting while the buffer is full. An audio callback drains the
+    /// matching [`LiveConsumer`]. The future completes after the input is
+    /// finished and all audio is pushed, dropping the producer so the
+    /// consumer reports [`is_finished`](LiveConsumer::is_finished). See the
+    /// [`live`] module for buffer sizing and underruns.
+    ///
+    /// Requires the `live` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the converter's settings are invalid; the future
+    /// returns synthesis errors, invalid audio, and an error once the
+    /// [`LiveConsumer`](crate::LiveConsumer) is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use text2audio::{live_buffer, Text2Audio};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let converter = Text2Audio::new("api_key");
+    /// let (producer, mut consumer) = live_buffer(24000, 1, Duration::from_secs(2));
+    /// // Move `consumer` into the output callback, e.g. of a cpal stream:
+    /// // move |data: &mut [f32], _| { consumer.fill_f32(data); }
+    ///
+    /// let (mut input, feed) = converter.convert_live(producer)?;
+    /// let text = async move {
+    ///     input.push("你好，世界！").await?;
+    ///     input.finish().await;
+    ///     Ok::<_, text2audio::Error>(())
+    /// };
+    /// futures::try_join!(text, feed)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "live")]
+    pub fn convert_live(
+        &self,
+        mut producer: LiveProducer,
+    ) -> Result<(
+        IncrementalInput,
+        impl std::future::Future<Output = Result<()>> + '_,
+    )> {
+        let (input, audio) = self.convert_incremental()?;
+        let feed = async move {
+            futures::pin_mut!(audio);
+            while let Some(segment) = audio.next().await {
+                producer.push_wav(&segment?).await?;
+            }
+            Ok(())
+        };
+        Ok((input, feed))
+    }
+
+    /// Convert text and play it while later segments are still synthesizing
+    ///
+    /// Each segment is pushed to the default audio output as soon as it is
+    /// synthesized, so playback starts after the first segment instead of
+    /// after the whole document. If synthesis falls behind playback, the
+    /// output idles until the next segment arrives. Returns once playback of
+    /// the last segment has finished.
+    ///
+    /// Requires the `playback` feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if splitting or synthesis fails, or no audio output
+    /// device is available.
+    #[cfg(feature = "playback")]
+    pub async fn convert_and_play_streaming(&self, text: &str) -> Result<()> {
+        self.bundled(
+            ConversionMode::Segmented,
+            self.convert_and_play_streaming_unbundled(text),
+        )
+        .await
+    }
+
+    #[cfg(feature = "playback")]
+    async fn convert_and_play_streaming_unbundled(&self, text: &str) -> Result<()> {
+        let stream = self.convert_stream(text).await?;
+        futures::pin_mut!(stream);
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let player = self
+            .runtime_handle()
+            .spawn_blocking(move || playback::play_queue(receiver));
+
+        let mut result = Ok(());
+        while let Some(audio) = stream.next().await {
+            match audio {
+                Ok(audio) => {
+                    if sender.send(audio).is_err() {
+                        // The player stopped early; its error is reported below
+                        break;
+                    }
+                }
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        drop(sender);
+
+        let played = player.await.map_err(|e| {
+            Error::audio(
+                AudioErrorKind::Other,
+                format!("Playback task failed: {}", e),
+            )
+        })?;
+        result.and(played)
+    }
+
+    /// Split text without synthesizing any audio
+    ///
+    /// Returns the segments that [`convert`](Self::convert) would synthesize,
+    /// together with length statistics useful for tuning
+    /// `max_segment_length`. Only the AI split request is made.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the text is empty or AI splitting fails.
+    pub async fn plan(&self, text: &str) -> Result<ConversionPlan> {
+        self.check_parameters(&mut Vec::new())?;
+        let text = self.preprocess(text, &mut Vec::new());
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(Error::EmptyInput);
+        }
+
+        let max_length = self.max_length(&mut Vec::new());
+        if self.select_mode(text, max_length)? == ConversionMode::Direct {
+            return Ok(ConversionPlan::new(
+                ConversionMode::Direct,
+                vec![text.to_string()],
+            ));
+        }
+
+        let (segments, pauses) = self
+            .split_segments(text, max_length, &mut Vec::new())
+            .await?;
+        if segments.is_empty() {
+            return Err(Error::EmptyInput);
+        }
+
+        let mut plan = ConversionPlan::new(ConversionMode::Segmented, segments);
+        for (idx, pause) in plan.pauses.iter_mut().enumerate() {
+            *pause = pause_at(&pauses, idx);
+        }
+        Ok(plan)
+    }
+
+    /// Processing mode [`convert`](Self::convert) would use for `text`
+    ///
+    /// Applies the [mode selection](Self::with_mode) without making any
+    /// requests.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the text is empty, or if direct mode is forced and
+    /// the text exceeds [`TTS_MAX_CHARS`].
+    pub fn conversion_mode(&self, text: &str) -> Result<ConversionMode> {
+        let text = self.preprocess(text, &mut Vec::new());
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(Error::EmptyInput);
+        }
+        self.select_mode(text, self.max_length(&mut Vec::new()))
+    }
+
+    /// Estimate the duration of the speech for `text`
+    ///
+    /// Counts the spoken characters after preprocessing and multiplies them
+    /// by the [calibrated](Self::with_calibration_file) rate of the current
+    /// voice and speed, or a default rate scaled by the speed. Pauses are not
+    /// included. No requests are made.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key");
+    /// assert_eq!(converter.estimate("你好，世界！"), Duration::from_millis(1500));
+    /// ```
+    pub fn estimate(&self, text: &str) -> Duration {
+        let chars = spoken_chars(&self.preprocess(text, &mut Vec::new()));
+        Duration::from_secs_f64(chars as f64 * self.seconds_per_char(&mut Vec::new()))
+    }
+
+    /// Calibrated speech rate of the current voice and speed, or the
+    /// default rate scaled by the speed
+    ///
+    /// Reads the calibration file, so a conversion calls this once and
+    /// passes the result on. A file that cannot be read is reported in
+    /// `warnings` and the default rate used.
+    fn seconds_per_char(&self, warnings: &mut Vec<Warning>) -> f64 {
+        let default = DEFAULT_SECONDS_PER_CHAR / f64::from(self.speed);
+        match self.calibration() {
+            Ok(Some(entry)) => entry.seconds_per_char,
+            Ok(None) => default,
+            Err(e) => {
+                warnings.push(Warning::CalibrationNotLoaded {
+                    message: e.to_string(),
+                });
+                default
+            }
+        }
+    }
+
+    /// Maximum segment length, or the character budget of the target
+    /// segment duration
+    ///
+    /// Resolved once per conversion and passed to the splitting steps, so
+    /// the calibration file is read once and the budget cannot change
+    /// partway through a split.
+    fn max_length(&self, warnings: &mut Vec<Warning>) -> usize {
+        match self.target_segment_duration {
+            Some(duration) => {
+                let budget = duration.as_secs_f64() / self.seconds_per_char(warnings);
+                (budget as usize).clamp(1, TTS_MAX_CHARS)
+            }
+            None => self.max_segment_length,
+        }
+    }
+
+    /// Record each segment's target duration, warning about segments that
+    /// exceed it beyond the tolerance
+    fn check_segment_durations(&self, report: &mut ConversionReport) {
+        let Some(target) = self.target_segment_duration else {
+            return;
+        };
+        for segment in &mut report.segments {
+            segment.target_duration = Some(target);
+            if exceeds_target(segment.duration, target) {
+                report.warnings.push(Warning::SegmentOverDuration {
+                    index: segment.index,
+                    duration: segment.duration,
+                    target,
+                });
+            }
+        }
+    }
+
+    /// Under strict validation, split segments whose audio exceeds the
+    /// target duration again and synthesize the pieces in their place
+    ///
+    /// Pieces are sized at the rate the segment was actually spoken at and
+    /// never cut a sentence, so a single sentence is kept whole. Every piece but the last is followed by no pause, and the first
+    /// counts the requests of the audio it replaces.
+    async fn resplit_over_duration<'a>(
+        &self,
+        segments: &'a [String],
+        synthesized: Vec<(Vec<u8>, u32)>,
+        pauses: &'a [Duration],
+        sections: &'a [ActiveSection<'a>],
+    ) -> Result<ResplitSegments<'a>> {
+        let target = match self.target_segment_duration {
+            Some(target) if self.strict_validation => target,
+            _ => {
+                return Ok((
+                    Cow::Borrowed(segments),
+                    synthesized,
+                    Cow::Borrowed(pauses),
+                    Cow::Borrowed(sections),
+                ))
+            }
+        };
+
+        let mut texts = Vec::with_capacity(segments.len());
+        let mut audio = Vec::with_capacity(synthesized.len());
+        let mut gaps = Vec::with_capacity(segments.len());
+        let mut piece_sections = Vec::with_capacity(sections.len());
+        let section = |idx: usize| sections.get(idx).copied().flatten();
+        for (idx, (text, (segment_audio, attempts))) in segments.iter().zip(synthesized).enumerate()
+        {
+            let duration = probe_bytes(&segment_audio)?.duration;
+            let pieces = if exceeds_target(duration, target) {
+                let ratio = target.as_secs_f64() / duration.as_secs_f64();
+                let budget = (text.chars().count() as f64 * ratio) as usize;
+                LocalSplitter::new(budget)
+                    .with_sentence_terminators(&self.sentence_terminators)
+                    .with_whole_sentences()
+                    .split(text)
+            } else {
+                Vec::new()
+            };
+            if pieces.len() < 2 {
+                texts.push(text.clone());
+                audio.push((segment_audio, attempts));
+                gaps.push(pause_at(pauses, idx));
+                piece_sections.push(section(idx));
+                continue;
+            }
+            // The first piece also accounts for the discarded audio's requests
+            let mut discarded = attempts;
+            for (piece_idx, piece) in pieces.iter().enumerate() {
+                let (piece_audio, piece_attempts) =
+                    self.synthesize_in_section(idx, piece, sections).await?;
+                audio.push((piece_audio, piece_attempts + std::mem::take(&mut discarded)));
+                texts.push(piece.clone());
+                piece_sections.push(section(idx));
+                gaps.push(if piece_idx + 1 == pieces.len() {
+                    pause_at(pauses, idx)
+                } else {
+                    Duration::ZERO
+                });
+            }
+        }
+        Ok((
+            Cow::Owned(texts),
+            audio,
+            Cow::Owned(gaps),
+            Cow::Owned(piece_sections),
+        ))
+    }
+
+    /// Calibrated speech rate of the current voice and speed
+    ///
+    /// Returns `None` without a [calibration file](Self::with_calibration_file)
+    /// or before a conversion with this voice and speed completed. Load the
+    /// file with [`Calibration::load`] to inspect every entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the calibration file cannot be read.
+    pub fn calibration(&self) -> Result<Option<CalibrationEntry>> {
+        match &self.calibration_file {
+            Some(path) => Ok(Calibration::load(path)?.get(self.voice, self.speed)),
+            None => Ok(None),
+        }
+    }
+
+    /// Fold a completed conversion into the calibration file
+    async fn record_calibration(&self, report: &mut ConversionReport) {
+        let Some(path) = &self.calibration_file else {
+            return;
+        };
+        let chars = report
+            .segments
+            .iter()
+            .filter_map(|segment| segment.text.as_deref())
+            .map(spoken_chars)
+            .sum();
+        let duration = report.segments.iter().map(|segment| segment.duration).sum();
+
+        let result = async {
+            let Some(_lock) =
+                FileLock::acquire(Project::lock_path(path), self.lock_timeout).await?
+            else {
+                return Err(Error::Config(
+                    "calibration file is locked by another process".to_string(),
+                ));
+            };
+            let mut calibration = Calibration::load(path)?;
+            calibration.observe(self.voice, self.speed, chars, duration);
+            calibration.save(path)
+        }
+        .await;
+        if let Err(e) = result {
+            report.warnings.push(Warning::CalibrationNotSaved {
+                message: e.to_string(),
+            });
+        }
+    }
+
+    /// Split text without synthesizing any audio and return the segments
+    ///
+    /// Shorthand for [`plan`](Self::plan) when only the segment texts are
+    /// needed. Indices into the returned list are the ones accepted by
+    /// [`with_segment_overrides`](Self::with_segment_overrides).
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the text is empty or AI splitting fails.
+    pub async fn split_only(&self, text: &str) -> Result<Vec<String>> {
+        Ok(self.plan(text).await?.segments)
+    }
+
+    /// Check up front that every voice can be synthesized
+    ///
+    /// Synthesizes a tiny probe for each distinct voice so a multi-voice job
+    /// fails before any real work is done. Results are cached on the
+    /// converter, so repeated calls only probe voices not seen before.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] listing every voice the backend rejected.
+    /// Transport errors are returned as-is and not cached, since they say
+    /// nothing about the voice.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use text2audio::{Text2Audio, Voice};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let converter = Text2Audio::new("api_key");
+    /// converter.validate_voices(&[Voice::Tongtong, Voice::Jam]).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn validate_voices(&self, voices: &[Voice]) -> Result<()> {
+        let mut unavailable = Vec::new();
+
+        for (idx, voice) in voices.iter().enumerate() {
+            if voices[..idx].contains(voice) {
+                continue;
+            }
+            let cached = self.voice_probes.lock().unwrap().get(voice).copied();
+            let available = match cached {
+                Some(available) => available,
+                None => {
+                    let available = self.probe_voice(*voice).await?;
+                    self.voice_probes.lock().unwrap().insert(*voice, available);
+                    available
+                }
+            };
+            if !available {
+                unavailable.push(voice.as_str());
+            }
+        }
+
+        if unavailable.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Config(format!(
+                "unavailable voices: {}",
+                unavailable.join(", ")
+            )))
+        }
+    }
+
+    /// Request statistics for the chat and TTS endpoints
+    ///
+    /// Accumulates every request made by this converter, including retries
+    /// and requests to a custom backend, until
+    /// [`reset_stats`](Self::reset_stats) is called. Each
+    /// [`ConversionReport`] carries the share of a single conversion.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::Text2Audio;
+    ///
+    /// let converter = Text2Audio::new("api_key");
+    /// let stats = converter.stats();
+    /// println!("TTS p95: {:?}", stats.tts.latency.p95());
+    /// println!("TTS retry rate: {:.1}%", stats.tts.retry_rate() * 100.0);
+    /// ```
+    pub fn stats(&self) -> ClientStats {
+        self.stats.snapshot()
+    }
+
+    /// Clear the accumulated request statistics
+    pub fn reset_stats(&self) {
+        self.stats.reset();
+    }
+
+    async fn probe_voice(&self, voice: Voice) -> Result<bool> {
+        let tts_config = &TtsConfig::builder().voice(voice).build()?;
+        let backend = &self.backend();
+        // Only transport errors are retried; a rejected voice stays rejected
+        self.retry_policy
+            .run(
+                |attempt| async move {
+                    match timed_synthesize(
+                        backend.as_ref(),
+                        &self.stats,
+                        VOICE_PROBE_TEXT,
+                        tts_config,
+                        attempt > 0,
+                    )
+                    .await
+                    {
+                        Ok(_) => Ok(true),
+                        Err(e) if e.is_transport() => Err(e),
+                        Err(_) => Ok(false),
+                    }
+                },
+                |e| backend.classify(e),
+            )
+            .await
+            .0
+    }
+
+    /// Substitute the default for a non-finite setting, remembering a warning
+    /// that replaces any left by an earlier call for the same setting
+    fn finite_or_default(&mut self, name: &'static str, value: f32, default: f32) -> f32 {
+        self.forget_non_finite(name);
+        if value.is_finite() {
+            return value;
+        }
+        self.remember_non_finite(name, value, default);
+        default
+    }
+
+    /// Drop the warnings about non-finite values of setting `name`
+    fn forget_non_finite(&mut self, name: &'static str) {
+        self.parameter_warnings.retain(
+            |warning| !matches!(warning, Warning::NonFiniteParameter { name: n, .. } if *n == name),
+        );
+    }
+
+    fn remember_non_finite(&mut self, name: &'static str, value: f32, default: f32) {
+        self.parameter_warnings.push(Warning::NonFiniteParameter {
+            name,
+            value,
+            default,
+        });
+    }
+
+    /// Features enabled for the AI splitting model that it does not
+    /// support, as listed in [`MODEL_CAPABILITIES`]
+    fn unsupported_model_features(&self) -> Vec<Warning> {
+        if self.split_backend.is_some() {
+            return Vec::new();
+        }
+        let capabilities = self.model.capabilities();
+        [
+            ("thinking", self.enable_thinking, capabilities.thinking),
+            (
+                "the coding plan endpoint",
+                self.coding_plan,
+                capabilities.coding_plan,
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, enabled, supported)| *enabled && !supported)
+        .map(|(feature, _, _)| Warning::UnsupportedModelFeature {
+            model: self.model,
+            feature,
+        })
+        .collect()
+    }
+
+    /// Surface settings replaced by their defaults or left off for the
+    /// model, failing in strict mode, on an invalid split regex or on empty
+    /// sentence terminators
+    fn check_parameters(&self, warnings: &mut Vec<Warning>) -> Result<()> {
+        if let Some(Err(message)) = &self.split_pattern {
+            return Err(Error::Config(message.clone()));
+        }
+        if self.sentence_terminators.is_empty() {
+            return Err(Error::Config(
+                "Sentence terminators must not be empty".to_string(),
+            ));
+        }
+        let mut parameter_warnings = self.parameter_warnings.clone();
+        parameter_warnings.extend(self.unsupported_model_features());
+        if self.strict_validation {
+            if let Some(warning) = parameter_warnings.first() {
+                return Err(Error::Config(warning.to_string()));
+            }
+        }
+        warnings.extend(parameter_warnings);
+        Ok(())
+    }
+
+    /// Reject output that is malformed in strict RIFF mode, empty, or below
+    /// the minimum duration
+    fn check_output(&self, report: &ConversionReport, output: &Output<'_>) -> Result<()> {
+        if self.strict_riff {
+            output.validate_riff()?;
+        }
+
+        let total: Duration = report
+            .segments
+            .iter()
+            .map(|segment| segment.duration + segment.pause_after)
+            .sum();
+        if !total.is_zero() && total >= self.min_duration {
+            return Ok(());
+        }
+
+        let mut reasons = Vec::new();
+        if !total.is_zero() {
+            reasons.push(format!(
+                "{:.3}s is below the minimum of {:.3}s",
+                total.as_secs_f64(),
+                self.min_duration.as_secs_f64()
+            ));
+        }
+        let empty = report
+            .segments
+            .iter()
+            .filter(|segment| segment.duration.is_zero())
+            .count();
+        if empty > 0 {
+            reasons.push(format!(
+                "{} of {} segments returned no audio",
+                empty, report.segment_count
+            ));
+        }
+        let rewritten = report
+            .warnings
+            .iter()
+            .filter(|warning| matches!(warning, Warning::LongToken { .. }))
+            .count();
+        if let (Some((_, policy)), true) = (self.long_token_policy, rewritten > 0) {
+            reasons.push(format!(
+                "long token policy {:?} rewrote {} tokens",
+                policy, rewritten
+            ));
+        }
+
+        let kind = if total.is_zero() {
+            AudioErrorKind::Empty
+        } else {
+            AudioErrorKind::TooShort
+        };
+        Err(Error::audio(
+            kind,
+            format!("produced no audio: {}", reasons.join("; ")),
+        ))
+    }
+
+    fn preprocess<'a>(&self, text: &'a str, warnings: &mut Vec<Warning>) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text.trim());
+        // Section markers are never read aloud, whichever path the text takes
+        if text.contains("<!--") {
+            text = rewrite(text, preprocess::strip_section_markers);
+        }
+        // Links go next, so "[1](url)" is not taken for a footnote marker
+        if self.strip_link_urls {
+            text = rewrite(text, preprocess::strip_link_urls);
+        }
+        // Footnotes go next, before superscripts are read as powers
+        if self.footnote_policy.is_some() || self.footnote_bodies != FootnoteBodies::Keep {
+            text = rewrite(text, |text| {
+                preprocess::apply_footnote_policy(
+                    text,
+                    self.footnote_policy.unwrap_or(FootnotePolicy::Keep),
+                    self.footnote_bodies,
+                    self.symbol_language,
+                )
+            });
+        }
+        // Numbers go before symbols, so "3/4" is not read as a division
+        if let Some(context) = &self.numeric_context {
+            text = rewrite(text, |text| self.normalize_numbers(text, context, warnings));
+        }
+        if self.symbol_verbalization {
+            text = rewrite(text, |text| self.verbalize_symbols(text, warnings));
+        }
+        if let Some((max_chars, policy)) = self.long_token_policy {
+            text = rewrite(text, |text| {
+                preprocess::apply_long_token_policy(text, max_chars, policy, warnings)
+            });
+        }
+        // Spacing runs last so it sees the final text of every segment
+        if let Some(mode) = self.cjk_latin_spacing {
+            text = rewrite(text, |text| preprocess::apply_cjk_latin_spacing(text, mode));
+        }
+        text
+    }
+
+    /// Replace symbols with readings, warning if too many are unknown
+    fn verbalize_symbols<'a>(&self, text: &'a str, warnings: &mut Vec<Warning>) -> Cow<'a, str> {
+        let mut unknown = Vec::new();
+        let text = preprocess::verbalize_symbols(
+            text,
+            self.symbol_language,
+            &self.symbol_readings,
+            &mut unknown,
+        );
+        if unknown.len() > self.unknown_symbol_threshold {
+            let mut symbols = Vec::new();
+            for &symbol in &unknown {
+                if !symbols.contains(&symbol) {
+                    symbols.push(symbol);
+                }
+            }
+            warnings.push(Warning::UnknownSymbols {
+                symbols,
+                count: unknown.len(),
+                threshold: self.unknown_symbol_threshold,
+            });
+        }
+        text
+    }
+
+    /// Replace numbers with readings, warning about ambiguous ones
+    fn normalize_numbers<'a>(
+        &self,
+        text: &'a str,
+        context: &NumericContext,
+        warnings: &mut Vec<Warning>,
+    ) -> Cow<'a, str> {
+        let mut ambiguous = Vec::new();
+        let text = numeric::normalize_numbers(text, context, &mut ambiguous);
+        if !ambiguous.is_empty() {
+            let mut samples = Vec::new();
+            for number in &ambiguous {
+                if samples.len() < AMBIGUOUS_NUMBER_SAMPLES && !samples.contains(number) {
+                    samples.push(number.clone());
+                }
+            }
+            warnings.push(Warning::AmbiguousNumbers {
+                samples,
+                count: ambiguous.len(),
+            });
+        }
+        text
+    }
+
+    fn splitter(&self, max_length: usize) -> AiSplitter {
+        let splitter = AiSplitter::new(self.api_key.clone(), self.model, max_length)
+            .with_thinking(self.enable_thinking)
+            .with_coding_plan(self.coding_plan)
+            .with_greedy_decoding(self.deterministic)
+            .with_inter_request_delay(self.inter_request_delay)
+            .with_max_prompt_chars(self.max_prompt_chars)
+            .with_length_unit(self.length_unit)
+            .with_choice_selection(self.choice_selection)
+            .with_retry_policy(self.retry_policy.clone())
+            .with_stats(self.stats.clone());
+        match self.max_prompt_bytes {
+            Some(max_bytes) => splitter.with_max_prompt_bytes(max_bytes),
+            None => splitter,
+        }
+    }
+
+    async fn convert_direct(
+        &self,
+        text: &str,
+        max_length: usize,
+        output: &mut Output<'_>,
+        report: &mut ConversionReport,
+    ) -> Result<()> {
+        let segments = self.apply_segment_overrides(vec![text.to_string()], &mut report.warnings);
+        bundle::planned(&segments);
+        let text = segments[0].as_str();
+
+        let char_count = text.chars().count();
+        if char_count > TTS_MAX_CHARS {
+            // A single request would be rejected by the API; split locally instead
+            report.warnings.push(Warning::DirectFallback {
+                chars: char_count,
+                limit: TTS_MAX_CHARS,
+            });
+            report.mode = ConversionMode::Segmented;
+            let split_started = Instant::now();
+            let segments = self.local_splitter(max_length).split(text);
+            report.timings.split_duration += split_started.elapsed();
+            return self
+                .synthesize_and_merge(segments.into(), &[], &[], output, report)
+                .await;
+        }
+
+        let synth_started = Instant::now();
+        let (audio_bytes, attempts) = match self.stall_timeout {
+            Some(timeout) => {
+                let work = watchdog::guard(
+                    timeout,
+                    self.abort_on_stall,
+                    &mut report.warnings,
+                    self.synthesize_segment(0, text),
+                );
+                self.tracked(1, work).await?
+            }
+            None => self.tracked(1, self.synthesize_segment(0, text)).await?,
+        };
+        report.timings.synth_duration += synth_started.elapsed();
+        report.push_segment(text, &audio_bytes, SegmentSource::Synthesized { attempts })?;
+
+        let merge_started = Instant::now();
+        let result = if self.has_post_processing() {
+            // A one-segment merge, so the output matches the segmented path
+            report.pad_last_segment(self.uniform_segment_duration);
+            self.merge_to_output(vec![audio_bytes], &[], output, &mut report.warnings)
+                .await
+        } else {
+            output
+                .write_single(&audio_bytes, self.preserve_extra_chunks)
+                .await
+        };
+        report.timings.merge_duration += merge_started.elapsed();
+        result
+    }
+
+    /// Whether any option applied while merging is set
+    ///
+    /// Direct conversions only bypass the merge when this is false.
+    fn has_post_processing(&self) -> bool {
+        self.uniform_segment_duration.is_some()
+            || self.audio_inspector.is_some()
+            || self.require_nonsilent
+            || self.target_loudness.is_some()
+    }
+
+    async fn convert_segmented(
+        &self,
+        text: &str,
+        max_length: usize,
+        output: &mut Output<'_>,
+        report: &mut ConversionReport,
+    ) -> Result<()> {
+        let split_started = Instant::now();
+        let (segments, pauses) = self
+            .split_segments(text, max_length, &mut report.split_races)
+            .await?;
+        report.timings.split_duration += split_started.elapsed();
+        let segments = self.apply_segment_overrides(segments, &mut report.warnings);
+        self.synthesize_and_merge(segments.into(), &[], &pauses, output, report)
+            .await
+    }
+
+    /// Convert text split at section markers, each section under its profile
+    async fn convert_sections(
+        &self,
+        sections: Vec<(Option<&str>, &str)>,
+        max_length: usize,
+        output: &mut Output<'_>,
+        report: &mut ConversionReport,
+    ) -> Result<()> {
+        let split_started = Instant::now();
+        let mut segments = Vec::new();
+        let mut pauses = Vec::new();
+        let mut profiles = Vec::new();
+        let mut active: ActiveSection = None;
+        // Newlines since the last segment, which may span several sections
+        let mut newlines = 0;
+        for (name, raw) in sections {
+            if let Some(name) = name {
+                match self.section_profiles.get_key_value(name) {
+                    Some((name, profile)) => active = Some((name, profile)),
+                    None if name == DEFAULT_SECTION => active = None,
+                    None => report.warnings.push(Warning::UnknownSection {
+                        name: name.to_string(),
+                    }),
+                }
+            }
+            let text = self.preprocess(raw, &mut report.warnings);
+            let text = text.trim();
+            if text.is_empty() {
+                newlines += raw.matches('\n').count();
+                continue;
+            }
+            // A newline pause across the boundary, as within a section
+            let leading = &raw[..raw.len() - raw.trim_start().len()];
+            newlines += leading.matches('\n').count();
+            if let (Some(last), Some((short, long))) = (pauses.last_mut(), self.newline_pauses) {
+                match newlines {
+                    0 => {}
+                    1 => *last += short,
+                    _ => *last += long,
+                }
+            }
+            newlines = raw[raw.trim_end().len()..].matches('\n').count();
+            let (section_segments, section_pauses) = match self.select_mode(text, max_length)? {
+                ConversionMode::Direct => (vec![text.to_string()], Vec::new()),
+                ConversionMode::Segmented => {
+                    self.split_segments(text, max_length, &mut report.split_races)
+                        .await?
+                }
+            };
+            let gap = active
+                .and_then(|(_, profile)| profile.gap)
+                .unwrap_or_default();
+            for (i, segment) in section_segments.into_iter().enumerate() {
+                segments.push(segment);
+                pauses.push(pause_at(&section_pauses, i) + gap);
+                profiles.push(active);
+            }
+        }
+        if let Some(last) = pauses.last_mut() {
+            *last = Duration::ZERO;
+        }
+        let segments = self.apply_segment_overrides(segments, &mut report.warnings);
+        report.timings.split_duration += split_started.elapsed();
+        self.synthesize_and_merge(segments.into(), &profiles, &pauses, output, report)
+            .await
+    }
+
+    /// Split runs of list items locally under list awareness, and the rest
+    /// with [`ai_split`](Self::ai_split)
+    async fn split_lists(
+        &self,
+        text: &str,
+        max_length: usize,
+        races: &mut Vec<SplitRace>,
+    ) -> Result<Vec<String>> {
+        if !self.list_awareness {
+            return self.ai_split(text, max_length, races).await;
+        }
+        let mut segments = Vec::new();
+        for (block, list) in list_blocks(text) {
+            if list {
+                segments.extend(self.local_splitter(max_length).split(block));
+            } else if !block.trim().is_empty() {
+                segments.extend(self.ai_split(block.trim(), max_length, races).await?);
+            }
+        }
+        Ok(segments)
+    }
+
+    /// Split with the AI splitter under the split strategy, recording the
+    /// outcome of a race in `races`
+    async fn ai_split(
+        &self,
+        text: &str,
+        max_length: usize,
+        races: &mut Vec<SplitRace>,
+    ) -> Result<Vec<String>> {
+        // The splitter returns short text as is, without a request to record
+        if self.length_unit.fits(text, max_length) {
+            return self.splitter(max_length).split(text).await;
+        }
+        match self.split_strategy {
+            SplitStrategy::Ai => self.request_split(text, max_length).await,
+            // Waiting out the AI split keeps the outcome off the clock
+            SplitStrategy::Race { .. } if self.deterministic => Ok(self
+                .race_split(text, max_length, Duration::MAX, races)
+                .await),
+            SplitStrategy::Race { deadline } => {
+                Ok(self.race_split(text, max_length, deadline, races).await)
+            }
+        }
+    }
+
+    /// Split with the split backend, recording the chat request
+    ///
+    /// Splitting reports its own progress, before the segments to
+    /// synthesize are known.
+    async fn request_split(&self, text: &str, max_length: usize) -> Result<Vec<String>> {
+        self.tracked(0, self.request_split_untracked(text, max_length))
+            .await
+    }
+
+    async fn request_split_untracked(&self, text: &str, max_length: usize) -> Result<Vec<String>> {
+        let cached = self
+            .split_cache
+            .as_ref()
+            .map(|cache| (cache, self.splitter(max_length).cache_key(text)));
+        if let Some((cache, key)) = &cached {
+            if let Some(segments) = cache.get(key) {
+                return Ok(segments);
+            }
+        }
+
+        progress::split_started();
+        let result = match &self.split_backend {
+            Some(backend) => {
+                let (result, _) = self
+                    .retry_policy
+                    .run(
+                        |attempt| async move {
+                            let started = Instant::now();
+                            let result = backend.split(text).await;
+                            self.stats.record(
+                                Endpoint::Chat,
+                                started.elapsed(),
+                                result.is_ok(),
+                                attempt > 0,
+                            );
+                            result
+                        },
+                        |e| backend.classify(e),
+                    )
+                    .await;
+                result
+            }
+            // Retries and records each chunk request, so a failed chunk
+            // does not repeat the ones before it
+            None => self.splitter(max_length).split(text).await,
+        };
+        if let (Some((cache, key)), Ok(segments)) = (&cached, &result) {
+            cache.put(key, segments);
+        }
+        result
+    }
+
+    /// Rule-based splitter for segments of the configured length
+    fn local_splitter(&self, max_length: usize) -> LocalSplitter {
+        LocalSplitter::new(max_length)
+            .with_length_unit(self.length_unit)
+            .with_sentence_terminators(&self.sentence_terminators)
+            .with_list_awareness(self.list_awareness)
+    }
+
+    /// Use the AI split only if it arrives within `deadline` and beats the
+    /// rule-based split by [`RACE_MARGIN`](plan::RACE_MARGIN)
+    async fn race_split(
+        &self,
+        text: &str,
+        max_length: usize,
+        deadline: Duration,
+        races: &mut Vec<SplitRace>,
+    ) -> Vec<String> {
+        let fits = |segment: &str| self.length_unit.fits(segment, max_length);
+        let rule_based = self.local_splitter(max_length).split(text);
+        let rule_based_score = plan::split_score(&rule_based, fits);
+
+        // Scores are at most 1.0, so past this point the AI split cannot win
+        let ai = if rule_based_score + plan::RACE_MARGIN > 1.0 {
+            None
+        } else {
+            match tokio::time::timeout(deadline, self.request_split(text, max_length)).await {
+                Ok(Ok(segments)) if !segments.is_empty() => Some(segments),
+                _ => None,
+            }
+        };
+        let ai_score = ai
+            .as_ref()
+            .map(|segments| plan::split_score(segments, fits));
+
+        let (chosen, segments) = match (ai, ai_score) {
+            (Some(segments), Some(score)) if score >= rule_based_score + plan::RACE_MARGIN => {
+                (SplitChoice::Ai, segments)
+            }
+            _ => (SplitChoice::RuleBased, rule_based),
+        };
+        races.push(SplitRace {
+            chosen,
+            rule_based_score,
+            ai_score,
+        });
+        segments
+    }
+
+    fn has_newline_pauses(&self, text: &str) -> bool {
+        self.newline_pauses.is_some() && text.contains('\n')
+    }
+
+    fn split_pattern(&self) -> Option<&SplitPattern> {
+        self.split_pattern
+            .as_ref()
+            .and_then(|pattern| pattern.as_ref().ok())
+    }
+
+    /// Whether newline pauses or the split regex put a boundary in `text`
+    fn has_forced_boundaries(&self, text: &str) -> bool {
+        self.has_newline_pauses(text)
+            || self
+                .split_pattern()
+                .is_some_and(|pattern| pattern.is_match(text))
+    }
+
+    /// Mode for preprocessed, trimmed text under the mode selection
+    fn select_mode(&self, text: &str, max_length: usize) -> Result<ConversionMode> {
+        match self.mode {
+            ModeSelection::Auto => {
+                if self.length_unit.fits(text, max_length) && !self.has_forced_boundaries(text) {
+                    Ok(ConversionMode::Direct)
+                } else {
+                    Ok(ConversionMode::Segmented)
+                }
+            }
+            ModeSelection::ForceDirect => {
+                let chars = text.chars().count();
+                if chars > TTS_MAX_CHARS {
+                    return Err(Error::Config(format!(
+                        "direct mode was forced, but the text has {} chars and a TTS request \
+                         accepts at most {}",
+                        chars, TTS_MAX_CHARS
+                    )));
+                }
+                Ok(ConversionMode::Direct)
+            }
+            ModeSelection::ForceSegmented => Ok(ConversionMode::Segmented),
+        }
+    }
+
+    /// Split text into segments and the silence to insert after each
+    ///
+    /// With newline pauses enabled, every newline becomes a segment boundary
+    /// and each block is split on its own, so the pause lands exactly between
+    /// the segments on either side of it. Matches of the split regex are
+    /// boundaries too, without a pause.
+    async fn split_segments(
+        &self,
+        text: &str,
+        max_length: usize,
+        races: &mut Vec<SplitRace>,
+    ) -> Result<(Vec<String>, Vec<Duration>)> {
+        let split_pattern = self.split_pattern();
+        if self.newline_pauses.is_none() && split_pattern.is_none() {
+            return Ok((self.split_lists(text, max_length, races).await?, Vec::new()));
+        }
+
+        // Splitting at newlines first gives the same boundaries as
+        // splitting at matches first, and keeps each pause on its block
+        let blocks = match self.newline_pauses {
+            Some(_) => split_at_newlines(text),
+            None => vec![(text, None)],
+        };
+        let mut segments = Vec::new();
+        let mut pauses = Vec::new();
+        for (block, pause) in blocks {
+            let pieces = match split_pattern {
+                Some(pattern) => pattern.split(block),
+                None => vec![block],
+            };
+            for piece in pieces {
+                if self.length_unit.fits(piece, max_length) {
+                    segments.push(piece.to_string());
+                } else {
+                    segments.extend(self.split_lists(piece, max_length, races).await?);
+                }
+            }
+            pauses.resize(segments.len(), Duration::ZERO);
+            if let (Some(last), Some(pause), Some((short, long))) =
+                (pauses.last_mut(), pause, self.newline_pauses)
+            {
+                *last = match pause {
+                    Pause::Short => short,
+                    Pause::Long => long,
+                };
+            }
+        }
+
+        Ok((segments, pauses))
+    }
+
+    fn apply_segment_overrides(
+        &self,
+        mut segments: Vec<String>,
+        warnings: &mut Vec<Warning>,
+    ) -> Vec<String> {
+        if self.segment_overrides.is_empty() {
+            return segments;
+        }
+
+        let mut unused: Vec<usize> = self
+            .segment_overrides
+            .keys()
+            .filter(|&&index| index >= segments.len())
+            .copied()
+            .collect();
+        unused.sort_unstable();
+        for index in unused {
+            warnings.push(Warning::UnusedSegmentOverride {
+                index,
+                segment_count: segments.len(),
+            });
+        }
+
+        for (index, segment) in segments.iter_mut().enumerate() {
+            if let Some(text) = self.segment_overrides.get(&index) {
+                segment.clone_from(text);
+            }
+        }
+        segments
+    }
+
+    /// Run `work`, reporting the progress of its `total` segments to the
+    /// progress handler, if any
+    async fn tracked<T>(&self, total: usize, work: impl std::future::Future<Output = T>) -> T {
+        match &self.progress_handler {
+            Some(handler) => {
+                progress::track(
+                    total,
+                    handler.clone(),
+                    self.progress_heartbeat,
+                    self.retry_policy.clone(),
+                    work,
+                )
+                .await
+            }
+            None => work.await,
+        }
+    }
+
+    /// Synthesize segments and merge them, inserting `pauses[i]` of silence
+    /// after segment `i`
+    ///
+    /// Segment `i` is read under the profile of `sections[i]`; segments past
+    /// the end of `sections`, all of them if it is empty, use the
+    /// converter's own settings. Segments are shared with the parallel
+    /// pipeline rather than copied.
+    async fn synthesize_and_merge(
+        &self,
+        segments: Arc<[String]>,
+        sections: &[ActiveSection<'_>],
+        pauses: &[Duration],
+        output: &mut Output<'_>,
+        report: &mut ConversionReport,
+    ) -> Result<()> {
+        self.tracked(
+            segments.len(),
+            self.synthesize_and_merge_watched(segments, sections, pauses, output, report),
+        )
+        .await
+    }
+
+    async fn synthesize_and_merge_watched(
+        &self,
+        segments: Arc<[String]>,
+        sections: &[ActiveSection<'_>],
+        pauses: &[Duration],
+        output: &mut Output<'_>,
+        report: &mut ConversionReport,
+    ) -> Result<()> {
+        let Some(timeout) = self.stall_timeout else {
+            return self
+                .synthesize_and_merge_unwatched(segments, sections, pauses, output, report)
+                .await;
+        };
+        let mut stall_warnings = Vec::new();
+        let result = watchdog::guard(
+            timeout,
+            self.abort_on_stall,
+            &mut stall_warnings,
+            self.synthesize_and_merge_unwatched(segments, sections, pauses, output, report),
+        )
+        .await;
+        report.warnings.extend(stall_warnings);
+        result
+    }
+
+    async fn synthesize_and_merge_unwatched(
+        &self,
+        segments: Arc<[String]>,
+        sections: &[ActiveSection<'_>],
+        pauses: &[Duration],
+        output: &mut Output<'_>,
+        report: &mut ConversionReport,
+    ) -> Result<()> {
+        if segments.is_empty() {
+            return Err(Error::EmptyInput);
+        }
+        bundle::planned(&segments);
+
+        if let (Some(limit_bytes), Output::Path(output_path)) = (self.max_memory, &*output) {
+            return self
+                .synthesize_and_merge_bounded(
+                    &segments,
+                    sections,
+                    pauses,
+                    output_path,
+                    report,
+                    limit_bytes,
+                )
+                .await;
+        }
+
+        let synth_started = Instant::now();
+        let synthesized = if self.enable_parallel {
+            let results = self.synthesize_parallel(segments.clone(), sections).await?;
+            self.finish_parallel(&segments, results, report).await?
+        } else {
+            self.collect_audio_sequential(&segments, sections).await?
+        };
+        report.timings.synth_duration += synth_started.elapsed();
+
+        let (segments, synthesized, pauses, sections) = self
+            .resplit_over_duration(&segments[..], synthesized, pauses, sections)
+            .await?;
+
+        let mut audio_segments = Vec::with_capacity(synthesized.len());
+        for (idx, (text, (audio, attempts))) in segments.iter().zip(synthesized).enumerate() {
+            report.push_segment(text, &audio, SegmentSource::Synthesized { attempts })?;
+            report.pad_last_segment(self.uniform_segment_duration);
+            report.push_pause(pause_at(&pauses, idx));
+            record_section(report, &sections, idx);
+            audio_segments.push(audio);
+        }
+
+        let merge_started = Instant::now();
+        let result = self
+            .merge_to_output(audio_segments, &pauses, output, &mut report.warnings)
+            .await;
+        report.timings.merge_duration += merge_started.elapsed();
+        result
+    }
+
+    /// Merge segments into `output`, running the audio inspector first
+    ///
+    /// The merge runs on the blocking thread pool.
+    async fn merge_to_output(
+        &self,
+        audio_segments: Vec<Vec<u8>>,
+        pauses: &[Duration],
+        output: &mut Output<'_>,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<()> {
+        let job = MergeJob {
+            audio_inspector: self.audio_inspector.clone(),
+            require_nonsilent: self.require_nonsilent,
+            target_loudness: self.target_loudness,
+            strict_validation: self.strict_validation,
+            uniform_segment_duration: self.uniform_segment_duration,
+            merge_threads: self.merge_threads,
+        };
+        let pauses = pauses.to_vec();
+        let runtime = self.runtime_handle();
+        let merge_warnings = match output {
+            Output::Path(path) => {
+                write_blocking(&runtime, path, move |path| {
+                    job.run(audio_segments, &pauses, &mut Output::Path(path))
+                })
+                .await?
+            }
+            Output::Memory(buffer) => {
+                let (wav, merge_warnings) = run_blocking(&runtime, move || {
+                    let mut wav = Vec::new();
+                    let warnings =
+                        job.run(audio_segments, &pauses, &mut Output::Memory(&mut wav))?;
+                    Ok((wav, warnings))
+                })
+                .await?;
+                **buffer = wav;
+                merge_warnings
+            }
+        };
+        warnings.extend(merge_warnings);
+        Ok(())
+    }
+
+    /// Synthesize in order, switching to an incremental on-disk merge once
+    /// the buffered audio exceeds `limit_bytes`
+    async fn synthesize_and_merge_bounded(
+        &self,
+        segments: &[String],
+        sections: &[ActiveSection<'_>],
+        pauses: &[Duration],
+        output_path: &str,
+        report: &mut ConversionReport,
+        limit_bytes: usize,
+    ) -> Result<()> {
+        // Segments written incrementally are never held together, so these
+        // could not be applied
+        if self.target_loudness.is_some()
+            || self.require_nonsilent
+            || self.audio_inspector.is_some()
+        {
+            return Err(Error::Config(
+                "with_max_memory cannot be combined with loudness normalization, \
+                 with_require_nonsilent or with_audio_inspector"
+                    .to_string(),
+            ));
+        }
+        let concurrency = if self.enable_parallel {
+            self.max_parallel
+        } else {
+            1
+        };
+        let mut results = stream::iter(segments.iter().enumerate())
+            .map(|(index, segment)| {
+                self.after_delay(index, self.synthesize_in_section(index, segment, sections))
+            })
+            .buffered(concurrency);
+
+        let mut held = Vec::new();
+        let mut held_bytes = 0;
+        let mut merger: Option<IncrementalMerger> = None;
+        let runtime = self.runtime_handle();
+        let mut idx = 0;
+        let started = Instant::now();
+        let mut merging = Duration::ZERO;
+
+        while let Some(result) = results.next().await {
+            let (audio, attempts) = result?;
+            let merge_started = Instant::now();
+            report.push_segment(
+                &segments[idx],
+                &audio,
+                SegmentSource::Synthesized { attempts },
+            )?;
+            report.pad_last_segment(self.uniform_segment_duration);
+            report.push_pause(pause_at(pauses, idx));
+            record_section(report, sections, idx);
+
+            match merger.take() {
+                Some(incremental) => {
+                    let pause = pause_at(pauses, idx);
+                    merger =
+                        Some(append_blocking(&runtime, incremental, vec![(audio, pause)]).await?);
+                    watchdog::progressed();
+                }
+                None => {
+                    held_bytes += audio.len();
+                    held.push(audio);
+                    if held_bytes > limit_bytes {
+                        report
+                            .warnings
+                            .push(Warning::IncrementalMerge { limit_bytes });
+                        let mut incremental = IncrementalMerger::new(output_path);
+                        if let Some(duration) = self.uniform_segment_duration {
+                            incremental = incremental.with_uniform_segment_duration(duration);
+                        }
+                        let segments = held
+                            .drain(..)
+                            .enumerate()
+                            .map(|(held_idx, audio)| (audio, pause_at(pauses, held_idx)))
+                            .collect();
+                        merger = Some(append_blocking(&runtime, incremental, segments).await?);
+                    }
+                }
+            }
+            merging += merge_started.elapsed();
+            idx += 1;
+        }
+        report.timings.synth_duration += started.elapsed().saturating_sub(merging);
+
+        let merge_started = Instant::now();
+        match merger {
+            Some(merger) => report
+                .warnings
+                .extend(write_blocking(&runtime, output_path, move |_| merger.finalize()).await?),
+            None => {
+                self.merge_to_output(
+                    held,
+                    pauses,
+                    &mut Output::Path(output_path),
+                    &mut report.warnings,
+                )
+                .await?
+            }
+        }
+        report.timings.merge_duration += merging + merge_started.elapsed();
+        Ok(())
+    }
+
+    fn backend(&self) -> Arc<dyn TtsBackend> {
+        match &self.backend {
+            Some(backend) => backend.clone(),
+            None => {
+                let client = Client::new(self.api_key.clone());
+                match self.response_handler.clone() {
+                    Some(handler) => {
+                        Arc::new(client.with_response_handler(move |body| handler(body)))
+                    }
+                    None => Arc::new(client),
+                }
+            }
+        }
+    }
+
+    /// TTS settings for segment `index` holding `text`, shared by every
+    /// request for it
+    fn segment_tts_config(&self, index: usize, text: &str, voice: Voice) -> Result<TtsConfig> {
+        self.tts_config(index, text, voice, self.speed, self.volume)
+    }
+
+    /// TTS settings for segment `index` under the profile of
+    /// `sections[index]`, or the converter's outside any section
+    fn section_segment_config(
+        &self,
+        index: usize,
+        text: &str,
+        sections: &[ActiveSection<'_>],
+    ) -> Result<TtsConfig> {
+        match sections.get(index).copied().flatten() {
+            Some((_, profile)) => self.section_tts_config(index, text, Some(profile)),
+            None => self.segment_tts_config(index, text, self.voice),
+        }
+    }
+
+    /// TTS settings for segment `index` of a section, falling back to the
+    /// converter's settings where the profile sets none
+    fn section_tts_config(
+        &self,
+        index: usize,
+        text: &str,
+        profile: Option<&SectionProfile>,
+    ) -> Result<TtsConfig> {
+        let profile = profile.cloned().unwrap_or_default();
+        let speed = profile.speed.unwrap_or(self.speed);
+        let volume = profile.volume.unwrap_or(self.volume);
+        self.tts_config(
+            index,
+            text,
+            profile.voice.unwrap_or(self.voice),
+            speed.clamp(*SPEED_RANGE.start(), *SPEED_RANGE.end()),
+            volume.clamp(*VOLUME_RANGE.start(), *VOLUME_RANGE.end()),
+        )
+    }
+
+    fn tts_config(
+        &self,
+        index: usize,
+        text: &str,
+        voice: Voice,
+        speed: f32,
+        volume: f32,
+    ) -> Result<TtsConfig> {
+        let mut config = TtsConfig::builder()
+            .voice(voice)
+            .speed(speed)
+            .volume(volume)
+            .build()?;
+        if self.idempotency_keys {
+            config.idempotency_key = Some(segment_idempotency_key(index, text, &config));
+        }
+        Ok(config)
+    }
+
+    /// Synthesize segment `index`, returning its audio and the number of
+    /// requests it took
+    ///
+    /// The single entry point for synthesizing a segment in the converter's
+    /// voice; the sequential and parallel paths both go through it, so every
+    /// TTS setting applies to both alike.
+    async fn synthesize_segment(&self, index: usize, text: &str) -> Result<(Vec<u8>, u32)> {
+        self.text_to_audio_in_voice(index, text, self.voice).await
+    }
+
+    /// Synthesize segment `index` under the profile of `sections[index]`,
+    /// or like [`synthesize_segment`](Self::synthesize_segment) outside any
+    /// section
+    async fn synthesize_in_section(
+        &self,
+        index: usize,
+        text: &str,
+        sections: &[ActiveSection<'_>],
+    ) -> Result<(Vec<u8>, u32)> {
+        let tts_config = self.section_segment_config(index, text, sections)?;
+        self.synthesize_with_config(index, text, &tts_config).await
+    }
+
+    /// Synthesize one segment in `voice`, returning its audio and the
+    /// number of requests it took
+    async fn text_to_audio_in_voice(
+        &self,
+        index: usize,
+        text: &str,
+        voice: Voice,
+    ) -> Result<(Vec<u8>, u32)> {
+        let tts_config = self.segment_tts_config(index, text, voice)?;
+        self.synthesize_with_config(index, text, &tts_config).await
+    }
+
+    /// Synthesize one segment with a prepared TTS config, applying the
+    /// oversize policy to segments a single request cannot hold
+    async fn synthesize_with_config(
+        &self,
+        index: usize,
+        text: &str,
+        tts_config: &TtsConfig,
+    ) -> Result<(Vec<u8>, u32)> {
+        let (result, attempts) = self.synthesize_counted(index, text, tts_config).await;
+        result.map(|audio| (audio, attempts))
+    }
+
+    /// Synthesize one segment with a prepared TTS config, returning the
+    /// number of requests it took whether or not it succeeded
+    async fn synthesize_counted(
+        &self,
+        index: usize,
+        text: &str,
+        tts_config: &TtsConfig,
+    ) -> (Result<Vec<u8>>, u32) {
+        let (result, attempts) = self.synthesize_within_limit(index, text, tts_config).await;
+        if let Ok(audio) = &result {
+            progress::segment_finished(index, audio, SegmentSource::Synthesized { attempts });
+        }
+        (result, attempts)
+    }
+
+    async fn synthesize_within_limit(
+        &self,
+        index: usize,
+        text: &str,
+        tts_config: &TtsConfig,
+    ) -> (Result<Vec<u8>>, u32) {
+        let chars = text.chars().count();
+        if chars <= TTS_MAX_CHARS {
+            return self.synthesize_with_retry(index, text, tts_config).await;
+        }
+
+        match self.oversize_policy {
+            OversizePolicy::Error => {
+                bundle::segment_failed(index);
+                let error = Error::Config(format!(
+                    "segment {} has {} chars, but a TTS request accepts at most {}",
+                    index, chars, TTS_MAX_CHARS
+                ));
+                (Err(error), 0)
+            }
+            OversizePolicy::AutoSplit => {
+                let pieces = LocalSplitter::new(TTS_MAX_CHARS)
+                    .with_sentence_terminators(&self.sentence_terminators)
+                    .split(text);
+                let mut audio = Vec::with_capacity(pieces.len());
+                let mut attempts = 0;
+                for (number, piece) in pieces.iter().enumerate() {
+                    // Pieces differ in text, so they must not share a key
+                    let mut piece_config = tts_config.clone();
+                    if let Some(key) = &tts_config.idempotency_key {
+                        piece_config.idempotency_key = Some(format!("{}.{}", key, number));
+                    }
+                    let (piece_audio, piece_attempts) = self
+                        .after_delay(
+                            number,
+                            self.synthesize_with_retry(index, piece, &piece_config),
+                        )
+                        .await;
+                    attempts += piece_attempts;
+                    match piece_audio {
+                        Ok(piece_audio) => audio.push(piece_audio),
+                        Err(e) => return (Err(e), attempts),
+                    }
+                }
+                let merged = AudioMerger::merge_vec_with_threads(audio, &[], None, 0);
+                (merged.map(|(merged, _)| merged), attempts)
+            }
+        }
+    }
+
+    /// Synthesize text that fits one request, retrying with exponential
+    /// backoff, and return the number of requests made
+    async fn synthesize_with_retry(
+        &self,
+        index: usize,
+        text: &str,
+        tts_config: &TtsConfig,
+    ) -> (Result<Vec<u8>>, u32) {
+        let backend = &self.backend();
+        let (result, attempts) = self
+            .retry_policy
+            .run(
+                |attempt| {
+                    watchdog::attempt_started(index);
+                    progress::attempt_started(index);
+                    timed_synthesize(backend.as_ref(), &self.stats, text, tts_config, attempt > 0)
+                },
+                |e| backend.classify(e),
+            )
+            .await;
+
+        watchdog::segment_finished(index);
+        if result.is_err() {
+            progress::segment_failed(index);
+            bundle::segment_failed(index);
+        }
+        (result, attempts)
+    }
+
+    async fn collect_audio_sequential(
+        &self,
+        segments: &[String],
+        sections: &[ActiveSection<'_>],
+    ) -> Result<Vec<(Vec<u8>, u32)>> {
+        let mut audio_segments = Vec::new();
+
+        for (index, segment) in segments.iter().enumerate() {
+            if index > 0 {
+                self.delay_next_request().await;
+            }
+            audio_segments.push(self.synthesize_in_section(index, segment, sections).await?);
+        }
+
+        Ok(audio_segments)
+    }
+
+    /// Wait the configured delay before the next sequential request
+    async fn delay_next_request(&self) {
+        if !self.inter_request_delay.is_zero() {
+            tokio::time::sleep(self.inter_request_delay).await;
+        }
+    }
+
+    /// Run `request` for segment `index`, first waiting the configured delay
+    /// if segments are synthesized one at a time and it is not the first
+    async fn after_delay<F: std::future::Future>(&self, index: usize, request: F) -> F::Output {
+        if index > 0 && !self.enable_parallel {
+            self.delay_next_request().await;
+        }
+        request.await
+    }
+
+    /// Synthesize segments concurrently, returning one result per segment in
+    /// input order with the number of requests it took
+    ///
+    /// Fails with [`Error::Audio`] if the results do not cover every segment
+    /// exactly once.
+    ///
+    /// Only concurrency and ordering live here; each segment is synthesized
+    /// exactly as [`synthesize_segment`](Self::synthesize_segment) would.
+    async fn collect_audio_parallel<S>(
+        &self,
+        segments: Arc<[S]>,
+    ) -> Result<Vec<Result<(Vec<u8>, u32)>>>
+    where
+        S: AsRef<str> + Send + Sync + 'static,
+    {
+        let results = self.synthesize_parallel(segments, &[]).await?;
+        Ok(results
+            .into_iter()
+            .map(|result| result.map_err(|failed| failed.error))
+            .collect())
+    }
+
+    /// Synthesize segments concurrently, keeping the TTS config and request
+    /// count of each failed segment for a retry
+    ///
+    /// Each segment's config, idempotency key included, is built once here.
+    /// Requests share the segment list and, unless idempotency keys need a
+    /// config per segment, one TTS config, so no segment text is copied.
+    async fn synthesize_parallel<S>(
+        &self,
+        segments: Arc<[S]>,
+        sections: &[ActiveSection<'_>],
+    ) -> Result<Vec<ParallelResult>>
+    where
+        S: AsRef<str> + Send + Sync + 'static,
+    {
+        let configs: Vec<Arc<TtsConfig>> =
+            if self.idempotency_keys || sections.iter().any(Option::is_some) {
+                segments
+                    .iter()
+                    .enumerate()
+                    .map(|(index, text)| {
+                        self.section_segment_config(index, text.as_ref(), sections)
+                            .map(Arc::new)
+                    })
+                    .collect::<Result<_>>()?
+            } else {
+                // Without a key or a profile, the config does not depend on the segment
+                let shared_config = Arc::new(self.segment_tts_config(0, "", self.voice)?);
+                vec![shared_config; segments.len()]
+            };
+
+        let requests = stream::iter(configs.into_iter().enumerate()).map(|(index, tts_config)| {
+            let segments = segments.clone();
+
+            async move {
+                let segment = segments[index].as_ref();
+                let (result, attempts) = self.synthesize_counted(index, segment, &tts_config).await;
+                let result = match result {
+                    Ok(audio) => Ok((audio, attempts)),
+                    Err(error) => Err(FailedSegment {
+                        error,
+                        attempts,
+                        tts_config,
+                    }),
+                };
+                (index, result)
+            }
+        });
+        let results: Vec<_> = if self.deterministic {
+            requests.buffered(self.max_parallel).collect().await
+        } else {
+            requests.buffer_unordered(self.max_parallel).collect().await
+        };
+
+        // Completion order is arbitrary; restore input order
+        restore_order(results, segments.len())
+    }
+
+    /// Resolve parallel results, resynthesizing failed segments sequentially
+    /// when parallel fallback is enabled and the failures look like transport
+    /// problems
+    ///
+    /// A failed segment is sent again with the config, and so the
+    /// idempotency key, of its parallel attempts.
+    async fn finish_parallel(
+        &self,
+        segments: &[String],
+        results: Vec<ParallelResult>,
+        report: &mut ConversionReport,
+    ) -> Result<Vec<(Vec<u8>, u32)>> {
+        let failed = results.iter().filter(|result| result.is_err()).count();
+        let transport = results
+            .iter()
+            .filter(|result| matches!(result, Err(failed) if failed.error.is_transport()))
+            .count();
+
+        // Fall back only when most failures are transport errors; API errors
+        // such as rejected input would fail again sequentially
+        if failed == 0 || !self.parallel_fallback || transport * 2 <= failed {
+            return results
+                .into_iter()
+                .map(|result| result.map_err(|failed| failed.error))
+                .collect();
+        }
+
+        report.warnings.push(Warning::ParallelFallback {
+            failed_segments: failed,
+        });
+
+        let mut audio_segments = Vec::with_capacity(segments.len());
+        for (index, (segment, result)) in segments.iter().zip(results).enumerate() {
+            let audio = match result {
+                Ok(audio) => audio,
+                Err(failed) => {
+                    let (audio, attempts) = self
+                        .synthesize_with_config(index, segment, &failed.tts_config)
+                        .await?;
+                    (audio, failed.attempts + attempts)
+                }
+            };
+            audio_segments.push(audio);
+        }
+        Ok(audio_segments)
+    }
+}
+
+/// Append segments, each followed by its pause, to an incremental merge on
+/// the blocking thread pool of `runtime`
+///
+/// The merger is handed back once done. If the caller stops waiting, it is
+/// dropped with the task's output, removing its partial file.
+async fn append_blocking(
+    runtime: &tokio::runtime::Handle,
+    mut merger: IncrementalMerger,
+    segments: Vec<(Vec<u8>, Duration)>,
+) -> Result<IncrementalMerger> {
+    run_blocking(runtime, move || {
+        for (audio, pause) in &segments {
+            merger.append(audio)?;
+            merger.append_silence(*pause)?;
+        }
+        Ok(merger)
+    })
+    .await
+}
+
+/// Merge settings of a converter, owned so that the merge can leave the
+/// async runtime
+struct MergeJob {
+    audio_inspector: Option<AudioInspector>,
+    require_nonsilent: bool,
+    target_loudness: Option<f32>,
+    strict_validation: bool,
+    uniform_segment_duration: Option<Duration>,
+    merge_threads: usize,
+}
+
+impl MergeJob {
+    fn run(
+        &self,
+        audio_segments: Vec<Vec<u8>>,
+        pauses: &[Duration],
+        output: &mut Output<'_>,
+    ) -> Result<Vec<Warning>> {
+        if self.audio_inspector.is_none()
+            && !self.require_nonsilent
+            && self.target_loudness.is_none()
+        {
+            return output.write_merged(
+                audio_segments,
+                pauses,
+                self.uniform_segment_duration,
+                self.merge_threads,
+            );
+        }
+
+        let (mut wav, mut pcm, mut warnings) = AudioMerger::merge_pcm_with_threads(
+            audio_segments,
+            pauses,
+            self.uniform_segment_duration,
+            self.merge_threads,
+        )?;
+        if let Some(target) = self.target_loudness {
+            if pcm.normalize_loudness(target as f64).is_some() {
+                wav = pcm.to_wav(probe_bytes(&wav)?.spec)?;
+            } else {
+                let warning = Warning::LoudnessNotNormalized { target };
+                if self.strict_validation {
+                    return Err(Error::audio(
+                        AudioErrorKind::LoudnessNotNormalized,
+                        warning.to_string(),
+                    ));
+                }
+                warnings.push(warning);
+            }
+        }
+        if self.require_nonsilent && pcm.peak() < NONSILENT_MIN_PEAK {
+            return Err(Error::audio(AudioErrorKind::Silent, "output is silent"));
+        }
+        if let Some(inspector) = &self.audio_inspector {
+            inspect_audio(inspector, &pcm, &mut warnings);
+        }
+        output.write_bytes(wav)?;
+        Ok(warnings)
+    }
+}
+
+/// Destination of a conversion's merged audio
+enum Output<'a> {
+    /// WAV file at the given path
+    Path(&'a str),
+    /// In-memory WAV bytes
+    Memory(&'a mut Vec<u8>),
+}
+
+impl Output<'_> {
+    async fn write_single(&mut self, audio: &[u8], preserve_extra_chunks: bool) -> Result<()> {
+        match self {
+            Output::Path(path) if preserve_extra_chunks => {
+                AudioMerger::save_single_preserving_chunks(audio, path).await
+            }
+            Output::Path(path) => AudioMerger::save_single(audio, path).await,
+            Output::Memory(buffer) => {
+                **buffer = AudioMerger::single_to_vec(audio, preserve_extra_chunks)?;
+                Ok(())
+            }
+        }
+    }
+
+    fn validate_riff(&self) -> Result<()> {
+        match self {
+            Output::Path(path) => riff_chunks(&std::fs::read(path)?).map(|_| ()),
+            Output::Memory(buffer) => riff_chunks(buffer).map(|_| ()),
+        }
+    }
+
+    fn write_bytes(&mut self, wav: Vec<u8>) -> Result<()> {
+        match self {
+            Output::Path(path) => std::fs::write(path, wav)?,
+            Output::Memory(buffer) => **buffer = wav,
+        }
+        Ok(())
+    }
+
+    fn write_merged(
+        &mut self,
+        audio_segments: Vec<Vec<u8>>,
+        pauses: &[Duration],
+        uniform_duration: Option<Duration>,
+        threads: usize,
+    ) -> Result<Vec<Warning>> {
+        match self {
+            Output::Path(path) => AudioMerger::merge_file_with_threads(
+                audio_segments,
+                pauses,
+                uniform_duration,
+                path,
+                threads,
+            ),
+            Output::Memory(buffer) => {
+                let (audio, warnings) = AudioMerger::merge_vec_with_threads(
+                    audio_segments,
+                    pauses,
+                    uniform_duration,
+                    threads,
+                )?;
+                **buffer = audio;
+                Ok(warnings)
+            }
+        }
+    }
+}
+
+/// Run the audio inspector, turning a panic into a warning
+fn inspect_audio(inspector: &AudioInspector, audio: &PcmAudio, warnings: &mut Vec<Warning>) {
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| inspector(audio)));
+    if let Err(payload) = result {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        warnings.push(Warning::InspectorPanic { message });
+    }
+}
+
+/// Synthesize through `backend`, recording the request in `stats`
+async fn timed_synthesize(
+    backend: &dyn TtsBackend,
+    stats: &StatsRecorder,
+    text: &str,
+    tts_config: &TtsConfig,
+    retry: bool,
+) -> Result<Vec<u8>> {
+    let started = Instant::now();
+    let result = backend.synthesize(text, tts_config).await;
+    stats.record(Endpoint::Tts, started.elapsed(), result.is_ok(), retry);
+    result
+}
+
+/// Sort results tagged with their segment index into input order
+///
+/// Fails with [`Error::Audio`] unless every index in `0..len` appears exactly
+/// once, so a reordering bug cannot silently scramble the merged audio.
+fn restore_order<T>(mut results: Vec<(usize, T)>, len: usize) -> Result<Vec<T>> {
+    results.sort_by_key(|(index, _)| *index);
+    let complete = results.len() == len
+        && results
+            .iter()
+            .enumerate()
+            .all(|(expected, (index, _))| *index == expected);
+    if !complete {
+        return Err(Error::audio(
+            AudioErrorKind::SegmentOrder,
+            "segment ordering corrupted",
+        ));
+    }
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
+
+/// Audio of a segment synthesized in parallel with the requests it took
+type ParallelResult = std::result::Result<(Vec<u8>, u32), FailedSegment>;
+
+/// A segment whose parallel synthesis failed
+struct FailedSegment {
+    error: Error,
+    /// Requests made before giving up
+    attempts: u32,
+    /// Settings and idempotency key the requests were sent with
+    tts_config: Arc<TtsConfig>,
+}
+
+/// Segment texts, their audio with the requests it took, the pauses after
+/// them and their sections
+type ResplitSegments<'a> = (
+    Cow<'a, [String]>,
+    Vec<(Vec<u8>, u32)>,
+    Cow<'a, [Duration]>,
+    Cow<'a, [ActiveSection<'a>]>,
+);
+
+/// Section a segment is read in, by name with its profile, or `None` for
+/// the converter's own settings
+type ActiveSection<'a> = Option<(&'a str, &'a SectionProfile)>;
+
+/// Record the section of segment `idx`, the last one pushed, in `report`
+fn record_section(report: &mut ConversionReport, sections: &[ActiveSection<'_>], idx: usize) {
+    if let (Some(segment), Some((name, _))) = (
+        report.segments.last_mut(),
+        sections.get(idx).copied().flatten(),
+    ) {
+        segment.section = Some(name.to_string());
+    }
+}
+
+/// Whether `duration` exceeds `target` by more than
+/// [`SEGMENT_DURATION_TOLERANCE`]
+fn exceeds_target(duration: Duration, target: Duration) -> bool {
+    duration.as_secs_f64() > target.as_secs_f64() * (1.0 + SEGMENT_DURATION_TOLERANCE)
+}
+
+/// Silence to insert after segment `idx`
+fn pause_at(pauses: &[Duration], idx: usize) -> Duration {
+    pauses.get(idx).copied().unwrap_or_default()
+}
+
+/// Exact playback duration of `frames` frames at `sample_rate`, to the
+/// nanosecond
+fn frames_duration(frames: usize, sample_rate: u32) -> Duration {
+    let nanos = frames as u128 * 1_000_000_000 / u128::from(sample_rate.max(1));
+    Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+}
+
+impl Default for Text2Audio {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+/// Builder for Text2Audio configuration
+///
+/// Provides a fluent interface for configuring text-to-audio conversion.
+pub struct Builder {
+    converter: Text2Audio,
+}
+
+impl Builder {
+    fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            converter: Text2Audio::new(api_key),
+        }
+    }
+
+    /// Set the AI model for text splitting
+    pub fn model(mut self, model: Model) -> Self {
+        self.converter = self.converter.with_model(model);
+        self
+    }
+
+    /// Set the voice type for TTS
+    pub fn voice(mut self, voice: Voice) -> Self {
+        self.converter = self.converter.with_voice(voice);
+        self
+    }
+
+    /// Apply a reading profile preset
+    pub fn profile(mut self, profile: ReadingProfile) -> Self {
+        self.converter = self.converter.with_profile(profile);
+        self
+    }
+
+    /// Set the speech speed
+    pub fn speed(mut self, speed: f32) -> Self {
+        self.converter = self.converter.with_speed(speed);
+        self
+    }
+
+    /// Set the speech volume
+    pub fn volume(mut self, volume: f32) -> Self {
+        self.converter = self.converter.with_volume(volume);
+        self
+    }
+
+    /// Register profiles for named sections of a document
+    pub fn section_profiles(mut self, profiles: HashMap<String, SectionProfile>) -> Self {
+        self.converter = self.converter.with_section_profiles(profiles);
+        self
+    }
+
+    /// Set the maximum segment length
+    pub fn max_segment_length(mut self, max_length: usize) -> Self {
+        self.converter = self.converter.with_max_segment_length(max_length);
+        self
+    }
+
+    /// Split into segments of about `duration` of audio each
+    pub fn target_segment_duration(mut self, duration: Duration) -> Self {
+        self.converter = self.converter.with_target_segment_duration(duration);
+        self
+    }
+
+    /// Set the unit in which the maximum segment length is measured
+    pub fn length_unit(mut self, unit: LengthUnit) -> Self {
+        self.converter = self.converter.with_length_unit(unit);
+        self
+    }
+
+    /// Enable parallel processing
+    pub fn parallel(mut self, max_parallel: usize) -> Self {
+        self.converter = self.converter.with_parallel(max_parallel);
+        self
+    }
+
+    /// Wait between consecutive requests of sequential synthesis and AI
+    /// splitting
+    pub fn inter_request_delay(mut self, delay: Duration) -> Self {
+        self.converter = self.converter.with_inter_request_delay(delay);
+        self
+    }
+
+    /// Enable thinking mode for AI splitting
+    pub fn thinking(mut self, enable: bool) -> Self {
+        self.converter = self.converter.with_thinking(enable);
+        self
+    }
+
+    /// Enable coding plan endpoint
+    pub fn coding_plan(mut self, enable: bool) -> Self {
+        self.converter = self.converter.with_coding_plan(enable);
+        self
+    }
+
+    /// Set the maximum characters of a prompt sent for AI splitting
+    pub fn max_prompt_chars(mut self, max_chars: usize) -> Self {
+        self.converter = self.converter.with_max_prompt_chars(max_chars);
+        self
+    }
+
+    /// Set the maximum UTF-8 bytes of a prompt sent for AI splitting
+    pub fn max_prompt_bytes(mut self, max_bytes: usize) -> Self {
+        self.converter = self.converter.with_max_prompt_bytes(max_bytes);
+        self
+    }
+
+    /// Set the number of retries after the first attempt and their delay
+    pub fn retry_config(mut self, max_retries: u32, delay: Duration) -> Self {
+        self.converter = self.converter.with_retry_config(max_retries, delay);
+        self
+    }
+
+    /// Set how failed TTS and split requests are retried
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.converter = self.converter.with_retry_policy(policy);
+        self
+    }
+
+    /// Rewrite over-long unbreakable tokens before splitting
+    pub fn long_token_policy(mut self, max_chars: usize, policy: LongTokenPolicy) -> Self {
+        self.converter = self.converter.with_long_token_policy(max_chars, policy);
+        self
+    }
+
+    /// Read markdown links as their text and images as their alt text
+    pub fn strip_link_urls(mut self, enable: bool) -> Self {
+        self.converter = self.converter.with_strip_link_urls(enable);
+        self
+    }
+
+    /// Handle footnote and reference markers such as `[12]` or `¹`
+    pub fn footnote_policy(mut self, policy: FootnotePolicy) -> Self {
+        self.converter = self.converter.with_footnote_policy(policy);
+        self
+    }
+
+    /// Skip footnote bodies or move them to the end of the text
+    pub fn footnote_bodies(mut self, bodies: FootnoteBodies) -> Self {
+        self.converter = self.converter.with_footnote_bodies(bodies);
+        self
+    }
+
+    /// Set the punctuation marks at which local splitting ends a sentence
+    pub fn sentence_terminators(mut self, terminators: &[char]) -> Self {
+        self.converter = self.converter.with_sentence_terminators(terminators);
+        self
+    }
+
+    /// Keep markdown list items and table rows whole when splitting
+    pub fn list_awareness(mut self, enable: bool) -> Self {
+        self.converter = self.converter.with_list_awareness(enable);
+        self
+    }
+
+    /// Normalize spacing between CJK and Latin text
+    pub fn cjk_latin_spacing(mut self, mode: SpacingMode) -> Self {
+        self.converter = self.converter.with_cjk_latin_spacing(mode);
+        self
+    }
+
+    /// Replace math and technical symbols with spoken readings
+    pub fn symbol_verbalization(mut self, enable: bool) -> Self {
+        self.converter = self.converter.with_symbol_verbalization(enable);
+        self
+    }
+
+    /// Set the language of the readings used for symbols
+    pub fn symbol_language(mut self, language: SymbolLanguage) -> Self {
+        self.converter = self.converter.with_symbol_language(language);
+        self
+    }
+
+    /// Add or override symbol readings
+    pub fn symbol_readings(mut self, readings: HashMap<char, String>) -> Self {
+        self.converter = self.converter.with_symbol_readings(readings);
+        self
+    }
+
+    /// Set how many symbols without a reading may reach the TTS input
+    pub fn unknown_symbol_threshold(mut self, threshold: usize) -> Self {
+        self.converter = self.converter.with_unknown_symbol_threshold(threshold);
+        self
+    }
+
+    /// Read dates, times, phone numbers and other numbers aloud per locale
+    pub fn numeric_profile(mut self, context: NumericContext) -> Self {
+        self.converter = self.converter.with_numeric_profile(context);
+        self
+    }
+
+    /// Replace the text of specific segments before synthesis
+    pub fn segment_overrides(mut self, overrides: HashMap<usize, String>) -> Self {
+        self.converter = self.converter.with_segment_overrides(overrides);
+        self
+    }
+
+    /// Spawn background tasks on a caller-provided runtime
+    pub fn runtime_handle(mut self, handle: tokio::runtime::Handle) -> Self {
+        self.converter = self.converter.with_runtime_handle(handle);
+        self
+    }
+
+    /// Bound the memory used to hold synthesized segment audio
+    pub fn max_memory(mut self, bytes: usize) -> Self {
+        self.converter = self.converter.with_max_memory(bytes);
+        self
+    }
+
+    /// Insert silence where the text has line breaks
+    pub fn newline_pauses(mut self, short: Duration, long: Duration) -> Self {
+        self.converter = self.converter.with_newline_pauses(short, long);
+        self
+    }
+
+    /// Split the text at every match of a regular expression
+    pub fn split_regex(mut self, pattern: &str, keep_delimiter: bool) -> Self {
+        self.converter = self.converter.with_split_regex(pattern, keep_delimiter);
+        self
+    }
+
+    /// Set the pauses of bilingual conversions
+    pub fn bilingual_pauses(mut self, within_pair: Duration, between_pairs: Duration) -> Self {
+        self.converter = self
+            .converter
+            .with_bilingual_pauses(within_pair, between_pairs);
+        self
+    }
+
+    /// Set the minimum duration of a successful conversion's audio
+    pub fn min_duration(mut self, duration: Duration) -> Self {
+        self.converter = self.converter.with_min_duration(duration);
+        self
+    }
+
+    /// Reject output whose audio is silent
+    pub fn require_nonsilent(mut self, enable: bool) -> Self {
+        self.converter = self.converter.with_require_nonsilent(enable);
+        self
+    }
+
+    /// Validate the RIFF structure of the output after writing it
+    pub fn strict_riff(mut self, enable: bool) -> Self {
+        self.converter = self.converter.with_strict_riff(enable);
+        self
+    }
+
+    /// Keep extra chunks from the provider's WAV in direct-mode output
+    pub fn preserve_extra_chunks(mut self, enable: bool) -> Self {
+        self.converter = self.converter.with_preserve_extra_chunks(enable);
+        self
+    }
+
+    /// Set the format of converted audio
+    pub fn output_format(mut self, format: OutputFormat) -> Self {
+        self.converter = self.converter.with_output_format(format);
+        self
+    }
+
+    /// Write a metadata sidecar next to the output file
+    pub fn sidecar_metadata(mut self, enable: bool) -> Self {
+        self.converter = self.converter.with_sidecar_metadata(enable);
+        self
+    }
+
+    /// Write speech marks for web players to `path`
+    pub fn speech_marks(mut self, path: impl Into<PathBuf>) -> Self {
+        self.converter = self.converter.with_speech_marks(path);
+        self
+    }
+
+    /// Mark section boundaries in speech marks
+    pub fn speech_mark_sections(mut self, enable: bool) -> Self {
+        self.converter = self.converter.with_speech_mark_sections(enable);
+        self
+    }
+
+    /// Set how much segment text conversion reports keep
+    pub fn report_detail(mut self, detail: ReportDetail) -> Self {
+        self.converter = self.converter.with_report_detail(detail);
+        self
+    }
+
+    /// Force direct or segmented mode regardless of text length
+    pub fn mode(mut self, mode: ModeSelection) -> Self {
+        self.converter = self.converter.with_mode(mode);
+        self
+    }
+
+    /// Set how segments longer than a TTS request accepts are handled
+    pub fn oversize_policy(mut self, policy: OversizePolicy) -> Self {
+        self.converter = self.converter.with_oversize_policy(policy);
+        self
+    }
+
+    /// Set how batch operations surface errors
+    pub fn error_mode(mut self, mode: ErrorMode) -> Self {
+        self.converter = self.converter.with_error_mode(mode);
+        self
+    }
+
+    /// Pad every segment with trailing silence to the same duration
+    pub fn uniform_segment_duration(mut self, duration: Duration) -> Self {
+        self.converter = self.converter.with_uniform_segment_duration(duration);
+        self
+    }
+
+    /// Set how long to wait for another process's lock on a project
+    pub fn lock_timeout(mut self, timeout: Duration) -> Self {
+        self.converter = self.converter.with_lock_timeout(timeout);
+        self
+    }
+
+    /// Warn when synthesis makes no progress for `timeout`
+    pub fn stall_timeout(mut self, timeout: Duration) -> Self {
+        self.converter = self.converter.with_stall_timeout(timeout);
+        self
+    }
+
+    /// Fail a stalled conversion instead of only warning
+    pub fn abort_on_stall(mut self, enable: bool) -> Self {
+        self.converter = self.converter.with_abort_on_stall(enable);
+        self
+    }
+
+    /// Report the progress of synthesis to a callback
+    pub fn progress_handler(
+        mut self,
+        handler: impl Fn(&ProgressEvent) + Send + Sync + 'static,
+    ) -> Self {
+        self.converter = self.converter.with_progress_handler(handler);
+        self
+    }
+
+    /// Set the interval of progress heartbeats
+    pub fn progress_heartbeat(mut self, interval: Duration) -> Self {
+        self.converter = self.converter.with_progress_heartbeat(interval);
+        self
+    }
+
+    /// Set how many segments an incremental input queues for synthesis
+    pub fn queue_depth(mut self, depth: usize) -> Self {
+        self.converter = self.converter.with_queue_depth(depth);
+        self
+    }
+
+    /// Set the longest chunk yielded by `convert_stream_chunks`
+    pub fn chunk_duration(mut self, duration: Duration) -> Self {
+        self.converter = self.converter.with_chunk_duration(duration);
+        self
+    }
+
+    /// Set what an incremental input does when its queue is full
+    pub fn queue_policy(mut self, policy: QueuePolicy) -> Self {
+        self.converter = self.converter.with_queue_policy(policy);
+        self
+    }
+
+    /// Normalize the loudness of each conversion's audio to a target
+    pub fn target_loudness(mut self, lufs: f32) -> Self {
+        self.converter = self.converter.with_target_loudness(lufs);
+        self
+    }
+
+    /// Run a callback on the audio of each conversion before it is written
+    pub fn audio_inspector(
+        mut self,
+        inspector: impl Fn(&PcmAudio) + Send + Sync + 'static,
+    ) -> Self {
+        self.converter = self.converter.with_audio_inspector(inspector);
+        self
+    }
+
+    /// Transform each raw TTS response body before it is checked
+    pub fn response_handler(
+        mut self,
+        handler: impl Fn(&[u8]) -> Result<Vec<u8>> + Send + Sync + 'static,
+    ) -> Self {
+        self.converter = self.converter.with_response_handler(handler);
+        self
+    }
+
+    /// Set the number of threads decoding segments during merge
+    #[cfg(feature = "rayon")]
+    pub fn merge_threads(mut self, threads: usize) -> Self {
+        self.converter = self.converter.with_merge_threads(threads);
+        self
+    }
+
+    /// Reject invalid settings instead of substituting defaults
+    pub fn strict_validation(mut self, enable: bool) -> Self {
+        self.converter = self.converter.with_strict_validation(enable);
+        self
+    }
+
+    /// Retry failed segments sequentially when parallel synthesis fails
+    pub fn parallel_fallback(mut self, enable: bool) -> Self {
+        self.converter = self.converter.with_parallel_fallback(enable);
+        self
+    }
+
+    /// Attach an idempotency key to each segment's TTS requests
+    pub fn idempotency_keys(mut self, enable: bool) -> Self {
+        self.converter = self.converter.with_idempotency_keys(enable);
+        self
+    }
+
+    /// Make repeated conversions of the same input reproducible
+    pub fn deterministic(mut self, enable: bool) -> Self {
+        self.converter = self.converter.with_deterministic(enable);
+        self
+    }
+
+    /// Write a diagnostic bundle to `dir` when a conversion fails
+    pub fn failure_bundle(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.converter = self.converter.with_failure_bundle(dir);
+        self
+    }
+
+    /// Include the failing segment's text in failure bundles
+    pub fn failure_bundle_text(mut self, enable: bool) -> Self {
+        self.converter = self.converter.with_failure_bundle_text(enable);
+        self
+    }
+
+    /// Learn the speech rate from completed conversions
+    pub fn calibration_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.converter = self.converter.with_calibration_file(path);
+        self
+    }
+
+    /// Set how `convert_to_files` names files
+    pub fn file_naming(mut self, naming: FileNaming) -> Self {
+        self.converter = self.converter.with_file_naming(naming);
+        self
+    }
+
+    /// Set a template for the names of the files `convert_to_files` writes
+    pub fn file_template(mut self, template: OutputTemplate) -> Self {
+        self.converter = self.converter.with_file_template(template);
+        self
+    }
+
+    /// Set what happens when the output file already exists
+    pub fn overwrite_policy(mut self, policy: OverwritePolicy) -> Self {
+        self.converter = self.converter.with_overwrite_policy(policy);
+        self
+    }
+
+    /// Check that the output location can be written before any work is
+    /// done
+    pub fn output_preflight(mut self, enable: bool) -> Self {
+        self.converter = self.converter.with_output_preflight(enable);
+        self
+    }
+
+    /// Set a custom TTS backend
+    pub fn backend(mut self, backend: Arc<dyn TtsBackend>) -> Self {
+        self.converter = self.converter.with_backend(backend);
+        self
+    }
+
+    /// Set a custom AI splitting backend
+    pub fn split_backend(mut self, backend: Arc<dyn SplitBackend>) -> Self {
+        self.converter = self.converter.with_split_backend(backend);
+        self
+    }
+
+    /// Cache AI split results on disk
+    pub fn split_cache(mut self, dir: impl AsRef<Path>) -> Self {
+        self.converter = self.converter.with_split_cache(dir);
+        self
+    }
+
+    /// Set how text longer than the segment length is split
+    pub fn split_strategy(mut self, strategy: SplitStrategy) -> Self {
+        self.converter = self.converter.with_split_strategy(strategy);
+        self
+    }
+
+    /// Set which choice of an AI split response is used
+    pub fn choice_selection(mut self, selection: ChoiceSelection) -> Self {
+        self.converter = self.converter.with_choice_selection(selection);
+        self
+    }
+
+    /// Build the Text2Audio converter
+    pub fn build(self) -> Text2Audio {
+        self.converter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{
+        self, convert_verified, read_samples, temp_path, ClassifyingBackend, EchoChatBackend,
+        FailAfterBackend, FixedLengthBackend, FlakyBackend, MockBackend, PendingBackend,
+        PerCharBackend, SerialOnlyBackend, SlowBackend, StaggeredBackend, StallingBackend,
+        StaticBackend, StaticSplitBackend, VoiceRejectingBackend,
+    };
+
+    #[test]
+    fn test_new() {
+        let converter = Text2Audio::new("test_key");
+        assert_eq!(converter.model, Model::default());
+        assert_eq!(converter.voice, Voice::default());
+        assert_eq!(converter.speed, 1.0);
+        assert_eq!(converter.volume, 1.0);
+        assert_eq!(converter.max_segment_length, 500);
+    }
+
+    #[test]
+    fn test_with_model() {
+        let converter = Text2Audio::new("test_key").with_model(Model::GLM4_7);
+        assert_eq!(converter.model, Model::GLM4_7);
+    }
+
+    #[test]
+    fn test_with_voice() {
+        let converter = Text2Audio::new("test_key").with_voice(Voice::Xiaochen);
+        assert_eq!(converter.voice, Voice::Xiaochen);
+    }
+
+    #[test]
+    fn test_with_profile() {
+        for profile in [
+            ReadingProfile::Audiobook,
+            ReadingProfile::Podcast,
+            ReadingProfile::Announcement,
+            ReadingProfile::FastReview,
+        ] {
+            let converter = Text2Audio::new("test_key")
+                .with_newline_pauses(Duration::from_millis(1), Duration::from_millis(2))
+                .with_profile(profile);
+            assert_eq!(converter.voice, profile.voice());
+            assert_eq!(converter.speed, profile.speed());
+            assert_eq!(converter.volume, profile.volume());
+            assert_eq!(converter.max_segment_length, profile.max_segment_length());
+            assert_eq!(converter.newline_pauses, profile.newline_pauses());
+        }
+    }
+
+    #[test]
+    fn test_profile_overridable() {
+        let converter = Text2Audio::builder("test_key")
+            .profile(ReadingProfile::FastReview)
+            .speed(1.2)
+            .voice(Voice::Kazi)
+            .build();
+        assert_eq!(converter.speed, 1.2);
+        assert_eq!(converter.voice, Voice::Kazi);
+        assert_eq!(converter.max_segment_length, 1000);
+    }
+
+    #[test]
+    fn test_with_speed() {
+        let converter = Text2Audio::new("test_key").with_speed(1.2);
+        assert_eq!(converter.speed, 1.2);
+    }
+
+    #[test]
+    fn test_speed_clamp() {
+        let converter = Text2Audio::new("test_key").with_speed(3.0);
+        assert_eq!(converter.speed, 2.0);
+
+        let converter = Text2Audio::new("test_key").with_speed(0.2);
+        assert_eq!(converter.speed, 0.5);
+    }
+
+    #[test]
+    fn test_with_volume() {
+        let converter = Text2Audio::new("test_key").with_volume(2.5);
+        assert_eq!(converter.volume, 2.5);
+    }
+
+    #[test]
+    fn test_volume_clamp() {
+        let converter = Text2Audio::new("test_key").with_volume(15.0);
+        assert_eq!(converter.volume, 10.0);
+
+        let converter = Text2Audio::new("test_key").with_volume(-1.0);
+        assert_eq!(converter.volume, 0.0);
+    }
+
+    #[test]
+    fn test_with_max_segment_length() {
+        let converter = Text2Audio::new("test_key").with_max_segment_length(800);
+        assert_eq!(converter.max_segment_length, 800);
+    }
+
+    #[test]
+    fn test_max_segment_length_clamp() {
+        let converter = Text2Audio::new("test_key").with_max_segment_length(50);
+        assert_eq!(converter.max_segment_length, 100);
+
+        let converter = Text2Audio::new("test_key").with_max_segment_length(2000);
+        assert_eq!(converter.max_segment_length, 1024);
+    }
+
+    #[test]
+    fn test_with_parallel() {
+        let converter = Text2Audio::new("test_key").with_parallel(5);
+        assert!(converter.enable_parallel);
+        assert_eq!(converter.max_parallel, 5);
+    }
+
+    #[test]
+    fn test_parallel_clamp() {
+        let converter = Text2Audio::new("test_key").with_parallel(20);
+        assert_eq!(converter.max_parallel, 10);
+
+        let converter = Text2Audio::new("test_key").with_parallel(0);
+        assert_eq!(converter.max_parallel, 1);
+    }
+
+    #[test]
+    fn test_with_thinking() {
+        let converter = Text2Audio::new("test_key").with_thinking(true);
+        assert!(converter.enable_thinking);
+    }
+
+    #[test]
+    fn test_with_coding_plan() {
+        let converter = Text2Audio::new("test_key").with_coding_plan(true);
+        assert!(converter.coding_plan);
+    }
+
+    #[test]
+    fn test_with_max_prompt_chars() {
+        let converter = Text2Audio::new("test_key").with_max_prompt_chars(2000);
+        assert_eq!(converter.max_prompt_chars, 2000);
+        assert_eq!(converter.max_prompt_bytes, None);
+        let converter = converter.with_max_prompt_bytes(4096);
+        assert_eq!(converter.max_prompt_bytes, Some(4096));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_with_merge_threads() {
+        let converter = Text2Audio::builder("test_key").merge_threads(2).build();
+        assert_eq!(converter.merge_threads, 2);
+    }
+
+    #[test]
+    fn test_builder() {
+        let converter = Text2Audio::builder("api_key")
+            .model(Model::GLM4_7)
+            .voice(Voice::Tongtong)
+            .speed(1.5)
+            .volume(3.0)
+            .max_segment_length(300)
+            .parallel(4)
+            .thinking(true)
+            .coding_plan(false)
+            .build();
+
+        assert_eq!(converter.model, Model::GLM4_7);
+        assert_eq!(converter.voice, Voice::Tongtong);
+        assert_eq!(converter.speed, 1.5);
+        assert_eq!(converter.volume, 3.0);
+        assert_eq!(converter.max_segment_length, 300);
+        assert!(converter.enable_parallel);
+        assert_eq!(converter.max_parallel, 4);
+        assert!(converter.enable_thinking);
+        assert!(!converter.coding_plan);
+    }
+
+    #[test]
+    fn test_direct_path_applies_post_processing() {
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(StaticBackend::new(
+                test_util::wav_with_extra_chunks(),
+            )))
+            .with_preserve_extra_chunks(true);
+        let convert = |converter: &Text2Audio, segmented: bool| {
+            let mut wav = Vec::new();
+            let mut report = ConversionReport::new(ConversionMode::Direct);
+            let mut output = Output::Memory(&mut wav);
+            let segments = ["你好。".to_string()];
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            if segmented {
+                runtime.block_on(converter.synthesize_and_merge(
+                    segments.as_slice().into(),
+                    &[],
+                    &[],
+                    &mut output,
+                    &mut report,
+                ))
+            } else {
+                runtime.block_on(converter.convert_direct(
+                    &segments[0],
+                    converter.max_segment_length,
+                    &mut output,
+                    &mut report,
+                ))
+            }
+            .unwrap();
+            wav
+        };
+
+        // Without post-processing the direct path passes the response through
+        assert_ne!(convert(&converter, false), convert(&converter, true));
+
+        let converter = converter.with_uniform_segment_duration(Duration::from_millis(1));
+        let direct = convert(&converter, false);
+        assert_eq!(direct, convert(&converter, true));
+        assert_eq!(read_samples(&direct), [vec![1, 2, 3], vec![0; 21]].concat());
+    }
+
+    #[test]
+    fn test_sentence_terminators_set_local_boundaries() {
+        let text = format!("{}；", "字".repeat(79)).repeat(15);
+        let split = |converter: Text2Audio, backend: Arc<MockBackend>| {
+            let output = temp_path("terminators.wav");
+            let mut report = ConversionReport::new(ConversionMode::Direct);
+            let result =
+                tokio::runtime::Runtime::new()
+                    .unwrap()
+                    .block_on(converter.convert_direct(
+                        &text,
+                        converter.max_segment_length,
+                        &mut Output::Path(&output),
+                        &mut report,
+                    ));
+            let _ = std::fs::remove_file(&output);
+            result.map(|_| backend.requests())
+        };
+
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_max_segment_length(100);
+        let requests = split(converter, backend).unwrap();
+        assert_eq!(requests[0].chars().count(), 100);
+
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_max_segment_length(100)
+            .with_sentence_terminators(&['。', '；']);
+        let requests = split(converter, backend).unwrap();
+        assert_eq!(requests.len(), 15);
+        assert!(requests
+            .iter()
+            .all(|r| r.chars().count() == 80 && r.ends_with('；')));
+
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_sentence_terminators(&[]);
+        let mut warnings = Vec::new();
+        assert!(matches!(
+            converter.check_parameters(&mut warnings),
+            Err(Error::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_model_features() {
+        let models = [
+            Model::GLM4_7,
+            Model::GLM4_6,
+            Model::GLM4_5,
+            Model::GLM4_5Flash,
+            Model::GLM4_5Air,
+        ];
+        for model in models {
+            for (thinking, coding_plan) in
+                [(false, false), (true, false), (false, true), (true, true)]
+            {
+                let converter = Text2Audio::new("test_key")
+                    .with_model(model)
+                    .with_thinking(thinking)
+                    .with_coding_plan(coding_plan);
+                let capabilities = model.capabilities();
+                let mut expected = Vec::new();
+                if thinking && !capabilities.thinking {
+                    expected.push("thinking");
+                }
+                if coding_plan && !capabilities.coding_plan {
+                    expected.push("the coding plan endpoint");
+                }
+
+                let mut warnings = Vec::new();
+                converter.check_parameters(&mut warnings).unwrap();
+                let features: Vec<_> = warnings
+                    .iter()
+                    .map(|warning| match warning {
+                        Warning::UnsupportedModelFeature { model: m, feature } => {
+                            assert_eq!(*m, model);
+                            *feature
+                        }
+                        other => panic!("unexpected warning {:?}", other),
+                    })
+                    .collect();
+                assert_eq!(
+                    features,
+                    expected,
+                    "{} {:?}",
+                    model.as_str(),
+                    (thinking, coding_plan)
+                );
+
+                let strict = converter
+                    .with_strict_validation(true)
+                    .check_parameters(&mut Vec::new());
+                assert_eq!(strict.is_err(), !expected.is_empty());
+            }
+        }
+
+        // A custom split backend does not use the model
+        let converter = Text2Audio::new("test_key")
+            .with_model(Model::GLM4_5Flash)
+            .with_coding_plan(true)
+            .with_split_backend(Arc::new(StaticSplitBackend::new(&[], Duration::ZERO)));
+        let mut warnings = Vec::new();
+        converter.check_parameters(&mut warnings).unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_direct_over_limit_falls_back_to_local_split() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key").with_backend(backend.clone());
+        let text = "这是一个用于测试的句子。".repeat(100);
+        let output = temp_path("direct_fallback.wav");
+
+        let mut report = ConversionReport::new(ConversionMode::Direct);
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(converter.convert_direct(
+                &text,
+                converter.max_segment_length,
+                &mut Output::Path(&output),
+                &mut report,
+            ))
+            .unwrap();
+
+        let requests = backend.requests();
+        assert!(requests.len() > 1);
+        assert!(requests.iter().all(|r| r.chars().count() <= TTS_MAX_CHARS));
+        assert_eq!(report.mode, ConversionMode::Segmented);
+        assert_eq!(report.segment_count, requests.len());
+        assert_eq!(
+            report.warnings,
+            vec![Warning::DirectFallback {
+                chars: text.chars().count(),
+                limit: TTS_MAX_CHARS
+            }]
+        );
+
+        let audio = std::fs::read(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+        assert_eq!(read_samples(&audio).len(), text.chars().count());
+    }
+
+    #[test]
+    fn test_with_long_token_policy() {
+        let converter =
+            Text2Audio::new("test_key").with_long_token_policy(0, LongTokenPolicy::Truncate(5));
+        assert_eq!(
+            converter.long_token_policy,
+            Some((1, LongTokenPolicy::Truncate(5)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_long_token_policy_runs_before_splitting() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_long_token_policy(50, LongTokenPolicy::Skip);
+        let text = format!("开始 {} 结束。", "x".repeat(3000));
+        let output = temp_path("long_token.wav");
+
+        let report = convert_verified(&converter, &text, &output).await;
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(report.mode, ConversionMode::Direct);
+        assert_eq!(backend.requests(), vec!["开始  结束。"]);
+        assert!(matches!(
+            report.warnings[..],
+            [Warning::LongToken { chars: 3000, .. }]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_strip_link_urls_before_splitting() {
+        let url = format!("https://example.com/{}", "a".repeat(400));
+        let text = format!(
+            "请看[文档]({url})和![示意图]({url})，以及[代码]({url})。",
+            url = url
+        );
+        let output = temp_path("links.wav");
+
+        // The URLs push the text past the limit of a direct request
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_mode(ModeSelection::ForceDirect);
+        assert!(matches!(
+            converter.convert(&text, &output).await,
+            Err(Error::Config(_))
+        ));
+
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_mode(ModeSelection::ForceDirect)
+            .with_strip_link_urls(true);
+        let report = convert_verified(&converter, &text, &output).await;
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(report.mode, ConversionMode::Direct);
+        assert_eq!(backend.requests(), vec!["请看文档和示意图，以及代码。"]);
+    }
+
+    #[tokio::test]
+    async fn test_footnotes_handled_before_symbols() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_symbol_verbalization(true)
+            .with_footnote_policy(FootnotePolicy::Strip)
+            .with_footnote_bodies(FootnoteBodies::MoveToEnd);
+        let text = "[1] 张三，2020。\n面积为x²[1]，见注释¹。";
+        let output = temp_path("footnotes.wav");
+
+        convert_verified(&converter, text, &output).await;
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(
+            backend.requests(),
+            vec!["面积为x的平方，见注释。\n\n注释\n张三，2020。"]
+        );
+    }
+
+    #[test]
+    fn test_segment_overrides_replace_by_index() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_segment_overrides(HashMap::from([
+                (1, "替换".to_string()),
+                (7, "unused".to_string()),
+            ]));
+        let segments = vec!["一。".to_string(), "二。".to_string(), "三。".to_string()];
+        let output = temp_path("segment_overrides.wav");
+
+        let mut report = ConversionReport::new(ConversionMode::Segmented);
+        let segments = converter.apply_segment_overrides(segments, &mut report.warnings);
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(converter.synthesize_and_merge(
+                segments.into(),
+                &[],
+                &[],
+                &mut Output::Path(&output),
+                &mut report,
+            ))
+            .unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(backend.requests(), vec!["一。", "替换", "三。"]);
+        assert_eq!(
+            report.warnings,
+            vec![Warning::UnusedSegmentOverride {
+                index: 7,
+                segment_count: 3
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_segment_override_direct_mode() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_segment_overrides(HashMap::from([(0, "新文本".to_string())]));
+        let output = temp_path("segment_override_direct.wav");
+
+        convert_verified(&converter, "原文本", &output).await;
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(backend.requests(), vec!["新文本"]);
+    }
+
+    #[tokio::test]
+    async fn test_convert_stream_yields_segment_audio() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key").with_backend(backend.clone());
+
+        let stream = converter.convert_stream("你好，世界！").await.unwrap();
+        let audio: Vec<_> = stream.collect().await;
+
+        assert_eq!(audio.len(), 1);
+        assert_eq!(read_samples(audio[0].as_ref().unwrap()).len(), 6);
+        assert_eq!(backend.requests(), vec!["你好，世界！"]);
+    }
+
+    #[tokio::test]
+    async fn test_split_cache_skips_repeated_chat_calls() {
+        let dir = temp_path("split_cache");
+        let half = format!("{}。", "字".repeat(299));
+        let split_backend = Arc::new(StaticSplitBackend::new(&[&half, &half], Duration::ZERO));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_split_backend(split_backend.clone())
+            .with_split_cache(&dir);
+        let output = temp_path("split_cache.wav");
+
+        convert_verified(&converter, &half.repeat(2), &output).await;
+        assert_eq!(split_backend.calls(), 1);
+        let report = convert_verified(&converter, &half.repeat(2), &output).await;
+        assert_eq!(split_backend.calls(), 1);
+        assert_eq!(report.segments.len(), 2);
+
+        // Different text is a miss
+        let other = format!("{}。", "文".repeat(299)).repeat(2);
+        convert_verified(&converter, &other, &output).await;
+        assert_eq!(split_backend.calls(), 2);
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_awareness_keeps_items_whole() {
+        let item = |name: &str| format!("- {}：{}。{}", name, "说明".repeat(20), "补充".repeat(8));
+        let items = [item("安装"), item("配置"), item("运行")];
+        let text = format!("使用步骤如下：\n{}\n以上就是全部步骤。", items.join("\n"));
+        let backend = Arc::new(MockBackend::new());
+        let split_backend = Arc::new(StaticSplitBackend::new(&["不应使用"], Duration::ZERO));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_split_backend(split_backend.clone())
+            .with_max_segment_length(100)
+            .with_list_awareness(true);
+        let output = temp_path("list_awareness.wav");
+
+        let report = convert_verified(&converter, &text, &output).await;
+        std::fs::remove_file(&output).unwrap();
+
+        // Each 61-char item is a segment of its own, and the prose around
+        // the list fits without an AI split
+        let mut expected = vec!["使用步骤如下：".to_string()];
+        expected.extend(items.iter().cloned());
+        expected.push("以上就是全部步骤。".to_string());
+        assert_eq!(backend.requests(), expected);
+        assert_eq!(report.segment_count, 5);
+        assert_eq!(split_backend.calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_out_of_order_completion_merges_in_order() {
+        // Each segment is shorter than the one before, so finishes first
+        let segments: Vec<String> = [299, 199, 99, 9]
+            .iter()
+            .map(|&len| format!("{}。", "字".repeat(len)))
+            .collect();
+        let refs: Vec<&str> = segments.iter().map(String::as_str).collect();
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(StaggeredBackend::new(Duration::from_micros(200))))
+            .with_split_backend(Arc::new(StaticSplitBackend::new(&refs, Duration::ZERO)))
+            .with_parallel(4);
+        let output = temp_path("out_of_order.wav");
+
+        let report = convert_verified(&converter, &segments.concat(), &output).await;
+        let samples = read_samples(&std::fs::read(&output).unwrap());
+        std::fs::remove_file(&output).unwrap();
+
+        let texts: Vec<_> = report
+            .segments
+            .iter()
+            .map(|s| s.text.as_deref().unwrap())
+            .collect();
+        assert_eq!(texts, refs);
+        let expected: Vec<i16> = [300, 200, 100, 10]
+            .iter()
+            .flat_map(|&len| vec![len as i16; len])
+            .collect();
+        let spoken: Vec<i16> = samples.into_iter().filter(|&s| s != 0).collect();
+        assert_eq!(spoken, expected);
+    }
+
+    #[test]
+    fn test_restore_order_rejects_gaps_and_duplicates() {
+        let sorted = restore_order(vec![(2, 'c'), (0, 'a'), (1, 'b')], 3).unwrap();
+        assert_eq!(sorted, vec!['a', 'b', 'c']);
+
+        for results in [
+            vec![(0, 'a'), (2, 'c')],
+            vec![(0, 'a'), (0, 'a'), (1, 'b')],
+            vec![(0, 'a'), (1, 'b'), (3, 'd')],
+        ] {
+            let error = restore_order(results, 3).unwrap_err();
+            assert!(
+                matches!(error, Error::Audio { kind: AudioErrorKind::SegmentOrder, message: m } if m == "segment ordering corrupted")
+            );
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_phase_timings_add_up_to_total() {
+        let half = format!("{}。", "字".repeat(299));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(SlowBackend::new(Duration::from_millis(40))))
+            .with_split_backend(Arc::new(StaticSplitBackend::new(
+                &[&half, &half],
+                Duration::from_millis(60),
+            )));
+        let output = temp_path("phase_timings.wav");
+
+        // The clock only moves while requests sleep
+        let report = convert_verified(&converter, &half.repeat(2), &output).await;
+        std::fs::remove_file(&output).unwrap();
+        let timings = report.timings;
+        assert_eq!(timings.split_duration, Duration::from_millis(60));
+        assert_eq!(timings.synth_duration, Duration::from_millis(80));
+        assert_eq!(timings.merge_duration, Duration::ZERO);
+        assert_eq!(timings.total, Duration::from_millis(140));
+
+        let output = temp_path("phase_timings_direct.wav");
+        let report = convert_verified(&converter, "你好。", &output).await;
+        std::fs::remove_file(&output).unwrap();
+        assert_eq!(report.timings.split_duration, Duration::ZERO);
+        assert_eq!(report.timings.synth_duration, Duration::from_millis(40));
+
+        // Projects time their phases too
+        let project_path = temp_path("phase_timings.project.json");
+        converter
+            .new_project(half.repeat(2))
+            .save(&project_path)
+            .unwrap();
+        let report = converter.run_project(&project_path, &output).await.unwrap();
+        let timings = report.timings;
+        assert_eq!(timings.split_duration, Duration::from_millis(60));
+        assert_eq!(timings.synth_duration, Duration::from_millis(80));
+        assert_eq!(timings.total, Duration::from_millis(140));
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(&project_path).unwrap();
+        std::fs::remove_dir_all(Project::audio_dir(&project_path)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failure_bundle_on_merge_failure() {
+        let dir = std::path::PathBuf::from(temp_path("failure_bundle"));
+        let half = format!("{}。", "字".repeat(299));
+        let converter = Text2Audio::new("secret-key")
+            .with_backend(Arc::new(StaticBackend::new(b"not a wav file".to_vec())))
+            .with_split_backend(Arc::new(StaticSplitBackend::new(
+                &[&half, &half],
+                Duration::ZERO,
+            )))
+            .with_failure_bundle(&dir)
+            .with_failure_bundle_text(true);
+        let output = temp_path("failure_bundle.wav");
+
+        let error = converter
+            .convert_with_report(&half.repeat(2), &output)
+            .await
+            .unwrap_err();
+
+        let files: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        let json_path = files
+            .iter()
+            .find(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .unwrap();
+        let json = std::fs::read_to_string(json_path).unwrap();
+        assert!(!json.contains("secret-key"));
+        let bundle: FailureBundle = serde_json::from_str(&json).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(bundle.crate_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(bundle.config.api_key, "<redacted>");
+        assert!(bundle.config.custom_backend);
+        assert_eq!(bundle.error.code, error.code());
+        assert_eq!(bundle.error.message, error.to_string());
+        assert_eq!(bundle.mode, ConversionMode::Segmented);
+        let statuses: Vec<_> = bundle.segments.iter().map(|s| s.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                bundle::SegmentStatus::Failed,
+                bundle::SegmentStatus::Pending
+            ]
+        );
+        let failed = bundle.failed_segment.unwrap();
+        assert_eq!(failed.index, 0);
+        assert_eq!(failed.text.as_ref(), Some(&half));
+        assert_eq!(files.len(), 2);
+        assert_eq!(bundle.attachments.len(), 1);
+        assert_eq!(
+            failed.audio_attachment.as_ref(),
+            Some(&bundle.attachments[0])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failure_bundle_warnings_leave_out_text() {
+        let dir = std::path::PathBuf::from(temp_path("failure_bundle_warnings"));
+        let text = format!("开始 {} 结束。", "secret".repeat(100));
+        let output = temp_path("failure_bundle_warnings.wav");
+
+        for include_text in [false, true] {
+            let converter = Text2Audio::new("test_key")
+                .with_backend(Arc::new(FailAfterBackend::new(0)))
+                .with_retry_config(0, Duration::ZERO)
+                .with_long_token_policy(50, LongTokenPolicy::Skip)
+                .with_failure_bundle(&dir)
+                .with_failure_bundle_text(include_text);
+            assert!(converter.convert(&text, &output).await.is_err());
+            let entry = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap();
+            let json = std::fs::read_to_string(entry.path()).unwrap();
+            std::fs::remove_dir_all(&dir).unwrap();
+
+            let bundle: FailureBundle = serde_json::from_str(&json).unwrap();
+            if include_text {
+                // Warnings read as in the report
+                assert!(bundle.warnings[0].contains("secret"));
+            } else {
+                assert!(!json.contains("secret"));
+                assert_eq!(
+                    bundle.warnings,
+                    vec!["token of 600 characters without a break; rewritten (text omitted)"]
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failure_bundle_on_every_entry_point() {
+        let dir = std::path::PathBuf::from(temp_path("failure_bundle_entry_points"));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(FailAfterBackend::new(0)))
+            .with_retry_config(0, Duration::ZERO)
+            .with_failure_bundle(&dir);
+        let output = temp_path("failure_bundle_entry_points.wav");
+        let project = temp_path("failure_bundle_entry_points.json");
+        converter.new_project("你好。").save(&project).unwrap();
+        let bundles = || {
+            std::fs::read_dir(&dir)
+                .unwrap()
+                .filter(|entry| {
+                    let path = entry.as_ref().unwrap().path();
+                    path.extension().is_some_and(|ext| ext == "json")
+                })
+                .count()
+        };
+
+        assert!(converter.convert("你好。", &output).await.is_err());
+        assert_eq!(bundles(), 1);
+        assert!(converter.convert_to_bytes("你好。").await.is_err());
+        assert_eq!(bundles(), 2);
+        assert!(converter
+            .convert_formats("你好。", vec![(output.clone(), OutputFormat::Wav)])
+            .await
+            .is_err());
+        assert_eq!(bundles(), 3);
+        let files = temp_path("failure_bundle_entry_points_files");
+        assert!(converter.convert_to_files("你好。", &files).await.is_err());
+        assert_eq!(bundles(), 4);
+        assert!(converter.run_project(&project, &output).await.is_err());
+        assert_eq!(bundles(), 5);
+        let pairs = vec![("你好。".to_string(), "Hello.".to_string())];
+        assert!(converter
+            .convert_bilingual(pairs, &output, Voice::Xiaochen)
+            .await
+            .is_err());
+        assert_eq!(bundles(), 6);
+        let stream = converter.convert_stream("你好。").await.unwrap();
+        futures::pin_mut!(stream);
+        assert!(stream.next().await.unwrap().is_err());
+        assert_eq!(bundles(), 7);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        let _ = std::fs::remove_dir_all(&files);
+        let _ = std::fs::remove_dir_all(Project::audio_dir(&project));
+        std::fs::remove_file(&project).unwrap();
+        assert!(!Path::new(&output).exists());
+    }
+
+    #[tokio::test]
+    async fn test_target_segment_duration() {
+        let converter = Text2Audio::new("test_key")
+            .with_max_segment_length(800)
+            .with_target_segment_duration(Duration::from_secs(45));
+        assert_eq!(converter.max_length(&mut Vec::new()), 180);
+        assert_eq!(converter.with_speed(2.0).max_length(&mut Vec::new()), 360);
+        let converter =
+            Text2Audio::new("test_key").with_target_segment_duration(Duration::from_secs(3600));
+        assert_eq!(converter.max_length(&mut Vec::new()), TTS_MAX_CHARS);
+
+        // Calibrated to the mock's one sample per character
+        let path = std::path::PathBuf::from(temp_path("target_duration.json"));
+        let mut calibration = Calibration::default();
+        calibration.observe(
+            Voice::default(),
+            DEFAULT_SPEED,
+            test_util::MOCK_SAMPLE_RATE as usize,
+            Duration::from_secs(1),
+        );
+        calibration.save(&path).unwrap();
+        let short = format!("{}。", "字".repeat(99));
+        let long = format!("{}。", "字".repeat(49)).repeat(4);
+        let converter = |strict| {
+            Text2Audio::new("test_key")
+                .with_backend(Arc::new(MockBackend::new()))
+                .with_split_backend(Arc::new(StaticSplitBackend::new(
+                    &[&short, &long],
+                    Duration::ZERO,
+                )))
+                .with_calibration_file(&path)
+                .with_target_segment_duration(Duration::from_millis(5))
+                .with_strict_validation(strict)
+        };
+        assert_eq!(converter(false).max_length(&mut Vec::new()), 120);
+        let output = temp_path("target_duration.wav");
+
+        // The 200-char segment lasts 8.3 ms
+        let text = format!("{}{}", short, long);
+        let report = convert_verified(&converter(false), &text, &output).await;
+        assert_eq!(report.segment_count, 2);
+        assert!(report
+            .segments
+            .iter()
+            .all(|s| s.target_duration == Some(Duration::from_millis(5))));
+        let over: Vec<_> = report
+            .warnings
+            .iter()
+            .filter(|w| matches!(w, Warning::SegmentOverDuration { .. }))
+            .collect();
+        assert_eq!(
+            over,
+            vec![&Warning::SegmentOverDuration {
+                index: 1,
+                duration: report.segments[1].duration,
+                target: Duration::from_millis(5),
+            }]
+        );
+
+        // Strict mode splits it again at the rate it was spoken at
+        let report = convert_verified(&converter(true), &text, &output).await;
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let texts: Vec<_> = report
+            .segments
+            .iter()
+            .map(|s| s.text.as_deref().unwrap())
+            .collect();
+        let half = format!("{}。", "字".repeat(49)).repeat(2);
+        assert_eq!(texts, vec![short.as_str(), half.as_str(), half.as_str()]);
+        assert!(report
+            .warnings
+            .iter()
+            .all(|w| !matches!(w, Warning::SegmentOverDuration { .. })));
+        assert_eq!(report.sources.tts_attempts, 4);
+
+        // A single sentence is never cut, only warned about
+        let sentence = format!("{}。", "字".repeat(199));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_split_backend(Arc::new(StaticSplitBackend::new(
+                &[&sentence],
+                Duration::ZERO,
+            )))
+            .with_target_segment_duration(Duration::from_millis(5))
+            .with_strict_validation(true);
+        let report = convert_verified(&converter, &sentence, &output).await;
+        std::fs::remove_file(&output).unwrap();
+        assert_eq!(report.segment_count, 1);
+        assert!(report
+            .warnings
+            .iter()
+            .any(|w| matches!(w, Warning::SegmentOverDuration { index: 0, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_calibration_converges_toward_observed_rate() {
+        let path = std::path::PathBuf::from(temp_path("calibration.json"));
+        let output = temp_path("calibration.wav");
+        let text = "一二三四五，六七八九十。";
+        let converter = |samples| {
+            Text2Audio::new("test_key")
+                .with_backend(Arc::new(FixedLengthBackend::new(samples)))
+                .with_calibration_file(&path)
+        };
+
+        // Uncalibrated: 12 spoken chars at the default rate
+        assert_eq!(converter(0).estimate(text), Duration::from_secs(3));
+        assert_eq!(converter(0).calibration().unwrap(), None);
+
+        // 1.2 seconds of audio, 0.1 seconds per char
+        let report = converter(test_util::MOCK_SAMPLE_RATE as usize * 6 / 5)
+            .convert_with_report(text, &output)
+            .await
+            .unwrap();
+        assert!(report.warnings.is_empty());
+        let first = converter(0).estimate(text);
+        assert!((first.as_secs_f64() - 1.2).abs() < 1e-6);
+
+        // 2.4 seconds, 0.2 seconds per char: the estimate moves toward it
+        converter(test_util::MOCK_SAMPLE_RATE as usize * 12 / 5)
+            .convert(text, &output)
+            .await
+            .unwrap();
+        let second = converter(0).estimate(text);
+        assert!(second > first && second < Duration::from_millis(2400));
+        assert_eq!(converter(0).calibration().unwrap().unwrap().observations, 2);
+
+        // Other voices stay uncalibrated
+        let other = converter(0).with_voice(Voice::Jam);
+        assert_eq!(other.estimate(text), Duration::from_secs(3));
+
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_calibration_warns_once() {
+        let path = std::path::PathBuf::from(temp_path("corrupt_calibration.json"));
+        let output = temp_path("corrupt_calibration.wav");
+        std::fs::write(&path, "{not json").unwrap();
+        let sentence = format!("{}。", "字".repeat(14));
+        // 5 seconds at the default 0.25 seconds per char: 20 chars
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_split_backend(Arc::new(StaticSplitBackend::new(
+                &[sentence.as_str(); 4],
+                Duration::ZERO,
+            )))
+            .with_calibration_file(&path)
+            .with_target_segment_duration(Duration::from_secs(5));
+        assert_eq!(converter.max_length(&mut Vec::new()), 20);
+
+        let text = sentence.repeat(4);
+        let report = convert_verified(&converter, &text, &output).await;
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(report.mode, ConversionMode::Segmented);
+        let not_loaded = report
+            .warnings
+            .iter()
+            .filter(|w| matches!(w, Warning::CalibrationNotLoaded { .. }))
+            .count();
+        assert_eq!(not_loaded, 1);
+    }
+
+    #[tokio::test]
+    async fn test_convert_to_files_names() {
+        let dir = std::path::PathBuf::from(temp_path("segment_files"));
+        let half = format!("人工智能，{}。", "字".repeat(299));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_split_backend(Arc::new(StaticSplitBackend::new(
+                &[&half, &half],
+                Duration::ZERO,
+            )))
+            .with_file_naming(FileNaming::Slug);
+
+        let paths = converter
+            .convert_to_files(&half.repeat(2), &dir)
+            .await
+            .unwrap();
+        let names: Vec<_> = paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        let slug = format!("人工智能_{}", "字".repeat(15));
+        assert_eq!(
+            names,
+            vec![format!("{}.wav", slug), format!("{}_002.wav", slug)]
+        );
+        let samples = read_samples(&std::fs::read(&paths[1]).unwrap());
+        assert_eq!(samples.len(), half.chars().count());
+
+        // The report covers each file and its checksums
+        let report = converter
+            .with_overwrite_policy(OverwritePolicy::Overwrite)
+            .convert_to_files_with_report(&half.repeat(2), &dir)
+            .await
+            .unwrap();
+        assert_eq!(report.segment_count, 2);
+        assert_eq!(report.sources.synthesized, 2);
+        let written: Vec<_> = report
+            .outputs
+            .iter()
+            .map(|o| PathBuf::from(&o.path))
+            .collect();
+        assert_eq!(written, paths);
+        for output in &report.outputs {
+            assert_eq!(output, &digest::digest_file(&output.path).unwrap());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_convert_to_files_template() {
+        let dir = std::path::PathBuf::from(temp_path("templated_files"));
+        let silent = "…".repeat(600);
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_split_backend(Arc::new(StaticSplitBackend::new(
+                &["CON。", &silent],
+                Duration::ZERO,
+            )))
+            .with_voice(Voice::Jam)
+            .with_file_template(OutputTemplate::new("{slug}-{voice}-{chapter}").unwrap());
+
+        let paths = converter
+            .convert_to_files(&format!("CON。{}", silent), &dir)
+            .await
+            .unwrap();
+        let names: Vec<_> = paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["CON_-Jam.wav", "Jam.wav"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_convert_stream_chunks() {
+        let samples: Vec<i16> = (0..2 * 12345).map(|n| (n % 1000) as i16).collect();
+        let half = format!("人工智能，{}。", "字".repeat(299));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(StaticBackend::new(test_util::wav_bytes(
+                &samples, 24000, 2,
+            ))))
+            .with_split_backend(Arc::new(StaticSplitBackend::new(
+                &[&half, &half],
+                Duration::ZERO,
+            )))
+            .with_chunk_duration(Duration::from_millis(100));
+
+        let chunks: Vec<AudioChunk> = converter
+            .convert_stream_chunks(&half.repeat(2))
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        // 12345 frames make five 100 ms chunks of 2400 frames and one of 345
+        assert_eq!(chunks.len(), 12);
+        let segment_duration = Duration::from_secs_f64(12345.0 / 24000.0);
+        for (n, chunk) in chunks.iter().enumerate() {
+            let (segment, index) = (n / 6, n % 6);
+            assert_eq!((chunk.segment_index, chunk.chunk_index), (segment, index));
+            let frames = if index == 5 { 345 } else { 2400 };
+            assert_eq!(chunk.pcm.samples().len(), 2 * frames);
+            assert_eq!(chunk.pcm.channels(), 2);
+            let expected =
+                segment_duration * segment as u32 + Duration::from_millis(100) * index as u32;
+            let error = chunk.offset.max(expected) - chunk.offset.min(expected);
+            assert!(error < Duration::from_micros(1));
+        }
+        for segment in chunks.chunks(6) {
+            let joined: Vec<i16> = segment
+                .iter()
+                .flat_map(|chunk| chunk.pcm.samples().iter().copied())
+                .collect();
+            assert_eq!(joined, samples);
+        }
+
+        // Without a chunk duration, each segment is one chunk
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_split_backend(Arc::new(StaticSplitBackend::new(
+                &[&half, &half],
+                Duration::ZERO,
+            )));
+        let chunks: Vec<AudioChunk> = converter
+            .convert_stream_chunks(&half.repeat(2))
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].offset, chunks[0].pcm.duration());
+    }
+
+    #[tokio::test]
+    async fn test_stream_chunk_offsets_count_pauses_and_failures() {
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(test_util::CorruptBackend::new("第二行。")))
+            .with_newline_pauses(Duration::from_millis(10), Duration::from_millis(20));
+
+        let chunks: Vec<Result<AudioChunk>> = converter
+            .convert_stream_chunks("第一行。\n第二行。\n\n第三行。")
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        assert_eq!(chunks.len(), 3);
+        let first = chunks[0].as_ref().unwrap();
+        assert_eq!(first.offset, Duration::ZERO);
+        assert!(matches!(chunks[1], Err(Error::Audio { .. })));
+        // Four samples at 24 kHz, then both pauses, the one after the
+        // failed segment included
+        let third = chunks[2].as_ref().unwrap();
+        assert_eq!(third.segment_index, 2);
+        assert_eq!(
+            third.offset,
+            Duration::from_nanos(166_666) + Duration::from_millis(10 + 20)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_convert_incremental_synthesizes_before_finish() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key").with_backend(backend.clone());
+        let (mut input, audio) = converter.convert_incremental().unwrap();
+        futures::pin_mut!(audio);
+
+        input.push("你好，").await.unwrap();
+        input.push("世界！再").await.unwrap();
+        let first = audio.next().await.unwrap().unwrap();
+        assert_eq!(read_samples(&first).len(), 6);
+        assert_eq!(backend.requests(), vec!["你好，世界！"]);
+
+        input.push("见。").await.unwrap();
+        input.finish().await;
+        let rest: Vec<_> = audio.collect().await;
+        assert_eq!(rest.len(), 1);
+        assert_eq!(backend.requests(), vec!["你好，世界！", "再见。"]);
+    }
+
+    #[tokio::test]
+    async fn test_convert_incremental_writes_failure_bundle() {
+        let dir = std::path::PathBuf::from(temp_path("failure_bundle_incremental"));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(FailAfterBackend::new(1)))
+            .with_retry_config(0, Duration::ZERO)
+            .with_failure_bundle(&dir)
+            .with_failure_bundle_text(true);
+        let (mut input, audio) = converter.convert_incremental().unwrap();
+        futures::pin_mut!(audio);
+
+        input.push("你好。再见。").await.unwrap();
+        input.finish().await;
+        let results: Vec<_> = audio.collect().await;
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+
+        let bundles: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(bundles.len(), 1);
+        let bundle: FailureBundle = serde_json::from_str(&bundles[0]).unwrap();
+        let statuses: Vec<_> = bundle.segments.iter().map(|s| s.status).collect();
+        assert_eq!(
+            statuses,
+            vec![bundle::SegmentStatus::Done, bundle::SegmentStatus::Failed]
+        );
+        let failed = bundle.failed_segment.unwrap();
+        assert_eq!(failed.index, 1);
+        assert_eq!(failed.text.as_deref(), Some("再见。"));
+    }
+
+    #[tokio::test]
+    async fn test_incremental_queue_backpressure() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_queue_depth(1);
+        let (mut input, audio) = converter.convert_incremental().unwrap();
+        futures::pin_mut!(audio);
+
+        input.push("一。二").await.unwrap();
+        {
+            // The queue holds "一。", so the next sentence waits for room
+            let push = input.push("。三");
+            futures::pin_mut!(push);
+            assert!(futures::poll!(push.as_mut()).is_pending());
+            audio.next().await.unwrap().unwrap();
+            push.await.unwrap();
+        }
+        assert_eq!(backend.requests(), vec!["一。"]);
+
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_queue_depth(1)
+            .with_queue_policy(QueuePolicy::Error);
+        let (mut input, audio) = converter.convert_incremental().unwrap();
+        futures::pin_mut!(audio);
+        input.push("一。二").await.unwrap();
+        assert!(matches!(
+            input.push("。三").await,
+            Err(Error::QueueFull { depth: 1 })
+        ));
+        audio.next().await.unwrap().unwrap();
+        // The rejected chunk was not taken and is pushed again
+        input.push("。三").await.unwrap();
+        let (_, rest) = futures::join!(input.finish(), audio.collect::<Vec<_>>());
+        assert_eq!(rest.len(), 2);
+        assert_eq!(backend.requests(), vec!["一。", "一。", "二。", "三"]);
+    }
+
+    #[tokio::test]
+    async fn test_incremental_push_is_cancel_safe() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_queue_depth(1);
+        let (mut input, audio) = converter.convert_incremental().unwrap();
+        futures::pin_mut!(audio);
+
+        input.push("一。二").await.unwrap();
+        {
+            // The queue is full, so the chunk's sentences wait and the push
+            // is abandoned
+            let push = input.push("。三。四");
+            futures::pin_mut!(push);
+            assert!(futures::poll!(push.as_mut()).is_pending());
+        }
+        let (_, segments) = futures::join!(input.finish(), audio.collect::<Vec<_>>());
+        assert_eq!(segments.len(), 3);
+        assert_eq!(backend.requests(), vec!["一。", "二。三。", "四"]);
+    }
+
+    #[cfg(feature = "live")]
+    #[tokio::test]
+    async fn test_convert_live_fills_buffer() {
+        let converter = Text2Audio::new("test_key").with_backend(Arc::new(MockBackend::new()));
+        let (producer, mut consumer) =
+            live_buffer(test_util::MOCK_SAMPLE_RATE, 1, Duration::from_secs(1));
+        let (mut input, feed) = converter.convert_live(producer).unwrap();
+
+        input.push("你好，世界！再").await.unwrap();
+        input.push("见。").await.unwrap();
+        input.finish().await;
+        feed.await.unwrap();
+
+        let mut out = [0i16; 16];
+        assert_eq!(consumer.fill(&mut out), 9);
+        assert!(out[..9].iter().all(|&sample| sample != 0));
+        assert!(consumer.is_finished());
+        assert_eq!(consumer.underruns(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_progress_eta_approaches_zero() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(SlowBackend::new(Duration::from_millis(100))))
+            .with_newline_pauses(Duration::ZERO, Duration::ZERO)
+            .with_progress_handler(move |event| recorded.lock().unwrap().push(event.clone()))
+            .with_progress_heartbeat(Duration::from_millis(30));
+        let output = temp_path("progress.wav");
+
+        convert_verified(&converter, "一。\n二。\n三。\n四。\n五。", &output).await;
+        std::fs::remove_file(&output).unwrap();
+
+        let events = events.lock().unwrap();
+        let finished: Vec<_> = events
+            .iter()
+            .filter(|event| matches!(event.kind, ProgressKind::SegmentFinished { .. }))
+            .collect();
+        assert_eq!(
+            finished
+                .iter()
+                .map(|event| event.completed)
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 5]
+        );
+        assert!(events.iter().all(|event| event.total == 5));
+        // Heartbeats keep coming while a segment is synthesized
+        assert!(events.len() >= finished.len() + 3 * 4);
+
+        let first_estimate = events.iter().position(|e| e.eta.is_some()).unwrap();
+        assert_eq!(
+            events[first_estimate].kind,
+            ProgressKind::SegmentFinished {
+                index: 1,
+                source: SegmentSource::Synthesized { attempts: 1 },
+            }
+        );
+        assert!(events[..first_estimate]
+            .iter()
+            .all(|event| event.throughput.is_none()));
+        let etas: Vec<Duration> = events[first_estimate..]
+            .iter()
+            .map(|event| event.eta.unwrap())
+            .collect();
+        assert!(etas.windows(2).all(|pair| pair[1] <= pair[0]), "{:?}", etas);
+        assert_eq!(etas[0], Duration::from_millis(300));
+        assert_eq!(etas.last(), Some(&Duration::ZERO));
+        let throughput = finished[1].throughput.unwrap();
+        assert!((throughput.segments_per_second - 10.0).abs() < 1e-9);
+        assert!((throughput.audio_seconds_per_second - 4.0 / 24000.0 / 0.2).abs() < 1e-6);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_progress_reports_split_chunks() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let splitter = AiSplitter::new("test_key", Model::default(), 300)
+            .with_max_prompt_bytes(1500)
+            .with_chat_backend(Arc::new(EchoChatBackend::new(Duration::from_millis(200))));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_split_backend(Arc::new(splitter))
+            .with_progress_handler(move |event| recorded.lock().unwrap().push(event.clone()));
+        let output = temp_path("progress_split.wav");
+
+        convert_verified(&converter, &"人工智能改变世界。".repeat(120), &output).await;
+        std::fs::remove_file(&output).unwrap();
+
+        let kinds: Vec<ProgressKind> = events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| event.kind)
+            .filter(|kind| *kind != ProgressKind::Heartbeat)
+            .collect();
+        let first_segment = kinds
+            .iter()
+            .position(|kind| matches!(kind, ProgressKind::SegmentFinished { .. }))
+            .unwrap();
+        assert_eq!(kinds[0], ProgressKind::SplitStarted);
+        let split = &kinds[1..first_segment];
+        assert!(split.len() >= 4);
+        for pair in split.chunks(2) {
+            let (
+                ProgressKind::SplitChunkSent { chunk, chunks },
+                ProgressKind::SplitChunkParsed {
+                    chunk: parsed,
+                    chunks: parsed_chunks,
+                    segments,
+                },
+            ) = (pair[0], pair[1])
+            else {
+                panic!("unexpected split events {:?}", pair);
+            };
+            assert_eq!((parsed, parsed_chunks), (chunk, chunks));
+            assert!(chunk < chunks && segments > 1);
+        }
+        assert!(kinds[first_segment..]
+            .iter()
+            .all(|kind| matches!(kind, ProgressKind::SegmentFinished { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_timing_sidecar() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend)
+            .with_timing_sidecar(true);
+        let output = temp_path("timing_sidecar.wav");
+
+        let report = convert_verified(&converter, "  你好，世界！", &output).await;
+        let sidecar = format!("{}.segments.json", output);
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&sidecar).unwrap()).unwrap();
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(&sidecar).unwrap();
+
+        assert_eq!(report.segments.len(), 1);
+        assert_eq!(report.segments[0].source_range, 0..8);
+        assert_eq!(
+            report.segments[0].duration,
+            Duration::from_secs_f64(6.0 / test_util::MOCK_SAMPLE_RATE as f64)
+        );
+        assert_eq!(json[0]["char_start"], 0);
+        assert_eq!(json[0]["char_end"], 8);
+        assert_eq!(json[0]["text"], "你好，世界！");
+    }
+
+    #[tokio::test]
+    async fn test_speech_marks_golden() {
+        let text = "开场白。\n<!-- section: ad -->\n广告时间。\n\"限时\"优惠。\n\n\
+                    <!-- section: default -->\n结束。";
+        let marks = temp_path("speech_marks.marks");
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(PerCharBackend::new(Duration::from_millis(100))))
+            .with_deterministic(true)
+            .with_newline_pauses(Duration::from_millis(250), Duration::from_millis(750))
+            .with_section_profiles(HashMap::from([(
+                "ad".to_string(),
+                SectionProfile::new().with_gap(Duration::from_millis(500)),
+            )]))
+            .with_timing_sidecar(true)
+            .with_speech_marks(&marks);
+        let output = temp_path("speech_marks.wav");
+
+        convert_verified(&converter, text, &output).await;
+        let plain = std::fs::read_to_string(&marks).unwrap();
+        let converter = converter.with_speech_mark_sections(true);
+        let report = convert_verified(&converter, text, &output).await;
+        let with_sections = std::fs::read_to_string(&marks).unwrap();
+        let timings = std::fs::read_to_string(format!("{}.segments.json", output)).unwrap();
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(format!("{}.segments.json", output)).unwrap();
+        std::fs::remove_file(&marks).unwrap();
+
+        assert_eq!(with_sections, include_str!("testdata/speech_marks.ndjson"));
+        let sentences: Vec<&str> = with_sections
+            .lines()
+            .filter(|line| line.contains("\"sentence\""))
+            .collect();
+        assert_eq!(plain.lines().collect::<Vec<_>>(), sentences);
+
+        // Sentence starts are those of the timing sidecar
+        let timings: Vec<serde_json::Value> = serde_json::from_str(&timings).unwrap();
+        let starts: Vec<u64> = sentences
+            .iter()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["time"]
+                    .as_u64()
+                    .unwrap()
+            })
+            .collect();
+        let expected: Vec<u64> = timings
+            .iter()
+            .map(|segment| (segment["start_seconds"].as_f64().unwrap() * 1000.0).round() as u64)
+            .collect();
+        assert_eq!(starts, expected);
+        assert!(starts.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(report.to_speech_marks(true).unwrap(), with_sections);
+        let Err(Error::Config(message)) = ConversionReport {
+            detail: ReportDetail::Hashes,
+            ..report
+        }
+        .to_speech_marks(false) else {
+            panic!("speech marks need segment text");
+        };
+        assert!(message.contains("speech marks"));
+    }
+
+    #[tokio::test]
+    async fn test_report_detail() {
+        let backend = Arc::new(MockBackend::new());
+        let mut converter = Text2Audio::new("test_key")
+            .with_backend(backend)
+            .with_newline_pauses(Duration::from_millis(10), Duration::from_millis(50))
+            .with_timing_sidecar(true);
+        let text = "第一段。\n\n第二段。\n第三段。";
+        let output = temp_path("report_detail.wav");
+
+        let full = convert_verified(&converter, text, &output).await;
+        converter.report_detail = Some(ReportDetail::Hashes);
+        let hashes = convert_verified(&converter, text, &output).await;
+        converter.report_detail = Some(ReportDetail::None);
+        let none = convert_verified(&converter, text, &output).await;
+        converter.report_detail = None;
+        std::fs::remove_file(format!("{}.segments.json", output)).unwrap();
+
+        assert_eq!(full.detail, ReportDetail::Full);
+        assert_eq!(full.segments[1].text.as_deref(), Some("第二段。"));
+        for (full, hashed) in full.segments.iter().zip(&hashes.segments) {
+            assert_eq!(hashed.text, None);
+            assert_eq!(hashed.text_sha256, full.text_sha256);
+            assert_eq!(hashed.source_range, full.source_range);
+        }
+        assert!(none.segments.iter().all(|s| s.text_sha256.is_none()));
+
+        // Serialized reports shrink with the detail they keep
+        let full_json = full.to_timing_json().unwrap();
+        let hashes_json = hashes.to_timing_json().unwrap();
+        assert!(hashes_json.len() < full_json.len());
+        assert!(!hashes_json.contains("第二段"));
+        let Err(Error::Config(message)) = none.to_timing_json() else {
+            panic!("timing JSON needs hashes");
+        };
+        assert!(message.contains("ReportDetail::Hashes"));
+
+        // Patching needs the text of every segment
+        let Err(Error::Config(message)) = hashes.to_plan() else {
+            panic!("patching needs full text");
+        };
+        assert!(message.contains("patching"));
+        assert!(message.contains("with_report_detail(ReportDetail::Full)"));
+        let mut plan = full.to_plan().unwrap();
+        plan.segments[1] = "改过的第二段。".to_string();
+        let plan_path = temp_path("report_detail.plan.json");
+        std::fs::write(&plan_path, plan.to_json()).unwrap();
+        let patched = converter
+            .convert_segments_from_json(&plan_path, &output)
+            .await
+            .unwrap();
+        std::fs::remove_file(&plan_path).unwrap();
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(format!("{}.segments.json", output)).unwrap();
+        let texts: Vec<_> = patched
+            .segments
+            .iter()
+            .map(|s| s.text.as_deref().unwrap())
+            .collect();
+        assert_eq!(texts, ["第一段。", "改过的第二段。", "第三段。"]);
+        assert_eq!(patched.segments[0].pause_after, Duration::from_millis(50));
+
+        // Large reports keep only hashes unless configured otherwise
+        let mut report = ConversionReport::new(ConversionMode::Direct);
+        let wav = test_util::wav_bytes(&[0; 4], 16000, 1);
+        let large = "字".repeat(FULL_REPORT_MAX_BYTES / 3 + 1);
+        report
+            .push_segment(&large, &wav, SegmentSource::Checkpoint)
+            .unwrap();
+        let mut kept = report.clone();
+        converter.limit_report_detail(&mut report);
+        assert_eq!(report.detail, ReportDetail::Hashes);
+        assert_eq!(report.segments[0].text, None);
+        converter.report_detail = Some(ReportDetail::Full);
+        converter.limit_report_detail(&mut kept);
+        assert_eq!(kept.segments[0].text.as_deref(), Some(large.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_report_detail_applies_while_pushing() {
+        let mut converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_newline_pauses(Duration::from_millis(10), Duration::from_millis(50))
+            .with_report_detail(ReportDetail::Hashes);
+        let text = "第一段。\n\n第二段。\n第三段。";
+        let mut wav = Vec::new();
+        let hashes = converter
+            .convert_into(text, &mut Output::Memory(&mut wav))
+            .await
+            .unwrap();
+        converter.report_detail = Some(ReportDetail::Full);
+        let full = converter
+            .convert_into(text, &mut Output::Memory(&mut wav))
+            .await
+            .unwrap();
+
+        // Dropped text still aligns to the input
+        for (full, hashed) in full.segments.iter().zip(&hashes.segments) {
+            assert_eq!(hashed.text, None);
+            assert_eq!(hashed.text_sha256, full.text_sha256);
+            assert_eq!(hashed.source_range, full.source_range);
+        }
+
+        // By default text is dropped as soon as it grows too large, and
+        // later segments never store it
+        converter.report_detail = None;
+        let mut report = converter.new_report(ConversionMode::Segmented);
+        let wav = test_util::wav_bytes(&[0; 4], 16000, 1);
+        let large = "字".repeat(FULL_REPORT_MAX_BYTES / 3 + 1);
+        report
+            .push_segment("第一段。", &wav, SegmentSource::Checkpoint)
+            .unwrap();
+        assert_eq!(report.segments[0].text.as_deref(), Some("第一段。"));
+        report
+            .push_segment(&large, &wav, SegmentSource::Checkpoint)
+            .unwrap();
+        report
+            .push_segment("第三段。", &wav, SegmentSource::Checkpoint)
+            .unwrap();
+        assert_eq!(report.detail, ReportDetail::Hashes);
+        assert_eq!(report.text_bytes(), 0);
+        assert!(report.segments.iter().all(|s| s.text_sha256.is_some()));
+
+        // Sidecars written from the report keep the text until the end
+        let report = converter
+            .with_timing_sidecar(true)
+            .new_report(ConversionMode::Segmented);
+        assert_eq!(report.text_limit, None);
+    }
+
+    #[test]
+    fn test_runtime_handle() {
+        let io_runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("t2a-io")
+            .build()
+            .unwrap();
+        let converter =
+            Text2Audio::new("test_key").with_runtime_handle(io_runtime.handle().clone());
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let thread = runtime.block_on(async {
+            converter
+                .runtime_handle()
+                .spawn(async { std::thread::current().name().map(str::to_string) })
+                .await
+                .unwrap()
+        });
+        assert_eq!(thread.as_deref(), Some("t2a-io"));
+    }
+
+    #[test]
+    fn test_merge_runs_on_runtime_handle() {
+        let io_runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("t2a-io")
+            .build()
+            .unwrap();
+        let thread = Arc::new(Mutex::new(None));
+        let inspected = thread.clone();
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_runtime_handle(io_runtime.handle().clone())
+            .with_audio_inspector(move |_| {
+                *inspected.lock().unwrap() = std::thread::current().name().map(str::to_string);
+            });
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime
+            .block_on(converter.convert_to_bytes("你好。"))
+            .unwrap();
+        assert_eq!(thread.lock().unwrap().as_deref(), Some("t2a-io"));
+    }
+
+    #[tokio::test]
+    async fn test_convert_bilingual_alternates_voices() {
+        // 1 ms is 24 frames at the mock sample rate
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_voice(Voice::Tongtong)
+            .with_bilingual_pauses(Duration::from_millis(1), Duration::from_millis(2))
+            .with_timing_sidecar(true);
+        let output = temp_path("bilingual.wav");
+        let pairs = vec![
+            ("你好。".to_string(), "Hi.".to_string()),
+            ("谢谢。".to_string(), "Thanks.".to_string()),
+        ];
+
+        let report = converter
+            .convert_bilingual(pairs, &output, Voice::Jam)
+            .await
+            .unwrap();
+        verify_output(&report, std::path::Path::new(&output)).unwrap();
+        let samples = read_samples(&std::fs::read(&output).unwrap());
+        let sidecar = format!("{}.segments.json", output);
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&sidecar).unwrap()).unwrap();
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(&sidecar).unwrap();
+
+        assert_eq!(
+            backend.requests(),
+            vec!["你好。", "Hi.", "谢谢。", "Thanks."]
+        );
+        let voices: Vec<String> = backend
+            .configs()
+            .iter()
+            .map(|config| format!("{:?}", config.voice))
+            .collect();
+        let expected: Vec<String> = [Voice::Tongtong, Voice::Jam, Voice::Tongtong, Voice::Jam]
+            .iter()
+            .map(|voice| format!("{:?}", voice.as_tts_voice()))
+            .collect();
+        assert_eq!(voices, expected);
+        assert_eq!(
+            samples,
+            [
+                vec![1000; 3],
+                vec![0; 24],
+                vec![1000; 3],
+                vec![0; 48],
+                vec![1000; 3],
+                vec![0; 24],
+                vec![1000; 7],
+            ]
+            .concat()
+        );
+        let tracks: Vec<_> = report.segments.iter().map(|s| s.track).collect();
+        assert_eq!(
+            tracks,
+            vec![
+                Some(Track::Primary),
+                Some(Track::Secondary),
+                Some(Track::Primary),
+                Some(Track::Secondary)
+            ]
+        );
+        assert_eq!(report.segments[1].source_range, 0..3);
+        assert_eq!(report.segments[3].pause_after, Duration::ZERO);
+        assert_eq!(json[0]["track"], "primary");
+        assert_eq!(json[1]["track"], "secondary");
+
+        let empty = converter
+            .convert_bilingual(Vec::new(), &output, Voice::Jam)
+            .await;
+        assert!(matches!(empty, Err(Error::EmptyInput)));
+    }
+
+    #[tokio::test]
+    async fn test_uniform_segment_duration() {
+        // 1 ms is 24 frames at the mock sample rate
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_newline_pauses(Duration::ZERO, Duration::ZERO)
+            .with_uniform_segment_duration(Duration::from_millis(1));
+
+        let wav = converter.convert_to_bytes("你好。").await.unwrap();
+        assert_eq!(read_samples(&wav), [vec![1000; 3], vec![0; 21]].concat());
+
+        let output = temp_path("uniform_duration.wav");
+        let report = convert_verified(&converter, "第一行。\n第二行很长。", &output).await;
+        let samples = read_samples(&std::fs::read(&output).unwrap());
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(samples.len(), 48);
+        assert_eq!(samples[..24], [vec![1000; 4], vec![0; 20]].concat());
+        assert_eq!(samples[24..], [vec![1000; 6], vec![0; 18]].concat());
+        assert_eq!(report.segments[1].start, Duration::from_millis(1));
+        assert!(report.warnings.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sidecar_metadata() {
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_newline_pauses(Duration::from_millis(10), Duration::from_millis(20))
+            .with_sidecar_metadata(true);
+        let output = temp_path("sidecar_metadata.wav");
+
+        let report = convert_verified(&converter, "第一行。\n第二行。", &output).await;
+        let sidecar = format!("{}.json", output);
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&sidecar).unwrap()).unwrap();
+        let bytes = std::fs::metadata(&output).unwrap().len();
+        let frames = read_samples(&std::fs::read(&output).unwrap()).len();
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(&sidecar).unwrap();
+
+        assert_eq!(report.segment_count, 2);
+        assert_eq!(json["segment_count"], 2);
+        assert_eq!(json["sample_rate"], test_util::MOCK_SAMPLE_RATE);
+        assert_eq!(json["channels"], 1);
+        assert_eq!(json["bytes"], bytes);
+        let duration = json["duration_seconds"].as_f64().unwrap();
+        assert!((duration - frames as f64 / test_util::MOCK_SAMPLE_RATE as f64).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_convert_formats_synthesizes_once() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_newline_pauses(Duration::from_millis(10), Duration::from_millis(20));
+        let first = temp_path("formats_first.wav");
+        let second = temp_path("formats_second.wav");
+
+        let report = converter
+            .convert_formats(
+                "第一行。\n第二行。",
+                vec![
+                    (first.clone(), OutputFormat::Wav),
+                    (second.clone(), OutputFormat::Wav),
+                ],
+            )
+            .await
+            .unwrap();
+        let first_bytes = std::fs::read(&first).unwrap();
+        let second_bytes = std::fs::read(&second).unwrap();
+        std::fs::remove_file(&first).unwrap();
+        std::fs::remove_file(&second).unwrap();
+
+        assert_eq!(backend.requests().len(), 2);
+        assert_eq!(report.segment_count, 2);
+        assert_eq!(first_bytes, second_bytes);
+        assert_eq!(read_samples(&first_bytes).len(), 4 + 240 + 4);
+    }
+
+    #[tokio::test]
+    async fn test_deterministic_conversions_are_identical() {
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(StaggeredBackend::new(Duration::from_millis(2))))
+            .with_parallel(4)
+            .with_newline_pauses(Duration::from_millis(10), Duration::from_millis(20))
+            .with_timing_sidecar(true)
+            .with_sidecar_metadata(true)
+            .with_deterministic(true);
+        let output = temp_path("deterministic.wav");
+        let text = "第一行比较长的一句话。\n二。\n第三行。\n\n第四段落的文字。";
+
+        let mut runs = Vec::new();
+        for _ in 0..2 {
+            let report = converter.convert_with_report(text, &output).await.unwrap();
+            let files: Vec<Vec<u8>> = [
+                output.clone(),
+                format!("{}.json", output),
+                format!("{}.segments.json", output),
+            ]
+            .iter()
+            .map(|path| std::fs::read(path).unwrap())
+            .collect();
+            for path in converter.output_files(&output) {
+                std::fs::remove_file(path).unwrap();
+            }
+            assert_eq!(report.timings, PhaseTimings::default());
+            assert_eq!(report.stats.tts.requests, 4);
+            runs.push((format!("{:?}", report), report.manifest_sha256(), files));
+        }
+        assert_eq!(runs[0], runs[1]);
+    }
+
+    #[tokio::test]
+    async fn test_report_output_digests() {
+        use sha2::{Digest, Sha256};
+
+        let sha256 = |path: &str| format!("{:x}", Sha256::digest(std::fs::read(path).unwrap()));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_newline_pauses(Duration::from_millis(10), Duration::from_millis(20))
+            .with_sidecar_metadata(true);
+        let output = temp_path("digest.wav");
+
+        let report = converter
+            .convert_with_report("第一行。\n第二行。", &output)
+            .await
+            .unwrap();
+        let sidecar = format!("{}.json", output);
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&sidecar).unwrap()).unwrap();
+        assert_eq!(report.outputs.len(), 1);
+        assert_eq!(report.outputs[0].path, output);
+        assert_eq!(report.outputs[0].sha256, sha256(&output));
+        assert_eq!(report.outputs[0].sha256, json["sha256"].as_str().unwrap());
+        assert_eq!(
+            report.outputs[0].bytes,
+            std::fs::metadata(&output).unwrap().len()
+        );
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(&sidecar).unwrap();
+
+        // Written in place under a memory limit, the file is hashed once done
+        let bounded = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_newline_pauses(Duration::from_millis(10), Duration::from_millis(20))
+            .with_max_memory(1);
+        let report = bounded
+            .convert_with_report("第一行。\n第二行。", &output)
+            .await
+            .unwrap();
+        assert_eq!(report.outputs[0].sha256, sha256(&output));
+        std::fs::remove_file(&output).unwrap();
+
+        let first = temp_path("digest_first.wav");
+        let second = temp_path("digest_second.wav");
+        let report = converter
+            .with_sidecar_metadata(false)
+            .convert_formats(
+                "第一行。\n第二行。",
+                vec![
+                    (first.clone(), OutputFormat::Wav),
+                    (second.clone(), OutputFormat::Wav),
+                ],
+            )
+            .await
+            .unwrap();
+        let hashes = [sha256(&first), sha256(&second)];
+        std::fs::remove_file(&first).unwrap();
+        std::fs::remove_file(&second).unwrap();
+        let reported: Vec<_> = report.outputs.iter().map(|o| o.sha256.clone()).collect();
+        assert_eq!(reported, hashes);
+        let listing = format!("{}  {}\n{}  {}\n", hashes[0], first, hashes[1], second);
+        assert_eq!(
+            report.manifest_sha256(),
+            Some(format!("{:x}", Sha256::digest(listing)))
+        );
+        assert_eq!(
+            ConversionReport::new(ConversionMode::Direct).manifest_sha256(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mode_selection() {
+        let short = "你好。";
+        // Over the max segment length, but every line fits on its own
+        let long = "这是一个用于测试的句子。\n".repeat(60);
+        let converter = |mode| {
+            let backend = Arc::new(MockBackend::new());
+            let converter = Text2Audio::new("test_key")
+                .with_backend(backend.clone())
+                .with_newline_pauses(Duration::ZERO, Duration::ZERO)
+                .with_mode(mode);
+            (converter, backend)
+        };
+        let output = temp_path("mode_selection.wav");
+
+        let cases = [
+            (ModeSelection::Auto, short, ConversionMode::Direct, 1),
+            (ModeSelection::Auto, &long, ConversionMode::Segmented, 60),
+            (ModeSelection::ForceDirect, short, ConversionMode::Direct, 1),
+            (ModeSelection::ForceDirect, &long, ConversionMode::Direct, 1),
+            (
+                ModeSelection::ForceSegmented,
+                short,
+                ConversionMode::Segmented,
+                1,
+            ),
+            (
+                ModeSelection::ForceSegmented,
+                &long,
+                ConversionMode::Segmented,
+                60,
+            ),
+        ];
+        for (mode, text, expected, requests) in cases {
+            let (converter, backend) = converter(mode);
+            assert_eq!(converter.conversion_mode(text).unwrap(), expected);
+            assert_eq!(converter.plan(text).await.unwrap().mode, expected);
+            let report = convert_verified(&converter, text, &output).await;
+            assert_eq!(report.mode, expected, "{:?}", mode);
+            assert_eq!(backend.requests().len(), requests, "{:?}", mode);
+        }
+        std::fs::remove_file(&output).unwrap();
+
+        let too_long = "句".repeat(TTS_MAX_CHARS + 1);
+        let (converter, backend) = converter(ModeSelection::ForceDirect);
+        assert!(matches!(
+            converter.conversion_mode(&too_long),
+            Err(Error::Config(_))
+        ));
+        let result = converter.convert(&too_long, &output).await;
+        assert!(matches!(result, Err(Error::Config(m)) if m.contains("forced")));
+        assert!(backend.requests().is_empty());
+        assert_eq!(converter.stats().chat.requests, 0);
+    }
+
+    #[tokio::test]
+    async fn test_convert_many_error_modes() {
+        let outputs: Vec<String> = (0..3)
+            .map(|i| temp_path(&format!("many_{}.wav", i)))
+            .collect();
+        let jobs: Vec<(&str, &str)> = outputs
+            .iter()
+            .map(|output| ("你好。", output.as_str()))
+            .collect();
+        // The first job succeeds and the other two fail
+        let converter = |mode| {
+            Text2Audio::new("test_key")
+                .with_backend(Arc::new(FailAfterBackend::new(1)))
+                .with_retry_config(0, Duration::ZERO)
+                .with_error_mode(mode)
+        };
+        let cleanup = || {
+            for output in &outputs {
+                let _ = std::fs::remove_file(output);
+            }
+        };
+
+        let results = converter(ErrorMode::Collect)
+            .convert_many(&jobs)
+            .await
+            .unwrap();
+        cleanup();
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(Error::Http(_))));
+        assert!(matches!(results[2], Err(Error::Http(_))));
+
+        let result = converter(ErrorMode::FailFast).convert_many(&jobs).await;
+        let third_written = std::path::Path::new(&outputs[2]).exists();
+        cleanup();
+        assert!(matches!(result, Err(Error::Http(_))));
+        assert!(!third_written);
+
+        let result = converter(ErrorMode::Aggregate).convert_many(&jobs).await;
+        cleanup();
+        assert!(matches!(result, Err(Error::Multiple(errors)) if errors.len() == 2));
+
+        let results = converter(ErrorMode::Aggregate)
+            .convert_many(&jobs[..1])
+            .await
+            .unwrap();
+        cleanup();
+        assert!(results[0].is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_convert_formats_requires_output() {
+        let converter = Text2Audio::new("test_key").with_backend(Arc::new(MockBackend::new()));
+        let result = converter.convert_formats("你好。", Vec::new()).await;
+        assert!(matches!(result, Err(Error::Config(_))));
+    }
+
+    #[tokio::test]
+    async fn test_convert_formats_rejects_conflicting_outputs() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_sidecar_metadata(true)
+            .with_speech_marks(temp_path("formats_conflict.marks"));
+        let output = temp_path("formats_conflict.wav");
+
+        let same_path = converter
+            .convert_formats(
+                "你好。",
+                vec![
+                    (output.clone(), OutputFormat::Wav),
+                    (output.clone(), OutputFormat::Wav),
+                ],
+            )
+            .await;
+        assert!(matches!(same_path, Err(Error::Config(_))));
+        let sidecar = converter
+            .convert_formats(
+                "你好。",
+                vec![
+                    (output.clone(), OutputFormat::Wav),
+                    (format!("{}.json", output), OutputFormat::Wav),
+                ],
+            )
+            .await;
+        assert!(matches!(sidecar, Err(Error::Config(_))));
+        assert!(backend.requests().is_empty());
+        assert!(!Path::new(&output).exists());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_conversions_leave_no_stray_files() {
+        let dir = PathBuf::from(temp_path("dropped"));
+        let half = format!("{}。", "字".repeat(299));
+        let cache = dir.join("cache");
+        let output = dir.join("out.wav");
+        let output = output.to_str().unwrap();
+
+        for answered in 0..4 {
+            std::fs::create_dir_all(&dir).unwrap();
+            let backend = Arc::new(StallingBackend::new(answered));
+            let converter = Text2Audio::new("test_key")
+                .with_backend(backend.clone())
+                .with_split_backend(Arc::new(StaticSplitBackend::new(
+                    &[&half, &half, &half, &half],
+                    Duration::ZERO,
+                )))
+                .with_split_cache(&cache)
+                .with_max_memory(1);
+            let text = half.repeat(4);
+            // Boxed, so dropping it runs the future's destructors right away
+            let mut convert = Box::pin(converter.convert_with_report(&text, output));
+            tokio::select! {
+                _ = &mut convert => panic!("conversion finished despite the stall"),
+                _ = backend.stalled.notified() => {}
+            }
+            // From the second segment on the output is being merged on disk
+            assert_eq!(Path::new(output).exists(), answered > 0);
+            drop(convert);
+
+            let files = test_util::files_under(&dir);
+            assert_eq!(files.len(), 1, "{:?}", files);
+            assert!(files[0].starts_with(&cache));
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+
+        let text = "第一行。\n第二行。\n第三行。";
+        let project_path = dir.join("book.t2a");
+        for answered in 0..3 {
+            std::fs::create_dir_all(&dir).unwrap();
+            let backend = Arc::new(StallingBackend::new(answered));
+            let converter = Text2Audio::new("test_key")
+                .with_backend(backend.clone())
+                .with_newline_pauses(Duration::ZERO, Duration::ZERO);
+            converter.new_project(text).save(&project_path).unwrap();
+            let mut run = Box::pin(converter.run_project(project_path.to_str().unwrap(), output));
+            tokio::select! {
+                _ = &mut run => panic!("project finished despite the stall"),
+                _ = backend.stalled.notified() => {}
+            }
+            assert!(Project::lock_path(&project_path).exists());
+            drop(run);
+
+            // Only the project and the audio of its completed segments remain
+            let files = test_util::files_under(&dir);
+            let audio_dir = Project::audio_dir(&project_path);
+            assert_eq!(files.len(), 1 + answered, "{:?}", files);
+            assert!(files
+                .iter()
+                .all(|file| *file == project_path || file.starts_with(&audio_dir)));
+            assert!(files.iter().all(|file| file.extension().unwrap() != "tmp"));
+            assert_eq!(
+                Project::load(&project_path).unwrap().completed.len(),
+                answered
+            );
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_project_resumes_after_failure() {
+        let text = "第一行。\n第二行。\n第三行。";
+        let project_path = temp_path("resume.t2a");
+        let output = temp_path("resume.wav");
+        let pauses = (Duration::from_millis(10), Duration::from_millis(20));
+
+        let failing = Text2Audio::new("test_key")
+            .with_backend(Arc::new(FailAfterBackend::new(1)))
+            .with_newline_pauses(pauses.0, pauses.1)
+            .with_retry_config(0, Duration::ZERO);
+        failing.new_project(text).save(&project_path).unwrap();
+        let result = failing.run_project(&project_path, &output).await;
+        assert!(matches!(result, Err(Error::Http(_))));
+        assert!(!std::path::Path::new(&output).exists());
+
+        // Partial progress survives a save/load round trip
+        let project = Project::load(&project_path).unwrap();
+        assert_eq!(project.segments.as_ref().unwrap().len(), 3);
+        assert_eq!(project.completed.len(), 1);
+        assert!(!project.is_complete());
+        project.save(&project_path).unwrap();
+        assert_eq!(Project::load(&project_path).unwrap(), project);
+
+        let backend = Arc::new(MockBackend::new());
+        let converter = project
+            .settings
+            .apply(Text2Audio::new("test_key"))
+            .with_backend(backend.clone());
+        let report = converter.run_project(&project_path, &output).await.unwrap();
+        let samples = read_samples(&std::fs::read(&output).unwrap());
+        let project = Project::load(&project_path).unwrap();
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(&project_path).unwrap();
+        std::fs::remove_dir_all(Project::audio_dir(&project_path)).unwrap();
+
+        assert_eq!(backend.requests(), vec!["第二行。", "第三行。"]);
+        assert_eq!(report.segment_count, 3);
+        assert_eq!(samples.len(), 4 + 240 + 4 + 240 + 4);
+        assert!(project.is_complete());
+    }
+
+    #[tokio::test]
+    async fn test_run_project_post_processing_applies_to_checkpoints() {
+        let text = "第一行。\n第二行。\n第三行。";
+        let padding = Some(Duration::from_millis(5));
+        let converter = |backend: Arc<dyn TtsBackend>, padding: Option<Duration>| {
+            let converter = Text2Audio::new("test_key")
+                .with_backend(backend)
+                .with_newline_pauses(Duration::from_millis(1), Duration::from_millis(2))
+                .with_retry_config(0, Duration::ZERO);
+            match padding {
+                Some(duration) => converter.with_uniform_segment_duration(duration),
+                None => converter,
+            }
+        };
+        let run = |converter: Text2Audio, name: &'static str| async move {
+            let project_path = temp_path(&format!("{}.t2a", name));
+            let output = temp_path(&format!("{}.wav", name));
+            if !std::path::Path::new(&project_path).exists() {
+                converter.new_project(text).save(&project_path).unwrap();
+            }
+            let result = converter.run_project(&project_path, &output).await;
+            let audio = result.map(|_| std::fs::read(&output).unwrap());
+            (audio, project_path, output)
+        };
+
+        // Padding toggled between a failed run and its resume
+        for (first, second) in [(None, padding), (padding, None)] {
+            let (fresh, fresh_project, fresh_output) =
+                run(converter(Arc::new(MockBackend::new()), second), "fresh_run").await;
+
+            let (failed, project_path, _) = run(
+                converter(Arc::new(FailAfterBackend::new(2)), first),
+                "toggled_run",
+            )
+            .await;
+            assert!(matches!(failed, Err(Error::Http(_))));
+            let backend = Arc::new(MockBackend::new());
+            let (resumed, _, output) = run(converter(backend.clone(), second), "toggled_run").await;
+
+            assert_eq!(backend.requests(), vec!["第三行。"]);
+            assert_eq!(resumed.unwrap(), fresh.unwrap());
+            for path in [&fresh_project, &project_path] {
+                std::fs::remove_file(path).unwrap();
+                std::fs::remove_dir_all(Project::audio_dir(path)).unwrap();
+            }
+            std::fs::remove_file(&fresh_output).unwrap();
+            std::fs::remove_file(&output).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_project_concurrent_runs() {
+        let text = "第一行。\n第二行。\n第三行。";
+        let project_path = temp_path("concurrent.t2a");
+        let outputs = (temp_path("concurrent_1.wav"), temp_path("concurrent_2.wav"));
+        let pause = Duration::from_millis(10);
+
+        let holder = Text2Audio::new("test_key")
+            .with_backend(Arc::new(SlowBackend::new(Duration::from_millis(50))))
+            .with_newline_pauses(pause, pause);
+        holder.new_project(text).save(&project_path).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        let blocked = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_newline_pauses(pause, pause)
+            .with_lock_timeout(Duration::from_millis(10));
+
+        let (first, second) = tokio::join!(holder.run_project(&project_path, &outputs.0), async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            blocked.run_project(&project_path, &outputs.1).await
+        });
+        first.unwrap();
+        second.unwrap();
+        let audio = (
+            std::fs::read(&outputs.0).unwrap(),
+            std::fs::read(&outputs.1).unwrap(),
+        );
+        let project = Project::load(&project_path).unwrap();
+        let lock_left = Project::lock_path(&project_path).exists();
+        std::fs::remove_file(&outputs.0).unwrap();
+        std::fs::remove_file(&outputs.1).unwrap();
+        std::fs::remove_file(&project_path).unwrap();
+        std::fs::remove_dir_all(Project::audio_dir(&project_path)).unwrap();
+
+        // The blocked run synthesized everything itself without saving
+        assert_eq!(backend.requests().len(), 3);
+        assert_eq!(read_samples(&audio.0), read_samples(&audio.1));
+        assert!(project.is_complete());
+        assert!(!lock_left);
+    }
+
+    #[tokio::test]
+    async fn test_run_project_waits_for_lock() {
+        let project_path = temp_path("wait_lock.t2a");
+        let outputs = (temp_path("wait_lock_1.wav"), temp_path("wait_lock_2.wav"));
+
+        let holder = Text2Audio::new("test_key")
+            .with_backend(Arc::new(SlowBackend::new(Duration::from_millis(50))));
+        holder
+            .new_project("第一行。\n第二行。")
+            .save(&project_path)
+            .unwrap();
+        let backend = Arc::new(MockBackend::new());
+        let waiting = Text2Audio::new("test_key").with_backend(backend.clone());
+
+        let (first, second) = tokio::join!(holder.run_project(&project_path, &outputs.0), async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            waiting.run_project(&project_path, &outputs.1).await
+        });
+        first.unwrap();
+        second.unwrap();
+        let audio = (
+            std::fs::read(&outputs.0).unwrap(),
+            std::fs::read(&outputs.1).unwrap(),
+        );
+        std::fs::remove_file(&outputs.0).unwrap();
+        std::fs::remove_file(&outputs.1).unwrap();
+        std::fs::remove_file(&project_path).unwrap();
+        std::fs::remove_dir_all(Project::audio_dir(&project_path)).unwrap();
+
+        // Everything was completed by the time the lock was released
+        assert!(backend.requests().is_empty());
+        assert_eq!(audio.0, audio.1);
+    }
+
+    #[tokio::test]
+    async fn test_run_project_reports_segment_sources() {
+        let project_path = temp_path("sources.t2a");
+        let output = temp_path("sources.wav");
+        let pause = Duration::from_millis(10);
+
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_newline_pauses(pause, pause);
+        converter
+            .new_project("第一行。\n第二行。\n第三行。")
+            .save(&project_path)
+            .unwrap();
+        let report = converter.run_project(&project_path, &output).await.unwrap();
+        assert_eq!(report.sources.synthesized, 3);
+        assert_eq!(report.sources.tts_attempts, 3);
+
+        // Invalidate the middle segment's stored audio
+        let project = Project::load(&project_path).unwrap();
+        let audio_dir = Project::audio_dir(&project_path);
+        std::fs::write(audio_dir.join(&project.completed[1].audio_file), b"stale").unwrap();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(FlakyBackend::new(1)))
+            .with_newline_pauses(pause, pause)
+            .with_retry_config(3, Duration::ZERO)
+            .with_progress_handler(move |event| recorded.lock().unwrap().push(event.clone()));
+        let report = converter.run_project(&project_path, &output).await.unwrap();
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(&project_path).unwrap();
+        std::fs::remove_dir_all(&audio_dir).unwrap();
+
+        let sources: Vec<_> = report.segments.iter().map(|s| s.source).collect();
+        assert_eq!(
+            sources,
+            vec![
+                SegmentSource::Checkpoint,
+                SegmentSource::Synthesized { attempts: 2 },
+                SegmentSource::Checkpoint,
+            ]
+        );
+        assert_eq!(
+            report.sources,
+            SourceCounts {
+                synthesized: 1,
+                tts_attempts: 2,
+                checkpoint: 2,
+            }
+        );
+        assert_eq!(report.stats.tts.requests, 2);
+
+        // Progress events tell the sources apart as segments finish
+        let finished: Vec<_> = events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|event| match event.kind {
+                ProgressKind::SegmentFinished { source, .. } => Some((source, event.sources)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            finished
+                .iter()
+                .map(|(source, _)| *source)
+                .collect::<Vec<_>>(),
+            sources
+        );
+        assert_eq!(
+            finished[0].1,
+            SourceCounts {
+                checkpoint: 1,
+                ..SourceCounts::default()
+            }
+        );
+        assert_eq!(finished.last().unwrap().1, report.sources);
+    }
+
+    #[tokio::test]
+    async fn test_run_project_rejects_other_settings() {
+        let project_path = temp_path("other_settings.t2a");
+        Text2Audio::new("test_key")
+            .with_voice(Voice::Jam)
+            .new_project("你好。")
+            .save(&project_path)
+            .unwrap();
+
+        let converter = || Text2Audio::new("test_key").with_backend(Arc::new(MockBackend::new()));
+        // Pauses and preprocessing change the audio just like the voice
+        let converters = [
+            converter(),
+            converter()
+                .with_voice(Voice::Jam)
+                .with_newline_pauses(Duration::ZERO, Duration::ZERO),
+            converter()
+                .with_voice(Voice::Jam)
+                .with_symbol_verbalization(true),
+        ];
+        let mut results = Vec::new();
+        for converter in &converters {
+            let result = converter
+                .run_project(&project_path, &temp_path("other_settings.wav"))
+                .await;
+            results.push(result);
+        }
+        std::fs::remove_file(&project_path).unwrap();
 
-        let mut audio_segments = Vec::new();
         for result in results {
-            audio_segments.push(result?);
+            assert!(matches!(result, Err(Error::Config(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_length_unit_words() {
+        let sentence = "The quick brown fox jumps over the lazy dog. ";
+        let text = sentence.repeat(20);
+        assert!(text.chars().count() > 500);
+
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_length_unit(LengthUnit::Words);
+        let plan = converter.plan(&text).await.unwrap();
+        assert_eq!(plan.mode, ConversionMode::Direct);
+
+        // Blocks of 90 words fit a 100-word limit without any AI split
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_newline_pauses(Duration::ZERO, Duration::ZERO)
+            .with_length_unit(LengthUnit::Words)
+            .with_max_segment_length(100);
+        let block = sentence.repeat(10);
+        let text = format!("{}\n{}", block, block);
+        let output = temp_path("length_unit_words.wav");
+        let report = convert_verified(&converter, &text, &output).await;
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(report.segment_count, 2);
+        assert_eq!(backend.requests(), vec![block.trim(), block.trim()]);
+    }
+
+    #[tokio::test]
+    async fn test_cjk_latin_spacing_applied_to_all_segments() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_newline_pauses(Duration::ZERO, Duration::ZERO)
+            .with_cjk_latin_spacing(SpacingMode::Insert);
+        let output = temp_path("spacing.wav");
+
+        convert_verified(&converter, "使用GPU加速。\n使用 GPU  加速。", &output).await;
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(
+            backend.requests(),
+            vec!["使用 GPU 加速。", "使用 GPU 加速。"]
+        );
+    }
+
+    fn energy(samples: &[i16]) -> i64 {
+        samples.iter().map(|&s| s as i64 * s as i64).sum()
+    }
+
+    #[tokio::test]
+    async fn test_audio_inspector_matches_written_file() {
+        for text in ["你好。", "第一行。\n第二行。"] {
+            let inspected = Arc::new(Mutex::new(None));
+            let sink = inspected.clone();
+            let converter = Text2Audio::new("test_key")
+                .with_backend(Arc::new(MockBackend::new()))
+                .with_newline_pauses(Duration::from_millis(10), Duration::from_millis(20))
+                .with_audio_inspector(move |audio| {
+                    *sink.lock().unwrap() = Some((energy(audio.samples()), audio.frames().count()));
+                });
+            let output = temp_path("inspector.wav");
+
+            let report = convert_verified(&converter, text, &output).await;
+            let samples = read_samples(&std::fs::read(&output).unwrap());
+            std::fs::remove_file(&output).unwrap();
+
+            assert!(report.warnings.is_empty());
+            assert_eq!(
+                inspected.lock().unwrap().take(),
+                Some((energy(&samples), samples.len()))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_audio_inspector_panic_becomes_warning() {
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_audio_inspector(|_| panic!("analysis failed"));
+        let output = temp_path("inspector_panic.wav");
+
+        let report = convert_verified(&converter, "你好。", &output).await;
+        let samples = read_samples(&std::fs::read(&output).unwrap());
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(samples.len(), 3);
+        assert_eq!(
+            report.warnings,
+            vec![Warning::InspectorPanic {
+                message: "analysis failed".to_string()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_convert_to_bytes_matches_file() {
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_newline_pauses(Duration::from_millis(10), Duration::from_millis(20));
+        let output = temp_path("to_bytes.wav");
+
+        let bytes = converter
+            .convert_to_bytes("第一行。\n第二行。")
+            .await
+            .unwrap();
+        convert_verified(&converter, "第一行。\n第二行。", &output).await;
+        let file = std::fs::read(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(bytes, file);
+
+        // Empty input fails the same way in memory as on disk
+        let empty = converter.convert_to_bytes(" \n").await;
+        assert!(matches!(empty, Err(Error::EmptyInput)));
+        let empty = converter.convert(" \n", &output).await;
+        assert!(matches!(empty, Err(Error::EmptyInput)));
+        assert!(!Path::new(&output).exists());
+
+        // Direct and segmented output both carry exact RIFF and data sizes
+        let direct = converter.convert_to_bytes("你好。").await.unwrap();
+        for wav in [&bytes, &direct] {
+            let chunks = audio_merger::riff_chunks(wav).unwrap();
+            let (id, data) = chunks.last().unwrap();
+            assert_eq!(id, b"data");
+            assert_eq!(data.len(), read_samples(wav).len() * 2);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_inter_request_delay() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_inter_request_delay(Duration::from_secs(2));
+        let segments = ["第一段。", "第二段。", "第三段。"].map(String::from);
+
+        let started = tokio::time::Instant::now();
+        let audio = converter
+            .collect_audio_sequential(&segments, &[])
+            .await
+            .unwrap();
+
+        // Two delays between three requests, none before or after
+        assert_eq!(started.elapsed(), Duration::from_secs(4));
+        assert_eq!(audio.len(), 3);
+        assert_eq!(backend.requests().len(), 3);
+
+        // A pending delay ends with the conversion
+        let cancelled = tokio::time::timeout(
+            Duration::from_secs(1),
+            converter.collect_audio_sequential(&segments, &[]),
+        )
+        .await;
+        assert!(cancelled.is_err());
+        assert_eq!(backend.requests().len(), 4);
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert_eq!(backend.requests().len(), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_inter_request_delay_on_bounded_and_stream_paths() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_newline_pauses(Duration::ZERO, Duration::ZERO)
+            .with_inter_request_delay(Duration::from_secs(2));
+        let segments = ["第一段。", "第二段。", "第三段。"].map(String::from);
+        let output = temp_path("bounded_delay.wav");
+
+        let started = tokio::time::Instant::now();
+        let mut report = ConversionReport::new(ConversionMode::Segmented);
+        converter
+            .synthesize_and_merge_bounded(&segments, &[], &[], &output, &mut report, usize::MAX)
+            .await
+            .unwrap();
+        std::fs::remove_file(&output).unwrap();
+        assert_eq!(started.elapsed(), Duration::from_secs(4));
+        assert_eq!(backend.requests().len(), 3);
+
+        let started = tokio::time::Instant::now();
+        let stream = converter
+            .convert_stream("第一段。\n第二段。\n第三段。")
+            .await
+            .unwrap();
+        let audio: Vec<_> = stream.collect().await;
+        assert_eq!(audio.len(), 3);
+        assert_eq!(started.elapsed(), Duration::from_secs(4));
+    }
+
+    #[tokio::test]
+    async fn test_convert_to_audio() {
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_newline_pauses(Duration::from_millis(10), Duration::from_millis(20));
+        let text = "第一行。\n第二行。";
+        let output = temp_path("to_audio.wav");
+
+        let audio = converter.convert_to_audio(text).await.unwrap();
+        let report = convert_verified(&converter, text, &output).await;
+        let file = std::fs::read(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(audio.as_wav_bytes(), file.as_slice());
+        assert_eq!(
+            audio.to_wav_bytes(),
+            converter.convert_to_bytes(text).await.unwrap()
+        );
+        let spans: Vec<_> = report
+            .segments
+            .iter()
+            .map(|s| s.start..s.start + s.duration)
+            .collect();
+        assert_eq!(audio.segments(), spans.as_slice());
+        assert_eq!(audio.segments().len(), 2);
+        assert_eq!(audio.into_pcm().unwrap().samples(), read_samples(&file));
+    }
+
+    #[tokio::test]
+    async fn test_convert_with_audio() {
+        let text = "第一行。\n第二行。";
+        for max_memory in [None, Some(1 << 20)] {
+            let mut converter = Text2Audio::new("test_key")
+                .with_backend(Arc::new(MockBackend::new()))
+                .with_newline_pauses(Duration::from_millis(10), Duration::from_millis(20));
+            if let Some(bytes) = max_memory {
+                converter = converter.with_max_memory(bytes);
+            }
+            let output = temp_path("with_audio.wav");
+
+            let (report, audio) = converter.convert_with_audio(text, &output).await.unwrap();
+            let file = std::fs::read(&output).unwrap();
+            std::fs::remove_file(&output).unwrap();
+
+            assert_eq!(audio.as_wav_bytes(), file.as_slice());
+            let spans: Vec<_> = report
+                .segments
+                .iter()
+                .map(|s| s.start..s.start + s.duration)
+                .collect();
+            assert_eq!(audio.segments(), spans.as_slice());
+            assert_eq!(audio.segments().len(), 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_convert_with_audio_skipped_output() {
+        let output = temp_path("with_audio_skipped.wav");
+        let existing = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .convert_to_bytes("旧的。")
+            .await
+            .unwrap();
+        std::fs::write(&output, &existing).unwrap();
+
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_overwrite_policy(OverwritePolicy::Skip);
+        let (report, audio) = converter
+            .convert_with_audio("新的。", &output)
+            .await
+            .unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        assert!(report.outputs.is_empty());
+        assert_eq!(audio.as_wav_bytes(), existing.as_slice());
+        assert!(audio.segments().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_convert_to_base64() {
+        use base64::Engine;
+
+        let converter = Text2Audio::new("test_key").with_backend(Arc::new(MockBackend::new()));
+        let encoded = converter.convert_to_base64("你好。").await.unwrap();
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(&encoded.base64)
+            .unwrap();
+
+        assert_eq!(encoded.mime_type, "audio/wav");
+        assert!(encoded
+            .data_uri()
+            .starts_with("data:audio/wav;base64,UklGR"));
+        assert_eq!(read_samples(&decoded), vec![1000; 3]);
+    }
+
+    #[cfg(not(feature = "mp3"))]
+    #[tokio::test]
+    async fn test_output_format_mp3_requires_feature() {
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_output_format(OutputFormat::Mp3 { bitrate_kbps: 64 });
+        let result = converter.convert_to_base64("你好。").await;
+        assert!(matches!(result, Err(Error::Config(_))));
+    }
+
+    #[cfg(not(feature = "mp3"))]
+    #[tokio::test]
+    async fn test_mp3_path_fails_before_synthesis() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key").with_backend(backend.clone());
+        let output = temp_path("unencodable.mp3");
+
+        let result = converter.convert_with_report("你好。", &output).await;
+        assert!(matches!(result, Err(Error::Config(msg)) if msg.contains("mp3")));
+        let result = converter
+            .convert_formats(
+                "你好。",
+                vec![
+                    (temp_path("encodable.wav"), OutputFormat::Wav),
+                    (output.clone(), OutputFormat::Mp3 { bitrate_kbps: 64 }),
+                ],
+            )
+            .await;
+        assert!(matches!(result, Err(Error::Config(_))));
+
+        assert!(backend.requests().is_empty());
+        assert!(!Path::new(&output).exists());
+    }
+
+    #[cfg(feature = "mp3")]
+    #[tokio::test]
+    async fn test_convert_to_base64_mp3() {
+        use base64::Engine;
+
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_output_format(OutputFormat::Mp3 { bitrate_kbps: 64 });
+        let encoded = converter
+            .convert_to_base64(&"你好。".repeat(100))
+            .await
+            .unwrap();
+        let mp3 = base64::engine::general_purpose::STANDARD
+            .decode(&encoded.base64)
+            .unwrap();
+
+        assert_eq!(encoded.mime_type, "audio/mpeg");
+        let mut decoder = minimp3::Decoder::new(std::io::Cursor::new(mp3));
+        assert!(decoder.next_frame().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_output_format_follows_extension() {
+        let converter = Text2Audio::new("test_key").with_backend(Arc::new(MockBackend::new()));
+        let flac = temp_path("inferred.flac");
+        let report = convert_verified(&converter, "你好。", &flac).await;
+        let audio = std::fs::read(&flac).unwrap();
+        std::fs::remove_file(&flac).unwrap();
+        assert_eq!(read_samples(&audio).len(), 3);
+        assert_eq!(
+            report.warnings,
+            vec![Warning::UnknownOutputExtension {
+                extension: "flac".to_string()
+            }]
+        );
+
+        let converter = converter.with_output_format(OutputFormat::Wav);
+        let mp3 = temp_path("configured.mp3");
+        let report = convert_verified(&converter, "你好。", &mp3).await;
+        let audio = std::fs::read(&mp3).unwrap();
+        std::fs::remove_file(&mp3).unwrap();
+        assert_eq!(read_samples(&audio).len(), 3);
+        assert_eq!(
+            report.warnings,
+            vec![Warning::OutputFormatMismatch {
+                extension: "mp3".to_string(),
+                format: "wav"
+            }]
+        );
+    }
+
+    #[cfg(feature = "mp3")]
+    #[tokio::test]
+    async fn test_mp3_inferred_from_extension() {
+        let converter = Text2Audio::new("test_key").with_backend(Arc::new(MockBackend::new()));
+        let path = temp_path("inferred.mp3");
+        let report = converter
+            .convert_with_report(&"你好。".repeat(100), &path)
+            .await
+            .unwrap();
+        let mp3 = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(report.warnings.is_empty());
+        let mut decoder = minimp3::Decoder::new(std::io::Cursor::new(mp3));
+        assert!(decoder.next_frame().is_ok());
+    }
+
+    #[cfg(feature = "mp3")]
+    #[tokio::test]
+    async fn test_convert_formats_wav_and_mp3() {
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_newline_pauses(Duration::from_millis(500), Duration::from_millis(800));
+        let wav_path = temp_path("formats.wav");
+        let mp3_path = temp_path("formats.mp3");
+
+        converter
+            .convert_formats(
+                "第一行。\n第二行。",
+                vec![
+                    (wav_path.clone(), OutputFormat::Wav),
+                    (mp3_path.clone(), OutputFormat::Mp3 { bitrate_kbps: 64 }),
+                ],
+            )
+            .await
+            .unwrap();
+        let wav = std::fs::read(&wav_path).unwrap();
+        let mp3 = std::fs::read(&mp3_path).unwrap();
+        std::fs::remove_file(&wav_path).unwrap();
+        std::fs::remove_file(&mp3_path).unwrap();
+
+        let mut decoder = minimp3::Decoder::new(std::io::Cursor::new(mp3));
+        let mut mp3_samples = 0;
+        loop {
+            match decoder.next_frame() {
+                Ok(frame) => {
+                    assert_eq!(frame.sample_rate as u32, test_util::MOCK_SAMPLE_RATE);
+                    mp3_samples += frame.data.len() / frame.channels;
+                }
+                Err(minimp3::Error::Eof) => break,
+                Err(e) => panic!("invalid MP3: {}", e),
+            }
+        }
+
+        let wav_duration = probe_bytes(&wav).unwrap().duration.as_secs_f64();
+        let mp3_duration = mp3_samples as f64 / test_util::MOCK_SAMPLE_RATE as f64;
+        assert!(wav_duration > 0.5);
+        // Encoder delay and frame padding add up to a few MP3 frames
+        assert!((mp3_duration - wav_duration).abs() < 0.15);
+    }
+
+    #[tokio::test]
+    async fn test_empty_output_rejected_and_deleted() {
+        // Two sentences that only fit the shortest segment length apart
+        let sentence = format!("{}。", "字".repeat(59));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(FixedLengthBackend::new(0)))
+            .with_split_backend(Arc::new(StaticSplitBackend::new(
+                &[&sentence, &sentence],
+                Duration::ZERO,
+            )))
+            .with_max_segment_length(100);
+        let output = temp_path("empty_output.wav");
+
+        let result = converter
+            .convert_with_report(&sentence.repeat(2), &output)
+            .await;
+        match result {
+            Err(Error::Audio {
+                kind: AudioErrorKind::Empty,
+                message,
+            }) => {
+                assert!(message.starts_with("produced no audio:"));
+                assert!(message.contains("2 of 2 segments returned no audio"));
+            }
+            other => panic!("expected an audio error, got {:?}", other),
+        }
+        assert!(!std::path::Path::new(&output).exists());
+    }
+
+    #[tokio::test]
+    async fn test_single_sample_output_passes_without_minimum() {
+        let converter =
+            Text2Audio::new("test_key").with_backend(Arc::new(FixedLengthBackend::new(1)));
+        let output = temp_path("single_sample.wav");
+
+        convert_verified(&converter, "你好。", &output).await;
+        let samples = read_samples(&std::fs::read(&output).unwrap());
+        std::fs::remove_file(&output).unwrap();
+        assert_eq!(samples.len(), 1);
+
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(FixedLengthBackend::new(1)))
+            .with_min_duration(Duration::from_millis(100));
+        let result = converter.convert("你好。", &output).await;
+        assert!(
+            matches!(result, Err(Error::Audio { kind: AudioErrorKind::TooShort, message: m }) if m.contains("below the minimum"))
+        );
+        assert!(!std::path::Path::new(&output).exists());
+    }
+
+    #[tokio::test]
+    async fn test_require_nonsilent() {
+        let silent = test_util::wav_bytes(&[0, 20, -20, 0], test_util::MOCK_SAMPLE_RATE, 1);
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(StaticBackend::new(silent)))
+            .with_require_nonsilent(true);
+        let output = temp_path("require_nonsilent.wav");
+
+        let result = converter.convert("你好。", &output).await;
+        let error = result.unwrap_err();
+        assert!(matches!(&error, Error::Audio { message: m, .. } if m == "output is silent"));
+        assert_eq!(error.code(), "T2A_AUDIO_SILENT");
+        assert!(!std::path::Path::new(&output).exists());
+
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_require_nonsilent(true);
+        convert_verified(&converter, "你好。", &output).await;
+        std::fs::remove_file(&output).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_target_loudness_normalizes_output() {
+        let rate = test_util::MOCK_SAMPLE_RATE;
+        let tone: Vec<i16> = (0..rate * 2)
+            .map(|i| {
+                let t = i as f64 / rate as f64;
+                (3000.0 * (2.0 * std::f64::consts::PI * 440.0 * t).sin()) as i16
+            })
+            .collect();
+        let output = temp_path("target_loudness.wav");
+
+        for target in [-23.0, -16.0] {
+            let converter = Text2Audio::new("test_key")
+                .with_backend(Arc::new(StaticBackend::new(test_util::wav_bytes(
+                    &tone, rate, 1,
+                ))))
+                .with_target_loudness(target);
+            convert_verified(&converter, "你好。", &output).await;
+            let samples = read_samples(&std::fs::read(&output).unwrap());
+            let measured = loudness::integrated_loudness(&samples, 1, rate).unwrap();
+            assert!((measured - target as f64).abs() < 0.5, "{}", measured);
+            assert!(loudness::true_peak(&samples, 1) <= loudness::TRUE_PEAK_CEILING + 0.1);
+        }
+
+        // Audio too quiet to measure is written unchanged with a warning,
+        // or rejected in strict mode
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(StaticBackend::new(test_util::wav_bytes(
+                &[0; 4800], rate, 1,
+            ))))
+            .with_target_loudness(-16.0);
+        let report = convert_verified(&converter, "你好。", &output).await;
+        assert_eq!(
+            read_samples(&std::fs::read(&output).unwrap()),
+            vec![0; 4800]
+        );
+        assert_eq!(
+            report.warnings,
+            vec![Warning::LoudnessNotNormalized { target: -16.0 }]
+        );
+        std::fs::remove_file(&output).unwrap();
+
+        let result = converter
+            .with_strict_validation(true)
+            .convert("你好。", &output)
+            .await;
+        assert!(matches!(result, Err(Error::Audio {
+            kind: AudioErrorKind::LoudnessNotNormalized,
+            message: m,
+        }) if m.contains("loudness")));
+        assert!(!Path::new(&output).exists());
+    }
+
+    #[tokio::test]
+    async fn test_strict_riff_accepts_output() {
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_strict_riff(true);
+        let output = temp_path("strict_riff.wav");
+
+        convert_verified(&converter, "你好。", &output).await;
+        let audio = std::fs::read(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+        assert!(riff_chunks(&audio).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_preserve_extra_chunks_direct_mode() {
+        let source = test_util::wav_with_extra_chunks();
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(StaticBackend::new(source)))
+            .with_preserve_extra_chunks(true);
+        let output = temp_path("preserve_chunks.wav");
+
+        convert_verified(&converter, "你好。", &output).await;
+        let audio = std::fs::read(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+        let ids: Vec<[u8; 4]> = riff_chunks(&audio).unwrap().iter().map(|c| c.0).collect();
+        assert_eq!(ids, [*b"fmt ", *b"data", *b"cust", *b"bext", *b"LIST"]);
+
+        // Segmented output is merged and drops the chunks even when enabled
+        let sentence = format!("{}。", "字".repeat(59));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(StaticBackend::new(
+                test_util::wav_with_extra_chunks(),
+            )))
+            .with_split_backend(Arc::new(StaticSplitBackend::new(
+                &[&sentence, &sentence],
+                Duration::ZERO,
+            )))
+            .with_max_segment_length(100)
+            .with_preserve_extra_chunks(true);
+        let report = convert_verified(&converter, &sentence.repeat(2), &output).await;
+        let audio = std::fs::read(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+        assert_eq!(report.segments.len(), 2);
+        assert_eq!(riff_chunks(&audio).unwrap().len(), 2);
+    }
+
+    fn synthesize_segments_with(
+        converter: &Text2Audio,
+        name: &str,
+    ) -> (ConversionReport, Vec<i16>) {
+        let segments: Vec<String> = (1..=4).map(|i| "字".repeat(i * 10)).collect();
+        let output = temp_path(name);
+        let mut report = ConversionReport::new(ConversionMode::Segmented);
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(converter.synthesize_and_merge(
+                segments.into(),
+                &[],
+                &[],
+                &mut Output::Path(&output),
+                &mut report,
+            ))
+            .unwrap();
+        let audio = std::fs::read(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+        (report, read_samples(&audio))
+    }
+
+    #[test]
+    fn test_max_memory_switches_to_incremental_merge() {
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_parallel(2)
+            .with_max_memory(100);
+
+        let (report, samples) = synthesize_segments_with(&converter, "max_memory.wav");
+
+        assert_eq!(samples.len(), 100);
+        assert_eq!(report.segment_count, 4);
+        assert_eq!(
+            report.warnings,
+            vec![Warning::IncrementalMerge { limit_bytes: 100 }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_max_memory_rejects_whole_output_processing() {
+        let output = temp_path("max_memory_post.wav");
+        let converters = [
+            Text2Audio::new("test_key").with_target_loudness(-16.0),
+            Text2Audio::new("test_key").with_require_nonsilent(true),
+            Text2Audio::new("test_key").with_audio_inspector(|_| {}),
+        ];
+
+        for converter in converters {
+            let backend = Arc::new(MockBackend::new());
+            let converter = converter
+                .with_backend(backend.clone())
+                .with_mode(ModeSelection::ForceSegmented)
+                .with_max_memory(1 << 20);
+            let result = converter.convert("第一句话。第二句话。", &output).await;
+            assert!(matches!(result, Err(Error::Config(m)) if m.contains("with_max_memory")));
+            assert!(backend.requests().is_empty());
+            assert!(!Path::new(&output).exists());
+        }
+    }
+
+    #[test]
+    fn test_max_memory_not_reached() {
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_max_memory(1 << 20);
+
+        let (report, samples) = synthesize_segments_with(&converter, "max_memory_unused.wav");
+
+        assert_eq!(samples.len(), 100);
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_parallel_fallback_retries_sequentially() {
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(SerialOnlyBackend::new()))
+            .with_parallel(4)
+            .with_retry_config(0, Duration::from_millis(1))
+            .with_parallel_fallback(true);
+
+        let (report, samples) = synthesize_segments_with(&converter, "parallel_fallback.wav");
+
+        assert_eq!(samples.len(), 100);
+        assert_eq!(report.segment_count, 4);
+        assert_eq!(
+            report.warnings,
+            vec![Warning::ParallelFallback { failed_segments: 3 }]
+        );
+        let durations: Vec<_> = report.segments.iter().map(|s| s.duration).collect();
+        assert!(durations.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_parallel_fallback_keeps_keys_and_attempts() {
+        let backend = Arc::new(SerialOnlyBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_parallel(4)
+            .with_retry_config(1, Duration::from_millis(1))
+            .with_parallel_fallback(true);
+
+        let (report, _) = synthesize_segments_with(&converter, "parallel_fallback_keys.wav");
+
+        let requests = backend.requests();
+        let mut keys = Vec::new();
+        for segment in &report.segments {
+            let text = segment.text.as_deref().unwrap();
+            let sent: Vec<_> = requests.iter().filter(|(t, _)| t == text).collect();
+            assert_eq!(
+                segment.source,
+                SegmentSource::Synthesized {
+                    attempts: sent.len() as u32
+                }
+            );
+            assert!(sent
+                .iter()
+                .all(|(_, key)| key.is_some() && *key == sent[0].1));
+            keys.push(sent[0].1.clone());
+        }
+        assert!(requests.len() > report.segments.len());
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), 4);
+    }
+
+    #[test]
+    fn test_parallel_failure_without_fallback() {
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(SerialOnlyBackend::new()))
+            .with_parallel(4)
+            .with_retry_config(0, Duration::from_millis(1));
+        let segments: Vec<String> = (1..=4).map(|i| "字".repeat(i * 10)).collect();
+        let output = temp_path("parallel_no_fallback.wav");
+        let mut report = ConversionReport::new(ConversionMode::Segmented);
+
+        let result =
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(converter.synthesize_and_merge(
+                    segments.into(),
+                    &[],
+                    &[],
+                    &mut Output::Path(&output),
+                    &mut report,
+                ));
+
+        assert!(matches!(result, Err(Error::Http(_))));
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_retries_disabled_returns_first_error() {
+        let backend = Arc::new(FlakyBackend::new(2));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_retry_config(0, Duration::from_secs(60));
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let result = runtime.block_on(converter.synthesize_segment(0, "你好。"));
+        assert!(matches!(result, Err(Error::Http(m)) if m == "request timed out"));
+        assert_eq!(backend.keys().len(), 1);
+
+        let converter = converter.with_parallel(2);
+        let results = runtime
+            .block_on(converter.collect_audio_parallel(vec!["第一段。".to_string()].into()))
+            .unwrap();
+        assert!(matches!(&results[0], Err(Error::Http(m)) if m == "request timed out"));
+        assert_eq!(backend.keys().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_attempt_counts() {
+        for (max_retries, attempts) in [(0, 1), (1, 2), (3, 4)] {
+            for parallel in [1, 2] {
+                let backend = Arc::new(FlakyBackend::new(usize::MAX));
+                let converter = Text2Audio::new("test_key")
+                    .with_backend(backend.clone())
+                    .with_parallel(parallel)
+                    .with_retry_config(max_retries, Duration::from_millis(1));
+
+                let result = if parallel > 1 {
+                    let mut results = converter
+                        .collect_audio_parallel(vec!["你好。".to_string()].into())
+                        .await
+                        .unwrap();
+                    results.remove(0)
+                } else {
+                    converter.synthesize_segment(0, "你好。").await
+                };
+                assert!(result.is_err());
+                assert_eq!(backend.keys().len(), attempts);
+            }
+        }
+
+        // A segment that succeeds on its last retry
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(FlakyBackend::new(1)))
+            .with_retry_config(1, Duration::from_millis(1));
+        let (_, attempts) = converter.synthesize_segment(0, "你好。").await.unwrap();
+        assert_eq!(attempts, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_backend_classifies_its_errors() {
+        let policy = RetryPolicy::new(3, Duration::from_secs(1));
+        let cases = [
+            (RetryClass::Retryable, 3, Duration::from_secs(3)),
+            (
+                RetryClass::RateLimited { retry_after: None },
+                3,
+                Duration::from_secs(3),
+            ),
+            (
+                RetryClass::RateLimited {
+                    retry_after: Some(Duration::from_secs(10)),
+                },
+                3,
+                Duration::from_secs(20),
+            ),
+            (RetryClass::Fatal, 1, Duration::ZERO),
+        ];
+        for (class, requests, waited) in cases {
+            let backend = Arc::new(ClassifyingBackend::new(class, 2));
+            let converter = Text2Audio::new("test_key")
+                .with_backend(backend.clone())
+                .with_retry_policy(policy.clone());
+            let started = tokio::time::Instant::now();
+            let result = converter.synthesize_segment(0, "你好。").await;
+            assert_eq!(result.is_ok(), class != RetryClass::Fatal, "{:?}", class);
+            assert_eq!(backend.requests(), requests, "{:?}", class);
+            assert_eq!(started.elapsed(), waited, "{:?}", class);
+        }
+
+        // A fatal error is returned even if the policy's classifier would
+        // retry it
+        let backend = Arc::new(ClassifyingBackend::new(RetryClass::Fatal, 2));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_retry_policy(policy.with_classifier(|_| true));
+        assert!(converter.synthesize_segment(0, "你好。").await.is_err());
+        assert_eq!(backend.requests(), 1);
+        assert_eq!(
+            Error::RateLimited("quota".to_string()).retry_class(),
+            RetryClass::RateLimited { retry_after: None }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retry_policy_covers_tts_and_split_requests() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let policy =
+            RetryPolicy::new(3, Duration::from_millis(1)).with_max_delay(Duration::from_millis(2));
+
+        let backend = Arc::new(FlakyBackend::new(2));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_retry_policy(policy.clone());
+        let (_, attempts) = converter.synthesize_segment(0, "你好。").await.unwrap();
+        assert_eq!((attempts, backend.keys().len()), (3, 3));
+        assert_eq!(converter.stats().tts.retries, 2);
+
+        // Transport errors are not retried under this classifier
+        let backend = Arc::new(FlakyBackend::new(2));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_retry_policy(policy.clone().with_classifier(|e| !e.is_transport()));
+        assert!(converter.synthesize_segment(0, "你好。").await.is_err());
+        assert_eq!(backend.keys().len(), 1);
+
+        // One retry in total, shared by every segment
+        let remaining = Arc::new(AtomicUsize::new(1));
+        let budget = policy.clone().with_budget(move |_, _| {
+            remaining
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok()
+        });
+        let backend = Arc::new(FlakyBackend::new(usize::MAX));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_retry_policy(budget);
+        assert!(converter.synthesize_segment(0, "你好。").await.is_err());
+        assert!(converter.synthesize_segment(1, "你好。").await.is_err());
+        assert_eq!(backend.keys().len(), 3);
+
+        let split_backend =
+            Arc::new(StaticSplitBackend::new(&["第一段。", "第二段。"], Duration::ZERO).failing(2));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(MockBackend::new()))
+            .with_split_backend(split_backend.clone())
+            .with_max_segment_length(5)
+            .with_retry_policy(policy);
+        let segments = converter
+            .request_split("第一段。第二段。", 5)
+            .await
+            .unwrap();
+        assert_eq!(segments, vec!["第一段。", "第二段。"]);
+        assert_eq!(split_backend.calls(), 3);
+        assert_eq!(converter.stats().chat.retries, 2);
+    }
+
+    #[tokio::test]
+    async fn test_oversize_policy() {
+        let long = format!("{}。{}。", "长".repeat(999), "短".repeat(99));
+        let segments: Arc<[String]> = vec![long.clone()].into();
+
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_parallel(2);
+        let result = converter.synthesize_segment(0, &long).await;
+        assert!(matches!(result, Err(Error::Config(m)) if m.contains("1100 chars")));
+        let results = converter
+            .collect_audio_parallel(segments.clone())
+            .await
+            .unwrap();
+        assert!(matches!(&results[0], Err(Error::Config(_))));
+        assert!(backend.requests().is_empty());
+
+        let converter = converter.with_oversize_policy(OversizePolicy::AutoSplit);
+        let (audio, attempts) = converter.synthesize_segment(0, &long).await.unwrap();
+        assert_eq!(read_samples(&audio).len(), 1100);
+        assert_eq!(attempts, 2);
+        let mut results = converter.collect_audio_parallel(segments).await.unwrap();
+        assert_eq!(results.remove(0).unwrap().0, audio);
+
+        let requests = backend.requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[..2].concat(), long);
+        assert!(requests.iter().all(|r| r.chars().count() <= TTS_MAX_CHARS));
+        let keys: Vec<_> = backend
+            .configs()
+            .into_iter()
+            .map(|config| config.idempotency_key)
+            .collect();
+        assert_ne!(keys[0], keys[1]);
+    }
+
+    #[tokio::test]
+    async fn test_tts_settings_reach_requests_in_both_modes() {
+        let segments: Arc<[String]> = vec!["第一段。".to_string(), "第二段。".to_string()].into();
+
+        for parallel in [1, 2] {
+            for idempotency_keys in [false, true] {
+                let backend = Arc::new(MockBackend::new());
+                let converter = Text2Audio::new("test_key")
+                    .with_backend(backend.clone())
+                    .with_voice(Voice::Jam)
+                    .with_speed(1.5)
+                    .with_volume(3.0)
+                    .with_idempotency_keys(idempotency_keys)
+                    .with_parallel(parallel);
+
+                if parallel > 1 {
+                    let results = converter
+                        .collect_audio_parallel(segments.clone())
+                        .await
+                        .unwrap();
+                    assert!(results.iter().all(Result::is_ok));
+                } else {
+                    converter
+                        .collect_audio_sequential(&segments, &[])
+                        .await
+                        .unwrap();
+                }
+
+                let configs = backend.configs();
+                assert_eq!(configs.len(), 2);
+                for config in &configs {
+                    assert_eq!(
+                        format!("{:?}", config.voice),
+                        format!("{:?}", Voice::Jam.as_tts_voice())
+                    );
+                    assert_eq!(config.speed, 1.5);
+                    assert_eq!(config.volume, 3.0);
+                    assert_eq!(config.idempotency_key.is_some(), idempotency_keys);
+                }
+            }
         }
+    }
 
-        Ok(audio_segments)
+    #[tokio::test]
+    async fn test_outputs_overlapping_inputs_rejected_before_writing() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_sidecar_metadata(true);
+        let output = temp_path("overlap.wav");
+
+        // The metadata sidecar of the output is the plan itself
+        let plan_path = format!("{}.json", output);
+        let plan = converter.plan("你好。").await.unwrap();
+        std::fs::write(&plan_path, plan.to_json()).unwrap();
+        let result = converter
+            .convert_segments_from_json(&plan_path, &output)
+            .await;
+        assert!(matches!(result, Err(Error::Config(m)) if m.contains("overlaps input")));
+        assert!(ConversionPlan::from_json(&std::fs::read_to_string(&plan_path).unwrap()).is_ok());
+        std::fs::remove_file(&plan_path).unwrap();
+
+        // The output lands in the project's segment audio directory
+        let project_path = temp_path("overlap.project.json");
+        converter.new_project("你好。").save(&project_path).unwrap();
+        let audio_output = Project::audio_dir(&project_path).join("0.wav");
+        let result = converter
+            .run_project(&project_path, audio_output.to_str().unwrap())
+            .await;
+        assert!(matches!(result, Err(Error::Config(m)) if m.contains("overlaps input")));
+        assert!(!Project::lock_path(&project_path).exists());
+        std::fs::remove_file(&project_path).unwrap();
+
+        // Two jobs writing one file
+        let result = converter
+            .convert_many(&[("第一章", output.as_str()), ("第二章", output.as_str())])
+            .await;
+        assert!(matches!(result, Err(Error::Config(m)) if m.contains("overlaps output")));
+
+        assert!(backend.requests().is_empty());
+        assert!(!std::path::Path::new(&output).exists());
     }
-}
 
-impl Default for Text2Audio {
-    fn default() -> Self {
-        Self::new("")
+    #[tokio::test]
+    async fn test_overwrite_policy_with_existing_output() {
+        let output = temp_path("existing.wav");
+        let renamed = output.replace("existing.wav", "existing (1).wav");
+        let converter = |policy| {
+            Text2Audio::new("test_key")
+                .with_backend(Arc::new(MockBackend::new()))
+                .with_overwrite_policy(policy)
+        };
+
+        std::fs::write(&output, b"old").unwrap();
+        let result = converter(OverwritePolicy::Error)
+            .convert("你好。", &output)
+            .await;
+        assert!(
+            matches!(result, Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::AlreadyExists)
+        );
+        assert_eq!(std::fs::read(&output).unwrap(), b"old");
+
+        let report = converter(OverwritePolicy::Skip)
+            .convert_with_report("你好。", &output)
+            .await
+            .unwrap();
+        assert_eq!(report.segment_count, 0);
+        assert!(matches!(
+            &report.warnings[..],
+            [Warning::OutputSkipped { .. }]
+        ));
+        assert_eq!(std::fs::read(&output).unwrap(), b"old");
+
+        let report = converter(OverwritePolicy::Rename)
+            .convert_with_report("你好。", &output)
+            .await
+            .unwrap();
+        assert!(report.warnings.contains(&Warning::OutputRenamed {
+            path: output.clone(),
+            renamed: renamed.clone()
+        }));
+        assert_eq!(std::fs::read(&output).unwrap(), b"old");
+        assert_eq!(read_samples(&std::fs::read(&renamed).unwrap()).len(), 3);
+        std::fs::remove_file(&renamed).unwrap();
+
+        converter(OverwritePolicy::Overwrite)
+            .convert("你好。", &output)
+            .await
+            .unwrap();
+        assert_eq!(read_samples(&std::fs::read(&output).unwrap()).len(), 3);
+        std::fs::remove_file(&output).unwrap();
     }
-}
 
-/// Builder for Text2Audio configuration
-///
-/// Provides a fluent interface for configuring text-to-audio conversion.
-pub struct Builder {
-    converter: Text2Audio,
-}
+    #[tokio::test]
+    async fn test_overwrite_policy_with_existing_format_output() {
+        let existing = temp_path("existing_format.wav");
+        let fresh = temp_path("fresh_format.wav");
+        let outputs = || {
+            vec![
+                (existing.clone(), OutputFormat::Wav),
+                (fresh.clone(), OutputFormat::Wav),
+            ]
+        };
+        let converter = |policy| {
+            Text2Audio::new("test_key")
+                .with_backend(Arc::new(MockBackend::new()))
+                .with_overwrite_policy(policy)
+        };
 
-impl Builder {
-    fn new(api_key: impl Into<String>) -> Self {
-        Self {
-            converter: Text2Audio::new(api_key),
+        std::fs::write(&existing, b"old").unwrap();
+        let result = converter(OverwritePolicy::Error)
+            .convert_formats("你好。", outputs())
+            .await;
+        assert!(
+            matches!(result, Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::AlreadyExists)
+        );
+        assert!(!Path::new(&fresh).exists());
+
+        let report = converter(OverwritePolicy::Skip)
+            .convert_formats("你好。", outputs())
+            .await
+            .unwrap();
+        let written: Vec<_> = report.outputs.iter().map(|o| o.path.clone()).collect();
+        assert_eq!(written, vec![fresh.clone()]);
+        assert!(report.warnings.contains(&Warning::OutputSkipped {
+            path: existing.clone()
+        }));
+        assert_eq!(std::fs::read(&existing).unwrap(), b"old");
+
+        let report = converter(OverwritePolicy::Rename)
+            .convert_formats("你好。", outputs())
+            .await
+            .unwrap();
+        let written: Vec<_> = report.outputs.iter().map(|o| o.path.clone()).collect();
+        assert_eq!(
+            written,
+            vec![
+                existing.replace("existing_format.wav", "existing_format (1).wav"),
+                fresh.replace("fresh_format.wav", "fresh_format (1).wav"),
+            ]
+        );
+        assert_eq!(std::fs::read(&existing).unwrap(), b"old");
+        for path in written.iter().chain([&existing, &fresh]) {
+            std::fs::remove_file(path).unwrap();
         }
-    }
 
-    /// Set the AI model for text splitting
-    pub fn model(mut self, model: Model) -> Self {
-        self.converter = self.converter.with_model(model);
-        self
+        // Nothing left to write
+        std::fs::write(&existing, b"old").unwrap();
+        let backend = Arc::new(MockBackend::new());
+        let report = converter(OverwritePolicy::Skip)
+            .with_backend(backend.clone())
+            .convert_formats("你好。", vec![(existing.clone(), OutputFormat::Wav)])
+            .await
+            .unwrap();
+        assert!(report.outputs.is_empty());
+        assert!(backend.requests().is_empty());
+        std::fs::remove_file(&existing).unwrap();
     }
 
-    /// Set the voice type for TTS
-    pub fn voice(mut self, voice: Voice) -> Self {
-        self.converter = self.converter.with_voice(voice);
-        self
+    #[tokio::test]
+    async fn test_overwrite_policy_with_existing_segment_files() {
+        let dir = PathBuf::from(temp_path("existing_segment_files"));
+        let first = dir.join("001.wav");
+        let second = dir.join("002.wav");
+        let text = "第一段。\n第二段。";
+        let converter = |policy| {
+            let backend = Arc::new(MockBackend::new());
+            let converter = Text2Audio::new("test_key")
+                .with_backend(backend.clone())
+                .with_newline_pauses(Duration::from_millis(10), Duration::from_millis(20))
+                .with_overwrite_policy(policy);
+            (converter, backend)
+        };
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&first, b"old").unwrap();
+        let (errors, backend) = converter(OverwritePolicy::Error);
+        let result = errors.convert_to_files(text, &dir).await;
+        assert!(
+            matches!(result, Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::AlreadyExists)
+        );
+        assert!(backend.requests().is_empty());
+        assert!(!second.exists());
+
+        let (skips, backend) = converter(OverwritePolicy::Skip);
+        let paths = skips.convert_to_files(text, &dir).await.unwrap();
+        assert_eq!(paths, vec![second.clone()]);
+        assert_eq!(backend.requests(), vec!["第二段。"]);
+        assert_eq!(std::fs::read(&first).unwrap(), b"old");
+        assert!(skips.convert_to_files(text, &dir).await.unwrap().is_empty());
+        assert_eq!(backend.requests().len(), 1);
+
+        let (renames, _) = converter(OverwritePolicy::Rename);
+        let paths = renames.convert_to_files(text, &dir).await.unwrap();
+        assert_eq!(
+            paths,
+            vec![dir.join("001 (1).wav"), dir.join("002 (1).wav")]
+        );
+        assert_eq!(std::fs::read(&first).unwrap(), b"old");
+        assert_eq!(read_samples(&std::fs::read(&paths[0]).unwrap()).len(), 4);
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
-    /// Set the speech speed
-    pub fn speed(mut self, speed: f32) -> Self {
-        self.converter = self.converter.with_speed(speed);
-        self
+    #[tokio::test]
+    async fn test_output_preflight_fails_before_synthesis() {
+        let dir = temp_path("preflight_dir");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key").with_backend(backend.clone());
+        let error = converter.convert("你好。", &dir).await.unwrap_err();
+        assert!(
+            matches!(&error, Error::Config(m) if m.ends_with("is a directory; pass a file path"))
+        );
+        let error = converter
+            .convert_formats("你好。", vec![(dir.clone(), OutputFormat::Wav)])
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::Config(_)));
+        assert!(backend.requests().is_empty());
+
+        // Without the preflight, the failure only surfaces after synthesis
+        let converter = converter.with_output_preflight(false);
+        assert!(converter.convert("你好。", &dir).await.is_err());
+        assert_eq!(backend.requests().len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
-    /// Set the speech volume
-    pub fn volume(mut self, volume: f32) -> Self {
-        self.converter = self.converter.with_volume(volume);
-        self
+    #[tokio::test]
+    async fn test_section_profiles_reach_requests() {
+        let text = "开场白。\n<!-- section: ad -->\n广告时间。\n<!-- section: music -->\n音乐。\n\
+                    <!-- section: default -->\n结束。";
+        let ad = SectionProfile::new()
+            .with_voice(Voice::Jam)
+            .with_speed(1.5)
+            .with_gap(Duration::from_millis(10));
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_parallel(2)
+            .with_section_profiles(HashMap::from([("ad".to_string(), ad)]));
+        let output = temp_path("sections.wav");
+
+        let report = convert_verified(&converter, text, &output).await;
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(
+            backend.requests(),
+            vec!["开场白。", "广告时间。", "音乐。", "结束。"]
+        );
+        let configs = backend.configs();
+        let voices: Vec<String> = configs
+            .iter()
+            .map(|config| format!("{:?}", config.voice))
+            .collect();
+        let expected: Vec<String> = [Voice::Tongtong, Voice::Jam, Voice::Jam, Voice::Tongtong]
+            .iter()
+            .map(|voice| format!("{:?}", voice.as_tts_voice()))
+            .collect();
+        assert_eq!(voices, expected);
+        let speeds: Vec<f32> = configs.iter().map(|config| config.speed).collect();
+        assert_eq!(speeds, vec![1.0, 1.5, 1.5, 1.0]);
+
+        let sections: Vec<Option<&str>> = report
+            .segments
+            .iter()
+            .map(|segment| segment.section.as_deref())
+            .collect();
+        assert_eq!(sections, vec![None, Some("ad"), Some("ad"), None]);
+        let pauses: Vec<Duration> = report.segments.iter().map(|s| s.pause_after).collect();
+        assert_eq!(
+            pauses,
+            vec![
+                Duration::ZERO,
+                Duration::from_millis(10),
+                Duration::from_millis(10),
+                Duration::ZERO
+            ]
+        );
+        assert!(report
+            .warnings
+            .iter()
+            .any(|w| matches!(w, Warning::UnknownSection { name } if name == "music")));
     }
 
-    /// Set the maximum segment length
-    pub fn max_segment_length(mut self, max_length: usize) -> Self {
-        self.converter = self.converter.with_max_segment_length(max_length);
-        self
+    #[tokio::test]
+    async fn test_section_profiles_under_memory_limit() {
+        let text = "开场白。\n<!-- section: ad -->\n广告时间。\n<!-- section: default -->\n结束。";
+        let ad = SectionProfile::new()
+            .with_voice(Voice::Jam)
+            .with_gap(Duration::from_millis(10));
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_max_memory(1)
+            .with_section_profiles(HashMap::from([("ad".to_string(), ad)]));
+        let output = temp_path("sections_bounded.wav");
+
+        let report = convert_verified(&converter, text, &output).await;
+        let samples = read_samples(&std::fs::read(&output).unwrap());
+        std::fs::remove_file(&output).unwrap();
+
+        assert!(report
+            .warnings
+            .contains(&Warning::IncrementalMerge { limit_bytes: 1 }));
+        let voices: Vec<String> = backend
+            .configs()
+            .iter()
+            .map(|config| format!("{:?}", config.voice))
+            .collect();
+        let expected: Vec<String> = [Voice::Tongtong, Voice::Jam, Voice::Tongtong]
+            .iter()
+            .map(|voice| format!("{:?}", voice.as_tts_voice()))
+            .collect();
+        assert_eq!(voices, expected);
+        let sections: Vec<Option<&str>> = report
+            .segments
+            .iter()
+            .map(|segment| segment.section.as_deref())
+            .collect();
+        assert_eq!(sections, vec![None, Some("ad"), None]);
+        // 4 + 5 + 3 characters of audio and the ad's 10 ms gap
+        assert_eq!(samples.len(), 4 + 5 + 240 + 3);
     }
 
-    /// Enable parallel processing
-    pub fn parallel(mut self, max_parallel: usize) -> Self {
-        self.converter = self.converter.with_parallel(max_parallel);
-        self
+    #[tokio::test]
+    async fn test_section_profiles_fall_back_from_parallel() {
+        let text = "开场白。\n<!-- section: ad -->\n广告时间。\n<!-- section: default -->\n结束。";
+        let backend = Arc::new(SerialOnlyBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_parallel(3)
+            .with_retry_config(0, Duration::ZERO)
+            .with_parallel_fallback(true)
+            .with_section_profiles(HashMap::from([(
+                "ad".to_string(),
+                SectionProfile::new().with_voice(Voice::Jam),
+            )]));
+        let output = temp_path("sections_fallback.wav");
+
+        let report = convert_verified(&converter, text, &output).await;
+        std::fs::remove_file(&output).unwrap();
+
+        assert!(report
+            .warnings
+            .contains(&Warning::ParallelFallback { failed_segments: 2 }));
+        let texts: Vec<&str> = report
+            .segments
+            .iter()
+            .map(|segment| segment.text.as_deref().unwrap())
+            .collect();
+        assert_eq!(texts, vec!["开场白。", "广告时间。", "结束。"]);
+        let sections: Vec<Option<&str>> = report
+            .segments
+            .iter()
+            .map(|segment| segment.section.as_deref())
+            .collect();
+        assert_eq!(sections, vec![None, Some("ad"), None]);
     }
 
-    /// Enable thinking mode for AI splitting
-    pub fn thinking(mut self, enable: bool) -> Self {
-        self.converter = self.converter.with_thinking(enable);
-        self
+    #[tokio::test]
+    async fn test_section_markers_never_reach_backend() {
+        let text = "开场白。\n<!-- section: ad -->\n广告时间。";
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_section_profiles(HashMap::from([(
+                "ad".to_string(),
+                SectionProfile::new()
+                    .with_speed(f32::NAN)
+                    .with_volume(f32::INFINITY),
+            )]));
+
+        let plan = converter.plan(text).await.unwrap();
+        assert_eq!(plan.segments, vec!["开场白。\n广告时间。"]);
+
+        let output = temp_path("section_profile_non_finite.wav");
+        let report = convert_verified(&converter, text, &output).await;
+        std::fs::remove_file(&output).unwrap();
+        for config in backend.configs() {
+            assert_eq!(
+                (config.speed, config.volume),
+                (DEFAULT_SPEED, DEFAULT_VOLUME)
+            );
+        }
+        let names: Vec<&str> = report
+            .warnings
+            .iter()
+            .filter_map(|w| match w {
+                Warning::NonFiniteParameter { name, .. } => Some(*name),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, vec!["section speed", "section volume"]);
     }
 
-    /// Enable coding plan endpoint
-    pub fn coding_plan(mut self, enable: bool) -> Self {
-        self.converter = self.converter.with_coding_plan(enable);
-        self
+    #[tokio::test(start_paused = true)]
+    async fn test_stall_warning_lists_in_flight_segment() {
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(SlowBackend::new(Duration::from_secs(90))))
+            .with_stall_timeout(Duration::from_secs(60));
+        let output = temp_path("stall_warning.wav");
+
+        let report = convert_verified(&converter, "你好。", &output).await;
+        std::fs::remove_file(&output).unwrap();
+
+        let stalls: Vec<_> = report
+            .warnings
+            .iter()
+            .filter_map(|warning| match warning {
+                Warning::Stalled { idle, in_flight } => Some((*idle, in_flight.clone())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(stalls.len(), 1);
+        assert_eq!(stalls[0].0, Duration::from_secs(60));
+        assert_eq!(
+            stalls[0].1,
+            vec![StalledSegment {
+                index: 0,
+                elapsed: Duration::from_secs(60),
+                attempts: 1,
+            }]
+        );
     }
 
-    /// Set retry configuration
-    pub fn retry_config(mut self, max_retries: u32, delay: Duration) -> Self {
-        self.converter = self.converter.with_retry_config(max_retries, delay);
-        self
+    #[tokio::test(start_paused = true)]
+    async fn test_abort_on_stall() {
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(PendingBackend))
+            .with_stall_timeout(Duration::from_secs(60))
+            .with_abort_on_stall(true);
+        let output = temp_path("stall_abort.wav");
+        let started = tokio::time::Instant::now();
+
+        let result = converter.convert_with_report("你好。", &output).await;
+        let Err(Error::Stalled { idle, in_flight }) = result else {
+            panic!("expected a stall, got {:?}", result.map(|_| ()));
+        };
+        assert_eq!(idle, Duration::from_secs(120));
+        assert_eq!(started.elapsed(), Duration::from_secs(120));
+        assert_eq!(in_flight.len(), 1);
+        assert_eq!(in_flight[0].index, 0);
+        assert!(!std::path::Path::new(&output).exists());
     }
 
-    /// Build the Text2Audio converter
-    pub fn build(self) -> Text2Audio {
-        self.converter
+    #[test]
+    fn test_idempotency_key_stable_across_retries() {
+        let backend = Arc::new(FlakyBackend::new(1));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_retry_config(2, Duration::from_millis(1));
+        let segments = vec!["第一段。".to_string(), "第二段。".to_string()];
+
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(converter.collect_audio_sequential(&segments, &[]))
+            .unwrap();
+
+        let keys = backend.keys();
+        assert_eq!(keys.len(), 3);
+        assert!(keys.iter().all(Option::is_some));
+        assert_eq!(keys[0], keys[1]);
+        assert_ne!(keys[1], keys[2]);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[tokio::test]
+    async fn test_idempotency_key_stable_across_project_resume() {
+        let backend = Arc::new(FlakyBackend::new(1));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_newline_pauses(Duration::from_millis(10), Duration::from_millis(20))
+            .with_retry_config(0, Duration::ZERO);
+        let project_path = temp_path("resume_keys.t2a");
+        let output = temp_path("resume_keys.wav");
+        converter
+            .new_project("第一行。\n第二行。")
+            .save(&project_path)
+            .unwrap();
 
-    #[test]
-    fn test_new() {
-        let converter = Text2Audio::new("test_key");
-        assert_eq!(converter.model, Model::default());
-        assert_eq!(converter.voice, Voice::default());
-        assert_eq!(converter.speed, 1.0);
-        assert_eq!(converter.volume, 1.0);
-        assert_eq!(converter.max_segment_length, 500);
+        assert!(converter.run_project(&project_path, &output).await.is_err());
+        converter.run_project(&project_path, &output).await.unwrap();
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(&project_path).unwrap();
+        std::fs::remove_dir_all(Project::audio_dir(&project_path)).unwrap();
+
+        let keys = backend.keys();
+        assert_eq!(keys.len(), 3);
+        assert!(keys[0].is_some());
+        assert_eq!(keys[0], keys[1]);
+        assert_ne!(keys[1], keys[2]);
     }
 
     #[test]
-    fn test_with_model() {
-        let converter = Text2Audio::new("test_key").with_model(Model::GLM4_7);
-        assert_eq!(converter.model, Model::GLM4_7);
+    fn test_idempotency_keys_disabled() {
+        let backend = Arc::new(FlakyBackend::new(0));
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_parallel(2)
+            .with_idempotency_keys(false);
+        let segments = vec!["第一段。".to_string(), "第二段。".to_string()];
+
+        let results = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(converter.collect_audio_parallel(segments.into()))
+            .unwrap();
+
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(backend.keys(), vec![None, None]);
     }
 
-    #[test]
-    fn test_with_voice() {
-        let converter = Text2Audio::new("test_key").with_voice(Voice::Xiaochen);
-        assert_eq!(converter.voice, Voice::Xiaochen);
+    /// Segment text that counts how often it is cloned
+    struct CountedText {
+        text: String,
+        clones: Arc<std::sync::atomic::AtomicUsize>,
     }
 
-    #[test]
-    fn test_with_speed() {
-        let converter = Text2Audio::new("test_key").with_speed(1.2);
-        assert_eq!(converter.speed, 1.2);
+    impl Clone for CountedText {
+        fn clone(&self) -> Self {
+            self.clones
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Self {
+                text: self.text.clone(),
+                clones: self.clones.clone(),
+            }
+        }
+    }
+
+    impl AsRef<str> for CountedText {
+        fn as_ref(&self) -> &str {
+            &self.text
+        }
     }
 
     #[test]
-    fn test_speed_clamp() {
-        let converter = Text2Audio::new("test_key").with_speed(3.0);
-        assert_eq!(converter.speed, 2.0);
+    fn test_parallel_shares_segments_without_cloning() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_parallel(3)
+            .with_idempotency_keys(false);
+        let clones = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let segments: Arc<[CountedText]> = (1..=6)
+            .map(|i| CountedText {
+                text: "字".repeat(i),
+                clones: clones.clone(),
+            })
+            .collect();
 
-        let converter = Text2Audio::new("test_key").with_speed(0.2);
-        assert_eq!(converter.speed, 0.5);
+        let results = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(converter.collect_audio_parallel(segments))
+            .unwrap();
+
+        assert_eq!(clones.load(std::sync::atomic::Ordering::SeqCst), 0);
+        let lengths: Vec<usize> = results
+            .iter()
+            .map(|result| read_samples(&result.as_ref().unwrap().0).len())
+            .collect();
+        assert_eq!(lengths, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(backend.requests().len(), 6);
     }
 
     #[test]
-    fn test_with_volume() {
-        let converter = Text2Audio::new("test_key").with_volume(2.5);
-        assert_eq!(converter.volume, 2.5);
+    fn test_nan_speed_rejected_before_request() {
+        let backend = Arc::new(MockBackend::new());
+        let mut converter = Text2Audio::new("test_key").with_backend(backend.clone());
+        // Bypass the setter, which already substitutes the default
+        converter.speed = f32::NAN;
+
+        let result = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(converter.synthesize_segment(0, "你好。"));
+
+        assert!(matches!(result, Err(Error::Config(_))));
+        assert!(backend.requests().is_empty());
     }
 
     #[test]
-    fn test_volume_clamp() {
-        let converter = Text2Audio::new("test_key").with_volume(15.0);
-        assert_eq!(converter.volume, 10.0);
+    fn test_non_finite_parameters_never_reach_backend() {
+        let subnormal = f32::MIN_POSITIVE / 2.0;
+        let values = [
+            f32::NAN,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            subnormal,
+            -subnormal,
+        ];
+        let runtime = tokio::runtime::Runtime::new().unwrap();
 
-        let converter = Text2Audio::new("test_key").with_volume(-1.0);
-        assert_eq!(converter.volume, 0.0);
+        for speed in values {
+            for volume in values {
+                let backend = Arc::new(MockBackend::new());
+                let converter = Text2Audio::builder("test_key")
+                    .speed(speed)
+                    .volume(volume)
+                    .backend(backend.clone())
+                    .build();
+                let output = temp_path("non_finite.wav");
+
+                let report = runtime
+                    .block_on(converter.convert_with_report("你好。", &output))
+                    .unwrap();
+                std::fs::remove_file(&output).unwrap();
+
+                let expected = [speed, volume].iter().filter(|v| !v.is_finite()).count();
+                let non_finite = report
+                    .warnings
+                    .iter()
+                    .filter(|w| matches!(w, Warning::NonFiniteParameter { .. }))
+                    .count();
+                assert_eq!(non_finite, expected);
+                for config in backend.configs() {
+                    assert!(config.speed.is_finite() && SPEED_RANGE.contains(&config.speed));
+                    assert!(config.volume.is_finite() && VOLUME_RANGE.contains(&config.volume));
+                }
+            }
+        }
     }
 
     #[test]
-    fn test_with_max_segment_length() {
-        let converter = Text2Audio::new("test_key").with_max_segment_length(800);
-        assert_eq!(converter.max_segment_length, 800);
+    fn test_non_finite_parameter_defaults() {
+        let converter = Text2Audio::new("test_key")
+            .with_speed(f32::NAN)
+            .with_volume(f32::INFINITY);
+        assert_eq!(converter.speed, DEFAULT_SPEED);
+        assert_eq!(converter.volume, DEFAULT_VOLUME);
     }
 
     #[test]
-    fn test_max_segment_length_clamp() {
-        let converter = Text2Audio::new("test_key").with_max_segment_length(50);
-        assert_eq!(converter.max_segment_length, 100);
+    fn test_strict_validation_rejects_non_finite() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_strict_validation(true)
+            .with_volume(f32::NEG_INFINITY);
+        let output = temp_path("strict.wav");
 
-        let converter = Text2Audio::new("test_key").with_max_segment_length(2000);
-        assert_eq!(converter.max_segment_length, 1024);
+        let result = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(converter.convert("你好。", &output));
+
+        assert!(matches!(result, Err(Error::Config(msg)) if msg.contains("volume")));
+        assert!(backend.requests().is_empty());
     }
 
     #[test]
-    fn test_with_parallel() {
-        let converter = Text2Audio::new("test_key").with_parallel(5);
-        assert!(converter.enable_parallel);
-        assert_eq!(converter.max_parallel, 5);
+    fn test_strict_validation_accepts_corrected_parameter() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_strict_validation(true)
+            .with_speed(f32::NAN)
+            .with_speed(1.2);
+        let output = temp_path("strict_corrected.wav");
+
+        let report = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(converter.convert_with_report("你好。", &output))
+            .unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(converter.speed, 1.2);
+        assert!(!report
+            .warnings
+            .iter()
+            .any(|w| matches!(w, Warning::NonFiniteParameter { .. })));
+        assert_eq!(backend.requests().len(), 1);
     }
 
     #[test]
-    fn test_parallel_clamp() {
-        let converter = Text2Audio::new("test_key").with_parallel(20);
-        assert_eq!(converter.max_parallel, 10);
+    fn test_validate_voices_lists_unavailable_and_caches() {
+        let backend = Arc::new(VoiceRejectingBackend::new(&["Jam", "Kazi"]));
+        let converter = Text2Audio::new("test_key").with_backend(backend.clone());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
 
-        let converter = Text2Audio::new("test_key").with_parallel(0);
-        assert_eq!(converter.max_parallel, 1);
+        let voices = [Voice::Tongtong, Voice::Jam, Voice::Tongtong, Voice::Kazi];
+        let err = runtime
+            .block_on(converter.validate_voices(&voices))
+            .unwrap_err();
+        assert!(matches!(&err, Error::Config(msg) if msg.contains("Jam, Kazi")));
+        assert_eq!(backend.probes(), 3);
+
+        runtime
+            .block_on(converter.validate_voices(&[Voice::Tongtong, Voice::Xiaochen]))
+            .unwrap();
+        assert_eq!(backend.probes(), 4);
     }
 
     #[test]
-    fn test_with_thinking() {
-        let converter = Text2Audio::new("test_key").with_thinking(true);
-        assert!(converter.enable_thinking);
+    fn test_newline_pauses_inserted_between_segments() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_newline_pauses(Duration::from_millis(10), Duration::from_millis(50));
+        let output = temp_path("newline_pauses.wav");
+
+        let report = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(converter.convert_with_report("第一行。\n第二行。\n\n第三段。", &output))
+            .unwrap();
+        let samples = read_samples(&std::fs::read(&output).unwrap());
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(backend.requests(), vec!["第一行。", "第二行。", "第三段。"]);
+        // 4 samples per segment, 240 for the short pause and 1200 for the long one
+        assert_eq!(samples.len(), 4 + 240 + 4 + 1200 + 4);
+        assert!(samples[4..244].iter().all(|&s| s == 0));
+        assert!(samples[248..1448].iter().all(|&s| s == 0));
+        assert_eq!(samples[1448], 1000);
+        assert_eq!(report.mode, ConversionMode::Segmented);
+        assert_eq!(report.segments[1].pause_after, Duration::from_millis(50));
+        assert_eq!(
+            report.segments[2].start,
+            report.segments[1].start + report.segments[1].duration + Duration::from_millis(50)
+        );
     }
 
-    #[test]
-    fn test_with_coding_plan() {
-        let converter = Text2Audio::new("test_key").with_coding_plan(true);
-        assert!(converter.coding_plan);
+    #[tokio::test]
+    async fn test_split_regex_boundaries() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_split_regex("^第[一二三]章", true);
+        let text = "序言。\n第一章 开始。第一章正文。\n第二章 结束。";
+
+        let plan = converter.plan(text).await.unwrap();
+        assert_eq!(plan.mode, ConversionMode::Segmented);
+        assert_eq!(
+            plan.segments,
+            vec!["序言。", "第一章 开始。第一章正文。", "第二章 结束。"]
+        );
+
+        let converter = converter
+            .with_split_regex("^第[一二三]章", false)
+            .with_newline_pauses(Duration::from_millis(10), Duration::from_millis(50));
+        let output = temp_path("split_regex.wav");
+        let report = convert_verified(&converter, text, &output).await;
+        std::fs::remove_file(&output).unwrap();
+        assert_eq!(
+            backend.requests(),
+            vec!["序言。", "开始。第一章正文。", "结束。"]
+        );
+        assert_eq!(report.segments[0].pause_after, Duration::from_millis(10));
+
+        let invalid = Text2Audio::new("test_key")
+            .with_backend(backend)
+            .with_split_regex("第(一章", true);
+        let result = invalid.convert("你好。", &output).await;
+        assert!(matches!(result, Err(Error::Config(m)) if m.starts_with("Invalid split regex")));
+    }
+
+    #[tokio::test]
+    async fn test_convert_segments_from_json_round_trip() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_newline_pauses(Duration::from_millis(10), Duration::from_millis(50));
+        let text = "第一段。\n\n第二段。\n第三段。";
+
+        let plan_path = temp_path("round_trip.plan.json");
+        std::fs::write(&plan_path, converter.plan(text).await.unwrap().to_json()).unwrap();
+        let from_plan = temp_path("from_plan.wav");
+        let report = converter
+            .convert_segments_from_json(&plan_path, &from_plan)
+            .await
+            .unwrap();
+        verify_output(&report, std::path::Path::new(&from_plan)).unwrap();
+        let direct = temp_path("from_text.wav");
+        convert_verified(&converter, text, &direct).await;
+
+        assert_eq!(report.segment_count, 3);
+        assert_eq!(report.segments[0].pause_after, Duration::from_millis(50));
+        assert_eq!(
+            read_samples(&std::fs::read(&from_plan).unwrap()),
+            read_samples(&std::fs::read(&direct).unwrap())
+        );
+        for path in [&plan_path, &from_plan, &direct] {
+            std::fs::remove_file(path).unwrap();
+        }
+
+        std::fs::write(&plan_path, "[]").unwrap();
+        let result = converter
+            .convert_segments_from_json(&plan_path, &from_plan)
+            .await;
+        std::fs::remove_file(&plan_path).unwrap();
+        assert!(matches!(result, Err(Error::EmptyInput)));
+    }
+
+    #[tokio::test]
+    async fn test_one_shot_functions_use_crate_errors() {
+        // Empty text fails before any request, so the key is never used
+        let path = temp_path("one_shot.wav");
+        let result = crate::convert("test_key", " \n", &path).await;
+        assert!(matches!(result, Err(Error::EmptyInput)));
+        let result = crate::convert_with_voice("test_key", "", &path, Voice::Xiaochen).await;
+        assert!(matches!(result, Err(Error::EmptyInput)));
+        assert!(!Path::new(&path).exists());
+    }
+
+    #[tokio::test]
+    async fn test_symbol_verbalization() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_symbol_verbalization(true);
+        let output = temp_path("symbols.wav");
+        let text = "勾股定理：a²+b²=c²，且√2≈1.414；当T→0时ΔS≥0，水在100℃沸腾。";
+
+        let report = convert_verified(&converter, text, &output).await;
+        assert!(report.warnings.is_empty());
+        let requests = backend.requests();
+        assert!(requests.concat().contains("a的平方+b的平方=c的平方"));
+        assert!(!requests.concat().chars().any(preprocess::is_symbol));
+
+        // Two unknown symbols are within a threshold of 2, three are not
+        let converter = converter.with_unknown_symbol_threshold(2);
+        let report = convert_verified(&converter, "∮E·dl⊕B", &output).await;
+        assert!(report.warnings.is_empty());
+        let report = convert_verified(&converter, "∮E·dl⊕B⊗C", &output).await;
+        std::fs::remove_file(&output).unwrap();
+        assert_eq!(
+            report.warnings,
+            vec![Warning::UnknownSymbols {
+                symbols: vec!['∮', '⊕', '⊗'],
+                count: 3,
+                threshold: 2,
+            }]
+        );
+        let unmapped = backend
+            .requests()
+            .concat()
+            .chars()
+            .filter(|&c| preprocess::is_symbol(c))
+            .count();
+        assert_eq!(unmapped, 5);
+    }
+
+    #[tokio::test]
+    async fn test_numeric_profile() {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_symbol_verbalization(true)
+            .with_numeric_profile(NumericContext::zh_cn());
+        let output = temp_path("numeric.wav");
+        let text = "2024-03-04 15:30，加3/4杯水，再加1/2勺盐，致电13800138000。";
+
+        let report = convert_verified(&converter, text, &output).await;
+        std::fs::remove_file(&output).unwrap();
+        assert_eq!(
+            backend.requests().concat(),
+            "二零二四年三月四日 十五点三十分，加四分之三杯水，再加二分之一勺盐，\
+             致电幺三八 零零幺三 八零零零。"
+        );
+        assert_eq!(
+            report.warnings,
+            vec![Warning::AmbiguousNumbers {
+                samples: vec!["3/4".to_string(), "1/2".to_string()],
+                count: 2,
+            }]
+        );
+    }
+
+    fn race_converter(split_backend: Arc<StaticSplitBackend>) -> (Text2Audio, Arc<MockBackend>) {
+        let backend = Arc::new(MockBackend::new());
+        let converter = Text2Audio::new("test_key")
+            .with_backend(backend.clone())
+            .with_split_backend(split_backend)
+            .with_max_segment_length(100)
+            .with_split_strategy(SplitStrategy::Race {
+                deadline: Duration::from_millis(50),
+            });
+        (converter, backend)
+    }
+
+    #[tokio::test]
+    async fn test_race_uses_rule_based_split_at_deadline() {
+        let split_backend = Arc::new(StaticSplitBackend::new(
+            &["不会用到。"],
+            Duration::from_secs(10),
+        ));
+        let (converter, backend) = race_converter(split_backend.clone());
+        let long = format!("{}。", "长".repeat(99));
+        let output = temp_path("race_deadline.wav");
+
+        let started = Instant::now();
+        let report = convert_verified(&converter, &format!("{}短。", long), &output).await;
+        std::fs::remove_file(&output).unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(split_backend.calls(), 1);
+        assert_eq!(backend.requests(), vec![long.as_str(), "短。"]);
+        assert_eq!(report.split_races.len(), 1);
+        assert_eq!(report.split_races[0].chosen, SplitChoice::RuleBased);
+        assert_eq!(report.split_races[0].ai_score, None);
+    }
+
+    #[tokio::test]
+    async fn test_race_prefers_better_ai_split() {
+        let half = format!("{}。", "字".repeat(74));
+        let split_backend = Arc::new(StaticSplitBackend::new(&[&half, &half], Duration::ZERO));
+        let (converter, backend) = race_converter(split_backend);
+
+        let plan_segments = converter.plan(&"字".repeat(150)).await.unwrap().segments;
+        assert_eq!(plan_segments, vec![half.clone(), half.clone()]);
+
+        let output = temp_path("race_ai.wav");
+        let report = convert_verified(&converter, &"字".repeat(150), &output).await;
+        std::fs::remove_file(&output).unwrap();
+        assert_eq!(backend.requests(), vec![half.as_str(), half.as_str()]);
+        let race = report.split_races[0];
+        assert_eq!(race.chosen, SplitChoice::Ai);
+        assert_eq!(race.ai_score, Some(1.0));
+        assert!(race.rule_based_score < 1.0 - plan::RACE_MARGIN);
+    }
+
+    #[tokio::test]
+    async fn test_race_skips_ai_when_rule_based_cannot_lose() {
+        let split_backend = Arc::new(StaticSplitBackend::new(&["不会用到。"], Duration::ZERO));
+        let (converter, _) = race_converter(split_backend.clone());
+        let text = format!("{}。{}。", "长".repeat(99), "短".repeat(99));
+
+        let plan = converter.plan(&text).await.unwrap();
+        assert_eq!(plan.segments.len(), 2);
+        assert_eq!(split_backend.calls(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stats_latency_buckets() {
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(SlowBackend::new(Duration::from_millis(120))));
+        let output = temp_path("stats_latency.wav");
+
+        let report = converter
+            .convert_with_report("你好。", &output)
+            .await
+            .unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        // The paused clock makes the request take exactly 120ms, which falls
+        // into the bucket bounded by 250ms
+        assert_eq!(report.stats.tts.requests, 1);
+        assert_eq!(report.stats.tts.latency.counts()[2], 1);
+        assert_eq!(report.stats.tts.latency.total(), 1);
+        assert_eq!(report.stats.chat.requests, 0);
+        assert_eq!(converter.stats(), report.stats);
     }
 
     #[test]
-    fn test_builder() {
-        let converter = Text2Audio::builder("api_key")
-            .model(Model::GLM4_7)
-            .voice(Voice::Tongtong)
-            .speed(1.5)
-            .volume(3.0)
-            .max_segment_length(300)
-            .parallel(4)
-            .thinking(true)
-            .coding_plan(false)
-            .build();
+    fn test_stats_retry_tally_and_reset() {
+        let converter = Text2Audio::new("test_key")
+            .with_backend(Arc::new(FlakyBackend::new(2)))
+            .with_parallel(2)
+            .with_retry_config(3, Duration::from_millis(1));
+        let segments = vec!["第一段。".to_string(), "第二段。".to_string()];
 
-        assert_eq!(converter.model, Model::GLM4_7);
-        assert_eq!(converter.voice, Voice::Tongtong);
-        assert_eq!(converter.speed, 1.5);
-        assert_eq!(converter.volume, 3.0);
-        assert_eq!(converter.max_segment_length, 300);
-        assert!(converter.enable_parallel);
-        assert_eq!(converter.max_parallel, 4);
-        assert!(converter.enable_thinking);
-        assert!(!converter.coding_plan);
+        let results = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(converter.collect_audio_parallel(segments.into()))
+            .unwrap();
+        assert!(results.iter().all(Result::is_ok));
+
+        let stats = converter.stats();
+        assert_eq!(stats.tts.requests, 4);
+        assert_eq!(stats.tts.successes, 2);
+        assert_eq!(stats.tts.retries, 2);
+        assert_eq!(stats.tts.retry_rate(), 0.5);
+
+        converter.reset_stats();
+        assert_eq!(converter.stats(), ClientStats::default());
     }
 
     #[test]
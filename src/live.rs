@@ -0,0 +1,295 @@
+//! Synthesized audio fed into a lock-free ring buffer for live playback
+//!
+//! [`live_buffer`] creates a single-producer, single-consumer ring of 16-bit
+//! samples. The [`LiveProducer`] side is filled by
+//! [`Text2Audio::convert_live`](crate::Text2Audio::convert_live) (or by hand
+//! with [`LiveProducer::push_wav`]); the [`LiveConsumer`] side is drained from
+//! an audio callback, such as a `cpal` output stream, without locking or
+//! allocating.
+//!
+//! # Buffer sizing
+//!
+//! The buffer does not delay playback: the consumer hears a sentence as soon
+//! as its first samples are pushed. What the size trades off is how much
+//! audio the producer may run ahead. Segments arrive whole, so a buffer
+//! shorter than a segment makes the producer wait for playback to free
+//! space, waking up more often; a longer buffer absorbs whole segments at
+//! the cost of memory (48 KB per second of 24 kHz mono audio). A few
+//! seconds suits most sentence-by-sentence use.
+//!
+//! # Underruns
+//!
+//! When synthesis of the next sentence takes longer than the buffered audio
+//! lasts, the consumer runs dry. [`LiveConsumer::fill`] then pads the
+//! callback's buffer with silence, so playback never stalls, and counts an
+//! underrun. Silence before the first sample arrives and after the producer
+//! is dropped is not counted. To ride out slow first requests, wait until
+//! [`LiveConsumer::buffered`] reaches a threshold before starting the
+//! output stream.
+
+use crate::audio_merger::AudioMerger;
+use crate::error::{Error, Result};
+use hound::{SampleFormat, WavSpec};
+use ringbuf::traits::{Consumer, Observer, Producer, Split};
+use ringbuf::{HeapCons, HeapProd, HeapRb};
+use std::time::Duration;
+
+/// Longest wait of a producer for the consumer to free space
+const MAX_PRODUCER_WAIT: Duration = Duration::from_millis(20);
+
+/// Create a ring buffer holding `capacity` of audio in `sample_rate` and
+/// `channels`
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use text2audio::live_buffer;
+///
+/// let (producer, consumer) = live_buffer(24000, 1, Duration::from_secs(2));
+/// assert_eq!(consumer.capacity(), 48000);
+/// ```
+pub fn live_buffer(
+    sample_rate: u32,
+    channels: u16,
+    capacity: Duration,
+) -> (LiveProducer, LiveConsumer) {
+    let spec = WavSpec {
+        channels: channels.max(1),
+        sample_rate: sample_rate.max(1),
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let frames = (capacity.as_secs_f64() * f64::from(spec.sample_rate)).ceil() as usize;
+    let (producer, consumer) = HeapRb::new(frames.max(1) * usize::from(spec.channels)).split();
+    (
+        LiveProducer {
+            producer,
+            spec,
+            segments: 0,
+        },
+        LiveConsumer {
+            consumer,
+            spec,
+            started: false,
+            underruns: 0,
+        },
+    )
+}
+
+fn samples_duration(samples: usize, spec: WavSpec) -> Duration {
+    let frames = samples / usize::from(spec.channels);
+    Duration::from_secs_f64(frames as f64 / f64::from(spec.sample_rate))
+}
+
+/// Writing side of a [`live_buffer`]
+///
+/// Dropping it tells the consumer that no more audio will come.
+pub struct LiveProducer {
+    producer: HeapProd<i16>,
+    spec: WavSpec,
+    segments: usize,
+}
+
+impl LiveProducer {
+    /// Push a WAV segment, converting it to the buffer's sample rate and
+    /// channel count
+    ///
+    /// Waits while the buffer is full, so the producer never runs further
+    /// ahead of playback than the buffer holds.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if `wav` is not valid WAV audio, or if the
+    /// [`LiveConsumer`] was dropped.
+    pub async fn push_wav(&mut self, wav: &[u8]) -> Result<()> {
+        let samples = AudioMerger::decode_as(wav, self.segments, self.spec)?;
+        self.segments += 1;
+        self.push_samples(&samples).await
+    }
+
+    /// Push interleaved samples, waiting while the buffer is full
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Io`] with [`BrokenPipe`](std::io::ErrorKind::BrokenPipe)
+    /// if the [`LiveConsumer`] was dropped, since nothing would ever free
+    /// space for the rest of the samples.
+    pub async fn push_samples(&mut self, mut samples: &[i16]) -> Result<()> {
+        while !samples.is_empty() {
+            if !self.producer.read_is_held() {
+                return Err(Error::Io(std::io::Error::new(
+                    std::io::ErrorKind::BrokenPipe,
+                    "live consumer was dropped",
+                )));
+            }
+            let pushed = self.producer.push_slice(samples);
+            samples = &samples[pushed..];
+            if !samples.is_empty() {
+                // Sleep about as long as playback takes to free the space
+                let missing = samples.len().min(self.producer.capacity().get());
+                let wait = samples_duration(missing, self.spec).min(MAX_PRODUCER_WAIT);
+                tokio::time::sleep(wait.max(Duration::from_millis(1))).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Audio waiting in the buffer
+    pub fn buffered(&self) -> Duration {
+        samples_duration(self.producer.occupied_len(), self.spec)
+    }
+}
+
+/// Reading side of a [`live_buffer`], for an audio callback
+///
+/// Its methods neither lock nor allocate.
+pub struct LiveConsumer {
+    consumer: HeapCons<i16>,
+    spec: WavSpec,
+    started: bool,
+    underruns: u64,
+}
+
+impl LiveConsumer {
+    /// Sample rate of the buffered audio in Hz
+    pub fn sample_rate(&self) -> u32 {
+        self.spec.sample_rate
+    }
+
+    /// Number of interleaved channels of the buffered audio
+    pub fn channels(&self) -> u16 {
+        self.spec.channels
+    }
+
+    /// Number of samples the buffer holds
+    pub fn capacity(&self) -> usize {
+        self.consumer.capacity().get()
+    }
+
+    /// Audio waiting in the buffer
+    pub fn buffered(&self) -> Duration {
+        samples_duration(self.consumer.occupied_len(), self.spec)
+    }
+
+    /// Whether the producer is gone and all of its audio was read
+    pub fn is_finished(&self) -> bool {
+        !self.consumer.write_is_held() && self.consumer.is_empty()
+    }
+
+    /// Number of fills that ran dry while more audio was expected
+    pub fn underruns(&self) -> u64 {
+        self.underruns
+    }
+
+    /// Fill `out` with buffered samples, padding with silence if the buffer
+    /// runs dry
+    ///
+    /// Returns the number of samples read from the buffer.
+    pub fn fill(&mut self, out: &mut [i16]) -> usize {
+        let read = self.consumer.pop_slice(out);
+        out[read..].fill(0);
+        self.record_fill(read, out.len());
+        read
+    }
+
+    /// Fill `out` with buffered samples scaled to -1.0..1.0, padding with
+    /// silence if the buffer runs dry
+    ///
+    /// Returns the number of samples read from the buffer.
+    pub fn fill_f32(&mut self, out: &mut [f32]) -> usize {
+        let mut chunk = [0i16; 256];
+        let mut read = 0;
+        while read < out.len() {
+            let want = (out.len() - read).min(chunk.len());
+            let popped = self.consumer.pop_slice(&mut chunk[..want]);
+            for (out, &sample) in out[read..].iter_mut().zip(&chunk[..popped]) {
+                *out = f32::from(sample) / 32768.0;
+            }
+            read += popped;
+            if popped < want {
+                break;
+            }
+        }
+        out[read..].fill(0.0);
+        self.record_fill(read, out.len());
+        read
+    }
+
+    fn record_fill(&mut self, read: usize, wanted: usize) {
+        if read > 0 {
+            self.started = true;
+        }
+        if read < wanted && self.started && self.consumer.write_is_held() {
+            self.underruns += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::wav_bytes;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_live_buffer_backpressure_and_underruns() {
+        // Room for 10 ms of 24 kHz mono audio
+        let (mut producer, mut consumer) = live_buffer(24000, 1, Duration::from_millis(10));
+        assert_eq!(consumer.capacity(), 240);
+
+        let mut out = [1i16; 60];
+        assert_eq!(consumer.fill(&mut out), 0);
+        assert_eq!(consumer.underruns(), 0, "nothing was expected yet");
+
+        let segment = wav_bytes(&[500; 300], 24000, 1);
+        {
+            let push = producer.push_wav(&segment);
+            tokio::pin!(push);
+            // The buffer fills up and the producer waits for space
+            assert!(futures::poll!(push.as_mut()).is_pending());
+            assert_eq!(consumer.buffered(), Duration::from_millis(10));
+            assert_eq!(consumer.fill(&mut out), 60);
+            push.await.unwrap();
+        }
+        assert_eq!(producer.buffered(), Duration::from_millis(10));
+
+        let mut out = [0f32; 250];
+        assert_eq!(consumer.fill_f32(&mut out), 240);
+        assert_eq!(out[0], 500.0 / 32768.0);
+        assert_eq!(out[240], 0.0);
+        assert_eq!(consumer.underruns(), 1);
+
+        drop(producer);
+        assert!(consumer.is_finished());
+        consumer.fill(&mut [0; 10]);
+        assert_eq!(consumer.underruns(), 1, "the producer is gone");
+    }
+
+    #[tokio::test]
+    async fn test_live_buffer_converts_segments() {
+        let (mut producer, mut consumer) = live_buffer(24000, 2, Duration::from_secs(1));
+        producer
+            .push_wav(&wav_bytes(&[100, 300], 12000, 1))
+            .await
+            .unwrap();
+        let mut out = [0i16; 8];
+        assert_eq!(consumer.fill(&mut out), 8);
+        assert_eq!(&out[..2], &[100, 100]);
+        assert!(producer.push_wav(b"RIFF").await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_push_stops_when_consumer_dropped() {
+        let (mut producer, consumer) = live_buffer(24000, 1, Duration::from_millis(10));
+        let segment = wav_bytes(&[500; 300], 24000, 1);
+        {
+            let push = producer.push_wav(&segment);
+            tokio::pin!(push);
+            assert!(futures::poll!(push.as_mut()).is_pending());
+            drop(consumer);
+            let err = push.await.unwrap_err();
+            assert!(matches!(&err, Error::Io(e) if e.kind() == std::io::ErrorKind::BrokenPipe));
+        }
+        assert!(producer.push_samples(&[1]).await.is_err());
+    }
+}
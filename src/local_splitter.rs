@@ -0,0 +1,527 @@
+use crate::client::TTS_MAX_CHARS;
+use crate::preprocess::{is_cjk, is_cjk_punctuation};
+use serde::{Deserialize, Serialize};
+
+/// Default punctuation marks that end a sentence
+pub const DEFAULT_SENTENCE_TERMINATORS: &[char] = &['。', '！', '？', '.', '!', '?'];
+
+/// Unit in which the maximum segment length is measured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LengthUnit {
+    /// Characters, which suit CJK text
+    #[default]
+    Chars,
+    /// Words, which suit Latin-script text
+    ///
+    /// A word is a whitespace-delimited run of non-CJK text; each CJK
+    /// character counts as one word and CJK punctuation as none. Segments
+    /// are additionally kept within [`TTS_MAX_CHARS`] characters, the limit
+    /// of a single TTS request.
+    Words,
+}
+
+impl LengthUnit {
+    /// Length of `text` in this unit
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::LengthUnit;
+    ///
+    /// assert_eq!(LengthUnit::Chars.measure("Hello world"), 11);
+    /// assert_eq!(LengthUnit::Words.measure("Hello world"), 2);
+    /// assert_eq!(LengthUnit::Words.measure("你好，world"), 3);
+    /// ```
+    pub fn measure(&self, text: &str) -> usize {
+        match self {
+            LengthUnit::Chars => text.chars().count(),
+            LengthUnit::Words => word_starts(text).count(),
+        }
+    }
+
+    /// Whether `text` fits in one segment of at most `max_length` units
+    pub(crate) fn fits(&self, text: &str, max_length: usize) -> bool {
+        match self {
+            LengthUnit::Chars => self.measure(text) <= max_length,
+            LengthUnit::Words => {
+                self.measure(text) <= max_length && text.chars().count() <= TTS_MAX_CHARS
+            }
+        }
+    }
+}
+
+/// Byte offsets at which the words of `text` start
+///
+/// Symbols such as quotes, hyphens and apostrophes neither start nor end a
+/// word, so "don't" and "“Hello”" are one word each.
+fn word_starts(text: &str) -> impl Iterator<Item = usize> + '_ {
+    let mut in_word = false;
+    text.char_indices().filter_map(move |(idx, c)| {
+        if c.is_whitespace() || is_cjk_punctuation(c) {
+            in_word = false;
+        } else if is_cjk(c) && c.is_alphanumeric() {
+            in_word = false;
+            return Some(idx);
+        } else if c.is_alphanumeric() && !in_word {
+            in_word = true;
+            return Some(idx);
+        }
+        None
+    })
+}
+
+/// Whether `line` starts a markdown list item or is a table row
+///
+/// Items start with `-`, `*` or `+`, or a number followed by `.` or `)`,
+/// and then whitespace; table rows start with `|`.
+fn starts_item(line: &str) -> bool {
+    let line = line.trim_start();
+    if line.starts_with('|') {
+        return true;
+    }
+    let marker = match line.strip_prefix(['-', '*', '+']) {
+        Some(rest) => rest,
+        None => {
+            let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+            match line[digits..].strip_prefix(['.', ')']) {
+                Some(rest) if (1..=9).contains(&digits) => rest,
+                _ => return false,
+            }
+        }
+    };
+    marker.starts_with([' ', '\t'])
+}
+
+/// Cut text into list items and table rows, each with the indented lines
+/// continuing it, and the prose between them
+///
+/// Returns each part with whether it is an item. The parts together cover
+/// `text`.
+pub(crate) fn list_items(text: &str) -> Vec<(&str, bool)> {
+    let mut parts: Vec<(&str, bool)> = Vec::new();
+    let mut start = 0;
+    let mut in_item = false;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let item = starts_item(line);
+        let continues =
+            in_item && !item && line.starts_with([' ', '\t']) && !line.trim().is_empty();
+        if offset > start && !continues && (item || in_item) {
+            parts.push((&text[start..offset], in_item));
+            start = offset;
+        }
+        if !continues {
+            in_item = item;
+        }
+        offset += line.len();
+    }
+    if start < text.len() {
+        parts.push((&text[start..], in_item));
+    }
+    parts
+}
+
+/// Cut text into runs of consecutive list items and table rows, and the
+/// prose between them
+///
+/// Returns each run with whether it is a list. Blank lines between items
+/// stay in the list.
+pub(crate) fn list_blocks(text: &str) -> Vec<(&str, bool)> {
+    let mut blocks: Vec<(usize, usize, bool)> = Vec::new();
+    let mut end = 0;
+    for (part, item) in list_items(text) {
+        let start = end;
+        end += part.len();
+        let in_list = blocks.last().is_some_and(|&(_, _, list)| list);
+        let list = item || (in_list && part.trim().is_empty());
+        match blocks.last_mut() {
+            Some(last) if last.2 == list => last.1 = end,
+            _ => blocks.push((start, end, list)),
+        }
+    }
+    blocks
+        .into_iter()
+        .map(|(start, end, list)| (&text[start..end], list))
+        .collect()
+}
+
+/// Cut text before every `n`th of the given char offsets
+fn cut_every(text: &str, starts: impl Iterator<Item = usize>, n: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+
+    for (count, idx) in starts.enumerate() {
+        if count > 0 && count % n == 0 {
+            pieces.push(&text[start..idx]);
+            start = idx;
+        }
+    }
+    pieces.push(&text[start..]);
+
+    pieces
+}
+
+/// Rule-based text splitter that works without any network access
+///
+/// Splits text at sentence terminators, [`DEFAULT_SENTENCE_TERMINATORS`]
+/// unless set with [`with_sentence_terminators`](Self::with_sentence_terminators),
+/// and packs consecutive sentences into
+/// segments no longer than `max_length` characters, or words with
+/// [`LengthUnit::Words`]. Sentences that are longer than `max_length` on
+/// their own are cut at the limit.
+pub struct LocalSplitter {
+    max_length: usize,
+    unit: LengthUnit,
+    terminators: Vec<char>,
+    list_awareness: bool,
+    whole_sentences: bool,
+}
+
+impl LocalSplitter {
+    /// Create a new local splitter
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::local_splitter::LocalSplitter;
+    ///
+    /// let splitter = LocalSplitter::new(100);
+    /// let segments = splitter.split("第一句。第二句。");
+    /// assert_eq!(segments, vec!["第一句。第二句。"]);
+    /// ```
+    pub fn new(max_length: usize) -> Self {
+        Self {
+            max_length: max_length.max(1),
+            unit: LengthUnit::Chars,
+            terminators: DEFAULT_SENTENCE_TERMINATORS.to_vec(),
+            list_awareness: false,
+            whole_sentences: false,
+        }
+    }
+
+    /// Set the unit in which `max_length` is measured
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::local_splitter::{LengthUnit, LocalSplitter};
+    ///
+    /// let splitter = LocalSplitter::new(3).with_length_unit(LengthUnit::Words);
+    /// let segments = splitter.split("One two. Three four five.");
+    /// assert_eq!(segments, vec!["One two.", "Three four five."]);
+    /// ```
+    pub fn with_length_unit(mut self, unit: LengthUnit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Set the punctuation marks that end a sentence
+    ///
+    /// Replaces [`DEFAULT_SENTENCE_TERMINATORS`]. With no terminators, text
+    /// is only cut at `max_length`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::local_splitter::LocalSplitter;
+    ///
+    /// let splitter = LocalSplitter::new(4).with_sentence_terminators(&['。', '；']);
+    /// let segments = splitter.split("一二；三四。");
+    /// assert_eq!(segments, vec!["一二；", "三四。"]);
+    /// ```
+    pub fn with_sentence_terminators(mut self, terminators: &[char]) -> Self {
+        self.terminators = terminators.to_vec();
+        self
+    }
+
+    /// Keep markdown list items and table rows whole
+    ///
+    /// Each line starting with `-`, `*`, `+` or a number followed by `.` or
+    /// `)`, or with `|` for a table row, ends the text before it and is
+    /// packed as one unit together with its indented continuation lines,
+    /// rather than sentence by sentence. Only an item longer than
+    /// `max_length` on its own is split at its sentences.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::local_splitter::LocalSplitter;
+    ///
+    /// let splitter = LocalSplitter::new(12).with_list_awareness(true);
+    /// let segments = splitter.split("步骤：\n- 安装。然后重启\n- 运行程序。");
+    /// assert_eq!(segments, vec!["步骤：", "- 安装。然后重启", "- 运行程序。"]);
+    /// ```
+    pub fn with_list_awareness(mut self, enable: bool) -> Self {
+        self.list_awareness = enable;
+        self
+    }
+
+    /// Never cut a sentence, even one longer than `max_length`
+    ///
+    /// For text already known to fit a TTS request, such as a segment
+    /// being split again.
+    pub(crate) fn with_whole_sentences(mut self) -> Self {
+        self.whole_sentences = true;
+        self
+    }
+
+    /// Split text into segments of at most `max_length` characters or words
+    pub fn split(&self, text: &str) -> Vec<String> {
+        let mut segments = Vec::new();
+        let mut current = String::new();
+        let mut current_len = 0;
+        let mut current_chars = 0;
+
+        for piece in self.pieces(text) {
+            let piece_len = self.unit.measure(piece);
+            let piece_chars = piece.chars().count();
+            let too_long = current_len + piece_len > self.max_length
+                || (self.unit == LengthUnit::Words && current_chars + piece_chars > TTS_MAX_CHARS);
+            if too_long && !current.is_empty() {
+                segments.push(current.trim().to_string());
+                current.clear();
+                current_len = 0;
+                current_chars = 0;
+            }
+            current.push_str(piece);
+            current_len += piece_len;
+            current_chars += piece_chars;
+        }
+
+        if !current.trim().is_empty() {
+            segments.push(current.trim().to_string());
+        }
+
+        segments.retain(|s| !s.is_empty());
+        segments
+    }
+
+    /// Cut text into the pieces packed into segments: sentences cut at
+    /// `max_length` and, with list awareness, whole list items
+    fn pieces<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let sentences = |text: &'a str| {
+            self.sentences(text)
+                .into_iter()
+                .flat_map(|sentence| self.hard_cut(sentence))
+                .collect::<Vec<_>>()
+        };
+        if !self.list_awareness {
+            return sentences(text);
+        }
+        list_items(text)
+            .into_iter()
+            .flat_map(|(part, item)| {
+                if item && self.unit.fits(part.trim_end(), self.max_length) {
+                    vec![part]
+                } else {
+                    sentences(part)
+                }
+            })
+            .collect()
+    }
+
+    /// Split text into sentences, keeping each terminator with its sentence
+    fn sentences<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let mut sentences = Vec::new();
+        let mut start = 0;
+
+        for (idx, c) in text.char_indices() {
+            if self.terminators.contains(&c) {
+                let end = idx + c.len_utf8();
+                sentences.push(&text[start..end]);
+                start = end;
+            }
+        }
+
+        if start < text.len() {
+            sentences.push(&text[start..]);
+        }
+
+        sentences
+    }
+
+    /// Cut a sentence into pieces of at most `max_length` units
+    fn hard_cut<'a>(&self, sentence: &'a str) -> Vec<&'a str> {
+        if self.whole_sentences {
+            return vec![sentence];
+        }
+        let char_starts = |text: &'a str| text.char_indices().map(|(idx, _)| idx);
+        match self.unit {
+            LengthUnit::Chars => cut_every(sentence, char_starts(sentence), self.max_length),
+            LengthUnit::Words => cut_every(sentence, word_starts(sentence), self.max_length)
+                .into_iter()
+                .flat_map(|piece| cut_every(piece, char_starts(piece), TTS_MAX_CHARS))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_short_text() {
+        let splitter = LocalSplitter::new(100);
+        assert_eq!(splitter.split("Hello world."), vec!["Hello world."]);
+    }
+
+    #[test]
+    fn test_split_packs_sentences() {
+        let splitter = LocalSplitter::new(6);
+        let segments = splitter.split("一二。三四。五六七。八。");
+        assert_eq!(segments, vec!["一二。三四。", "五六七。八。"]);
+    }
+
+    #[test]
+    fn test_split_custom_terminators() {
+        let text = "甲乙；丙丁：戊己。庚辛！";
+        let splitter = LocalSplitter::new(6).with_sentence_terminators(&['；', '。']);
+        assert_eq!(
+            splitter.split(text),
+            vec!["甲乙；", "丙丁：戊己。", "庚辛！"]
+        );
+
+        // Default terminators left out of the set no longer end sentences
+        let splitter = LocalSplitter::new(6).with_sentence_terminators(&['：']);
+        assert_eq!(splitter.split(text), vec!["甲乙；丙丁：", "戊己。庚辛！"]);
+    }
+
+    #[test]
+    fn test_split_hard_cut() {
+        let splitter = LocalSplitter::new(4);
+        let segments = splitter.split("一二三四五六七八九十");
+        assert_eq!(segments, vec!["一二三四", "五六七八", "九十"]);
+    }
+
+    #[test]
+    fn test_split_whole_sentences() {
+        let splitter = LocalSplitter::new(4).with_whole_sentences();
+        assert_eq!(splitter.split("一二三四五六。"), vec!["一二三四五六。"]);
+        assert_eq!(
+            splitter.split("一二。三四五六七。八。"),
+            vec!["一二。", "三四五六七。", "八。"]
+        );
+    }
+
+    #[test]
+    fn test_split_respects_max_length() {
+        let splitter = LocalSplitter::new(50);
+        let text = "这是一个测试句子。".repeat(40);
+        let segments = splitter.split(&text);
+        assert!(segments.len() > 1);
+        assert!(segments.iter().all(|s| s.chars().count() <= 50));
+        assert_eq!(segments.concat(), text);
+    }
+
+    #[test]
+    fn test_measure_words() {
+        assert_eq!(LengthUnit::Words.measure(""), 0);
+        assert_eq!(LengthUnit::Words.measure("  one,  two\tthree. "), 3);
+        assert_eq!(LengthUnit::Words.measure("使用GPU加速。"), 5);
+        assert_eq!(LengthUnit::Words.measure("“引号”"), 2);
+        assert_eq!(LengthUnit::Words.measure("don't “quote” e-mail 3.5 --"), 4);
+    }
+
+    #[test]
+    fn test_split_words() {
+        let splitter = LocalSplitter::new(10).with_length_unit(LengthUnit::Words);
+        let text = "The quick brown fox jumps over the lazy dog. ".repeat(10);
+        let segments = splitter.split(&text);
+        assert_eq!(segments.len(), 10);
+        assert!(segments.iter().all(|s| LengthUnit::Words.measure(s) <= 10));
+    }
+
+    #[test]
+    fn test_split_words_hard_cut() {
+        let splitter = LocalSplitter::new(3).with_length_unit(LengthUnit::Words);
+        let segments = splitter.split("one two three four five six seven");
+        assert_eq!(segments, vec!["one two three", "four five six", "seven"]);
+    }
+
+    #[test]
+    fn test_split_words_respects_request_limit() {
+        let splitter = LocalSplitter::new(1000).with_length_unit(LengthUnit::Words);
+        let text = "abcdefghij ".repeat(300);
+        let segments = splitter.split(&text);
+        assert!(segments.len() > 1);
+        assert!(segments.iter().all(|s| s.chars().count() <= TTS_MAX_CHARS));
+    }
+
+    #[test]
+    fn test_list_items_and_blocks() {
+        let text =
+            "准备：\n- 第一项\n  续行。\n\n1. 编号项\n2) 另一项\n| 列 | 值 |\n结尾。-不是列表";
+        assert_eq!(
+            list_items(text),
+            vec![
+                ("准备：\n", false),
+                ("- 第一项\n  续行。\n", true),
+                ("\n", false),
+                ("1. 编号项\n", true),
+                ("2) 另一项\n", true),
+                ("| 列 | 值 |\n", true),
+                ("结尾。-不是列表", false),
+            ]
+        );
+        assert_eq!(
+            list_blocks(text),
+            vec![
+                ("准备：\n", false),
+                (
+                    "- 第一项\n  续行。\n\n1. 编号项\n2) 另一项\n| 列 | 值 |\n",
+                    true
+                ),
+                ("结尾。-不是列表", false),
+            ]
+        );
+        assert!(!starts_item("---"));
+        assert!(!starts_item("3.5 分"));
+        assert!(!starts_item("1234567890. 太长"));
+    }
+
+    #[test]
+    fn test_split_keeps_list_items_whole() {
+        let text = "功能：\n- 安装。下载后运行\n- 配置！然后重启。\n- 完成";
+        let splitter = LocalSplitter::new(10);
+        assert_eq!(
+            splitter.split(text),
+            vec![
+                "功能：\n- 安装。",
+                "下载后运行\n- 配置",
+                "！然后重启。",
+                "- 完成"
+            ]
+        );
+
+        let splitter = splitter.with_list_awareness(true);
+        assert_eq!(
+            splitter.split(text),
+            vec![
+                "功能：",
+                "- 安装。下载后运行",
+                "- 配置！然后重启。",
+                "- 完成"
+            ]
+        );
+
+        // Short items are packed; an item too long on its own is split
+        let splitter = LocalSplitter::new(20).with_list_awareness(true);
+        assert_eq!(
+            splitter.split("- 甲\n- 乙\n- 丙丙丙丙丙丙丙丙丙丙。丁丁丁丁丁丁丁丁丁丁丁丁。"),
+            vec![
+                "- 甲\n- 乙",
+                "- 丙丙丙丙丙丙丙丙丙丙。",
+                "丁丁丁丁丁丁丁丁丁丁丁丁。"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_empty() {
+        let splitter = LocalSplitter::new(10);
+        assert!(splitter.split("").is_empty());
+        assert!(splitter.split("   ").is_empty());
+    }
+}
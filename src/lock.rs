@@ -0,0 +1,291 @@
+//! Advisory lock files guarding state shared between processes
+
+use crate::error::Result;
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+
+/// Age after which a lock that was not refreshed is considered abandoned
+pub(crate) const STALE_LOCK_AGE: Duration = Duration::from_secs(600);
+
+/// Interval between attempts to take a contended lock
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Age after which a takeover marker left by a crashed process is ignored
+const STALE_TAKEOVER_AGE: Duration = Duration::from_secs(10);
+
+/// Exclusive lock held by creating a file next to the guarded resource
+///
+/// The file records the holder's PID and the time it was last refreshed, in
+/// milliseconds since the Unix epoch. A lock whose holder has exited, or
+/// that was not refreshed within [`STALE_LOCK_AGE`], is stale and gets
+/// taken over, so a crashed process cannot block others forever. Creating
+/// the file with `create_new` is atomic on Unix and Windows alike, so one
+/// implementation serves both. Taking over is guarded the same way: only
+/// the process that creates the `.takeover` marker next to the lock may
+/// remove it, so two processes judging one lock stale cannot remove a lock
+/// the other has just taken. The lock is released when dropped.
+#[derive(Debug)]
+pub(crate) struct FileLock {
+    path: PathBuf,
+    stamp: String,
+}
+
+impl FileLock {
+    /// Take the lock at `path`, waiting up to `timeout` while it is held
+    ///
+    /// Returns `None` if the lock is still held when the timeout expires.
+    pub(crate) async fn acquire(
+        path: impl Into<PathBuf>,
+        timeout: Duration,
+    ) -> Result<Option<Self>> {
+        let path = path.into();
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(lock) = Self::try_acquire(&path)? {
+                return Ok(Some(lock));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            tokio::time::sleep(POLL_INTERVAL.min(deadline - now)).await;
+        }
+    }
+
+    /// Take the lock at `path` if it is free or stale
+    pub(crate) fn try_acquire(path: &Path) -> Result<Option<Self>> {
+        if let Some(lock) = Self::create(path)? {
+            return Ok(Some(lock));
+        }
+
+        let Ok(contents) = std::fs::read_to_string(path) else {
+            // Released in the meantime
+            return Self::create(path);
+        };
+        if !is_stale(path, &contents) {
+            return Ok(None);
+        }
+        let Some(_takeover) = Takeover::create(path)? else {
+            // Another process is taking the lock over
+            return Ok(None);
+        };
+        // Only remove the lock judged stale, not one taken over since
+        if std::fs::read_to_string(path).is_ok_and(|current| current == contents) {
+            remove_if_present(path)?;
+        }
+        Self::create(path)
+    }
+
+    fn create(path: &Path) -> Result<Option<Self>> {
+        match OpenOptions::new().write(true).create_new(true).open(path) {
+            Ok(mut file) => {
+                let stamp = stamp();
+                file.write_all(stamp.as_bytes())?;
+                Ok(Some(Self {
+                    path: path.to_path_buf(),
+                    stamp,
+                }))
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Record that the holder is still alive
+    pub(crate) fn refresh(&mut self) -> Result<()> {
+        let stamp = stamp();
+        std::fs::write(&self.path, &stamp)?;
+        self.stamp = stamp;
+        Ok(())
+    }
+}
+
+/// Marker held while removing a stale lock, released when dropped
+struct Takeover {
+    path: PathBuf,
+}
+
+impl Takeover {
+    fn create(lock: &Path) -> Result<Option<Self>> {
+        let mut path = lock.as_os_str().to_owned();
+        path.push(".takeover");
+        let path = PathBuf::from(path);
+        for _ in 0..2 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Some(Self { path })),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+            // A marker this old was left by a process that crashed midway
+            let abandoned = std::fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > STALE_TAKEOVER_AGE);
+            if !abandoned {
+                return Ok(None);
+            }
+            remove_if_present(&path)?;
+        }
+        Ok(None)
+    }
+}
+
+impl Drop for Takeover {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn remove_if_present(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // Leave the file alone if another process took the lock over
+        if std::fs::read_to_string(&self.path).is_ok_and(|current| current == self.stamp) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn now_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+fn stamp() -> String {
+    format!("{} {}", std::process::id(), now_millis())
+}
+
+/// Whether a lock file with `contents` was abandoned by its holder
+fn is_stale(path: &Path, contents: &str) -> bool {
+    let mut fields = contents.split_whitespace();
+    let pid = fields.next().and_then(|pid| pid.parse::<u32>().ok());
+    let millis = fields.next().and_then(|millis| millis.parse::<u128>().ok());
+
+    match (pid, millis) {
+        (Some(pid), Some(millis)) => {
+            now_millis().saturating_sub(millis) > STALE_LOCK_AGE.as_millis() || !process_alive(pid)
+        }
+        // The holder may not have written its stamp yet, so judge by the
+        // file's modification time instead
+        _ => std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > STALE_LOCK_AGE),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn process_alive(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+/// Without a portable liveness check, only the lock's age marks it stale
+#[cfg(not(target_os = "linux"))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_path;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lock_excludes_and_releases() {
+        let path = PathBuf::from(temp_path("exclusive.lock"));
+        let lock = FileLock::acquire(&path, Duration::ZERO).await.unwrap();
+        assert!(lock.is_some());
+        let started = Instant::now();
+        assert!(FileLock::acquire(&path, Duration::from_millis(50))
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(started.elapsed(), Duration::from_millis(50));
+
+        drop(lock);
+        assert!(!path.exists());
+        let lock = FileLock::acquire(&path, Duration::ZERO).await.unwrap();
+        assert!(lock.is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_waiting_task_gets_released_lock() {
+        let path = PathBuf::from(temp_path("contended.lock"));
+        let lock = FileLock::acquire(&path, Duration::ZERO)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let waiter = FileLock::acquire(&path, Duration::from_secs(5));
+        tokio::pin!(waiter);
+        assert!(futures::poll!(waiter.as_mut()).is_pending());
+        drop(lock);
+        let waited = waiter.await.unwrap();
+
+        assert!(waited.is_some());
+    }
+
+    #[test]
+    fn test_stale_lock_taken_over() {
+        let path = PathBuf::from(temp_path("stale.lock"));
+
+        // A holder that crashed long ago
+        std::fs::write(&path, format!("{} 0", u32::MAX)).unwrap();
+        let lock = FileLock::try_acquire(&path).unwrap();
+        assert!(lock.is_some());
+        drop(lock);
+
+        // A live holder that refreshed recently
+        std::fs::write(&path, stamp()).unwrap();
+        assert!(FileLock::try_acquire(&path).unwrap().is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_stale_lock_takeover_is_exclusive() {
+        let path = PathBuf::from(temp_path("takeover.lock"));
+        let stale = format!("{} 0", u32::MAX);
+        std::fs::write(&path, &stale).unwrap();
+
+        // Another process is taking the stale lock over, so it is left alone
+        let takeover = Takeover::create(&path).unwrap().unwrap();
+        assert!(Takeover::create(&path).unwrap().is_none());
+        assert!(FileLock::try_acquire(&path).unwrap().is_none());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), stale);
+
+        // Once it is done, the lock can be taken over again
+        drop(takeover);
+        let lock = FileLock::try_acquire(&path).unwrap().unwrap();
+        assert_ne!(std::fs::read_to_string(&path).unwrap(), stale);
+        let mut marker = path.as_os_str().to_owned();
+        marker.push(".takeover");
+        assert!(!Path::new(&marker).exists());
+        drop(lock);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_drop_keeps_lock_taken_over() {
+        let path = PathBuf::from(temp_path("taken_over.lock"));
+        let lock = FileLock::try_acquire(&path).unwrap().unwrap();
+        std::fs::write(&path, "1 1").unwrap();
+
+        drop(lock);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "1 1");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
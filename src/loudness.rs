@@ -0,0 +1,359 @@
+//! Loudness metering per ITU-R BS.1770 and normalization to a target
+//!
+//! Loudness is the gated integrated loudness of K-weighted audio, in LUFS,
+//! with every channel weighted equally. Peaks are true peaks estimated by
+//! four-times oversampling, in dBTP.
+
+use std::f64::consts::PI;
+use std::ops::RangeInclusive;
+
+/// Highest true peak, in dBTP, that loudness normalization lets through
+pub const TRUE_PEAK_CEILING: f64 = -1.0;
+
+/// Target loudness, in LUFS, used in place of a NaN or infinite one
+pub const DEFAULT_TARGET_LOUDNESS: f32 = -16.0;
+
+/// Accepted target loudness, in LUFS
+pub(crate) const TARGET_LOUDNESS_RANGE: RangeInclusive<f32> = -70.0..=0.0;
+
+/// Loudness below which a block of audio is ignored, in LUFS
+const ABSOLUTE_GATE: f64 = -70.0;
+
+/// Gap below the ungated loudness at which blocks are ignored, in LU
+const RELATIVE_GATE: f64 = 10.0;
+
+/// Length of a gating block
+const BLOCK_SECONDS: f64 = 0.4;
+
+/// Blocks overlap by three quarters of their length
+const BLOCK_STEPS: usize = 4;
+
+/// Taps either side of an interpolated point when estimating true peaks
+const INTERPOLATION_TAPS: isize = 6;
+
+/// Points estimated between each pair of samples for true peaks
+const OVERSAMPLING: usize = 4;
+
+/// Time over which the limiter lowers the gain ahead of a peak
+const LIMITER_ATTACK_SECONDS: f64 = 0.002;
+
+/// Time over which the limiter restores the gain after a peak
+const LIMITER_RELEASE_SECONDS: f64 = 0.05;
+
+/// Integrated loudness of interleaved 16-bit samples, in LUFS
+///
+/// Returns `None` for audio shorter than one 400 ms gating block, or when
+/// every block falls below the -70 LUFS absolute gate, as with silence.
+pub fn integrated_loudness(samples: &[i16], channels: u16, sample_rate: u32) -> Option<f64> {
+    let channels = deinterleave(samples, channels);
+    let block = (BLOCK_SECONDS * sample_rate as f64).round() as usize;
+    let frames = channels.first().map_or(0, Vec::len);
+    if block == 0 || frames < block {
+        return None;
+    }
+
+    // Squared K-weighted samples summed over channels, then over each step
+    let mut power = vec![0.0; frames];
+    for channel in &channels {
+        for (total, sample) in power.iter_mut().zip(k_weight(channel, sample_rate)) {
+            *total += sample * sample;
+        }
+    }
+    let step = block / BLOCK_STEPS;
+    let steps: Vec<f64> = power
+        .chunks_exact(step.max(1))
+        .map(|s| s.iter().sum())
+        .collect();
+    let blocks: Vec<f64> = steps
+        .windows(BLOCK_STEPS)
+        .map(|window| window.iter().sum::<f64>() / (step * BLOCK_STEPS) as f64)
+        .filter(|&power| loudness(power) > ABSOLUTE_GATE)
+        .collect();
+    if blocks.is_empty() {
+        return None;
+    }
+
+    let threshold = loudness(mean(&blocks)) - RELATIVE_GATE;
+    let gated: Vec<f64> = blocks
+        .into_iter()
+        .filter(|&power| loudness(power) > threshold)
+        .collect();
+    Some(loudness(mean(&gated)))
+}
+
+/// True peak of interleaved 16-bit samples, in dBTP
+///
+/// Silence has a true peak of negative infinity.
+pub fn true_peak(samples: &[i16], channels: u16) -> f64 {
+    let peak = deinterleave(samples, channels)
+        .iter()
+        .flat_map(|channel| local_peaks(channel))
+        .fold(0.0, f64::max);
+    20.0 * peak.log10()
+}
+
+/// Apply the single gain that brings `samples` to `target` LUFS, limiting
+/// true peaks to [`TRUE_PEAK_CEILING`]
+///
+/// Returns the gain applied in dB, or `None`, leaving the samples
+/// unchanged, when their loudness cannot be measured.
+pub(crate) fn normalize(
+    samples: &mut [i16],
+    channels: u16,
+    sample_rate: u32,
+    target: f64,
+) -> Option<f64> {
+    let gain_db = target - integrated_loudness(samples, channels, sample_rate)?;
+    let gain = 10f64.powf(gain_db / 20.0);
+    let ceiling = 10f64.powf(TRUE_PEAK_CEILING / 20.0);
+
+    // Gain reduction each frame needs for its peaks to stay under the ceiling
+    let decoded = deinterleave(samples, channels);
+    let frames = decoded.first().map_or(0, Vec::len);
+    let mut limit = vec![1.0_f64; frames];
+    for channel in &decoded {
+        for (limit, peak) in limit.iter_mut().zip(local_peaks(channel)) {
+            if peak * gain > ceiling {
+                *limit = limit.min(ceiling / (peak * gain));
+            }
+        }
+    }
+
+    // Ramp the reduction in ahead of each peak and out after it, never
+    // exceeding what a frame needs
+    let attack = 1.0 / (LIMITER_ATTACK_SECONDS * sample_rate as f64).max(1.0);
+    let release = 1.0 / (LIMITER_RELEASE_SECONDS * sample_rate as f64).max(1.0);
+    for i in (1..frames).rev() {
+        let ramped = limit[i] + (1.0 - limit[i]) * attack;
+        limit[i - 1] = limit[i - 1].min(ramped);
+    }
+    for i in 1..frames {
+        let ramped = limit[i - 1] + (1.0 - limit[i - 1]) * release;
+        limit[i] = limit[i].min(ramped);
+    }
+
+    let channels = channels.max(1) as usize;
+    for (frame, limit) in samples.chunks_exact_mut(channels).zip(&limit) {
+        for sample in frame {
+            let scaled = *sample as f64 * gain * limit;
+            *sample = scaled.round().clamp(i16::MIN as f64, i16::MAX as f64) as i16;
+        }
+    }
+    Some(gain_db)
+}
+
+/// Loudness of a mean square power, in LUFS
+fn loudness(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Split interleaved samples into channels scaled to full scale 1.0
+fn deinterleave(samples: &[i16], channels: u16) -> Vec<Vec<f64>> {
+    let channels = channels.max(1) as usize;
+    (0..channels)
+        .map(|channel| {
+            samples
+                .iter()
+                .skip(channel)
+                .step_by(channels)
+                .take(samples.len() / channels)
+                .map(|&sample| sample as f64 / 32768.0)
+                .collect()
+        })
+        .collect()
+}
+
+/// Apply the K-weighting filter: a high shelf modelling the head followed
+/// by a high pass
+fn k_weight(samples: &[f64], sample_rate: u32) -> impl Iterator<Item = f64> + '_ {
+    let mut shelf = Biquad::high_shelf(sample_rate);
+    let mut high_pass = Biquad::high_pass(sample_rate);
+    samples
+        .iter()
+        .map(move |&sample| high_pass.process(shelf.process(sample)))
+}
+
+/// Largest absolute value of each sample and the points interpolated
+/// between it and the next
+fn local_peaks(samples: &[f64]) -> impl Iterator<Item = f64> + '_ {
+    let kernels: Vec<Vec<f64>> = (1..OVERSAMPLING)
+        .map(|phase| interpolation_kernel(phase as f64 / OVERSAMPLING as f64))
+        .collect();
+    (0..samples.len()).map(move |i| {
+        kernels
+            .iter()
+            .map(|kernel| {
+                let mut value = 0.0;
+                for (tap, weight) in (1 - INTERPOLATION_TAPS..=INTERPOLATION_TAPS).zip(kernel) {
+                    if let Some(sample) = samples.get((i as isize + tap) as usize) {
+                        value += sample * weight;
+                    }
+                }
+                value.abs()
+            })
+            .fold(samples[i].abs(), f64::max)
+    })
+}
+
+/// Hann-windowed sinc weights of the samples around a point `offset` past
+/// a sample
+fn interpolation_kernel(offset: f64) -> Vec<f64> {
+    let half_width = INTERPOLATION_TAPS as f64;
+    (1 - INTERPOLATION_TAPS..=INTERPOLATION_TAPS)
+        .map(|tap| {
+            let x = offset - tap as f64;
+            let sinc = if x == 0.0 {
+                1.0
+            } else {
+                (PI * x).sin() / (PI * x)
+            };
+            let window = 0.5 + 0.5 * (PI * x / half_width).cos();
+            sinc * window
+        })
+        .collect()
+}
+
+/// Second-order IIR filter in direct form I
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self {
+            b: b.map(|b| b / a[0]),
+            a: [a[1] / a[0], a[2] / a[0]],
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    /// Shelf of the K-weighting filter, whose coefficients at 48 kHz are
+    /// those given in BS.1770
+    fn high_shelf(sample_rate: u32) -> Self {
+        let k = (PI * 1681.974450955533 / sample_rate as f64).tan();
+        let q = 0.7071752369554196;
+        let vh = 10f64.powf(3.999843853973347 / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        Self::new(
+            [
+                vh + vb * k / q + k * k,
+                2.0 * (k * k - vh),
+                vh - vb * k / q + k * k,
+            ],
+            [
+                1.0 + k / q + k * k,
+                2.0 * (k * k - 1.0),
+                1.0 - k / q + k * k,
+            ],
+        )
+    }
+
+    /// High pass of the K-weighting filter, whose coefficients at 48 kHz are
+    /// those given in BS.1770
+    fn high_pass(sample_rate: u32) -> Self {
+        let k = (PI * 38.13547087602444 / sample_rate as f64).tan();
+        let q = 0.5003270373238773;
+        Self::new(
+            [1.0, -2.0, 1.0],
+            [
+                1.0 + k / q + k * k,
+                2.0 * (k * k - 1.0),
+                1.0 - k / q + k * k,
+            ],
+        )
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f64, amplitude: f64, sample_rate: u32, seconds: f64) -> Vec<i16> {
+        let frames = (sample_rate as f64 * seconds) as usize;
+        (0..frames)
+            .map(|i| {
+                let t = i as f64 / sample_rate as f64;
+                (amplitude * 32767.0 * (2.0 * PI * frequency * t).sin()).round() as i16
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sine_loudness_matches_reference() {
+        // A full-scale 997 Hz sine in one channel reads -3.01 LUFS
+        for sample_rate in [24000, 48000] {
+            let samples = sine(997.0, 1.0, sample_rate, 2.0);
+            let measured = integrated_loudness(&samples, 1, sample_rate).unwrap();
+            assert!(
+                (measured + 3.01).abs() < 0.1,
+                "{} Hz: {}",
+                sample_rate,
+                measured
+            );
+
+            let stereo: Vec<i16> = samples.iter().flat_map(|&s| [s, s]).collect();
+            let measured = integrated_loudness(&stereo, 2, sample_rate).unwrap();
+            assert!(
+                (measured - 0.0).abs() < 0.1,
+                "{} Hz stereo: {}",
+                sample_rate,
+                measured
+            );
+        }
+
+        assert_eq!(integrated_loudness(&[0; 48000], 1, 48000), None);
+        assert_eq!(
+            integrated_loudness(&sine(997.0, 1.0, 48000, 0.3), 1, 48000),
+            None
+        );
+    }
+
+    #[test]
+    fn test_true_peak_finds_inter_sample_peaks() {
+        // A quarter-rate sine sampled at ±45° never hits its peak
+        let samples: Vec<i16> = (0..4800)
+            .map(|i| (0.5 * 32767.0 * (PI / 2.0 * i as f64 + PI / 4.0).sin()).round() as i16)
+            .collect();
+        let sample_peak = 20.0 * (*samples.iter().max().unwrap() as f64 / 32768.0).log10();
+        let peak = true_peak(&samples, 1);
+        assert!(peak > sample_peak + 2.5, "{} vs {}", peak, sample_peak);
+        assert!((peak - 20.0 * 0.5f64.log10()).abs() < 0.2);
+        assert_eq!(true_peak(&[0; 100], 1), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_normalize_reaches_target_under_ceiling() {
+        let mut quiet = sine(440.0, 0.05, 24000, 3.0);
+        let gain = normalize(&mut quiet, 1, 24000, -16.0).unwrap();
+        assert!(gain > 0.0);
+        let measured = integrated_loudness(&quiet, 1, 24000).unwrap();
+        assert!((measured + 16.0).abs() < 0.1, "{}", measured);
+
+        // Reaching -3 LUFS would push peaks over the ceiling
+        let mut loud = sine(440.0, 0.5, 24000, 3.0);
+        normalize(&mut loud, 1, 24000, -3.0).unwrap();
+        assert!(true_peak(&loud, 1) <= TRUE_PEAK_CEILING + 0.1);
+        assert!(integrated_loudness(&loud, 1, 24000).unwrap() > -6.0);
+
+        let mut silence = vec![0; 24000];
+        assert_eq!(normalize(&mut silence, 1, 24000, -16.0), None);
+        assert!(silence.iter().all(|&s| s == 0));
+    }
+}
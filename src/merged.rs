@@ -0,0 +1,246 @@
+//! Merged audio of a conversion, convertible between bytes, files and PCM
+
+use crate::audio_merger::{probe_bytes, PcmAudio, WavInfo};
+use crate::digest::{write_hashed, OutputDigest};
+use crate::encode::{encode, OutputFormat};
+use crate::error::Result;
+use crate::report::ConversionReport;
+use hound::{SampleFormat, WavSpec};
+use std::ops::Range;
+use std::path::Path;
+use std::time::Duration;
+
+/// Final WAV audio of a conversion with its format and segment timings
+///
+/// Returned by
+/// [`Text2Audio::convert_to_audio`](crate::Text2Audio::convert_to_audio),
+/// [`Text2Audio::convert_with_audio`](crate::Text2Audio::convert_with_audio)
+/// and
+/// [`IncrementalMerger::finalize_audio`](crate::IncrementalMerger::finalize_audio),
+/// or loaded from WAV bytes or a file. The WAV bytes are kept exactly as
+/// written, extra chunks included, so exporting them to bytes or a file is
+/// lossless. Once loaded, encoding to another format works from memory.
+///
+/// `convert_with_audio` keeps the merged audio it wrote the output from.
+/// `finalize_audio` streams to a file to bound memory, so it reads the
+/// finished file back once.
+///
+/// # Examples
+///
+/// ```no_run
+/// use text2audio::{MergedAudio, OutputFormat};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let audio = MergedAudio::from_file("output.wav")?;
+/// let mp3 = audio.encode(OutputFormat::Mp3 { bitrate_kbps: 64 })?;
+/// let wav: Vec<u8> = audio.into();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergedAudio {
+    wav: Vec<u8>,
+    info: WavInfo,
+    segments: Vec<Range<Duration>>,
+}
+
+impl MergedAudio {
+    /// Take ownership of WAV bytes
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Audio`](crate::Error::Audio) if the bytes are not a
+    /// readable WAV file.
+    pub fn from_wav_bytes(wav: Vec<u8>) -> Result<Self> {
+        let info = probe_bytes(&wav)?;
+        Ok(Self {
+            wav,
+            info,
+            segments: Vec::new(),
+        })
+    }
+
+    /// Load a WAV file
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the file cannot be read or is not a readable WAV
+    /// file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_wav_bytes(std::fs::read(path)?)
+    }
+
+    /// Encode decoded samples as 16-bit WAV
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the audio has no channels or WAV encoding fails.
+    pub fn from_pcm(pcm: &PcmAudio) -> Result<Self> {
+        let spec = WavSpec {
+            channels: pcm.channels(),
+            sample_rate: pcm.sample_rate(),
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        Self::from_wav_bytes(pcm.to_wav(spec)?)
+    }
+
+    /// Record each segment's span from a conversion report
+    pub(crate) fn with_segments(mut self, report: &ConversionReport) -> Self {
+        self.segments = report
+            .segments
+            .iter()
+            .map(|segment| segment.start..segment.start + segment.duration)
+            .collect();
+        self
+    }
+
+    /// Sample rate, channels, bit depth and sample format
+    pub fn spec(&self) -> WavSpec {
+        self.info.spec
+    }
+
+    /// Playback duration
+    pub fn duration(&self) -> Duration {
+        self.info.duration
+    }
+
+    /// Span of each segment's audio in merge order, excluding the pause
+    /// after it; empty for audio loaded from bytes or a file
+    pub fn segments(&self) -> &[Range<Duration>] {
+        &self.segments
+    }
+
+    /// The WAV bytes
+    pub fn as_wav_bytes(&self) -> &[u8] {
+        &self.wav
+    }
+
+    /// A copy of the WAV bytes
+    pub fn to_wav_bytes(&self) -> Vec<u8> {
+        self.wav.clone()
+    }
+
+    /// Write the WAV bytes to `path`, returning the digest of the file
+    ///
+    /// # Errors
+    ///
+    /// Returns error if file I/O fails.
+    pub fn write_to(&self, path: impl AsRef<Path>) -> Result<OutputDigest> {
+        let path = path.as_ref().to_string_lossy();
+        Ok(write_hashed(&path, &self.wav)?)
+    }
+
+    /// Encode the audio in `format`
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the format cannot be encoded, such as MP3 without
+    /// the `mp3` feature.
+    pub fn encode(&self, format: OutputFormat) -> Result<Vec<u8>> {
+        Ok(encode(&self.wav, format)?.into_owned())
+    }
+
+    /// Decode the samples
+    ///
+    /// Lossless for 16-bit integer audio, which the TTS service returns;
+    /// other bit depths are scaled to 16 bits as described for
+    /// [`PcmAudio`], and extra chunks are not kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the sample data cannot be decoded.
+    pub fn into_pcm(self) -> Result<PcmAudio> {
+        PcmAudio::from_wav(&self.wav, 0)
+    }
+}
+
+impl TryFrom<Vec<u8>> for MergedAudio {
+    type Error = crate::Error;
+
+    fn try_from(wav: Vec<u8>) -> Result<Self> {
+        Self::from_wav_bytes(wav)
+    }
+}
+
+impl TryFrom<&Path> for MergedAudio {
+    type Error = crate::Error;
+
+    fn try_from(path: &Path) -> Result<Self> {
+        Self::from_file(path)
+    }
+}
+
+impl TryFrom<&PcmAudio> for MergedAudio {
+    type Error = crate::Error;
+
+    fn try_from(pcm: &PcmAudio) -> Result<Self> {
+        Self::from_pcm(pcm)
+    }
+}
+
+impl From<MergedAudio> for Vec<u8> {
+    fn from(audio: MergedAudio) -> Self {
+        audio.wav
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_merger::IncrementalMerger;
+    use crate::digest::digest_file;
+    use crate::test_util::{temp_path, wav_bytes, wav_with_extra_chunks};
+
+    #[test]
+    fn test_bytes_and_file_round_trips() {
+        for wav in [
+            wav_bytes(&[1, -2, 3, -4], 24000, 2),
+            wav_with_extra_chunks(),
+        ] {
+            let audio = MergedAudio::try_from(wav.clone()).unwrap();
+            assert_eq!(audio.as_wav_bytes(), wav.as_slice());
+            assert_eq!(audio.to_wav_bytes(), wav);
+
+            let path = temp_path("merged_round_trip.wav");
+            let written = audio.write_to(&path).unwrap();
+            assert_eq!(written, digest_file(&path).unwrap());
+            let loaded = MergedAudio::try_from(Path::new(&path)).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(loaded, audio);
+            assert_eq!(Vec::from(loaded), wav);
+        }
+
+        assert!(MergedAudio::from_wav_bytes(b"not a wav".to_vec()).is_err());
+        assert!(MergedAudio::from_file(temp_path("merged_missing.wav")).is_err());
+    }
+
+    #[test]
+    fn test_pcm_round_trips() {
+        let wav = wav_bytes(&[1, -2, 3, -4, i16::MAX, i16::MIN], 24000, 2);
+        let audio = MergedAudio::from_wav_bytes(wav.clone()).unwrap();
+        assert_eq!(audio.spec().channels, 2);
+        assert_eq!(audio.duration(), Duration::from_micros(125));
+
+        let pcm = audio.into_pcm().unwrap();
+        assert_eq!(pcm.samples(), &[1, -2, 3, -4, i16::MAX, i16::MIN]);
+        let encoded = MergedAudio::try_from(&pcm).unwrap();
+        assert_eq!(encoded.to_wav_bytes(), wav);
+        assert_eq!(encoded.into_pcm().unwrap(), pcm);
+    }
+
+    #[test]
+    fn test_incremental_merger_finalizes_to_audio() {
+        let path = temp_path("merged_incremental.wav");
+        let mut merger = IncrementalMerger::new(path.clone());
+        merger.append(&wav_bytes(&[1, 2], 24000, 1)).unwrap();
+        merger.append(&wav_bytes(&[3], 24000, 1)).unwrap();
+        let (audio, warnings) = merger.finalize_audio().unwrap();
+        let file = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(warnings.is_empty());
+        assert_eq!(audio.to_wav_bytes(), file);
+        assert_eq!(audio.into_pcm().unwrap().samples(), &[1, 2, 3]);
+    }
+}
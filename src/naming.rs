@@ -0,0 +1,334 @@
+//! File names for per-segment output, for output that already exists, and
+//! checks that outputs do not overwrite inputs and can be written
+
+use crate::error::{Error, Result};
+use crate::paths::OutputTemplate;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Longest slug in a segment file name, in characters
+pub const MAX_SLUG_CHARS: usize = 20;
+
+/// How [`convert_to_files`](crate::Text2Audio::convert_to_files) names the
+/// file of each segment
+///
+/// Set with [`Text2Audio::with_file_naming`](crate::Text2Audio::with_file_naming).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileNaming {
+    /// Zero-padded segment number, such as `001.wav`
+    #[default]
+    Index,
+    /// Slug of the segment's opening words, such as `人工智能技术.wav`
+    ///
+    /// Segments without a usable slug fall back to their number, and a slug
+    /// already taken by an earlier segment gets the number appended.
+    Slug,
+    /// Number and slug, such as `001_人工智能技术.wav`
+    IndexAndSlug,
+}
+
+impl From<FileNaming> for OutputTemplate {
+    fn from(naming: FileNaming) -> Self {
+        let template = match naming {
+            FileNaming::Index => "{index}",
+            FileNaming::Slug => "{slug}",
+            FileNaming::IndexAndSlug => "{index}_{slug}",
+        };
+        OutputTemplate::new(template).expect("built-in templates are valid")
+    }
+}
+
+/// What to do when the output file of a conversion already exists
+///
+/// Set with [`Text2Audio::with_overwrite_policy`](crate::Text2Audio::with_overwrite_policy).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Replace the existing file
+    #[default]
+    Overwrite,
+    /// Fail with an [`Error::Io`](crate::Error::Io) of kind
+    /// [`AlreadyExists`](std::io::ErrorKind::AlreadyExists) before any work
+    Error,
+    /// Return without converting, leaving the existing file as is
+    Skip,
+    /// Write to the first free path numbered like `output (1).wav`
+    Rename,
+}
+
+/// First path numbered like `output (1).wav` next to `path` that does not
+/// exist yet
+pub(crate) fn numbered_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy());
+    (1..)
+        .map(|n| {
+            let name = match &extension {
+                Some(extension) => format!("{} ({}).{}", stem, n, extension),
+                None => format!("{} ({})", stem, n),
+            };
+            path.with_file_name(name)
+        })
+        .find(|candidate| !candidate.exists())
+        .expect("some numbered path is free")
+}
+
+/// Absolute path of `path` with `..` and symlinks resolved, also for a file
+/// that does not exist yet
+fn resolved(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    match (parent.canonicalize(), path.file_name()) {
+        (Ok(parent), Some(name)) => parent.join(name),
+        _ => std::env::current_dir()
+            .map(|dir| dir.join(path))
+            .unwrap_or_else(|_| path.to_path_buf()),
+    }
+}
+
+/// Check that no output of an operation overwrites one of its inputs or
+/// another of its outputs, before anything is written
+///
+/// Paths are compared after resolving them, and a path inside a directory
+/// overlaps the directory.
+///
+/// # Errors
+///
+/// Returns [`Error::Config`] naming the first two overlapping paths.
+pub(crate) fn check_path_conflicts<I, O>(inputs: &[I], outputs: &[O]) -> Result<()>
+where
+    I: AsRef<Path>,
+    O: AsRef<Path>,
+{
+    let overlap = |a: &Path, b: &Path| a.starts_with(b) || b.starts_with(a);
+    let inputs: Vec<(&Path, PathBuf)> = inputs
+        .iter()
+        .map(|input| (input.as_ref(), resolved(input.as_ref())))
+        .collect();
+    let mut written: Vec<(&Path, PathBuf)> = Vec::with_capacity(outputs.len());
+    for output in outputs {
+        let output = output.as_ref();
+        let resolved_output = resolved(output);
+        if let Some((input, _)) = inputs
+            .iter()
+            .find(|(_, input)| overlap(&resolved_output, input))
+        {
+            return Err(Error::Config(format!(
+                "output {} overlaps input {}",
+                output.display(),
+                input.display()
+            )));
+        }
+        if let Some((other, _)) = written
+            .iter()
+            .find(|(_, other)| overlap(&resolved_output, other))
+        {
+            return Err(Error::Config(format!(
+                "output {} overlaps output {}",
+                output.display(),
+                other.display()
+            )));
+        }
+        written.push((output, resolved_output));
+    }
+    Ok(())
+}
+
+/// Probes created by this process so far, keeping concurrent probes of the
+/// same output apart
+static PREFLIGHT_PROBES: AtomicU64 = AtomicU64::new(0);
+
+/// Check that `output` can be written, before any work is done
+///
+/// Creates and removes a probe file next to the output, so a bad location
+/// fails in an instant with a hint rather than deep in the merge.
+///
+/// # Errors
+///
+/// Returns [`Error::Config`] if `output` is a directory or its parent is
+/// not one, and [`Error::Io`] naming the directory if it does not exist or
+/// cannot be written to.
+pub(crate) fn preflight_output(output: &Path) -> Result<()> {
+    if output.is_dir() {
+        return Err(Error::Config(format!(
+            "output {} is a directory; pass a file path",
+            output.display()
+        )));
+    }
+    let parent = match output.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    if parent.exists() && !parent.is_dir() {
+        return Err(Error::Config(format!(
+            "output {} is inside {}, which is not a directory",
+            output.display(),
+            parent.display()
+        )));
+    }
+    let with_hint = |e: std::io::Error, action: &str| {
+        let hint = match e.kind() {
+            std::io::ErrorKind::PermissionDenied => "no write permission".to_string(),
+            std::io::ErrorKind::NotFound => "no such directory".to_string(),
+            _ => e.to_string(),
+        };
+        Error::Io(std::io::Error::new(
+            e.kind(),
+            format!("cannot {} {}: {}", action, parent.display(), hint),
+        ))
+    };
+
+    let name = output.file_name().unwrap_or_default().to_string_lossy();
+    let probe = parent.join(format!(
+        ".{}.{}.{}.preflight",
+        name,
+        std::process::id(),
+        PREFLIGHT_PROBES.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .map_err(|e| with_hint(e, "write to"))?;
+    std::fs::remove_file(&probe).map_err(|e| with_hint(e, "remove a probe file in"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::TemplateValues;
+
+    #[test]
+    fn test_numbered_path() {
+        let dir = std::env::temp_dir().join(format!("numbered_path_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("book.wav");
+        assert_eq!(numbered_path(&output), dir.join("book (1).wav"));
+        std::fs::write(dir.join("book (1).wav"), b"").unwrap();
+        assert_eq!(numbered_path(&output), dir.join("book (2).wav"));
+        assert_eq!(numbered_path(&dir.join("book")), dir.join("book (1)"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_path_conflicts() {
+        let dir = std::env::temp_dir().join(format!("path_conflicts_{}", std::process::id()));
+        let audio_dir = dir.join("book.audio");
+        std::fs::create_dir_all(&audio_dir).unwrap();
+        let plan = dir.join("book.plan.json");
+        std::fs::write(&plan, b"{}").unwrap();
+
+        let none: [&Path; 0] = [];
+        assert!(check_path_conflicts(&[&plan], &[dir.join("book.wav")]).is_ok());
+        assert!(check_path_conflicts(&none, &[dir.join("a.wav"), dir.join("b.wav")]).is_ok());
+
+        // The same file through a different spelling
+        let respelled = audio_dir.join("..").join("book.plan.json");
+        let error = check_path_conflicts(&[&plan], &[&respelled]).unwrap_err();
+        assert!(matches!(&error, Error::Config(m) if m.contains("book.plan.json")));
+        // A file inside an input directory, and a directory around an input
+        assert!(check_path_conflicts(&[&audio_dir], &[audio_dir.join("0.wav")]).is_err());
+        assert!(check_path_conflicts(&[audio_dir.join("0.wav")], &[&dir]).is_err());
+        // Two outputs in one operation
+        let output = dir.join("out.wav");
+        let error = check_path_conflicts(&none, &[&output, &output]).unwrap_err();
+        assert!(matches!(&error, Error::Config(m) if m.contains("overlaps output")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_preflight_output() {
+        let dir = std::env::temp_dir().join(format!("preflight_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("output")).unwrap();
+
+        let error = preflight_output(&dir.join("output")).unwrap_err();
+        assert!(
+            matches!(&error, Error::Config(m) if m.ends_with("is a directory; pass a file path"))
+        );
+
+        // A missing parent is reported, not created
+        let nested = dir.join("a").join("b").join("out.wav");
+        let error = preflight_output(&nested).unwrap_err();
+        assert!(matches!(&error, Error::Io(e) if e.kind() == std::io::ErrorKind::NotFound));
+        assert!(!dir.join("a").exists());
+
+        // Concurrent checks of one output do not collide, and no probe file
+        // is left behind
+        let output = dir.join("output").join("out.wav");
+        std::thread::scope(|scope| {
+            let checks: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| preflight_output(&output)))
+                .collect();
+            for check in checks {
+                check.join().unwrap().unwrap();
+            }
+        });
+        assert_eq!(std::fs::read_dir(dir.join("output")).unwrap().count(), 0);
+
+        std::fs::write(dir.join("file"), b"").unwrap();
+        let error = preflight_output(&dir.join("file").join("out.wav")).unwrap_err();
+        assert!(matches!(&error, Error::Config(m) if m.contains("not a directory")));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let read_only = dir.join("read_only");
+            std::fs::create_dir(&read_only).unwrap();
+            std::fs::set_permissions(&read_only, std::fs::Permissions::from_mode(0o555)).unwrap();
+            // Privileged users write anyway, leaving nothing to check
+            if std::fs::write(read_only.join("probe"), b"").is_err() {
+                let error = preflight_output(&read_only.join("out.wav")).unwrap_err();
+                assert!(
+                    matches!(&error, Error::Io(e) if e.kind() == std::io::ErrorKind::PermissionDenied)
+                );
+                assert!(error.to_string().contains("no write permission"));
+            }
+            std::fs::set_permissions(&read_only, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_naming_templates() {
+        let segments = ["第一章。", "第一章。", "……", "Intro", "intro"];
+        let names = |naming: FileNaming, extension: &str| {
+            OutputTemplate::from(naming).file_names(
+                &segments,
+                &TemplateValues::default(),
+                extension,
+            )
+        };
+        assert_eq!(
+            names(FileNaming::Index, "wav"),
+            ["001.wav", "002.wav", "003.wav", "004.wav", "005.wav"]
+        );
+        assert_eq!(
+            names(FileNaming::Slug, "mp3"),
+            [
+                "第一章.mp3",
+                "第一章_002.mp3",
+                "003.mp3",
+                "Intro.mp3",
+                "intro_005.mp3"
+            ]
+        );
+        assert_eq!(
+            names(FileNaming::IndexAndSlug, "wav"),
+            [
+                "001_第一章.wav",
+                "002_第一章.wav",
+                "003.wav",
+                "004_Intro.wav",
+                "005_intro.wav"
+            ]
+        );
+    }
+}
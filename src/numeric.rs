@@ -0,0 +1,916 @@
+//! Locale-aware readings of dates, times, phone numbers and other numbers
+
+use crate::preprocess::{code_spans, is_cjk, SymbolLanguage};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+/// Chinese digits, indexed by value
+const ZH_DIGITS: [char; 10] = ['零', '一', '二', '三', '四', '五', '六', '七', '八', '九'];
+
+/// English numbers below twenty, indexed by value
+const EN_ONES: [&str; 20] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+
+/// English multiples of ten, indexed by tens digit
+const EN_TENS: [&str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+
+/// English month names, indexed by month minus one
+const EN_MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Longest day of each month, indexed by month minus one
+const MONTH_DAYS: [u32; 12] = [31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+/// Order of day and month in dates such as "3/4/2024"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DateOrder {
+    /// Month first, as in the US: "3/4/2024" is March 4
+    MonthDay,
+    /// Day first, as in most of Europe: "3/4/2024" is 3 April
+    DayMonth,
+}
+
+/// How "a/b" is read when it could be both a date and a fraction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SlashReading {
+    /// Read it as a date in the [date order](DateOrder)
+    Date,
+    /// Read it as a fraction
+    Fraction,
+}
+
+/// Character between the integer and fractional parts of a number
+///
+/// The other of `.` and `,` groups thousands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DecimalSeparator {
+    /// "3.14", with thousands grouped as "1,000"
+    Point,
+    /// "3,14", with thousands grouped as "1.000"
+    Comma,
+}
+
+/// How numbers in the text are read aloud
+///
+/// Set with
+/// [`Text2Audio::with_numeric_profile`](crate::Text2Audio::with_numeric_profile).
+/// Start from a built-in profile and override single fields:
+///
+/// ```
+/// use text2audio::numeric::{NumericContext, SlashReading};
+///
+/// // A Chinese calendar where "3/4" means March 4
+/// let context = NumericContext::zh_cn().with_slash_reading(SlashReading::Date);
+/// ```
+///
+/// Dates ("2024-03-04", "3/4/2024"), times ("15:30", "3:30 pm"), phone
+/// numbers ("138-0013-8000", "(555) 123-4567"), fractions, decimals and
+/// numbers with grouped thousands are spelled out; other numbers are left
+/// to the TTS service. Numbers touching letters, such as "v1.2" or "A4",
+/// and numbers inside URLs or code spans are kept as written.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NumericContext {
+    /// Language of the readings
+    pub language: SymbolLanguage,
+    /// Order of day and month in "a/b/yyyy" dates and dates read from "a/b"
+    pub date_order: DateOrder,
+    /// Reading of "a/b" when both a date and a fraction make sense
+    pub slash: SlashReading,
+    /// Digits in each group of a phone number written without separators;
+    /// a run of exactly as many digits as the groups hold is read digit by
+    /// digit
+    pub phone_groups: Vec<usize>,
+    /// Decimal separator
+    pub decimal: DecimalSeparator,
+}
+
+impl Default for NumericContext {
+    fn default() -> Self {
+        Self::zh_cn()
+    }
+}
+
+impl NumericContext {
+    /// Chinese readings for mainland China
+    ///
+    /// "3/4" is a fraction, "13800138000" a mobile number read in groups
+    /// of 3, 4 and 4, and the decimal separator a point.
+    pub fn zh_cn() -> Self {
+        Self {
+            language: SymbolLanguage::Chinese,
+            date_order: DateOrder::MonthDay,
+            slash: SlashReading::Fraction,
+            phone_groups: vec![3, 4, 4],
+            decimal: DecimalSeparator::Point,
+        }
+    }
+
+    /// English readings for the United States
+    ///
+    /// "3/4" is March 4, "5551234567" a phone number read in groups of
+    /// 3, 3 and 4, and the decimal separator a point.
+    pub fn en_us() -> Self {
+        Self {
+            language: SymbolLanguage::English,
+            date_order: DateOrder::MonthDay,
+            slash: SlashReading::Date,
+            phone_groups: vec![3, 3, 4],
+            decimal: DecimalSeparator::Point,
+        }
+    }
+
+    /// Set the language of the readings
+    pub fn with_language(mut self, language: SymbolLanguage) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Set the order of day and month
+    pub fn with_date_order(mut self, order: DateOrder) -> Self {
+        self.date_order = order;
+        self
+    }
+
+    /// Set the reading of "a/b" when both a date and a fraction make sense
+    pub fn with_slash_reading(mut self, reading: SlashReading) -> Self {
+        self.slash = reading;
+        self
+    }
+
+    /// Set the digit groups of phone numbers written without separators
+    pub fn with_phone_groups(mut self, groups: &[usize]) -> Self {
+        self.phone_groups = groups.to_vec();
+        self
+    }
+
+    /// Set the decimal separator
+    pub fn with_decimal_separator(mut self, separator: DecimalSeparator) -> Self {
+        self.decimal = separator;
+        self
+    }
+
+    fn chinese(&self) -> bool {
+        self.language == SymbolLanguage::Chinese
+    }
+}
+
+/// Pattern of every number form with a reading, most specific first
+fn number_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(concat!(
+            r"(?P<iy>[0-9]{4})[-/](?P<im>[0-9]{1,2})[-/](?P<id>[0-9]{1,2})",
+            r"|(?P<phone>(?:\+[0-9]{1,3}[ -])?(?:\([0-9]{2,4}\) ?|[0-9]{2,4}[ -])[0-9]{3,4}[ -][0-9]{4})",
+            r"|(?P<h>[0-9]{1,2}):(?P<mi>[0-9]{2})(?::(?P<s>[0-9]{2}))?(?: ?(?P<ampm>[AaPp]\.?[Mm]\.?))?",
+            r"|(?P<da>[0-9]{1,2})/(?P<db>[0-9]{1,2})/(?P<dy>[0-9]{4})",
+            r"|(?P<num>[0-9]{1,3})/(?P<den>[0-9]{1,3})",
+            r"|(?P<number>[0-9]+(?:[.,][0-9]+)+)",
+            r"|(?P<digits>[0-9]+)",
+        ))
+        .expect("number pattern is valid")
+    })
+}
+
+/// Replace numbers with readings under `context`
+///
+/// Numbers that could be read more than one way are read as `context`
+/// prefers and pushed to `ambiguous` as written. Text without any digit is
+/// borrowed.
+pub(crate) fn normalize_numbers<'a>(
+    text: &'a str,
+    context: &NumericContext,
+    ambiguous: &mut Vec<String>,
+) -> Cow<'a, str> {
+    if !text.chars().any(|c| c.is_ascii_digit()) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut output = String::with_capacity(text.len() * 2);
+    for (is_code, span) in code_spans(text) {
+        if is_code {
+            output.push_str(span);
+        } else {
+            normalize_span(span, context, ambiguous, &mut output);
+        }
+    }
+    Cow::Owned(output)
+}
+
+fn normalize_span(
+    text: &str,
+    context: &NumericContext,
+    ambiguous: &mut Vec<String>,
+    output: &mut String,
+) {
+    let mut copied = 0;
+    for captures in number_pattern().captures_iter(text) {
+        let found = captures.get(0).expect("group 0 always matches");
+        if !standalone(text, found.start(), found.end()) {
+            continue;
+        }
+        if let Some(reading) = reading(&captures, context, ambiguous) {
+            output.push_str(&text[copied..found.start()]);
+            output.push_str(&reading);
+            copied = found.end();
+        }
+    }
+    output.push_str(&text[copied..]);
+}
+
+/// Whether `text[start..end]` is a number of its own, not part of a word,
+/// version, URL or longer number
+fn standalone(text: &str, start: usize, end: usize) -> bool {
+    let word = |c: char| c.is_alphanumeric() && !is_cjk(c);
+    let before = text[..start].chars().next_back();
+    if before.is_some_and(|c| word(c) || "/:._@#-+,".contains(c)) {
+        return false;
+    }
+    let mut after = text[end..].chars();
+    match after.next() {
+        Some(c) if word(c) || "/:_@#".contains(c) => return false,
+        Some('.' | ',' | '-') if after.next().is_some_and(|c| c.is_ascii_digit()) => return false,
+        _ => {}
+    }
+    // Anything in a URL is kept as written
+    let token_start = text[..start]
+        .rfind(char::is_whitespace)
+        .map_or(0, |idx| idx + 1);
+    let token_end = text[end..]
+        .find(char::is_whitespace)
+        .map_or(text.len(), |idx| end + idx);
+    let token = &text[token_start..token_end];
+    !(token.contains("://") || token.starts_with("www."))
+}
+
+/// Reading of a matched number, or `None` to keep it as written
+fn reading(
+    captures: &Captures<'_>,
+    context: &NumericContext,
+    ambiguous: &mut Vec<String>,
+) -> Option<String> {
+    let value = |name: &str| captures.name(name).map(|m| m.as_str());
+    let int = |name: &str| value(name).and_then(|s| s.parse::<u32>().ok());
+    let written = || captures[0].to_string();
+
+    if let (Some(year), Some(month), Some(day)) = (value("iy"), int("im"), int("id")) {
+        return valid_date(month, day).then(|| date_reading(Some(year), month, day, context));
+    }
+    if let Some(phone) = value("phone") {
+        let groups: Vec<&str> = phone
+            .split([' ', '-', '(', ')'])
+            .filter(|group| !group.is_empty())
+            .collect();
+        return Some(phone_reading(&groups, context));
+    }
+    if let (Some(hour), Some(minute)) = (int("h"), int("mi")) {
+        return time_reading(hour, minute, int("s"), value("ampm"), context);
+    }
+    if let (Some(a), Some(b), Some(year)) = (int("da"), int("db"), value("dy")) {
+        // A date only valid in the other order is read in that order
+        let (month, day) =
+            ordered(a, b, context.date_order).or_else(|| ordered(b, a, context.date_order))?;
+        return Some(date_reading(Some(year), month, day, context));
+    }
+    if let (Some(a), Some(b)) = (int("num"), int("den")) {
+        let date = ordered(a, b, context.date_order);
+        let fraction = b > 0;
+        if date.is_some() && fraction {
+            ambiguous.push(written());
+        }
+        return match date {
+            Some((month, day)) if !fraction || context.slash == SlashReading::Date => {
+                Some(date_reading(None, month, day, context))
+            }
+            _ if fraction => Some(fraction_reading(a, b, context)),
+            _ => None,
+        };
+    }
+    if let Some(number) = value("number") {
+        let (reading, uncertain) = decimal_reading(number, context)?;
+        if uncertain {
+            ambiguous.push(written());
+        }
+        return Some(reading);
+    }
+    let digits = value("digits")?;
+    let phone_digits: usize = context.phone_groups.iter().sum();
+    if phone_digits == 0 || digits.len() != phone_digits {
+        return None;
+    }
+    let mut groups = Vec::new();
+    let mut rest = digits;
+    for &len in &context.phone_groups {
+        let (group, tail) = rest.split_at(len);
+        groups.push(group);
+        rest = tail;
+    }
+    Some(phone_reading(&groups, context))
+}
+
+/// `(month, day)` of "a/b" in `order`, if it is a valid date
+fn ordered(a: u32, b: u32, order: DateOrder) -> Option<(u32, u32)> {
+    let (month, day) = match order {
+        DateOrder::MonthDay => (a, b),
+        DateOrder::DayMonth => (b, a),
+    };
+    valid_date(month, day).then_some((month, day))
+}
+
+fn valid_date(month: u32, day: u32) -> bool {
+    (1..=12).contains(&month) && day >= 1 && day <= MONTH_DAYS[month as usize - 1]
+}
+
+fn date_reading(year: Option<&str>, month: u32, day: u32, context: &NumericContext) -> String {
+    if context.chinese() {
+        let mut reading = String::new();
+        if let Some(year) = year {
+            reading.extend(year.bytes().map(|b| ZH_DIGITS[(b - b'0') as usize]));
+            reading.push('年');
+        }
+        format!(
+            "{}{}月{}日",
+            reading,
+            zh_cardinal(month.into()),
+            zh_cardinal(day.into())
+        )
+    } else {
+        let mut reading = format!(
+            "{} {}",
+            EN_MONTHS[month as usize - 1],
+            en_ordinal(day.into())
+        );
+        if let Some(year) = year.and_then(|year| year.parse().ok()) {
+            reading.push_str(", ");
+            reading.push_str(&en_year(year));
+        }
+        reading
+    }
+}
+
+fn time_reading(
+    hour: u32,
+    minute: u32,
+    second: Option<u32>,
+    ampm: Option<&str>,
+    context: &NumericContext,
+) -> Option<String> {
+    let pm = ampm.map(|ampm| ampm.starts_with(['p', 'P']));
+    let hours = if pm.is_some() { 1..=12 } else { 0..=23 };
+    if !hours.contains(&hour) || minute > 59 || second.is_some_and(|s| s > 59) {
+        return None;
+    }
+
+    if context.chinese() {
+        let mut reading = match pm {
+            Some(true) => "下午".to_string(),
+            Some(false) => "上午".to_string(),
+            None => String::new(),
+        };
+        match hour {
+            2 => reading.push('两'),
+            _ => reading.push_str(&zh_cardinal(hour.into())),
+        }
+        reading.push('点');
+        match (minute, second) {
+            (0, None) => reading.push('整'),
+            _ => {
+                if minute < 10 {
+                    reading.push('零');
+                }
+                reading.push_str(&zh_cardinal(minute.into()));
+                reading.push('分');
+            }
+        }
+        if let Some(second) = second {
+            reading.push_str(&zh_cardinal(second.into()));
+            reading.push('秒');
+        }
+        Some(reading)
+    } else {
+        let mut reading = en_cardinal(hour.into());
+        match minute {
+            0 if pm.is_none() && second.is_none() => reading.push_str(" o'clock"),
+            0 => {}
+            1..=9 => {
+                reading.push_str(" oh ");
+                reading.push_str(EN_ONES[minute as usize]);
+            }
+            _ => {
+                reading.push(' ');
+                reading.push_str(&en_cardinal(minute.into()));
+            }
+        }
+        if let Some(second) = second {
+            let unit = if second == 1 { "second" } else { "seconds" };
+            reading.push_str(&format!(" and {} {}", en_cardinal(second.into()), unit));
+        }
+        match pm {
+            Some(true) => reading.push_str(" p m"),
+            Some(false) => reading.push_str(" a m"),
+            None => {}
+        }
+        Some(reading)
+    }
+}
+
+fn fraction_reading(numerator: u32, denominator: u32, context: &NumericContext) -> String {
+    if context.chinese() {
+        return format!(
+            "{}分之{}",
+            zh_cardinal(denominator.into()),
+            zh_cardinal(numerator.into())
+        );
+    }
+    let plural = numerator != 1;
+    let denominator = match (denominator, plural) {
+        (1, _) => return format!("{} over one", en_cardinal(numerator.into())),
+        (2, false) => "half".to_string(),
+        (2, true) => "halves".to_string(),
+        (4, false) => "quarter".to_string(),
+        (4, true) => "quarters".to_string(),
+        (n, false) => en_ordinal(n.into()),
+        (n, true) => en_ordinal(n.into()) + "s",
+    };
+    format!("{} {}", en_cardinal(numerator.into()), denominator)
+}
+
+/// Reading of a number with a decimal or thousands separator, and whether
+/// it could also have been meant with the separators swapped
+fn decimal_reading(number: &str, context: &NumericContext) -> Option<(String, bool)> {
+    let (decimal, group) = match context.decimal {
+        DecimalSeparator::Point => ('.', ','),
+        DecimalSeparator::Comma => (',', '.'),
+    };
+    let (integer, fraction) = match number.split_once(decimal) {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (number, None),
+    };
+    if fraction.is_some_and(|fraction| fraction.contains([decimal, group])) {
+        return None;
+    }
+    let groups: Vec<&str> = integer.split(group).collect();
+    let grouped = groups.len() > 1;
+    if grouped && (groups[0].len() > 3 || groups[1..].iter().any(|group| group.len() != 3)) {
+        return None;
+    }
+    let value: u64 = groups.concat().parse().ok()?;
+    // "1.500" and "1,500" swap meanings between the two separators
+    let uncertain = match fraction {
+        Some(fraction) => !grouped && fraction.len() == 3 && integer.len() <= 3,
+        None => groups.len() == 2,
+    } && !integer.starts_with('0');
+
+    let mut reading = if context.chinese() {
+        zh_cardinal(value)
+    } else {
+        en_cardinal(value)
+    };
+    if let Some(fraction) = fraction {
+        if context.chinese() {
+            reading.push('点');
+            reading.extend(fraction.bytes().map(|b| ZH_DIGITS[(b - b'0') as usize]));
+        } else {
+            reading.push_str(" point");
+            for b in fraction.bytes() {
+                reading.push(' ');
+                reading.push_str(EN_ONES[(b - b'0') as usize]);
+            }
+        }
+    }
+    Some((reading, uncertain))
+}
+
+/// Digit-by-digit reading of a phone number's groups, with a pause
+/// between groups
+fn phone_reading(groups: &[&str], context: &NumericContext) -> String {
+    let read_group = |group: &str| -> String {
+        if context.chinese() {
+            group
+                .chars()
+                .filter_map(|c| match c {
+                    '+' => Some('加'),
+                    // "幺" keeps 1 apart from 7 over the phone
+                    '1' => Some('幺'),
+                    c => c.to_digit(10).map(|digit| ZH_DIGITS[digit as usize]),
+                })
+                .collect()
+        } else {
+            group
+                .chars()
+                .filter_map(|c| match c {
+                    '+' => Some("plus"),
+                    c => c.to_digit(10).map(|digit| EN_ONES[digit as usize]),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        }
+    };
+    let separator = if context.chinese() { " " } else { ", " };
+    groups
+        .iter()
+        .map(|group| read_group(group))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// Chinese reading of `n`, grouping digits by 万 and 亿
+fn zh_cardinal(n: u64) -> String {
+    const SECTION_UNITS: [&str; 5] = ["", "万", "亿", "万亿", "亿亿"];
+    if n == 0 {
+        return ZH_DIGITS[0].to_string();
+    }
+    let mut sections = Vec::new();
+    let mut rest = n;
+    while rest > 0 {
+        sections.push((rest % 10_000) as u32);
+        rest /= 10_000;
+    }
+
+    let mut reading = String::new();
+    let mut gap = false;
+    for (idx, &section) in sections.iter().enumerate().rev() {
+        if section == 0 {
+            gap = !reading.is_empty();
+            continue;
+        }
+        if !reading.is_empty() && (gap || section < 1000) {
+            reading.push('零');
+        }
+        reading.push_str(&zh_section(section));
+        reading.push_str(SECTION_UNITS[idx]);
+        gap = false;
+    }
+    // Ten to nineteen read "十…", not "一十…", at the start of a number
+    match reading.strip_prefix("一十") {
+        Some(rest) => format!("十{}", rest),
+        None => reading,
+    }
+}
+
+/// Chinese reading of `1..=9999`
+fn zh_section(n: u32) -> String {
+    const UNITS: [&str; 4] = ["千", "百", "十", ""];
+    let digits = [n / 1000, n / 100 % 10, n / 10 % 10, n % 10];
+    let mut reading = String::new();
+    let mut zero = false;
+    for (digit, unit) in digits.into_iter().zip(UNITS) {
+        if digit == 0 {
+            zero = !reading.is_empty();
+            continue;
+        }
+        if zero {
+            reading.push('零');
+            zero = false;
+        }
+        reading.push(ZH_DIGITS[digit as usize]);
+        reading.push_str(unit);
+    }
+    reading
+}
+
+/// English reading of `n`
+fn en_cardinal(n: u64) -> String {
+    const SCALES: [(u64, &str); 6] = [
+        (1_000_000_000_000_000_000, "quintillion"),
+        (1_000_000_000_000_000, "quadrillion"),
+        (1_000_000_000_000, "trillion"),
+        (1_000_000_000, "billion"),
+        (1_000_000, "million"),
+        (1_000, "thousand"),
+    ];
+    if n < 1000 {
+        return en_below_thousand(n as u32);
+    }
+    let mut words = Vec::new();
+    let mut rest = n;
+    for (scale, name) in SCALES {
+        if rest >= scale {
+            words.push(format!(
+                "{} {}",
+                en_below_thousand((rest / scale) as u32),
+                name
+            ));
+            rest %= scale;
+        }
+    }
+    if rest > 0 {
+        words.push(en_below_thousand(rest as u32));
+    }
+    words.join(" ")
+}
+
+fn en_below_thousand(n: u32) -> String {
+    let (hundreds, rest) = (n / 100, n % 100);
+    let tens = match rest {
+        0..=19 => EN_ONES[rest as usize].to_string(),
+        _ if rest % 10 == 0 => EN_TENS[(rest / 10) as usize].to_string(),
+        _ => format!(
+            "{}-{}",
+            EN_TENS[(rest / 10) as usize],
+            EN_ONES[(rest % 10) as usize]
+        ),
+    };
+    match (hundreds, rest) {
+        (0, _) => tens,
+        (_, 0) => format!("{} hundred", EN_ONES[hundreds as usize]),
+        _ => format!("{} hundred {}", EN_ONES[hundreds as usize], tens),
+    }
+}
+
+/// English ordinal of `n`, as in "twenty-first"
+fn en_ordinal(n: u64) -> String {
+    let cardinal = en_cardinal(n);
+    let split = cardinal.rfind([' ', '-']).map_or(0, |idx| idx + 1);
+    let (head, last) = cardinal.split_at(split);
+    let last = match last {
+        "one" => "first".to_string(),
+        "two" => "second".to_string(),
+        "three" => "third".to_string(),
+        "five" => "fifth".to_string(),
+        "eight" => "eighth".to_string(),
+        "nine" => "ninth".to_string(),
+        "twelve" => "twelfth".to_string(),
+        word if word.ends_with('y') => format!("{}ieth", &word[..word.len() - 1]),
+        word => format!("{}th", word),
+    };
+    format!("{}{}", head, last)
+}
+
+/// English reading of a year, as in "nineteen ninety-nine" or "two
+/// thousand five"
+fn en_year(year: u32) -> String {
+    let (century, rest) = (year / 100, year % 100);
+    match (century, rest) {
+        _ if year < 1000 || (2000..2010).contains(&year) => en_cardinal(year.into()),
+        (_, 0) => format!("{} hundred", en_cardinal(century.into())),
+        (_, 1..=9) => format!(
+            "{} oh {}",
+            en_cardinal(century.into()),
+            EN_ONES[rest as usize]
+        ),
+        _ => format!(
+            "{} {}",
+            en_cardinal(century.into()),
+            en_cardinal(rest.into())
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(text: &str, context: &NumericContext) -> (String, Vec<String>) {
+        let mut ambiguous = Vec::new();
+        let output = normalize_numbers(text, context, &mut ambiguous).into_owned();
+        (output, ambiguous)
+    }
+
+    #[test]
+    fn test_zh_cn_profile() {
+        let context = NumericContext::zh_cn();
+        let cases = [
+            (
+                "会议在2024-03-04举行",
+                "会议在二零二四年三月四日举行",
+                false,
+            ),
+            ("2024/12/25", "二零二四年十二月二十五日", false),
+            ("2024-02-30", "2024-02-30", false),
+            ("加3/4杯水", "加四分之三杯水", true),
+            ("1/2", "二分之一", true),
+            ("3/45", "四十五分之三", false),
+            ("13/4", "四分之十三", false),
+            ("5/0", "5/0", false),
+            ("12/25/2024", "二零二四年十二月二十五日", false),
+            ("25/12/2024", "二零二四年十二月二十五日", false),
+            ("电话13800138000", "电话幺三八 零零幺三 八零零零", false),
+            ("138-0013-8000", "幺三八 零零幺三 八零零零", false),
+            (
+                "+86 138 0013 8000",
+                "加八六 幺三八 零零幺三 八零零零",
+                false,
+            ),
+            ("(010) 1234-5678", "零幺零 幺二三四 五六七八", false),
+            ("编号1380013800", "编号1380013800", false),
+            ("15:30开会", "十五点三十分开会", false),
+            ("8:05", "八点零五分", false),
+            ("2:00", "两点整", false),
+            ("23:59:07", "二十三点五十九分七秒", false),
+            ("3:30 pm", "下午三点三十分", false),
+            ("25:00", "25:00", false),
+            ("16:9的屏幕", "16:9的屏幕", false),
+            ("圆周率3.14", "圆周率三点一四", false),
+            ("0.5", "零点五", false),
+            ("1,000,000人", "一百万人", false),
+            ("12,345.67", "一万二千三百四十五点六七", false),
+            ("1,500", "一千五百", true),
+            ("1.500", "一点五零零", true),
+            ("10.25", "十点二五", false),
+            ("100015", "100015", false),
+            ("1,23", "1,23", false),
+            ("版本v1.2.3", "版本v1.2.3", false),
+            ("见http://a.cn/3/4", "见http://a.cn/3/4", false),
+            ("`x = 3/4`", "`x = 3/4`", false),
+            ("A4纸", "A4纸", false),
+            ("没有数字", "没有数字", false),
+        ];
+        for (input, expected, ambiguous) in cases {
+            let (output, flagged) = normalize(input, &context);
+            assert_eq!(output, expected, "{}", input);
+            assert_eq!(!flagged.is_empty(), ambiguous, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_en_us_profile() {
+        let context = NumericContext::en_us();
+        let cases = [
+            ("Due 3/4.", "Due March fourth.", true),
+            (
+                "on 12/25/2024",
+                "on December twenty-fifth, twenty twenty-four",
+                false,
+            ),
+            (
+                "25/12/2024",
+                "December twenty-fifth, twenty twenty-four",
+                false,
+            ),
+            ("2024-07-04", "July fourth, twenty twenty-four", false),
+            ("1/1/2000", "January first, two thousand", false),
+            ("3/2/2005", "March second, two thousand five", false),
+            ("6/1/1905", "June first, nineteen oh five", false),
+            ("1/31/1900", "January thirty-first, nineteen hundred", false),
+            ("3/45", "three forty-fifths", false),
+            ("1/32", "one thirty-second", false),
+            ("13/2", "thirteen halves", false),
+            ("7/1", "July first", true),
+            (
+                "call 555-123-4567",
+                "call five five five, one two three, four five six seven",
+                false,
+            ),
+            (
+                "(555) 123-4567",
+                "five five five, one two three, four five six seven",
+                false,
+            ),
+            (
+                "+1 555-123-4567",
+                "plus one, five five five, one two three, four five six seven",
+                false,
+            ),
+            (
+                "5551234567",
+                "five five five, one two three, four five six seven",
+                false,
+            ),
+            ("at 15:30", "at fifteen thirty", false),
+            ("9:05 am", "nine oh five a m", false),
+            ("12:00 P.M.", "twelve p m", false),
+            ("7:00", "seven o'clock", false),
+            ("0:00:01", "zero and one second", false),
+            ("13:30 pm", "13:30 pm", false),
+            ("3.14", "three point one four", false),
+            (
+                "1,234,567",
+                "one million two hundred thirty-four thousand five hundred sixty-seven",
+                false,
+            ),
+            ("2,500.5", "two thousand five hundred point five", false),
+            ("1,500", "one thousand five hundred", true),
+            ("0.125", "zero point one two five", false),
+            ("11.5", "eleven point five", false),
+            ("1.2.3", "1.2.3", false),
+            ("see www.x.com/1/2", "see www.x.com/1/2", false),
+            ("4th", "4th", false),
+            ("2024", "2024", false),
+        ];
+        for (input, expected, ambiguous) in cases {
+            let (output, flagged) = normalize(input, &context);
+            assert_eq!(output, expected, "{}", input);
+            assert_eq!(!flagged.is_empty(), ambiguous, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_overridden_fields() {
+        let dates = NumericContext::zh_cn().with_slash_reading(SlashReading::Date);
+        assert_eq!(
+            normalize("3/4", &dates),
+            ("三月四日".into(), vec!["3/4".into()])
+        );
+
+        let day_month = NumericContext::en_us().with_date_order(DateOrder::DayMonth);
+        assert_eq!(normalize("3/4", &day_month).0, "April third");
+        assert_eq!(
+            normalize("3/4/2024", &day_month).0,
+            "April third, twenty twenty-four"
+        );
+
+        let fractions = NumericContext::en_us().with_slash_reading(SlashReading::Fraction);
+        assert_eq!(normalize("3/4", &fractions).0, "three quarters");
+        assert_eq!(normalize("1/4", &fractions).0, "one quarter");
+
+        let comma = NumericContext::en_us().with_decimal_separator(DecimalSeparator::Comma);
+        assert_eq!(normalize("3,14", &comma).0, "three point one four");
+        assert_eq!(
+            normalize("1.234.567,8", &comma).0,
+            "one million two hundred thirty-four thousand five hundred sixty-seven point eight"
+        );
+        assert_eq!(normalize("1,500", &comma).1, vec!["1,500".to_string()]);
+
+        let landline = NumericContext::zh_cn().with_phone_groups(&[4, 4]);
+        assert_eq!(normalize("88886666", &landline).0, "八八八八 六六六六");
+        assert_eq!(normalize("13800138000", &landline).0, "13800138000");
+
+        let english = NumericContext::zh_cn().with_language(SymbolLanguage::English);
+        assert_eq!(normalize("3/4", &english).0, "three quarters");
+    }
+
+    #[test]
+    fn test_fullwidth_digits_left_as_written() {
+        let context = NumericContext::zh_cn();
+        assert_eq!(normalize("价格是1.２元。", &context).0, "价格是1.２元。");
+        assert_eq!(
+            normalize("日期２０２４-3-4。", &context).0,
+            "日期２０２４-3-4。"
+        );
+        assert_eq!(
+            normalize("电话１３８-0013-8000", &context).0,
+            "电话１３８-0013-8000"
+        );
+    }
+
+    #[test]
+    fn test_cardinals() {
+        let zh = [
+            (0, "零"),
+            (10, "十"),
+            (15, "十五"),
+            (20, "二十"),
+            (101, "一百零一"),
+            (110, "一百一十"),
+            (1001, "一千零一"),
+            (10_000, "一万"),
+            (100_015, "十万零一十五"),
+            (1_000_000, "一百万"),
+            (10_001_000, "一千万一千"),
+            (100_000_001, "一亿零一"),
+            (120_003_000, "一亿二千万三千"),
+        ];
+        for (n, expected) in zh {
+            assert_eq!(zh_cardinal(n), expected, "{}", n);
+        }
+        let en = [
+            (0, "zero"),
+            (21, "twenty-one"),
+            (100, "one hundred"),
+            (115, "one hundred fifteen"),
+            (1_000_001, "one million one"),
+            (u64::MAX, "eighteen quintillion four hundred forty-six quadrillion seven hundred forty-four trillion seventy-three billion seven hundred nine million five hundred fifty-one thousand six hundred fifteen"),
+        ];
+        for (n, expected) in en {
+            assert_eq!(en_cardinal(n), expected, "{}", n);
+        }
+        assert_eq!(en_ordinal(20), "twentieth");
+        assert_eq!(en_ordinal(112), "one hundred twelfth");
+    }
+}
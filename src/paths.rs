@@ -0,0 +1,592 @@
+//! Filesystem-safe names for generated output files
+
+use crate::config::Voice;
+use crate::error::{Error, Result};
+use crate::naming::MAX_SLUG_CHARS;
+use crate::preprocess::is_cjk;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Device names Windows reserves, in any case and with any extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Filesystem-safe slug of the start of `text`
+///
+/// Letters and digits of any script are kept; every run of other characters
+/// becomes one `_`. The slug is cut to `max_len` characters, at a word
+/// boundary unless the cut falls in CJK text. A slug that Windows reserves
+/// for a device, such as `CON` or `nul`, gets a trailing `_`.
+///
+/// # Arguments
+///
+/// * `text` - Text to take the slug from
+/// * `max_len` - Longest slug, in characters
+///
+/// # Examples
+///
+/// ```
+/// use text2audio::paths::slugify;
+///
+/// assert_eq!(slugify("第一章：开端", 20), "第一章_开端");
+/// assert_eq!(slugify("The quick brown fox", 12), "The_quick");
+/// assert_eq!(slugify("con", 20), "con_");
+/// ```
+pub fn slugify(text: &str, max_len: usize) -> String {
+    let mut slug = String::new();
+    let mut chars = 0;
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            if chars == max_len {
+                // Drop a partial last word rather than cutting it; CJK has
+                // no word boundaries to cut at
+                if !is_cjk(c) {
+                    if let Some(boundary) = slug.rfind('_') {
+                        slug.truncate(boundary);
+                    }
+                }
+                break;
+            }
+            slug.push(c);
+            chars += 1;
+        } else if !slug.is_empty() && !slug.ends_with('_') {
+            if chars == max_len {
+                break;
+            }
+            slug.push('_');
+            chars += 1;
+        }
+    }
+    let mut slug = slug.trim_end_matches('_').to_string();
+    if is_reserved(&slug) {
+        if slug.chars().count() == max_len {
+            slug.pop();
+        }
+        slug.push('_');
+    }
+    slug
+}
+
+/// Whether Windows reserves `name` for a device, with or without extension
+fn is_reserved(name: &str) -> bool {
+    let base = name.split('.').next().unwrap_or_default().trim_end();
+    RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(base))
+}
+
+/// Whether `c` is not allowed in a file name on Windows or Unix
+fn is_forbidden(c: char) -> bool {
+    c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|')
+}
+
+/// `name` without trailing dots and spaces, which Windows drops, and with a
+/// `_` after a reserved device name
+fn safe_file_name(name: &str) -> String {
+    let mut name = name.trim_end_matches(['.', ' ']).to_string();
+    if is_reserved(&name) {
+        let end = name.find('.').unwrap_or(name.len());
+        name.insert(end, '_');
+    }
+    name
+}
+
+/// Values substituted into an [`OutputTemplate`]
+///
+/// Values left at their default render as empty text, except the index.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TemplateValues<'a> {
+    /// Stem of the output the files belong to
+    pub stem: &'a str,
+    /// Number of the file, counting from 1
+    pub index: usize,
+    /// Voice speaking the file
+    pub voice: Option<Voice>,
+    /// Speech speed of the file
+    pub speed: Option<f32>,
+    /// Number of the chapter the file belongs to
+    pub chapter: Option<usize>,
+    /// Text of the file, whose slug `{slug}` stands for
+    pub text: &'a str,
+}
+
+/// Placeholder of an [`OutputTemplate`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Stem,
+    Index,
+    Voice,
+    Speed,
+    Chapter,
+    Slug,
+}
+
+/// Piece of an [`OutputTemplate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    /// Placeholder and the width numbers are zero-padded to
+    Field(Field, Option<usize>),
+}
+
+/// Pattern for the names of generated output files
+///
+/// Placeholders in braces are replaced for each file:
+///
+/// | Placeholder | Value |
+/// |-------------|-------|
+/// | `{stem}` | Stem of the output the files belong to |
+/// | `{index}` | Number of the file from 1, zero-padded to 3 digits or to the width of the last number |
+/// | `{index:03}` | Number of the file, zero-padded to the given width |
+/// | `{voice}` | Name of the voice |
+/// | `{speed}` | Speech speed |
+/// | `{chapter}` | Number of the chapter, also with a width like `{chapter:02}` |
+/// | `{slug}` | [Slug](slugify) of the file's opening words, up to [`MAX_SLUG_CHARS`] characters |
+///
+/// `{{` and `}}` stand for literal braces. Values are made safe for file
+/// names, a placeholder without a value renders as empty text, and the name
+/// is trimmed of `_`, `-`, `.` and spaces at both ends, so an empty
+/// placeholder leaves no dangling separator. A name Windows reserves for a
+/// device gets a `_` appended.
+///
+/// # Examples
+///
+/// ```
+/// use text2audio::paths::{OutputTemplate, TemplateValues};
+///
+/// let template = OutputTemplate::new("{stem}-{index:02}-{slug}")?;
+/// let values = TemplateValues {
+///     stem: "book",
+///     index: 7,
+///     text: "第一章：开端",
+///     ..TemplateValues::default()
+/// };
+/// assert_eq!(template.render(&values), "book-07-第一章_开端");
+///
+/// assert!(OutputTemplate::new("{title}").is_err());
+/// # Ok::<(), text2audio::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputTemplate {
+    source: String,
+    parts: Vec<Part>,
+}
+
+impl OutputTemplate {
+    /// Parse a template
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - File name pattern without extension, such as
+    ///   `"{index}_{slug}"`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] if the template is empty, names an unknown
+    /// placeholder, gives a width to a placeholder other than `{index}` and
+    /// `{chapter}`, has an unmatched brace, or contains a character not
+    /// allowed in file names, such as `/`.
+    pub fn new(template: &str) -> Result<Self> {
+        let invalid = |reason: String| {
+            Error::Config(format!(
+                "invalid output template \"{}\": {}",
+                template, reason
+            ))
+        };
+        if template.is_empty() {
+            return Err(invalid("template is empty".to_string()));
+        }
+
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' | '}' if chars.peek() == Some(&c) => {
+                    chars.next();
+                    literal.push(c);
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => return Err(invalid("unclosed `{`".to_string())),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Self::parse_field(&placeholder).map_err(invalid)?);
+                }
+                '}' => return Err(invalid("unmatched `}`".to_string())),
+                c if is_forbidden(c) => {
+                    return Err(invalid(format!("{:?} is not allowed in file names", c)))
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Self {
+            source: template.to_string(),
+            parts,
+        })
+    }
+
+    /// Placeholder and width of `{placeholder}`
+    fn parse_field(placeholder: &str) -> std::result::Result<Part, String> {
+        let (name, width) = match placeholder.split_once(':') {
+            Some((name, width)) => (name, Some(width)),
+            None => (placeholder, None),
+        };
+        let field = match name {
+            "stem" => Field::Stem,
+            "index" => Field::Index,
+            "voice" => Field::Voice,
+            "speed" => Field::Speed,
+            "chapter" => Field::Chapter,
+            "slug" => Field::Slug,
+            _ => return Err(format!("unknown placeholder {{{}}}", name)),
+        };
+        let width = match width {
+            None => None,
+            Some(_) if !matches!(field, Field::Index | Field::Chapter) => {
+                return Err(format!("placeholder {{{}}} takes no width", name))
+            }
+            Some(width) if !width.is_empty() && width.bytes().all(|b| b.is_ascii_digit()) => Some(
+                width
+                    .parse()
+                    .map_err(|_| format!("width {} is too large", width))?,
+            ),
+            Some(width) => return Err(format!("invalid width \"{}\" in {{{}}}", width, name)),
+        };
+        Ok(Part::Field(field, width))
+    }
+
+    /// The template as written
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// File name, without extension, for `values`
+    ///
+    /// `{index}` without a width is zero-padded to 3 digits.
+    ///
+    /// # Arguments
+    ///
+    /// * `values` - Values of the placeholders
+    pub fn render(&self, values: &TemplateValues) -> String {
+        self.render_with(values, 3)
+    }
+
+    /// File names for `texts`, numbered from 1, with `extension`
+    ///
+    /// Each name is rendered like [`render`](Self::render) with the file's
+    /// number and text, and `{index}` without a width is zero-padded to the
+    /// width of the last number, at least 3 digits. An empty name becomes
+    /// the number, and a name already taken, ignoring case, gets the
+    /// number appended; if that is taken too, the number is counted up
+    /// until the name is free, so every name is unique.
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - Text of each file
+    /// * `values` - Values shared by all files; `index` and `text` are
+    ///   replaced for each file
+    /// * `extension` - Extension of the files, without the dot
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::paths::{OutputTemplate, TemplateValues};
+    ///
+    /// let template = OutputTemplate::new("{slug}")?;
+    /// let names = template.file_names(&["Intro", "intro", "……"], &TemplateValues::default(), "wav");
+    /// assert_eq!(names, ["Intro.wav", "intro_002.wav", "003.wav"]);
+    /// # Ok::<(), text2audio::Error>(())
+    /// ```
+    pub fn file_names<S: AsRef<str>>(
+        &self,
+        texts: &[S],
+        values: &TemplateValues,
+        extension: &str,
+    ) -> Vec<String> {
+        let width = texts.len().to_string().len().max(3);
+        let mut taken = HashSet::new();
+        texts
+            .iter()
+            .enumerate()
+            .map(|(idx, text)| {
+                let values = TemplateValues {
+                    index: idx + 1,
+                    text: text.as_ref(),
+                    ..*values
+                };
+                let rendered = self.render_with(&values, width);
+                let numbered = |number: usize| {
+                    let number = format!("{:0width$}", number, width = width);
+                    if rendered.is_empty() {
+                        number
+                    } else {
+                        format!("{}_{}", rendered, number)
+                    }
+                };
+                let mut stem = if rendered.is_empty() {
+                    numbered(idx + 1)
+                } else {
+                    rendered.clone()
+                };
+                // Case-insensitive filesystems treat "Intro" and "intro" alike
+                let mut number = idx + 1;
+                while taken.contains(&stem.to_lowercase()) {
+                    stem = numbered(number);
+                    number += 1;
+                }
+                taken.insert(stem.to_lowercase());
+                format!("{}.{}", stem, extension)
+            })
+            .collect()
+    }
+
+    /// File name for `values` with `{index}` padded to `index_width`
+    fn render_with(&self, values: &TemplateValues, index_width: usize) -> String {
+        let mut name = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => name.push_str(literal),
+                Part::Field(Field::Stem, _) => {
+                    name.extend(
+                        values
+                            .stem
+                            .chars()
+                            .map(|c| if is_forbidden(c) { '_' } else { c }),
+                    )
+                }
+                Part::Field(Field::Index, width) => name.push_str(&format!(
+                    "{:0width$}",
+                    values.index,
+                    width = width.unwrap_or(index_width)
+                )),
+                Part::Field(Field::Voice, _) => {
+                    if let Some(voice) = values.voice {
+                        name.push_str(voice.as_str());
+                    }
+                }
+                Part::Field(Field::Speed, _) => {
+                    if let Some(speed) = values.speed {
+                        name.push_str(&speed.to_string());
+                    }
+                }
+                Part::Field(Field::Chapter, width) => {
+                    if let Some(chapter) = values.chapter {
+                        name.push_str(&format!("{:0width$}", chapter, width = width.unwrap_or(0)));
+                    }
+                }
+                Part::Field(Field::Slug, _) => name.push_str(&slugify(values.text, MAX_SLUG_CHARS)),
+            }
+        }
+        safe_file_name(name.trim_matches(|c| matches!(c, '_' | '-' | '.' | ' ')))
+    }
+}
+
+impl fmt::Display for OutputTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl std::str::FromStr for OutputTemplate {
+    type Err = Error;
+
+    fn from_str(template: &str) -> Result<Self> {
+        Self::new(template)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        let long_title = "长".repeat(300);
+        let cases: [(&str, usize, &str); 16] = [
+            (
+                "人工智能技术，正在改变世界。",
+                20,
+                "人工智能技术_正在改变世界",
+            ),
+            ("  “Hello”, world! (v2.0)", 20, "Hello_world_v2_0"),
+            ("../etc/passwd", 20, "etc_passwd"),
+            ("C:\\Windows\\System32", 20, "C_Windows_System32"),
+            ("a:b*c?d<e>f|g\"h\\i", 20, "a_b_c_d_e_f_g_h_i"),
+            ("。！？……", 20, ""),
+            ("🎧 Podcast 🎙️ #12", 20, "Podcast_12"),
+            ("🎉🎉🎉", 20, ""),
+            (
+                "The quick brown fox jumps over the lazy dog",
+                20,
+                "The_quick_brown_fox",
+            ),
+            (&long_title, 20, "长长长长长长长长长长长长长长长长长长长长"),
+            ("CON", 20, "CON_"),
+            ("nul.txt", 20, "nul_txt"),
+            ("Com1", 4, "Com_"),
+            ("LPT9 ...", 20, "LPT9_"),
+            ("README...  ", 20, "README"),
+            ("anything", 0, ""),
+        ];
+        for (text, max_len, slug) in cases {
+            assert_eq!(slugify(text, max_len), slug, "slug of {:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_template_validation() {
+        assert!(
+            OutputTemplate::new("{index:03}_{stem}-{voice}-{speed}-{chapter:2}-{slug}").is_ok()
+        );
+        assert!(OutputTemplate::new("{{literal}}").is_ok());
+        for (template, reason) in [
+            ("", "empty"),
+            ("{title}", "unknown placeholder {title}"),
+            ("{Index}", "unknown placeholder {Index}"),
+            ("{slug:10}", "takes no width"),
+            ("{index:x}", "invalid width"),
+            ("{index:}", "invalid width"),
+            ("{index", "unclosed"),
+            ("index}", "unmatched"),
+            ("{chapter}/{index}", "not allowed"),
+            ("part:{index}", "not allowed"),
+        ] {
+            let error = OutputTemplate::new(template).unwrap_err();
+            assert!(
+                matches!(&error, Error::Config(m) if m.contains(reason)),
+                "{:?}: {}",
+                template,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_nasty_values() {
+        let template =
+            OutputTemplate::new("{stem}_{chapter:02}_{index}_{voice}_{speed}_{slug}").unwrap();
+        let values = TemplateValues {
+            stem: "my/book:v2",
+            index: 4,
+            voice: Some(Voice::Jam),
+            speed: Some(1.5),
+            chapter: Some(3),
+            text: "“Why?” she asked.",
+        };
+        assert_eq!(
+            template.render(&values),
+            "my_book_v2_03_004_Jam_1.5_Why_she_asked"
+        );
+
+        // Missing values leave no dangling separators
+        let values = TemplateValues {
+            index: 4,
+            ..TemplateValues::default()
+        };
+        assert_eq!(template.render(&values), "004");
+
+        let stem = OutputTemplate::new("{stem}").unwrap();
+        for (input, name) in [
+            ("CON", "CON_"),
+            ("aux.tar", "aux_.tar"),
+            ("notes. . .", "notes"),
+            ("../..", ""),
+            ("🎧 播客", "🎧 播客"),
+        ] {
+            let values = TemplateValues {
+                stem: input,
+                ..TemplateValues::default()
+            };
+            assert_eq!(stem.render(&values), name, "stem {:?}", input);
+        }
+        assert_eq!(
+            OutputTemplate::new("{{{index:1}}}")
+                .unwrap()
+                .render(&TemplateValues {
+                    index: 12,
+                    ..TemplateValues::default()
+                }),
+            "{12}"
+        );
+    }
+
+    #[test]
+    fn test_template_file_names() {
+        let segments = ["第一章。", "第一章。", "……", "Intro", "intro", "Con"];
+        let names = |template: &str| {
+            OutputTemplate::new(template).unwrap().file_names(
+                &segments,
+                &TemplateValues::default(),
+                "wav",
+            )
+        };
+        assert_eq!(
+            names("{slug}"),
+            [
+                "第一章.wav",
+                "第一章_002.wav",
+                "003.wav",
+                "Intro.wav",
+                "intro_005.wav",
+                "Con_.wav"
+            ]
+        );
+        assert_eq!(
+            names("{index}_{slug}"),
+            [
+                "001_第一章.wav",
+                "002_第一章.wav",
+                "003.wav",
+                "004_Intro.wav",
+                "005_intro.wav",
+                "006_Con.wav"
+            ]
+        );
+        let many: Vec<String> = (0..1000).map(|n| n.to_string()).collect();
+        let names = OutputTemplate::new("{index}").unwrap().file_names(
+            &many,
+            &TemplateValues::default(),
+            "mp3",
+        );
+        assert_eq!(
+            (names[0].as_str(), names[999].as_str()),
+            ("0001.mp3", "1000.mp3")
+        );
+
+        // Fallback and suffixed names never reuse a name already taken
+        let names = |texts: &[&str]| {
+            OutputTemplate::new("{slug}").unwrap().file_names(
+                texts,
+                &TemplateValues::default(),
+                "wav",
+            )
+        };
+        assert_eq!(
+            names(&["a_002", "a", "a"]),
+            ["a_002.wav", "a.wav", "a_003.wav"]
+        );
+        assert_eq!(
+            names(&["a_003", "a", "a"]),
+            ["a_003.wav", "a.wav", "a_004.wav"]
+        );
+        assert_eq!(names(&["002", "……"]), ["002.wav", "003.wav"]);
+        assert_eq!(
+            names(&["a", "a", "A_002"]),
+            ["a.wav", "a_002.wav", "A_002_003.wav"]
+        );
+    }
+}
@@ -0,0 +1,494 @@
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+/// Width (in characters) of each bucket in the segment length histogram
+pub const HISTOGRAM_BUCKET_WIDTH: usize = 100;
+
+/// Punctuation that counts as a natural end of a sentence
+const TERMINAL_PUNCTUATION: &[char] = &['。', '！', '？', '…', '.', '!', '?', ';', '；'];
+
+/// Closing quotes and brackets allowed after terminal punctuation
+pub(crate) const CLOSING_MARKS: &[char] = &['"', '\'', '”', '’', '」', '』', '）', ')', '》', '】'];
+
+/// Chinese conjunctions that rarely start a well-formed segment
+const LEADING_CONJUNCTIONS: &[&str] = &[
+    "但是", "而且", "并且", "所以", "因此", "然而", "或者", "以及", "而是", "不过",
+];
+
+/// How the converter will process a given text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConversionMode {
+    /// The text is sent to the TTS API in a single request
+    Direct,
+    /// The text is split into segments that are synthesized and merged
+    Segmented,
+}
+
+impl fmt::Display for ConversionMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionMode::Direct => write!(f, "direct"),
+            ConversionMode::Segmented => write!(f, "segmented"),
+        }
+    }
+}
+
+/// Which processing mode the converter uses
+///
+/// Set with [`Text2Audio::with_mode`](crate::Text2Audio::with_mode).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ModeSelection {
+    /// Direct mode for text within the segment length, segmented otherwise
+    #[default]
+    Auto,
+    /// Always send the text in a single request
+    ForceDirect,
+    /// Always run the splitter, even when it returns a single segment
+    ForceSegmented,
+}
+
+/// How text longer than the segment length is split
+///
+/// Set with [`Text2Audio::with_split_strategy`](crate::Text2Audio::with_split_strategy).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SplitStrategy {
+    /// Always use the AI splitter
+    #[default]
+    Ai,
+    /// Race the AI splitter against the rule-based [`LocalSplitter`]
+    ///
+    /// The rule-based split is used unless the AI split arrives within
+    /// `deadline` and scores at least [`RACE_MARGIN`] higher; see
+    /// [`split_score`]. When the rule-based split scores so well that the
+    /// AI split cannot beat it, no AI request is made. An AI request still
+    /// running at the deadline is cancelled, and a failed one falls back to
+    /// the rule-based split.
+    ///
+    /// [`LocalSplitter`]: crate::LocalSplitter
+    Race {
+        /// Longest wait for the AI split
+        deadline: Duration,
+    },
+}
+
+/// What to do with a segment longer than a TTS request accepts
+///
+/// Splitters keep segments within [`TTS_MAX_CHARS`](crate::TTS_MAX_CHARS),
+/// but segment overrides, edited plans and unvalidated AI splits may not.
+/// Set with [`Text2Audio::with_oversize_policy`](crate::Text2Audio::with_oversize_policy).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OversizePolicy {
+    /// Fail the segment before any request is sent
+    #[default]
+    Error,
+    /// Split the segment locally, synthesize the pieces and join their
+    /// audio into the segment's audio
+    AutoSplit,
+}
+
+/// Score by which the AI split must beat the rule-based split in a race
+pub const RACE_MARGIN: f64 = 0.1;
+
+/// Which splitter produced the segments of a raced split
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitChoice {
+    /// The AI splitter
+    Ai,
+    /// The rule-based splitter
+    RuleBased,
+}
+
+/// Outcome of one race between the AI and rule-based splits
+///
+/// Recorded in [`ConversionReport::split_races`](crate::ConversionReport::split_races)
+/// for each block of text split under [`SplitStrategy::Race`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SplitRace {
+    /// Splitter whose segments were used
+    pub chosen: SplitChoice,
+    /// Score of the rule-based split
+    pub rule_based_score: f64,
+    /// Score of the AI split, or `None` if it was not requested, failed or
+    /// missed the deadline
+    pub ai_score: Option<f64>,
+}
+
+/// Quality of a split between 0.0 and 1.0, higher is better
+///
+/// The mean of three shares: segments for which `fits` holds, segments
+/// ending at a sentence boundary as counted by [`SegmentStats`], and the
+/// mean segment length over the longest.
+pub fn split_score<S: AsRef<str>>(segments: &[S], fits: impl Fn(&str) -> bool) -> f64 {
+    if segments.is_empty() {
+        return 0.0;
+    }
+
+    let stats = SegmentStats::from_segments(segments);
+    let count = segments.len() as f64;
+    let within = segments.iter().filter(|s| fits(s.as_ref())).count() as f64 / count;
+    let terminal = 1.0 - stats.mid_sentence_endings as f64 / count;
+    let total: usize = segments.iter().map(|s| s.as_ref().chars().count()).sum();
+    let balance = total as f64 / count / stats.max.max(1) as f64;
+    (within + terminal + balance) / 3.0
+}
+
+/// Result of splitting text without synthesizing any audio
+///
+/// Produced by [`Text2Audio::plan`](crate::Text2Audio::plan). Segment indices
+/// match the order in which segments would be synthesized and merged.
+#[derive(Debug, Clone)]
+pub struct ConversionPlan {
+    /// Processing mode that would be used
+    pub mode: ConversionMode,
+    /// Segments in synthesis order
+    pub segments: Vec<String>,
+    /// Silence inserted after each segment
+    pub pauses: Vec<Duration>,
+    /// Length statistics and split quality proxies
+    pub stats: SegmentStats,
+}
+
+impl ConversionPlan {
+    /// Create a plan from already split segments, without pauses
+    pub fn new(mode: ConversionMode, segments: Vec<String>) -> Self {
+        let stats = SegmentStats::from_segments(&segments);
+        Self {
+            mode,
+            pauses: vec![Duration::ZERO; segments.len()],
+            segments,
+            stats,
+        }
+    }
+
+    /// Serialize the segments as JSON for synthesis in another process
+    ///
+    /// Produces an array of objects with `index`, `text`, `char_count`,
+    /// `paragraph_break` and `pause_after_ms` fields. `paragraph_break` is
+    /// true where a pause follows the segment. Read the plan back with
+    /// [`from_json`](Self::from_json) or synthesize it directly with
+    /// [`Text2Audio::convert_segments_from_json`](crate::Text2Audio::convert_segments_from_json).
+    pub fn to_json(&self) -> String {
+        let entries: Vec<PlanEntry> = self
+            .segments
+            .iter()
+            .enumerate()
+            .map(|(index, text)| {
+                let pause = self.pauses.get(index).copied().unwrap_or_default();
+                PlanEntry {
+                    index,
+                    text: text.clone(),
+                    char_count: text.chars().count(),
+                    paragraph_break: !pause.is_zero(),
+                    pause_after_ms: pause.as_millis() as u64,
+                }
+            })
+            .collect();
+
+        serde_json::to_string_pretty(&entries).expect("plan entries are serializable")
+    }
+
+    /// Read a plan written by [`to_json`](Self::to_json)
+    ///
+    /// The plan is always segmented. `char_count` is informational and
+    /// recomputed from the text; a missing `pause_after_ms` means no pause.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] if the JSON is not a plan or the indices do
+    /// not run from 0 in order.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let entries: Vec<PlanEntry> = serde_json::from_str(json)
+            .map_err(|e| Error::Config(format!("Invalid plan: {}", e)))?;
+
+        let mut segments = Vec::with_capacity(entries.len());
+        let mut pauses = Vec::with_capacity(entries.len());
+        for (position, entry) in entries.into_iter().enumerate() {
+            if entry.index != position {
+                return Err(Error::Config(format!(
+                    "Invalid plan: segment at position {} has index {}",
+                    position, entry.index
+                )));
+            }
+            segments.push(entry.text);
+            pauses.push(Duration::from_millis(entry.pause_after_ms));
+        }
+
+        let mut plan = Self::new(ConversionMode::Segmented, segments);
+        plan.pauses = pauses;
+        Ok(plan)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PlanEntry {
+    index: usize,
+    text: String,
+    #[serde(default)]
+    char_count: usize,
+    #[serde(default)]
+    paragraph_break: bool,
+    #[serde(default)]
+    pause_after_ms: u64,
+}
+
+impl fmt::Display for ConversionPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Mode: {}", self.mode)?;
+        writeln!(f, "Segments: {}", self.segments.len())?;
+        write!(f, "{}", self.stats)
+    }
+}
+
+/// One bucket of the segment length histogram
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistogramBucket {
+    /// Inclusive lower bound in characters
+    pub start: usize,
+    /// Exclusive upper bound in characters
+    pub end: usize,
+    /// Number of segments whose length falls in the bucket
+    pub count: usize,
+}
+
+/// Segment length statistics used to judge split quality
+///
+/// The mid-sentence and suspicious-start counts are cheap proxies for bad
+/// split points; they do not require any network access.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SegmentStats {
+    /// Length histogram with buckets of [`HISTOGRAM_BUCKET_WIDTH`] characters
+    pub histogram: Vec<HistogramBucket>,
+    /// Shortest segment length in characters
+    pub min: usize,
+    /// Median segment length in characters (lower median for even counts)
+    pub median: usize,
+    /// Longest segment length in characters
+    pub max: usize,
+    /// Segments that do not end with terminal punctuation
+    pub mid_sentence_endings: usize,
+    /// Segments starting with a lowercase Latin letter or a Chinese conjunction
+    pub suspicious_starts: usize,
+    /// Total number of segments
+    pub segment_count: usize,
+}
+
+impl SegmentStats {
+    /// Compute statistics for a list of segments
+    pub fn from_segments<S: AsRef<str>>(segments: &[S]) -> Self {
+        if segments.is_empty() {
+            return Self::default();
+        }
+
+        let mut lengths: Vec<usize> = segments
+            .iter()
+            .map(|s| s.as_ref().chars().count())
+            .collect();
+        lengths.sort_unstable();
+
+        let max = lengths[lengths.len() - 1];
+        let mut histogram: Vec<HistogramBucket> = (0..=max / HISTOGRAM_BUCKET_WIDTH)
+            .map(|i| HistogramBucket {
+                start: i * HISTOGRAM_BUCKET_WIDTH,
+                end: (i + 1) * HISTOGRAM_BUCKET_WIDTH,
+                count: 0,
+            })
+            .collect();
+        for len in &lengths {
+            histogram[len / HISTOGRAM_BUCKET_WIDTH].count += 1;
+        }
+
+        let segments = segments.iter().map(|s| s.as_ref().trim());
+        let mid_sentence_endings = segments.clone().filter(|s| !ends_sentence(s)).count();
+        let suspicious_starts = segments.filter(|s| starts_suspiciously(s)).count();
+
+        Self {
+            histogram,
+            min: lengths[0],
+            median: lengths[(lengths.len() - 1) / 2],
+            max,
+            mid_sentence_endings,
+            suspicious_starts,
+            segment_count: lengths.len(),
+        }
+    }
+
+    /// Fraction of segments with no bad split indicators, from 0.0 to 1.0
+    ///
+    /// Each segment contributes two checks (ending and start); an empty
+    /// segment list scores 1.0.
+    pub fn quality_score(&self) -> f32 {
+        if self.segment_count == 0 {
+            return 1.0;
+        }
+        let checks = (self.segment_count * 2) as f32;
+        let failures = (self.mid_sentence_endings + self.suspicious_starts) as f32;
+        1.0 - failures / checks
+    }
+}
+
+impl fmt::Display for SegmentStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Length (chars): min {} / median {} / max {}",
+            self.min, self.median, self.max
+        )?;
+        writeln!(f, "Histogram:")?;
+        for bucket in &self.histogram {
+            writeln!(
+                f,
+                "  {:>5}-{:<5} {:>4} {}",
+                bucket.start,
+                bucket.end - 1,
+                bucket.count,
+                "#".repeat(bucket.count)
+            )?;
+        }
+        writeln!(f, "Mid-sentence endings: {}", self.mid_sentence_endings)?;
+        writeln!(f, "Suspicious starts: {}", self.suspicious_starts)?;
+        writeln!(f, "Quality score: {:.2}", self.quality_score())
+    }
+}
+
+/// Check whether a segment ends with terminal punctuation
+///
+/// Trailing closing quotes and brackets are ignored, so `他说："好。"` counts
+/// as a complete sentence.
+pub(crate) fn ends_sentence(segment: &str) -> bool {
+    segment
+        .trim_end_matches(CLOSING_MARKS)
+        .chars()
+        .next_back()
+        .is_some_and(|c| TERMINAL_PUNCTUATION.contains(&c))
+}
+
+/// Check whether a segment starts like the continuation of a sentence
+pub(crate) fn starts_suspiciously(segment: &str) -> bool {
+    match segment.chars().next() {
+        Some(c) if c.is_ascii_lowercase() => true,
+        Some(_) => LEADING_CONJUNCTIONS.iter().any(|c| segment.starts_with(c)),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_lengths() {
+        let segments = vec!["a".repeat(50), "b".repeat(150), "c".repeat(120)];
+        let stats = SegmentStats::from_segments(&segments);
+        assert_eq!(stats.min, 50);
+        assert_eq!(stats.median, 120);
+        assert_eq!(stats.max, 150);
+        assert_eq!(stats.segment_count, 3);
+    }
+
+    #[test]
+    fn test_stats_median_even() {
+        let segments = ["一二", "一二三四", "一", "一二三"];
+        let stats = SegmentStats::from_segments(&segments);
+        assert_eq!(stats.median, 2);
+    }
+
+    #[test]
+    fn test_stats_histogram() {
+        let segments = vec!["x".repeat(10), "x".repeat(99), "x".repeat(250)];
+        let stats = SegmentStats::from_segments(&segments);
+        assert_eq!(
+            stats.histogram,
+            vec![
+                HistogramBucket {
+                    start: 0,
+                    end: 100,
+                    count: 2
+                },
+                HistogramBucket {
+                    start: 100,
+                    end: 200,
+                    count: 0
+                },
+                HistogramBucket {
+                    start: 200,
+                    end: 300,
+                    count: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stats_split_quality() {
+        let segments = [
+            "这是第一句。",
+            "但是这一句没有结束",
+            "而且，他说：“好。”",
+            "and then it continued.",
+            "Another sentence!",
+        ];
+        let stats = SegmentStats::from_segments(&segments);
+        assert_eq!(stats.mid_sentence_endings, 1);
+        assert_eq!(stats.suspicious_starts, 3);
+        assert!((stats.quality_score() - 0.6).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_split_score() {
+        let fits = |s: &str| s.chars().count() <= 10;
+        assert_eq!(split_score(&["第一句。", "第二句。"], fits), 1.0);
+        // A hard cut leaves the first segment mid-sentence and unbalanced
+        let cut = split_score(&["一二三四五六七八九十", "一二。"], fits);
+        assert!((cut - (1.0 + 0.5 + 0.65) / 3.0).abs() < 1e-9);
+        assert!(split_score(&["一二三四五六七八九十一。"], fits) < 1.0);
+        assert_eq!(split_score::<&str>(&[], fits), 0.0);
+    }
+
+    #[test]
+    fn test_stats_empty() {
+        let stats = SegmentStats::from_segments::<String>(&[]);
+        assert_eq!(stats.segment_count, 0);
+        assert!(stats.histogram.is_empty());
+        assert_eq!(stats.quality_score(), 1.0);
+    }
+
+    #[test]
+    fn test_plan_json_round_trip() {
+        let mut plan = ConversionPlan::new(
+            ConversionMode::Segmented,
+            vec!["第一段。".to_string(), "第二段。".to_string()],
+        );
+        plan.pauses[0] = Duration::from_millis(800);
+
+        let json = plan.to_json();
+        let entries: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(entries[0]["char_count"], 4);
+        assert_eq!(entries[0]["paragraph_break"], true);
+        assert_eq!(entries[1]["paragraph_break"], false);
+
+        let loaded = ConversionPlan::from_json(&json).unwrap();
+        assert_eq!(loaded.segments, plan.segments);
+        assert_eq!(loaded.pauses, plan.pauses);
+
+        let out_of_order = r#"[{"index": 1, "text": "第二段。"}]"#;
+        assert!(matches!(
+            ConversionPlan::from_json(out_of_order),
+            Err(Error::Config(m)) if m.contains("index 1")
+        ));
+        assert!(ConversionPlan::from_json("{}").is_err());
+    }
+
+    #[test]
+    fn test_plan_display() {
+        let plan = ConversionPlan::new(
+            ConversionMode::Segmented,
+            vec!["第一段。".to_string(), "第二段".to_string()],
+        );
+        let output = plan.to_string();
+        assert!(output.contains("Mode: segmented"));
+        assert!(output.contains("Segments: 2"));
+        assert!(output.contains("Mid-sentence endings: 1"));
+    }
+}
@@ -0,0 +1,37 @@
+use crate::error::{AudioErrorKind, Error, Result};
+use rodio::{Decoder, OutputStream, Sink};
+use std::io::Cursor;
+use std::sync::mpsc::Receiver;
+
+/// Play WAV segments from `receiver` in order until the sender is dropped
+///
+/// Runs on a blocking thread because the output stream is not `Send`. When
+/// the queue runs dry (synthesis slower than playback) the sink simply idles
+/// until the next segment is appended, then resumes.
+pub(crate) fn play_queue(receiver: Receiver<Vec<u8>>) -> Result<()> {
+    let (_stream, handle) = OutputStream::try_default().map_err(|e| {
+        Error::audio(
+            AudioErrorKind::Playback,
+            format!("No audio output device: {}", e),
+        )
+    })?;
+    let sink = Sink::try_new(&handle).map_err(|e| {
+        Error::audio(
+            AudioErrorKind::Playback,
+            format!("Failed to open audio sink: {}", e),
+        )
+    })?;
+
+    for (idx, audio) in receiver.into_iter().enumerate() {
+        let source = Decoder::new(Cursor::new(audio)).map_err(|e| {
+            Error::audio(
+                AudioErrorKind::Playback,
+                format!("Segment {} cannot be played: {}", idx, e),
+            )
+        })?;
+        sink.append(source);
+    }
+
+    sink.sleep_until_end();
+    Ok(())
+}
@@ -0,0 +1,1347 @@
+//! Text preprocessing applied before splitting
+
+use crate::report::Warning;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Spoken replacement for over-long URLs
+pub const URL_PLACEHOLDER: &str = "一个链接";
+
+/// Number of characters of a long token kept in warnings
+const PREVIEW_CHARS: usize = 16;
+
+/// What to do with tokens longer than the configured limit
+///
+/// A token is a maximal run of characters containing neither whitespace nor
+/// CJK characters, such as a URL or a base64 blob. CJK text never forms a
+/// long token because it splits cleanly at any character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LongTokenPolicy {
+    /// Drop the token entirely
+    Skip,
+    /// Keep only the first `n` characters
+    Truncate(usize),
+    /// Read the first `n` characters followed by "等"
+    SpellFirst(usize),
+}
+
+impl LongTokenPolicy {
+    fn apply(&self, token: &str) -> String {
+        match self {
+            LongTokenPolicy::Skip => String::new(),
+            LongTokenPolicy::Truncate(n) => token.chars().take(*n).collect(),
+            LongTokenPolicy::SpellFirst(n) => {
+                let mut head: String = token.chars().take(*n).collect();
+                head.push('等');
+                head
+            }
+        }
+    }
+}
+
+/// Spacing convention between CJK and Latin text
+///
+/// Applies where a CJK character meets a Latin letter, a digit, or a symbol
+/// that belongs to a Latin token such as `%` or `$`. CJK punctuation never
+/// forms a boundary, so no space is added next to `，` or `。`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpacingMode {
+    /// Put exactly one space at every boundary, as in "使用 GPU 加速"
+    Insert,
+    /// Remove spaces at every boundary, as in "使用GPU加速"
+    Remove,
+}
+
+/// Language of the spoken readings substituted for symbols
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SymbolLanguage {
+    /// Chinese readings, as in "x的平方约等于2"
+    #[default]
+    Chinese,
+    /// English readings, as in "x squared approximately equals 2"
+    English,
+}
+
+/// Built-in readings of math and technical symbols as
+/// `(symbol, Chinese, English)`
+const SYMBOL_READINGS: &[(char, &str, &str)] = &[
+    ('≈', "约等于", "approximately equals"),
+    ('≠', "不等于", "is not equal to"),
+    ('≡', "恒等于", "is identical to"),
+    ('≤', "小于等于", "is less than or equal to"),
+    ('≥', "大于等于", "is greater than or equal to"),
+    ('±', "正负", "plus or minus"),
+    ('×', "乘", "times"),
+    ('÷', "除以", "divided by"),
+    ('√', "根号", "the square root of"),
+    ('∞', "无穷大", "infinity"),
+    ('∑', "求和", "the sum of"),
+    ('∫', "积分", "the integral of"),
+    ('∈', "属于", "is in"),
+    ('∠', "角", "angle"),
+    ('⊥', "垂直于", "is perpendicular to"),
+    ('∥', "平行于", "is parallel to"),
+    ('∵', "因为", "because"),
+    ('∴', "所以", "therefore"),
+    ('π', "派", "pi"),
+    ('Δ', "德尔塔", "delta"),
+    ('→', "指向", "to"),
+    ('←', "来自", "from"),
+    ('⇒', "推出", "implies"),
+    ('↑', "上升", "up"),
+    ('↓', "下降", "down"),
+    ('℃', "摄氏度", "degrees Celsius"),
+    ('℉', "华氏度", "degrees Fahrenheit"),
+    ('°', "度", "degrees"),
+];
+
+/// Superscript characters and the plain characters they stand for
+const SUPERSCRIPTS: &[(char, char)] = &[
+    ('⁰', '0'),
+    ('¹', '1'),
+    ('²', '2'),
+    ('³', '3'),
+    ('⁴', '4'),
+    ('⁵', '5'),
+    ('⁶', '6'),
+    ('⁷', '7'),
+    ('⁸', '8'),
+    ('⁹', '9'),
+    ('⁻', '-'),
+    ('⁺', '+'),
+];
+
+fn superscript(c: char) -> Option<char> {
+    SUPERSCRIPTS
+        .iter()
+        .find(|(sup, _)| *sup == c)
+        .map(|(_, plain)| *plain)
+}
+
+fn subscript(c: char) -> Option<char> {
+    // Subscript digits U+2080..U+2089 map to '0'..'9' in order
+    matches!(c, '₀'..='₉').then(|| char::from(b'0' + (c as u32 - '₀' as u32) as u8))
+}
+
+/// Check whether a character is a math or technical symbol that TTS engines
+/// tend to skip
+///
+/// Covers the symbol blocks of the built-in table, so a symbol missing from
+/// it is reported as unknown rather than silently dropped by the engine.
+pub(crate) fn is_symbol(c: char) -> bool {
+    matches!(c, '¹' | '²' | '³' | '°' | '±' | '×' | '÷')
+        || matches!(c as u32,
+            0x2070..=0x209F // Superscripts and subscripts
+            | 0x2100..=0x214F // Letterlike symbols
+            | 0x2190..=0x21FF // Arrows
+            | 0x2200..=0x22FF // Mathematical operators
+            | 0x2300..=0x23FF // Miscellaneous technical
+            | 0x27C0..=0x27EF // Miscellaneous mathematical symbols A
+            | 0x2980..=0x2AFF // Miscellaneous mathematical symbols B and
+                              // supplemental operators
+        )
+}
+
+/// Built-in reading of `c`, reading "°C" and "°F" as the temperature units
+fn symbol_reading(
+    c: char,
+    next: Option<char>,
+) -> Option<&'static (char, &'static str, &'static str)> {
+    let c = match (c, next) {
+        ('°', Some('C')) => '℃',
+        ('°', Some('F')) => '℉',
+        _ => c,
+    };
+    SYMBOL_READINGS.iter().find(|(symbol, ..)| *symbol == c)
+}
+
+/// Spoken reading of a superscript run such as "²" or "⁻¹"
+fn power_reading(exponent: &str, language: SymbolLanguage) -> String {
+    match (exponent, language) {
+        ("2", SymbolLanguage::Chinese) => "的平方".to_string(),
+        ("3", SymbolLanguage::Chinese) => "的立方".to_string(),
+        ("2", SymbolLanguage::English) => "squared".to_string(),
+        ("3", SymbolLanguage::English) => "cubed".to_string(),
+        (_, SymbolLanguage::Chinese) => format!("的{}次方", exponent.replace('-', "负")),
+        (_, SymbolLanguage::English) => {
+            format!("to the power of {}", exponent.replace('-', "minus "))
+        }
+    }
+}
+
+/// Replace math and technical symbols with spoken readings
+///
+/// `custom` readings take precedence over the built-in table. Superscript
+/// runs become powers ("x²" reads "x的平方"), subscript digits become plain
+/// digits, and "°C"/"°F" read as temperatures. Backtick code spans are
+/// copied unchanged. Symbols with no reading are kept and pushed to
+/// `unknown` in order of appearance. Text without any symbol is borrowed.
+pub(crate) fn verbalize_symbols<'a>(
+    text: &'a str,
+    language: SymbolLanguage,
+    custom: &HashMap<char, String>,
+    unknown: &mut Vec<char>,
+) -> Cow<'a, str> {
+    let touched = |c: char| {
+        custom.contains_key(&c)
+            || superscript(c).is_some()
+            || subscript(c).is_some()
+            || is_symbol(c)
+            || symbol_reading(c, None).is_some()
+    };
+    if !text.chars().any(touched) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut output = String::with_capacity(text.len() + text.len() / 4);
+    for (is_code, span) in code_spans(text) {
+        if is_code {
+            output.push_str(span);
+        } else {
+            verbalize_span(span, language, custom, unknown, &mut output);
+        }
+    }
+    Cow::Owned(output)
+}
+
+fn verbalize_span(
+    text: &str,
+    language: SymbolLanguage,
+    custom: &HashMap<char, String>,
+    unknown: &mut Vec<char>,
+    output: &mut String,
+) {
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let reading = if let Some(reading) = custom.get(&c) {
+            reading.clone()
+        } else if let Some(plain) = superscript(c) {
+            let mut exponent = String::from(plain);
+            while let Some(plain) = chars.peek().and_then(|&next| superscript(next)) {
+                exponent.push(plain);
+                chars.next();
+            }
+            power_reading(&exponent, language)
+        } else if let Some(digit) = subscript(c) {
+            output.push(digit);
+            continue;
+        } else if let Some(&(_, chinese, english)) = symbol_reading(c, chars.peek().copied()) {
+            if c == '°' && matches!(chars.peek(), Some('C' | 'F')) {
+                chars.next();
+            }
+            match language {
+                SymbolLanguage::Chinese => chinese.to_string(),
+                SymbolLanguage::English => english.to_string(),
+            }
+        } else {
+            if is_symbol(c) {
+                unknown.push(c);
+            }
+            output.push(c);
+            continue;
+        };
+
+        push_reading(output, &reading, chars.peek().copied(), language);
+    }
+}
+
+/// Append a reading, spacing it from neighbouring words in English
+fn push_reading(output: &mut String, reading: &str, next: Option<char>, language: SymbolLanguage) {
+    let spaced = language == SymbolLanguage::English;
+    if spaced
+        && output
+            .chars()
+            .next_back()
+            .is_some_and(|c| !c.is_whitespace())
+    {
+        output.push(' ');
+    }
+    output.push_str(reading);
+    if spaced && next.is_some_and(|c| c.is_alphanumeric() || c == '(') {
+        output.push(' ');
+    }
+}
+
+/// Pause implied by the newlines after a block of text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Pause {
+    /// A single newline
+    Short,
+    /// A blank line
+    Long,
+}
+
+/// Split text at newlines into trimmed blocks and the pause following each
+///
+/// A single newline yields [`Pause::Short`] and one or more blank lines
+/// yield [`Pause::Long`]. The last block has no pause, and leading or
+/// trailing newlines are ignored.
+pub(crate) fn split_at_newlines(text: &str) -> Vec<(&str, Option<Pause>)> {
+    let mut blocks: Vec<(&str, Option<Pause>)> = Vec::new();
+    let mut newlines = 0;
+
+    for line in text.split('\n') {
+        let line = line.trim();
+        if line.is_empty() {
+            newlines += 1;
+            continue;
+        }
+        if let Some((_, pause)) = blocks.last_mut() {
+            *pause = Some(if newlines >= 2 {
+                Pause::Long
+            } else {
+                Pause::Short
+            });
+        }
+        blocks.push((line, None));
+        // The separator before the next line counts as one newline
+        newlines = 1;
+    }
+
+    blocks
+}
+
+/// Pattern whose matches are segment boundaries
+#[derive(Debug, Clone)]
+pub(crate) struct SplitPattern {
+    regex: Regex,
+    keep_delimiter: bool,
+}
+
+impl SplitPattern {
+    /// Compile `pattern` in multi-line mode, so `^` and `$` match at line
+    /// boundaries, or return why it is invalid
+    pub(crate) fn new(pattern: &str, keep_delimiter: bool) -> Result<Self, String> {
+        let regex = RegexBuilder::new(pattern)
+            .multi_line(true)
+            .build()
+            .map_err(|e| format!("Invalid split regex: {}", e))?;
+        Ok(Self {
+            regex,
+            keep_delimiter,
+        })
+    }
+
+    /// Whether the pattern matches anywhere in `text`
+    pub(crate) fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+
+    /// Split text at every match into trimmed, non-empty pieces
+    ///
+    /// A kept delimiter starts the piece after it, as a chapter heading
+    /// starts its chapter.
+    pub(crate) fn split<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        for m in self.regex.find_iter(text) {
+            pieces.push(&text[start..m.start()]);
+            start = if self.keep_delimiter {
+                m.start()
+            } else {
+                m.end()
+            };
+        }
+        pieces.push(&text[start..]);
+
+        pieces
+            .into_iter()
+            .map(str::trim)
+            .filter(|piece| !piece.is_empty())
+            .collect()
+    }
+}
+
+/// Check whether a character belongs to a CJK script or CJK punctuation
+pub(crate) fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3000..=0x303F // CJK symbols and punctuation
+        | 0x3040..=0x30FF // Hiragana and Katakana
+        | 0x3400..=0x4DBF // CJK unified ideographs extension A
+        | 0x4E00..=0x9FFF // CJK unified ideographs
+        | 0xAC00..=0xD7AF // Hangul syllables
+        | 0xF900..=0xFAFF // CJK compatibility ideographs
+        | 0xFF00..=0xFFEF // Halfwidth and fullwidth forms
+        | 0x20000..=0x2FA1F // CJK extensions B-F and supplement
+    )
+}
+
+fn is_url(token: &str) -> bool {
+    let lower = token.to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://") || lower.starts_with("www.")
+}
+
+/// Check whether a character is CJK punctuation or a fullwidth form
+pub(crate) fn is_cjk_punctuation(c: char) -> bool {
+    matches!(c as u32, 0x3000..=0x303F | 0xFF00..=0xFFEF)
+}
+
+/// Check whether a character can be part of a Latin token next to CJK text
+fn is_latin(c: char) -> bool {
+    c.is_ascii_alphanumeric()
+        || matches!(c, '%' | '$' | '#' | '+')
+        || (c.is_alphabetic() && !is_cjk(c))
+}
+
+fn is_spacing_boundary(a: char, b: char) -> bool {
+    let cjk = |c| is_cjk(c) && !is_cjk_punctuation(c);
+    (cjk(a) && is_latin(b)) || (is_latin(a) && cjk(b))
+}
+
+/// Apply a preprocessing step to `text`, keeping it borrowed if the step
+/// changes nothing
+///
+/// A step that changes nothing returns its input, or a slice of it, as
+/// [`Cow::Borrowed`].
+pub(crate) fn rewrite<'a>(
+    text: Cow<'a, str>,
+    step: impl FnOnce(&str) -> Cow<'_, str>,
+) -> Cow<'a, str> {
+    let range = match step(&text) {
+        Cow::Owned(changed) => return Cow::Owned(changed),
+        Cow::Borrowed(kept) => {
+            let start = kept.as_ptr() as usize - text.as_ptr() as usize;
+            start..start + kept.len()
+        }
+    };
+    match text {
+        Cow::Borrowed(text) => Cow::Borrowed(&text[range]),
+        Cow::Owned(mut text) => {
+            text.truncate(range.end);
+            text.drain(..range.start);
+            Cow::Owned(text)
+        }
+    }
+}
+
+/// Normalize the spaces between CJK and Latin text according to `mode`
+///
+/// Backtick code spans and fenced code blocks are copied unchanged, as are
+/// URLs, which run to the next whitespace or CJK punctuation. Only spaces
+/// and tabs at a boundary are touched; newlines are kept. Text already
+/// spaced according to `mode` is borrowed.
+pub(crate) fn apply_cjk_latin_spacing(text: &str, mode: SpacingMode) -> Cow<'_, str> {
+    if is_spaced(text, mode) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut output = String::with_capacity(text.len() + text.len() / 8);
+    for (is_code, span) in code_spans(text) {
+        if is_code {
+            output.push_str(span);
+        } else {
+            space_span(span, mode, &mut output);
+        }
+    }
+    Cow::Owned(output)
+}
+
+/// Whether every boundary in `text` is already spaced according to `mode`
+///
+/// Code spans and URLs are checked too, so this may report work where
+/// [`apply_cjk_latin_spacing`] changes nothing, but never the reverse.
+fn is_spaced(text: &str, mode: SpacingMode) -> bool {
+    let mut prev = None;
+    let mut gap = "";
+    let mut idx = 0;
+    while let Some(c) = text[idx..].chars().next() {
+        let rest = &text[idx..];
+        if c == ' ' || c == '\t' {
+            let len = rest.find(|c| c != ' ' && c != '\t').unwrap_or(rest.len());
+            gap = &rest[..len];
+            idx += len;
+            continue;
+        }
+        if prev.is_some_and(|p| is_spacing_boundary(p, c)) {
+            let spaced = match mode {
+                SpacingMode::Insert => gap == " ",
+                SpacingMode::Remove => gap.is_empty(),
+            };
+            if !spaced {
+                return false;
+            }
+        }
+        gap = "";
+        prev = Some(c);
+        idx += c.len_utf8();
+    }
+    true
+}
+
+/// Split text into alternating prose and backtick-delimited code spans
+///
+/// An unterminated code span runs to the end of the text.
+pub(crate) fn code_spans(text: &str) -> Vec<(bool, &str)> {
+    let mut spans = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('`') {
+        let fence = if rest[start..].starts_with("```") {
+            "```"
+        } else {
+            "`"
+        };
+        let body = start + fence.len();
+        let end = rest[body..]
+            .find(fence)
+            .map_or(rest.len(), |end| body + end + fence.len());
+        spans.push((false, &rest[..start]));
+        spans.push((true, &rest[start..end]));
+        rest = &rest[end..];
+    }
+    spans.push((false, rest));
+    spans
+}
+
+fn space_span(text: &str, mode: SpacingMode, output: &mut String) {
+    let mut prev: Option<char> = None;
+    let mut gap = "";
+    let mut idx = 0;
+
+    while let Some(c) = text[idx..].chars().next() {
+        let rest = &text[idx..];
+        if c == ' ' || c == '\t' {
+            let len = rest.find(|c| c != ' ' && c != '\t').unwrap_or(rest.len());
+            gap = &rest[..len];
+            idx += len;
+            continue;
+        }
+
+        // A URL is copied as one unit so nothing inside it is respaced
+        let glued = gap.is_empty() && prev.is_some_and(|p| p.is_ascii_alphanumeric());
+        let len = if is_url(rest) && !glued {
+            rest.find(|c: char| c.is_whitespace() || is_cjk_punctuation(c))
+                .unwrap_or(rest.len())
+        } else {
+            c.len_utf8()
+        };
+        let atom = &rest[..len];
+
+        match prev {
+            Some(p) if is_spacing_boundary(p, c) => {
+                if mode == SpacingMode::Insert {
+                    output.push(' ');
+                }
+            }
+            _ => output.push_str(gap),
+        }
+        output.push_str(atom);
+        gap = "";
+        prev = atom.chars().last();
+        idx += len;
+    }
+    output.push_str(gap);
+}
+
+/// Rewrite tokens longer than `max_chars` according to `policy`
+///
+/// URLs are replaced with [`URL_PLACEHOLDER`] regardless of the policy. Each
+/// rewritten token produces a [`Warning::LongToken`]. Text without long
+/// tokens is borrowed.
+pub(crate) fn apply_long_token_policy<'a>(
+    text: &'a str,
+    max_chars: usize,
+    policy: LongTokenPolicy,
+    warnings: &mut Vec<Warning>,
+) -> Cow<'a, str> {
+    let mut tokens = text.split(|c: char| c.is_whitespace() || is_cjk(c));
+    if !tokens.any(|token| token.chars().count() > max_chars) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut output = String::with_capacity(text.len());
+    let mut token_start = None;
+
+    let mut flush = |output: &mut String, token: &str| {
+        let chars = token.chars().count();
+        if chars <= max_chars {
+            output.push_str(token);
+            return;
+        }
+        warnings.push(Warning::LongToken {
+            preview: token.chars().take(PREVIEW_CHARS).collect(),
+            chars,
+        });
+        if is_url(token) {
+            output.push_str(URL_PLACEHOLDER);
+        } else {
+            output.push_str(&policy.apply(token));
+        }
+    };
+
+    for (idx, c) in text.char_indices() {
+        if c.is_whitespace() || is_cjk(c) {
+            if let Some(start) = token_start.take() {
+                flush(&mut output, &text[start..idx]);
+            }
+            output.push(c);
+        } else if token_start.is_none() {
+            token_start = Some(idx);
+        }
+    }
+    if let Some(start) = token_start {
+        flush(&mut output, &text[start..]);
+    }
+
+    Cow::Owned(output)
+}
+
+/// What to do with inline footnote and reference markers
+///
+/// Markers are bracketed numbers such as `[12]`, `[1,2]`, `[3-5]` or
+/// `［注2］`, and superscript digit runs attached to CJK text or
+/// punctuation, such as "研究表明¹". Superscripts after Latin letters or
+/// digits are read as exponents instead, so "x²" is left alone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FootnotePolicy {
+    /// Remove markers, as readers skip them
+    #[default]
+    Strip,
+    /// Read markers aloud, as in "参考文献一" or "注二"
+    Verbalize,
+    /// Leave markers in the text
+    Keep,
+}
+
+/// What to do with footnote bodies, the lines starting with a marker
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FootnoteBodies {
+    /// Read them where they appear
+    #[default]
+    Keep,
+    /// Leave them out
+    Skip,
+    /// Move them under a "注释" ("Notes") heading at the end of the text
+    MoveToEnd,
+}
+
+/// An inline footnote marker
+struct Marker {
+    /// Byte length of the marker
+    len: usize,
+    /// Whether the marker is a note (`[注2]`, `¹`) rather than a reference
+    note: bool,
+    /// Referenced numbers, a list or a range
+    numbers: Vec<u32>,
+    range: bool,
+}
+
+/// Parse a bracketed marker such as `[12]`, `[1,2]`, `[3-5]` or `［注2］` at
+/// the start of `text`
+fn bracket_marker(text: &str) -> Option<Marker> {
+    let mut chars = text.char_indices();
+    if !matches!(chars.next(), Some((_, '[' | '［'))) {
+        return None;
+    }
+    let mut chars = chars.peekable();
+    let note = chars.next_if(|&(_, c)| c == '注').is_some();
+    let mut numbers = Vec::new();
+    let mut range = false;
+    let mut current: Option<u32> = None;
+    for (idx, c) in chars {
+        match c {
+            '0'..='9' | '０'..='９' => {
+                let digit = c.to_digit(10).unwrap_or_else(|| c as u32 - '０' as u32);
+                current = Some(current.unwrap_or(0).checked_mul(10)?.checked_add(digit)?);
+            }
+            ',' | '，' | '、' | '-' | '–' | '~' | '～' => {
+                numbers.push(current.take()?);
+                range |= !matches!(c, ',' | '，' | '、');
+            }
+            ' ' => {}
+            ']' | '］' => {
+                numbers.push(current?);
+                // A range has exactly two ends
+                if range && numbers.len() != 2 {
+                    return None;
+                }
+                return Some(Marker {
+                    len: idx + c.len_utf8(),
+                    note,
+                    numbers,
+                    range,
+                });
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// Parse a superscript digit run at the start of `text`
+fn superscript_marker(text: &str) -> Option<Marker> {
+    let mut len = 0;
+    let mut number = String::new();
+    for c in text.chars() {
+        match superscript(c) {
+            Some(digit @ '0'..='9') => {
+                number.push(digit);
+                len += c.len_utf8();
+            }
+            _ => break,
+        }
+    }
+    Some(Marker {
+        len,
+        note: true,
+        numbers: vec![number.parse().ok()?],
+        range: false,
+    })
+}
+
+/// Chinese reading of `n`, falling back to digits above 99
+fn chinese_number(n: u32) -> String {
+    const DIGITS: [char; 10] = ['零', '一', '二', '三', '四', '五', '六', '七', '八', '九'];
+    let (tens, ones) = (n / 10, (n % 10) as usize);
+    match tens {
+        0 => DIGITS[ones].to_string(),
+        1..=9 => {
+            let mut reading = String::new();
+            if tens > 1 {
+                reading.push(DIGITS[tens as usize]);
+            }
+            reading.push('十');
+            if ones > 0 {
+                reading.push(DIGITS[ones]);
+            }
+            reading
+        }
+        _ => n.to_string(),
+    }
+}
+
+impl Marker {
+    fn reading(&self, language: SymbolLanguage) -> String {
+        match language {
+            SymbolLanguage::Chinese => {
+                let numbers: Vec<String> =
+                    self.numbers.iter().map(|&n| chinese_number(n)).collect();
+                let kind = if self.note { "注" } else { "参考文献" };
+                let separator = if self.range { "至" } else { "、" };
+                format!("{}{}", kind, numbers.join(separator))
+            }
+            SymbolLanguage::English => {
+                let numbers: Vec<String> = self.numbers.iter().map(u32::to_string).collect();
+                let kind = if self.note { "note" } else { "reference" };
+                let plural = if numbers.len() > 1 { "s" } else { "" };
+                let list = match numbers.split_last() {
+                    Some((last, rest)) if !rest.is_empty() => {
+                        let conjunction = if self.range { "to" } else { "and" };
+                        format!("{} {} {}", rest.join(", "), conjunction, last)
+                    }
+                    _ => numbers.join(""),
+                };
+                format!("{}{} {}", kind, plural, list)
+            }
+        }
+    }
+}
+
+/// Marker starting at `text`, given the character before it
+///
+/// Superscripts count at the start of a line, where they open a body.
+fn marker_at(text: &str, previous: Option<char>) -> Option<Marker> {
+    if text.starts_with(['[', '［']) {
+        bracket_marker(text)
+    } else {
+        match previous {
+            None | Some('\n') => superscript_marker(text),
+            Some(c) if !c.is_whitespace() && !c.is_ascii_alphanumeric() => superscript_marker(text),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `line` is a footnote body, a line starting with a marker
+fn is_footnote_body(line: &str) -> bool {
+    let line = line.trim_start();
+    let marker = if line.starts_with(['[', '［']) {
+        bracket_marker(line)
+    } else {
+        superscript_marker(line)
+    };
+    marker.is_some_and(|marker| !line[marker.len..].trim().is_empty())
+}
+
+/// Handle footnote markers and bodies
+///
+/// Bodies are handled first, outside code fences, then `policy` is applied
+/// to every marker outside backtick code spans, including those starting
+/// relocated bodies. Readings use `language`. Text without bodies or
+/// markers is borrowed.
+pub(crate) fn apply_footnote_policy(
+    text: &str,
+    policy: FootnotePolicy,
+    bodies: FootnoteBodies,
+    language: SymbolLanguage,
+) -> Cow<'_, str> {
+    let text = match bodies {
+        FootnoteBodies::Keep => Cow::Borrowed(text),
+        FootnoteBodies::Skip | FootnoteBodies::MoveToEnd => {
+            let mut kept = Vec::new();
+            let mut moved = Vec::new();
+            let mut in_fence = false;
+            for line in text.lines() {
+                if line.trim_start().starts_with("```") {
+                    in_fence = !in_fence;
+                }
+                if !in_fence && is_footnote_body(line) {
+                    moved.push(line.trim());
+                } else {
+                    kept.push(line);
+                }
+            }
+            if moved.is_empty() && !text.contains('\r') {
+                // Joining the lines again would only trim the end
+                Cow::Borrowed(text.trim_end())
+            } else {
+                let mut text = kept.join("\n").trim_end().to_string();
+                if bodies == FootnoteBodies::MoveToEnd && !moved.is_empty() {
+                    text.push_str(match language {
+                        SymbolLanguage::Chinese => "\n\n注释\n",
+                        SymbolLanguage::English => "\n\nNotes\n",
+                    });
+                    text.push_str(&moved.join("\n"));
+                }
+                Cow::Owned(text)
+            }
+        }
+    };
+    let marked = |c: char| c == '[' || c == '［' || superscript(c).is_some();
+    if policy == FootnotePolicy::Keep || !text.contains(marked) {
+        return text;
+    }
+
+    let mut output = String::with_capacity(text.len());
+    for (is_code, span) in code_spans(&text) {
+        if is_code {
+            output.push_str(span);
+            continue;
+        }
+        let mut rest = span;
+        while let Some(c) = rest.chars().next() {
+            let Some(marker) = marker_at(rest, output.chars().next_back()) else {
+                output.push(c);
+                rest = &rest[c.len_utf8()..];
+                continue;
+            };
+            rest = &rest[marker.len..];
+            match policy {
+                FootnotePolicy::Verbalize => {
+                    push_reading(
+                        &mut output,
+                        &marker.reading(language),
+                        rest.chars().next(),
+                        language,
+                    );
+                }
+                _ => {
+                    // Drop the space before the marker, or after it at the
+                    // start of a line
+                    let trimmed = output.trim_end_matches([' ', '\t']).len();
+                    output.truncate(trimmed);
+                    if output.is_empty() || output.ends_with('\n') {
+                        rest = rest.trim_start_matches([' ', '\t']);
+                    }
+                }
+            }
+        }
+    }
+    Cow::Owned(output)
+}
+
+/// Section name that returns to the converter's own settings, unless a
+/// profile of that name is registered
+pub const DEFAULT_SECTION: &str = "default";
+
+/// Name of the `<!-- section: name -->` marker at the start of `text`, and
+/// the marker's byte length
+fn section_marker(text: &str) -> Option<(&str, usize)> {
+    let body = text.strip_prefix("<!--")?;
+    let end = body.find("-->")?;
+    let name = body[..end].trim().strip_prefix("section:")?.trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((name, "<!--".len() + end + "-->".len()))
+}
+
+/// Split text at `<!-- section: name -->` markers, dropping the markers
+///
+/// Returns each section's marker name and text, starting with the text
+/// before the first marker, which has no name. A marker on a line of its
+/// own is dropped with its line break, so it adds no blank line between
+/// the sections. Other HTML comments are left in the text.
+pub(crate) fn split_sections(text: &str) -> Vec<(Option<&str>, &str)> {
+    let mut sections = Vec::new();
+    let mut name = None;
+    let mut start = 0;
+    let mut search = 0;
+    while let Some(offset) = text[search..].find("<!--") {
+        let marker_start = search + offset;
+        match section_marker(&text[marker_start..]) {
+            Some((next, len)) => {
+                sections.push((name, &text[start..marker_start]));
+                name = Some(next);
+                start = marker_start + len;
+                let line_start = text[..marker_start].rfind('\n').map_or(0, |i| i + 1);
+                if text[line_start..marker_start].trim().is_empty() {
+                    let rest = text[start..].trim_start_matches([' ', '\t']);
+                    if let Some(after) = rest
+                        .strip_prefix("\r\n")
+                        .or_else(|| rest.strip_prefix('\n'))
+                    {
+                        start = text.len() - after.len();
+                    }
+                }
+                search = start;
+            }
+            None => search = marker_start + "<!--".len(),
+        }
+    }
+    sections.push((name, &text[start..]));
+    sections
+}
+
+/// Remove `<!-- section: name -->` markers, as [`split_sections`] does
+pub(crate) fn strip_section_markers(text: &str) -> Cow<'_, str> {
+    let sections = split_sections(text);
+    if sections.len() == 1 {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(sections.into_iter().map(|(_, text)| text).collect())
+}
+
+/// Byte offset of the bracket closing the one that `text` starts with, on
+/// the same line
+fn closing_bracket(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    for (idx, c) in text.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(idx);
+            }
+        } else if c == '\n' {
+            return None;
+        }
+    }
+    None
+}
+
+/// Parse a markdown link or image at the start of `text`, returning its
+/// byte length and the text to read in its place
+fn markdown_link(text: &str) -> Option<(usize, String)> {
+    let label_start = usize::from(text.starts_with("!["));
+    if !text[label_start..].starts_with('[') {
+        return None;
+    }
+    let label_end = label_start + closing_bracket(&text[label_start..], '[', ']')?;
+    let target = &text[label_end + 1..];
+    if !target.starts_with('(') {
+        return None;
+    }
+    let len = label_end + 1 + closing_bracket(target, '(', ')')? + 1;
+    // Link text may itself hold an image, as in badges
+    let label = strip_link_urls(&text[label_start + 1..label_end]);
+    Some((len, label.trim().to_string()))
+}
+
+/// Replace markdown links with their text and images with their alt text
+///
+/// `[文档](https://example.com)` reads "文档" and `![架构图](a.png)` reads
+/// "架构图"; an image without alt text is dropped. Links must sit on one
+/// line. Backtick code spans are copied unchanged. Text without links is
+/// borrowed.
+pub(crate) fn strip_link_urls(text: &str) -> Cow<'_, str> {
+    // Every link has its label's closing bracket right before its target
+    if !text.contains("](") {
+        return Cow::Borrowed(text);
+    }
+
+    let mut output = String::with_capacity(text.len());
+    for (is_code, span) in code_spans(text) {
+        if is_code {
+            output.push_str(span);
+            continue;
+        }
+        let mut rest = span;
+        while let Some(c) = rest.chars().next() {
+            let link = if c == '[' || c == '!' {
+                markdown_link(rest)
+            } else {
+                None
+            };
+            match link {
+                Some((len, label)) => {
+                    output.push_str(&label);
+                    rest = &rest[len..];
+                }
+                None => {
+                    output.push(c);
+                    rest = &rest[c.len_utf8()..];
+                }
+            }
+        }
+    }
+    Cow::Owned(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_long_url_uses_placeholder() {
+        let url = format!("https://example.com/{}", "a".repeat(300));
+        let text = format!("请访问 {} 获取详情。", url);
+        let mut warnings = Vec::new();
+        let output = apply_long_token_policy(&text, 100, LongTokenPolicy::Skip, &mut warnings);
+        assert_eq!(output, "请访问 一个链接 获取详情。");
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(&warnings[0], Warning::LongToken { chars, .. } if *chars == url.len()));
+    }
+
+    #[test]
+    fn test_base64_blob_policies() {
+        let blob = "QUJD".repeat(750);
+        let text = format!("数据：{}。", blob);
+        let mut warnings = Vec::new();
+
+        let skipped = apply_long_token_policy(&text, 100, LongTokenPolicy::Skip, &mut warnings);
+        assert_eq!(skipped, "数据：。");
+
+        let truncated =
+            apply_long_token_policy(&text, 100, LongTokenPolicy::Truncate(4), &mut warnings);
+        assert_eq!(truncated, "数据：QUJD。");
+
+        let spelled =
+            apply_long_token_policy(&text, 100, LongTokenPolicy::SpellFirst(4), &mut warnings);
+        assert_eq!(spelled, "数据：QUJD等。");
+
+        assert_eq!(warnings.len(), 3);
+    }
+
+    #[test]
+    fn test_long_cjk_run_untouched() {
+        let text = "汉字".repeat(500);
+        let mut warnings = Vec::new();
+        let output = apply_long_token_policy(&text, 100, LongTokenPolicy::Skip, &mut warnings);
+        assert_eq!(output, text);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_split_at_newlines() {
+        let text = "\n第一行。\r\n第二行。\n\n  \n第三段。\n";
+        assert_eq!(
+            split_at_newlines(text),
+            vec![
+                ("第一行。", Some(Pause::Short)),
+                ("第二行。", Some(Pause::Long)),
+                ("第三段。", None),
+            ]
+        );
+        assert_eq!(split_at_newlines("一行"), vec![("一行", None)]);
+        assert!(split_at_newlines("\n \n").is_empty());
+    }
+
+    #[test]
+    fn test_split_pattern() {
+        let text = "序言。\n第一章 开始。正文。\n第二章 结束。";
+        let keep = SplitPattern::new("^第[一二三]章", true).unwrap();
+        assert_eq!(
+            keep.split(text),
+            vec!["序言。", "第一章 开始。正文。", "第二章 结束。"]
+        );
+        let drop = SplitPattern::new("^第[一二三]章", false).unwrap();
+        assert_eq!(drop.split(text), vec!["序言。", "开始。正文。", "结束。"]);
+        assert_eq!(keep.split("没有章节。"), vec!["没有章节。"]);
+        assert!(SplitPattern::new("第(一章", true)
+            .unwrap_err()
+            .starts_with("Invalid split regex"));
+    }
+
+    #[test]
+    fn test_cjk_latin_spacing_insert() {
+        let cases = [
+            ("使用GPU加速", "使用 GPU 加速"),
+            ("使用  GPU\t加速", "使用 GPU 加速"),
+            ("需要3GB内存", "需要 3GB 内存"),
+            ("增长了50%以上", "增长了 50% 以上"),
+            ("花费$20左右", "花费 $20 左右"),
+            ("学习C++和C#语言", "学习 C++ 和 C# 语言"),
+            ("支持GPU，速度快。", "支持 GPU，速度快。"),
+            ("（GPU）加速", "（GPU）加速"),
+            ("版本v1.2发布", "版本 v1.2 发布"),
+            ("第一行GPU\n第二行", "第一行 GPU\n第二行"),
+            ("Hello world", "Hello world"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                apply_cjk_latin_spacing(input, SpacingMode::Insert),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_cjk_latin_spacing_remove() {
+        let cases = [
+            ("使用 GPU 加速", "使用GPU加速"),
+            ("需要 3GB 内存", "需要3GB内存"),
+            ("增长了 50% 以上", "增长了50%以上"),
+            ("支持 GPU ，速度快。", "支持GPU ，速度快。"),
+            ("中文 中文 and English", "中文 中文and English"),
+            ("第一行 GPU\n 第二行", "第一行GPU\n 第二行"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                apply_cjk_latin_spacing(input, SpacingMode::Remove),
+                expected,
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_cjk_latin_spacing_idempotent() {
+        let text = "在2024年用GPU训练了3GB的模型，提速50%。";
+        for mode in [SpacingMode::Insert, SpacingMode::Remove] {
+            let once = apply_cjk_latin_spacing(text, mode);
+            assert_eq!(apply_cjk_latin_spacing(&once, mode), once);
+        }
+    }
+
+    #[test]
+    fn test_cjk_latin_spacing_skips_code_and_urls() {
+        let text = "运行`cargo build中文`命令，见 https://例子.com/文档GPU 和```\nlet x = \"中文GPU\";\n```结束";
+        assert_eq!(
+            apply_cjk_latin_spacing(text, SpacingMode::Insert),
+            "运行`cargo build中文`命令，见 https://例子.com/文档GPU 和```\nlet x = \"中文GPU\";\n```结束"
+        );
+        assert_eq!(
+            apply_cjk_latin_spacing("访问 www.a.com文档 吧", SpacingMode::Remove),
+            "访问www.a.com文档 吧"
+        );
+    }
+
+    #[test]
+    fn test_verbalize_formula() {
+        let verbalize = |text: &str, language| {
+            let mut unknown = Vec::new();
+            let output = verbalize_symbols(text, language, &HashMap::new(), &mut unknown);
+            (output.into_owned(), unknown)
+        };
+
+        let text = "圆的方程是x²+y²=r²，π≈3.14，温度约25℃→30°C，x⁻¹与a₁。";
+        let (chinese, unknown) = verbalize(text, SymbolLanguage::Chinese);
+        assert_eq!(
+            chinese,
+            "圆的方程是x的平方+y的平方=r的平方，派约等于3.14，温度约25摄氏度指向30摄氏度，x的负1次方与a1。"
+        );
+        assert!(unknown.is_empty());
+        assert!(!chinese.chars().any(is_symbol));
+
+        let (english, _) = verbalize("x³≈2×y¹⁰ at 20°F", SymbolLanguage::English);
+        assert_eq!(
+            english,
+            "x cubed approximately equals 2 times y to the power of 10 at 20 degrees Fahrenheit"
+        );
+
+        let (output, unknown) = verbalize("∮E⊕B `x²`", SymbolLanguage::Chinese);
+        assert_eq!(output, "∮E⊕B `x²`");
+        assert_eq!(unknown, vec!['∮', '⊕']);
+    }
+
+    #[test]
+    fn test_verbalize_custom_readings() {
+        let custom = HashMap::from([('→', "变为".to_string()), ('∮', "环路积分".to_string())]);
+        let mut unknown = Vec::new();
+        let output = verbalize_symbols("A→B，∮", SymbolLanguage::Chinese, &custom, &mut unknown);
+        assert_eq!(output, "A变为B，环路积分");
+        assert!(unknown.is_empty());
+    }
+
+    #[test]
+    fn test_short_tokens_untouched() {
+        let text = "Hello world, see https://a.io now.";
+        let mut warnings = Vec::new();
+        let output = apply_long_token_policy(text, 100, LongTokenPolicy::Skip, &mut warnings);
+        assert_eq!(output, text);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_inline_footnote_markers() {
+        let text = "研究表明[1]，结果显著［注2］。见文献[3-5]与[6,7]。x² 与 面积¹⁰ `a[1]`";
+        let strip = |text| {
+            apply_footnote_policy(
+                text,
+                FootnotePolicy::Strip,
+                FootnoteBodies::Keep,
+                SymbolLanguage::Chinese,
+            )
+        };
+        assert_eq!(
+            strip(text),
+            "研究表明，结果显著。见文献与。x² 与 面积 `a[1]`"
+        );
+        assert_eq!(
+            strip("as shown [12]. Next [a] and [1-2-3]"),
+            "as shown. Next [a] and [1-2-3]"
+        );
+
+        let chinese = apply_footnote_policy(
+            text,
+            FootnotePolicy::Verbalize,
+            FootnoteBodies::Keep,
+            SymbolLanguage::Chinese,
+        );
+        assert_eq!(
+            chinese,
+            "研究表明参考文献一，结果显著注二。见文献参考文献三至五与参考文献六、七。x² 与 面积注十 `a[1]`"
+        );
+
+        let english = apply_footnote_policy(
+            "as shown [12]. See [1,2,3] and [4-6].",
+            FootnotePolicy::Verbalize,
+            FootnoteBodies::Keep,
+            SymbolLanguage::English,
+        );
+        assert_eq!(
+            english,
+            "as shown reference 12. See references 1, 2 and 3 and references 4 to 6."
+        );
+    }
+
+    #[test]
+    fn test_footnote_bodies() {
+        let text = "正文一[1]。\n正文二²。\n\n[1] 张三，2020。\n² 李四的评论。";
+        let handle =
+            |policy, bodies| apply_footnote_policy(text, policy, bodies, SymbolLanguage::Chinese);
+
+        assert_eq!(
+            handle(FootnotePolicy::Strip, FootnoteBodies::Skip),
+            "正文一。\n正文二。"
+        );
+        assert_eq!(
+            handle(FootnotePolicy::Strip, FootnoteBodies::Keep),
+            "正文一。\n正文二。\n\n张三，2020。\n李四的评论。"
+        );
+
+        let text = "[1] 张三，2020。\n正文一[1]。\n```\n[2] code\n```";
+        let moved = apply_footnote_policy(
+            text,
+            FootnotePolicy::Verbalize,
+            FootnoteBodies::MoveToEnd,
+            SymbolLanguage::Chinese,
+        );
+        assert_eq!(
+            moved,
+            "正文一参考文献一。\n```\n[2] code\n```\n\n注释\n参考文献一 张三，2020。"
+        );
+    }
+
+    #[test]
+    fn test_split_sections() {
+        let text = "开场。<!-- section: intro -->\n欢迎！\n<!--section:ad-->广告。<!-- 备注 --><!-- section: two words -->";
+        assert_eq!(
+            split_sections(text),
+            vec![
+                (None, "开场。"),
+                (Some("intro"), "\n欢迎！\n"),
+                (Some("ad"), "广告。<!-- 备注 --><!-- section: two words -->"),
+            ]
+        );
+        assert_eq!(split_sections("无标记"), vec![(None, "无标记")]);
+        assert_eq!(
+            split_sections("开场。\n  <!-- section: ad -->  \n广告。"),
+            vec![(None, "开场。\n  "), (Some("ad"), "广告。")]
+        );
+    }
+
+    #[test]
+    fn test_strip_section_markers() {
+        assert_eq!(
+            strip_section_markers(
+                "开场。\n<!-- section: ad -->\n广告。<!-- section: default -->结束。"
+            ),
+            "开场。\n广告。结束。"
+        );
+        assert!(matches!(
+            strip_section_markers("无标记<!-- 备注 -->"),
+            Cow::Borrowed("无标记<!-- 备注 -->")
+        ));
+    }
+
+    #[test]
+    fn test_strip_link_urls() {
+        assert_eq!(
+            strip_link_urls("详见[官方文档](https://example.com/docs?a=1)。"),
+            "详见官方文档。"
+        );
+        assert_eq!(
+            strip_link_urls(
+                "See [the wiki](https://en.wikipedia.org/wiki/Rust_(language) \"Rust\") now."
+            ),
+            "See the wiki now."
+        );
+        assert_eq!(
+            strip_link_urls("![架构图](img/arch.png)，![](spacer.gif)结束"),
+            "架构图，结束"
+        );
+        assert_eq!(
+            strip_link_urls("[![构建状态](badge.svg)](https://ci.example.com)"),
+            "构建状态"
+        );
+        assert_eq!(
+            strip_link_urls("数组[0] 与 [注](\n换行) 与 `[a](b)` 与 a [b] (c)"),
+            "数组[0] 与 [注](\n换行) 与 `[a](b)` 与 a [b] (c)"
+        );
+    }
+
+    #[test]
+    fn test_clean_text_stays_borrowed() {
+        let text = "研究表明 GPU 很快。\nSee the docs now.";
+        let mut warnings = Vec::new();
+        let mut unknown = Vec::new();
+        let stages: Vec<Cow<str>> = vec![
+            strip_link_urls(text),
+            apply_footnote_policy(
+                text,
+                FootnotePolicy::Strip,
+                FootnoteBodies::MoveToEnd,
+                SymbolLanguage::Chinese,
+            ),
+            verbalize_symbols(text, SymbolLanguage::Chinese, &HashMap::new(), &mut unknown),
+            apply_long_token_policy(text, 100, LongTokenPolicy::Skip, &mut warnings),
+            apply_cjk_latin_spacing(text, SpacingMode::Insert),
+        ];
+        for stage in stages {
+            assert!(matches!(stage, Cow::Borrowed(s) if s == text));
+        }
+        assert!(warnings.is_empty() && unknown.is_empty());
+        assert!(matches!(
+            apply_cjk_latin_spacing("研究表明GPU很快", SpacingMode::Insert),
+            Cow::Owned(s) if s == "研究表明 GPU 很快"
+        ));
+
+        // A step returning a slice of its input stays borrowed, and one
+        // applied to owned text keeps the same slice
+        let trimmed = rewrite(Cow::Borrowed("  文本  "), |text| {
+            Cow::Borrowed(text.trim())
+        });
+        assert!(matches!(trimmed, Cow::Borrowed("文本")));
+        let trimmed = rewrite(Cow::Owned("  文本  ".to_string()), |text| {
+            Cow::Borrowed(text.trim())
+        });
+        assert_eq!(trimmed, "文本");
+    }
+}
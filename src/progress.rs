@@ -0,0 +1,373 @@
+//! Progress events with throughput and estimated time remaining
+
+use crate::audio_merger::probe_bytes;
+use crate::report::{SegmentSource, SourceCounts};
+use crate::retry::RetryPolicy;
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+tokio::task_local! {
+    static TRACKER: Arc<Tracker>;
+}
+
+/// Span of recent segments that throughput is measured over
+pub const THROUGHPUT_WINDOW: Duration = Duration::from_secs(30);
+
+/// Segments that must finish before throughput and ETA are estimated
+pub const MIN_ETA_SAMPLES: usize = 2;
+
+/// Callback receiving progress events
+pub(crate) type ProgressHandler = Arc<dyn Fn(&ProgressEvent) + Send + Sync>;
+
+/// What prompted a [`ProgressEvent`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressKind {
+    /// A segment's audio was synthesized or reused from a checkpoint
+    SegmentFinished {
+        /// Index of the segment
+        index: usize,
+        /// Where the segment's audio came from
+        source: SegmentSource,
+    },
+    /// The heartbeat interval passed without another event
+    Heartbeat,
+    /// Splitting of a text with the AI splitter began
+    SplitStarted,
+    /// A chunk of the text was sent to the chat model
+    SplitChunkSent {
+        /// Index of the chunk
+        chunk: usize,
+        /// Chunks the text was cut into
+        chunks: usize,
+    },
+    /// The response to a chunk was parsed into segments
+    SplitChunkParsed {
+        /// Index of the chunk
+        chunk: usize,
+        /// Chunks the text was cut into
+        chunks: usize,
+        /// Segments the chunk was split into
+        segments: usize,
+    },
+}
+
+/// Recent rate of synthesis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Throughput {
+    /// Segments finished per wall-clock second
+    pub segments_per_second: f64,
+    /// Seconds of audio synthesized per wall-clock second
+    pub audio_seconds_per_second: f64,
+}
+
+/// Progress of the synthesis of one conversion
+///
+/// Passed to the handler set with
+/// [`Text2Audio::with_progress_handler`](crate::Text2Audio::with_progress_handler).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressEvent {
+    /// What prompted the event
+    pub kind: ProgressKind,
+    /// Segments finished so far, checkpoints included
+    pub completed: usize,
+    /// Segments finished so far per source
+    pub sources: SourceCounts,
+    /// Segments to synthesize in total, zero while splitting
+    pub total: usize,
+    /// Audio of the segments finished so far
+    pub audio: Duration,
+    /// Time since synthesis, or splitting, started
+    pub elapsed: Duration,
+    /// Rate over the last [`THROUGHPUT_WINDOW`], `None` until
+    /// [`MIN_ETA_SAMPLES`] segments have finished; segments reused from a
+    /// checkpoint take no synthesis time and are left out
+    pub throughput: Option<Throughput>,
+    /// Estimated time until every segment is synthesized, `None` until
+    /// [`MIN_ETA_SAMPLES`] segments have finished or while nothing finished
+    /// within the window
+    pub eta: Option<Duration>,
+}
+
+/// Progress of the segments synthesized under one [`track`]
+struct Tracker {
+    handler: ProgressHandler,
+    /// Retry settings the ETA allows for
+    retries: RetryPolicy,
+    started: Instant,
+    state: Mutex<State>,
+}
+
+struct State {
+    total: usize,
+    completed: usize,
+    sources: SourceCounts,
+    audio: Duration,
+    last_event: Instant,
+    /// Finish time and audio of the segments finished within the window
+    recent: VecDeque<(Instant, Duration)>,
+    /// Rate as of the last finished segment
+    rate: Option<Throughput>,
+    /// Attempts so far of each segment being synthesized
+    in_flight: BTreeMap<usize, u32>,
+}
+
+impl Tracker {
+    /// Build an event of `kind` as of `now`
+    fn event(&self, state: &mut State, kind: ProgressKind, now: Instant) -> ProgressEvent {
+        state.last_event = now;
+        while let Some(&(finished, _)) = state.recent.front() {
+            if now - finished <= THROUGHPUT_WINDOW {
+                break;
+            }
+            state.recent.pop_front();
+        }
+        if matches!(kind, ProgressKind::SegmentFinished { .. }) {
+            state.rate = self.throughput(state, now);
+        }
+        let throughput = match state.completed {
+            completed if completed < MIN_ETA_SAMPLES => None,
+            _ => Some(self.throughput(state, now).unwrap_or(Throughput {
+                segments_per_second: 0.0,
+                audio_seconds_per_second: 0.0,
+            })),
+        };
+        ProgressEvent {
+            kind,
+            completed: state.completed,
+            sources: state.sources,
+            total: state.total,
+            audio: state.audio,
+            elapsed: now - self.started,
+            throughput,
+            eta: self.eta(state, now),
+        }
+    }
+
+    fn throughput(&self, state: &State, now: Instant) -> Option<Throughput> {
+        if state.recent.is_empty() {
+            return None;
+        }
+        // Segments in the window started either at the start or within it
+        let span = (now - self.started).min(THROUGHPUT_WINDOW).as_secs_f64();
+        if span <= 0.0 {
+            return None;
+        }
+        let audio: Duration = state.recent.iter().map(|(_, audio)| *audio).sum();
+        Some(Throughput {
+            segments_per_second: state.recent.len() as f64 / span,
+            audio_seconds_per_second: audio.as_secs_f64() / span,
+        })
+    }
+
+    /// Remaining segments at the rate of the last finished segment, less the
+    /// time since, but never less than the segments after the current ones
+    /// need, plus one more backoff for each segment already retrying
+    fn eta(&self, state: &State, now: Instant) -> Option<Duration> {
+        if state.completed < MIN_ETA_SAMPLES || state.recent.is_empty() {
+            return None;
+        }
+        let rate = state.rate?.segments_per_second;
+        if rate <= 0.0 {
+            return None;
+        }
+        let remaining = state.total.saturating_sub(state.completed);
+        if remaining == 0 {
+            return Some(Duration::ZERO);
+        }
+        let since = state
+            .recent
+            .back()
+            .map_or(0.0, |(finished, _)| (now - *finished).as_secs_f64());
+        let estimate = remaining as f64 / rate - since;
+        let floor = remaining.saturating_sub(state.in_flight.len().max(1)) as f64 / rate;
+        // A segment already retrying is assumed to fail once more, and its
+        // backoffs run alongside the other segments
+        let backoff = state
+            .in_flight
+            .values()
+            .filter(|&&attempts| attempts > 1 && attempts <= self.retries.max_retries())
+            .map(|&attempts| self.retries.delay(attempts - 1))
+            .max()
+            .unwrap_or_default();
+        Some(Duration::from_secs_f64(estimate.max(floor)) + backoff)
+    }
+}
+
+/// Run `f` on the tracker of the enclosing [`track`], if any, calling the
+/// handler with the event it returns
+fn record(f: impl FnOnce(&Tracker, &mut State) -> Option<ProgressEvent>) {
+    let _ = TRACKER.try_with(|tracker| {
+        let event = {
+            let mut state = tracker.state.lock().unwrap();
+            f(tracker, &mut state)
+        };
+        // Called without the lock, so a slow handler only delays its caller
+        if let Some(event) = event {
+            (tracker.handler)(&event);
+        }
+    });
+}
+
+/// Record the start of an attempt to synthesize segment `index`
+pub(crate) fn attempt_started(index: usize) {
+    record(|_, state| {
+        *state.in_flight.entry(index).or_insert(0) += 1;
+        None
+    });
+}
+
+/// Record that segment `index` ran out of attempts
+pub(crate) fn segment_failed(index: usize) {
+    record(|_, state| {
+        state.in_flight.remove(&index);
+        None
+    });
+}
+
+/// Record that segment `index` finished as `audio` from `source`
+pub(crate) fn segment_finished(index: usize, audio: &[u8], source: SegmentSource) {
+    let duration = probe_bytes(audio)
+        .map(|info| info.duration)
+        .unwrap_or_default();
+    record(|tracker, state| {
+        let now = Instant::now();
+        state.in_flight.remove(&index);
+        state.completed += 1;
+        state.audio += duration;
+        match source {
+            SegmentSource::Synthesized { attempts } => {
+                state.sources.synthesized += 1;
+                state.sources.tts_attempts += attempts;
+                state.recent.push_back((now, duration));
+            }
+            SegmentSource::Checkpoint => state.sources.checkpoint += 1,
+        }
+        let kind = ProgressKind::SegmentFinished { index, source };
+        Some(tracker.event(state, kind, now))
+    });
+}
+
+/// Record an event of `kind`, which changes no counts
+fn notify(kind: ProgressKind) {
+    record(|tracker, state| Some(tracker.event(state, kind, Instant::now())));
+}
+
+/// Record the start of an AI split
+pub(crate) fn split_started() {
+    notify(ProgressKind::SplitStarted);
+}
+
+/// Record that chunk `chunk` of `chunks` was sent to the chat model
+pub(crate) fn split_chunk_sent(chunk: usize, chunks: usize) {
+    notify(ProgressKind::SplitChunkSent { chunk, chunks });
+}
+
+/// Record that chunk `chunk` of `chunks` was parsed into `segments`
+pub(crate) fn split_chunk_parsed(chunk: usize, chunks: usize, segments: usize) {
+    notify(ProgressKind::SplitChunkParsed {
+        chunk,
+        chunks,
+        segments,
+    });
+}
+
+/// Run `work`, reporting the progress of its `total` segments to `handler`
+///
+/// Segments report through the functions of this module. Unless
+/// `heartbeat` is zero, a [`ProgressKind::Heartbeat`] event is sent
+/// whenever `heartbeat` passes without another event.
+pub(crate) async fn track<T>(
+    total: usize,
+    handler: ProgressHandler,
+    heartbeat: Duration,
+    retries: RetryPolicy,
+    work: impl Future<Output = T>,
+) -> T {
+    let started = Instant::now();
+    let tracker = Arc::new(Tracker {
+        handler,
+        retries,
+        started,
+        state: Mutex::new(State {
+            total,
+            completed: 0,
+            sources: SourceCounts::default(),
+            audio: Duration::ZERO,
+            last_event: started,
+            recent: VecDeque::new(),
+            rate: None,
+            in_flight: BTreeMap::new(),
+        }),
+    });
+    let work = TRACKER.scope(tracker.clone(), work);
+    tokio::pin!(work);
+    if heartbeat.is_zero() {
+        return work.await;
+    }
+
+    loop {
+        let deadline = tracker.state.lock().unwrap().last_event + heartbeat;
+        tokio::select! {
+            result = &mut work => return result,
+            _ = tokio::time::sleep_until(deadline) => {}
+        }
+        let event = {
+            let mut state = tracker.state.lock().unwrap();
+            let now = Instant::now();
+            (state.last_event + heartbeat <= now)
+                .then(|| tracker.event(&mut state, ProgressKind::Heartbeat, now))
+        };
+        if let Some(event) = event {
+            (tracker.handler)(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_eta_allows_for_retrying_segments() {
+        let new_state = || State {
+            total: 4,
+            completed: 0,
+            sources: SourceCounts::default(),
+            audio: Duration::ZERO,
+            last_event: Instant::now(),
+            recent: VecDeque::new(),
+            rate: None,
+            in_flight: BTreeMap::new(),
+        };
+        let tracker = Tracker {
+            handler: Arc::new(|_| {}),
+            retries: RetryPolicy::new(2, Duration::from_millis(100)),
+            started: Instant::now(),
+            state: Mutex::new(new_state()),
+        };
+        let mut state = new_state();
+        for index in 0..2 {
+            tokio::time::advance(Duration::from_millis(100)).await;
+            let now = Instant::now();
+            state.completed += 1;
+            state.recent.push_back((now, Duration::from_secs(1)));
+            let source = SegmentSource::Synthesized { attempts: 1 };
+            let kind = ProgressKind::SegmentFinished { index, source };
+            let event = tracker.event(&mut state, kind, now);
+            assert_eq!(event.eta.is_some(), index == 1);
+        }
+        let now = Instant::now();
+        assert_eq!(tracker.eta(&state, now), Some(Duration::from_millis(200)));
+
+        // Segment 2 is on its second attempt, which may fail and back off
+        // another 200 ms
+        state.in_flight.insert(2, 2);
+        assert_eq!(tracker.eta(&state, now), Some(Duration::from_millis(400)));
+        // On its last attempt no further retry is possible
+        state.in_flight.insert(2, 3);
+        assert_eq!(tracker.eta(&state, now), Some(Duration::from_millis(200)));
+    }
+}
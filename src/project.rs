@@ -0,0 +1,477 @@
+//! Resumable conversion jobs stored in a single project file
+
+use crate::client::Model;
+use crate::config::Voice;
+use crate::error::{Error, Result};
+use crate::local_splitter::LengthUnit;
+use crate::numeric::NumericContext;
+use crate::preprocess::{
+    FootnoteBodies, FootnotePolicy, LongTokenPolicy, SpacingMode, SymbolLanguage,
+};
+use crate::temp::write_atomic;
+use crate::Text2Audio;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Major version of the project format written by this crate
+///
+/// Projects with a newer major version are rejected by [`Project::load`].
+pub const PROJECT_FORMAT_MAJOR: u32 = 1;
+
+/// Minor version of the project format written by this crate
+pub const PROJECT_FORMAT_MINOR: u32 = 2;
+
+/// Version of the audio stored for completed segments
+///
+/// Segment audio is the raw audio returned by the TTS backend. Loudness
+/// normalization, uniform segment padding and other post-processing are
+/// applied when the segments are merged, so audio from an earlier run is
+/// treated exactly like freshly synthesized audio, whatever post-processing
+/// either run used. Version 0, recorded by projects written before format
+/// 1.1, stored the same raw audio and is read as this version. Completed
+/// segments recorded with any other version are dropped by
+/// [`Project::load`] and synthesized again.
+pub const SEGMENT_AUDIO_VERSION: u32 = 1;
+
+/// Whether audio stored with `version` can be used as is
+fn is_current_audio(version: u32) -> bool {
+    version == 0 || version == SEGMENT_AUDIO_VERSION
+}
+
+/// Settings that determine a project's audio
+///
+/// A project can only be run by a converter with the same settings; see
+/// [`apply`](Self::apply).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectSettings {
+    /// AI model used for splitting
+    pub model: Model,
+    /// TTS voice
+    pub voice: Voice,
+    /// Speech speed
+    pub speed: f32,
+    /// Speech volume
+    pub volume: f32,
+    /// Maximum characters per segment
+    pub max_segment_length: usize,
+    /// Unit of `max_segment_length`
+    #[serde(default)]
+    pub length_unit: LengthUnit,
+    /// Pauses after single and blank-line newlines; see
+    /// [`Text2Audio::with_newline_pauses`]
+    #[serde(default)]
+    pub newline_pauses: Option<(Duration, Duration)>,
+    /// Whether link URLs are dropped
+    #[serde(default)]
+    pub strip_link_urls: bool,
+    /// Handling of footnote markers, `None` to leave them alone
+    #[serde(default)]
+    pub footnote_policy: Option<FootnotePolicy>,
+    /// Handling of footnote bodies
+    #[serde(default)]
+    pub footnote_bodies: FootnoteBodies,
+    /// Spacing between CJK and Latin text, `None` to keep it as written
+    #[serde(default)]
+    pub cjk_latin_spacing: Option<SpacingMode>,
+    /// Whether symbols are read aloud
+    #[serde(default)]
+    pub symbol_verbalization: bool,
+    /// Language of symbol and footnote readings
+    #[serde(default)]
+    pub symbol_language: SymbolLanguage,
+    /// Custom symbol readings added to the built-in ones
+    #[serde(default)]
+    pub symbol_readings: HashMap<char, String>,
+    /// Number of unknown symbols tolerated before warning
+    #[serde(default)]
+    pub unknown_symbol_threshold: usize,
+    /// Locale for reading numbers, `None` to leave them to the TTS service
+    #[serde(default)]
+    pub numeric_context: Option<NumericContext>,
+    /// Maximum token length and what to do with longer tokens
+    #[serde(default)]
+    pub long_token_policy: Option<(usize, LongTokenPolicy)>,
+}
+
+impl ProjectSettings {
+    /// Configure `converter` with these settings
+    ///
+    /// Other settings of the converter, such as its backend or retry
+    /// configuration, are kept. Newline pauses and preprocessing options
+    /// are replaced, including ones these settings leave off.
+    pub fn apply(&self, converter: Text2Audio) -> Text2Audio {
+        let mut converter = converter
+            .with_model(self.model)
+            .with_voice(self.voice)
+            .with_speed(self.speed)
+            .with_volume(self.volume)
+            .with_max_segment_length(self.max_segment_length)
+            .with_length_unit(self.length_unit);
+        converter.newline_pauses = self.newline_pauses;
+        converter.strip_link_urls = self.strip_link_urls;
+        converter.footnote_policy = self.footnote_policy;
+        converter.footnote_bodies = self.footnote_bodies;
+        converter.cjk_latin_spacing = self.cjk_latin_spacing;
+        converter.symbol_verbalization = self.symbol_verbalization;
+        converter.symbol_language = self.symbol_language;
+        converter.symbol_readings = self.symbol_readings.clone();
+        converter.unknown_symbol_threshold = self.unknown_symbol_threshold;
+        converter.numeric_context = self.numeric_context.clone();
+        converter.long_token_policy = self.long_token_policy;
+        converter
+    }
+}
+
+/// A segment of a project's text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectSegment {
+    /// Text sent to the TTS API
+    pub text: String,
+    /// Silence inserted after the segment
+    pub pause_after: Duration,
+}
+
+/// A segment whose audio has been synthesized
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompletedSegment {
+    /// Index of the segment
+    pub index: usize,
+    /// File name of the segment's WAV in the project's audio directory
+    pub audio_file: String,
+    /// FNV-1a hash of the WAV bytes, as 16 hex digits
+    pub hash: String,
+    /// [`SEGMENT_AUDIO_VERSION`] the audio was stored with, 0 for projects
+    /// written before format 1.1
+    #[serde(default)]
+    pub audio_version: u32,
+}
+
+/// A conversion job that can be saved, passed around and resumed
+///
+/// The project file is JSON holding the source text, the settings, the
+/// split segments once computed, the completed segments and the warnings
+/// so far. Segment audio lives next to it in the directory returned by
+/// [`audio_dir`](Self::audio_dir) and is referenced by file name and hash,
+/// so the project file stays small. The stored audio is raw TTS audio,
+/// post-processed at merge time; see [`SEGMENT_AUDIO_VERSION`]. Run or
+/// resume a project with
+/// [`Text2Audio::run_project`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use text2audio::Text2Audio;
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let converter = Text2Audio::new("api_key");
+/// converter.new_project("很长的文本……").save("book.t2a")?;
+///
+/// // Safe to run again after a failure; completed segments are kept
+/// converter.run_project("book.t2a", "book.wav").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Project {
+    /// Format version as "MAJOR.MINOR"
+    version: String,
+    /// Original input text
+    pub text: String,
+    /// Settings the project is converted with
+    pub settings: ProjectSettings,
+    /// Split segments, once computed
+    pub segments: Option<Vec<ProjectSegment>>,
+    /// Segments with synthesized audio
+    pub completed: Vec<CompletedSegment>,
+    /// Warnings reported so far
+    pub warnings: Vec<String>,
+}
+
+impl Project {
+    /// Create a project that has not been split or synthesized yet
+    pub fn new(text: impl Into<String>, settings: ProjectSettings) -> Self {
+        Self {
+            version: format!("{}.{}", PROJECT_FORMAT_MAJOR, PROJECT_FORMAT_MINOR),
+            text: text.into(),
+            settings,
+            segments: None,
+            completed: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Format version of the project as "MAJOR.MINOR"
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Whether every segment has been synthesized
+    pub fn is_complete(&self) -> bool {
+        self.segments
+            .as_ref()
+            .is_some_and(|segments| self.completed.len() == segments.len())
+    }
+
+    /// Write the project file
+    ///
+    /// The file is replaced atomically, so an interrupted save leaves the
+    /// previous version intact.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if file I/O fails.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Config(format!("Failed to serialize project: {}", e)))?;
+        write_atomic(path, path.with_extension("t2a.tmp"), json)?;
+        Ok(())
+    }
+
+    /// Read a project file
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] if the file is not a project, or was written
+    /// by a newer major version of the format, and error if file I/O fails.
+    ///
+    /// Completed segments whose audio was stored with an incompatible
+    /// [`SEGMENT_AUDIO_VERSION`] are dropped, so they are synthesized again.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        let invalid = |e: serde_json::Error| Error::Config(format!("Invalid project file: {}", e));
+        let value: serde_json::Value = serde_json::from_str(&json).map_err(invalid)?;
+
+        // Check the version first, since newer majors may not parse at all
+        let version = value["version"].as_str().unwrap_or_default();
+        let major = version
+            .split('.')
+            .next()
+            .and_then(|major| major.parse::<u32>().ok())
+            .ok_or_else(|| {
+                Error::Config(format!("Invalid project format version \"{}\"", version))
+            })?;
+        if major > PROJECT_FORMAT_MAJOR {
+            return Err(Error::Config(format!(
+                "Project format {} is newer than the supported {}.{}",
+                version, PROJECT_FORMAT_MAJOR, PROJECT_FORMAT_MINOR
+            )));
+        }
+
+        let mut project: Self = serde_json::from_value(value).map_err(invalid)?;
+        project
+            .completed
+            .retain(|completed| is_current_audio(completed.audio_version));
+        for completed in &mut project.completed {
+            completed.audio_version = SEGMENT_AUDIO_VERSION;
+        }
+        Ok(project)
+    }
+
+    /// Directory holding the segment audio of the project at `path`
+    pub fn audio_dir(path: impl AsRef<Path>) -> PathBuf {
+        let mut dir = path.as_ref().as_os_str().to_owned();
+        dir.push(".audio");
+        PathBuf::from(dir)
+    }
+
+    /// Lock file guarding the project at `path` against concurrent runs
+    pub fn lock_path(path: impl AsRef<Path>) -> PathBuf {
+        let mut lock = path.as_ref().as_os_str().to_owned();
+        lock.push(".lock");
+        PathBuf::from(lock)
+    }
+
+    /// Audio of a completed segment, if its file exists and matches its hash
+    pub(crate) fn completed_audio(&self, index: usize, audio_dir: &Path) -> Option<Vec<u8>> {
+        let completed = self
+            .completed
+            .iter()
+            .find(|c| c.index == index && is_current_audio(c.audio_version))?;
+        let audio = std::fs::read(audio_dir.join(&completed.audio_file)).ok()?;
+        (hash_audio(&audio) == completed.hash).then_some(audio)
+    }
+
+    /// Store a segment's audio and record it as completed
+    pub(crate) fn complete_segment(
+        &mut self,
+        index: usize,
+        audio: &[u8],
+        audio_dir: &Path,
+    ) -> Result<()> {
+        let audio_file = format!("segment_{:05}.wav", index);
+        write_atomic(
+            &audio_dir.join(&audio_file),
+            audio_dir.join(format!("{}.tmp", audio_file)),
+            audio,
+        )?;
+        self.completed.retain(|c| c.index != index);
+        self.completed.push(CompletedSegment {
+            index,
+            audio_file,
+            hash: hash_audio(audio),
+            audio_version: SEGMENT_AUDIO_VERSION,
+        });
+        self.completed.sort_by_key(|c| c.index);
+        Ok(())
+    }
+}
+
+/// FNV-1a hash of audio bytes as 16 hex digits
+///
+/// Stable across platforms and releases, unlike the standard library's
+/// hashers.
+fn hash_audio(audio: &[u8]) -> String {
+    let hash = audio.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_path;
+
+    fn settings() -> ProjectSettings {
+        ProjectSettings {
+            model: Model::GLM4_5Flash,
+            voice: Voice::Jam,
+            speed: 1.2,
+            volume: 1.0,
+            max_segment_length: 300,
+            length_unit: LengthUnit::Words,
+            newline_pauses: Some((Duration::from_millis(300), Duration::from_millis(800))),
+            strip_link_urls: true,
+            footnote_policy: Some(FootnotePolicy::Verbalize),
+            footnote_bodies: FootnoteBodies::MoveToEnd,
+            cjk_latin_spacing: Some(SpacingMode::Insert),
+            symbol_verbalization: true,
+            symbol_language: SymbolLanguage::English,
+            symbol_readings: HashMap::from([('∮', "loop integral".to_string())]),
+            unknown_symbol_threshold: 3,
+            numeric_context: Some(NumericContext::en_us()),
+            long_token_policy: Some((80, LongTokenPolicy::SpellFirst(10))),
+        }
+    }
+
+    #[test]
+    fn test_project_round_trip() {
+        let mut project = Project::new("你好。", settings());
+        project.segments = Some(vec![ProjectSegment {
+            text: "你好。".to_string(),
+            pause_after: Duration::from_millis(250),
+        }]);
+        let path = temp_path("round_trip.t2a");
+
+        project.save(&path).unwrap();
+        let loaded = Project::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, project);
+        assert_eq!(loaded.version(), "1.2");
+        assert!(!loaded.is_complete());
+    }
+
+    #[test]
+    fn test_newer_major_rejected() {
+        let path = temp_path("newer_major.t2a");
+        std::fs::write(&path, r#"{"version": "2.0", "text": "future"}"#).unwrap();
+
+        let result = Project::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(Error::Config(m)) if m.contains("newer")));
+    }
+
+    #[test]
+    fn test_newer_minor_accepted() {
+        let mut project = Project::new("你好。", settings());
+        project.version = "1.7".to_string();
+        let path = temp_path("newer_minor.t2a");
+
+        project.save(&path).unwrap();
+        let loaded = Project::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.unwrap().version(), "1.7");
+    }
+
+    #[test]
+    fn test_completed_audio_checks_hash() {
+        let mut project = Project::new("你好。", settings());
+        let dir = PathBuf::from(temp_path("hash_check.audio"));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        project.complete_segment(0, b"audio", &dir).unwrap();
+        assert_eq!(project.completed_audio(0, &dir), Some(b"audio".to_vec()));
+        std::fs::write(dir.join(&project.completed[0].audio_file), b"changed").unwrap();
+        assert_eq!(project.completed_audio(0, &dir), None);
+        assert_eq!(project.completed_audio(1, &dir), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_segment_audio_versions() {
+        let mut project = Project::new("你好。", settings());
+        let dir = PathBuf::from(temp_path("stale_audio.audio"));
+        std::fs::create_dir_all(&dir).unwrap();
+        project.complete_segment(0, b"raw", &dir).unwrap();
+        project.complete_segment(1, b"unversioned", &dir).unwrap();
+        project.complete_segment(2, b"unknown", &dir).unwrap();
+        project.completed[1].audio_version = 0;
+        project.completed[2].audio_version = SEGMENT_AUDIO_VERSION + 1;
+        let path = temp_path("stale_audio.t2a");
+
+        project.save(&path).unwrap();
+        let loaded = Project::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Audio from before versioning is the same raw audio and is kept
+        assert_eq!(
+            project.completed_audio(1, &dir),
+            Some(b"unversioned".to_vec())
+        );
+        assert_eq!(project.completed_audio(2, &dir), None);
+        assert_eq!(loaded.completed.len(), 2);
+        assert_eq!(loaded.completed_audio(0, &dir), Some(b"raw".to_vec()));
+        assert_eq!(
+            loaded.completed_audio(1, &dir),
+            Some(b"unversioned".to_vec())
+        );
+        assert!(loaded
+            .completed
+            .iter()
+            .all(|completed| completed.audio_version == SEGMENT_AUDIO_VERSION));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_settings_apply() {
+        let converter = settings().apply(Text2Audio::new("test_key"));
+        assert_eq!(converter.project_settings(), settings());
+
+        // Options left off by the settings are cleared on the converter
+        let defaults = Text2Audio::new("test_key").project_settings();
+        let converter = defaults.apply(settings().apply(Text2Audio::new("test_key")));
+        assert_eq!(converter.project_settings(), defaults);
+    }
+
+    #[test]
+    fn test_settings_from_format_1_1() {
+        let json = r#"{"model": "GLM4_5Flash", "voice": "Jam", "speed": 1.0,
+            "volume": 1.0, "max_segment_length": 300}"#;
+        let loaded: ProjectSettings = serde_json::from_str(json).unwrap();
+
+        // Projects without the newer fields were run without these options
+        let mut expected = Text2Audio::new("test_key").project_settings();
+        expected.model = Model::GLM4_5Flash;
+        expected.voice = Voice::Jam;
+        expected.max_segment_length = 300;
+        assert_eq!(loaded, expected);
+    }
+}
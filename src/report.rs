@@ -0,0 +1,893 @@
+use crate::alignment::{align_anchors, SegmentAnchor};
+use crate::audio_merger::{probe_bytes, probe_reader};
+use crate::bundle;
+use crate::client::Model;
+use crate::digest::{self, OutputDigest};
+use crate::error::{Error, Result};
+use crate::plan::{ConversionMode, ConversionPlan, SplitRace};
+use crate::preprocess::DEFAULT_SECTION;
+use crate::stats::ClientStats;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::fmt;
+use std::io::Read;
+use std::ops::Range;
+use std::time::Duration;
+
+/// Non-fatal condition encountered during a conversion
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// Buffered segment audio exceeded the memory limit, so segments were
+    /// appended to the output file as they completed
+    IncrementalMerge {
+        /// Configured memory limit in bytes
+        limit_bytes: usize,
+    },
+    /// The speech rate of a conversion could not be saved to the
+    /// calibration file
+    CalibrationNotSaved {
+        /// Why saving failed
+        message: String,
+    },
+    /// The calibration file could not be read, so the default speech rate
+    /// was used
+    CalibrationNotLoaded {
+        /// Why loading failed
+        message: String,
+    },
+    /// Direct-mode text exceeded the TTS request limit and was split locally
+    DirectFallback {
+        /// Character count of the text
+        chars: usize,
+        /// Maximum characters accepted by one TTS request
+        limit: usize,
+    },
+    /// An over-long unbreakable token was rewritten during preprocessing
+    LongToken {
+        /// First characters of the token
+        preview: String,
+        /// Character count of the token
+        chars: usize,
+    },
+    /// A segment override referred to an index past the last segment
+    UnusedSegmentOverride {
+        /// Index given in the override map
+        index: usize,
+        /// Number of segments produced by splitting
+        segment_count: usize,
+    },
+    /// A segment's WAV spec differed from the output spec and was converted
+    SegmentSpecConverted {
+        /// Index of the converted segment
+        index: usize,
+        /// Original sample rate of the segment
+        sample_rate: u32,
+        /// Original channel count of the segment
+        channels: u16,
+        /// Sample rate of the merged output
+        target_sample_rate: u32,
+        /// Channel count of the merged output
+        target_channels: u16,
+    },
+    /// A non-finite numeric setting was replaced with its default
+    NonFiniteParameter {
+        /// Name of the setting
+        name: &'static str,
+        /// Value that was given
+        value: f32,
+        /// Default used instead
+        default: f32,
+    },
+    /// A feature was enabled for an AI model that does not support it, so
+    /// it was left off
+    UnsupportedModelFeature {
+        /// Model used for AI splitting
+        model: Model,
+        /// Feature that was left off
+        feature: &'static str,
+    },
+    /// Parallel synthesis failed with transport errors, so the failed
+    /// segments were synthesized again sequentially
+    ParallelFallback {
+        /// Number of segments that failed in parallel mode
+        failed_segments: usize,
+    },
+    /// The audio inspector panicked; the audio was written regardless
+    InspectorPanic {
+        /// Panic message, if it was a string
+        message: String,
+    },
+    /// A segment was longer than the uniform segment duration, so it was
+    /// written unpadded and shifts the segments after it
+    SegmentExceedsUniformDuration {
+        /// Index of the segment
+        index: usize,
+        /// Duration of the segment's audio
+        duration: Duration,
+        /// Configured uniform duration
+        target: Duration,
+    },
+    /// A segment's audio was longer than the target segment duration
+    /// allows
+    SegmentOverDuration {
+        /// Index of the segment
+        index: usize,
+        /// Duration of the segment's audio
+        duration: Duration,
+        /// Configured target segment duration
+        target: Duration,
+    },
+    /// Synthesis made no progress within the stall timeout
+    Stalled {
+        /// Time since the last progress
+        idle: Duration,
+        /// Segments being synthesized when the stall was detected
+        in_flight: Vec<StalledSegment>,
+    },
+    /// More symbols without a spoken reading reached the TTS input than
+    /// the configured threshold allows
+    UnknownSymbols {
+        /// Distinct unknown symbols in order of first appearance
+        symbols: Vec<char>,
+        /// Total occurrences of unknown symbols
+        count: usize,
+        /// Configured threshold
+        threshold: usize,
+    },
+    /// Numbers with more than one plausible reading were read as the
+    /// numeric profile prefers
+    AmbiguousNumbers {
+        /// The first of them as written
+        samples: Vec<String>,
+        /// Total ambiguous numbers
+        count: usize,
+    },
+    /// The configured output format contradicts the output file extension;
+    /// the configured format was written
+    OutputFormatMismatch {
+        /// Extension of the output path
+        extension: String,
+        /// Extension of the configured format
+        format: &'static str,
+    },
+    /// The output file extension names no supported format, and no format
+    /// was configured, so WAV was written
+    UnknownOutputExtension {
+        /// Extension of the output path
+        extension: String,
+    },
+    /// A section marker named no registered profile, so the settings in
+    /// effect were kept
+    UnknownSection {
+        /// Name in the marker
+        name: String,
+    },
+    /// The output file already existed, so nothing was converted
+    OutputSkipped {
+        /// Path of the existing file
+        path: String,
+    },
+    /// The output file already existed, so the output was written next to it
+    OutputRenamed {
+        /// Path of the existing file
+        path: String,
+        /// Path the output was written to
+        renamed: String,
+    },
+    /// The output was too short or quiet to measure its loudness, so it was
+    /// written without loudness normalization
+    LoudnessNotNormalized {
+        /// Configured target loudness in LUFS
+        target: f32,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::DirectFallback { chars, limit } => write!(
+                f,
+                "text has {} characters, exceeding the TTS limit of {}; split locally",
+                chars, limit
+            ),
+            Warning::CalibrationNotSaved { message } => {
+                write!(f, "calibration not saved: {}", message)
+            }
+            Warning::CalibrationNotLoaded { message } => {
+                write!(f, "calibration not loaded, using the default rate: {}", message)
+            }
+            Warning::IncrementalMerge { limit_bytes } => write!(
+                f,
+                "segment audio exceeded {} bytes; merged incrementally on disk",
+                limit_bytes
+            ),
+            Warning::LongToken { preview, chars } => write!(
+                f,
+                "token starting with \"{}\" has {} characters without a break; rewritten",
+                preview, chars
+            ),
+            Warning::UnusedSegmentOverride {
+                index,
+                segment_count,
+            } => write!(
+                f,
+                "override for segment {} ignored; text was split into {} segments",
+                index, segment_count
+            ),
+            Warning::SegmentSpecConverted {
+                index,
+                sample_rate,
+                channels,
+                target_sample_rate,
+                target_channels,
+            } => write!(
+                f,
+                "segment {} was {} Hz/{} ch; converted to {} Hz/{} ch",
+                index, sample_rate, channels, target_sample_rate, target_channels
+            ),
+            Warning::NonFiniteParameter {
+                name,
+                value,
+                default,
+            } => write!(f, "{} was {}; using the default {}", name, value, default),
+            Warning::UnsupportedModelFeature { model, feature } => write!(
+                f,
+                "{} does not support {}; it was left off",
+                model.as_str(),
+                feature
+            ),
+            Warning::ParallelFallback { failed_segments } => write!(
+                f,
+                "{} segments failed in parallel mode; retried sequentially",
+                failed_segments
+            ),
+            Warning::InspectorPanic { message } => {
+                write!(f, "audio inspector panicked: {}", message)
+            }
+            Warning::SegmentExceedsUniformDuration {
+                index,
+                duration,
+                target,
+            } => write!(
+                f,
+                "segment {} lasts {:.2}s, longer than the uniform {:.2}s; left unpadded",
+                index,
+                duration.as_secs_f64(),
+                target.as_secs_f64()
+            ),
+            Warning::SegmentOverDuration {
+                index,
+                duration,
+                target,
+            } => write!(
+                f,
+                "segment {} lasts {:.2}s, over the target of {:.2}s",
+                index,
+                duration.as_secs_f64(),
+                target.as_secs_f64()
+            ),
+            Warning::Stalled { idle, in_flight } => write!(
+                f,
+                "no progress for {:.1}s; segments in flight: {}",
+                idle.as_secs_f64(),
+                describe_in_flight(in_flight)
+            ),
+            Warning::UnknownSymbols {
+                symbols,
+                count,
+                threshold,
+            } => write!(
+                f,
+                "{} symbols without a reading exceed the threshold of {}: {}",
+                count,
+                threshold,
+                symbols.iter().collect::<String>()
+            ),
+            Warning::AmbiguousNumbers { samples, count } => write!(
+                f,
+                "{} numbers could be read more than one way and were read per the numeric profile: {}",
+                count,
+                samples.join(", ")
+            ),
+            Warning::OutputFormatMismatch { extension, format } => write!(
+                f,
+                "output path ends in .{} but the configured format is {}; wrote {}",
+                extension, format, format
+            ),
+            Warning::UnknownOutputExtension { extension } => write!(
+                f,
+                "output extension .{} is not a supported format; wrote WAV",
+                extension
+            ),
+            Warning::UnknownSection { name } => write!(
+                f,
+                "section \"{}\" has no profile; kept the current settings",
+                name
+            ),
+            Warning::OutputSkipped { path } => {
+                write!(f, "{} already exists; skipped the conversion", path)
+            }
+            Warning::OutputRenamed { path, renamed } => {
+                write!(f, "{} already exists; wrote {} instead", path, renamed)
+            }
+            Warning::LoudnessNotNormalized { target } => write!(
+                f,
+                "loudness could not be measured; not normalized to {} LUFS",
+                target
+            ),
+        }
+    }
+}
+
+impl Warning {
+    /// The warning's message with any text taken from the input left out
+    ///
+    /// Warnings quoting the input keep only what happened and the counts;
+    /// all others read as their [`Display`](fmt::Display) message.
+    pub(crate) fn without_text(&self) -> String {
+        match self {
+            Warning::LongToken { chars, .. } => format!(
+                "token of {} characters without a break; rewritten (text omitted)",
+                chars
+            ),
+            Warning::UnknownSymbols {
+                count, threshold, ..
+            } => format!(
+                "{} symbols without a reading exceed the threshold of {} (symbols omitted)",
+                count, threshold
+            ),
+            Warning::AmbiguousNumbers { count, .. } => format!(
+                "{} numbers could be read more than one way and were read per the numeric \
+                 profile (samples omitted)",
+                count
+            ),
+            Warning::UnknownSection { .. } => {
+                "a section has no profile; kept the current settings (name omitted)".to_string()
+            }
+            warning => warning.to_string(),
+        }
+    }
+}
+
+/// A segment being synthesized when a stall was detected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StalledSegment {
+    /// Index of the segment
+    pub index: usize,
+    /// Time since the segment's first attempt started
+    pub elapsed: Duration,
+    /// Attempts started so far, including the one in flight
+    pub attempts: u32,
+}
+
+/// List stalled segments as "2 (31.0s, attempt 1), 3 (…)", or "none"
+pub(crate) fn describe_in_flight(in_flight: &[StalledSegment]) -> String {
+    if in_flight.is_empty() {
+        return "none".to_string();
+    }
+    in_flight
+        .iter()
+        .map(|segment| {
+            format!(
+                "{} ({:.1}s, attempt {})",
+                segment.index,
+                segment.elapsed.as_secs_f64(),
+                segment.attempts
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Where a segment's audio came from
+///
+/// Audio only ever comes from the TTS API or from a project checkpoint:
+/// the crate keeps no audio cache, and every segment of a report is voiced,
+/// never replaced by silence or skipped. Checkpoints are the only way a
+/// segment avoids a request, so they count as the cache hits when
+/// reconciling billing. The enum is non-exhaustive so that sources such as
+/// an audio cache can be added when they exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SegmentSource {
+    /// Synthesized by the TTS API during this conversion
+    Synthesized {
+        /// TTS requests made for the segment, including retries
+        attempts: u32,
+    },
+    /// Reused from a project checkpoint without a TTS request
+    Checkpoint,
+}
+
+/// Number of segments per [`SegmentSource`] in a conversion
+///
+/// `synthesized + checkpoint` is the number of segments in the report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SourceCounts {
+    /// Segments synthesized by the TTS API
+    pub synthesized: usize,
+    /// TTS requests made for the synthesized segments, including retries
+    pub tts_attempts: u32,
+    /// Segments reused from a project checkpoint
+    pub checkpoint: usize,
+}
+
+/// Narration track of a segment in bilingual output
+///
+/// Produced by
+/// [`Text2Audio::convert_bilingual`](crate::Text2Audio::convert_bilingual).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Track {
+    /// First text of a pair, in the converter's voice
+    Primary,
+    /// Second text of a pair, in the secondary voice
+    Secondary,
+}
+
+/// How much segment text a [`ConversionReport`] keeps
+///
+/// Levels are ordered from least to most detail, so a report satisfies any
+/// level up to its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReportDetail {
+    /// No segment text or hashes; timings and source ranges only
+    None,
+    /// The SHA-256 of each segment's text instead of the text
+    Hashes,
+    /// Each segment's text and its SHA-256
+    Full,
+}
+
+/// Per-segment details of a completed conversion
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentReport {
+    /// Position of the segment in the merged audio
+    pub index: usize,
+    /// Text sent to the TTS API, with [`ReportDetail::Full`]
+    pub text: Option<String>,
+    /// SHA-256 of the text as lowercase hex, with [`ReportDetail::Hashes`]
+    /// or more
+    ///
+    /// Stable across runs and releases, so it identifies a segment's text
+    /// without keeping it.
+    pub text_sha256: Option<String>,
+    /// Approximate char range of the original input covered by the segment
+    ///
+    /// Ranges of consecutive segments are monotonic, non-overlapping and
+    /// together cover the whole input, including text removed by
+    /// preprocessing.
+    pub source_range: Range<usize>,
+    /// Offset of the segment in the merged audio
+    pub start: Duration,
+    /// Duration of the segment's audio
+    pub duration: Duration,
+    /// Silence inserted after the segment
+    pub pause_after: Duration,
+    /// Where the segment's audio came from
+    pub source: SegmentSource,
+    /// Track of the segment in bilingual output, `None` otherwise
+    pub track: Option<Track>,
+    /// Section profile the segment was synthesized with, `None` for the
+    /// converter's own settings
+    pub section: Option<String>,
+    /// Target duration of the segment's audio, `None` unless set with
+    /// [`Text2Audio::with_target_segment_duration`](crate::Text2Audio::with_target_segment_duration)
+    pub target_duration: Option<Duration>,
+}
+
+/// Wall-clock time spent in each phase of a conversion
+///
+/// The phases run one after another, except that synthesis and merging
+/// interleave under a [memory limit](crate::Text2Audio::with_max_memory);
+/// time spent appending to the output then counts as merging. `total` also
+/// covers preprocessing and output checks, so it is at least the sum of the
+/// phases.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PhaseTimings {
+    /// Splitting text into segments, including AI split requests
+    pub split_duration: Duration,
+    /// Synthesizing segments, including retries
+    pub synth_duration: Duration,
+    /// Merging segment audio and writing the output
+    pub merge_duration: Duration,
+    /// The whole conversion
+    pub total: Duration,
+}
+
+/// Summary of a completed conversion
+///
+/// Returned by [`Text2Audio::convert_with_report`](crate::Text2Audio::convert_with_report).
+#[derive(Debug, Clone)]
+pub struct ConversionReport {
+    /// Processing mode that was actually used
+    pub mode: ConversionMode,
+    /// Number of synthesized segments
+    pub segment_count: usize,
+    /// Non-fatal conditions encountered along the way
+    pub warnings: Vec<Warning>,
+    /// Per-segment text, source range and timing in merge order
+    pub segments: Vec<SegmentReport>,
+    /// Segment counts per source, for reconciling TTS usage
+    pub sources: SourceCounts,
+    /// Outcome of each race under
+    /// [`SplitStrategy::Race`](crate::SplitStrategy::Race), in text order
+    pub split_races: Vec<SplitRace>,
+    /// Requests made by this conversion
+    ///
+    /// Conversions running concurrently on the same converter may count
+    /// each other's requests.
+    pub stats: ClientStats,
+    /// Time spent splitting, synthesizing and merging
+    pub timings: PhaseTimings,
+    /// Checksums of the files written, in the order they were written;
+    /// empty for conversions to memory
+    pub outputs: Vec<OutputDigest>,
+    /// How much segment text the report keeps; see
+    /// [`Text2Audio::with_report_detail`](crate::Text2Audio::with_report_detail)
+    pub detail: ReportDetail,
+    /// Bytes of segment text above which pushed segments keep only hashes
+    pub(crate) text_limit: Option<usize>,
+    /// Bytes of segment text kept, updated as segments are pushed
+    pub(crate) text_bytes: usize,
+    /// Alignment anchor of each segment, kept whatever the detail
+    pub(crate) anchors: Vec<SegmentAnchor>,
+}
+
+impl ConversionReport {
+    pub(crate) fn new(mode: ConversionMode) -> Self {
+        Self {
+            mode,
+            segment_count: 0,
+            warnings: Vec::new(),
+            segments: Vec::new(),
+            sources: SourceCounts::default(),
+            split_races: Vec::new(),
+            stats: ClientStats::default(),
+            timings: PhaseTimings::default(),
+            outputs: Vec::new(),
+            detail: ReportDetail::Full,
+            text_limit: None,
+            text_bytes: 0,
+            anchors: Vec::new(),
+        }
+    }
+
+    /// Keep at most `detail` for segments pushed from now on, and only
+    /// hashes once the kept text exceeds `text_limit` bytes
+    ///
+    /// Text left out is never stored, so a large conversion does not hold
+    /// a second copy of its input in the report.
+    pub(crate) fn limit_pushed_detail(&mut self, detail: ReportDetail, text_limit: Option<usize>) {
+        self.reduce_detail(detail);
+        self.text_limit = text_limit;
+    }
+
+    /// Check that the report keeps at least `needed` detail for `feature`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] naming the feature and the detail to
+    /// convert with if the report keeps less.
+    pub fn require_detail(&self, needed: ReportDetail, feature: &str) -> Result<()> {
+        if self.detail >= needed {
+            return Ok(());
+        }
+        Err(Error::Config(format!(
+            "{} needs a report with {:?} detail, but this report keeps {:?}; \
+             convert with `with_report_detail(ReportDetail::{:?})`",
+            feature, needed, self.detail, needed
+        )))
+    }
+
+    /// Drop the segment text and hashes that `detail` leaves out
+    pub(crate) fn reduce_detail(&mut self, detail: ReportDetail) {
+        if detail >= self.detail {
+            return;
+        }
+        for segment in &mut self.segments {
+            segment.text = None;
+            if detail == ReportDetail::None {
+                segment.text_sha256 = None;
+            }
+        }
+        self.text_bytes = 0;
+        self.detail = detail;
+    }
+
+    /// Bytes of segment text the report keeps
+    pub(crate) fn text_bytes(&self) -> usize {
+        self.text_bytes
+    }
+
+    /// Serialize segment start times as speech marks, one JSON object per
+    /// line
+    ///
+    /// The format follows Amazon Polly's speech marks, so web players
+    /// built for them can highlight along. Each segment gives a line
+    /// `{"time":<ms>,"type":"sentence","value":"<text>"}`, where `time` is
+    /// the segment's start in the merged audio in whole milliseconds and
+    /// `value` the text sent to the TTS API. With `sections`, a line
+    /// `{"time":<ms>,"type":"ssml","value":"<mark name=\"<section>\"/>"}`
+    /// precedes the first segment of each
+    /// [section](crate::Text2Audio::with_section_profiles) that differs from
+    /// the one before, the text starting in [`DEFAULT_SECTION`]. Every line,
+    /// the last included, ends in `\n`, and fields keep this order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] unless the report keeps
+    /// [`ReportDetail::Full`].
+    pub fn to_speech_marks(&self, sections: bool) -> Result<String> {
+        self.require_detail(ReportDetail::Full, "speech marks")?;
+        let mut marks = String::new();
+        let mut push = |mark: SpeechMark<'_>| {
+            marks.push_str(&serde_json::to_string(&mark).expect("speech marks are serializable"));
+            marks.push('\n');
+        };
+        let mut section = DEFAULT_SECTION;
+        for segment in &self.segments {
+            let time = segment.start.as_millis() as u64;
+            let segment_section = segment.section.as_deref().unwrap_or(DEFAULT_SECTION);
+            if sections && segment_section != section {
+                section = segment_section;
+                push(SpeechMark {
+                    time,
+                    kind: "ssml",
+                    value: format!("<mark name=\"{}\"/>", escape_attribute(section)).into(),
+                });
+            }
+            push(SpeechMark {
+                time,
+                kind: "sentence",
+                value: segment.text.as_deref().unwrap_or_default().into(),
+            });
+        }
+        Ok(marks)
+    }
+
+    /// Rebuild the plan the conversion synthesized, for patching segments
+    /// and converting again
+    ///
+    /// Edit the plan's segments and pass its [JSON](ConversionPlan::to_json)
+    /// to
+    /// [`Text2Audio::convert_segments_from_json`](crate::Text2Audio::convert_segments_from_json).
+    /// Pauses are the silence that followed each segment, including any
+    /// padding to a uniform segment duration.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] unless the report keeps
+    /// [`ReportDetail::Full`].
+    pub fn to_plan(&self) -> Result<ConversionPlan> {
+        self.require_detail(ReportDetail::Full, "patching segments")?;
+        let segments = self
+            .segments
+            .iter()
+            .map(|segment| segment.text.clone().unwrap_or_default())
+            .collect();
+        let mut plan = ConversionPlan::new(self.mode, segments);
+        plan.pauses = self.segments.iter().map(|s| s.pause_after).collect();
+        Ok(plan)
+    }
+
+    /// SHA-256 over the checksums and paths of every output, as lowercase
+    /// hex, or `None` if nothing was written
+    ///
+    /// Hashes a `sha256sum`-style listing of [`outputs`](Self::outputs), one
+    /// `<sha256>  <path>` line per file, so a single value covers a
+    /// conversion written to several files.
+    pub fn manifest_sha256(&self) -> Option<String> {
+        (!self.outputs.is_empty()).then(|| digest::manifest_sha256(&self.outputs))
+    }
+
+    /// Drop the measurements that vary from run to run: phase timings and
+    /// request latencies
+    pub(crate) fn clear_wall_clock(&mut self) {
+        self.timings = PhaseTimings::default();
+        self.stats.chat.latency = Default::default();
+        self.stats.tts.latency = Default::default();
+    }
+
+    /// Report of a conversion skipped because its output already existed
+    pub(crate) fn skipped(warnings: Vec<Warning>) -> Self {
+        Self {
+            warnings,
+            ..Self::new(ConversionMode::Direct)
+        }
+    }
+
+    /// Record the next segment in merge order, timing it from its WAV header
+    pub(crate) fn push_segment(
+        &mut self,
+        text: &str,
+        audio: &[u8],
+        source: SegmentSource,
+    ) -> Result<()> {
+        let start = self
+            .segments
+            .last()
+            .map(|last| last.start + last.duration + last.pause_after)
+            .unwrap_or_default();
+        let duration = match probe_bytes(audio) {
+            Ok(info) => info.duration,
+            Err(e) => {
+                bundle::invalid_audio(self.segments.len(), audio);
+                return Err(e);
+            }
+        };
+        let kept = (self.detail >= ReportDetail::Full).then(|| text.to_string());
+        self.text_bytes += kept.as_ref().map_or(0, String::len);
+        self.segments.push(SegmentReport {
+            index: self.segments.len(),
+            text: kept,
+            text_sha256: (self.detail >= ReportDetail::Hashes).then(|| digest::text_sha256(text)),
+            source_range: 0..0,
+            start,
+            duration,
+            pause_after: Duration::ZERO,
+            source,
+            track: None,
+            section: None,
+            target_duration: None,
+        });
+        self.anchors.push(SegmentAnchor::new(text));
+        self.segment_count = self.segments.len();
+        if self.detail == ReportDetail::Full
+            && self.text_limit.is_some_and(|limit| self.text_bytes > limit)
+        {
+            self.reduce_detail(ReportDetail::Hashes);
+        }
+        match source {
+            SegmentSource::Synthesized { attempts } => {
+                self.sources.synthesized += 1;
+                self.sources.tts_attempts += attempts;
+            }
+            SegmentSource::Checkpoint => self.sources.checkpoint += 1,
+        }
+        Ok(())
+    }
+
+    /// Record silence inserted after the last segment
+    pub(crate) fn push_pause(&mut self, pause: Duration) {
+        if let Some(last) = self.segments.last_mut() {
+            last.pause_after += pause;
+        }
+    }
+
+    /// Record padding of the last segment up to the uniform duration, if any
+    pub(crate) fn pad_last_segment(&mut self, uniform_duration: Option<Duration>) {
+        if let (Some(duration), Some(last)) = (uniform_duration, self.segments.last_mut()) {
+            last.pause_after += duration.saturating_sub(last.duration);
+        }
+    }
+
+    /// Fill in each segment's source range in the original input
+    pub(crate) fn align_to_source(&mut self, original: &str) {
+        let ranges = align_anchors(original, &self.anchors);
+        for (segment, range) in self.segments.iter_mut().zip(ranges) {
+            segment.source_range = range;
+        }
+    }
+
+    /// Serialize per-segment source ranges and timings as JSON
+    ///
+    /// Produces an array of objects with `index`, `text`, `text_sha256`,
+    /// `char_start`, `char_end`, `start_seconds` and `duration_seconds`
+    /// fields, suitable for read-along highlighting. `text` is left out
+    /// below [`ReportDetail::Full`]. Segments of bilingual output also have
+    /// a `track` field, `"primary"` or `"secondary"`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Config`] unless the report keeps at least
+    /// [`ReportDetail::Hashes`].
+    pub fn to_timing_json(&self) -> Result<String> {
+        self.require_detail(ReportDetail::Hashes, "timing JSON")?;
+        let entries: Vec<TimingEntry<'_>> = self
+            .segments
+            .iter()
+            .map(|segment| TimingEntry {
+                index: segment.index,
+                text: segment.text.as_deref(),
+                text_sha256: segment.text_sha256.as_deref(),
+                char_start: segment.source_range.start,
+                char_end: segment.source_range.end,
+                start_seconds: segment.start.as_secs_f64(),
+                duration_seconds: segment.duration.as_secs_f64(),
+                track: segment.track,
+            })
+            .collect();
+
+        Ok(serde_json::to_string_pretty(&entries).expect("timing entries are serializable"))
+    }
+}
+
+/// Summary of a written output file for media library tooling
+///
+/// Written as `<output>.json` when
+/// [`with_sidecar_metadata`](crate::Text2Audio::with_sidecar_metadata) is
+/// enabled, so players can show the total duration without scanning the
+/// audio.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OutputMetadata {
+    /// Playback duration of the audio
+    pub duration_seconds: f64,
+    /// Sample rate in Hz
+    pub sample_rate: u32,
+    /// Number of channels
+    pub channels: u16,
+    /// Number of synthesized segments
+    pub segment_count: usize,
+    /// Size of the output file
+    pub bytes: u64,
+    /// SHA-256 of the output file as lowercase hex
+    pub sha256: String,
+}
+
+impl OutputMetadata {
+    /// Read the audio format from a WAV header
+    ///
+    /// Only the header is read; the sample data is not scanned.
+    pub(crate) fn from_wav_header<R: Read>(
+        wav: R,
+        segment_count: usize,
+        output: &OutputDigest,
+    ) -> Result<Self> {
+        let info = probe_reader(wav)?;
+        Ok(Self {
+            duration_seconds: info.frames as f64 / info.spec.sample_rate.max(1) as f64,
+            sample_rate: info.spec.sample_rate,
+            channels: info.spec.channels,
+            segment_count,
+            bytes: output.bytes,
+            sha256: output.sha256.clone(),
+        })
+    }
+
+    /// Serialize as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("output metadata is serializable")
+    }
+}
+
+#[derive(Serialize)]
+struct SpeechMark<'a> {
+    time: u64,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    value: Cow<'a, str>,
+}
+
+/// Escape `value` for a double-quoted XML attribute
+fn escape_attribute(value: &str) -> Cow<'_, str> {
+    if !value.contains(['&', '<', '>', '"']) {
+        return Cow::Borrowed(value);
+    }
+    let mut escaped = String::with_capacity(value.len() + 8);
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+#[derive(Serialize)]
+struct TimingEntry<'a> {
+    index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text_sha256: Option<&'a str>,
+    char_start: usize,
+    char_end: usize,
+    start_seconds: f64,
+    duration_seconds: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    track: Option<Track>,
+}
@@ -0,0 +1,495 @@
+//! Retrying remote calls with exponential backoff
+
+use crate::error::{Error, Result};
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Retries after the first attempt under the default policy
+pub const DEFAULT_MAX_RETRIES: u32 = 2;
+
+/// Delay before the first retry under the default policy
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Factor each delay grows by under the default policy
+pub const DEFAULT_RETRY_MULTIPLIER: f64 = 2.0;
+
+/// How a failed call should be retried
+///
+/// Returned by [`Error::retry_class`] and by the `classify` method of the
+/// [`TtsBackend`](crate::TtsBackend) and
+/// [`SplitBackend`](crate::SplitBackend) that failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryClass {
+    /// Retry after the policy's backoff
+    Retryable,
+    /// Retry, waiting at least `retry_after` if the service said how long
+    RateLimited {
+        /// Wait the service asked for, overriding a shorter backoff
+        retry_after: Option<Duration>,
+    },
+    /// Retry once, then give up if it fails the same way again
+    ///
+    /// For glitches that are usually but not always transient, such as a
+    /// response in the wrong audio format.
+    RetryableOnce,
+    /// Give up; another attempt would fail the same way
+    Fatal,
+}
+
+/// Decides whether an error is worth another attempt
+type Classifier = Arc<dyn Fn(&Error) -> bool + Send + Sync>;
+
+/// Decides whether a retry may go ahead, given its number and delay
+type Budget = Arc<dyn Fn(u32, Duration) -> bool + Send + Sync>;
+
+/// When and how often failed remote calls are tried again
+///
+/// The delay before retry `n` (counting from 0) is `base_delay *
+/// multiplier^n`, capped at the maximum delay. With jitter, each delay is
+/// scaled by a random factor between `1 - jitter` and 1, so the cap still
+/// holds. Errors are first sorted by their [`RetryClass`]: fatal errors are
+/// never retried, and a rate limit's `retry_after` lengthens the delay past
+/// the cap. Used for TTS and split requests alike, set with
+/// [`Text2Audio::with_retry_policy`](crate::Text2Audio::with_retry_policy).
+///
+/// # Examples
+///
+/// ```
+/// use text2audio::{Error, RetryPolicy};
+/// use std::time::Duration;
+///
+/// let policy = RetryPolicy::new(4, Duration::from_millis(200))
+///     .with_max_delay(Duration::from_secs(1))
+///     .with_jitter(0.5)
+///     .with_classifier(Error::is_transport);
+/// assert_eq!(policy.delay(0), Duration::from_millis(200));
+/// assert_eq!(policy.delay(3), Duration::from_secs(1));
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+    multiplier: f64,
+    max_delay: Option<Duration>,
+    jitter: f64,
+    classifier: Option<Classifier>,
+    budget: Option<Budget>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_RETRIES, DEFAULT_RETRY_DELAY)
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_retries", &self.max_retries)
+            .field("base_delay", &self.base_delay)
+            .field("multiplier", &self.multiplier)
+            .field("max_delay", &self.max_delay)
+            .field("jitter", &self.jitter)
+            .field("classifier", &self.classifier.is_some())
+            .field("budget", &self.budget.is_some())
+            .finish()
+    }
+}
+
+impl RetryPolicy {
+    /// Create a policy doubling `base_delay` after each of up to
+    /// `max_retries` retries, without jitter or a maximum delay
+    ///
+    /// `max_retries` counts the attempts after the first, so 0 disables
+    /// retries. Every error is retried.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_retries` - Maximum number of retries after the first attempt
+    /// * `base_delay` - Delay before the first retry
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::RetryPolicy;
+    /// use std::time::Duration;
+    ///
+    /// let policy = RetryPolicy::new(3, Duration::from_millis(100));
+    /// assert_eq!(policy.delay(2), Duration::from_millis(400));
+    /// ```
+    pub fn new(max_retries: u32, base_delay: Duration) -> Self {
+        Self {
+            max_retries,
+            base_delay,
+            multiplier: DEFAULT_RETRY_MULTIPLIER,
+            max_delay: None,
+            jitter: 0.0,
+            classifier: None,
+            budget: None,
+        }
+    }
+
+    /// Set the factor each delay grows by
+    ///
+    /// Values below 1.0 are raised to 1.0, so delays never shrink, and a
+    /// non-finite value keeps [`DEFAULT_RETRY_MULTIPLIER`].
+    ///
+    /// # Arguments
+    ///
+    /// * `multiplier` - Growth factor of the delay (default 2.0)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::RetryPolicy;
+    /// use std::time::Duration;
+    ///
+    /// // Constant delay
+    /// let policy = RetryPolicy::new(3, Duration::from_millis(100)).with_multiplier(1.0);
+    /// assert_eq!(policy.delay(2), Duration::from_millis(100));
+    /// ```
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        if multiplier.is_finite() {
+            self.multiplier = multiplier.max(1.0);
+        }
+        self
+    }
+
+    /// Cap the delay before any one retry
+    ///
+    /// # Arguments
+    ///
+    /// * `max_delay` - Longest delay between attempts (default none)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::RetryPolicy;
+    /// use std::time::Duration;
+    ///
+    /// let policy = RetryPolicy::new(10, Duration::from_secs(1))
+    ///     .with_max_delay(Duration::from_secs(30));
+    /// assert_eq!(policy.delay(9), Duration::from_secs(30));
+    /// ```
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = Some(max_delay);
+        self
+    }
+
+    /// Randomize delays, so that clients failing together retry apart
+    ///
+    /// Each delay is scaled by a random factor between `1 - jitter` and 1.
+    /// Values are clamped to 0.0 - 1.0, and a non-finite value disables
+    /// jitter.
+    ///
+    /// # Arguments
+    ///
+    /// * `jitter` - Largest fraction taken off a delay (default 0.0)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::RetryPolicy;
+    /// use std::time::Duration;
+    ///
+    /// let policy = RetryPolicy::new(3, Duration::from_millis(100)).with_jitter(0.5);
+    /// ```
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = if jitter.is_finite() {
+            jitter.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        self
+    }
+
+    /// Retry only errors for which `classifier` returns true
+    ///
+    /// Other errors are returned after the attempt that produced them, as
+    /// are errors of class [`RetryClass::Fatal`] whatever the classifier
+    /// says.
+    ///
+    /// # Arguments
+    ///
+    /// * `classifier` - Whether an error is worth another attempt (default: every error is)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{Error, RetryPolicy};
+    ///
+    /// // Rejected input fails the same way every time
+    /// let policy = RetryPolicy::default().with_classifier(Error::is_transport);
+    /// ```
+    pub fn with_classifier(
+        mut self,
+        classifier: impl Fn(&Error) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.classifier = Some(Arc::new(classifier));
+        self
+    }
+
+    /// Ask `budget` before each retry whether it may go ahead
+    ///
+    /// `budget` is called with the number of the retry, counting from 1,
+    /// and the delay before it. Returning false gives up, returning the
+    /// last error. The budget is shared by every call made under the
+    /// policy, so it can limit retries across a whole conversion.
+    ///
+    /// # Arguments
+    ///
+    /// * `budget` - Whether a retry may go ahead (default: always)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::RetryPolicy;
+    /// use std::sync::atomic::{AtomicU32, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// // At most 10 retries in total
+    /// let remaining = Arc::new(AtomicU32::new(10));
+    /// let policy = RetryPolicy::default().with_budget(move |_, _| {
+    ///     remaining
+    ///         .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+    ///         .is_ok()
+    /// });
+    /// ```
+    pub fn with_budget(
+        mut self,
+        budget: impl Fn(u32, Duration) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.budget = Some(Arc::new(budget));
+        self
+    }
+
+    /// Maximum number of retries after the first attempt
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Delay before the first retry
+    pub fn base_delay(&self) -> Duration {
+        self.base_delay
+    }
+
+    /// Delay before retry `retry`, counting from 0, without jitter
+    pub fn delay(&self, retry: u32) -> Duration {
+        let cap = self.max_delay.unwrap_or(Duration::MAX);
+        if self.base_delay.is_zero() {
+            return Duration::ZERO;
+        }
+        let factor = self.multiplier.powf(retry as f64);
+        Duration::try_from_secs_f64(self.base_delay.as_secs_f64() * factor)
+            .unwrap_or(Duration::MAX)
+            .min(cap)
+    }
+
+    /// Delay before retry `retry` with jitter applied, `random` being
+    /// uniform in 0.0 - 1.0
+    fn jittered_delay(&self, retry: u32, random: f64) -> Duration {
+        let delay = self.delay(retry);
+        let factor = 1.0 - self.jitter * random.clamp(0.0, 1.0);
+        Duration::try_from_secs_f64(delay.as_secs_f64() * factor)
+            .unwrap_or(delay)
+            .min(delay)
+    }
+
+    /// Run `op` until it succeeds, retrying failures under this policy
+    ///
+    /// `op` is called with the number of the attempt, counting from 0.
+    /// Errors are classified with [`Error::retry_class`]. The error of the
+    /// last attempt is returned once the retries run out, an error is
+    /// fatal, the classifier rejects an error or the budget refuses a retry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use text2audio::{Error, RetryPolicy};
+    /// use std::time::Duration;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let policy = RetryPolicy::new(2, Duration::from_millis(1));
+    /// let result = policy
+    ///     .execute(|attempt| async move {
+    ///         if attempt < 2 {
+    ///             Err(Error::Http("timed out".to_string()))
+    ///         } else {
+    ///             Ok(attempt)
+    ///         }
+    ///     })
+    ///     .await;
+    /// assert_eq!(result.unwrap(), 2);
+    /// # }
+    /// ```
+    pub async fn execute<T, F, Fut>(&self, op: F) -> Result<T>
+    where
+        F: FnMut(u32) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.run(op, Error::retry_class).await.0
+    }
+
+    /// Like [`execute`](Self::execute), classifying errors with `classify`
+    /// and also returning the number of attempts made
+    pub(crate) async fn run<T, F, Fut>(
+        &self,
+        mut op: F,
+        classify: impl Fn(&Error) -> RetryClass,
+    ) -> (Result<T>, u32)
+    where
+        F: FnMut(u32) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        let mut retried_once = false;
+        loop {
+            let error = match op(attempt).await {
+                Ok(value) => return (Ok(value), attempt + 1),
+                Err(e) => e,
+            };
+            let class = classify(&error);
+            let exhausted = match class {
+                RetryClass::Fatal => true,
+                RetryClass::RetryableOnce => std::mem::replace(&mut retried_once, true),
+                _ => false,
+            };
+            if attempt >= self.max_retries || exhausted || !self.retryable(&error) {
+                return (Err(error), attempt + 1);
+            }
+            let mut delay = self.jittered_delay(attempt, random_unit());
+            if let RetryClass::RateLimited {
+                retry_after: Some(retry_after),
+            } = class
+            {
+                delay = delay.max(retry_after);
+            }
+            if let Some(budget) = &self.budget {
+                if !budget(attempt + 1, delay) {
+                    return (Err(error), attempt + 1);
+                }
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    fn retryable(&self, error: &Error) -> bool {
+        match &self.classifier {
+            Some(classifier) => classifier(error),
+            None => true,
+        }
+    }
+}
+
+/// Random number in 0.0 - 1.0, good enough to spread retries apart
+fn random_unit() -> f64 {
+    // RandomState is seeded randomly for each instance
+    let hash = RandomState::new().build_hasher().finish();
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_delay_sequence() {
+        let policies = [
+            RetryPolicy::new(20, Duration::from_millis(100)),
+            RetryPolicy::new(20, Duration::from_millis(7)).with_multiplier(1.5),
+            RetryPolicy::new(20, Duration::from_millis(250))
+                .with_multiplier(3.0)
+                .with_max_delay(Duration::from_secs(5)),
+            RetryPolicy::new(20, Duration::from_millis(40)).with_multiplier(0.5),
+            RetryPolicy::new(20, Duration::from_secs(1)).with_multiplier(f64::NAN),
+            RetryPolicy::new(u32::MAX, Duration::from_secs(1)).with_multiplier(10.0),
+        ];
+        for policy in &policies {
+            let cap = policy.max_delay.unwrap_or(Duration::MAX);
+            assert_eq!(policy.delay(0), policy.base_delay.min(cap), "{:?}", policy);
+            for retry in 1..200 {
+                let (previous, delay) = (policy.delay(retry - 1), policy.delay(retry));
+                assert!(previous <= delay, "{:?} at {}", policy, retry);
+                assert!(delay <= cap, "{:?} at {}", policy, retry);
+            }
+            assert!(policy.delay(u32::MAX) <= cap);
+        }
+        assert_eq!(policies[0].delay(3), Duration::from_millis(800));
+        assert_eq!(policies[2].delay(20), Duration::from_secs(5));
+        assert_eq!(policies[3].delay(5), Duration::from_millis(40));
+        assert_eq!(policies[4].delay(2), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_jitter_bounds() {
+        for jitter in [0.0, 0.25, 1.0, 7.0, -1.0, f64::INFINITY] {
+            let policy = RetryPolicy::new(10, Duration::from_millis(100))
+                .with_max_delay(Duration::from_secs(2))
+                .with_jitter(jitter);
+            for retry in 0..10 {
+                let delay = policy.delay(retry);
+                let shortest = delay.mul_f64(1.0 - policy.jitter);
+                for random in [0.0, 0.5, 1.0] {
+                    let jittered = policy.jittered_delay(retry, random);
+                    assert!(shortest <= jittered && jittered <= delay);
+                }
+                for _ in 0..20 {
+                    let jittered = policy.jittered_delay(retry, random_unit());
+                    assert!(shortest <= jittered && jittered <= delay);
+                }
+            }
+        }
+        let samples: Vec<f64> = (0..100).map(|_| random_unit()).collect();
+        assert!(samples.iter().all(|r| (0.0..1.0).contains(r)));
+        assert!(samples.iter().any(|&r| r != samples[0]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_execute_stops_on_classifier_and_budget() {
+        let failing = |calls: &AtomicU32| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err::<(), _>(Error::TtsApi("rejected".to_string())) }
+        };
+
+        let calls = AtomicU32::new(0);
+        let policy = RetryPolicy::new(3, Duration::from_secs(1));
+        let started = tokio::time::Instant::now();
+        let (result, attempts) = policy.run(|_| failing(&calls), Error::retry_class).await;
+        assert!(matches!(result, Err(Error::TtsApi(_))));
+        assert_eq!((attempts, calls.load(Ordering::SeqCst)), (4, 4));
+        assert_eq!(started.elapsed(), Duration::from_secs(7));
+
+        let calls = AtomicU32::new(0);
+        let once = |_: &Error| RetryClass::RetryableOnce;
+        let (_, attempts) = policy.run(|_| failing(&calls), once).await;
+        assert_eq!((attempts, calls.load(Ordering::SeqCst)), (2, 2));
+
+        let calls = AtomicU32::new(0);
+        let transport_only = policy.clone().with_classifier(Error::is_transport);
+        assert!(transport_only.execute(|_| failing(&calls)).await.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let calls = AtomicU32::new(0);
+        let asked = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let budget = policy.clone().with_budget({
+            let asked = asked.clone();
+            move |retry, delay| {
+                asked.lock().unwrap().push((retry, delay));
+                retry < 2
+            }
+        });
+        assert!(budget.execute(|_| failing(&calls)).await.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            *asked.lock().unwrap(),
+            vec![(1, Duration::from_secs(1)), (2, Duration::from_secs(2))]
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! Per-endpoint request statistics for capacity planning
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds of the latency histogram buckets in milliseconds
+///
+/// Latencies above the last bound fall into a final overflow bucket.
+pub const LATENCY_BUCKETS_MS: [u64; 9] = [50, 100, 250, 500, 1000, 2500, 5000, 10000, 30000];
+
+/// API endpoint a request was sent to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+    /// Chat completion, used for AI splitting
+    Chat,
+    /// Text-to-speech synthesis
+    Tts,
+}
+
+/// Latency histogram with fixed buckets
+///
+/// Bucket `i` counts latencies up to [`LATENCY_BUCKETS_MS`]`[i]`; the last
+/// bucket counts everything slower.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    counts: [u64; LATENCY_BUCKETS_MS.len() + 1],
+}
+
+impl LatencyHistogram {
+    /// Record one latency
+    pub fn record(&mut self, latency: Duration) {
+        let millis = latency.as_millis();
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|&bound| millis <= bound as u128)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.counts[bucket] += 1;
+    }
+
+    /// Count per bucket, with the overflow bucket last
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Total number of recorded latencies
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Upper bound of the bucket containing the given percentile
+    ///
+    /// `percentile` is between 0 and 100. Returns `None` when nothing was
+    /// recorded, and [`Duration::MAX`] when the percentile falls into the
+    /// overflow bucket.
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+
+        let rank = ((percentile.clamp(0.0, 100.0) / 100.0) * total as f64).ceil() as u64;
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank.max(1) {
+                return Some(
+                    LATENCY_BUCKETS_MS
+                        .get(bucket)
+                        .map(|&ms| Duration::from_millis(ms))
+                        .unwrap_or(Duration::MAX),
+                );
+            }
+        }
+        Some(Duration::MAX)
+    }
+
+    /// Upper bound of the median latency bucket
+    pub fn p50(&self) -> Option<Duration> {
+        self.percentile(50.0)
+    }
+
+    /// Upper bound of the 95th percentile latency bucket
+    pub fn p95(&self) -> Option<Duration> {
+        self.percentile(95.0)
+    }
+
+    fn since(&self, earlier: &Self) -> Self {
+        let mut counts = self.counts;
+        for (count, before) in counts.iter_mut().zip(earlier.counts) {
+            *count = count.saturating_sub(before);
+        }
+        Self { counts }
+    }
+}
+
+/// Request statistics for one endpoint
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EndpointStats {
+    /// Requests sent, including retries
+    pub requests: u64,
+    /// Requests that succeeded
+    pub successes: u64,
+    /// Requests that were retries of an earlier failed attempt
+    pub retries: u64,
+    /// Latency of every request, successful or not
+    pub latency: LatencyHistogram,
+}
+
+impl EndpointStats {
+    /// Fraction of requests that were retries
+    pub fn retry_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.retries as f64 / self.requests as f64
+        }
+    }
+
+    fn since(&self, earlier: &Self) -> Self {
+        Self {
+            requests: self.requests.saturating_sub(earlier.requests),
+            successes: self.successes.saturating_sub(earlier.successes),
+            retries: self.retries.saturating_sub(earlier.retries),
+            latency: self.latency.since(&earlier.latency),
+        }
+    }
+}
+
+/// Request statistics for the chat and TTS endpoints
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientStats {
+    /// Chat completion requests
+    pub chat: EndpointStats,
+    /// TTS requests
+    pub tts: EndpointStats,
+}
+
+impl ClientStats {
+    /// Statistics for the given endpoint
+    pub fn endpoint(&self, endpoint: Endpoint) -> &EndpointStats {
+        match endpoint {
+            Endpoint::Chat => &self.chat,
+            Endpoint::Tts => &self.tts,
+        }
+    }
+
+    /// Statistics accumulated after `earlier` was taken
+    pub(crate) fn since(&self, earlier: &Self) -> Self {
+        Self {
+            chat: self.chat.since(&earlier.chat),
+            tts: self.tts.since(&earlier.tts),
+        }
+    }
+}
+
+/// Thread-safe accumulator behind [`ClientStats`] snapshots
+#[derive(Debug, Default)]
+pub(crate) struct StatsRecorder {
+    stats: Mutex<ClientStats>,
+}
+
+impl StatsRecorder {
+    /// Record a completed request
+    pub(crate) fn record(&self, endpoint: Endpoint, latency: Duration, success: bool, retry: bool) {
+        let mut stats = self.stats.lock().unwrap();
+        let endpoint = match endpoint {
+            Endpoint::Chat => &mut stats.chat,
+            Endpoint::Tts => &mut stats.tts,
+        };
+        endpoint.requests += 1;
+        if success {
+            endpoint.successes += 1;
+        }
+        if retry {
+            endpoint.retries += 1;
+        }
+        endpoint.latency.record(latency);
+    }
+
+    pub(crate) fn snapshot(&self) -> ClientStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    pub(crate) fn reset(&self) {
+        *self.stats.lock().unwrap() = ClientStats::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets() {
+        let mut histogram = LatencyHistogram::default();
+        histogram.record(Duration::from_millis(10));
+        histogram.record(Duration::from_millis(50));
+        histogram.record(Duration::from_millis(51));
+        histogram.record(Duration::from_secs(60));
+
+        assert_eq!(histogram.counts(), &[2, 1, 0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(histogram.total(), 4);
+    }
+
+    #[test]
+    fn test_histogram_percentiles() {
+        let mut histogram = LatencyHistogram::default();
+        assert_eq!(histogram.p50(), None);
+
+        for _ in 0..19 {
+            histogram.record(Duration::from_millis(80));
+        }
+        histogram.record(Duration::from_millis(700));
+
+        assert_eq!(histogram.p50(), Some(Duration::from_millis(100)));
+        assert_eq!(histogram.p95(), Some(Duration::from_millis(100)));
+        assert_eq!(
+            histogram.percentile(100.0),
+            Some(Duration::from_millis(1000))
+        );
+    }
+
+    #[test]
+    fn test_recorder_since_and_reset() {
+        let recorder = StatsRecorder::default();
+        recorder.record(Endpoint::Tts, Duration::from_millis(10), false, false);
+        let before = recorder.snapshot();
+        recorder.record(Endpoint::Tts, Duration::from_millis(10), true, true);
+        recorder.record(Endpoint::Chat, Duration::from_millis(300), true, false);
+
+        let delta = recorder.snapshot().since(&before);
+        assert_eq!(delta.tts.requests, 1);
+        assert_eq!(delta.tts.retries, 1);
+        assert_eq!(delta.tts.retry_rate(), 1.0);
+        assert_eq!(delta.endpoint(Endpoint::Chat).successes, 1);
+
+        recorder.reset();
+        assert_eq!(recorder.snapshot(), ClientStats::default());
+    }
+}
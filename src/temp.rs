@@ -0,0 +1,85 @@
+//! Files written under a temporary name before they reach their final one
+
+use std::path::{Path, PathBuf};
+
+/// File removed when dropped unless [`persist`](Self::persist)ed
+///
+/// Guards files a conversion writes before moving them into place, so that
+/// neither an error nor a dropped future leaves them behind. Removal is
+/// best-effort: a failure to remove is ignored.
+#[derive(Debug)]
+pub(crate) struct TempFile {
+    path: Option<PathBuf>,
+}
+
+impl TempFile {
+    pub(crate) fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        self.path.as_deref().expect("path is only taken by persist")
+    }
+
+    /// Rename the file to `target`, keeping it
+    pub(crate) fn persist(mut self, target: &Path) -> std::io::Result<()> {
+        std::fs::rename(self.path(), target)?;
+        self.path = None;
+        Ok(())
+    }
+
+    /// Keep the file where it is
+    pub(crate) fn keep(mut self) {
+        self.path = None;
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Some(path) = &self.path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Replace `path` with `contents` by writing `temp` and renaming it
+///
+/// Readers see either the previous contents or the new ones, and `temp` is
+/// removed if writing or renaming fails.
+pub(crate) fn write_atomic(
+    path: &Path,
+    temp: impl Into<PathBuf>,
+    contents: impl AsRef<[u8]>,
+) -> std::io::Result<()> {
+    let temp = TempFile::new(temp);
+    std::fs::write(temp.path(), contents)?;
+    temp.persist(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_path;
+
+    #[test]
+    fn test_temp_file_removed_unless_persisted() {
+        let path = PathBuf::from(temp_path("temp_file.tmp"));
+        let target = PathBuf::from(temp_path("temp_file.json"));
+
+        std::fs::write(&path, b"partial").unwrap();
+        drop(TempFile::new(&path));
+        assert!(!path.exists());
+
+        write_atomic(&target, &path, b"done").unwrap();
+        assert!(!path.exists());
+        assert_eq!(std::fs::read(&target).unwrap(), b"done");
+
+        // A failed rename leaves neither file
+        let missing_dir = target.with_extension("missing").join("file.json");
+        assert!(write_atomic(&missing_dir, &path, b"lost").is_err());
+        assert!(!path.exists());
+        std::fs::remove_file(&target).unwrap();
+    }
+}
@@ -0,0 +1,660 @@
+//! Helpers shared by unit tests
+
+use crate::backend::{ChatBackend, SplitBackend, TtsBackend};
+use crate::client::TtsConfig;
+use crate::error::{Error, Result};
+use crate::retry::RetryClass;
+use futures::future::BoxFuture;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::io::Cursor;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Sample rate used by the mock backend
+pub const MOCK_SAMPLE_RATE: u32 = 24000;
+
+/// Encode 16-bit samples as an in-memory WAV file
+pub fn wav_bytes(samples: &[i16], sample_rate: u32, channels: u16) -> Vec<u8> {
+    let spec = WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut cursor = Cursor::new(Vec::new());
+    let mut writer = WavWriter::new(&mut cursor, spec).unwrap();
+    for sample in samples {
+        writer.write_sample(*sample).unwrap();
+    }
+    writer.finalize().unwrap();
+    cursor.into_inner()
+}
+
+/// MP3 stream of `frames` silent frames, 44.1 kHz mono at 32 kbps, after an
+/// empty ID3v2 tag if `id3`
+pub fn mp3_bytes(frames: usize, id3: bool) -> Vec<u8> {
+    let mut mp3 = Vec::new();
+    if id3 {
+        mp3.extend_from_slice(b"ID3\x04\x00\x00\x00\x00\x00\x00");
+    }
+    // MPEG-1 Layer III header; all-zero side info decodes to silence
+    let mut frame = vec![0u8; 104];
+    frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x10, 0xC4]);
+    for _ in 0..frames {
+        mp3.extend_from_slice(&frame);
+    }
+    mp3
+}
+
+/// Unique path in the system temp directory for a test output file
+pub fn temp_path(name: &str) -> String {
+    std::env::temp_dir()
+        .join(format!("text2audio_test_{}_{}", std::process::id(), name))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Convert `text` to `output`, asserting the file agrees with the report
+pub async fn convert_verified(
+    converter: &crate::Text2Audio,
+    text: &str,
+    output: &str,
+) -> crate::ConversionReport {
+    let report = converter.convert_with_report(text, output).await.unwrap();
+    if let Err(e) = crate::verify_output(&report, std::path::Path::new(output)) {
+        panic!("{}", e);
+    }
+    report
+}
+
+/// Decode all samples from WAV bytes
+pub fn read_samples(bytes: &[u8]) -> Vec<i16> {
+    hound::WavReader::new(Cursor::new(bytes))
+        .unwrap()
+        .samples::<i16>()
+        .map(|s| s.unwrap())
+        .collect()
+}
+
+/// TTS backend returning one sample per input character and recording requests
+#[derive(Default)]
+pub struct MockBackend {
+    pub requests: Mutex<Vec<String>>,
+    pub configs: Mutex<Vec<TtsConfig>>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn configs(&self) -> Vec<TtsConfig> {
+        self.configs.lock().unwrap().clone()
+    }
+}
+
+impl TtsBackend for MockBackend {
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        config: &'a TtsConfig,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        self.requests.lock().unwrap().push(text.to_string());
+        self.configs.lock().unwrap().push(config.clone());
+        let samples = vec![1000; text.chars().count()];
+        Box::pin(async move { Ok(wav_bytes(&samples, MOCK_SAMPLE_RATE, 1)) })
+    }
+}
+
+/// TTS backend that fails with a transport error whenever another request is
+/// already in flight, recording the text and idempotency key of every request
+#[derive(Default)]
+pub struct SerialOnlyBackend {
+    in_flight: AtomicUsize,
+    pub requests: Mutex<Vec<(String, Option<String>)>>,
+}
+
+impl SerialOnlyBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn requests(&self) -> Vec<(String, Option<String>)> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl TtsBackend for SerialOnlyBackend {
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        config: &'a TtsConfig,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        self.requests
+            .lock()
+            .unwrap()
+            .push((text.to_string(), config.idempotency_key.clone()));
+        Box::pin(async move {
+            if self.in_flight.fetch_add(1, Ordering::SeqCst) > 0 {
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                return Err(Error::Http("connection reset by peer".to_string()));
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            let samples = vec![1000; text.chars().count()];
+            Ok(wav_bytes(&samples, MOCK_SAMPLE_RATE, 1))
+        })
+    }
+}
+
+/// TTS backend that fails the first `failures` requests with a transport
+/// error and records the idempotency key of every request
+pub struct FlakyBackend {
+    failures: AtomicUsize,
+    pub keys: Mutex<Vec<Option<String>>>,
+}
+
+impl FlakyBackend {
+    pub fn new(failures: usize) -> Self {
+        Self {
+            failures: AtomicUsize::new(failures),
+            keys: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn keys(&self) -> Vec<Option<String>> {
+        self.keys.lock().unwrap().clone()
+    }
+}
+
+impl TtsBackend for FlakyBackend {
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        config: &'a TtsConfig,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        self.keys
+            .lock()
+            .unwrap()
+            .push(config.idempotency_key.clone());
+        let fail = self
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        let samples = vec![1000; text.chars().count()];
+        Box::pin(async move {
+            if fail {
+                return Err(Error::Http("request timed out".to_string()));
+            }
+            Ok(wav_bytes(&samples, MOCK_SAMPLE_RATE, 1))
+        })
+    }
+}
+
+/// TTS backend that fails the first `failures` requests with an API error it
+/// classifies as `class`
+pub struct ClassifyingBackend {
+    class: RetryClass,
+    failures: AtomicUsize,
+    requests: AtomicUsize,
+}
+
+impl ClassifyingBackend {
+    pub fn new(class: RetryClass, failures: usize) -> Self {
+        Self {
+            class,
+            failures: AtomicUsize::new(failures),
+            requests: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+impl TtsBackend for ClassifyingBackend {
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        _config: &'a TtsConfig,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        let fail = self
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        let samples = vec![1000; text.chars().count()];
+        Box::pin(async move {
+            if fail {
+                return Err(Error::TtsApi("local server busy".to_string()));
+            }
+            Ok(wav_bytes(&samples, MOCK_SAMPLE_RATE, 1))
+        })
+    }
+
+    fn classify(&self, _error: &Error) -> RetryClass {
+        self.class
+    }
+}
+
+/// TTS backend that succeeds for the first `successes` requests and then
+/// fails every request with a transport error
+pub struct FailAfterBackend {
+    successes: AtomicUsize,
+}
+
+impl FailAfterBackend {
+    pub fn new(successes: usize) -> Self {
+        Self {
+            successes: AtomicUsize::new(successes),
+        }
+    }
+}
+
+impl TtsBackend for FailAfterBackend {
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        _config: &'a TtsConfig,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        let succeed = self
+            .successes
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        let samples = vec![1000; text.chars().count()];
+        Box::pin(async move {
+            if !succeed {
+                return Err(Error::Http("connection reset".to_string()));
+            }
+            Ok(wav_bytes(&samples, MOCK_SAMPLE_RATE, 1))
+        })
+    }
+}
+
+/// TTS backend answering like [`MockBackend`], except that it returns bytes
+/// that are not a WAV file for the text `corrupt`
+pub struct CorruptBackend {
+    corrupt: String,
+}
+
+impl CorruptBackend {
+    pub fn new(corrupt: &str) -> Self {
+        Self {
+            corrupt: corrupt.to_string(),
+        }
+    }
+}
+
+impl TtsBackend for CorruptBackend {
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        _config: &'a TtsConfig,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        let audio = if text == self.corrupt {
+            b"not a wav file".to_vec()
+        } else {
+            wav_bytes(&vec![1000; text.chars().count()], MOCK_SAMPLE_RATE, 1)
+        };
+        Box::pin(async move { Ok(audio) })
+    }
+}
+
+/// TTS backend that rejects the listed voices with an API error and counts
+/// requests
+pub struct VoiceRejectingBackend {
+    rejected: Vec<String>,
+    probes: AtomicUsize,
+}
+
+impl VoiceRejectingBackend {
+    pub fn new(rejected: &[&str]) -> Self {
+        Self {
+            rejected: rejected.iter().map(|v| v.to_string()).collect(),
+            probes: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn probes(&self) -> usize {
+        self.probes.load(Ordering::SeqCst)
+    }
+}
+
+impl TtsBackend for VoiceRejectingBackend {
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        config: &'a TtsConfig,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        self.probes.fetch_add(1, Ordering::SeqCst);
+        let voice = format!("{:?}", config.voice);
+        let rejected = self.rejected.contains(&voice);
+        let samples = vec![1000; text.chars().count()];
+        Box::pin(async move {
+            if rejected {
+                return Err(Error::TtsApi(format!("unsupported voice {}", voice)));
+            }
+            Ok(wav_bytes(&samples, MOCK_SAMPLE_RATE, 1))
+        })
+    }
+}
+
+/// Split backend that answers with fixed segments after a delay, failing
+/// the first `failures` requests with a transport error
+pub struct StaticSplitBackend {
+    segments: Vec<String>,
+    delay: Duration,
+    calls: AtomicUsize,
+    failures: AtomicUsize,
+}
+
+impl StaticSplitBackend {
+    pub fn new(segments: &[&str], delay: Duration) -> Self {
+        Self {
+            segments: segments.iter().map(|s| s.to_string()).collect(),
+            delay,
+            calls: AtomicUsize::new(0),
+            failures: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn failing(self, failures: usize) -> Self {
+        self.failures.store(failures, Ordering::SeqCst);
+        self
+    }
+
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+impl SplitBackend for StaticSplitBackend {
+    fn split<'a>(&'a self, _text: &'a str) -> BoxFuture<'a, Result<Vec<String>>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let fail = self
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        Box::pin(async move {
+            tokio::time::sleep(self.delay).await;
+            if fail {
+                return Err(Error::Http("request timed out".to_string()));
+            }
+            Ok(self.segments.clone())
+        })
+    }
+}
+
+/// Chat backend that answers after a delay with the text of the prompt,
+/// split after each `。`
+pub struct EchoChatBackend {
+    delay: Duration,
+}
+
+impl EchoChatBackend {
+    pub fn new(delay: Duration) -> Self {
+        Self { delay }
+    }
+}
+
+impl ChatBackend for EchoChatBackend {
+    fn chat<'a>(&'a self, prompt: &'a str) -> BoxFuture<'a, Result<Vec<String>>> {
+        Box::pin(async move {
+            tokio::time::sleep(self.delay).await;
+            let (_, text) = prompt.split_once("待分割的文本：\n").unwrap_or_default();
+            Ok(vec![text.replace('。', "。|||")])
+        })
+    }
+}
+
+/// Chat backend answering like [`EchoChatBackend`] without delay, failing
+/// the requests numbered in `failing` (counting from 0) with a transport
+/// error, and recording every prompt
+pub struct FlakyChatBackend {
+    failing: Vec<usize>,
+    pub prompts: Mutex<Vec<String>>,
+}
+
+impl FlakyChatBackend {
+    pub fn new(failing: &[usize]) -> Self {
+        Self {
+            failing: failing.to_vec(),
+            prompts: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn prompts(&self) -> Vec<String> {
+        self.prompts.lock().unwrap().clone()
+    }
+}
+
+impl ChatBackend for FlakyChatBackend {
+    fn chat<'a>(&'a self, prompt: &'a str) -> BoxFuture<'a, Result<Vec<String>>> {
+        let mut prompts = self.prompts.lock().unwrap();
+        let fail = self.failing.contains(&prompts.len());
+        prompts.push(prompt.to_string());
+        Box::pin(async move {
+            if fail {
+                return Err(Error::Http("connection reset".to_string()));
+            }
+            let (_, text) = prompt.split_once("待分割的文本：\n").unwrap_or_default();
+            Ok(vec![text.replace('。', "。|||")])
+        })
+    }
+}
+
+/// TTS backend that answers after `per_char` for each input character, so
+/// longer texts finish later, returning one sample per character valued by
+/// the character count
+pub struct StaggeredBackend {
+    per_char: Duration,
+}
+
+impl StaggeredBackend {
+    pub fn new(per_char: Duration) -> Self {
+        Self { per_char }
+    }
+}
+
+impl TtsBackend for StaggeredBackend {
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        _config: &'a TtsConfig,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        let chars = text.chars().count();
+        let samples = vec![chars as i16; chars];
+        Box::pin(async move {
+            tokio::time::sleep(self.per_char * chars as u32).await;
+            Ok(wav_bytes(&samples, MOCK_SAMPLE_RATE, 1))
+        })
+    }
+}
+
+/// TTS backend returning `per_char` of audio for each input character
+pub struct PerCharBackend {
+    per_char: Duration,
+}
+
+impl PerCharBackend {
+    pub fn new(per_char: Duration) -> Self {
+        Self { per_char }
+    }
+}
+
+impl TtsBackend for PerCharBackend {
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        _config: &'a TtsConfig,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        let per_char = (self.per_char.as_secs_f64() * f64::from(MOCK_SAMPLE_RATE)) as usize;
+        let samples = vec![1000; per_char * text.chars().count()];
+        Box::pin(async move { Ok(wav_bytes(&samples, MOCK_SAMPLE_RATE, 1)) })
+    }
+}
+
+/// TTS backend that answers after a fixed delay
+pub struct SlowBackend {
+    delay: Duration,
+}
+
+impl SlowBackend {
+    pub fn new(delay: Duration) -> Self {
+        Self { delay }
+    }
+}
+
+impl TtsBackend for SlowBackend {
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        _config: &'a TtsConfig,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        let samples = vec![1000; text.chars().count()];
+        Box::pin(async move {
+            tokio::time::sleep(self.delay).await;
+            Ok(wav_bytes(&samples, MOCK_SAMPLE_RATE, 1))
+        })
+    }
+}
+
+/// TTS backend whose requests never complete
+pub struct PendingBackend;
+
+impl TtsBackend for PendingBackend {
+    fn synthesize<'a>(
+        &'a self,
+        _text: &'a str,
+        _config: &'a TtsConfig,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        Box::pin(std::future::pending())
+    }
+}
+
+/// TTS backend that returns the same number of samples for any text
+pub struct FixedLengthBackend {
+    samples: usize,
+}
+
+impl FixedLengthBackend {
+    pub fn new(samples: usize) -> Self {
+        Self { samples }
+    }
+}
+
+impl TtsBackend for FixedLengthBackend {
+    fn synthesize<'a>(
+        &'a self,
+        _text: &'a str,
+        _config: &'a TtsConfig,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        let samples = vec![1000; self.samples];
+        Box::pin(async move { Ok(wav_bytes(&samples, MOCK_SAMPLE_RATE, 1)) })
+    }
+}
+
+/// WAV bytes with extra chunks on both sides of the data chunk; the odd
+/// `bext` chunk is padded and the odd `LIST` chunk at the end is not
+pub fn wav_with_extra_chunks() -> Vec<u8> {
+    let plain = wav_bytes(&[1, 2, 3], 24000, 1);
+    let chunks = crate::audio_merger::riff_chunks(&plain).unwrap();
+    let chunk = |id: &[u8; 4], body: &[u8], pad: bool| {
+        let mut bytes = id.to_vec();
+        bytes.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(body);
+        if pad && body.len() % 2 == 1 {
+            bytes.push(0);
+        }
+        bytes
+    };
+
+    let mut wav = b"RIFF\0\0\0\0WAVE".to_vec();
+    wav.extend(chunk(b"fmt ", chunks[0].1, true));
+    wav.extend(chunk(b"cust", &[1, 2, 3, 4], true));
+    wav.extend(chunk(b"data", chunks[1].1, true));
+    wav.extend(chunk(b"bext", &[5, 6, 7], true));
+    wav.extend(chunk(b"LIST", b"INFOx", false));
+    let riff_size = (wav.len() - 8) as u32;
+    wav[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    wav
+}
+
+/// TTS backend that returns the same WAV bytes for any text
+pub struct StaticBackend {
+    audio: Vec<u8>,
+}
+
+impl StaticBackend {
+    pub fn new(audio: Vec<u8>) -> Self {
+        Self { audio }
+    }
+}
+
+impl TtsBackend for StaticBackend {
+    fn synthesize<'a>(
+        &'a self,
+        _text: &'a str,
+        _config: &'a TtsConfig,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        Box::pin(async move { Ok(self.audio.clone()) })
+    }
+}
+
+/// TTS backend answering the first `completed` requests like
+/// [`MockBackend`] and leaving the rest pending after signalling `stalled`
+pub struct StallingBackend {
+    completed: AtomicUsize,
+    pub stalled: tokio::sync::Notify,
+}
+
+impl StallingBackend {
+    pub fn new(completed: usize) -> Self {
+        Self {
+            completed: AtomicUsize::new(completed),
+            stalled: tokio::sync::Notify::new(),
+        }
+    }
+}
+
+impl TtsBackend for StallingBackend {
+    fn synthesize<'a>(
+        &'a self,
+        text: &'a str,
+        _config: &'a TtsConfig,
+    ) -> BoxFuture<'a, Result<Vec<u8>>> {
+        let answer = self
+            .completed
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        let samples = vec![1000; text.chars().count()];
+        Box::pin(async move {
+            if !answer {
+                self.stalled.notify_one();
+                std::future::pending::<()>().await;
+            }
+            Ok(wav_bytes(&samples, MOCK_SAMPLE_RATE, 1))
+        })
+    }
+}
+
+/// Every file under `dir`, recursively, in sorted order
+pub fn files_under(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(files_under(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
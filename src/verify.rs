@@ -0,0 +1,409 @@
+//! Checks that a written output file agrees with its conversion report
+//!
+//! Available with the `test-util` feature, for asserting in tests that a
+//! conversion produced consistent audio.
+
+use crate::report::{ConversionReport, ReportDetail};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Timing difference allowed per segment boundary
+///
+/// Pauses are rounded to whole samples when merged, so each boundary may
+/// shift the audio by up to one sample.
+pub const BOUNDARY_TOLERANCE: Duration = Duration::from_millis(1);
+
+/// One way in which an output file disagrees with its report
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Discrepancy {
+    /// The output file is not a complete WAV file
+    Unreadable {
+        /// Why the file could not be read
+        message: String,
+    },
+    /// `segment_count` differs from the number of segment reports
+    SegmentCount {
+        /// Value of `segment_count`
+        reported: usize,
+        /// Number of segment reports
+        segments: usize,
+    },
+    /// A segment's index differs from its position
+    SegmentIndex {
+        /// Position of the segment in the report
+        position: usize,
+        /// Index recorded for the segment
+        index: usize,
+    },
+    /// A segment does not start where the previous segment and its pause end
+    SegmentStart {
+        /// Index of the segment
+        index: usize,
+        /// Start implied by the previous segments
+        expected: Duration,
+        /// Start recorded for the segment
+        reported: Duration,
+    },
+    /// A segment's source range overlaps or precedes the previous one
+    SourceRange {
+        /// Index of the segment
+        index: usize,
+        /// End of the previous segment's range
+        previous_end: usize,
+        /// Start of the segment's range
+        start: usize,
+    },
+    /// The file's duration differs from the sum of segments and pauses
+    TotalDuration {
+        /// Duration implied by the report
+        expected: Duration,
+        /// Duration of the audio in the file
+        actual: Duration,
+    },
+    /// A sidecar next to the output disagrees with the report or the audio
+    Sidecar {
+        /// Path of the sidecar
+        path: PathBuf,
+        /// What disagrees
+        message: String,
+    },
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Discrepancy::Unreadable { message } => write!(f, "unreadable output: {}", message),
+            Discrepancy::SegmentCount { reported, segments } => write!(
+                f,
+                "segment_count is {} but {} segments are reported",
+                reported, segments
+            ),
+            Discrepancy::SegmentIndex { position, index } => {
+                write!(f, "segment at position {} has index {}", position, index)
+            }
+            Discrepancy::SegmentStart {
+                index,
+                expected,
+                reported,
+            } => write!(
+                f,
+                "segment {} starts at {:.3}s, expected {:.3}s",
+                index,
+                reported.as_secs_f64(),
+                expected.as_secs_f64()
+            ),
+            Discrepancy::SourceRange {
+                index,
+                previous_end,
+                start,
+            } => write!(
+                f,
+                "segment {} source range starts at {}, before the previous end {}",
+                index, start, previous_end
+            ),
+            Discrepancy::TotalDuration { expected, actual } => write!(
+                f,
+                "audio lasts {:.3}s, report adds up to {:.3}s",
+                actual.as_secs_f64(),
+                expected.as_secs_f64()
+            ),
+            Discrepancy::Sidecar { path, message } => {
+                write!(f, "sidecar {}: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+/// Discrepancies found by [`verify_output`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerificationError {
+    /// Every discrepancy found, in the order checked
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} discrepancies", self.discrepancies.len())?;
+        for discrepancy in &self.discrepancies {
+            write!(f, "\n  - {}", discrepancy)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+/// Check that the WAV file at `output_path` agrees with `report`
+///
+/// Re-reads every sample of the file and checks that segment indices and
+/// starts follow from the durations and pauses before them, that source
+/// ranges are monotonic (except in bilingual output, whose ranges index
+/// each pair's own text), and that the file lasts as long as the segments
+/// and pauses together, within [`BOUNDARY_TOLERANCE`] per boundary. A
+/// timing sidecar (`<output>.segments.json`) or metadata sidecar
+/// (`<output>.json`) next to the output is checked too.
+///
+/// Only WAV output can be checked; other formats are reported as
+/// [`Discrepancy::Unreadable`].
+///
+/// # Errors
+///
+/// Returns a [`VerificationError`] listing every discrepancy found.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::path::Path;
+/// use text2audio::{verify_output, Text2Audio};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let converter = Text2Audio::new("api_key");
+/// let report = converter.convert_with_report("你好，世界！", "hello.wav").await?;
+/// verify_output(&report, Path::new("hello.wav"))?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn verify_output(
+    report: &ConversionReport,
+    output_path: &Path,
+) -> Result<(), VerificationError> {
+    let mut discrepancies = Vec::new();
+
+    if report.segment_count != report.segments.len() {
+        discrepancies.push(Discrepancy::SegmentCount {
+            reported: report.segment_count,
+            segments: report.segments.len(),
+        });
+    }
+
+    let mut expected_start = Duration::ZERO;
+    let mut previous_end = 0;
+    for (position, segment) in report.segments.iter().enumerate() {
+        if segment.index != position {
+            discrepancies.push(Discrepancy::SegmentIndex {
+                position,
+                index: segment.index,
+            });
+        }
+        if abs_diff(segment.start, expected_start) > BOUNDARY_TOLERANCE * position as u32 {
+            discrepancies.push(Discrepancy::SegmentStart {
+                index: segment.index,
+                expected: expected_start,
+                reported: segment.start,
+            });
+        }
+        // Bilingual ranges index each pair's own text, not one input
+        let unordered = segment.track.is_none() && segment.source_range.start < previous_end;
+        if unordered || segment.source_range.end < segment.source_range.start {
+            discrepancies.push(Discrepancy::SourceRange {
+                index: segment.index,
+                previous_end,
+                start: segment.source_range.start,
+            });
+        }
+        expected_start += segment.duration + segment.pause_after;
+        previous_end = segment.source_range.end.max(previous_end);
+    }
+
+    match audio_duration(output_path) {
+        Ok(actual) => {
+            let tolerance = BOUNDARY_TOLERANCE * (report.segments.len() as u32 + 1);
+            if abs_diff(actual, expected_start) > tolerance {
+                discrepancies.push(Discrepancy::TotalDuration {
+                    expected: expected_start,
+                    actual,
+                });
+            }
+            check_metadata_sidecar(output_path, report, actual, &mut discrepancies);
+        }
+        Err(message) => discrepancies.push(Discrepancy::Unreadable { message }),
+    }
+    check_timing_sidecar(output_path, report, &mut discrepancies);
+
+    if discrepancies.is_empty() {
+        Ok(())
+    } else {
+        Err(VerificationError { discrepancies })
+    }
+}
+
+fn abs_diff(a: Duration, b: Duration) -> Duration {
+    a.max(b) - a.min(b)
+}
+
+/// Duration of the WAV file at `path`, reading every sample
+fn audio_duration(path: &Path) -> Result<Duration, String> {
+    let reader = hound::WavReader::open(path).map_err(|e| e.to_string())?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .into_samples::<f32>()
+            .try_fold(0u64, |count, sample| sample.map(|_| count + 1)),
+        hound::SampleFormat::Int => reader
+            .into_samples::<i32>()
+            .try_fold(0u64, |count, sample| sample.map(|_| count + 1)),
+    }
+    .map_err(|e| e.to_string())?;
+    let frames = samples / u64::from(spec.channels.max(1));
+    Ok(Duration::from_secs_f64(
+        frames as f64 / f64::from(spec.sample_rate),
+    ))
+}
+
+fn sidecar_path(output_path: &Path, suffix: &str) -> PathBuf {
+    let mut path = output_path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+fn check_timing_sidecar(
+    output_path: &Path,
+    report: &ConversionReport,
+    discrepancies: &mut Vec<Discrepancy>,
+) {
+    let path = sidecar_path(output_path, ".segments.json");
+    let Ok(json) = std::fs::read_to_string(&path) else {
+        return;
+    };
+    // Sidecars are written before the report drops any text, so compare
+    // only what the report still keeps
+    let Ok(expected) = report.to_timing_json() else {
+        return;
+    };
+    let expected: serde_json::Value = match serde_json::from_str(&expected) {
+        Ok(expected) => expected,
+        Err(e) => {
+            discrepancies.push(Discrepancy::Sidecar {
+                path,
+                message: format!("report timings are not valid JSON: {}", e),
+            });
+            return;
+        }
+    };
+    let written: Result<serde_json::Value, _> =
+        serde_json::from_str(&json).map(|mut written: serde_json::Value| {
+            if report.detail < ReportDetail::Full {
+                if let Some(entries) = written.as_array_mut() {
+                    for entry in entries.iter_mut().filter_map(|e| e.as_object_mut()) {
+                        entry.remove("text");
+                    }
+                }
+            }
+            written
+        });
+    match written {
+        Ok(written) if written == expected => {}
+        Ok(_) => discrepancies.push(Discrepancy::Sidecar {
+            path,
+            message: "timings differ from the report".to_string(),
+        }),
+        Err(e) => discrepancies.push(Discrepancy::Sidecar {
+            path,
+            message: e.to_string(),
+        }),
+    }
+}
+
+fn check_metadata_sidecar(
+    output_path: &Path,
+    report: &ConversionReport,
+    actual: Duration,
+    discrepancies: &mut Vec<Discrepancy>,
+) {
+    let path = sidecar_path(output_path, ".json");
+    let Ok(json) = std::fs::read_to_string(&path) else {
+        return;
+    };
+    let metadata: serde_json::Value = match serde_json::from_str(&json) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            discrepancies.push(Discrepancy::Sidecar {
+                path,
+                message: e.to_string(),
+            });
+            return;
+        }
+    };
+    match metadata["duration_seconds"].as_f64() {
+        Some(duration)
+            if (duration - actual.as_secs_f64()).abs() <= BOUNDARY_TOLERANCE.as_secs_f64() => {}
+        duration => discrepancies.push(Discrepancy::Sidecar {
+            path: path.clone(),
+            message: format!(
+                "duration is {:?}s, audio lasts {:.3}s",
+                duration,
+                actual.as_secs_f64()
+            ),
+        }),
+    }
+    if metadata["segment_count"].as_u64() != Some(report.segment_count as u64) {
+        discrepancies.push(Discrepancy::Sidecar {
+            path,
+            message: format!(
+                "segment count is {}, report has {}",
+                metadata["segment_count"], report.segment_count
+            ),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::SegmentSource;
+    use crate::test_util::{temp_path, wav_bytes};
+
+    #[test]
+    fn test_verify_output_lists_discrepancies() {
+        let path = temp_path("verify.wav");
+        std::fs::write(&path, wav_bytes(&[0; 48], 24000, 1)).unwrap();
+        let mut report = ConversionReport::new(crate::ConversionMode::Segmented);
+        let segment = wav_bytes(&[0; 24], 24000, 1);
+        report
+            .push_segment("一", &segment, SegmentSource::Synthesized { attempts: 1 })
+            .unwrap();
+        report
+            .push_segment("二", &segment, SegmentSource::Synthesized { attempts: 1 })
+            .unwrap();
+        assert_eq!(verify_output(&report, Path::new(&path)), Ok(()));
+
+        report.push_pause(Duration::from_millis(500));
+        report.segments[1].start = Duration::from_millis(300);
+        report.segments[1].index = 5;
+        report.segment_count = 3;
+        let error = verify_output(&report, Path::new(&path)).unwrap_err();
+        assert_eq!(
+            error.discrepancies,
+            vec![
+                Discrepancy::SegmentCount {
+                    reported: 3,
+                    segments: 2
+                },
+                Discrepancy::SegmentIndex {
+                    position: 1,
+                    index: 5
+                },
+                Discrepancy::SegmentStart {
+                    index: 5,
+                    expected: Duration::from_millis(1),
+                    reported: Duration::from_millis(300)
+                },
+                Discrepancy::TotalDuration {
+                    expected: Duration::from_millis(502),
+                    actual: Duration::from_millis(2)
+                },
+            ]
+        );
+
+        std::fs::write(&path, b"RIFF").unwrap();
+        let error = verify_output(&report, Path::new(&path)).unwrap_err();
+        assert!(matches!(
+            error.discrepancies.last(),
+            Some(Discrepancy::Unreadable { .. })
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
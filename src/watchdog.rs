@@ -0,0 +1,140 @@
+//! Detection of conversions that stop making progress
+
+use crate::error::{Error, Result};
+use crate::report::{StalledSegment, Warning};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+tokio::task_local! {
+    static TRACKER: Arc<Tracker>;
+}
+
+/// Progress of the segments synthesized under one [`guard`]
+struct Tracker {
+    state: Mutex<State>,
+}
+
+struct State {
+    last_progress: Instant,
+    in_flight: BTreeMap<usize, InFlight>,
+}
+
+struct InFlight {
+    started: Instant,
+    attempts: u32,
+}
+
+impl Tracker {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(State {
+                last_progress: Instant::now(),
+                in_flight: BTreeMap::new(),
+            }),
+        }
+    }
+
+    fn last_progress(&self) -> Instant {
+        self.state.lock().unwrap().last_progress
+    }
+
+    fn in_flight(&self, now: Instant) -> Vec<StalledSegment> {
+        self.state
+            .lock()
+            .unwrap()
+            .in_flight
+            .iter()
+            .map(|(&index, segment)| StalledSegment {
+                index,
+                elapsed: now - segment.started,
+                attempts: segment.attempts,
+            })
+            .collect()
+    }
+}
+
+/// Run `f` on the tracker of the enclosing [`guard`], if any
+fn record(f: impl FnOnce(&mut State)) {
+    let _ = TRACKER.try_with(|tracker| {
+        let mut state = tracker.state.lock().unwrap();
+        state.last_progress = Instant::now();
+        f(&mut state);
+    });
+}
+
+/// Record the start of an attempt to synthesize segment `index`
+pub(crate) fn attempt_started(index: usize) {
+    record(|state| {
+        let now = state.last_progress;
+        state
+            .in_flight
+            .entry(index)
+            .or_insert(InFlight {
+                started: now,
+                attempts: 0,
+            })
+            .attempts += 1;
+    });
+}
+
+/// Record that segment `index` succeeded or ran out of attempts
+pub(crate) fn segment_finished(index: usize) {
+    record(|state| {
+        state.in_flight.remove(&index);
+    });
+}
+
+/// Record progress that is not tied to a segment, such as bytes written
+pub(crate) fn progressed() {
+    record(|_| {});
+}
+
+/// Run `work`, watching for stretches of `timeout` without progress
+///
+/// The first stall pushes a [`Warning::Stalled`] to `warnings`. If `abort`
+/// is set and another `timeout` passes without progress, `work` is dropped
+/// and [`Error::Stalled`] returned. Progress resets the watch. The watch
+/// runs in the caller's task and only wakes once per `timeout`.
+pub(crate) async fn guard<T>(
+    timeout: Duration,
+    abort: bool,
+    warnings: &mut Vec<Warning>,
+    work: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let tracker = Arc::new(Tracker::new());
+    let work = TRACKER.scope(tracker.clone(), work);
+    tokio::pin!(work);
+
+    // Progress time of the stall last warned about
+    let mut warned_for: Option<Instant> = None;
+    let mut deadline = tracker.last_progress() + timeout;
+    loop {
+        tokio::select! {
+            result = &mut work => return result,
+            _ = tokio::time::sleep_until(deadline) => {}
+        }
+
+        let last = tracker.last_progress();
+        let now = Instant::now();
+        if last + timeout > now {
+            deadline = last + timeout;
+            continue;
+        }
+        if warned_for != Some(last) {
+            warnings.push(Warning::Stalled {
+                idle: now - last,
+                in_flight: tracker.in_flight(now),
+            });
+            warned_for = Some(last);
+        } else if abort {
+            return Err(Error::Stalled {
+                idle: now - last,
+                in_flight: tracker.in_flight(now),
+            });
+        }
+        deadline = now + timeout;
+    }
+}